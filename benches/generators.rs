@@ -2,7 +2,7 @@ use webb_bulletproofs::{BulletproofGens, PedersenGens};
 
 #[macro_use]
 extern crate criterion;
-use criterion::Criterion;
+use criterion::{BenchmarkId, Criterion};
 
 fn pc_gens(c: &mut Criterion) {
 	c.bench_function("PedersenGens::new", |b| b.iter(PedersenGens::default));
@@ -16,9 +16,23 @@ fn bp_gens(c: &mut Criterion) {
 	);
 }
 
+/// Creating large generators, which is computed on the thread pool with the
+/// `rayon` feature.
+fn large_bp_gens(c: &mut Criterion) {
+	let mut group = c.benchmark_group("BulletproofGens::new large");
+	group.sample_size(10);
+	for &size in [1 << 14, 1 << 16].iter() {
+		group.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, &size| {
+			b.iter(|| BulletproofGens::new(size, 1))
+		});
+	}
+	group.finish();
+}
+
 criterion_group! {
 	bp,
 	bp_gens,
+	large_bp_gens,
 	pc_gens,
 }
 
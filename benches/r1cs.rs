@@ -18,6 +18,9 @@ extern crate merlin;
 extern crate rand;
 extern crate webb_bulletproofs;
 
+#[cfg(feature = "rayon")]
+extern crate rayon;
+
 use curve25519_dalek::{ristretto::CompressedRistretto, scalar::Scalar};
 use merlin::Transcript;
 use rand::{seq::SliceRandom, Rng};
@@ -232,4 +235,275 @@ criterion_group! {
 	bench_kshuffle_verify,
 }
 
-criterion_main!(kshuffle_prove, kshuffle_verify);
+/// Enforces that the quantity of v is in the range [0, 2^n).
+fn range_proof<CS: ConstraintSystem>(
+	cs: &mut CS,
+	mut v: LinearCombination,
+	v_assignment: Option<u64>,
+	n: usize,
+) -> Result<(), R1CSError> {
+	let mut exp_2 = Scalar::one();
+	for i in 0..n {
+		let (a, b, o) = cs.allocate_multiplier(v_assignment.map(|q| {
+			let bit: u64 = (q >> i) & 1;
+			((1 - bit).into(), bit.into())
+		}))?;
+		cs.constrain(o.into());
+		cs.constrain(a + (b - 1u64));
+		v = v - b * exp_2;
+		exp_2 = exp_2 + exp_2;
+	}
+	cs.constrain(v);
+	Ok(())
+}
+
+/// Number of 64-bit range proofs in the benchmarked circuit.
+const RANGE_PROOF_COUNT: usize = 16;
+
+fn bench_verification_key(c: &mut Criterion) {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64 * RANGE_PROOF_COUNT, 1);
+
+	let mut rng = rand::thread_rng();
+	let values: Vec<u64> = (0..RANGE_PROOF_COUNT).map(|_| rng.gen()).collect();
+
+	let (proof, commitments) = {
+		let mut prover_transcript = Transcript::new(b"VerificationKeyBenchmark");
+		let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+		let mut commitments = Vec::new();
+		for v in values.iter() {
+			let (com, var) = prover.commit((*v).into(), Scalar::random(&mut rng));
+			range_proof(&mut prover, var.into(), Some(*v), 64).unwrap();
+			commitments.push(com);
+		}
+		(prover.prove(&bp_gens).unwrap(), commitments)
+	};
+
+	let vk = VerificationKey::from_circuit(
+		RANGE_PROOF_COUNT,
+		|verifier, vars| {
+			vars.into_iter()
+				.try_for_each(|var| range_proof(verifier, var.into(), None, 64))
+		},
+		&bp_gens,
+		&pc_gens,
+	)
+	.unwrap();
+
+	let synthesizing_proof = proof.clone();
+	let synthesizing_commitments = commitments.clone();
+	c.bench_function("range proof circuit verification with synthesis", move |b| {
+		b.iter(|| {
+			let mut verifier_transcript = Transcript::new(b"VerificationKeyBenchmark");
+			let mut verifier = Verifier::new(&mut verifier_transcript);
+			for com in synthesizing_commitments.iter() {
+				let var = verifier.commit(*com);
+				range_proof(&mut verifier, var.into(), None, 64).unwrap();
+			}
+			verifier.verify(&synthesizing_proof, &pc_gens, &bp_gens).unwrap();
+		})
+	});
+
+	c.bench_function("range proof circuit verification with verification key", move |b| {
+		b.iter(|| {
+			let mut verifier_transcript = Transcript::new(b"VerificationKeyBenchmark");
+			vk.verify(&mut verifier_transcript, &commitments, &proof).unwrap();
+		})
+	});
+}
+
+fn bench_precomputed_verification_key(c: &mut Criterion) {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64 * RANGE_PROOF_COUNT, 1);
+
+	for count in [1, RANGE_PROOF_COUNT].iter().cloned() {
+		let mut rng = rand::thread_rng();
+		let values: Vec<u64> = (0..count).map(|_| rng.gen()).collect();
+
+		let (proof, commitments) = {
+			let mut prover_transcript = Transcript::new(b"PrecomputedKeyBenchmark");
+			let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+			let mut commitments = Vec::new();
+			for v in values.iter() {
+				let (com, var) = prover.commit((*v).into(), Scalar::random(&mut rng));
+				range_proof(&mut prover, var.into(), Some(*v), 64).unwrap();
+				commitments.push(com);
+			}
+			(prover.prove(&bp_gens).unwrap(), commitments)
+		};
+
+		let vk = VerificationKey::from_circuit(
+			count,
+			|verifier, vars| {
+				vars.into_iter()
+					.try_for_each(|var| range_proof(verifier, var.into(), None, 64))
+			},
+			&bp_gens,
+			&pc_gens,
+		)
+		.unwrap();
+		let mut precomputed_vk = vk.clone();
+		precomputed_vk.precompute();
+
+		for (name, vk) in [("generic", vk), ("precomputed", precomputed_vk)].iter() {
+			let vk = vk.clone();
+			let proof = proof.clone();
+			let commitments = commitments.clone();
+			let label = format!("{}-bit range proof circuit verification with {} key", 64 * count, name);
+			c.bench_function(&label, move |b| {
+				b.iter(|| {
+					let mut verifier_transcript = Transcript::new(b"PrecomputedKeyBenchmark");
+					vk.verify(&mut verifier_transcript, &commitments, &proof).unwrap();
+				})
+			});
+		}
+	}
+}
+
+criterion_group! {
+	name = verification_key;
+	config = Criterion::default().sample_size(10);
+	targets =
+	bench_verification_key,
+	bench_precomputed_verification_key,
+}
+
+/// Allocates `n` multipliers computing \\((i + 1) \cdot 2\\), and only
+/// constrains every thousandth left input, and the first one to the
+/// committed value `v`.  With `padding`, the last constraint also has a
+/// zero-weighted term for the left input of every multiplier.
+fn sparse_circuit<CS: ConstraintSystem>(
+	cs: &mut CS,
+	v: Variable,
+	v_assignment: Option<u64>,
+	n: usize,
+	padding: bool,
+) -> Result<(), R1CSError> {
+	let mut left = Vec::with_capacity(n);
+	for i in 0..n {
+		let l_assignment = if i == 0 { v_assignment } else { Some(i as u64 + 1) };
+		let (l, ..) = cs.allocate_multiplier(l_assignment.map(|l| (l.into(), 2u64.into())))?;
+		if i > 0 && i % 1000 == 0 {
+			cs.constrain(l - (i as u64 + 1));
+		}
+		left.push(l);
+	}
+	let zeros: LinearCombination = left.iter().map(|l| (*l, Scalar::zero())).collect();
+	if padding {
+		cs.constrain(left[0] - v + zeros);
+	} else {
+		cs.constrain(left[0] - v);
+	}
+	Ok(())
+}
+
+/// Number of multipliers in the sparse benchmarked circuit.
+const SPARSE_CIRCUIT_SIZE: usize = 10_000;
+
+fn bench_sparse_verification(c: &mut Criterion) {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(SPARSE_CIRCUIT_SIZE.next_power_of_two(), 1);
+
+	let (proof, com) = {
+		let mut prover_transcript = Transcript::new(b"SparseBenchmark");
+		let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+		let (com, var) = prover.commit(1u64.into(), Scalar::random(&mut rand::thread_rng()));
+		sparse_circuit(&mut prover, var, Some(1), SPARSE_CIRCUIT_SIZE, false).unwrap();
+		(prover.prove(&bp_gens).unwrap(), com)
+	};
+
+	// The zero-weighted terms of the padded circuit make the verifier
+	// flatten the constraints into dense vectors.
+	for (name, padding) in [("sparse", false), ("dense", true)].iter().cloned() {
+		let label = format!("10k-multiplier circuit verification with {} constraints", name);
+		c.bench_function(&label, |b| {
+			b.iter(|| {
+				let mut verifier_transcript = Transcript::new(b"SparseBenchmark");
+				let mut verifier = Verifier::new(&mut verifier_transcript);
+				let var = verifier.commit(com);
+				sparse_circuit(&mut verifier, var, None, SPARSE_CIRCUIT_SIZE, padding).unwrap();
+				verifier.verify(&proof, &pc_gens, &bp_gens).unwrap();
+			})
+		});
+	}
+}
+
+criterion_group! {
+	name = sparse_verification;
+	config = Criterion::default().sample_size(10);
+	targets =
+	bench_sparse_verification,
+}
+
+/// Number of 64-bit range proofs in the circuit of 2^16 multipliers.
+#[cfg(feature = "rayon")]
+const PARALLEL_RANGE_PROOF_COUNT: usize = 1024;
+
+#[cfg(feature = "rayon")]
+fn bench_parallel_verification(c: &mut Criterion) {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64 * PARALLEL_RANGE_PROOF_COUNT, 1);
+
+	let mut rng = rand::thread_rng();
+	let values: Vec<u64> = (0..PARALLEL_RANGE_PROOF_COUNT).map(|_| rng.gen()).collect();
+
+	let (proof, commitments) = {
+		let mut prover_transcript = Transcript::new(b"ParallelVerificationBenchmark");
+		let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+		let mut commitments = Vec::new();
+		for v in values.iter() {
+			let (com, var) = prover.commit((*v).into(), Scalar::random(&mut rng));
+			range_proof(&mut prover, var.into(), Some(*v), 64).unwrap();
+			commitments.push(com);
+		}
+		(prover.prove(&bp_gens).unwrap(), commitments)
+	};
+
+	let vk = VerificationKey::from_circuit(
+		PARALLEL_RANGE_PROOF_COUNT,
+		|verifier, vars| {
+			vars.into_iter()
+				.try_for_each(|var| range_proof(verifier, var.into(), None, 64))
+		},
+		&bp_gens,
+		&pc_gens,
+	)
+	.unwrap();
+
+	let max_threads = rayon::current_num_threads();
+	for threads in [1, max_threads].iter().cloned() {
+		let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+		let vk = vk.clone();
+		let proof = proof.clone();
+		let commitments = commitments.clone();
+		let label = format!("2^16-multiplier circuit verification with {} threads", threads);
+		c.bench_function(&label, move |b| {
+			b.iter(|| {
+				pool.install(|| {
+					let mut verifier_transcript = Transcript::new(b"ParallelVerificationBenchmark");
+					vk.verify(&mut verifier_transcript, &commitments, &proof).unwrap();
+				})
+			})
+		});
+	}
+}
+
+#[cfg(feature = "rayon")]
+criterion_group! {
+	name = parallel_verification;
+	config = Criterion::default().sample_size(10);
+	targets =
+	bench_parallel_verification,
+}
+
+#[cfg(feature = "rayon")]
+criterion_main!(
+	kshuffle_prove,
+	kshuffle_verify,
+	verification_key,
+	sparse_verification,
+	parallel_verification
+);
+
+#[cfg(not(feature = "rayon"))]
+criterion_main!(kshuffle_prove, kshuffle_verify, verification_key, sparse_verification);
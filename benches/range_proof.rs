@@ -3,6 +3,9 @@
 extern crate criterion;
 use criterion::Criterion;
 
+#[cfg(feature = "rayon")]
+extern crate rayon;
+
 use rand::Rng;
 
 use curve25519_dalek::scalar::Scalar;
@@ -99,6 +102,39 @@ fn verify_aggregated_rangeproof_n_64(c: &mut Criterion) {
 	verify_aggregated_rangeproof_helper(64, c);
 }
 
+static BATCH_SIZES: [usize; 4] = [1, 16, 64, 256];
+
+fn verify_batch_rangeproof_helper(n: usize, c: &mut Criterion) {
+	let label = format!("Batched {}-bit rangeproof verification", n);
+
+	c.bench_function_over_inputs(
+		&label,
+		move |b, &&batch_size| {
+			let pc_gens = PedersenGens::default();
+			let bp_gens = BulletproofGens::new(n, 1);
+			let mut rng = rand::thread_rng();
+			let transcript = Transcript::new(b"BatchRangeProofBenchmark");
+
+			let (min, max) = (0u64, ((1u128 << n) - 1) as u64);
+			let proofs: Vec<_> = (0..batch_size)
+				.map(|_| {
+					let value = rng.gen_range(min, max);
+					let blinding = Scalar::random(&mut rng);
+					RangeProof::prove_single(&bp_gens, &pc_gens, &mut transcript.clone(), value, &blinding, n).unwrap()
+				})
+				.collect();
+			let batch: Vec<_> = proofs.iter().map(|(proof, V)| (proof, V)).collect();
+
+			b.iter(|| RangeProof::verify_batch(&batch, &bp_gens, &pc_gens, &transcript, n));
+		},
+		&BATCH_SIZES,
+	);
+}
+
+fn verify_batch_rangeproof_n_64(c: &mut Criterion) {
+	verify_batch_rangeproof_helper(64, c);
+}
+
 criterion_group! {
 	name = create_rp;
 	config = Criterion::default().sample_size(10);
@@ -119,4 +155,74 @@ criterion_group! {
 	verify_aggregated_rangeproof_n_64,
 }
 
-criterion_main!(create_rp, verify_rp);
+criterion_group! {
+	name = verify_batch_rp;
+	config = Criterion::default().sample_size(10);
+	targets = verify_batch_rangeproof_n_64,
+}
+
+#[cfg(feature = "rayon")]
+const PARALLEL_BATCH_SIZE: usize = 1000;
+
+#[cfg(feature = "rayon")]
+fn verify_parallel_batch_rangeproof_n_64(c: &mut Criterion) {
+	let n = 64;
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(n, 1);
+	let mut rng = rand::thread_rng();
+	let transcript = Transcript::new(b"ParallelBatchRangeProofBenchmark");
+
+	let proofs: Vec<_> = (0..PARALLEL_BATCH_SIZE)
+		.map(|_| {
+			let value = rng.gen::<u64>();
+			let blinding = Scalar::random(&mut rng);
+			RangeProof::prove_single(&bp_gens, &pc_gens, &mut transcript.clone(), value, &blinding, n).unwrap()
+		})
+		.collect();
+	let batch: Vec<_> = proofs.iter().map(|(proof, V)| (proof, V)).collect();
+
+	c.bench_function(
+		&format!("{} {}-bit rangeproofs verified one by one", PARALLEL_BATCH_SIZE, n),
+		|b| {
+			b.iter(|| {
+				for (proof, V) in batch.iter() {
+					proof
+						.verify_single(&bp_gens, &pc_gens, &mut transcript.clone(), V, n)
+						.unwrap();
+				}
+			})
+		},
+	);
+	c.bench_function(
+		&format!(
+			"{} {}-bit rangeproofs verified in a serial batch",
+			PARALLEL_BATCH_SIZE, n
+		),
+		|b| b.iter(|| RangeProof::verify_batch(&batch, &bp_gens, &pc_gens, &transcript, n).unwrap()),
+	);
+	let parallelism = rayon::current_num_threads();
+	c.bench_function(
+		&format!(
+			"{} {}-bit rangeproofs verified in a parallel batch of {} partitions",
+			PARALLEL_BATCH_SIZE, n, parallelism
+		),
+		|b| {
+			b.iter(|| {
+				RangeProof::verify_batch_parallel(&batch, &bp_gens, &pc_gens, &transcript, n, parallelism).unwrap()
+			})
+		},
+	);
+}
+
+#[cfg(feature = "rayon")]
+criterion_group! {
+	name = verify_parallel_batch_rp;
+	config = Criterion::default().sample_size(10);
+	targets = verify_parallel_batch_rangeproof_n_64,
+}
+
+#[cfg(feature = "rayon")]
+criterion_main!(create_rp, verify_rp, verify_batch_rp, verify_parallel_batch_rp);
+
+#[cfg(not(feature = "rayon"))]
+criterion_main!(create_rp, verify_rp, verify_batch_rp);
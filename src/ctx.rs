@@ -0,0 +1,265 @@
+#![allow(non_snake_case)]
+//! Confidential transactions built from range proofs and a balance check.
+
+use alloc::vec::Vec;
+use core::iter;
+
+use curve25519_dalek::{
+	ristretto::{CompressedRistretto, RistrettoPoint},
+	scalar::Scalar,
+	traits::{IsIdentity, VartimeMultiscalarMul},
+};
+use merlin::Transcript;
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(feature = "std")]
+use rand::thread_rng;
+#[cfg(feature = "std")]
+use serde::de::Visitor;
+#[cfg(feature = "std")]
+use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+	errors::ProofError,
+	generators::{BulletproofGens, PedersenGens},
+	range_proof::RangeProof,
+	transcript::TranscriptProtocol,
+	util,
+};
+
+/// The bitsize of the range proofs on the outputs.
+const OUTPUT_BITSIZE: usize = 64;
+
+/// A proof that a transaction with hidden amounts is balanced.
+///
+/// The inputs and outputs of the transaction are Pedersen commitments
+/// \\(V = v B + \tilde{v} \widetilde{B}\\).  The proof shows that
+///
+/// * every output value is in the range \\([0, 2^{64})\\), with an aggregated
+///   [`RangeProof`], and
+/// * the input values equal the output values plus the public `fee`, by
+///   revealing the excess blinding factor \\(\tilde{e} = \sum \tilde{v}\_{in} -
+///   \sum \tilde{v}\_{out}\\), so that \\(\sum V\_{in} - \sum V\_{out} - fee
+///   \cdot B = \tilde{e} \widetilde{B}\\).
+///
+/// Since the excess is a difference of random blinding factors, revealing
+/// it does not reveal anything about the amounts.
+#[derive(Clone, Debug)]
+pub struct ConfidentialTx {
+	/// Aggregated range proof over the outputs.
+	range_proof: RangeProof,
+	/// Difference between the input and output blinding factors.
+	excess_blinding: Scalar,
+}
+
+impl ConfidentialTx {
+	/// Creates a proof for a transaction spending the `inputs` into the
+	/// `outputs` and the public `fee`.
+	///
+	/// This is a convenience wrapper around
+	/// [`ConfidentialTx::prove_with_rng`], passing in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn prove(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		inputs: &[(u64, Scalar)],
+		outputs: &[(u64, Scalar)],
+		fee: u64,
+	) -> Result<(ConfidentialTx, Vec<CompressedRistretto>, Vec<CompressedRistretto>), ProofError> {
+		ConfidentialTx::prove_with_rng(bp_gens, pc_gens, transcript, inputs, outputs, fee, &mut thread_rng())
+	}
+
+	/// Creates a proof for a transaction spending the `inputs` into the
+	/// `outputs` and the public `fee`, where the inputs and outputs are
+	/// given as pairs of a value and a blinding factor.
+	///
+	/// The `bp_gens` must have a party capacity of at least the number of
+	/// outputs, rounded up to a power of two.
+	///
+	/// Returns the proof, the input commitments and the output commitments,
+	/// or [`ProofError::UnbalancedTransaction`] if the input values are not
+	/// the output values plus the fee.
+	pub fn prove_with_rng<T: RngCore + CryptoRng>(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		inputs: &[(u64, Scalar)],
+		outputs: &[(u64, Scalar)],
+		fee: u64,
+		rng: &mut T,
+	) -> Result<(ConfidentialTx, Vec<CompressedRistretto>, Vec<CompressedRistretto>), ProofError> {
+		let inputs_sum: u128 = inputs.iter().map(|&(v, _)| v as u128).sum();
+		let outputs_sum: u128 = outputs.iter().map(|&(v, _)| v as u128).sum();
+		if inputs_sum != outputs_sum + fee as u128 {
+			return Err(ProofError::UnbalancedTransaction);
+		}
+
+		let input_commitments: Vec<_> = inputs
+			.iter()
+			.map(|&(v, v_blinding)| pc_gens.commit(v.into(), v_blinding).compress())
+			.collect();
+		transcript.ctx_domain_sep(fee, &input_commitments);
+
+		let values: Vec<u64> = outputs.iter().map(|&(v, _)| v).collect();
+		let blindings: Vec<Scalar> = outputs.iter().map(|&(_, v_blinding)| v_blinding).collect();
+
+		let (range_proof, output_commitments) = RangeProof::prove_multiple_with_rng(
+			bp_gens,
+			pc_gens,
+			transcript,
+			&values,
+			&blindings,
+			OUTPUT_BITSIZE,
+			rng,
+		)?;
+
+		let excess_blinding = inputs.iter().map(|(_, v_blinding)| v_blinding).sum::<Scalar>()
+			- outputs.iter().map(|(_, v_blinding)| v_blinding).sum::<Scalar>();
+
+		Ok((
+			ConfidentialTx {
+				range_proof,
+				excess_blinding,
+			},
+			input_commitments,
+			output_commitments,
+		))
+	}
+
+	/// Verifies that the transaction spending the `input_commitments` into
+	/// the `output_commitments` and the public `fee` is balanced.
+	///
+	/// This is a convenience wrapper around
+	/// [`ConfidentialTx::verify_with_rng`], passing in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn verify(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		input_commitments: &[CompressedRistretto],
+		output_commitments: &[CompressedRistretto],
+		fee: u64,
+	) -> Result<(), ProofError> {
+		self.verify_with_rng(
+			bp_gens,
+			pc_gens,
+			transcript,
+			input_commitments,
+			output_commitments,
+			fee,
+			&mut thread_rng(),
+		)
+	}
+
+	/// Verifies that the transaction spending the `input_commitments` into
+	/// the `output_commitments` and the public `fee` is balanced.
+	///
+	/// The balance equation is checked first, since it is much cheaper than
+	/// the verification of the range proof.
+	pub fn verify_with_rng<T: RngCore + CryptoRng>(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		input_commitments: &[CompressedRistretto],
+		output_commitments: &[CompressedRistretto],
+		fee: u64,
+		rng: &mut T,
+	) -> Result<(), ProofError> {
+		// sum(V_in) - sum(V_out) - fee * B - excess * B_blinding == 0
+		let scalars = iter::repeat(Scalar::one())
+			.take(input_commitments.len())
+			.chain(iter::repeat(-Scalar::one()).take(output_commitments.len()))
+			.chain(iter::once(-Scalar::from(fee)))
+			.chain(iter::once(-self.excess_blinding));
+		let points = input_commitments
+			.iter()
+			.chain(output_commitments.iter())
+			.map(|V| V.decompress())
+			.chain(iter::once(Some(pc_gens.B)))
+			.chain(iter::once(Some(pc_gens.B_blinding)));
+		let balance = RistrettoPoint::optional_multiscalar_mul(scalars, points).ok_or(ProofError::FormatError)?;
+		if !balance.is_identity() {
+			return Err(ProofError::VerificationError);
+		}
+
+		transcript.ctx_domain_sep(fee, input_commitments);
+
+		self.range_proof
+			.verify_multiple_with_rng(bp_gens, pc_gens, transcript, output_commitments, OUTPUT_BITSIZE, rng)
+	}
+
+	/// Returns the aggregated range proof over the outputs.
+	pub fn range_proof(&self) -> &RangeProof {
+		&self.range_proof
+	}
+
+	/// Returns the excess blinding factor of the balance equation.
+	pub fn excess_blinding(&self) -> Scalar {
+		self.excess_blinding
+	}
+
+	/// Serializes the proof into a byte array of the excess blinding
+	/// factor, followed by the encoding of the range proof.
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut buf = self.excess_blinding.as_bytes().to_vec();
+		buf.extend_from_slice(&self.range_proof.to_bytes());
+		buf
+	}
+
+	/// Deserializes the proof from a byte slice.
+	///
+	/// Returns an error if the byte slice cannot be parsed into a
+	/// `ConfidentialTx`.
+	pub fn from_bytes(slice: &[u8]) -> Result<ConfidentialTx, ProofError> {
+		if slice.len() < 32 {
+			return Err(ProofError::FormatError);
+		}
+		let excess_blinding = Scalar::from_canonical_bytes(util::read32(slice)).ok_or(ProofError::FormatError)?;
+		let range_proof = RangeProof::from_bytes(&slice[32..])?;
+
+		Ok(ConfidentialTx {
+			range_proof,
+			excess_blinding,
+		})
+	}
+}
+
+#[cfg(feature = "std")]
+impl Serialize for ConfidentialTx {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		serializer.serialize_bytes(&self.to_bytes()[..])
+	}
+}
+
+#[cfg(feature = "std")]
+impl<'de> Deserialize<'de> for ConfidentialTx {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		struct ConfidentialTxVisitor;
+
+		impl<'de> Visitor<'de> for ConfidentialTxVisitor {
+			type Value = ConfidentialTx;
+
+			fn expecting(&self, formatter: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+				formatter.write_str("a valid ConfidentialTx")
+			}
+
+			fn visit_bytes<E>(self, v: &[u8]) -> Result<ConfidentialTx, E>
+			where
+				E: serde::de::Error,
+			{
+				ConfidentialTx::from_bytes(v).map_err(serde::de::Error::custom)
+			}
+		}
+
+		deserializer.deserialize_bytes(ConfidentialTxVisitor)
+	}
+}
@@ -9,6 +9,9 @@ use alloc::vec::Vec;
 #[cfg(feature = "std")]
 use thiserror::Error;
 
+#[cfg(feature = "yoloproofs")]
+use crate::r1cs::Variable;
+
 /// Represents an error in proof creation, verification, or parsing.
 #[derive(Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "std", derive(Error))]
@@ -24,16 +27,37 @@ pub enum ProofError {
 	#[cfg_attr(feature = "std", error("Wrong number of blinding factors supplied."))]
 	WrongNumBlindingFactors,
 	/// This error occurs when attempting to create a proof with
-	/// bitsize other than \\(8\\), \\(16\\), \\(32\\), or \\(64\\).
-	#[cfg_attr(feature = "std", error("Invalid bitsize, must have n = 8,16,32,64."))]
+	/// bitsize outside of \\(1\\) to \\(64\\).
+	#[cfg_attr(feature = "std", error("Invalid bitsize, must have 1 <= n <= 64."))]
 	InvalidBitsize,
 	/// This error occurs when attempting to create an aggregated
-	/// proof with non-power-of-two aggregation size.
-	#[cfg_attr(feature = "std", error("Invalid aggregation size, m must be a power of 2."))]
+	/// proof of no values, or of a number of constraint systems which is
+	/// not a power of two.
+	#[cfg_attr(
+		feature = "std",
+		error("Invalid aggregation size, m must be at least 1, and a power of 2 for constraint systems.")
+	)]
 	InvalidAggregation,
+	/// This error occurs when the input vectors of a proof are empty or
+	/// do not have the same length.
+	#[cfg_attr(feature = "std", error("Invalid length of the input vectors."))]
+	InvalidInputLength,
 	/// This error occurs when there are insufficient generators for the proof.
-	#[cfg_attr(feature = "std", error("Invalid generators size, too few generators for proof"))]
-	InvalidGeneratorsLength,
+	///
+	/// The capacities are the `gens_capacity` of the
+	/// [`BulletproofGens`](::BulletproofGens), or their `party_capacity`
+	/// when the aggregation size exceeds it.  The required capacities are
+	/// those returned by `BulletproofGens::required_capacity_for_range`.
+	#[cfg_attr(
+		feature = "std",
+		error("Invalid generators size, too few generators for proof: {required} required, {actual} available")
+	)]
+	InvalidGeneratorsLength {
+		/// The capacity required by the proof.
+		required: usize,
+		/// The capacity of the supplied generators.
+		actual: usize,
+	},
 	/// This error results from an internal error during proving.
 	///
 	/// The single-party prover is implemented by performing
@@ -42,6 +66,61 @@ pub enum ProofError {
 	/// consider its errors to be internal errors.
 	#[cfg_attr(feature = "std", error("Internal error during proof creation: {0}"))]
 	ProvingError(MPCError),
+	/// This error occurs when proof creation is cancelled through a
+	/// [`CancellationToken`](::CancellationToken).
+	#[cfg_attr(feature = "std", error("Proof creation was cancelled."))]
+	Cancelled,
+	/// This error occurs when attempting to prove a confidential
+	/// transaction whose inputs do not cover its outputs and fee.
+	#[cfg_attr(feature = "std", error("Transaction inputs do not equal outputs plus fee."))]
+	UnbalancedTransaction,
+	/// This error occurs when attempting to prove that a value given as a
+	/// scalar is in a range which it is not in.
+	#[cfg_attr(feature = "std", error("Value does not fit in the bitsize of the range."))]
+	ValueOutOfRange,
+	/// This error occurs when attempting to prove that a value is at
+	/// least a minimum, or another committed value, which is larger than
+	/// the value.
+	#[cfg_attr(feature = "std", error("Value is below the minimum of the range."))]
+	ValueBelowMinimum,
+	/// This error occurs when a range proof cannot be rewound, because it
+	/// was not created with the given rewind key or for the given
+	/// commitment.
+	#[cfg_attr(feature = "std", error("Proof could not be rewound with the given key."))]
+	RewindError,
+	/// This error occurs when attempting to prove a range for an existing
+	/// commitment which is not the commitment to the given value and
+	/// blinding factor.
+	#[cfg_attr(feature = "std", error("The value and blinding factor do not open the commitment."))]
+	WrongCommitment,
+	/// This error occurs when a proof in a batch fails to verify.
+	#[cfg_attr(feature = "std", error("RangeProof {index} of the batch did not verify correctly."))]
+	BatchVerificationError {
+		/// The index of the first proof of the batch that failed.
+		index: usize,
+	},
+	/// This error occurs when a chunk of proofs of a streamed batch fails
+	/// to verify.
+	#[cfg_attr(
+		feature = "std",
+		error("RangeProofs {start} to {end} of the batch did not verify correctly.")
+	)]
+	BatchChunkVerificationError {
+		/// The index of the first proof of the chunk.
+		start: usize,
+		/// The index after the last proof of the chunk.
+		end: usize,
+	},
+	/// This error occurs when statements of a
+	/// [`VerificationBatch`](::VerificationBatch) fail to verify.
+	#[cfg_attr(
+		feature = "std",
+		error("Statements {indexes:?} of the batch did not verify correctly.")
+	)]
+	BatchStatementsVerificationError {
+		/// The indexes of the statements that failed, in increasing order.
+		indexes: Vec<usize>,
+	},
 }
 
 impl From<MPCError> for ProofError {
@@ -49,7 +128,9 @@ impl From<MPCError> for ProofError {
 		match e {
 			MPCError::InvalidBitsize => ProofError::InvalidBitsize,
 			MPCError::InvalidAggregation => ProofError::InvalidAggregation,
-			MPCError::InvalidGeneratorsLength => ProofError::InvalidGeneratorsLength,
+			MPCError::InvalidGeneratorsLength { required, actual } => {
+				ProofError::InvalidGeneratorsLength { required, actual }
+			}
 			_ => ProofError::ProvingError(e),
 		}
 	}
@@ -70,24 +151,60 @@ pub enum MPCError {
 	#[cfg_attr(feature = "std", error("Dealer gave a malicious challenge value."))]
 	MaliciousDealer,
 	/// This error occurs when attempting to create a proof with
-	/// bitsize other than \\(8\\), \\(16\\), \\(32\\), or \\(64\\).
-	#[cfg_attr(feature = "std", error("Invalid bitsize, must have n = 8,16,32,64"))]
+	/// bitsize outside of \\(1\\) to \\(64\\).
+	#[cfg_attr(feature = "std", error("Invalid bitsize, must have 1 <= n <= 64"))]
 	InvalidBitsize,
 	/// This error occurs when attempting to create an aggregated
-	/// proof with non-power-of-two aggregation size.
-	#[cfg_attr(feature = "std", error("Invalid aggregation size, m must be a power of 2"))]
+	/// proof of no values, or of a number of constraint systems which is
+	/// not a power of two.
+	#[cfg_attr(
+		feature = "std",
+		error("Invalid aggregation size, m must be at least 1, and a power of 2 for constraint systems")
+	)]
 	InvalidAggregation,
 	/// This error occurs when there are insufficient generators for the proof.
-	#[cfg_attr(feature = "std", error("Invalid generators size, too few generators for proof"))]
-	InvalidGeneratorsLength,
+	///
+	/// The capacities are the `gens_capacity` of the
+	/// [`BulletproofGens`](::BulletproofGens), or their `party_capacity`
+	/// when the aggregation size exceeds it.  The required capacities are
+	/// those returned by `BulletproofGens::required_capacity_for_range`.
+	#[cfg_attr(
+		feature = "std",
+		error("Invalid generators size, too few generators for proof: {required} required, {actual} available")
+	)]
+	InvalidGeneratorsLength {
+		/// The capacity required by the proof.
+		required: usize,
+		/// The capacity of the supplied generators.
+		actual: usize,
+	},
 	/// This error occurs when the dealer is given the wrong number of
 	/// value commitments.
 	#[cfg_attr(feature = "std", error("Wrong number of value commitments"))]
 	WrongNumBitCommitments,
+	/// This error occurs when one or more parties submit malformed bit
+	/// commitments.
+	#[cfg_attr(feature = "std", error("Malformed bit commitments from parties {bad_commitments:?}"))]
+	MalformedBitCommitments {
+		/// A vector with the indexes of the parties whose bit commitments
+		/// were malformed.
+		bad_commitments: Vec<usize>,
+	},
 	/// This error occurs when the dealer is given the wrong number of
 	/// polynomial commitments.
 	#[cfg_attr(feature = "std", error("Wrong number of value commitments"))]
 	WrongNumPolyCommitments,
+	/// This error occurs when one or more parties submit malformed
+	/// polynomial commitments.
+	#[cfg_attr(
+		feature = "std",
+		error("Malformed polynomial commitments from parties {bad_commitments:?}")
+	)]
+	MalformedPolyCommitments {
+		/// A vector with the indexes of the parties whose polynomial
+		/// commitments were malformed.
+		bad_commitments: Vec<usize>,
+	},
 	/// This error occurs when the dealer is given the wrong number of
 	/// proof shares.
 	#[cfg_attr(feature = "std", error("Wrong number of proof shares"))]
@@ -102,6 +219,25 @@ pub enum MPCError {
 	},
 }
 
+/// Represents an error in constructing or checking generators.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "std", derive(Error))]
+pub enum GeneratorError {
+	/// This error occurs when a supplied generator is the identity.
+	#[cfg_attr(feature = "std", error("Generator is the identity point."))]
+	IdentityPoint,
+	/// This error occurs when the supplied generators are not distinct.
+	#[cfg_attr(feature = "std", error("Generators are not distinct."))]
+	EqualPoints,
+	/// This error occurs when generators do not match the ones derived
+	/// from a seed.
+	#[cfg_attr(feature = "std", error("Generators do not match the seed derivation."))]
+	AuditFailed,
+	/// This error occurs when the encoding of generators is malformed.
+	#[cfg_attr(feature = "std", error("Generator data could not be parsed."))]
+	FormatError,
+}
+
 /// Represents an error during the proving or verifying of a constraint system.
 ///
 /// XXX: should this be separate from a `ProofError`?
@@ -110,8 +246,21 @@ pub enum MPCError {
 #[cfg_attr(feature = "std", derive(Error))]
 pub enum R1CSError {
 	/// Occurs when there are insufficient generators for the proof.
-	#[cfg_attr(feature = "std", error("Invalid generators size, too few generators for proof"))]
-	InvalidGeneratorsLength,
+	///
+	/// The capacities are the `gens_capacity` of the
+	/// [`BulletproofGens`](::BulletproofGens), which must be at least the
+	/// number of multipliers, padded to a power of two, as returned by
+	/// `BulletproofGens::required_capacity_for_r1cs`.
+	#[cfg_attr(
+		feature = "std",
+		error("Invalid generators size, too few generators for proof: {required} required, {actual} available")
+	)]
+	InvalidGeneratorsLength {
+		/// The capacity required by the proof.
+		required: usize,
+		/// The capacity of the supplied generators.
+		actual: usize,
+	},
 	/// This error occurs when the proof encoding is malformed.
 	#[cfg_attr(feature = "std", error("Proof data could not be parsed."))]
 	FormatError,
@@ -123,23 +272,147 @@ pub enum R1CSError {
 	/// Occurs when trying to use a missing variable assignment.
 	/// Used by gadgets that build the constraint system to signal that
 	/// a variable assignment is not provided when the prover needs it.
-	#[cfg_attr(feature = "std", error("Variable does not have a value assignment."))]
-	MissingAssignment,
-	// Occurs when a gadget receives an inconsistent input.
+	#[cfg_attr(
+		feature = "std",
+		error("Variable {variable:?} does not have a value assignment ({context:?}).")
+	)]
+	MissingAssignment {
+		/// The variable whose assignment is missing, if it is known.
+		variable: Option<Variable>,
+		/// The description of the missing value given by the gadget, if
+		/// any.
+		context: Option<&'static str>,
+	},
+	/// Occurs when a gadget receives an inconsistent input.
+	///
+	/// Gadget libraries report their own failures with this variant,
+	/// usually through [`R1CSError::gadget`].
 	#[cfg_attr(feature = "std", error("Gadget error: {description:?}"))]
 	GadgetError {
 		/// The description of the reasons for the error.
 		description: String,
 	},
+	/// Occurs when the prover's witness cannot be used for the proof, such
+	/// as a value outside of the range a gadget decomposes it into.
+	#[cfg_attr(feature = "std", error("Malformed witness: {reason}"))]
+	MalformedWitness {
+		/// The reason why the witness is malformed.
+		reason: &'static str,
+	},
+	/// Occurs when proof creation is cancelled through a
+	/// [`CancellationToken`](::CancellationToken).
+	#[cfg_attr(feature = "std", error("Proof creation was cancelled."))]
+	Cancelled,
+	/// Occurs when the opening given to
+	/// [`Prover::commit_existing`](::r1cs::Prover::commit_existing) does
+	/// not match the commitment.
+	#[cfg_attr(feature = "std", error("The opening does not match the commitment."))]
+	CommitmentMismatch,
+	/// Occurs when the circuit synthesized by a verifier does not match the
+	/// circuit hash it expects, as bound with
+	/// [`Verifier::bind_circuit_hash`](::r1cs::Verifier::bind_circuit_hash),
+	/// or when a prover's circuit does not match a
+	/// [`CircuitDescription`](::r1cs::CircuitDescription).
+	#[cfg_attr(feature = "std", error("The circuit does not match the expected circuit."))]
+	CircuitMismatch,
+	/// Occurs when a proof is checked against a number of commitments other
+	/// than its circuit expects, such as a proof for a Merkle tree of
+	/// another depth checked with a
+	/// [`GadgetVerificationKey`](::r1cs::GadgetVerificationKey).
+	#[cfg_attr(
+		feature = "std",
+		error("The circuit expects {expected} commitments, but {actual} were given.")
+	)]
+	WrongNumberOfCommitments {
+		/// The number of commitments the circuit expects.
+		expected: usize,
+		/// The number of commitments given.
+		actual: usize,
+	},
+	/// Occurs when a constraint system cannot be rolled back to a
+	/// [`Snapshot`](::r1cs::Snapshot), because it committed a variable or a
+	/// public input since the snapshot was taken, or was already rolled
+	/// back to an earlier point.
+	#[cfg_attr(
+		feature = "std",
+		error("Cannot roll back across a commitment or another transcript operation.")
+	)]
+	RollbackAcrossTranscript,
+	/// Occurs when a public input is committed with
+	/// [`ConstraintSystem::commit_public_bytes`](::r1cs::ConstraintSystem::commit_public_bytes)
+	/// in randomized constraints, after challenges were drawn.
+	#[cfg_attr(
+		feature = "std",
+		error("Public inputs must be committed before the first challenge is drawn.")
+	)]
+	PublicInputAfterChallenge,
+	/// Occurs when proving from a prover whose randomized constraints were
+	/// already synthesized by another prover sharing them, that is the
+	/// prover it was checkpointed from or another prover restored from the
+	/// same [`ProverCheckpoint`](::r1cs::ProverCheckpoint).
+	#[cfg_attr(
+		feature = "std",
+		error("The randomized constraints were already synthesized from the same checkpoint.")
+	)]
+	RandomizedConstraintsConsumed,
+	/// Occurs when a constraint is not satisfied by the prover's
+	/// assignments, with diagnostics enabled by
+	/// [`Prover::enable_diagnostics`](::r1cs::Prover::enable_diagnostics).
+	#[cfg_attr(feature = "std", error("Constraint {index} ({label:?}) is not satisfied."))]
+	UnsatisfiedConstraint {
+		/// The index of the first unsatisfied constraint.
+		index: usize,
+		/// The label of the constraint, if it was added with
+		/// [`ConstraintSystem::constrain_labeled`](::r1cs::ConstraintSystem::constrain_labeled).
+		label: Option<&'static str>,
+	},
+	/// Occurs when a proof in a batch fails to verify.
+	#[cfg_attr(feature = "std", error("R1CSProof {index} of the batch did not verify correctly."))]
+	BatchVerificationError {
+		/// The index of the first proof of the batch that failed.
+		index: usize,
+	},
+	/// Occurs when proofs in a batch verified in parallel fail to verify.
+	#[cfg_attr(
+		feature = "std",
+		error("R1CSProofs {indexes:?} of the batch did not verify correctly.")
+	)]
+	BatchStatementsVerificationError {
+		/// The indexes of the proofs of the batch that failed, in
+		/// increasing order.
+		indexes: Vec<usize>,
+	},
+}
+
+#[cfg(feature = "yoloproofs")]
+impl R1CSError {
+	/// Creates a [`R1CSError::GadgetError`] with the given `description`.
+	pub fn gadget<D: Into<String>>(description: D) -> R1CSError {
+		R1CSError::GadgetError {
+			description: description.into(),
+		}
+	}
+
+	/// Creates a [`R1CSError::MalformedWitness`] with the given `reason`.
+	pub fn malformed_witness(reason: &'static str) -> R1CSError {
+		R1CSError::MalformedWitness { reason }
+	}
 }
 
 #[cfg(feature = "yoloproofs")]
 impl From<ProofError> for R1CSError {
 	fn from(e: ProofError) -> R1CSError {
 		match e {
-			ProofError::InvalidGeneratorsLength => R1CSError::InvalidGeneratorsLength,
+			ProofError::InvalidGeneratorsLength { required, actual } => {
+				R1CSError::InvalidGeneratorsLength { required, actual }
+			}
 			ProofError::FormatError => R1CSError::FormatError,
 			ProofError::VerificationError => R1CSError::VerificationError,
+			ProofError::Cancelled => R1CSError::Cancelled,
+			ProofError::BatchVerificationError { index } => R1CSError::BatchVerificationError { index },
+			ProofError::BatchStatementsVerificationError { indexes } => {
+				R1CSError::BatchStatementsVerificationError { indexes }
+			}
 			_ => panic!("unexpected error type in conversion"),
 		}
 	}
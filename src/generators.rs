@@ -9,13 +9,19 @@ extern crate alloc;
 use alloc::vec::Vec;
 use curve25519_dalek::{
 	constants::{RISTRETTO_BASEPOINT_COMPRESSED, RISTRETTO_BASEPOINT_POINT},
-	ristretto::RistrettoPoint,
+	ristretto::{CompressedRistretto, RistrettoPoint},
 	scalar::Scalar,
-	traits::MultiscalarMul,
+	traits::{IsIdentity, MultiscalarMul},
 };
 use digest::{ExtendableOutputDirty, Update, XofReader};
+use rand_core::{CryptoRng, RngCore};
 use sha3::{Sha3XofReader, Sha3_512, Shake256};
 
+#[cfg(feature = "std")]
+use rand::thread_rng;
+
+use crate::errors::GeneratorError;
+
 /// Represents a pair of base points for Pedersen commitments.
 ///
 /// The Bulletproofs implementation and API is designed to support
@@ -27,6 +33,10 @@ use sha3::{Sha3XofReader, Sha3_512, Shake256};
 /// * `B`: the `ristretto255` basepoint;
 /// * `B_blinding`: the result of `ristretto255` SHA3-512
 /// hash-to-group on input `B_bytes`.
+///
+/// Protocols which need commitments that cannot be replayed across
+/// contexts can derive separate bases per context with
+/// [`PedersenGens::new`].
 #[derive(Copy, Clone)]
 pub struct PedersenGens {
 	/// Base for the committed value
@@ -36,11 +46,62 @@ pub struct PedersenGens {
 }
 
 impl PedersenGens {
+	/// Creates Pedersen generators which are domain separated by `label`.
+	///
+	/// Both bases are derived with the `ristretto255` SHA3-512
+	/// hash-to-group function, from `"PedersenGens" || label || "B"` and
+	/// `"PedersenGens" || label || "b"` respectively.  Nobody knows their
+	/// discrete logarithms with respect to each other, to the default
+	/// generators, or to the generators of any other label, so commitments
+	/// made with them cannot be opened under another label.
+	pub fn new(label: &[u8]) -> Self {
+		let derive = |tag: u8| {
+			let mut input = b"PedersenGens".to_vec();
+			input.extend_from_slice(label);
+			input.push(tag);
+			RistrettoPoint::hash_from_bytes::<Sha3_512>(&input)
+		};
+		PedersenGens {
+			B: derive(b'B'),
+			B_blinding: derive(b'b'),
+		}
+	}
+
+	/// Creates Pedersen generators from the supplied base points.
+	///
+	/// Returns an error if either point is the identity, or if the points
+	/// are equal.
+	///
+	/// # Note
+	///
+	/// These checks only rule out degenerate bases.  The caller is
+	/// responsible for ensuring that nobody knows the discrete logarithm
+	/// of `B_blinding` with respect to `B` (the nothing-up-my-sleeve
+	/// property), e.g. by deriving both points with a hash-to-group
+	/// function from public inputs.  Otherwise the commitments are not
+	/// binding.
+	pub fn from_points(B: RistrettoPoint, B_blinding: RistrettoPoint) -> Result<Self, GeneratorError> {
+		if B.is_identity() || B_blinding.is_identity() {
+			return Err(GeneratorError::IdentityPoint);
+		}
+		if B == B_blinding {
+			return Err(GeneratorError::EqualPoints);
+		}
+		Ok(PedersenGens { B, B_blinding })
+	}
+
 	/// Creates a Pedersen commitment using the value scalar and a blinding
 	/// factor.
 	pub fn commit(&self, value: Scalar, blinding: Scalar) -> RistrettoPoint {
 		RistrettoPoint::multiscalar_mul(&[value, blinding], &[self.B, self.B_blinding])
 	}
+
+	/// Returns whether these are the bases of [`PedersenGens::default`].
+	#[cfg(feature = "yoloproofs")]
+	pub(crate) fn is_default(&self) -> bool {
+		let default = PedersenGens::default();
+		self.B == default.B && self.B_blinding == default.B_blinding
+	}
 }
 
 impl Default for PedersenGens {
@@ -75,11 +136,52 @@ impl GeneratorsChain {
 	/// the result.
 	fn fast_forward(mut self, n: usize) -> Self {
 		for _ in 0..n {
-			let mut buf = [0u8; 64];
-			self.reader.read(&mut buf);
+			self.skip_one();
 		}
 		self
 	}
+
+	/// Advances the reader past one generator without computing it.
+	fn skip_one(&mut self) {
+		self.next_uniform_bytes();
+	}
+
+	/// Squeezes the input of the hash-to-group map for the next generator.
+	fn next_uniform_bytes(&mut self) -> [u8; 64] {
+		let mut uniform_bytes = [0u8; 64];
+		self.reader.read(&mut uniform_bytes);
+		uniform_bytes
+	}
+
+	/// Appends the next `n` generators of the chain to `gens`.
+	///
+	/// With the `rayon` feature, the XOF output is still squeezed in
+	/// order, but the hash-to-group map, which dominates the cost, is
+	/// computed on the `rayon` thread pool, one chunk at a time.  The
+	/// generators are the same as without the feature.
+	fn append_to(self, gens: &mut Vec<RistrettoPoint>, n: usize) {
+		#[cfg(feature = "rayon")]
+		{
+			use rayon::prelude::*;
+
+			let mut chain = self;
+
+			// Bounds the XOF output buffered at once to 256 KiB.
+			const CHUNK_SIZE: usize = 4096;
+
+			gens.reserve(n);
+			let mut remaining = n;
+			while remaining > 0 {
+				let chunk: Vec<[u8; 64]> = (0..remaining.min(CHUNK_SIZE))
+					.map(|_| chain.next_uniform_bytes())
+					.collect();
+				remaining -= chunk.len();
+				gens.par_extend(chunk.par_iter().map(RistrettoPoint::from_uniform_bytes));
+			}
+		}
+		#[cfg(not(feature = "rayon"))]
+		gens.extend(self.take(n));
+	}
 }
 
 impl Default for GeneratorsChain {
@@ -92,10 +194,7 @@ impl Iterator for GeneratorsChain {
 	type Item = RistrettoPoint;
 
 	fn next(&mut self) -> Option<Self::Item> {
-		let mut uniform_bytes = [0u8; 64];
-		self.reader.read(&mut uniform_bytes);
-
-		Some(RistrettoPoint::from_uniform_bytes(&uniform_bytes))
+		Some(RistrettoPoint::from_uniform_bytes(&self.next_uniform_bytes()))
 	}
 
 	fn size_hint(&self) -> (usize, Option<usize>) {
@@ -103,6 +202,18 @@ impl Iterator for GeneratorsChain {
 	}
 }
 
+/// Returns the label of the generator chain with the given `tag` for the
+/// party with index `party`, keyed by `seed`.
+fn chain_label(seed: &[u8], tag: u8, party: usize) -> Vec<u8> {
+	use byteorder::{ByteOrder, LittleEndian};
+
+	let mut label = seed.to_vec();
+	let mut suffix = [tag, 0, 0, 0, 0];
+	LittleEndian::write_u32(&mut suffix[1..5], party as u32);
+	label.extend_from_slice(&suffix);
+	label
+}
+
 /// The `BulletproofGens` struct contains all the generators needed
 /// for aggregating up to `m` range proofs of up to `n` bits each.
 ///
@@ -119,6 +230,13 @@ impl Iterator for GeneratorsChain {
 /// different domain separation label, and proving and verification
 /// uses the first `n` elements of the arbitrary-length chain.
 ///
+/// The chains are keyed by a public seed, so that anyone can re-derive
+/// and [audit](BulletproofGens::audit) the generators.  The chain of
+/// \\(\mathbf G\\) generators for party \\(j\\) is the output of SHAKE256 on
+/// `"GeneratorsChain" || seed || "G" || LE32(j)`, and likewise with `"H"`
+/// for the \\(\mathbf H\\) generators.  [`BulletproofGens::new`] uses the
+/// built-in seed [`BulletproofGens::DEFAULT_SEED`].
+///
 /// This means that the aggregation size (number of
 /// parties) is orthogonal to the rangeproof size (number of bits),
 /// and allows using the same `BulletproofGens` object for different
@@ -140,9 +258,28 @@ pub struct BulletproofGens {
 	pub G_vec: Vec<Vec<RistrettoPoint>>,
 	/// Precomputed \\(\mathbf H\\) generators for each party.
 	pub H_vec: Vec<Vec<RistrettoPoint>>,
+	/// Seed keying the generator chains.
+	#[cfg_attr(feature = "serde", serde(default))]
+	seed: Vec<u8>,
+}
+
+/// The capacities of [`BulletproofGens`] needed for a proof.
+///
+/// Returned by [`BulletproofGens::required_capacity_for_range`] and
+/// [`BulletproofGens::required_capacity_for_r1cs`], and checked with
+/// [`BulletproofGens::fits`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct GensCapacity {
+	/// The minimum number of generators for each party.
+	pub gens_capacity: usize,
+	/// The minimum number of parties.
+	pub party_capacity: usize,
 }
 
 impl BulletproofGens {
+	/// The built-in seed used by [`BulletproofGens::new`].
+	pub const DEFAULT_SEED: &'static [u8] = b"";
+
 	/// Create a new `BulletproofGens` object.
 	///
 	/// # Inputs
@@ -156,16 +293,262 @@ impl BulletproofGens {
 	/// * `party_capacity` is the maximum number of parties that can produce an
 	///   aggregated proof.
 	pub fn new(gens_capacity: usize, party_capacity: usize) -> Self {
+		BulletproofGens::from_seed(BulletproofGens::DEFAULT_SEED, gens_capacity, party_capacity)
+	}
+
+	/// Create a new `BulletproofGens` object whose generator chains are
+	/// keyed by the public `seed`.
+	///
+	/// The inputs `gens_capacity` and `party_capacity` are as in
+	/// [`BulletproofGens::new`], which is equivalent to calling this
+	/// function with [`BulletproofGens::DEFAULT_SEED`].
+	pub fn from_seed(seed: &[u8], gens_capacity: usize, party_capacity: usize) -> Self {
 		let mut gens = BulletproofGens {
 			gens_capacity: 0,
 			party_capacity,
 			G_vec: (0..party_capacity).map(|_| Vec::new()).collect(),
 			H_vec: (0..party_capacity).map(|_| Vec::new()).collect(),
+			seed: seed.to_vec(),
 		};
 		gens.increase_capacity(gens_capacity);
 		gens
 	}
 
+	/// Create a new `BulletproofGens` object whose generator chains are
+	/// keyed by the public `seed`, taking the capacities first like
+	/// [`BulletproofGens::new`].
+	///
+	/// This is the same as [`BulletproofGens::from_seed`].
+	pub fn new_with_seed(gens_capacity: usize, party_capacity: usize, seed: &[u8]) -> Self {
+		BulletproofGens::from_seed(seed, gens_capacity, party_capacity)
+	}
+
+	/// Returns the capacities needed to prove and verify aggregated range
+	/// proofs of `m` values of `n` bits each.
+	///
+	/// Each value uses the generators of its own party, so the bit size
+	/// and the number of values are both padded to a power of two, rather
+	/// than their product.
+	pub fn required_capacity_for_range(n: usize, m: usize) -> GensCapacity {
+		GensCapacity {
+			gens_capacity: n.next_power_of_two(),
+			party_capacity: m.next_power_of_two(),
+		}
+	}
+
+	/// Returns the capacities needed to prove and verify a constraint
+	/// system proof with `num_multipliers` multipliers, including those
+	/// allocated in the randomized phase.
+	///
+	/// The multipliers are padded to a power of two, and only the
+	/// generators of the first party are used.
+	pub fn required_capacity_for_r1cs(num_multipliers: usize) -> GensCapacity {
+		GensCapacity {
+			gens_capacity: num_multipliers.next_power_of_two(),
+			party_capacity: 1,
+		}
+	}
+
+	/// Returns `true` if the generators have at least the given
+	/// `capacity`.
+	pub fn fits(&self, capacity: GensCapacity) -> bool {
+		self.gens_capacity >= capacity.gens_capacity && self.party_capacity >= capacity.party_capacity
+	}
+
+	/// Returns an identifier of the seed keying the generator chains.
+	///
+	/// The identifier is the first 32 bytes of the SHAKE256 output on
+	/// `"GeneratorsSeed" || seed`.  Applications can compare it to the
+	/// identifier of their expected seed, e.g. after loading generators
+	/// with [`BulletproofGens::from_bytes`], since proofs made with
+	/// generators derived from one seed do not verify with generators
+	/// derived from another.
+	pub fn seed_id(&self) -> [u8; 32] {
+		let mut shake = Shake256::default();
+		shake.update(b"GeneratorsSeed");
+		shake.update(&self.seed);
+
+		let mut id = [0u8; 32];
+		shake.finalize_xof_dirty().read(&mut id);
+		id
+	}
+
+	/// Checks that the generators are those derived from `seed`.
+	///
+	/// Deriving a generator is dominated by the hash-to-group map, so by
+	/// default only a sample of the generators is re-derived and compared:
+	/// the first and last ones of each vector, and every 16th in between.
+	/// If `full` is set, all of the generators are checked.
+	///
+	/// Returns [`GeneratorError::AuditFailed`] if the vector sizes do not
+	/// match the capacities, or if a checked generator differs.
+	pub fn audit(&self, seed: &[u8], full: bool) -> Result<(), GeneratorError> {
+		if self.G_vec.len() != self.party_capacity || self.H_vec.len() != self.party_capacity {
+			return Err(GeneratorError::AuditFailed);
+		}
+
+		let n = self.gens_capacity;
+		let is_sampled = |i: usize| full || i % 16 == 0 || i + 1 == n;
+		for i in 0..self.party_capacity {
+			for (tag, gens) in [(b'G', &self.G_vec[i]), (b'H', &self.H_vec[i])].iter() {
+				if gens.len() != n {
+					return Err(GeneratorError::AuditFailed);
+				}
+				let mut chain = GeneratorsChain::new(&chain_label(seed, *tag, i));
+				for (j, gen) in gens.iter().enumerate() {
+					if !is_sampled(j) {
+						chain.skip_one();
+					} else if chain.next() != Some(*gen) {
+						return Err(GeneratorError::AuditFailed);
+					}
+				}
+			}
+		}
+		Ok(())
+	}
+
+	/// Checks that `samples` generators drawn at random are those derived
+	/// from `seed`.
+	///
+	/// This is a convenience wrapper around
+	/// [`BulletproofGens::audit_random_with_rng`], passing in a threadsafe
+	/// RNG.
+	#[cfg(feature = "std")]
+	pub fn audit_random(&self, seed: &[u8], samples: usize) -> Result<(), GeneratorError> {
+		self.audit_random_with_rng(seed, samples, &mut thread_rng())
+	}
+
+	/// Checks that `samples` generators drawn at random using `rng` are
+	/// those derived from `seed`.
+	///
+	/// Unlike the sampled [`BulletproofGens::audit`], the checked positions
+	/// cannot be predicted, so this is suited to detect tampering with
+	/// generators loaded with [`BulletproofGens::from_bytes`]: a tampered
+	/// fraction \\(f\\) of the generators goes unnoticed with probability
+	/// \\((1 - f)^{samples}\\).
+	///
+	/// Returns [`GeneratorError::AuditFailed`] if the vector sizes do not
+	/// match the capacities, or if a checked generator differs.
+	pub fn audit_random_with_rng<T: RngCore + CryptoRng>(
+		&self,
+		seed: &[u8],
+		samples: usize,
+		rng: &mut T,
+	) -> Result<(), GeneratorError> {
+		if self.G_vec.len() != self.party_capacity || self.H_vec.len() != self.party_capacity {
+			return Err(GeneratorError::AuditFailed);
+		}
+		let n = self.gens_capacity;
+		if self.G_vec.iter().chain(self.H_vec.iter()).any(|gens| gens.len() != n) {
+			return Err(GeneratorError::AuditFailed);
+		}
+		if n == 0 || self.party_capacity == 0 {
+			return Ok(());
+		}
+
+		for _ in 0..samples {
+			let party = (rng.next_u64() % self.party_capacity as u64) as usize;
+			let index = (rng.next_u64() % n as u64) as usize;
+			let (tag, gens) = if rng.next_u32() & 1 == 0 {
+				(b'G', &self.G_vec[party])
+			} else {
+				(b'H', &self.H_vec[party])
+			};
+			let mut chain = GeneratorsChain::new(&chain_label(seed, tag, party)).fast_forward(index);
+			if chain.next() != Some(gens[index]) {
+				return Err(GeneratorError::AuditFailed);
+			}
+		}
+		Ok(())
+	}
+
+	/// Serializes the generators as a byte array.
+	///
+	/// The layout is
+	///
+	/// * `gens_capacity`, `party_capacity` and the length of the seed, as
+	///   little-endian 32-bit integers;
+	/// * the seed;
+	/// * for each party, its `gens_capacity` \\(\mathbf G\\) generators
+	///   followed by its `gens_capacity` \\(\mathbf H\\) generators, as
+	///   compressed points of 32 bytes.
+	///
+	/// Loading the generators with [`BulletproofGens::from_bytes`] is much
+	/// faster than deriving them again.
+	pub fn to_bytes(&self) -> Vec<u8> {
+		use byteorder::{ByteOrder, LittleEndian};
+
+		let mut buf = Vec::with_capacity(12 + self.seed.len() + 64 * self.gens_capacity * self.party_capacity);
+		let mut header = [0u8; 12];
+		LittleEndian::write_u32(&mut header[0..4], self.gens_capacity as u32);
+		LittleEndian::write_u32(&mut header[4..8], self.party_capacity as u32);
+		LittleEndian::write_u32(&mut header[8..12], self.seed.len() as u32);
+		buf.extend_from_slice(&header);
+		buf.extend_from_slice(&self.seed);
+		for (G, H) in self.G_vec.iter().zip(self.H_vec.iter()) {
+			for point in G.iter().chain(H.iter()) {
+				buf.extend_from_slice(point.compress().as_bytes());
+			}
+		}
+		buf
+	}
+
+	/// Deserializes the generators from a byte slice.
+	///
+	/// Returns [`GeneratorError::FormatError`] if the length of the slice
+	/// does not match the capacities in its header, or if a generator is
+	/// not a valid point or is the identity.
+	///
+	/// # Note
+	///
+	/// Decoding only checks that the generators are well-formed.  Use
+	/// [`BulletproofGens::audit`] or [`BulletproofGens::audit_random`] to
+	/// check that they were derived from the expected seed.
+	pub fn from_bytes(slice: &[u8]) -> Result<BulletproofGens, GeneratorError> {
+		use crate::util::read32;
+		use byteorder::{ByteOrder, LittleEndian};
+
+		if slice.len() < 12 {
+			return Err(GeneratorError::FormatError);
+		}
+		let gens_capacity = LittleEndian::read_u32(&slice[0..4]) as usize;
+		let party_capacity = LittleEndian::read_u32(&slice[4..8]) as usize;
+		let seed_len = LittleEndian::read_u32(&slice[8..12]) as usize;
+
+		// Check the length before allocating anything for the points.
+		let expected_len = gens_capacity
+			.checked_mul(party_capacity)
+			.and_then(|points| points.checked_mul(64))
+			.and_then(|len| len.checked_add(12))
+			.and_then(|len| len.checked_add(seed_len))
+			.ok_or(GeneratorError::FormatError)?;
+		if slice.len() != expected_len {
+			return Err(GeneratorError::FormatError);
+		}
+		let seed = slice[12..12 + seed_len].to_vec();
+
+		let mut points = slice[12 + seed_len..].chunks(32).map(|chunk| {
+			CompressedRistretto(read32(chunk))
+				.decompress()
+				.filter(|point| !point.is_identity())
+				.ok_or(GeneratorError::FormatError)
+		});
+		let mut G_vec = Vec::with_capacity(party_capacity);
+		let mut H_vec = Vec::with_capacity(party_capacity);
+		for _ in 0..party_capacity {
+			G_vec.push(points.by_ref().take(gens_capacity).collect::<Result<Vec<_>, _>>()?);
+			H_vec.push(points.by_ref().take(gens_capacity).collect::<Result<Vec<_>, _>>()?);
+		}
+
+		Ok(BulletproofGens {
+			gens_capacity,
+			party_capacity,
+			G_vec,
+			H_vec,
+			seed,
+		})
+	}
+
 	/// Returns j-th share of generators, with an appropriate
 	/// slice of vectors G and H for the j-th range proof.
 	pub fn share(&self, j: usize) -> BulletproofGensShare<'_> {
@@ -174,29 +557,22 @@ impl BulletproofGens {
 
 	/// Increases the generators' capacity to the amount specified.
 	/// If less than or equal to the current capacity, does nothing.
+	///
+	/// With the `rayon` feature, the new generators are computed on the
+	/// `rayon` thread pool.  They are the same as without the feature.
 	pub fn increase_capacity(&mut self, new_capacity: usize) {
-		use byteorder::{ByteOrder, LittleEndian};
-
 		if self.gens_capacity >= new_capacity {
 			return;
 		}
 
 		for i in 0..self.party_capacity {
-			let party_index = i as u32;
-			let mut label = [b'G', 0, 0, 0, 0];
-			LittleEndian::write_u32(&mut label[1..5], party_index);
-			self.G_vec[i].extend(
-				&mut GeneratorsChain::new(&label)
-					.fast_forward(self.gens_capacity)
-					.take(new_capacity - self.gens_capacity),
-			);
-
-			label[0] = b'H';
-			self.H_vec[i].extend(
-				&mut GeneratorsChain::new(&label)
-					.fast_forward(self.gens_capacity)
-					.take(new_capacity - self.gens_capacity),
-			);
+			GeneratorsChain::new(&chain_label(&self.seed, b'G', i))
+				.fast_forward(self.gens_capacity)
+				.append_to(&mut self.G_vec[i], new_capacity - self.gens_capacity);
+
+			GeneratorsChain::new(&chain_label(&self.seed, b'H', i))
+				.fast_forward(self.gens_capacity)
+				.append_to(&mut self.H_vec[i], new_capacity - self.gens_capacity);
 		}
 		self.gens_capacity = new_capacity;
 	}
@@ -275,21 +651,337 @@ pub struct BulletproofGensShare<'a> {
 }
 
 impl<'a> BulletproofGensShare<'a> {
+	/// Returns the [seed identifier](BulletproofGens::seed_id) of the
+	/// generators this share is a view into.
+	pub fn seed_id(&self) -> [u8; 32] {
+		self.gens.seed_id()
+	}
+
 	/// Return an iterator over this party's G generators with given size `n`.
-	pub(crate) fn G(&self, n: usize) -> impl Iterator<Item = &'a RistrettoPoint> {
+	///
+	/// The iterator stops early if `n` exceeds the `gens_capacity`.
+	pub fn G(&self, n: usize) -> impl Iterator<Item = &'a RistrettoPoint> {
 		self.gens.G_vec[self.share].iter().take(n)
 	}
 
 	/// Return an iterator over this party's H generators with given size `n`.
-	pub(crate) fn H(&self, n: usize) -> impl Iterator<Item = &'a RistrettoPoint> {
+	///
+	/// The iterator stops early if `n` exceeds the `gens_capacity`.
+	pub fn H(&self, n: usize) -> impl Iterator<Item = &'a RistrettoPoint> {
 		self.gens.H_vec[self.share].iter().take(n)
 	}
+
+	/// Return this party's first `n` G generators as a slice.
+	///
+	/// # Panics
+	///
+	/// Panics if `n` exceeds the `gens_capacity`.
+	pub fn G_slice(&self, n: usize) -> &'a [RistrettoPoint] {
+		self.slice(&self.gens.G_vec, n)
+	}
+
+	/// Return this party's first `n` H generators as a slice.
+	///
+	/// # Panics
+	///
+	/// Panics if `n` exceeds the `gens_capacity`.
+	pub fn H_slice(&self, n: usize) -> &'a [RistrettoPoint] {
+		self.slice(&self.gens.H_vec, n)
+	}
+
+	fn slice(&self, vec: &'a [Vec<RistrettoPoint>], n: usize) -> &'a [RistrettoPoint] {
+		assert!(
+			n <= self.gens.gens_capacity,
+			"requested {} generators, but the gens_capacity is {}",
+			n,
+			self.gens.gens_capacity
+		);
+		&vec[self.share][..n]
+	}
 }
 
 #[cfg(test)]
 mod tests {
 	use super::*;
 
+	#[test]
+	fn pedersen_gens_from_points() {
+		use curve25519_dalek::traits::Identity;
+
+		let default = PedersenGens::default();
+		let gens = PedersenGens::from_points(default.B, default.B_blinding).unwrap();
+		assert_eq!(gens.B, default.B);
+		assert_eq!(gens.B_blinding, default.B_blinding);
+
+		assert_eq!(
+			PedersenGens::from_points(RistrettoPoint::identity(), default.B_blinding).err(),
+			Some(GeneratorError::IdentityPoint)
+		);
+		assert_eq!(
+			PedersenGens::from_points(default.B, RistrettoPoint::identity()).err(),
+			Some(GeneratorError::IdentityPoint)
+		);
+		assert_eq!(
+			PedersenGens::from_points(default.B, default.B).err(),
+			Some(GeneratorError::EqualPoints)
+		);
+	}
+
+	#[test]
+	fn pedersen_gens_domain_separation() {
+		let default = PedersenGens::default();
+		let asset_a = PedersenGens::new(b"asset A");
+		let asset_b = PedersenGens::new(b"asset B");
+
+		// Deterministic, and accepted as non-degenerate bases.
+		assert_eq!(PedersenGens::new(b"asset A").B, asset_a.B);
+		assert_eq!(PedersenGens::new(b"asset A").B_blinding, asset_a.B_blinding);
+		assert!(PedersenGens::from_points(asset_a.B, asset_a.B_blinding).is_ok());
+
+		let bases = [
+			default.B,
+			default.B_blinding,
+			asset_a.B,
+			asset_a.B_blinding,
+			asset_b.B,
+			asset_b.B_blinding,
+			PedersenGens::new(b"").B,
+		];
+		for (i, P) in bases.iter().enumerate() {
+			for Q in bases.iter().skip(i + 1) {
+				assert_ne!(P, Q);
+			}
+		}
+
+		let value = Scalar::from(42u64);
+		let blinding = Scalar::from(7u64);
+		assert_ne!(asset_a.commit(value, blinding), asset_b.commit(value, blinding));
+	}
+
+	#[test]
+	fn default_seed_known_answers() {
+		let gens = BulletproofGens::new(4, 2);
+		assert_eq!(
+			hex::encode(gens.G_vec[0][0].compress().as_bytes()),
+			"fc3b25801422672a6a8d3adb5d8457d4301fe92324b4fc56ae934c8713ddfe2d"
+		);
+		assert_eq!(
+			hex::encode(gens.H_vec[1][3].compress().as_bytes()),
+			"54bd495763395ff96e7d55836712b330b3357096ee72867c089e7c02bf061f78"
+		);
+
+		let seeded = BulletproofGens::from_seed(BulletproofGens::DEFAULT_SEED, 4, 2);
+		assert_eq!(gens.G_vec, seeded.G_vec);
+		assert_eq!(gens.H_vec, seeded.H_vec);
+	}
+
+	#[test]
+	fn large_gens_known_answers() {
+		// Spans several chunks of the parallel computation.
+		let n = 5_000;
+		let gens = BulletproofGens::new(n, 2);
+		for (tag, vec) in [(b'G', &gens.G_vec), (b'H', &gens.H_vec)].iter() {
+			for (i, party_gens) in vec.iter().enumerate() {
+				let chain = GeneratorsChain::new(&chain_label(BulletproofGens::DEFAULT_SEED, *tag, i));
+				assert!(chain.take(n).eq(party_gens.iter().cloned()));
+			}
+		}
+
+		let hex = |point: &RistrettoPoint| hex::encode(point.compress().as_bytes());
+		assert_eq!(
+			hex(&gens.G_vec[0][0]),
+			"fc3b25801422672a6a8d3adb5d8457d4301fe92324b4fc56ae934c8713ddfe2d"
+		);
+		assert_eq!(
+			hex(&gens.G_vec[0][n - 1]),
+			"ce9909e99d45070e446af144b081b746030421660fce7e3b571fe13dce0a561f"
+		);
+		assert_eq!(
+			hex(&gens.H_vec[1][0]),
+			"c4d0c6aa6c07db20798b35906c8a8940fa8a1e2f6bf699ee13aaf3eb1f636d24"
+		);
+		assert_eq!(
+			hex(&gens.H_vec[1][n - 1]),
+			"6a207f3d46d8b8eb6a480ebfdcfd31fef69d8eb6f152b9862fcd9068ea7e087f"
+		);
+	}
+
+	#[test]
+	fn required_capacities() {
+		assert_eq!(BulletproofGens::required_capacity_for_range(64, 1), GensCapacity {
+			gens_capacity: 64,
+			party_capacity: 1
+		});
+		assert_eq!(BulletproofGens::required_capacity_for_range(10, 5), GensCapacity {
+			gens_capacity: 16,
+			party_capacity: 8
+		});
+		assert_eq!(BulletproofGens::required_capacity_for_r1cs(0), GensCapacity {
+			gens_capacity: 1,
+			party_capacity: 1
+		});
+		assert_eq!(BulletproofGens::required_capacity_for_r1cs(129), GensCapacity {
+			gens_capacity: 256,
+			party_capacity: 1
+		});
+
+		let gens = BulletproofGens::new(16, 8);
+		assert!(gens.fits(BulletproofGens::required_capacity_for_range(10, 5)));
+		assert!(gens.fits(BulletproofGens::required_capacity_for_r1cs(16)));
+		assert!(!gens.fits(BulletproofGens::required_capacity_for_range(32, 1)));
+		assert!(!gens.fits(BulletproofGens::required_capacity_for_range(8, 9)));
+		assert!(!gens.fits(BulletproofGens::required_capacity_for_r1cs(17)));
+	}
+
+	#[test]
+	fn seed_ids() {
+		let default = BulletproofGens::new(4, 1);
+		let seeded = BulletproofGens::from_seed(b"consortium", 4, 1);
+		assert_eq!(
+			BulletproofGens::new_with_seed(4, 1, b"consortium").seed_id(),
+			seeded.seed_id()
+		);
+		assert_eq!(
+			default.seed_id(),
+			BulletproofGens::from_seed(BulletproofGens::DEFAULT_SEED, 16, 2).seed_id()
+		);
+		assert_eq!(
+			seeded.seed_id(),
+			BulletproofGens::from_seed(b"consortium", 8, 1).seed_id()
+		);
+		assert_ne!(default.seed_id(), seeded.seed_id());
+		assert_eq!(seeded.share(0).seed_id(), seeded.seed_id());
+
+		// The identifier survives serialization and resizing.
+		let mut decoded = BulletproofGens::from_bytes(&seeded.to_bytes()).unwrap();
+		decoded.increase_capacity(8);
+		assert_eq!(decoded.seed_id(), seeded.seed_id());
+	}
+
+	#[test]
+	fn seeded_gens_audit() {
+		let mut gens = BulletproofGens::from_seed(b"protocol spec seed", 40, 2);
+		assert!(gens.audit(b"protocol spec seed", false).is_ok());
+		assert!(gens.audit(b"protocol spec seed", true).is_ok());
+		assert_eq!(gens.audit(b"other seed", false), Err(GeneratorError::AuditFailed));
+		assert!(BulletproofGens::new(40, 2)
+			.audit(BulletproofGens::DEFAULT_SEED, true)
+			.is_ok());
+
+		// Resizing keeps the seed.
+		gens.increase_capacity(64);
+		assert!(gens.audit(b"protocol spec seed", true).is_ok());
+
+		// A replaced generator is caught by the full audit, and by the
+		// sampled audit if it is sampled.
+		gens.H_vec[1][5] = gens.G_vec[0][0];
+		assert!(gens.audit(b"protocol spec seed", false).is_ok());
+		assert_eq!(
+			gens.audit(b"protocol spec seed", true),
+			Err(GeneratorError::AuditFailed)
+		);
+		gens.H_vec[1][16] = gens.G_vec[0][0];
+		assert_eq!(
+			gens.audit(b"protocol spec seed", false),
+			Err(GeneratorError::AuditFailed)
+		);
+	}
+
+	#[test]
+	fn bulletproof_gens_bytes_roundtrip() {
+		let gens = BulletproofGens::from_seed(b"protocol spec seed", 32, 3);
+		let bytes = gens.to_bytes();
+		assert_eq!(bytes.len(), 12 + 18 + 64 * 32 * 3);
+
+		let decoded = BulletproofGens::from_bytes(&bytes).unwrap();
+		assert_eq!(decoded.gens_capacity, 32);
+		assert_eq!(decoded.party_capacity, 3);
+		assert_eq!(decoded.G_vec, gens.G_vec);
+		assert_eq!(decoded.H_vec, gens.H_vec);
+		assert!(decoded.audit(b"protocol spec seed", true).is_ok());
+
+		// The seed is kept, so that the decoded generators can be extended.
+		let mut decoded = decoded;
+		decoded.increase_capacity(64);
+		assert_eq!(
+			decoded.G_vec,
+			BulletproofGens::from_seed(b"protocol spec seed", 64, 3).G_vec
+		);
+
+		let empty = BulletproofGens::new(0, 0);
+		let decoded = BulletproofGens::from_bytes(&empty.to_bytes()).unwrap();
+		assert_eq!(decoded.gens_capacity, 0);
+		assert_eq!(decoded.party_capacity, 0);
+	}
+
+	#[test]
+	fn corrupted_bulletproof_gens_bytes_are_rejected() {
+		use rand_chacha::{rand_core::SeedableRng, ChaChaRng};
+
+		let gens = BulletproofGens::new(16, 2);
+		let bytes = gens.to_bytes();
+
+		// Truncated or extended data.
+		assert_eq!(
+			BulletproofGens::from_bytes(&bytes[..bytes.len() - 1]).err(),
+			Some(GeneratorError::FormatError)
+		);
+		assert_eq!(
+			BulletproofGens::from_bytes(&bytes[..8]).err(),
+			Some(GeneratorError::FormatError)
+		);
+		let mut extended = bytes.clone();
+		extended.push(0);
+		assert_eq!(
+			BulletproofGens::from_bytes(&extended).err(),
+			Some(GeneratorError::FormatError)
+		);
+
+		// Capacities which do not match the data.
+		let mut wrong_capacity = bytes.clone();
+		wrong_capacity[0] = 8;
+		assert_eq!(
+			BulletproofGens::from_bytes(&wrong_capacity).err(),
+			Some(GeneratorError::FormatError)
+		);
+		let mut huge_capacity = bytes.clone();
+		huge_capacity[0..8].copy_from_slice(&[0xff; 8]);
+		assert_eq!(
+			BulletproofGens::from_bytes(&huge_capacity).err(),
+			Some(GeneratorError::FormatError)
+		);
+
+		// A point which does not decompress, and the identity.
+		let mut invalid_point = bytes.clone();
+		invalid_point[12 + 5 * 32..12 + 6 * 32].copy_from_slice(&[0xff; 32]);
+		assert_eq!(
+			BulletproofGens::from_bytes(&invalid_point).err(),
+			Some(GeneratorError::FormatError)
+		);
+		let mut identity = bytes.clone();
+		identity[bytes.len() - 32..].copy_from_slice(&[0; 32]);
+		assert_eq!(
+			BulletproofGens::from_bytes(&identity).err(),
+			Some(GeneratorError::FormatError)
+		);
+
+		// Valid points in the wrong places are only caught by an audit.
+		let mut tampered = BulletproofGens::from_bytes(&bytes).unwrap();
+		tampered.G_vec[1].rotate_left(1);
+		let mut rng = ChaChaRng::from_seed([7u8; 32]);
+		assert!(gens
+			.audit_random_with_rng(BulletproofGens::DEFAULT_SEED, 64, &mut rng)
+			.is_ok());
+		assert_eq!(
+			tampered.audit_random_with_rng(BulletproofGens::DEFAULT_SEED, 64, &mut rng),
+			Err(GeneratorError::AuditFailed)
+		);
+		assert_eq!(
+			tampered.audit(BulletproofGens::DEFAULT_SEED, false),
+			Err(GeneratorError::AuditFailed)
+		);
+	}
+
 	#[test]
 	fn aggregated_gens_iter_matches_flat_map() {
 		let gens = BulletproofGens::new(64, 8);
@@ -331,6 +1023,26 @@ mod tests {
 		helper(16, 1);
 	}
 
+	#[test]
+	fn share_slices_match_iterators() {
+		let mut gens = BulletproofGens::new(32, 3);
+		gens.increase_capacity(64);
+
+		for j in 0..3 {
+			let share = gens.share(j);
+			for &n in [0, 1, 17, 32, 64].iter() {
+				assert!(share.G_slice(n).iter().eq(share.G(n)));
+				assert!(share.H_slice(n).iter().eq(share.H(n)));
+			}
+		}
+	}
+
+	#[test]
+	#[should_panic(expected = "requested 65 generators, but the gens_capacity is 64")]
+	fn share_slice_beyond_capacity_panics() {
+		BulletproofGens::new(64, 1).share(0).G_slice(65);
+	}
+
 	#[test]
 	fn resizing_small_gens_matches_creating_bigger_gens() {
 		let gens = BulletproofGens::new(64, 8);
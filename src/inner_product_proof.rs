@@ -13,9 +13,99 @@ use curve25519_dalek::{
 };
 use merlin::Transcript;
 
-use crate::{errors::ProofError, transcript::TranscriptProtocol};
+#[cfg(feature = "serde")]
+use serde::de::Visitor;
+#[cfg(feature = "serde")]
+use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+	errors::ProofError,
+	progress::{Milestone, Progress},
+	transcript::TranscriptProtocol,
+	util,
+};
 
-#[derive(Clone, Debug)]
+/// An inner-product proof, as used inside range proofs and R1CS proofs.
+///
+/// The proof can also be used on its own, to show that a point
+/// \\(P = \langle \mathbf a, \mathbf G \rangle + \langle \mathbf b, \mathbf H
+/// \rangle + c Q\\) commits to vectors \\(\mathbf a, \mathbf b\\) whose
+/// inner product is \\(c\\).  The argument is not zero-knowledge: it
+/// reveals information about the vectors, and it is only sound if no
+/// discrete logarithm relation between \\(Q\\) and the generators is known.
+///
+/// The length of the vectors must be a power of two.  Other lengths can
+/// be padded with zeros, which does not change the inner product.
+///
+/// # Example
+/// ```
+/// extern crate curve25519_dalek;
+/// use curve25519_dalek::{
+///     ristretto::RistrettoPoint, scalar::Scalar, traits::VartimeMultiscalarMul,
+/// };
+///
+/// extern crate merlin;
+/// use merlin::Transcript;
+///
+/// extern crate webb_bulletproofs;
+/// use webb_bulletproofs::{inner_product, BulletproofGens, InnerProductProof, PedersenGens};
+///
+/// use std::iter;
+///
+/// # #[allow(non_snake_case)]
+/// # fn main() {
+/// fn prove_and_verify(a: &[u64], b: &[u64]) {
+///     // Pad the vectors with zeros to a power of two.
+///     let n = a.len().next_power_of_two();
+///     let pad = |v: &[u64]| -> Vec<Scalar> {
+///         v.iter()
+///             .map(|&x| Scalar::from(x))
+///             .chain(iter::repeat(Scalar::zero()))
+///             .take(n)
+///             .collect()
+///     };
+///     let (a, b) = (pad(a), pad(b));
+///     let c = inner_product(&a, &b);
+///
+///     // Generators with no known discrete logarithm relation.
+///     let bp_gens = BulletproofGens::new(n, 1);
+///     let G = bp_gens.share(0).G_slice(n).to_vec();
+///     let H = bp_gens.share(0).H_slice(n).to_vec();
+///     let Q = PedersenGens::default().B_blinding;
+///     let ones = vec![Scalar::one(); n];
+///
+///     // The commitment to the vectors and to their inner product.
+///     let P = RistrettoPoint::vartime_multiscalar_mul(
+///         a.iter().chain(b.iter()).chain(iter::once(&c)),
+///         G.iter().chain(H.iter()).chain(iter::once(&Q)),
+///     );
+///
+///     let proof = InnerProductProof::create(
+///         &mut Transcript::new(b"doctest example"),
+///         &Q,
+///         &ones,
+///         &ones,
+///         G.clone(),
+///         H.clone(),
+///         a,
+///         b,
+///     );
+///
+///     let verify = |P: &RistrettoPoint| {
+///         let mut transcript = Transcript::new(b"doctest example");
+///         proof.verify(n, &mut transcript, &ones, &ones, P, &Q, &G, &H)
+///     };
+///     assert!(verify(&P).is_ok());
+///     // A commitment to a different inner product does not verify.
+///     assert!(verify(&(P + Q)).is_err());
+/// }
+///
+/// prove_and_verify(&[3], &[5]);
+/// prove_and_verify(&[1, 2], &[3, 4]);
+/// prove_and_verify(&[1, 2, 3], &[4, 5, 6]);
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct InnerProductProof {
 	pub(crate) L_vec: Vec<CompressedRistretto>,
 	pub(crate) R_vec: Vec<CompressedRistretto>,
@@ -24,6 +114,13 @@ pub struct InnerProductProof {
 }
 
 impl InnerProductProof {
+	/// The maximum number of rounds of a proof, which is \\(\lg n\\) for
+	/// vectors of length \\(n\\).
+	///
+	/// Longer proofs are rejected when parsing and verifying, which bounds
+	/// the memory allocated for untrusted input.
+	pub const MAX_ROUNDS: usize = 31;
+
 	/// Create an inner-product proof.
 	///
 	/// The proof is created with respect to the bases \\(G\\), \\(H'\\),
@@ -36,6 +133,35 @@ impl InnerProductProof {
 	/// The lengths of the vectors must all be the same, and must all be
 	/// either 0 or a power of 2.
 	pub fn create(
+		transcript: &mut Transcript,
+		Q: &RistrettoPoint,
+		G_factors: &[Scalar],
+		H_factors: &[Scalar],
+		G_vec: Vec<RistrettoPoint>,
+		H_vec: Vec<RistrettoPoint>,
+		a_vec: Vec<Scalar>,
+		b_vec: Vec<Scalar>,
+	) -> InnerProductProof {
+		InnerProductProof::create_with_progress(
+			transcript,
+			Q,
+			G_factors,
+			H_factors,
+			G_vec,
+			H_vec,
+			a_vec,
+			b_vec,
+			Progress::default(),
+		)
+		.expect("proof creation without a cancellation token is never cancelled")
+	}
+
+	/// Create an inner-product proof like [`InnerProductProof::create`],
+	/// reporting each round to `progress`.
+	///
+	/// Returns `None` if cancellation was requested, after clearing the
+	/// witness vectors.
+	pub(crate) fn create_with_progress(
 		transcript: &mut Transcript,
 		Q: &RistrettoPoint,
 		G_factors: &[Scalar],
@@ -44,7 +170,8 @@ impl InnerProductProof {
 		mut H_vec: Vec<RistrettoPoint>,
 		mut a_vec: Vec<Scalar>,
 		mut b_vec: Vec<Scalar>,
-	) -> InnerProductProof {
+		progress: Progress<'_>,
+	) -> Option<InnerProductProof> {
 		// Create slices G, H, a, b backed by their respective
 		// vectors.  This lets us reslice as we compress the lengths
 		// of the vectors in the main loop below.
@@ -55,6 +182,8 @@ impl InnerProductProof {
 
 		let mut n = G.len();
 
+		enter_span!("ipp_create", n = n);
+
 		// All of the input vectors must have the same length.
 		assert_eq!(G.len(), n);
 		assert_eq!(H.len(), n);
@@ -70,6 +199,7 @@ impl InnerProductProof {
 		let lg_n = n.next_power_of_two().trailing_zeros() as usize;
 		let mut L_vec = Vec::with_capacity(lg_n);
 		let mut R_vec = Vec::with_capacity(lg_n);
+		let mut cancelled = false;
 
 		// If it's the first iteration, unroll the Hprime = H*y_inv scalar mults
 		// into multiscalar muls, for performance.
@@ -129,9 +259,14 @@ impl InnerProductProof {
 			b = b_L;
 			G = G_L;
 			H = H_L;
+
+			cancelled = progress.cancelled_at(Milestone::InnerProductRound {
+				round: L_vec.len() - 1,
+				rounds: lg_n,
+			});
 		}
 
-		while n != 1 {
+		while n != 1 && !cancelled {
 			n = n / 2;
 			let (a_L, a_R) = a.split_at_mut(n);
 			let (b_L, b_R) = b.split_at_mut(n);
@@ -173,14 +308,22 @@ impl InnerProductProof {
 			b = b_L;
 			G = G_L;
 			H = H_L;
+
+			cancelled = progress.cancelled_at(Milestone::InnerProductRound {
+				round: L_vec.len() - 1,
+				rounds: lg_n,
+			});
 		}
 
-		InnerProductProof {
-			L_vec,
-			R_vec,
-			a: a[0],
-			b: b[0],
+		// The folded scalars are part of the proof, while the rest of the
+		// vectors is derived from the witness.
+		let (a, b) = (a[0], b[0]);
+		util::clear_scalars(&mut [&mut a_vec, &mut b_vec]);
+		if cancelled {
+			return None;
 		}
+
+		Some(InnerProductProof { L_vec, R_vec, a, b })
 	}
 
 	/// Computes three vectors of verification scalars \\([u\_{i}^{2}]\\),
@@ -189,19 +332,23 @@ impl InnerProductProof {
 	/// notes](index.html#verification-equation) for details. The verifier must
 	/// provide the input length \\(n\\) explicitly to avoid unbounded
 	/// allocation within the inner product proof.
-	pub(crate) fn verification_scalars(
+	///
+	/// Returns [`ProofError::FormatError`] if the number of rounds of the
+	/// proof does not match \\(n\\), and [`ProofError::VerificationError`]
+	/// if one of its points is invalid.
+	pub fn verification_scalars(
 		&self,
 		n: usize,
 		transcript: &mut Transcript,
 	) -> Result<(Vec<Scalar>, Vec<Scalar>, Vec<Scalar>), ProofError> {
 		let lg_n = self.L_vec.len();
-		if lg_n >= 32 {
+		if lg_n > InnerProductProof::MAX_ROUNDS || self.R_vec.len() != lg_n {
 			// 4 billion multiplications should be enough for anyone
 			// and this check prevents overflow in 1<<lg_n below.
-			return Err(ProofError::VerificationError);
+			return Err(ProofError::FormatError);
 		}
 		if n != (1 << lg_n) {
-			return Err(ProofError::VerificationError);
+			return Err(ProofError::FormatError);
 		}
 
 		transcript.innerproduct_domain_sep(n as u64);
@@ -246,11 +393,24 @@ impl InnerProductProof {
 		Ok((challenges_sq, challenges_inv_sq, s))
 	}
 
-	/// This method is for testing that proof generation work,
-	/// but for efficiency the actual protocols would use `verification_scalars`
-	/// method to combine inner product verification with other checks
-	/// in a single multiscalar multiplication.
-	#[allow(dead_code)]
+	/// Verifies the proof that the point \\(P\\) is
+	/// \\[
+	///   P = \langle \mathbf a, \mathbf G' \rangle + \langle \mathbf b,
+	///   \mathbf H' \rangle + \langle \mathbf a, \mathbf b \rangle Q
+	/// \\]
+	/// for vectors \\(\mathbf a, \mathbf b\\) of length `n`, where
+	/// \\(G'\_i = G\_i \cdot \texttt{G\\_factors}\_i\\) and
+	/// \\(H'\_i = H\_i \cdot \texttt{H\\_factors}\_i\\).
+	///
+	/// The claimed inner product \\(c\\) is part of the commitment \\(P\\)
+	/// supplied by the caller, as \\(c Q\\).  The `transcript` must be in
+	/// the same state as the transcript passed to
+	/// [`InnerProductProof::create`].
+	///
+	/// Protocols which check other equations at the same time can instead
+	/// combine the scalars from
+	/// [`InnerProductProof::verification_scalars`] into a single
+	/// multiscalar multiplication.
 	pub fn verify<IG, IH>(
 		&self,
 		n: usize,
@@ -268,6 +428,9 @@ impl InnerProductProof {
 		IH: IntoIterator,
 		IH::Item: Borrow<Scalar>,
 	{
+		if G.len() != n || H.len() != n {
+			return Err(ProofError::VerificationError);
+		}
 		let (u_sq, u_inv_sq, s) = self.verification_scalars(n, transcript)?;
 
 		let g_times_a_times_s = G_factors
@@ -319,6 +482,29 @@ impl InnerProductProof {
 		}
 	}
 
+	/// Returns the number of rounds \\(\lg n\\) of the proof, for vectors
+	/// of length \\(n\\) padded to a power of two.
+	pub fn rounds(&self) -> usize {
+		self.L_vec.len()
+	}
+
+	/// Returns the points \\(L_0, \dots, L_{\lg n - 1}\\) sent in each
+	/// round of the proof.
+	pub fn L_vec(&self) -> &[CompressedRistretto] {
+		&self.L_vec
+	}
+
+	/// Returns the points \\(R_0, \dots, R_{\lg n - 1}\\) sent in each
+	/// round of the proof.
+	pub fn R_vec(&self) -> &[CompressedRistretto] {
+		&self.R_vec
+	}
+
+	/// Returns the final scalars \\(a, b\\) of the proof.
+	pub fn scalars(&self) -> (Scalar, Scalar) {
+		(self.a, self.b)
+	}
+
 	/// Returns the size in bytes required to serialize the inner
 	/// product proof.
 	///
@@ -328,6 +514,20 @@ impl InnerProductProof {
 		(self.L_vec.len() * 2 + 2) * 32
 	}
 
+	/// Returns the size in bytes of a proof for vectors of length `n`,
+	/// padded to a power of two.
+	#[cfg(feature = "yoloproofs")]
+	pub(crate) fn serialized_size_for(n: usize) -> usize {
+		let lg_n = n.next_power_of_two().trailing_zeros() as usize;
+		InnerProductProof::serialized_size_for_rounds(lg_n)
+	}
+
+	/// Returns the size in bytes of the serialization of a proof with
+	/// `rounds` rounds, that is for vectors of length \\(2^{rounds}\\).
+	pub fn serialized_size_for_rounds(rounds: usize) -> usize {
+		(rounds * 2 + 2) * 32
+	}
+
 	/// Serializes the proof into a byte array of \\(2n+2\\) 32-byte elements.
 	/// The layout of the inner product proof is:
 	/// * \\(n\\) pairs of compressed Ristretto points \\(L_0, R_0 \dots,
@@ -360,13 +560,65 @@ impl InnerProductProof {
 			.copied()
 	}
 
+	/// Writes the serialized proof to `writer` element by element, in the
+	/// same layout as [`InnerProductProof::to_bytes`].
+	///
+	/// Returns the number of bytes written.
+	#[cfg(feature = "std")]
+	pub fn write_to<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<usize> {
+		for (l, r) in self.L_vec.iter().zip(self.R_vec.iter()) {
+			writer.write_all(l.as_bytes())?;
+			writer.write_all(r.as_bytes())?;
+		}
+		writer.write_all(self.a.as_bytes())?;
+		writer.write_all(self.b.as_bytes())?;
+		Ok(self.serialized_size())
+	}
+
+	/// Reads a proof in the [`InnerProductProof::to_bytes`] format from
+	/// `reader`, consuming it to the end.
+	///
+	/// Returns an error if reading fails, if more than `max_size` bytes are
+	/// available, or if the bytes cannot be parsed into an
+	/// `InnerProductProof`.
+	#[cfg(feature = "std")]
+	pub fn read_from<R: std::io::Read>(reader: R, max_size: usize) -> Result<InnerProductProof, ProofError> {
+		let buf = util::read_bounded(reader, max_size).ok_or(ProofError::FormatError)?;
+		InnerProductProof::from_bytes(&buf)
+	}
+
+	/// Writes the serialized proof to `writer`, prefixed with its length as
+	/// a little-endian `u32`, so that it can be embedded in a larger
+	/// message and read back with [`InnerProductProof::read_framed`].
+	///
+	/// Returns the number of bytes written, including the prefix.
+	#[cfg(feature = "std")]
+	pub fn write_framed<W: std::io::Write>(&self, writer: W) -> std::io::Result<usize> {
+		util::write_framed(writer, self.serialized_size(), |writer| self.write_to(writer))
+	}
+
+	/// Reads a proof written by [`InnerProductProof::write_framed`] from
+	/// `reader`, consuming only the bytes of the frame.
+	///
+	/// Returns an error if reading fails or the input ends early, if the
+	/// frame is longer than `max_size` bytes, or if its content cannot be
+	/// parsed into an `InnerProductProof`.
+	#[cfg(feature = "std")]
+	pub fn read_framed<R: std::io::Read>(reader: R, max_size: usize) -> Result<InnerProductProof, ProofError> {
+		let buf = util::read_framed(reader, max_size).ok_or(ProofError::FormatError)?;
+		InnerProductProof::from_bytes(&buf)
+	}
+
 	/// Deserializes the proof from a byte slice.
 	/// Returns an error in the following cases:
 	/// * the slice does not have \\(2n+2\\) 32-byte elements,
-	/// * \\(n\\) is larger or equal to 32 (proof is too big),
-	/// * any of \\(2n\\) points are not valid compressed Ristretto points,
+	/// * \\(n\\) is larger than [`InnerProductProof::MAX_ROUNDS`] (proof is too
+	///   big),
 	/// * any of 2 scalars are not canonical scalars modulo Ristretto group
 	///   order.
+	///
+	/// The \\(2n\\) points are checked to be valid compressed Ristretto
+	/// points when the proof is verified.
 	pub fn from_bytes(slice: &[u8]) -> Result<InnerProductProof, ProofError> {
 		let b = slice.len();
 		if b % 32 != 0 {
@@ -380,7 +632,7 @@ impl InnerProductProof {
 			return Err(ProofError::FormatError);
 		}
 		let lg_n = (num_elements - 2) / 2;
-		if lg_n >= 32 {
+		if lg_n > InnerProductProof::MAX_ROUNDS {
 			return Err(ProofError::FormatError);
 		}
 
@@ -402,6 +654,67 @@ impl InnerProductProof {
 	}
 }
 
+#[cfg(feature = "serde")]
+impl Serialize for InnerProductProof {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		serializer.serialize_bytes(&self.to_bytes()[..])
+	}
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for InnerProductProof {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		struct InnerProductProofVisitor;
+
+		impl<'de> Visitor<'de> for InnerProductProofVisitor {
+			type Value = InnerProductProof;
+
+			fn expecting(&self, formatter: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+				formatter.write_str("a valid InnerProductProof")
+			}
+
+			fn visit_bytes<E>(self, v: &[u8]) -> Result<InnerProductProof, E>
+			where
+				E: serde::de::Error,
+			{
+				// Using Error::custom requires T: Display, which our error
+				// type only implements when it implements std::error::Error.
+				#[cfg(feature = "std")]
+				return InnerProductProof::from_bytes(v).map_err(serde::de::Error::custom);
+				// In no-std contexts, drop the error message.
+				#[cfg(not(feature = "std"))]
+				return InnerProductProof::from_bytes(v).map_err(|_| serde::de::Error::custom("deserialization error"));
+			}
+
+			fn visit_seq<A>(self, mut seq: A) -> Result<InnerProductProof, A::Error>
+			where
+				A: serde::de::SeqAccess<'de>,
+			{
+				// Self-describing formats such as JSON encode byte arrays
+				// as sequences of integers.  Stop reading them once they
+				// are too long for a proof.
+				let max_size = InnerProductProof::serialized_size_for_rounds(InnerProductProof::MAX_ROUNDS);
+				let mut bytes = Vec::new();
+				while let Some(byte) = seq.next_element::<u8>()? {
+					if bytes.len() == max_size {
+						return Err(serde::de::Error::invalid_length(max_size + 1, &self));
+					}
+					bytes.push(byte);
+				}
+				self.visit_bytes(&bytes)
+			}
+		}
+
+		deserializer.deserialize_bytes(InnerProductProofVisitor)
+	}
+}
+
 /// Computes an inner product of two vectors
 /// \\[
 ///    {\langle {\mathbf{a}}, {\mathbf{b}} \rangle} = \sum\_{i=0}^{n-1} a\_i
@@ -431,8 +744,8 @@ mod tests {
 
 		use crate::generators::BulletproofGens;
 		let bp_gens = BulletproofGens::new(n, 1);
-		let G: Vec<RistrettoPoint> = bp_gens.share(0).G(n).cloned().collect();
-		let H: Vec<RistrettoPoint> = bp_gens.share(0).H(n).cloned().collect();
+		let G = bp_gens.share(0).G_slice(n).to_vec();
+		let H = bp_gens.share(0).H_slice(n).to_vec();
 
 		// Q would be determined upstream in the protocol, so we pick a random one.
 		let Q = RistrettoPoint::hash_from_bytes::<Sha3_512>(b"test point");
@@ -503,6 +816,39 @@ mod tests {
 			.is_ok());
 	}
 
+	#[test]
+	fn framed_stream_roundtrip() {
+		use std::io::Read;
+
+		let proof = InnerProductProof {
+			L_vec: vec![CompressedRistretto([1u8; 32]); 3],
+			R_vec: vec![CompressedRistretto([2u8; 32]); 3],
+			a: Scalar::from(3u64),
+			b: Scalar::from(4u64),
+		};
+		let len = proof.serialized_size();
+
+		let mut buf = Vec::new();
+		assert_eq!(proof.write_to(&mut buf).unwrap(), len);
+		assert_eq!(buf, proof.to_bytes());
+		let read = InnerProductProof::read_from(&buf[..], len).unwrap();
+		assert_eq!(read.to_bytes(), buf);
+		assert!(InnerProductProof::read_from(&buf[..], len - 1).is_err());
+
+		let mut framed = Vec::new();
+		assert_eq!(proof.write_framed(&mut framed).unwrap(), 4 + len);
+		framed.push(0);
+		let mut reader = (&framed[..2]).chain(&framed[2..100]).chain(&framed[100..]);
+		let read = InnerProductProof::read_framed(&mut reader, len).unwrap();
+		assert_eq!(read.to_bytes(), buf);
+		let mut rest = Vec::new();
+		reader.read_to_end(&mut rest).unwrap();
+		assert_eq!(rest, vec![0]);
+
+		assert!(InnerProductProof::read_framed(&framed[..4 + len - 1], len).is_err());
+		assert!(InnerProductProof::read_framed(&framed[..], len - 1).is_err());
+	}
+
 	#[test]
 	fn make_ipp_1() {
 		test_helper_create(1);
@@ -528,6 +874,74 @@ mod tests {
 		test_helper_create(64);
 	}
 
+	#[test]
+	fn serialization() {
+		let mut rng = rand::thread_rng();
+
+		use crate::generators::BulletproofGens;
+		let n = 8;
+		let bp_gens = BulletproofGens::new(n, 1);
+		let G = bp_gens.share(0).G_slice(n).to_vec();
+		let H = bp_gens.share(0).H_slice(n).to_vec();
+		let Q = RistrettoPoint::hash_from_bytes::<Sha3_512>(b"test point");
+		let a: Vec<_> = (0..n).map(|_| Scalar::random(&mut rng)).collect();
+		let b: Vec<_> = (0..n).map(|_| Scalar::random(&mut rng)).collect();
+		let ones = vec![Scalar::one(); n];
+		let proof = InnerProductProof::create(
+			&mut Transcript::new(b"innerproducttest"),
+			&Q,
+			&ones,
+			&ones,
+			G,
+			H,
+			a.clone(),
+			b.clone(),
+		);
+
+		let bytes = proof.to_bytes();
+		assert_eq!(bytes.len(), InnerProductProof::serialized_size_for_rounds(3));
+		assert_eq!(bytes.len(), proof.serialized_size());
+		let parsed = InnerProductProof::from_bytes(&bytes).unwrap();
+		assert_eq!(parsed, proof);
+		assert_eq!(parsed.rounds(), 3);
+		assert_eq!(parsed.to_bytes(), bytes);
+
+		// A proof for vectors of length 1 has no rounds.
+		let empty = [Scalar::one().to_bytes(), Scalar::zero().to_bytes()].concat();
+		assert_eq!(InnerProductProof::from_bytes(&empty).unwrap().L_vec.len(), 0);
+
+		// Lengths which are not a whole number of elements, or an odd
+		// number of points
+		for len in [0, 31, 32, bytes.len() - 1, bytes.len() - 32, bytes.len() + 1].iter() {
+			let mut truncated = bytes.clone();
+			truncated.resize(*len, 0);
+			assert_eq!(
+				InnerProductProof::from_bytes(&truncated).unwrap_err(),
+				ProofError::FormatError
+			);
+		}
+
+		// Non-canonical scalars
+		for pos in [bytes.len() - 64, bytes.len() - 32].iter() {
+			let mut non_canonical = bytes.clone();
+			non_canonical[*pos..*pos + 32].copy_from_slice(&[0xff; 32]);
+			assert_eq!(
+				InnerProductProof::from_bytes(&non_canonical).unwrap_err(),
+				ProofError::FormatError
+			);
+		}
+
+		// Too many rounds
+		let max = InnerProductProof::serialized_size_for_rounds(InnerProductProof::MAX_ROUNDS);
+		let long = [vec![0u8; max - 64], empty.clone()].concat();
+		assert!(InnerProductProof::from_bytes(&long).is_ok());
+		let too_long = [vec![0u8; max], empty].concat();
+		assert_eq!(
+			InnerProductProof::from_bytes(&too_long).unwrap_err(),
+			ProofError::FormatError
+		);
+	}
+
 	#[test]
 	fn test_inner_product() {
 		let a = vec![
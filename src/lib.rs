@@ -8,6 +8,8 @@
 #[macro_use]
 extern crate alloc;
 
+#[macro_use]
+mod trace;
 mod util;
 
 #[doc(include = "../docs/notes-intro.md")]
@@ -23,15 +25,37 @@ mod notes {
 mod errors;
 mod generators;
 mod inner_product_proof;
+mod linear_proof;
+mod msm;
+mod progress;
 mod range_proof;
+mod range_proof_plus;
 mod transcript;
+#[cfg(feature = "transcript-audit")]
+mod transcript_tape;
+mod verification_batch;
 
 pub use crate::{
-	errors::ProofError,
-	generators::{BulletproofGens, BulletproofGensShare, PedersenGens},
-	range_proof::RangeProof,
+	errors::{GeneratorError, ProofError},
+	generators::{BulletproofGens, BulletproofGensShare, GensCapacity, PedersenGens},
+	inner_product_proof::{inner_product, InnerProductProof},
+	linear_proof::LinearProof,
+	msm::{DefaultMsmBackend, MsmBackend},
+	progress::{CancellationToken, Milestone, ProgressSink},
+	range_proof::{range_proof_size, range_proof_verification_msm_size, RangeProof, RangeProofVerificationBatcher},
+	range_proof_plus::RangeProofPlus,
+	transcript::fork_transcript,
+	verification_batch::{TermPoint, VerificationBatch, VerificationTerms},
 };
 
+#[cfg(feature = "transcript-audit")]
+pub use crate::transcript_tape::{TranscriptEntry, TranscriptOperation, TranscriptTape};
+
+#[cfg(feature = "zeroize")]
+pub use crate::util::ZeroizeOnDrop;
+
+pub mod ctx;
+
 #[doc(include = "../docs/aggregation-api.md")]
 pub mod range_proof_mpc {
 	pub use crate::{
@@ -42,3 +66,6 @@ pub mod range_proof_mpc {
 
 #[cfg(feature = "yoloproofs")]
 pub mod r1cs;
+
+#[cfg(feature = "proptest")]
+pub mod testing;
@@ -0,0 +1,586 @@
+#![allow(non_snake_case)]
+//! Linear proofs.
+//!
+//! A [`LinearProof`] shows knowledge of a secret vector \\(\mathbf a\\) and
+//! a blinding factor \\(r\\) opening a commitment
+//! \\[
+//! C = \langle \mathbf a, \mathbf G \rangle + r B + \langle \mathbf a,
+//! \mathbf b \rangle F
+//! \\]
+//! for a public vector \\(\mathbf b\\), revealing nothing else about
+//! \\(\mathbf a\\).  This is the building block of vector commitment
+//! schemes: \\(C\\) commits to \\(\mathbf a\\), and the proof opens the
+//! linear function \\(\langle \mathbf a, \mathbf b \rangle\\) of it.
+//!
+//! The generators \\(\mathbf G\\) are usually taken from the
+//! [`BulletproofGens`](::BulletproofGens), and the bases \\(F, B\\) from the
+//! [`PedersenGens`](::PedersenGens), as \\(B\\) and \\(\widetilde B\\).
+//!
+//! The protocol is the zero-knowledge variant of the inner-product
+//! argument of section 3 of [Compressed Σ-Protocol Theory and Practical
+//! Application to Plug & Play Secure Algorithmics](https://eprint.iacr.org/2020/152),
+//! as implemented upstream by dalek.
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+use core::iter;
+
+use curve25519_dalek::{
+	ristretto::{CompressedRistretto, RistrettoPoint},
+	scalar::Scalar,
+	traits::{Identity, IsIdentity},
+};
+use merlin::Transcript;
+use rand_core::{CryptoRng, RngCore};
+
+use crate::{
+	errors::ProofError,
+	inner_product_proof::inner_product,
+	msm::{DefaultMsmBackend, MsmBackend},
+	transcript::TranscriptProtocol,
+};
+
+#[cfg(feature = "std")]
+use serde::de::Visitor;
+#[cfg(feature = "std")]
+use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
+
+/// A proof of knowledge of an opening of a commitment
+/// \\(C = \langle \mathbf a, \mathbf G \rangle + r B + \langle \mathbf a,
+/// \mathbf b \rangle F\\) for a public vector \\(\mathbf b\\).
+///
+/// The commitment, the vector \\(\mathbf b\\) and the generators are not
+/// included in the proof, and must be known to the verifier.
+#[derive(Clone, Debug)]
+pub struct LinearProof {
+	/// Left commitments of the folding rounds
+	L_vec: Vec<CompressedRistretto>,
+	/// Right commitments of the folding rounds
+	R_vec: Vec<CompressedRistretto>,
+	/// Commitment to the nonces of the last round
+	S: CompressedRistretto,
+	/// Blinded folded secret vector
+	a: Scalar,
+	/// Blinded folded blinding factor
+	r: Scalar,
+}
+
+impl LinearProof {
+	/// Creates a linear proof that `C` commits to the vector `a_vec` with
+	/// the blinding factor `r`, and to its inner product with `b_vec`.
+	///
+	/// The commitment must be \\(C = \langle \mathbf a, \mathbf G \rangle +
+	/// r B + \langle \mathbf a, \mathbf b \rangle F\\), with the
+	/// generators `G_vec` and the bases `F` and `B`.  The vectors `a_vec`,
+	/// `b_vec` and `G_vec` must have the same, nonzero length.  Lengths
+	/// which are not a power of two are padded with zeros and identity
+	/// generators, which do not change the commitment.
+	///
+	/// The nonces are drawn from the `transcript`, rekeyed with the witness
+	/// and `rng`.
+	pub fn create<T: RngCore + CryptoRng>(
+		transcript: &mut Transcript,
+		rng: &mut T,
+		C: &RistrettoPoint,
+		mut r: Scalar,
+		mut a_vec: Vec<Scalar>,
+		mut b_vec: Vec<Scalar>,
+		mut G_vec: Vec<RistrettoPoint>,
+		F: &RistrettoPoint,
+		B: &RistrettoPoint,
+	) -> Result<LinearProof, ProofError> {
+		let n = b_vec.len();
+		if n == 0 || a_vec.len() != n {
+			return Err(ProofError::InvalidInputLength);
+		}
+		if G_vec.len() != n {
+			return Err(ProofError::InvalidGeneratorsLength {
+				required: n,
+				actual: G_vec.len(),
+			});
+		}
+
+		append_statement(transcript, C, &b_vec, &G_vec, F, B);
+
+		let mut rng = a_vec
+			.iter()
+			.fold(
+				transcript.build_rng().rekey_with_witness_bytes(b"r", r.as_bytes()),
+				|builder, a_i| builder.rekey_with_witness_bytes(b"a_i", a_i.as_bytes()),
+			)
+			.finalize(rng);
+
+		let padded_n = n.next_power_of_two();
+		a_vec.resize(padded_n, Scalar::zero());
+		b_vec.resize(padded_n, Scalar::zero());
+		G_vec.resize(padded_n, RistrettoPoint::identity());
+
+		// The slices are resliced as the vectors are folded in half.
+		let mut a = &mut a_vec[..];
+		let mut b = &mut b_vec[..];
+		let mut G = &mut G_vec[..];
+
+		let lg_n = padded_n.trailing_zeros() as usize;
+		let mut L_vec = Vec::with_capacity(lg_n);
+		let mut R_vec = Vec::with_capacity(lg_n);
+
+		let mut n = padded_n;
+		while n != 1 {
+			n /= 2;
+			let (a_L, a_R) = a.split_at_mut(n);
+			let (b_L, b_R) = b.split_at_mut(n);
+			let (G_L, G_R) = G.split_at_mut(n);
+
+			let c_L = inner_product(a_L, b_R);
+			let c_R = inner_product(a_R, b_L);
+
+			let s_j = Scalar::random(&mut rng);
+			let t_j = Scalar::random(&mut rng);
+
+			// L = <a_L, G_R> + s_j * B + c_L * F
+			let L_scalars: Vec<Scalar> = a_L
+				.iter()
+				.cloned()
+				.chain(iter::once(s_j))
+				.chain(iter::once(c_L))
+				.collect();
+			let L_points: Vec<RistrettoPoint> = G_R
+				.iter()
+				.cloned()
+				.chain(iter::once(*B))
+				.chain(iter::once(*F))
+				.collect();
+			let L = DefaultMsmBackend.vartime_msm(&L_scalars, &L_points).compress();
+
+			// R = <a_R, G_L> + t_j * B + c_R * F
+			let R_scalars: Vec<Scalar> = a_R
+				.iter()
+				.cloned()
+				.chain(iter::once(t_j))
+				.chain(iter::once(c_R))
+				.collect();
+			let R_points: Vec<RistrettoPoint> = G_L
+				.iter()
+				.cloned()
+				.chain(iter::once(*B))
+				.chain(iter::once(*F))
+				.collect();
+			let R = DefaultMsmBackend.vartime_msm(&R_scalars, &R_points).compress();
+
+			L_vec.push(L);
+			R_vec.push(R);
+
+			transcript.append_point(b"L", &L);
+			transcript.append_point(b"R", &R);
+
+			let x_j = transcript.challenge_scalar(b"x_j");
+			let x_j_inv = x_j.invert();
+
+			for i in 0..n {
+				// a_L = a_L + x_j^{-1} * a_R
+				a_L[i] += x_j_inv * a_R[i];
+				// b_L = b_L + x_j * b_R
+				b_L[i] += x_j * b_R[i];
+				// G_L = G_L + x_j * G_R
+				G_L[i] += x_j * G_R[i];
+			}
+			a = a_L;
+			b = b_L;
+			G = G_L;
+			r += x_j * s_j + x_j_inv * t_j;
+		}
+
+		let s_star = Scalar::random(&mut rng);
+		let t_star = Scalar::random(&mut rng);
+		let S = (t_star * B + s_star * b[0] * F + s_star * G[0]).compress();
+		transcript.append_point(b"S", &S);
+
+		let x_star = transcript.challenge_scalar(b"x_star");
+
+		Ok(LinearProof {
+			L_vec,
+			R_vec,
+			S,
+			a: s_star + x_star * a[0],
+			r: t_star + x_star * r,
+		})
+	}
+
+	/// Verifies that the proof shows knowledge of an opening of `C` with
+	/// the generators `G` and the bases `F` and `B`, for the public vector
+	/// `b_vec`.
+	///
+	/// The `transcript` must be in the same state as the one passed to
+	/// [`LinearProof::create`].
+	pub fn verify(
+		&self,
+		transcript: &mut Transcript,
+		C: &RistrettoPoint,
+		G: &[RistrettoPoint],
+		F: &RistrettoPoint,
+		B: &RistrettoPoint,
+		b_vec: Vec<Scalar>,
+	) -> Result<(), ProofError> {
+		let n = b_vec.len();
+		if n == 0 {
+			return Err(ProofError::InvalidInputLength);
+		}
+		if G.len() != n {
+			return Err(ProofError::InvalidGeneratorsLength {
+				required: n,
+				actual: G.len(),
+			});
+		}
+		let padded_n = n.next_power_of_two();
+		if self.L_vec.len() != padded_n.trailing_zeros() as usize {
+			return Err(ProofError::VerificationError);
+		}
+
+		append_statement(transcript, C, &b_vec, G, F, B);
+
+		let mut challenges = Vec::with_capacity(self.L_vec.len());
+		for (L, R) in self.L_vec.iter().zip(self.R_vec.iter()) {
+			transcript.validate_and_append_point(b"L", L)?;
+			transcript.validate_and_append_point(b"R", R)?;
+			challenges.push(transcript.challenge_scalar(b"x_j"));
+		}
+		let mut challenges_inv = challenges.clone();
+		Scalar::batch_invert(&mut challenges_inv);
+
+		transcript.validate_and_append_point(b"S", &self.S)?;
+		let x_star = transcript.challenge_scalar(b"x_star");
+
+		// The folded generator and public vector are the weighted sums
+		// G_0 = <s, G> and b_0 = <s, b>, whose padding terms vanish.
+		let s = subset_products(padded_n, &challenges);
+		let b_0 = inner_product(&s[..n], &b_vec);
+
+		// Checks that
+		//   S = r * B + a * (G_0 + b_0 * F) - x * (C + sum_j(x_j * L_j + x_j^{-1} *
+		// R_j)).
+		let scalars: Vec<Scalar> = iter::once(self.r)
+			.chain(iter::once(self.a * b_0))
+			.chain(iter::once(-x_star))
+			.chain(iter::once(-Scalar::one()))
+			.chain(s[..n].iter().map(|s_i| self.a * s_i))
+			.chain(challenges.iter().map(|x_j| -x_star * x_j))
+			.chain(challenges_inv.iter().map(|x_j_inv| -x_star * x_j_inv))
+			.collect();
+
+		let S = self.S.decompress().ok_or(ProofError::VerificationError)?;
+		let points: Option<Vec<RistrettoPoint>> = iter::once(Some(*B))
+			.chain(iter::once(Some(*F)))
+			.chain(iter::once(Some(*C)))
+			.chain(iter::once(Some(S)))
+			.chain(G.iter().map(|G_i| Some(*G_i)))
+			.chain(self.L_vec.iter().map(|L| L.decompress()))
+			.chain(self.R_vec.iter().map(|R| R.decompress()))
+			.collect();
+		let points = points.ok_or(ProofError::VerificationError)?;
+
+		if DefaultMsmBackend.vartime_msm(&scalars, &points).is_identity() {
+			Ok(())
+		} else {
+			Err(ProofError::VerificationError)
+		}
+	}
+
+	/// Returns the size in bytes required to serialize the linear proof.
+	pub fn serialized_size(&self) -> usize {
+		3 * 32 + (self.L_vec.len() + self.R_vec.len()) * 32
+	}
+
+	/// Serializes the proof into a byte array of \\(2 \lg n + 3\\) 32-byte
+	/// elements, where \\(n\\) is the length of the vectors, padded to a
+	/// power of two.
+	///
+	/// # Layout
+	///
+	/// The layout of the linear proof encoding is:
+	///
+	/// * \\(\lg n\\) pairs of compressed Ristretto points \\(L_0, R_0, \dots,
+	///   L_{\lg n - 1}, R_{\lg n - 1}\\),
+	/// * a compressed Ristretto point \\(S\\),
+	/// * two scalars \\(a, r\\).
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut buf = Vec::with_capacity(self.serialized_size());
+		for (L, R) in self.L_vec.iter().zip(self.R_vec.iter()) {
+			buf.extend_from_slice(L.as_bytes());
+			buf.extend_from_slice(R.as_bytes());
+		}
+		buf.extend_from_slice(self.S.as_bytes());
+		buf.extend_from_slice(self.a.as_bytes());
+		buf.extend_from_slice(self.r.as_bytes());
+		buf
+	}
+
+	/// Deserializes the proof from a byte slice.
+	///
+	/// Returns an error if the byte slice cannot be parsed into a
+	/// `LinearProof`.
+	pub fn from_bytes(slice: &[u8]) -> Result<LinearProof, ProofError> {
+		if slice.len() % 32 != 0 || slice.len() < 3 * 32 {
+			return Err(ProofError::FormatError);
+		}
+		let num_elements = slice.len() / 32 - 3;
+		if num_elements % 2 != 0 {
+			return Err(ProofError::FormatError);
+		}
+		let lg_n = num_elements / 2;
+		if lg_n >= 32 {
+			return Err(ProofError::FormatError);
+		}
+
+		use crate::util::read32;
+
+		let mut L_vec = Vec::with_capacity(lg_n);
+		let mut R_vec = Vec::with_capacity(lg_n);
+		for i in 0..lg_n {
+			let pos = 2 * i * 32;
+			L_vec.push(CompressedRistretto(read32(&slice[pos..])));
+			R_vec.push(CompressedRistretto(read32(&slice[pos + 32..])));
+		}
+
+		let pos = 2 * lg_n * 32;
+		let S = CompressedRistretto(read32(&slice[pos..]));
+		let a = Scalar::from_canonical_bytes(read32(&slice[pos + 32..])).ok_or(ProofError::FormatError)?;
+		let r = Scalar::from_canonical_bytes(read32(&slice[pos + 2 * 32..])).ok_or(ProofError::FormatError)?;
+
+		Ok(LinearProof { L_vec, R_vec, S, a, r })
+	}
+}
+
+#[cfg(feature = "std")]
+impl Serialize for LinearProof {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		serializer.serialize_bytes(&self.to_bytes()[..])
+	}
+}
+
+#[cfg(feature = "std")]
+impl<'de> Deserialize<'de> for LinearProof {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		struct LinearProofVisitor;
+
+		impl<'de> Visitor<'de> for LinearProofVisitor {
+			type Value = LinearProof;
+
+			fn expecting(&self, formatter: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+				formatter.write_str("a valid LinearProof")
+			}
+
+			fn visit_bytes<E>(self, v: &[u8]) -> Result<LinearProof, E>
+			where
+				E: serde::de::Error,
+			{
+				LinearProof::from_bytes(v).map_err(serde::de::Error::custom)
+			}
+		}
+
+		deserializer.deserialize_bytes(LinearProofVisitor)
+	}
+}
+
+/// Appends the public data of the statement to the `transcript`.
+fn append_statement(
+	transcript: &mut Transcript,
+	C: &RistrettoPoint,
+	b_vec: &[Scalar],
+	G: &[RistrettoPoint],
+	F: &RistrettoPoint,
+	B: &RistrettoPoint,
+) {
+	transcript.linear_proof_domain_sep(b_vec.len() as u64);
+	transcript.append_point(b"C", &C.compress());
+	for b_i in b_vec {
+		transcript.append_scalar(b"b_i", b_i);
+	}
+	for G_i in G {
+		transcript.append_point(b"G_i", &G_i.compress());
+	}
+	transcript.append_point(b"F", &F.compress());
+	transcript.append_point(b"B", &B.compress());
+}
+
+/// Computes the weights \\(s\_i\\) of the generators in the generator
+/// folded over all the rounds, which are the products of the challenges
+/// of the rounds selected by the bits of \\(i\\).
+///
+/// The challenges are in the order of the rounds, so the first one is
+/// selected by the most significant bit.
+fn subset_products(n: usize, challenges: &[Scalar]) -> Vec<Scalar> {
+	let lg_n = challenges.len();
+	let mut s = Vec::with_capacity(n);
+	s.push(Scalar::one());
+	for i in 1..n {
+		let lg_i = (32 - 1 - (i as u32).leading_zeros()) as usize;
+		let k = 1 << lg_i;
+		s.push(s[i - k] * challenges[(lg_n - 1) - lg_i]);
+	}
+	s
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::generators::{BulletproofGens, PedersenGens};
+
+	/// Creates a proof for random vectors of length `n`, and returns it
+	/// with the statement.
+	fn create(n: usize) -> (LinearProof, RistrettoPoint, Vec<Scalar>) {
+		let mut rng = rand::thread_rng();
+		let bp_gens = BulletproofGens::new(64, 1);
+		let pc_gens = PedersenGens::default();
+		let G = bp_gens.share(0).G_slice(n).to_vec();
+
+		let a: Vec<_> = (0..n).map(|_| Scalar::random(&mut rng)).collect();
+		let b: Vec<_> = (0..n).map(|_| Scalar::random(&mut rng)).collect();
+		let r = Scalar::random(&mut rng);
+
+		// C = <a, G> + r * B + <a, b> * F
+		let scalars: Vec<Scalar> = a
+			.iter()
+			.cloned()
+			.chain(iter::once(r))
+			.chain(iter::once(inner_product(&a, &b)))
+			.collect();
+		let points: Vec<RistrettoPoint> = G
+			.iter()
+			.cloned()
+			.chain(iter::once(pc_gens.B_blinding))
+			.chain(iter::once(pc_gens.B))
+			.collect();
+		let C = DefaultMsmBackend.vartime_msm(&scalars, &points);
+
+		let mut transcript = Transcript::new(b"LinearProofTest");
+		let proof = LinearProof::create(
+			&mut transcript,
+			&mut rng,
+			&C,
+			r,
+			a,
+			b.clone(),
+			G,
+			&pc_gens.B,
+			&pc_gens.B_blinding,
+		)
+		.unwrap();
+		(proof, C, b)
+	}
+
+	fn verify(proof: &LinearProof, C: &RistrettoPoint, b: Vec<Scalar>) -> Result<(), ProofError> {
+		let bp_gens = BulletproofGens::new(64, 1);
+		let pc_gens = PedersenGens::default();
+		let G = bp_gens.share(0).G_slice(b.len());
+
+		let mut transcript = Transcript::new(b"LinearProofTest");
+		proof.verify(&mut transcript, C, G, &pc_gens.B, &pc_gens.B_blinding, b)
+	}
+
+	#[test]
+	fn lengths_up_to_64() {
+		for n in 1..=64 {
+			let (proof, C, b) = create(n);
+			assert_eq!(proof.L_vec.len(), n.next_power_of_two().trailing_zeros() as usize);
+			assert!(verify(&proof, &C, b.clone()).is_ok(), "length {}", n);
+
+			// The proof is bound to the public vector.
+			let mut other_b = b;
+			other_b[n - 1] += Scalar::one();
+			assert!(verify(&proof, &C, other_b).is_err(), "length {}", n);
+		}
+	}
+
+	#[test]
+	fn wrong_statements_fail() {
+		let (proof, C, b) = create(6);
+		let pc_gens = PedersenGens::default();
+
+		assert!(verify(&proof, &(C + pc_gens.B), b.clone()).is_err());
+		assert!(verify(&proof, &C, b[..5].to_vec()).is_err());
+		assert_eq!(verify(&proof, &C, Vec::new()), Err(ProofError::InvalidInputLength));
+
+		let bp_gens = BulletproofGens::new(8, 1);
+		let mut transcript = Transcript::new(b"LinearProofTest");
+		assert_eq!(
+			proof.verify(
+				&mut transcript,
+				&C,
+				bp_gens.share(0).G_slice(5),
+				&pc_gens.B,
+				&pc_gens.B_blinding,
+				b.clone()
+			),
+			Err(ProofError::InvalidGeneratorsLength { required: 6, actual: 5 })
+		);
+
+		let mut transcript = Transcript::new(b"OtherTest");
+		assert!(proof
+			.verify(
+				&mut transcript,
+				&C,
+				bp_gens.share(0).G_slice(6),
+				&pc_gens.B,
+				&pc_gens.B_blinding,
+				b
+			)
+			.is_err());
+	}
+
+	#[test]
+	fn mismatched_lengths_are_rejected() {
+		let bp_gens = BulletproofGens::new(4, 1);
+		let pc_gens = PedersenGens::default();
+		let create = |a_len: usize, b_len: usize, G_len: usize| {
+			LinearProof::create(
+				&mut Transcript::new(b"LinearProofTest"),
+				&mut rand::thread_rng(),
+				&pc_gens.B,
+				Scalar::one(),
+				vec![Scalar::one(); a_len],
+				vec![Scalar::one(); b_len],
+				bp_gens.share(0).G_slice(G_len).to_vec(),
+				&pc_gens.B,
+				&pc_gens.B_blinding,
+			)
+			.map(|_| ())
+		};
+
+		assert_eq!(create(0, 0, 0), Err(ProofError::InvalidInputLength));
+		assert_eq!(create(3, 4, 4), Err(ProofError::InvalidInputLength));
+		assert_eq!(
+			create(4, 4, 3),
+			Err(ProofError::InvalidGeneratorsLength { required: 4, actual: 3 })
+		);
+	}
+
+	#[test]
+	fn serialization() {
+		for &n in [1, 2, 5, 16, 33].iter() {
+			let (proof, C, b) = create(n);
+
+			let bytes = proof.to_bytes();
+			assert_eq!(bytes.len(), proof.serialized_size());
+			let parsed = LinearProof::from_bytes(&bytes).unwrap();
+			assert_eq!(parsed.to_bytes(), bytes);
+			assert!(verify(&parsed, &C, b).is_ok());
+
+			assert!(LinearProof::from_bytes(&bytes[..bytes.len() - 32]).is_err());
+			assert!(LinearProof::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+		}
+
+		let (proof, ..) = create(4);
+		let serialized = bincode::serialize(&proof).unwrap();
+		let deserialized: LinearProof = bincode::deserialize(&serialized).unwrap();
+		assert_eq!(deserialized.to_bytes(), proof.to_bytes());
+	}
+}
@@ -0,0 +1,63 @@
+//! The `msm` module defines the [`MsmBackend`] trait, used to plug a
+//! custom multiscalar multiplication implementation into verification.
+
+#![allow(non_snake_case)]
+#![deny(missing_docs)]
+
+use curve25519_dalek::{ristretto::RistrettoPoint, scalar::Scalar, traits::VartimeMultiscalarMul};
+
+/// A variable-time multiscalar multiplication implementation.
+///
+/// Verifiers perform their final check as one large multiscalar
+/// multiplication, which can be offloaded to a different implementation
+/// (e.g. a GPU) by passing an `MsmBackend` to the `_with_backend`
+/// verification functions.
+///
+/// The backend only ever receives public data: the points of the proof,
+/// the commitments, the generators, and scalars computed from them and
+/// from the verifier's random weights.  Its result is compared against the
+/// identity, so a faulty backend can make verification fail or pass
+/// incorrectly, but cannot leak secrets.
+pub trait MsmBackend {
+	/// Computes \\(\sum_i s_i P_i\\) for the `scalars` \\(s_i\\) and the
+	/// `points` \\(P_i\\), which have the same length.
+	///
+	/// The default implementation delegates to
+	/// [`VartimeMultiscalarMul`] from `curve25519-dalek`.
+	fn vartime_msm(&self, scalars: &[Scalar], points: &[RistrettoPoint]) -> RistrettoPoint {
+		RistrettoPoint::vartime_multiscalar_mul(scalars, points)
+	}
+}
+
+/// The default [`MsmBackend`], using the `curve25519-dalek`
+/// implementation.
+///
+/// With the `rayon` feature, large multiplications are split into one
+/// chunk per thread of the current `rayon` thread pool, and the partial
+/// sums are added.  The result is the same point, so the same proofs are
+/// accepted.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct DefaultMsmBackend;
+
+impl MsmBackend for DefaultMsmBackend {
+	#[cfg(feature = "rayon")]
+	fn vartime_msm(&self, scalars: &[Scalar], points: &[RistrettoPoint]) -> RistrettoPoint {
+		use curve25519_dalek::traits::Identity;
+		use rayon::prelude::*;
+
+		// Below this many points per chunk, the Pippenger buckets are not
+		// filled enough to be worth the split.
+		const MIN_CHUNK_SIZE: usize = 1024;
+
+		let threads = rayon::current_num_threads();
+		if threads == 1 || points.len() < 2 * MIN_CHUNK_SIZE {
+			return RistrettoPoint::vartime_multiscalar_mul(scalars, points);
+		}
+		let chunk_size = ((points.len() + threads - 1) / threads).max(MIN_CHUNK_SIZE);
+		scalars
+			.par_chunks(chunk_size)
+			.zip(points.par_chunks(chunk_size))
+			.map(|(scalars, points)| RistrettoPoint::vartime_multiscalar_mul(scalars, points))
+			.reduce(RistrettoPoint::identity, |a, b| a + b)
+	}
+}
@@ -0,0 +1,105 @@
+//! The `progress` module contains the API for observing and cancelling
+//! long-running proof creation.
+
+#![deny(missing_docs)]
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// A milestone reached during proof creation.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Milestone {
+	/// The witness vectors are built.
+	WitnessBuilt,
+	/// The commitments to the witness vectors are computed: \\(A_I, A_O,
+	/// S\\) for constraint system proofs, or \\(A, S\\) for range proofs.
+	WitnessCommitted,
+	/// The commitments to the coefficients of \\(t(x)\\) are computed.
+	PolynomialCommitted,
+	/// The given round of the inner-product proof is done, out of `rounds`
+	/// rounds in total.  Rounds are numbered from zero.
+	InnerProductRound {
+		/// The index of the completed round.
+		round: usize,
+		/// The total number of rounds.
+		rounds: usize,
+	},
+}
+
+impl Milestone {
+	/// Returns an estimate of the fraction of the proving work done when
+	/// reaching this milestone.
+	///
+	/// The estimate assumes that the work is split evenly between
+	/// committing to the witness and the inner-product proof, which is a
+	/// rough approximation for large proofs.
+	pub fn fraction(&self) -> f64 {
+		match *self {
+			Milestone::WitnessBuilt => 0.05,
+			Milestone::WitnessCommitted => 0.4,
+			Milestone::PolynomialCommitted => 0.5,
+			Milestone::InnerProductRound { round, rounds } => 0.5 + 0.5 * (round + 1) as f64 / rounds as f64,
+		}
+	}
+}
+
+/// Receives progress reports during proof creation.
+///
+/// The sink is called synchronously from the proving thread, so it should
+/// return quickly.
+pub trait ProgressSink {
+	/// Reports that the `milestone` has been reached, with an estimate of
+	/// the `fraction` of the work done, between `0.0` and `1.0`.
+	fn report(&self, milestone: Milestone, fraction: f64);
+}
+
+/// A token to cooperatively cancel proof creation.
+///
+/// The token can be shared with another thread, which calls
+/// [`CancellationToken::cancel`].  The prover checks the token at each
+/// [`Milestone`], and aborts after clearing the partial secrets it holds.
+#[derive(Debug, Default)]
+pub struct CancellationToken {
+	cancelled: AtomicBool,
+}
+
+impl CancellationToken {
+	/// Creates a token which is not cancelled.
+	pub fn new() -> Self {
+		CancellationToken {
+			cancelled: AtomicBool::new(false),
+		}
+	}
+
+	/// Requests the cancellation of the proofs using this token.
+	pub fn cancel(&self) {
+		self.cancelled.store(true, Ordering::Relaxed);
+	}
+
+	/// Returns whether cancellation was requested.
+	pub fn is_cancelled(&self) -> bool {
+		self.cancelled.load(Ordering::Relaxed)
+	}
+}
+
+/// The optional progress sink and cancellation token of a prover.
+#[derive(Copy, Clone, Default)]
+pub(crate) struct Progress<'a> {
+	sink: Option<&'a dyn ProgressSink>,
+	cancel: Option<&'a CancellationToken>,
+}
+
+impl<'a> Progress<'a> {
+	pub(crate) fn new(sink: Option<&'a dyn ProgressSink>, cancel: Option<&'a CancellationToken>) -> Self {
+		Progress { sink, cancel }
+	}
+
+	/// Reports the `milestone` to the sink, and returns whether
+	/// cancellation was requested.
+	#[inline]
+	pub(crate) fn cancelled_at(&self, milestone: Milestone) -> bool {
+		if let Some(sink) = self.sink {
+			sink.report(milestone, milestone.fraction());
+		}
+		self.cancel.map_or(false, |cancel| cancel.is_cancelled())
+	}
+}
@@ -0,0 +1,312 @@
+#![allow(non_snake_case)]
+//! The `dealer` module contains the API for the dealer state while the dealer
+//! is engaging in an aggregated constraint system proof.
+//!
+//! For more explanation of how the `dealer`, `party`, and `messages` modules
+//! orchestrate the protocol execution, see the documentation in the
+//! [`aggregation`](::r1cs::aggregation) module.
+
+use alloc::vec::Vec;
+use core::iter;
+use curve25519_dalek::{
+	ristretto::{CompressedRistretto, RistrettoPoint},
+	scalar::Scalar,
+	traits::Identity,
+};
+use merlin::Transcript;
+
+use super::{super::R1CSProof, messages::*};
+use crate::{
+	errors::MPCError,
+	generators::{BulletproofGens, PedersenGens},
+	inner_product_proof::InnerProductProof,
+	transcript::{append_u64, TranscriptProtocol},
+	util,
+};
+
+/// Used to construct a dealer for the aggregated constraint system proof.
+pub struct Dealer {}
+
+impl Dealer {
+	/// Creates a new dealer coordinating `m` parties, each proving a
+	/// circuit with at most `n` multipliers.
+	///
+	/// The `n` is rounded up to a power of two, like in [`Party::new`].
+	///
+	/// [`Party::new`]: super::party::Party::new
+	pub fn new<'a, 'b>(
+		bp_gens: &'b BulletproofGens,
+		pc_gens: &'b PedersenGens,
+		transcript: &'a mut Transcript,
+		n: usize,
+		m: usize,
+	) -> Result<DealerAwaitingWireCommitments<'a, 'b>, MPCError> {
+		let n = n.next_power_of_two();
+		if !m.is_power_of_two() {
+			return Err(MPCError::InvalidAggregation);
+		}
+		if bp_gens.gens_capacity < n {
+			return Err(MPCError::InvalidGeneratorsLength {
+				required: n,
+				actual: bp_gens.gens_capacity,
+			});
+		}
+		if bp_gens.party_capacity < m {
+			return Err(MPCError::InvalidGeneratorsLength {
+				required: m,
+				actual: bp_gens.party_capacity,
+			});
+		}
+
+		transcript.r1cs_aggregation_domain_sep(n as u64, m as u64);
+
+		Ok(DealerAwaitingWireCommitments {
+			bp_gens,
+			pc_gens,
+			transcript,
+			n,
+			m,
+		})
+	}
+}
+
+/// A dealer waiting for the parties to send their [`WireCommitment`]s.
+pub struct DealerAwaitingWireCommitments<'a, 'b> {
+	bp_gens: &'b BulletproofGens,
+	pc_gens: &'b PedersenGens,
+	transcript: &'a mut Transcript,
+	n: usize,
+	m: usize,
+}
+
+impl<'a, 'b> DealerAwaitingWireCommitments<'a, 'b> {
+	/// Receive each party's [`WireCommitment`]s and compute the
+	/// [`WireChallenge`].
+	pub fn receive_wire_commitments(
+		self,
+		wire_commitments: Vec<WireCommitment>,
+	) -> Result<(DealerAwaitingPolyCommitments<'a, 'b>, WireChallenge), MPCError> {
+		if self.m != wire_commitments.len() {
+			return Err(MPCError::WrongNumBitCommitments);
+		}
+
+		// Commit the V's of each party, followed by their number
+		for wc in wire_commitments.iter() {
+			for V in wc.V_j.iter() {
+				self.transcript.append_point(b"V", V);
+			}
+			append_u64(self.transcript, b"m", wc.V_j.len() as u64);
+		}
+
+		// Commit aggregated A_I, A_O, S
+		let A_I: RistrettoPoint = wire_commitments.iter().map(|wc| wc.A_I_j).sum();
+		let A_O: RistrettoPoint = wire_commitments.iter().map(|wc| wc.A_O_j).sum();
+		let S: RistrettoPoint = wire_commitments.iter().map(|wc| wc.S_j).sum();
+		let (A_I, A_O, S) = (A_I.compress(), A_O.compress(), S.compress());
+		self.transcript.append_point(b"A_I1", &A_I);
+		self.transcript.append_point(b"A_O1", &A_O);
+		self.transcript.append_point(b"S1", &S);
+
+		// Aggregated proofs have no randomized constraints, so the
+		// second-phase commitments are the identity.
+		self.transcript.r1cs_1phase_domain_sep();
+		self.transcript.append_point(b"A_I2", &CompressedRistretto::identity());
+		self.transcript.append_point(b"A_O2", &CompressedRistretto::identity());
+		self.transcript.append_point(b"S2", &CompressedRistretto::identity());
+
+		// Bind the challenges to the size of the aggregated circuit.
+		let num_constraints: usize = wire_commitments.iter().map(|wc| wc.num_constraints).sum();
+		self.transcript
+			.r1cs_circuit_size((self.n * self.m) as u64, num_constraints as u64);
+
+		let y = self.transcript.challenge_scalar(b"y");
+		let z = self.transcript.challenge_scalar(b"z");
+
+		let constraint_offsets = wire_commitments
+			.iter()
+			.scan(0, |offset, wc| {
+				let party_offset = *offset;
+				*offset += wc.num_constraints;
+				Some(party_offset)
+			})
+			.collect();
+		let wire_challenge = WireChallenge {
+			y,
+			z,
+			constraint_offsets,
+		};
+
+		Ok((
+			DealerAwaitingPolyCommitments {
+				bp_gens: self.bp_gens,
+				pc_gens: self.pc_gens,
+				transcript: self.transcript,
+				n: self.n,
+				m: self.m,
+				y,
+				A_I,
+				A_O,
+				S,
+			},
+			wire_challenge,
+		))
+	}
+}
+
+/// A dealer which has sent the [`WireChallenge`] to the parties and
+/// is waiting for their [`PolyCommitment`]s.
+pub struct DealerAwaitingPolyCommitments<'a, 'b> {
+	bp_gens: &'b BulletproofGens,
+	pc_gens: &'b PedersenGens,
+	transcript: &'a mut Transcript,
+	n: usize,
+	m: usize,
+	y: Scalar,
+	A_I: CompressedRistretto,
+	A_O: CompressedRistretto,
+	S: CompressedRistretto,
+}
+
+impl<'a, 'b> DealerAwaitingPolyCommitments<'a, 'b> {
+	/// Receive [`PolyCommitment`]s from the parties and compute the
+	/// [`PolyChallenge`].
+	pub fn receive_poly_commitments(
+		self,
+		poly_commitments: Vec<PolyCommitment>,
+	) -> Result<(DealerAwaitingProofShares<'a, 'b>, PolyChallenge), MPCError> {
+		if self.m != poly_commitments.len() {
+			return Err(MPCError::WrongNumPolyCommitments);
+		}
+
+		// Commit sums of the T_i_j's
+		let T_1: RistrettoPoint = poly_commitments.iter().map(|pc| pc.T_1_j).sum();
+		let T_3: RistrettoPoint = poly_commitments.iter().map(|pc| pc.T_3_j).sum();
+		let T_4: RistrettoPoint = poly_commitments.iter().map(|pc| pc.T_4_j).sum();
+		let T_5: RistrettoPoint = poly_commitments.iter().map(|pc| pc.T_5_j).sum();
+		let T_6: RistrettoPoint = poly_commitments.iter().map(|pc| pc.T_6_j).sum();
+		let T = [
+			T_1.compress(),
+			T_3.compress(),
+			T_4.compress(),
+			T_5.compress(),
+			T_6.compress(),
+		];
+
+		self.transcript.append_point(b"T_1", &T[0]);
+		self.transcript.append_point(b"T_3", &T[1]);
+		self.transcript.append_point(b"T_4", &T[2]);
+		self.transcript.append_point(b"T_5", &T[3]);
+		self.transcript.append_point(b"T_6", &T[4]);
+
+		// The challenge for the second phase is unused, but it is part of
+		// the transcript of a single-party proof.
+		let _u = self.transcript.challenge_scalar(b"u");
+		let x = self.transcript.challenge_scalar(b"x");
+		let poly_challenge = PolyChallenge { x };
+
+		Ok((
+			DealerAwaitingProofShares {
+				bp_gens: self.bp_gens,
+				pc_gens: self.pc_gens,
+				transcript: self.transcript,
+				n: self.n,
+				m: self.m,
+				y: self.y,
+				A_I: self.A_I,
+				A_O: self.A_O,
+				S: self.S,
+				T,
+			},
+			poly_challenge,
+		))
+	}
+}
+
+/// A dealer which has sent the [`PolyChallenge`] to the parties and
+/// is waiting to aggregate their [`ProofShare`]s into an
+/// [`R1CSProof`].
+pub struct DealerAwaitingProofShares<'a, 'b> {
+	bp_gens: &'b BulletproofGens,
+	pc_gens: &'b PedersenGens,
+	transcript: &'a mut Transcript,
+	n: usize,
+	m: usize,
+	y: Scalar,
+	A_I: CompressedRistretto,
+	A_O: CompressedRistretto,
+	S: CompressedRistretto,
+	/// The aggregated commitments \\(T_1, T_3, T_4, T_5, T_6\\).
+	T: [CompressedRistretto; 5],
+}
+
+impl<'a, 'b> DealerAwaitingProofShares<'a, 'b> {
+	/// Assembles the [`ProofShare`]s into an aggregated [`R1CSProof`].
+	///
+	/// The dealer does not know the circuit, so it cannot verify the
+	/// proof: it only checks that every share has the expected size.  The
+	/// proof should be checked with [`verify`](super::verify), which
+	/// synthesizes the circuit of every party.
+	pub fn receive_shares(self, proof_shares: &[ProofShare]) -> Result<R1CSProof, MPCError> {
+		if self.m != proof_shares.len() {
+			return Err(MPCError::WrongNumProofShares);
+		}
+
+		let bad_shares: Vec<usize> = proof_shares
+			.iter()
+			.enumerate()
+			.filter(|(_, share)| share.check_size(self.n).is_err())
+			.map(|(j, _)| j)
+			.collect();
+		if !bad_shares.is_empty() {
+			return Err(MPCError::MalformedProofShares { bad_shares });
+		}
+
+		let t_x: Scalar = proof_shares.iter().map(|ps| ps.t_x).sum();
+		let t_x_blinding: Scalar = proof_shares.iter().map(|ps| ps.t_x_blinding).sum();
+		let e_blinding: Scalar = proof_shares.iter().map(|ps| ps.e_blinding).sum();
+
+		self.transcript.append_scalar(b"t_x", &t_x);
+		self.transcript.append_scalar(b"t_x_blinding", &t_x_blinding);
+		self.transcript.append_scalar(b"e_blinding", &e_blinding);
+
+		// Get a challenge value to combine statements for the IPP
+		let w = self.transcript.challenge_scalar(b"w");
+		let Q = w * self.pc_gens.B;
+
+		let G_factors: Vec<Scalar> = iter::repeat(Scalar::one()).take(self.n * self.m).collect();
+		let H_factors: Vec<Scalar> = util::exp_iter(self.y.invert()).take(self.n * self.m).collect();
+
+		let l_vec: Vec<Scalar> = proof_shares.iter().flat_map(|ps| ps.l_vec.iter().cloned()).collect();
+		let r_vec: Vec<Scalar> = proof_shares.iter().flat_map(|ps| ps.r_vec.iter().cloned()).collect();
+
+		let ipp_proof = InnerProductProof::create(
+			self.transcript,
+			&Q,
+			&G_factors,
+			&H_factors,
+			self.bp_gens.G(self.n, self.m).cloned().collect(),
+			self.bp_gens.H(self.n, self.m).cloned().collect(),
+			l_vec,
+			r_vec,
+		);
+
+		let [T_1, T_3, T_4, T_5, T_6] = self.T;
+		Ok(R1CSProof {
+			A_I1: self.A_I,
+			A_O1: self.A_O,
+			S1: self.S,
+			A_I2: CompressedRistretto::identity(),
+			A_O2: CompressedRistretto::identity(),
+			S2: CompressedRistretto::identity(),
+			T_1,
+			T_3,
+			T_4,
+			T_5,
+			T_6,
+			t_x,
+			t_x_blinding,
+			e_blinding,
+			ipp_proof,
+		})
+	}
+}
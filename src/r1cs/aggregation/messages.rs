@@ -0,0 +1,70 @@
+//! The `messages` module contains the API for the messages passed between the
+//! parties and the dealer of an aggregated constraint system proof.
+//!
+//! For more explanation of how the `dealer`, `party`, and `messages` modules
+//! orchestrate the protocol execution, see the documentation in the
+//! [`aggregation`](::r1cs::aggregation) module.
+
+use alloc::vec::Vec;
+use curve25519_dalek::{
+	ristretto::{CompressedRistretto, RistrettoPoint},
+	scalar::Scalar,
+};
+
+/// A commitment to a party's high-level variables and to the wires of the
+/// multipliers of its circuit.
+#[derive(Clone, Debug)]
+pub struct WireCommitment {
+	pub(super) V_j: Vec<CompressedRistretto>,
+	pub(super) num_constraints: usize,
+	pub(super) A_I_j: RistrettoPoint,
+	pub(super) A_O_j: RistrettoPoint,
+	pub(super) S_j: RistrettoPoint,
+}
+
+/// Challenge values derived from all parties' [`WireCommitment`]s.
+#[derive(Clone, Debug)]
+pub struct WireChallenge {
+	pub(super) y: Scalar,
+	pub(super) z: Scalar,
+	/// The number of constraints of the parties preceding each party.
+	pub(super) constraint_offsets: Vec<usize>,
+}
+
+/// A commitment to a party's polynomial coefficents.
+#[derive(Copy, Clone, Debug)]
+pub struct PolyCommitment {
+	pub(super) T_1_j: RistrettoPoint,
+	pub(super) T_3_j: RistrettoPoint,
+	pub(super) T_4_j: RistrettoPoint,
+	pub(super) T_5_j: RistrettoPoint,
+	pub(super) T_6_j: RistrettoPoint,
+}
+
+/// Challenge values derived from all parties' [`PolyCommitment`]s.
+#[derive(Copy, Clone, Debug)]
+pub struct PolyChallenge {
+	pub(super) x: Scalar,
+}
+
+/// A party's proof share, ready for aggregation into the final
+/// [`R1CSProof`](::r1cs::R1CSProof).
+#[derive(Clone, Debug)]
+pub struct ProofShare {
+	pub(super) t_x: Scalar,
+	pub(super) t_x_blinding: Scalar,
+	pub(super) e_blinding: Scalar,
+	pub(super) l_vec: Vec<Scalar>,
+	pub(super) r_vec: Vec<Scalar>,
+}
+
+impl ProofShare {
+	/// Checks that the l/r vectors of the proof share have the expected
+	/// length.
+	pub(super) fn check_size(&self, expected_n: usize) -> Result<(), ()> {
+		if self.l_vec.len() != expected_n || self.r_vec.len() != expected_n {
+			return Err(());
+		}
+		Ok(())
+	}
+}
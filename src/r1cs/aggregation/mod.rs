@@ -0,0 +1,211 @@
+#![allow(non_snake_case)]
+//! Aggregated proofs of a constraint system shared by several parties.
+//!
+//! Each of \\(m\\) parties synthesizes the same circuit with a
+//! [`Prover`](::r1cs::Prover) over its own committed inputs and witness.
+//! Like the [range proof MPC](::range_proof_mpc), the parties and a dealer
+//! then run a protocol which produces a single [`R1CSProof`] for the
+//! combined circuit:
+//!
+//! 1. each [`Party`](party::Party) is assigned a position \\(j\\) and sends a
+//!    [`WireCommitment`](messages::WireCommitment) to its inputs and to the
+//!    wires of its multipliers, using the \\(j\\)-th generator share;
+//! 2. the [`Dealer`](dealer::Dealer) absorbs the commitments and returns the
+//!    challenges \\(y, z\\);
+//! 3. each party commits to its share of the polynomial \\(t(x)\\) and the
+//!    dealer returns the challenge \\(x\\);
+//! 4. each party sends its [`ProofShare`](messages::ProofShare), which the
+//!    dealer combines with an inner-product argument over the generators of all
+//!    \\(m\\) shares.
+//!
+//! The \\(n\\) multipliers of party \\(j\\) occupy the positions starting
+//! at \\(j n\\) in the combined circuit, and its constraints follow the
+//! constraints of the parties before it.  The combined circuit is verified
+//! with [`verify`] against the concatenation of the parties' commitments.
+//!
+//! Only circuits without randomized constraints can be aggregated, since
+//! the parties cannot derive challenges from their own transcripts.
+
+use alloc::vec::Vec;
+use merlin::Transcript;
+use rand_core::{CryptoRng, RngCore};
+
+use curve25519_dalek::{ristretto::CompressedRistretto, scalar::Scalar};
+
+#[cfg(feature = "std")]
+use rand::thread_rng;
+
+use super::{
+	observer::Observer,
+	verifier::{decompress_commitments, pre_verify_committed_proof},
+	LinearCombination, R1CSProof, Variable, Verifier,
+};
+use crate::{
+	errors::R1CSError,
+	generators::{BulletproofGens, PedersenGens},
+	msm::DefaultMsmBackend,
+	transcript::{append_u64, TranscriptProtocol},
+};
+
+pub mod dealer;
+pub mod messages;
+pub mod party;
+
+/// Verifies an aggregated `proof` of `m` parties with at most `n`
+/// multipliers each against the concatenation of their `commitments`.
+///
+/// This is a convenience wrapper around [`verify_with_rng`], passing in a
+/// threadsafe RNG.
+#[cfg(feature = "std")]
+pub fn verify<F>(
+	proof: &R1CSProof,
+	bp_gens: &BulletproofGens,
+	pc_gens: &PedersenGens,
+	transcript: &mut Transcript,
+	commitments: &[CompressedRistretto],
+	n: usize,
+	m: usize,
+	gadget: F,
+) -> Result<(), R1CSError>
+where
+	F: Fn(&mut Verifier, Vec<Variable>) -> Result<(), R1CSError>,
+{
+	verify_with_rng(
+		proof,
+		bp_gens,
+		pc_gens,
+		transcript,
+		commitments,
+		n,
+		m,
+		gadget,
+		&mut thread_rng(),
+	)
+}
+
+/// Verifies an aggregated `proof` of `m` parties with at most `n`
+/// multipliers each against the concatenation of their `commitments`.
+///
+/// The commitments are split evenly between the parties, and the circuit of
+/// each party is synthesized by calling `gadget` with a verifier and the
+/// variables for its commitments.  The combined circuit has `n * m`
+/// multipliers, with `n` rounded up to a power of two.
+///
+/// The `transcript` must be in the same state as the one passed to
+/// [`Dealer::new`](dealer::Dealer::new).  The `gadget` must not append to
+/// the transcript of the verifier, which is discarded.
+///
+/// Returns [`R1CSError::GadgetError`] if `m` is not a power of two, if the
+/// commitments cannot be split evenly between the parties, or if the
+/// circuit of a party has more than `n` multipliers.
+pub fn verify_with_rng<F, T>(
+	proof: &R1CSProof,
+	bp_gens: &BulletproofGens,
+	pc_gens: &PedersenGens,
+	transcript: &mut Transcript,
+	commitments: &[CompressedRistretto],
+	n: usize,
+	m: usize,
+	gadget: F,
+	prng: &mut T,
+) -> Result<(), R1CSError>
+where
+	F: Fn(&mut Verifier, Vec<Variable>) -> Result<(), R1CSError>,
+	T: RngCore + CryptoRng,
+{
+	let n = n.next_power_of_two();
+	if m == 0 || !m.is_power_of_two() {
+		return Err(R1CSError::gadget("the number of parties must be a power of two"));
+	}
+	if commitments.len() % m != 0 {
+		return Err(R1CSError::gadget(
+			"the commitments cannot be split evenly between the parties",
+		));
+	}
+	if bp_gens.gens_capacity < n {
+		return Err(R1CSError::InvalidGeneratorsLength {
+			required: n,
+			actual: bp_gens.gens_capacity,
+		});
+	}
+	if bp_gens.party_capacity < m {
+		return Err(R1CSError::InvalidGeneratorsLength {
+			required: m,
+			actual: bp_gens.party_capacity,
+		});
+	}
+
+	enter_span!("r1cs_verify_aggregated", m = m, n = n);
+
+	// Synthesize the circuit of every party, and move its variables to
+	// the party's positions in the combined circuit.
+	let party_commitments = commitments.len() / m;
+	let mut constraints = Vec::new();
+	for j in 0..m {
+		let mut party_transcript = Transcript::new(b"AggregatedParty");
+		let mut verifier = Verifier::new(&mut party_transcript);
+		let vars = commitments[j * party_commitments..(j + 1) * party_commitments]
+			.iter()
+			.map(|V| verifier.commit(*V))
+			.collect();
+		gadget(&mut verifier, vars)?;
+
+		let (party_constraints, num_multipliers) = verifier.into_constraints()?;
+		if num_multipliers > n {
+			return Err(R1CSError::gadget("the circuit of a party has more than n multipliers"));
+		}
+		constraints.extend(
+			party_constraints
+				.into_iter()
+				.map(|lc| offset_variables(lc, j * n, j * party_commitments)),
+		);
+	}
+
+	// Replay the messages appended by the dealer.
+	transcript.r1cs_aggregation_domain_sep(n as u64, m as u64);
+	for j in 0..m {
+		for V in commitments[j * party_commitments..(j + 1) * party_commitments].iter() {
+			transcript.append_point(b"V", V);
+		}
+		append_u64(transcript, b"m", party_commitments as u64);
+	}
+
+	transcript.validate_and_append_point(b"A_I1", &proof.A_I1)?;
+	transcript.validate_and_append_point(b"A_O1", &proof.A_O1)?;
+	transcript.validate_and_append_point(b"S1", &proof.S1)?;
+
+	transcript.r1cs_1phase_domain_sep();
+
+	let G: Vec<_> = bp_gens.G(n, m).cloned().collect();
+	let H: Vec<_> = bp_gens.H(n, m).cloned().collect();
+
+	pre_verify_committed_proof(
+		transcript,
+		proof,
+		&decompress_commitments(commitments)?,
+		&constraints,
+		n * m,
+		n * m,
+		prng,
+		Observer::default(),
+	)?
+	.check(pc_gens, &G, &H, &DefaultMsmBackend)
+}
+
+/// Moves the multipliers of `lc` by `multiplier_offset` and its committed
+/// variables by `committed_offset`.
+fn offset_variables(lc: LinearCombination, multiplier_offset: usize, committed_offset: usize) -> LinearCombination {
+	lc.terms
+		.into_iter()
+		.map(|(var, coeff): (Variable, Scalar)| {
+			let var = match var {
+				Variable::MultiplierLeft(i) => Variable::MultiplierLeft(i + multiplier_offset),
+				Variable::MultiplierRight(i) => Variable::MultiplierRight(i + multiplier_offset),
+				Variable::MultiplierOutput(i) => Variable::MultiplierOutput(i + multiplier_offset),
+				Variable::Committed(i) => Variable::Committed(i + committed_offset),
+				Variable::One() => Variable::One(),
+			};
+			(var, coeff)
+		})
+		.collect()
+}
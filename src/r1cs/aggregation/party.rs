@@ -0,0 +1,475 @@
+#![allow(non_snake_case)]
+//! The `party` module contains the API for the party state while the party is
+//! engaging in an aggregated constraint system proof.
+//!
+//! Each state of the protocol is represented by a different Rust type.  The
+//! state transitions consume the previous state, making it a compile error
+//! to perform the steps out of order or to repeat a step.
+//!
+//! For more explanation of how the `dealer`, `party`, and `messages` modules
+//! orchestrate the protocol execution, see the documentation in the
+//! [`aggregation`](::r1cs::aggregation) module.
+
+use alloc::vec::Vec;
+use clear_on_drop::clear::Clear;
+use core::iter;
+use curve25519_dalek::{
+	ristretto::{CompressedRistretto, RistrettoPoint},
+	scalar::Scalar,
+	traits::MultiscalarMul,
+};
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(feature = "zeroize")]
+use crate::util::ZeroizeOnDrop;
+#[cfg(feature = "std")]
+use rand::thread_rng;
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+use super::{
+	super::{LinearCombination, Prover, Variable},
+	messages::*,
+};
+use crate::{
+	errors::{MPCError, R1CSError},
+	generators::{BulletproofGens, PedersenGens},
+	util,
+};
+
+/// Used to construct a party for the aggregated constraint system proof.
+pub struct Party {}
+
+impl Party {
+	/// Constructs a `PartyAwaitingPosition` from a `prover` whose gadgets
+	/// have been synthesized.
+	///
+	/// The `prover` should be created over a scratch transcript: the
+	/// challenges of the aggregated proof are derived by the dealer, so
+	/// anything the prover appended to its own transcript is ignored.  Its
+	/// circuit is padded to `n` multipliers, rounded up to a power of two,
+	/// which must be the same for all parties.
+	///
+	/// Fails if the circuit has randomized constraints or more than `n`
+	/// multipliers.
+	pub fn new<'a>(
+		bp_gens: &'a BulletproofGens,
+		prover: Prover<'_, 'a>,
+		n: usize,
+	) -> Result<PartyAwaitingPosition<'a>, R1CSError> {
+		let pc_gens = prover.pc_gens();
+		let (constraints, mut a_L, mut a_R, mut a_O, v, v_blinding) = prover.into_witness()?;
+
+		let padded_n = n.next_power_of_two();
+		if a_L.len() > padded_n {
+			return Err(R1CSError::InvalidGeneratorsLength {
+				required: a_L.len().next_power_of_two(),
+				actual: padded_n,
+			});
+		}
+		if bp_gens.gens_capacity < padded_n {
+			return Err(R1CSError::InvalidGeneratorsLength {
+				required: padded_n,
+				actual: bp_gens.gens_capacity,
+			});
+		}
+
+		// The padding multipliers are unconstrained, so zero assignments
+		// satisfy them.
+		a_L.resize(padded_n, Scalar::zero());
+		a_R.resize(padded_n, Scalar::zero());
+		a_O.resize(padded_n, Scalar::zero());
+
+		let V = v
+			.iter()
+			.zip(v_blinding.iter())
+			.map(|(v, v_blinding)| pc_gens.commit(*v, *v_blinding).compress())
+			.collect();
+
+		Ok(PartyAwaitingPosition {
+			bp_gens,
+			pc_gens,
+			n: padded_n,
+			constraints,
+			a_L,
+			a_R,
+			a_O,
+			v,
+			v_blinding,
+			V,
+		})
+	}
+}
+
+/// A party waiting for the dealer to assign their position in the aggregation.
+pub struct PartyAwaitingPosition<'a> {
+	bp_gens: &'a BulletproofGens,
+	pc_gens: &'a PedersenGens,
+	n: usize,
+	constraints: Vec<LinearCombination>,
+	a_L: Vec<Scalar>,
+	a_R: Vec<Scalar>,
+	a_O: Vec<Scalar>,
+	v: Vec<Scalar>,
+	v_blinding: Vec<Scalar>,
+	V: Vec<CompressedRistretto>,
+}
+
+impl<'a> PartyAwaitingPosition<'a> {
+	/// Assigns a position in the aggregated proof to this party,
+	/// allowing the party to commit to the wires of their circuit.
+	#[cfg(feature = "std")]
+	pub fn assign_position(self, j: usize) -> Result<(PartyAwaitingWireChallenge<'a>, WireCommitment), MPCError> {
+		self.assign_position_with_rng(j, &mut thread_rng())
+	}
+
+	/// Assigns a position in the aggregated proof to this party,
+	/// allowing the party to commit to the wires of their circuit.
+	pub fn assign_position_with_rng<T: RngCore + CryptoRng>(
+		mut self,
+		j: usize,
+		rng: &mut T,
+	) -> Result<(PartyAwaitingWireChallenge<'a>, WireCommitment), MPCError> {
+		if self.bp_gens.party_capacity <= j {
+			return Err(MPCError::InvalidGeneratorsLength {
+				required: j + 1,
+				actual: self.bp_gens.party_capacity,
+			});
+		}
+
+		let n = self.n;
+		let bp_share = self.bp_gens.share(j);
+
+		let i_blinding = Scalar::random(rng);
+		let o_blinding = Scalar::random(rng);
+		let s_blinding = Scalar::random(rng);
+
+		let s_L: Vec<Scalar> = (0..n).map(|_| Scalar::random(rng)).collect();
+		let s_R: Vec<Scalar> = (0..n).map(|_| Scalar::random(rng)).collect();
+
+		// A_I = <a_L, G> + <a_R, H> + i_blinding * B_blinding
+		let A_I = RistrettoPoint::multiscalar_mul(
+			iter::once(&i_blinding).chain(self.a_L.iter()).chain(self.a_R.iter()),
+			iter::once(&self.pc_gens.B_blinding)
+				.chain(bp_share.G(n))
+				.chain(bp_share.H(n)),
+		);
+		// A_O = <a_O, G> + o_blinding * B_blinding
+		let A_O = RistrettoPoint::multiscalar_mul(
+			iter::once(&o_blinding).chain(self.a_O.iter()),
+			iter::once(&self.pc_gens.B_blinding).chain(bp_share.G(n)),
+		);
+		// S = <s_L, G> + <s_R, H> + s_blinding * B_blinding
+		let S = RistrettoPoint::multiscalar_mul(
+			iter::once(&s_blinding).chain(s_L.iter()).chain(s_R.iter()),
+			iter::once(&self.pc_gens.B_blinding)
+				.chain(bp_share.G(n))
+				.chain(bp_share.H(n)),
+		);
+
+		let wire_commitment = WireCommitment {
+			V_j: self.V.clone(),
+			num_constraints: self.constraints.len(),
+			A_I_j: A_I,
+			A_O_j: A_O,
+			S_j: S,
+		};
+		let next_state = PartyAwaitingWireChallenge {
+			pc_gens: self.pc_gens,
+			n,
+			j,
+			constraints: core::mem::replace(&mut self.constraints, Vec::new()),
+			a_L: core::mem::replace(&mut self.a_L, Vec::new()),
+			a_R: core::mem::replace(&mut self.a_R, Vec::new()),
+			a_O: core::mem::replace(&mut self.a_O, Vec::new()),
+			v_blinding: core::mem::replace(&mut self.v_blinding, Vec::new()),
+			i_blinding,
+			o_blinding,
+			s_blinding,
+			s_L,
+			s_R,
+		};
+		Ok((next_state, wire_commitment))
+	}
+}
+
+impl<'a> PartyAwaitingPosition<'a> {
+	/// Overwrites the value openings, blinding factors and assignments
+	/// with zeroes.
+	fn clear_witness(&mut self) {
+		util::clear_scalars(&mut [
+			&mut self.v,
+			&mut self.v_blinding,
+			&mut self.a_L,
+			&mut self.a_R,
+			&mut self.a_O,
+		]);
+	}
+}
+
+/// Overwrite secrets with null bytes when they go out of scope.
+impl<'a> Drop for PartyAwaitingPosition<'a> {
+	fn drop(&mut self) {
+		self.clear_witness();
+	}
+}
+
+/// Wipes the witness of the party, which is also done when it is dropped.
+#[cfg(feature = "zeroize")]
+impl<'a> Zeroize for PartyAwaitingPosition<'a> {
+	fn zeroize(&mut self) {
+		self.clear_witness();
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl<'a> ZeroizeOnDrop for PartyAwaitingPosition<'a> {}
+
+/// A party which has committed to the wires of their circuit
+/// and is waiting for the aggregated challenges from the dealer.
+pub struct PartyAwaitingWireChallenge<'a> {
+	pc_gens: &'a PedersenGens,
+	n: usize,
+	j: usize,
+	constraints: Vec<LinearCombination>,
+	a_L: Vec<Scalar>,
+	a_R: Vec<Scalar>,
+	a_O: Vec<Scalar>,
+	v_blinding: Vec<Scalar>,
+	i_blinding: Scalar,
+	o_blinding: Scalar,
+	s_blinding: Scalar,
+	s_L: Vec<Scalar>,
+	s_R: Vec<Scalar>,
+}
+
+impl<'a> PartyAwaitingWireChallenge<'a> {
+	/// Receive a [`WireChallenge`] from the dealer and use it to
+	/// compute commitments to the party's polynomial coefficients.
+	#[cfg(feature = "std")]
+	pub fn apply_challenge(self, wc: &WireChallenge) -> Result<(PartyAwaitingPolyChallenge, PolyCommitment), MPCError> {
+		self.apply_challenge_with_rng(wc, &mut thread_rng())
+	}
+
+	/// Receive a [`WireChallenge`] from the dealer and use it to
+	/// compute commitments to the party's polynomial coefficients.
+	///
+	/// The party's multipliers occupy the positions `j * n..(j + 1) * n`
+	/// of the aggregated circuit, and its constraints follow the
+	/// constraints of the parties before it.
+	pub fn apply_challenge_with_rng<T: RngCore + CryptoRng>(
+		self,
+		wc: &WireChallenge,
+		rng: &mut T,
+	) -> Result<(PartyAwaitingPolyChallenge, PolyCommitment), MPCError> {
+		let constraint_offset = *wc.constraint_offsets.get(self.j).ok_or(MPCError::MaliciousDealer)?;
+
+		let n = self.n;
+		let offset_y = util::scalar_exp_vartime(&wc.y, (self.j * n) as u64);
+		let offset_y_inv = offset_y.invert();
+
+		let (wL, wR, wO, wV) =
+			flattened_constraints(&self.constraints, n, self.v_blinding.len(), &wc.z, constraint_offset);
+
+		let mut l_poly = util::VecPoly3::zero(n);
+		let mut r_poly = util::VecPoly3::zero(n);
+
+		let mut exp_y = offset_y; // start at y^(j*n)
+		let mut exp_y_inv = offset_y_inv;
+		let y_inv = wc.y.invert();
+		for i in 0..n {
+			// l_poly.1 = a_L + y^-n * (z * z^Q * W_R)
+			l_poly.1[i] = self.a_L[i] + exp_y_inv * wR[i];
+			// l_poly.2 = a_O
+			l_poly.2[i] = self.a_O[i];
+			// l_poly.3 = s_L
+			l_poly.3[i] = self.s_L[i];
+			// r_poly.0 = (z * z^Q * W_O) - y^n
+			r_poly.0[i] = wO[i] - exp_y;
+			// r_poly.1 = y^n * a_R + (z * z^Q * W_L)
+			r_poly.1[i] = exp_y * self.a_R[i] + wL[i];
+			// r_poly.3 = y^n * s_R
+			r_poly.3[i] = exp_y * self.s_R[i];
+
+			exp_y *= wc.y; // y^i -> y^(i+1)
+			exp_y_inv *= y_inv;
+		}
+
+		let t_poly = util::VecPoly3::special_inner_product(&l_poly, &r_poly);
+
+		let t_blinding_poly = util::Poly6 {
+			t1: Scalar::random(rng),
+			// t_2_blinding = <z*z^Q, W_V * v_blinding>
+			t2: wV
+				.iter()
+				.zip(self.v_blinding.iter())
+				.map(|(c, v_blinding)| c * v_blinding)
+				.sum(),
+			t3: Scalar::random(rng),
+			t4: Scalar::random(rng),
+			t5: Scalar::random(rng),
+			t6: Scalar::random(rng),
+		};
+
+		let poly_commitment = PolyCommitment {
+			T_1_j: self.pc_gens.commit(t_poly.t1, t_blinding_poly.t1),
+			T_3_j: self.pc_gens.commit(t_poly.t3, t_blinding_poly.t3),
+			T_4_j: self.pc_gens.commit(t_poly.t4, t_blinding_poly.t4),
+			T_5_j: self.pc_gens.commit(t_poly.t5, t_blinding_poly.t5),
+			T_6_j: self.pc_gens.commit(t_poly.t6, t_blinding_poly.t6),
+		};
+
+		let papc = PartyAwaitingPolyChallenge {
+			i_blinding: self.i_blinding,
+			o_blinding: self.o_blinding,
+			s_blinding: self.s_blinding,
+			l_poly,
+			r_poly,
+			t_poly,
+			t_blinding_poly,
+		};
+
+		Ok((papc, poly_commitment))
+	}
+}
+
+impl<'a> PartyAwaitingWireChallenge<'a> {
+	/// Overwrites the blinding factors and assignments with zeroes.
+	fn clear_witness(&mut self) {
+		self.i_blinding.clear();
+		self.o_blinding.clear();
+		self.s_blinding.clear();
+
+		util::clear_scalars(&mut [
+			&mut self.v_blinding,
+			&mut self.a_L,
+			&mut self.a_R,
+			&mut self.a_O,
+			&mut self.s_L,
+			&mut self.s_R,
+		]);
+	}
+}
+
+/// Overwrite secrets with null bytes when they go out of scope.
+impl<'a> Drop for PartyAwaitingWireChallenge<'a> {
+	fn drop(&mut self) {
+		self.clear_witness();
+	}
+}
+
+/// Wipes the witness of the party, which is also done when it is dropped.
+#[cfg(feature = "zeroize")]
+impl<'a> Zeroize for PartyAwaitingWireChallenge<'a> {
+	fn zeroize(&mut self) {
+		self.clear_witness();
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl<'a> ZeroizeOnDrop for PartyAwaitingWireChallenge<'a> {}
+
+/// A party which has committed to their polynomial coefficents
+/// and is waiting for the polynomial challenge from the dealer.
+pub struct PartyAwaitingPolyChallenge {
+	i_blinding: Scalar,
+	o_blinding: Scalar,
+	s_blinding: Scalar,
+	l_poly: util::VecPoly3,
+	r_poly: util::VecPoly3,
+	t_poly: util::Poly6,
+	t_blinding_poly: util::Poly6,
+}
+
+impl PartyAwaitingPolyChallenge {
+	/// Receive a [`PolyChallenge`] from the dealer and compute the
+	/// party's proof share.
+	pub fn apply_challenge(self, pc: &PolyChallenge) -> Result<ProofShare, MPCError> {
+		// Prevent a malicious dealer from annihilating the blinding
+		// factors by supplying a zero challenge.
+		if pc.x == Scalar::zero() {
+			return Err(MPCError::MaliciousDealer);
+		}
+
+		let x = pc.x;
+		Ok(ProofShare {
+			t_x: self.t_poly.eval(x),
+			t_x_blinding: self.t_blinding_poly.eval(x),
+			e_blinding: x * (self.i_blinding + x * (self.o_blinding + x * self.s_blinding)),
+			l_vec: self.l_poly.eval(x),
+			r_vec: self.r_poly.eval(x),
+		})
+	}
+}
+
+/// Overwrite secrets with null bytes when they go out of scope.
+impl Drop for PartyAwaitingPolyChallenge {
+	fn drop(&mut self) {
+		self.i_blinding.clear();
+		self.o_blinding.clear();
+		self.s_blinding.clear();
+
+		// Note: polynomials r_poly, l_poly, t_poly and t_blinding_poly
+		// are cleared within their own Drop impls.
+	}
+}
+
+/// Wipes the blinding factors and polynomials of the party, which is also
+/// done when it is dropped.
+#[cfg(feature = "zeroize")]
+impl Zeroize for PartyAwaitingPolyChallenge {
+	fn zeroize(&mut self) {
+		self.i_blinding.clear();
+		self.o_blinding.clear();
+		self.s_blinding.clear();
+		self.l_poly.clear();
+		self.r_poly.clear();
+		self.t_poly.clear();
+		self.t_blinding_poly.clear();
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for PartyAwaitingPolyChallenge {}
+
+/// Flattens the constraints of a party like the prover of a single-party
+/// proof, weighting them with the powers of the challenge `z` which follow
+/// the first `offset` constraints of the aggregated circuit.
+fn flattened_constraints(
+	constraints: &[LinearCombination],
+	n: usize,
+	m: usize,
+	z: &Scalar,
+	offset: usize,
+) -> (Vec<Scalar>, Vec<Scalar>, Vec<Scalar>, Vec<Scalar>) {
+	let mut wL = vec![Scalar::zero(); n];
+	let mut wR = vec![Scalar::zero(); n];
+	let mut wO = vec![Scalar::zero(); n];
+	let mut wV = vec![Scalar::zero(); m];
+
+	let mut exp_z = util::scalar_exp_vartime(z, offset as u64) * z;
+	for lc in constraints.iter() {
+		for (var, coeff) in &lc.terms {
+			match var {
+				Variable::MultiplierLeft(i) => {
+					wL[*i] += exp_z * coeff;
+				}
+				Variable::MultiplierRight(i) => {
+					wR[*i] += exp_z * coeff;
+				}
+				Variable::MultiplierOutput(i) => {
+					wO[*i] += exp_z * coeff;
+				}
+				Variable::Committed(i) => {
+					wV[*i] -= exp_z * coeff;
+				}
+				Variable::One() => {
+					// The prover doesn't need to handle constant terms
+				}
+			}
+		}
+		exp_z *= z;
+	}
+
+	(wL, wR, wO, wV)
+}
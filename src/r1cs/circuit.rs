@@ -0,0 +1,302 @@
+#![allow(non_snake_case)]
+//! Canonical description of a synthesized constraint system.
+
+use super::{
+	observer::Observer,
+	verifier::{decompress_commitments, pre_verify_committed_proof},
+	LinearCombination, PreVerified, R1CSProof, Variable, Verifier,
+};
+use crate::{
+	errors::R1CSError,
+	generators::PedersenGens,
+	transcript::{append_u64, TranscriptProtocol},
+};
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use curve25519_dalek::{ristretto::CompressedRistretto, scalar::Scalar, traits::Identity};
+use merlin::Transcript;
+use rand_core::{CryptoRng, RngCore};
+
+/// The constraints of a circuit, synthesized once and reused for every
+/// proof of the circuit.
+///
+/// A [`Verifier`] re-runs the gadget code for every proof.  A
+/// `CircuitDescription` keeps the result of the synthesis: the sparse
+/// weights of every constraint on the multipliers, the committed variables
+/// and the constant, together with the number of multipliers.  Verifying
+/// against a description with [`Verifier::verify_compiled`] skips the
+/// gadget code altogether, and a [`Prover`](::r1cs::Prover) can check
+/// with [`Prover::check_circuit`](::r1cs::Prover::check_circuit) that it
+/// synthesized the same circuit.
+///
+/// Only unlabeled circuits without randomized constraints or public inputs
+/// can be described, and the gadget code must not append to the transcript
+/// itself.  Proofs under bases other than the default ones must be created
+/// with [`Prover::new_with_gens`](::r1cs::Prover::new_with_gens), as the
+/// bases are bound to the transcript when verifying.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CircuitDescription {
+	/// The constraints of the circuit.
+	constraints: Vec<LinearCombination>,
+	/// Number of multipliers.
+	num_multipliers: usize,
+	/// Number of committed high-level variables.
+	num_committed: usize,
+}
+
+impl CircuitDescription {
+	/// Synthesizes the circuit defined by `gadget`.
+	///
+	/// The `gadget` closure receives a verifier and the variables for
+	/// `num_commitments` high-level commitments, and must build the same
+	/// constraints as the prover's gadget code.
+	pub fn from_circuit<F>(num_commitments: usize, gadget: F) -> Result<Self, R1CSError>
+	where
+		F: FnOnce(&mut Verifier, Vec<Variable>) -> Result<(), R1CSError>,
+	{
+		let mut transcript = Transcript::new(b"CircuitDescription");
+		let mut verifier = Verifier::new(&mut transcript);
+
+		// The commitments are only known at verification time,
+		// so placeholders are used to allocate the variables.
+		let vars = (0..num_commitments)
+			.map(|_| verifier.commit(CompressedRistretto::identity()))
+			.collect();
+		gadget(&mut verifier, vars)?;
+
+		let (constraints, num_multipliers) = verifier.into_constraints()?;
+		Ok(CircuitDescription {
+			constraints,
+			num_multipliers,
+			num_committed: num_commitments,
+		})
+	}
+
+	/// Returns the number of constraints in the circuit.
+	pub fn num_constraints(&self) -> usize {
+		self.constraints.len()
+	}
+
+	/// Returns the number of multipliers in the circuit.
+	pub fn num_multipliers(&self) -> usize {
+		self.num_multipliers
+	}
+
+	/// Returns the number of high-level commitments the circuit expects.
+	pub fn num_committed(&self) -> usize {
+		self.num_committed
+	}
+
+	/// Returns the canonical digest of the circuit.
+	///
+	/// See [`Prover::circuit_hash`](::r1cs::Prover::circuit_hash).
+	pub fn circuit_hash(&self) -> [u8; 32] {
+		circuit_hash(self.num_multipliers, self.num_committed, &self.constraints)
+	}
+
+	/// Serializes the description into a byte array.
+	///
+	/// # Layout
+	///
+	/// The layout of the encoding is:
+	/// * the number of multipliers, of committed variables and of constraints,
+	///   as little-endian `u64`s,
+	/// * for each constraint, its number of terms as a little-endian `u64`,
+	///   followed by the terms, each a one-byte kind tag and the index of the
+	///   variable as a little-endian `u64`, and its 32-byte coefficient.
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut buf = Vec::new();
+		buf.extend_from_slice(&(self.num_multipliers as u64).to_le_bytes());
+		buf.extend_from_slice(&(self.num_committed as u64).to_le_bytes());
+		buf.extend_from_slice(&(self.constraints.len() as u64).to_le_bytes());
+		for lc in self.constraints.iter() {
+			buf.extend_from_slice(&(lc.terms.len() as u64).to_le_bytes());
+			for (var, coeff) in lc.terms.iter() {
+				buf.extend_from_slice(&encode_variable(var));
+				buf.extend_from_slice(coeff.as_bytes());
+			}
+		}
+		buf
+	}
+
+	/// Deserializes the description from a byte slice in the
+	/// [`CircuitDescription::to_bytes`] format.
+	///
+	/// Returns an error if the encoding is malformed, if a coefficient is
+	/// not canonical, or if a term refers to a variable the circuit does
+	/// not have.
+	pub fn from_bytes(slice: &[u8]) -> Result<CircuitDescription, R1CSError> {
+		let mut reader = Reader(slice);
+		let num_multipliers = reader.read_len()?;
+		let num_committed = reader.read_len()?;
+		let num_constraints = reader.read_len()?;
+
+		// Every constraint takes at least the 8 bytes of its length.
+		if num_constraints > reader.0.len() / 8 {
+			return Err(R1CSError::FormatError);
+		}
+		let mut constraints = Vec::with_capacity(num_constraints);
+		for _ in 0..num_constraints {
+			let num_terms = reader.read_len()?;
+			if num_terms > reader.0.len() / TERM_SIZE {
+				return Err(R1CSError::FormatError);
+			}
+			let terms = (0..num_terms)
+				.map(|_| {
+					let var = decode_variable(reader.read(9)?, num_multipliers, num_committed)?;
+					let coeff = Scalar::from_canonical_bytes(reader.read(32)?.try_into().unwrap())
+						.ok_or(R1CSError::FormatError)?;
+					Ok((var, coeff))
+				})
+				.collect::<Result<LinearCombination, R1CSError>>()?;
+			constraints.push(terms);
+		}
+		if !reader.0.is_empty() {
+			return Err(R1CSError::FormatError);
+		}
+
+		Ok(CircuitDescription {
+			constraints,
+			num_multipliers,
+			num_committed,
+		})
+	}
+
+	/// Replays the messages a [`Verifier`] created with
+	/// [`Verifier::new_with_gens`] for the `pc_gens` appends to the
+	/// `transcript` for the `commitments`, and performs the cheap part of
+	/// the verification of the `proof`.
+	pub(super) fn pre_verify<T: RngCore + CryptoRng>(
+		&self,
+		transcript: &mut Transcript,
+		pc_gens: &PedersenGens,
+		commitments: &[CompressedRistretto],
+		proof: &R1CSProof,
+		prng: &mut T,
+	) -> Result<PreVerified, R1CSError> {
+		if commitments.len() != self.num_committed {
+			return Err(R1CSError::VerificationError);
+		}
+
+		// Replay the messages appended by `Verifier::new_with_gens` and
+		// `Verifier::commit`.
+		transcript.r1cs_domain_sep(b"");
+		if !pc_gens.is_default() {
+			transcript.r1cs_pedersen_gens(&pc_gens.B.compress(), &pc_gens.B_blinding.compress());
+		}
+		for V in commitments.iter() {
+			transcript.append_point(b"V", V);
+		}
+		append_u64(transcript, b"m", commitments.len() as u64);
+
+		transcript.validate_and_append_point(b"A_I1", &proof.A_I1)?;
+		transcript.validate_and_append_point(b"A_O1", &proof.A_O1)?;
+		transcript.validate_and_append_point(b"S1", &proof.S1)?;
+
+		// A described circuit has no randomized constraints.
+		transcript.r1cs_1phase_domain_sep();
+
+		pre_verify_committed_proof(
+			transcript,
+			proof,
+			&decompress_commitments(commitments)?,
+			&self.constraints,
+			self.num_multipliers,
+			self.num_multipliers,
+			prng,
+			Observer::default(),
+		)
+	}
+}
+
+/// Computes a canonical 32-byte digest of a synthesized circuit.
+///
+/// The encoding covers the number of constraints, multipliers and
+/// committed variables, followed by each constraint's terms sorted by
+/// variable (and then by coefficient).  Assignments are not part of the
+/// encoding, so the prover and the verifier obtain the same digest when
+/// the constraint system is built by the same gadget code.
+pub(crate) fn circuit_hash(
+	num_multipliers: usize,
+	num_committed: usize,
+	constraints: &[LinearCombination],
+) -> [u8; 32] {
+	let mut t = Transcript::new(b"r1cs circuit hash v1");
+	t.append_u64(b"constraints", constraints.len() as u64);
+	t.append_u64(b"multipliers", num_multipliers as u64);
+	t.append_u64(b"committed", num_committed as u64);
+
+	for lc in constraints {
+		let mut terms: Vec<_> = lc.terms.iter().map(|(var, coeff)| (*var, *coeff.as_bytes())).collect();
+		terms.sort();
+
+		t.append_u64(b"terms", terms.len() as u64);
+		for (var, coeff) in terms.iter() {
+			t.append_message(b"var", &encode_variable(var));
+			t.append_message(b"coeff", coeff);
+		}
+	}
+
+	let mut digest = [0u8; 32];
+	t.challenge_bytes(b"digest", &mut digest);
+	digest
+}
+
+/// The size of the encoding of a term: a variable and its coefficient.
+const TERM_SIZE: usize = 9 + 32;
+
+/// Reads the encoding of a [`CircuitDescription`].
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+	/// Returns the next `len` bytes.
+	fn read(&mut self, len: usize) -> Result<&'a [u8], R1CSError> {
+		if self.0.len() < len {
+			return Err(R1CSError::FormatError);
+		}
+		let (bytes, rest) = self.0.split_at(len);
+		self.0 = rest;
+		Ok(bytes)
+	}
+
+	/// Returns the next little-endian `u64` as a length.
+	fn read_len(&mut self) -> Result<usize, R1CSError> {
+		let len = u64::from_le_bytes(self.read(8)?.try_into().unwrap());
+		len.try_into().map_err(|_| R1CSError::FormatError)
+	}
+}
+
+/// Decodes a variable encoded by [`encode_variable`], checking that the
+/// circuit has it.
+fn decode_variable(buf: &[u8], num_multipliers: usize, num_committed: usize) -> Result<Variable, R1CSError> {
+	let index = u64::from_le_bytes(buf[1..].try_into().unwrap());
+	let index: usize = index.try_into().map_err(|_| R1CSError::FormatError)?;
+	let (var, bound) = match buf[0] {
+		0 => (Variable::Committed(index), num_committed),
+		1 => (Variable::MultiplierLeft(index), num_multipliers),
+		2 => (Variable::MultiplierRight(index), num_multipliers),
+		3 => (Variable::MultiplierOutput(index), num_multipliers),
+		4 if index == 0 => (Variable::One(), 1),
+		_ => return Err(R1CSError::FormatError),
+	};
+	if index >= bound {
+		return Err(R1CSError::FormatError);
+	}
+	Ok(var)
+}
+
+/// Encodes a variable as a one-byte kind tag followed by its index as a
+/// little-endian `u64`.
+fn encode_variable(var: &Variable) -> [u8; 9] {
+	let (tag, index) = match var {
+		Variable::Committed(i) => (0u8, *i),
+		Variable::MultiplierLeft(i) => (1u8, *i),
+		Variable::MultiplierRight(i) => (2u8, *i),
+		Variable::MultiplierOutput(i) => (3u8, *i),
+		Variable::One() => (4u8, 0),
+	};
+	let mut buf = [0u8; 9];
+	buf[0] = tag;
+	buf[1..].copy_from_slice(&(index as u64).to_le_bytes());
+	buf
+}
@@ -1,7 +1,9 @@
 //! Definition of the constraint system trait.
 
 use super::{LinearCombination, R1CSError, Variable};
-use curve25519_dalek::scalar::Scalar;
+use crate::transcript::TranscriptProtocol;
+use alloc::vec::Vec;
+use curve25519_dalek::{ristretto::CompressedRistretto, scalar::Scalar};
 use merlin::Transcript;
 
 /// The interface for a constraint system, abstracting over the prover
@@ -49,6 +51,10 @@ pub trait ConstraintSystem {
 	/// half-assigned multiplier has the `right` assigned to zero and all its
 	/// variables committed.
 	///
+	/// Unlike the variables of [`Prover::commit`](::r1cs::Prover::commit),
+	/// the variable has no Pedersen commitment of its own, so it suits
+	/// scratch witness values such as inverses or bits.
+	///
 	/// Returns unconstrained `Variable` for use in further constraints.
 	fn allocate(&mut self, assignment: Option<Scalar>) -> Result<Variable, R1CSError>;
 
@@ -73,6 +79,20 @@ pub trait ConstraintSystem {
 	/// ```
 	fn constrain(&mut self, lc: LinearCombination);
 
+	/// Enforce the explicit constraint that
+	/// ```text
+	/// lc = 0
+	/// ```
+	/// tagging it with a `label` which names the constraint in the
+	/// diagnostics of the [`Prover`](::r1cs::Prover).
+	///
+	/// The label is not absorbed into the transcript, so the proof is the
+	/// same as with [`ConstraintSystem::constrain`].
+	fn constrain_labeled(&mut self, lc: LinearCombination, label: &'static str) {
+		let _ = label;
+		self.constrain(lc)
+	}
+
 	/// Evaluate a linear combination. Only prover can evaluate and return the
 	/// scalar value, verifier returns None.
 	fn evaluate_lc(&self, lc: &LinearCombination) -> Option<Scalar>;
@@ -82,6 +102,105 @@ pub trait ConstraintSystem {
 	/// When allocating right variable, return right variable and output
 	/// variable.
 	fn allocate_single(&mut self, assignment: Option<Scalar>) -> Result<(Variable, Option<Variable>), R1CSError>;
+
+	/// Binds the proof to the public input `bytes`, such as a Merkle root
+	/// or a recipient address, by appending it to the transcript with the
+	/// given `label`.
+	///
+	/// Public inputs are part of the statement without being committed
+	/// variables: the prover and the verifier must commit the same inputs
+	/// in the same order, and a proof does not verify against other
+	/// inputs.
+	///
+	/// # Errors
+	///
+	/// Returns [`R1CSError::PublicInputAfterChallenge`] when called in
+	/// randomized constraints, since the challenges drawn before would not
+	/// be bound to the input.
+	fn commit_public_bytes(&mut self, label: &'static [u8], bytes: &[u8]) -> Result<(), R1CSError>;
+
+	/// Binds the proof to the public input `scalar`, like
+	/// [`ConstraintSystem::commit_public_bytes`] with its canonical
+	/// encoding.
+	fn commit_public_scalar(&mut self, label: &'static [u8], scalar: &Scalar) -> Result<(), R1CSError> {
+		self.commit_public_bytes(label, scalar.as_bytes())
+	}
+
+	/// Binds the proof to an auxiliary `point`, such as an ElGamal
+	/// ciphertext or the commitment of a sigma protocol, like
+	/// [`ConstraintSystem::commit_public_bytes`] with its compressed
+	/// encoding.
+	///
+	/// The point is appended to the transcript in the order of the calls,
+	/// interleaved with the high-level commitments, so that the challenges
+	/// of the proof are bound to it.
+	fn commit_point(&mut self, label: &'static [u8], point: &CompressedRistretto) -> Result<(), R1CSError> {
+		self.commit_public_bytes(label, point.as_bytes())
+	}
+
+	/// Enters the namespace `label`, until the matching
+	/// [`ConstraintSystem::pop_namespace`].
+	///
+	/// Namespaces bind the challenges of randomized constraints to the
+	/// gadget generating them, so that gadgets using the same challenge
+	/// labels can be composed safely.  In the randomization phase, entering
+	/// and leaving a namespace appends its label to the transcript, and a
+	/// callback given to
+	/// [`RandomizableConstraintSystem::specify_randomized_constraints`] is
+	/// invoked within the namespaces entered when it was specified.  Before
+	/// the randomization phase, namespaces do not change the transcript.
+	///
+	/// Prefer [`ConstraintSystem::scoped`], which keeps the calls balanced.
+	fn push_namespace(&mut self, label: &'static [u8]);
+
+	/// Leaves the innermost namespace entered with
+	/// [`ConstraintSystem::push_namespace`].
+	///
+	/// # Panics
+	///
+	/// Panics if no namespace was entered.
+	fn pop_namespace(&mut self);
+
+	/// Calls `f` within the namespace `label`, and returns its result.
+	///
+	/// See [`ConstraintSystem::push_namespace`].
+	///
+	/// ### Usage
+	///
+	/// ```text
+	/// cs.scoped(b"merkle-gadget", |cs| merkle_gadget(cs, leaf, path))?;
+	/// ```
+	fn scoped<T, F>(&mut self, label: &'static [u8], f: F) -> T
+	where
+		Self: Sized,
+		F: FnOnce(&mut Self) -> T,
+	{
+		self.push_namespace(label);
+		let result = f(self);
+		self.pop_namespace();
+		result
+	}
+}
+
+/// Invokes a randomized constraints `callback` within the `namespaces`
+/// entered when it was specified, outermost first.
+pub(crate) fn call_within_namespaces<CS, F>(
+	cs: &mut CS,
+	namespaces: &[&'static [u8]],
+	callback: F,
+) -> Result<(), R1CSError>
+where
+	CS: ConstraintSystem,
+	F: FnOnce(&mut CS) -> Result<(), R1CSError>,
+{
+	for label in namespaces.iter() {
+		cs.push_namespace(label);
+	}
+	let result = callback(cs);
+	for _ in namespaces.iter() {
+		cs.pop_namespace();
+	}
+	result
 }
 
 /// An extension to the constraint system trait that permits randomized
@@ -105,6 +224,9 @@ pub trait RandomizableConstraintSystem: ConstraintSystem {
 	/// the callback is invoked immediately and its result is return from this
 	/// method.
 	///
+	/// The callback is invoked exactly once, so it may move its captures,
+	/// e.g. the variables it constrains, out of its environment.
+	///
 	/// ### Usage
 	///
 	/// Inside the closure you can generate one or more challenges using
@@ -118,7 +240,7 @@ pub trait RandomizableConstraintSystem: ConstraintSystem {
 	/// ```
 	fn specify_randomized_constraints<F>(&mut self, callback: F) -> Result<(), R1CSError>
 	where
-		F: 'static + Fn(&mut Self::RandomizedCS) -> Result<(), R1CSError>;
+		F: 'static + FnOnce(&mut Self::RandomizedCS) -> Result<(), R1CSError>;
 }
 
 /// Represents a constraint system in the second phase:
@@ -145,4 +267,29 @@ pub trait RandomizedConstraintSystem: ConstraintSystem {
 	/// })
 	/// ```
 	fn challenge_scalar(&mut self, label: &'static [u8]) -> Scalar;
+
+	/// Fills `dest` with challenge bytes.
+	///
+	/// Like [`RandomizedConstraintSystem::challenge_scalar`], every call
+	/// advances the transcript, so repeated calls with the same `label`
+	/// yield independent challenges.
+	fn challenge_bytes(&mut self, label: &'static [u8], dest: &mut [u8]);
+
+	/// Generates `n` challenge scalars, drawn one after the other with the
+	/// same `label`.
+	///
+	/// The number `n` is appended to the transcript before the challenges
+	/// are drawn, so that a prover and a verifier asking for vectors of
+	/// different lengths derive different challenges, and the proof fails.
+	///
+	/// ```text
+	/// cs.specify_randomized_constraints(move |cs| {
+	///     let weights = cs.challenge_scalars(b"shuffle weights", k);
+	///     // ...
+	/// })
+	/// ```
+	fn challenge_scalars(&mut self, label: &'static [u8], n: usize) -> Vec<Scalar> {
+		self.transcript().r1cs_challenge_count(label, n as u64);
+		(0..n).map(|_| self.challenge_scalar(label)).collect()
+	}
 }
@@ -0,0 +1,713 @@
+//! Gadgets for common statements about committed values.
+
+use alloc::vec::Vec;
+use core::iter;
+use curve25519_dalek::{
+	ristretto::{CompressedRistretto, RistrettoPoint},
+	scalar::Scalar,
+	traits::IsIdentity,
+};
+use merlin::Transcript;
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(feature = "std")]
+use rand::thread_rng;
+
+#[cfg(feature = "serde")]
+use serde::de::Visitor;
+#[cfg(feature = "serde")]
+use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{
+	CircuitDescription, ConstraintSystem, LinearCombination, Prover, R1CSError, R1CSProof, Variable, Verifier,
+};
+use crate::generators::{BulletproofGens, PedersenGens};
+
+/// A variable together with its assignment.
+///
+/// The assignment is only known to the prover, and is `None` when the
+/// constraint system is built by the verifier.
+#[derive(Copy, Clone, Debug)]
+pub struct AllocatedScalar {
+	/// The variable in the constraint system.
+	pub variable: Variable,
+	/// The value of the variable, if known.
+	pub assignment: Option<Scalar>,
+}
+
+impl AllocatedScalar {
+	/// Returns the assignment of the variable, or a
+	/// [`R1CSError::MissingAssignment`] naming the variable and the
+	/// `context` if it is not known.
+	pub fn required(&self, context: &'static str) -> Result<Scalar, R1CSError> {
+		self.assignment.ok_or(R1CSError::MissingAssignment {
+			variable: Some(self.variable),
+			context: Some(context),
+		})
+	}
+}
+
+/// An assignment which may be missing, like the values gadget code
+/// receives on the verifier's side.
+pub trait Assignment<S> {
+	/// Returns the assigned value, or a [`R1CSError::MissingAssignment`]
+	/// with the `context` describing the value if it is missing.
+	fn required(self, context: &'static str) -> Result<S, R1CSError>;
+}
+
+impl<S> Assignment<S> for Option<S> {
+	fn required(self, context: &'static str) -> Result<S, R1CSError> {
+		self.ok_or(R1CSError::MissingAssignment {
+			variable: None,
+			context: Some(context),
+		})
+	}
+}
+
+/// A circuit whose constraints are built by a single implementation for
+/// both the prover and the verifier.
+///
+/// Gadget code usually builds the same constraints twice, once with the
+/// assignments for the prover and once without for the verifier, and any
+/// divergence between the two only surfaces as a
+/// [`R1CSError::VerificationError`].  A `Gadget` receives its inputs as
+/// [`AllocatedScalar`]s, whose assignments are `None` on the verifier's
+/// side, so that [`prove_gadget`] and [`verify_gadget`] drive both sides
+/// from the same code.
+pub trait Gadget {
+	/// Builds the constraints of the gadget over the `inputs`, and returns
+	/// the variables it allocates as outputs.
+	fn assemble<CS: ConstraintSystem>(
+		&self,
+		cs: &mut CS,
+		inputs: &[AllocatedScalar],
+	) -> Result<Vec<AllocatedScalar>, R1CSError>;
+}
+
+/// Commits to the `values` and proves that they satisfy the `gadget`.
+///
+/// This is a convenience wrapper around [`prove_gadget_with_rng`],
+/// passing in a threadsafe RNG.
+#[cfg(feature = "std")]
+pub fn prove_gadget<G: Gadget>(
+	gadget: &G,
+	pc_gens: &PedersenGens,
+	bp_gens: &BulletproofGens,
+	transcript: &mut Transcript,
+	values: &[Scalar],
+) -> Result<(R1CSProof, Vec<CompressedRistretto>), R1CSError> {
+	prove_gadget_with_rng(gadget, pc_gens, bp_gens, transcript, values, &mut thread_rng())
+}
+
+/// Commits to the `values` with blinding factors drawn from `prng`, and
+/// proves that they satisfy the `gadget`.
+///
+/// Returns the proof together with the commitments to the `values`, which
+/// the verifier passes to [`verify_gadget`].  Bases other than the default
+/// ones in `pc_gens` are bound to the transcript, as with
+/// [`Prover::new_with_gens`].
+pub fn prove_gadget_with_rng<G: Gadget, T: RngCore + CryptoRng>(
+	gadget: &G,
+	pc_gens: &PedersenGens,
+	bp_gens: &BulletproofGens,
+	transcript: &mut Transcript,
+	values: &[Scalar],
+	prng: &mut T,
+) -> Result<(R1CSProof, Vec<CompressedRistretto>), R1CSError> {
+	let mut prover = Prover::new_with_gens(pc_gens, transcript);
+
+	let (commitments, inputs): (Vec<_>, Vec<_>) = values
+		.iter()
+		.map(|value| {
+			let (commitment, variable) = prover.commit(*value, Scalar::random(prng));
+			let input = AllocatedScalar {
+				variable,
+				assignment: Some(*value),
+			};
+			(commitment, input)
+		})
+		.unzip();
+	gadget.assemble(&mut prover, &inputs)?;
+
+	let proof = prover.prove_with_rng(bp_gens, prng)?;
+	Ok((proof, commitments))
+}
+
+/// Verifies that the `proof` shows the values of the `commitments` satisfy
+/// the `gadget`.
+///
+/// This is a convenience wrapper around [`verify_gadget_with_rng`],
+/// passing in a threadsafe RNG.
+#[cfg(feature = "std")]
+pub fn verify_gadget<G: Gadget>(
+	gadget: &G,
+	pc_gens: &PedersenGens,
+	bp_gens: &BulletproofGens,
+	transcript: &mut Transcript,
+	commitments: &[CompressedRistretto],
+	proof: &R1CSProof,
+) -> Result<(), R1CSError> {
+	verify_gadget_with_rng(
+		gadget,
+		pc_gens,
+		bp_gens,
+		transcript,
+		commitments,
+		proof,
+		&mut thread_rng(),
+	)
+}
+
+/// Verifies that the `proof` shows the values of the `commitments` satisfy
+/// the `gadget`.
+///
+/// The `transcript` must be in the same state as the one passed to
+/// [`prove_gadget_with_rng`].
+pub fn verify_gadget_with_rng<G: Gadget, T: RngCore + CryptoRng>(
+	gadget: &G,
+	pc_gens: &PedersenGens,
+	bp_gens: &BulletproofGens,
+	transcript: &mut Transcript,
+	commitments: &[CompressedRistretto],
+	proof: &R1CSProof,
+	prng: &mut T,
+) -> Result<(), R1CSError> {
+	let mut verifier = Verifier::new_with_gens(transcript, pc_gens);
+
+	let inputs: Vec<_> = commitments
+		.iter()
+		.map(|commitment| AllocatedScalar {
+			variable: verifier.commit(*commitment),
+			assignment: None,
+		})
+		.collect();
+	gadget.assemble(&mut verifier, &inputs)?;
+
+	verifier.verify_with_rng(proof, pc_gens, bp_gens, prng)
+}
+
+/// The [`conservation_gadget`] as a [`Gadget`].
+///
+/// The last input is the total and the other inputs are the parts.  No
+/// outputs are allocated.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Conservation;
+
+impl Gadget for Conservation {
+	fn assemble<CS: ConstraintSystem>(
+		&self,
+		cs: &mut CS,
+		inputs: &[AllocatedScalar],
+	) -> Result<Vec<AllocatedScalar>, R1CSError> {
+		let (total, parts) = inputs
+			.split_last()
+			.ok_or_else(|| R1CSError::gadget("the conservation gadget needs a total"))?;
+		conservation_gadget(cs, parts, *total)?;
+		Ok(Vec::new())
+	}
+}
+
+/// Constrains the value of `total` to be the sum of the values of `parts`.
+///
+/// The total and each of the parts may live in separate commitments,
+/// possibly produced by different parties, so the constraint does not
+/// depend on how their blinding factors were chosen.  No multipliers are
+/// allocated.
+///
+/// # Relation to [`check_commitment_sum`]
+///
+/// When the blinding factor of the total commitment is the sum of the
+/// blinding factors of the part commitments, the commitments themselves
+/// add up, and [`check_commitment_sum`] verifies the same statement
+/// outside of the circuit, with a few point additions.  When the blinding
+/// factors are chosen independently, for instance because the parts are
+/// committed by different parties, the commitments do not add up even
+/// though the values do, and this in-circuit constraint is required.
+///
+/// Returns [`R1CSError::GadgetError`] if all the assignments are known and
+/// the parts do not add up to the total.
+pub fn conservation_gadget<CS: ConstraintSystem>(
+	cs: &mut CS,
+	parts: &[AllocatedScalar],
+	total: AllocatedScalar,
+) -> Result<(), R1CSError> {
+	let parts_sum: Option<Scalar> = parts.iter().map(|part| part.assignment).sum();
+	if let (Some(parts_sum), Some(total)) = (parts_sum, total.assignment) {
+		if parts_sum != total {
+			return Err(R1CSError::gadget("the parts do not add up to the total"));
+		}
+	}
+
+	let sum: LinearCombination = parts.iter().map(|part| (part.variable, Scalar::one())).collect();
+	cs.constrain(total.variable - sum);
+	Ok(())
+}
+
+/// Constrains the value of `v` to be one of the elements of the `set`.
+///
+/// The elements are public, and may be given as `u64`s or as arbitrary
+/// [`Scalar`]s, such as hashes mapped into the field.  The product of the
+/// differences \\(v - s_i\\) is constrained to be zero, which allocates one
+/// multiplier less than there are elements in the set.
+///
+/// Returns [`R1CSError::GadgetError`] if the set is empty, or if the
+/// assignment of `v` is known and is not in the set.
+pub fn set_membership_1_gadget<CS, S>(cs: &mut CS, v: AllocatedScalar, set: &[S]) -> Result<(), R1CSError>
+where
+	CS: ConstraintSystem,
+	S: Copy + Into<Scalar>,
+{
+	let (first, rest) = set
+		.split_first()
+		.ok_or_else(|| R1CSError::gadget("the set of the membership gadget is empty"))?;
+	if let Some(value) = v.assignment {
+		if !set.iter().any(|&element| element.into() == value) {
+			return Err(R1CSError::gadget("the value is not in the set"));
+		}
+	}
+
+	let mut product: LinearCombination = v.variable - (*first).into();
+	for &element in rest {
+		let (_, _, o) = cs.multiply(product, v.variable - element.into());
+		product = o.into();
+	}
+	cs.constrain(product);
+	Ok(())
+}
+
+/// Constrains the value of `v` to differ from every element of the `set`.
+///
+/// The elements are public, and may be given as `u64`s or as arbitrary
+/// [`Scalar`]s.  Each difference \\(v - s_i\\) is shown to be nonzero by
+/// multiplying it with its inverse, which allocates one multiplier for
+/// each element of the set.
+///
+/// Returns [`R1CSError::GadgetError`] if the assignment of `v` is known
+/// and is in the set.
+pub fn set_non_membership_gadget<CS, S>(cs: &mut CS, v: AllocatedScalar, set: &[S]) -> Result<(), R1CSError>
+where
+	CS: ConstraintSystem,
+	S: Copy + Into<Scalar>,
+{
+	for &element in set {
+		let element = element.into();
+		let assignments = match v.assignment {
+			Some(value) if value == element => return Err(R1CSError::gadget("the value is in the set")),
+			Some(value) => Some((value - element, (value - element).invert())),
+			None => None,
+		};
+
+		// diff * diff_inv = 1
+		let (diff, _, one) = cs.allocate_multiplier(assignments)?;
+		cs.constrain(diff - (v.variable - element));
+		cs.constrain(one - Scalar::one());
+	}
+	Ok(())
+}
+
+/// Constrains the value of `x` to be nonzero, by constraining `x_inv` to
+/// be its inverse.
+///
+/// Returns [`R1CSError::GadgetError`] if both assignments are known and
+/// `x_inv` is not the inverse of `x`, which is always the case when `x`
+/// is zero.
+pub fn is_nonzero_gadget<CS: ConstraintSystem>(
+	cs: &mut CS,
+	x: AllocatedScalar,
+	x_inv: AllocatedScalar,
+) -> Result<(), R1CSError> {
+	if let (Some(x), Some(x_inv)) = (x.assignment, x_inv.assignment) {
+		if x * x_inv != Scalar::one() {
+			return Err(R1CSError::gadget("the inverse does not match the value"));
+		}
+	}
+
+	// x * x_inv = 1
+	let (_, _, product) = cs.multiply(x.variable.into(), x_inv.variable.into());
+	cs.constrain_labeled(product - Variable::One(), "is_nonzero");
+	Ok(())
+}
+
+/// A two-to-one hash function which can be computed both natively and in a
+/// constraint system, such as the compression function of a Merkle tree.
+pub trait CircuitHash {
+	/// Computes the hash of `left` and `right`.
+	fn hash(&self, left: Scalar, right: Scalar) -> Scalar;
+
+	/// Builds the constraints of the hash of `left` and `right`, and
+	/// returns the output, whose assignment is the [`CircuitHash::hash`] of
+	/// the assignments of the inputs when they are known.
+	fn hash_gadget<CS: ConstraintSystem>(
+		&self,
+		cs: &mut CS,
+		left: AllocatedScalar,
+		right: AllocatedScalar,
+	) -> Result<AllocatedScalar, R1CSError>;
+}
+
+/// Computes the root of the Merkle tree of the `leaves` with the `hash`,
+/// as [`merkle_root_gadget`] does in a constraint system.
+///
+/// Returns [`R1CSError::GadgetError`] if there are no leaves.
+pub fn merkle_root<H: CircuitHash>(leaves: &[Scalar], hash: &H) -> Result<Scalar, R1CSError> {
+	if leaves.is_empty() {
+		return Err(R1CSError::gadget("the Merkle tree has no leaves"));
+	}
+
+	let mut level = leaves.to_vec();
+	level.resize(leaves.len().next_power_of_two(), Scalar::one());
+	while level.len() > 1 {
+		level = level.chunks(2).map(|pair| hash.hash(pair[0], pair[1])).collect();
+	}
+	Ok(level[0])
+}
+
+/// Computes the root of the Merkle tree of the `leaves` with the `hash`,
+/// and returns the variable of the root.
+///
+/// The tree is a full binary tree: when the number of leaves is not a
+/// power of two, it is padded with leaves of value one, the constant
+/// [`Variable::One`], which need no allocation.  Its \\(n - 1\\) hashes
+/// for \\(n\\) leaves after padding, fewer than twice the number of leaves,
+/// are built with [`CircuitHash::hash_gadget`].  A single leaf is its own
+/// root.
+///
+/// Returns [`R1CSError::GadgetError`] if there are no leaves.
+pub fn merkle_root_gadget<CS, H>(cs: &mut CS, leaves: &[AllocatedScalar], hash: &H) -> Result<Variable, R1CSError>
+where
+	CS: ConstraintSystem,
+	H: CircuitHash,
+{
+	if leaves.is_empty() {
+		return Err(R1CSError::gadget("the Merkle tree has no leaves"));
+	}
+
+	let padding = AllocatedScalar {
+		variable: Variable::One(),
+		assignment: Some(Scalar::one()),
+	};
+	let mut level = leaves.to_vec();
+	level.resize(leaves.len().next_power_of_two(), padding);
+	while level.len() > 1 {
+		level = level
+			.chunks(2)
+			.map(|pair| hash.hash_gadget(cs, pair[0], pair[1]))
+			.collect::<Result<_, _>>()?;
+	}
+	Ok(level[0].variable)
+}
+
+/// The membership of a leaf in a Merkle tree of a fixed `depth`, as a
+/// [`Gadget`].
+///
+/// The inputs are the leaf, the root, and for each level from the leaf up,
+/// the sibling of the node and a bit which is one if the node is the right
+/// child.  No outputs are allocated.  Each level costs three multipliers on
+/// top of the [`CircuitHash::hash_gadget`]: one to check the bit, one to
+/// order the node and its sibling, and one for the ordered pair.
+#[derive(Copy, Clone, Debug)]
+pub struct MerklePath<H> {
+	/// The number of levels between the leaf and the root.
+	pub depth: usize,
+	/// The hash of the tree.
+	pub hash: H,
+}
+
+impl<H: CircuitHash> MerklePath<H> {
+	/// Creates the gadget for trees of the given `depth`.
+	pub fn new(depth: usize, hash: H) -> Self {
+		MerklePath { depth, hash }
+	}
+
+	/// Computes the root of the tree from the `leaf` and its `path`, the
+	/// sibling of each node from the leaf up with whether the node is the
+	/// right child.
+	///
+	/// Returns [`R1CSError::GadgetError`] if the path is not `depth` long.
+	pub fn root(&self, leaf: Scalar, path: &[(Scalar, bool)]) -> Result<Scalar, R1CSError> {
+		if path.len() != self.depth {
+			return Err(R1CSError::gadget("the Merkle path does not have the depth of the tree"));
+		}
+		Ok(path.iter().fold(leaf, |node, &(sibling, is_right)| {
+			if is_right {
+				self.hash.hash(sibling, node)
+			} else {
+				self.hash.hash(node, sibling)
+			}
+		}))
+	}
+
+	/// Returns the inputs of the gadget for the `leaf` and its `path`,
+	/// together with the root they prove.
+	pub fn inputs(&self, leaf: Scalar, path: &[(Scalar, bool)]) -> Result<Vec<Scalar>, R1CSError> {
+		let root = self.root(leaf, path)?;
+		let mut inputs = vec![leaf, root];
+		for &(sibling, is_right) in path {
+			inputs.push(sibling);
+			inputs.push(Scalar::from(is_right as u64));
+		}
+		Ok(inputs)
+	}
+
+	/// Synthesizes the gadget once, for verifying any number of proofs of
+	/// membership in trees of this depth with [`verify_with_key`].
+	pub fn verification_key(&self) -> Result<GadgetVerificationKey, R1CSError> {
+		GadgetVerificationKey::new(self, 2 + 2 * self.depth)
+	}
+}
+
+impl<H: CircuitHash> Gadget for MerklePath<H> {
+	fn assemble<CS: ConstraintSystem>(
+		&self,
+		cs: &mut CS,
+		inputs: &[AllocatedScalar],
+	) -> Result<Vec<AllocatedScalar>, R1CSError> {
+		if inputs.len() != 2 + 2 * self.depth {
+			return Err(R1CSError::gadget("the Merkle path does not have the depth of the tree"));
+		}
+
+		let mut node = inputs[0];
+		for level in inputs[2..].chunks(2) {
+			let (sibling, is_right) = (level[0], level[1]);
+			if let Some(bit) = is_right.assignment {
+				if bit != Scalar::zero() && bit != Scalar::one() {
+					return Err(R1CSError::gadget("the direction of a Merkle path node is not a bit"));
+				}
+			}
+
+			// is_right * (1 - is_right) = 0
+			let (_, _, product) = cs.multiply(is_right.variable.into(), Variable::One() - is_right.variable);
+			cs.constrain_labeled(product.into(), "merkle_path_bit");
+
+			// The node and its sibling swap places when the node is the
+			// right child.
+			let (_, _, swap) = cs.multiply(is_right.variable.into(), sibling.variable - node.variable);
+			let (left_value, right_value) = match (node.assignment, sibling.assignment, is_right.assignment) {
+				(Some(node), Some(sibling), Some(bit)) => {
+					let swapped = bit * (sibling - node);
+					(Some(node + swapped), Some(sibling - swapped))
+				}
+				_ => (None, None),
+			};
+			let left = AllocatedScalar {
+				variable: cs.allocate(left_value)?,
+				assignment: left_value,
+			};
+			let right = AllocatedScalar {
+				variable: cs.allocate(right_value)?,
+				assignment: right_value,
+			};
+			cs.constrain(left.variable - node.variable - swap);
+			cs.constrain(right.variable - sibling.variable + swap);
+
+			node = self.hash.hash_gadget(cs, left, right)?;
+		}
+		cs.constrain_labeled(node.variable - inputs[1].variable, "merkle_path_root");
+		Ok(Vec::new())
+	}
+}
+
+/// Verification data for a [`Gadget`] with fixed parameters, synthesized
+/// once and reused for every proof.
+///
+/// A verifier re-runs the gadget code for every proof, deriving again the
+/// constants of its hash and the shape of its circuit.  A key keeps the
+/// [`CircuitDescription`] of the synthesis instead, together with its
+/// [`CircuitDescription::circuit_hash`] as a fingerprint which identifies
+/// the gadget and its parameters.  The key can be serialized with
+/// [`GadgetVerificationKey::to_bytes`], for instance to ship it with node
+/// software, and proofs are checked against it with [`verify_with_key`].
+///
+/// Unlike a [`VerificationKey`](::r1cs::VerificationKey), the key holds no
+/// generators, which are passed when verifying.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GadgetVerificationKey {
+	/// The synthesized circuit.
+	description: CircuitDescription,
+	/// The circuit hash of the `description`.
+	fingerprint: [u8; 32],
+}
+
+impl GadgetVerificationKey {
+	/// Synthesizes the `gadget` over `num_inputs` committed inputs.
+	pub fn new<G: Gadget>(gadget: &G, num_inputs: usize) -> Result<Self, R1CSError> {
+		let description = CircuitDescription::from_circuit(num_inputs, |verifier, variables| {
+			let inputs: Vec<_> = variables
+				.into_iter()
+				.map(|variable| AllocatedScalar {
+					variable,
+					assignment: None,
+				})
+				.collect();
+			gadget.assemble(verifier, &inputs).map(|_| ())
+		})?;
+		Ok(Self::from_description(description))
+	}
+
+	/// Wraps an already synthesized circuit.
+	pub fn from_description(description: CircuitDescription) -> Self {
+		let fingerprint = description.circuit_hash();
+		GadgetVerificationKey {
+			description,
+			fingerprint,
+		}
+	}
+
+	/// Returns the description of the circuit.
+	pub fn description(&self) -> &CircuitDescription {
+		&self.description
+	}
+
+	/// Returns the fingerprint of the gadget and its parameters.
+	pub fn fingerprint(&self) -> [u8; 32] {
+		self.fingerprint
+	}
+
+	/// Returns the number of committed inputs of the gadget.
+	pub fn num_inputs(&self) -> usize {
+		self.description.num_committed()
+	}
+
+	/// Serializes the key into a byte array of the 32-byte fingerprint
+	/// followed by the [`CircuitDescription::to_bytes`] encoding of the
+	/// circuit.
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut buf = self.fingerprint.to_vec();
+		buf.extend_from_slice(&self.description.to_bytes());
+		buf
+	}
+
+	/// Deserializes the key from a byte slice in the
+	/// [`GadgetVerificationKey::to_bytes`] format.
+	///
+	/// Returns [`R1CSError::FormatError`] if the encoding is malformed or
+	/// the circuit does not match the fingerprint.
+	pub fn from_bytes(slice: &[u8]) -> Result<Self, R1CSError> {
+		if slice.len() < 32 {
+			return Err(R1CSError::FormatError);
+		}
+		let key = Self::from_description(CircuitDescription::from_bytes(&slice[32..])?);
+		if key.fingerprint[..] != slice[..32] {
+			return Err(R1CSError::FormatError);
+		}
+		Ok(key)
+	}
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for GadgetVerificationKey {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		serializer.serialize_bytes(&self.to_bytes()[..])
+	}
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for GadgetVerificationKey {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		struct GadgetVerificationKeyVisitor;
+
+		impl<'de> Visitor<'de> for GadgetVerificationKeyVisitor {
+			type Value = GadgetVerificationKey;
+
+			fn expecting(&self, formatter: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+				formatter.write_str("a valid GadgetVerificationKey")
+			}
+
+			fn visit_bytes<E>(self, v: &[u8]) -> Result<GadgetVerificationKey, E>
+			where
+				E: serde::de::Error,
+			{
+				#[cfg(feature = "std")]
+				return GadgetVerificationKey::from_bytes(v).map_err(serde::de::Error::custom);
+				#[cfg(not(feature = "std"))]
+				return GadgetVerificationKey::from_bytes(v)
+					.map_err(|_| serde::de::Error::custom("deserialization error"));
+			}
+
+			fn visit_seq<A>(self, mut seq: A) -> Result<GadgetVerificationKey, A::Error>
+			where
+				A: serde::de::SeqAccess<'de>,
+			{
+				let mut bytes = Vec::new();
+				while let Some(byte) = seq.next_element::<u8>()? {
+					bytes.push(byte);
+				}
+				self.visit_bytes(&bytes)
+			}
+		}
+
+		deserializer.deserialize_bytes(GadgetVerificationKeyVisitor)
+	}
+}
+
+/// Verifies that the `proof` shows the values of the `commitments` satisfy
+/// the gadget of the `key`.
+///
+/// This is a convenience wrapper around [`verify_with_key_and_rng`],
+/// passing in a threadsafe RNG.
+#[cfg(feature = "std")]
+pub fn verify_with_key(
+	key: &GadgetVerificationKey,
+	pc_gens: &PedersenGens,
+	bp_gens: &BulletproofGens,
+	transcript: &mut Transcript,
+	commitments: &[CompressedRistretto],
+	proof: &R1CSProof,
+) -> Result<(), R1CSError> {
+	verify_with_key_and_rng(key, pc_gens, bp_gens, transcript, commitments, proof, &mut thread_rng())
+}
+
+/// Verifies that the `proof` shows the values of the `commitments` satisfy
+/// the gadget of the `key`, without running the gadget code.
+///
+/// A proof is accepted exactly when [`verify_gadget_with_rng`] accepts it
+/// for the gadget of the key.  Returns
+/// [`R1CSError::WrongNumberOfCommitments`] if the number of commitments is
+/// not the number of inputs of the gadget.
+pub fn verify_with_key_and_rng<T: RngCore + CryptoRng>(
+	key: &GadgetVerificationKey,
+	pc_gens: &PedersenGens,
+	bp_gens: &BulletproofGens,
+	transcript: &mut Transcript,
+	commitments: &[CompressedRistretto],
+	proof: &R1CSProof,
+	prng: &mut T,
+) -> Result<(), R1CSError> {
+	if commitments.len() != key.num_inputs() {
+		return Err(R1CSError::WrongNumberOfCommitments {
+			expected: key.num_inputs(),
+			actual: commitments.len(),
+		});
+	}
+	Verifier::verify_compiled_with_rng(transcript, &key.description, proof, commitments, pc_gens, bp_gens, prng)
+}
+
+/// Checks that the `total_commitment` is the sum of the `part_commitments`.
+///
+/// This is the homomorphic counterpart of [`conservation_gadget`]: if the
+/// check passes, the value committed in the total is the sum of the values
+/// committed in the parts.  It only succeeds if the blinding factor of the
+/// total is the sum of the blinding factors of the parts, so it can
+/// replace the in-circuit constraint only under that blinding discipline.
+///
+/// Returns `false` if any of the commitments is not a valid point.
+pub fn check_commitment_sum(total_commitment: &CompressedRistretto, part_commitments: &[CompressedRistretto]) -> bool {
+	let points: Option<Vec<RistrettoPoint>> = iter::once(total_commitment)
+		.chain(part_commitments.iter())
+		.map(|commitment| commitment.decompress())
+		.collect();
+
+	match points {
+		Some(points) => {
+			let parts_sum: RistrettoPoint = points[1..].iter().sum();
+			(points[0] - parts_sum).is_identity()
+		}
+		None => false,
+	}
+}
@@ -9,6 +9,8 @@ use core::{
 use curve25519_dalek::scalar::Scalar;
 use sp_std::collections::btree_map::BTreeMap;
 
+use super::ConstraintSystem;
+
 /// Represents a variable in a constraint system.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Ord, PartialOrd, Hash)]
 pub enum Variable {
@@ -32,6 +34,18 @@ impl From<Variable> for LinearCombination {
 	}
 }
 
+impl From<&Variable> for LinearCombination {
+	fn from(v: &Variable) -> LinearCombination {
+		LinearCombination::from(*v)
+	}
+}
+
+impl From<&LinearCombination> for LinearCombination {
+	fn from(lc: &LinearCombination) -> LinearCombination {
+		lc.clone()
+	}
+}
+
 impl<S: Into<Scalar>> From<S> for LinearCombination {
 	fn from(s: S) -> LinearCombination {
 		LinearCombination {
@@ -76,6 +90,38 @@ impl<S: Into<Scalar>> Mul<S> for Variable {
 	}
 }
 
+impl Neg for &Variable {
+	type Output = LinearCombination;
+
+	fn neg(self) -> Self::Output {
+		-*self
+	}
+}
+
+impl<L: Into<LinearCombination>> Add<L> for &Variable {
+	type Output = LinearCombination;
+
+	fn add(self, other: L) -> Self::Output {
+		*self + other
+	}
+}
+
+impl<L: Into<LinearCombination>> Sub<L> for &Variable {
+	type Output = LinearCombination;
+
+	fn sub(self, other: L) -> Self::Output {
+		*self - other
+	}
+}
+
+impl<S: Into<Scalar>> Mul<S> for &Variable {
+	type Output = LinearCombination;
+
+	fn mul(self, other: S) -> Self::Output {
+		*self * other
+	}
+}
+
 // Arithmetic on scalars with variables produces linear combinations
 
 impl Add<Variable> for Scalar {
@@ -108,6 +154,46 @@ impl Mul<Variable> for Scalar {
 	}
 }
 
+impl Add<&Variable> for Scalar {
+	type Output = LinearCombination;
+
+	fn add(self, other: &Variable) -> Self::Output {
+		self + *other
+	}
+}
+
+impl Sub<&Variable> for Scalar {
+	type Output = LinearCombination;
+
+	fn sub(self, other: &Variable) -> Self::Output {
+		self - *other
+	}
+}
+
+impl Mul<&Variable> for Scalar {
+	type Output = LinearCombination;
+
+	fn mul(self, other: &Variable) -> Self::Output {
+		self * *other
+	}
+}
+
+impl Mul<Variable> for u64 {
+	type Output = LinearCombination;
+
+	fn mul(self, other: Variable) -> Self::Output {
+		Scalar::from(self) * other
+	}
+}
+
+impl Mul<&Variable> for u64 {
+	type Output = LinearCombination;
+
+	fn mul(self, other: &Variable) -> Self::Output {
+		Scalar::from(self) * *other
+	}
+}
+
 /// Represents a linear combination of
 /// [`Variables`](::r1cs::Variable).  Each term is represented by a
 /// `(Variable, Scalar)` pair.
@@ -121,11 +207,23 @@ impl LinearCombination {
 		self.terms
 	}
 
+	/// Evaluates the linear combination against the assignments of `cs`.
+	///
+	/// On the prover's side, this plugs in the assignments of the variables
+	/// allocated so far, with `Variable::One()` evaluating to one.  The
+	/// verifier has no assignments and returns `None`.  Gadget code can
+	/// check that a constraint holds before adding it, e.g. with
+	/// `debug_assert_eq!(lc.evaluate(cs), Some(Scalar::zero()))`.
+	pub fn evaluate<CS: ConstraintSystem + ?Sized>(&self, cs: &CS) -> Option<Scalar> {
+		cs.evaluate_lc(self)
+	}
+
 	/// Simplify linear combination by taking Variables common across terms and
-	/// adding their corresponding scalars. Useful when linear combinations
-	/// become large. Takes ownership of linear combination as this function is
-	/// useful when memory is limited and the obvious action after this function
-	/// call will be to free the memory held by the old linear combination
+	/// adding their corresponding scalars, and dropping the terms whose
+	/// scalars add up to zero. Useful when linear combinations become large.
+	/// Takes ownership of linear combination as this function is useful when
+	/// memory is limited and the obvious action after this function call will
+	/// be to free the memory held by the old linear combination
 	pub fn simplify(self) -> Self {
 		// Build hashmap to hold unique variables with their values.
 		let mut vars: BTreeMap<Variable, Scalar> = BTreeMap::new();
@@ -135,11 +233,7 @@ impl LinearCombination {
 			*vars.entry(var).or_insert_with(Scalar::zero) += val;
 		}
 
-		let mut new_lc_terms = vec![];
-		for (var, val) in vars {
-			new_lc_terms.push((var, val));
-		}
-		new_lc_terms.iter().collect()
+		vars.into_iter().filter(|(_, val)| *val != Scalar::zero()).collect()
 	}
 }
 
@@ -192,6 +286,54 @@ impl<L: Into<LinearCombination>> Sub<L> for LinearCombination {
 	}
 }
 
+impl<L: Into<LinearCombination>> Add<L> for &LinearCombination {
+	type Output = LinearCombination;
+
+	fn add(self, rhs: L) -> Self::Output {
+		self.clone() + rhs
+	}
+}
+
+impl<L: Into<LinearCombination>> Sub<L> for &LinearCombination {
+	type Output = LinearCombination;
+
+	fn sub(self, rhs: L) -> Self::Output {
+		self.clone() - rhs
+	}
+}
+
+impl Add<LinearCombination> for Scalar {
+	type Output = LinearCombination;
+
+	fn add(self, other: LinearCombination) -> Self::Output {
+		other + self
+	}
+}
+
+impl Add<&LinearCombination> for Scalar {
+	type Output = LinearCombination;
+
+	fn add(self, other: &LinearCombination) -> Self::Output {
+		other + self
+	}
+}
+
+impl Sub<LinearCombination> for Scalar {
+	type Output = LinearCombination;
+
+	fn sub(self, other: LinearCombination) -> Self::Output {
+		-other + self
+	}
+}
+
+impl Sub<&LinearCombination> for Scalar {
+	type Output = LinearCombination;
+
+	fn sub(self, other: &LinearCombination) -> Self::Output {
+		-other + self
+	}
+}
+
 impl Mul<LinearCombination> for Scalar {
 	type Output = LinearCombination;
 
@@ -205,6 +347,14 @@ impl Mul<LinearCombination> for Scalar {
 	}
 }
 
+impl Mul<&LinearCombination> for Scalar {
+	type Output = LinearCombination;
+
+	fn mul(self, other: &LinearCombination) -> Self::Output {
+		self * other.clone()
+	}
+}
+
 impl Neg for LinearCombination {
 	type Output = Self;
 
@@ -216,6 +366,14 @@ impl Neg for LinearCombination {
 	}
 }
 
+impl Neg for &LinearCombination {
+	type Output = LinearCombination;
+
+	fn neg(self) -> Self::Output {
+		-self.clone()
+	}
+}
+
 impl<S: Into<Scalar>> Mul<S> for LinearCombination {
 	type Output = Self;
 
@@ -227,3 +385,147 @@ impl<S: Into<Scalar>> Mul<S> for LinearCombination {
 		self
 	}
 }
+
+impl<S: Into<Scalar>> Mul<S> for &LinearCombination {
+	type Output = LinearCombination;
+
+	fn mul(self, other: S) -> Self::Output {
+		self.clone() * other
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn simplify_merges_and_drops_terms() {
+		let x = Variable::MultiplierLeft(0);
+		let y = Variable::Committed(1);
+
+		let lc = x + x - Scalar::from(2u64) * x;
+		assert_eq!(lc.terms.len(), 3);
+		assert_eq!(lc.simplify(), LinearCombination::default());
+
+		let lc = (x + y + Scalar::from(3u64)) * Scalar::from(2u64) - x + Variable::One();
+		assert_eq!(lc.simplify().terms, vec![
+			(y, Scalar::from(2u64)),
+			(x, Scalar::one()),
+			(Variable::One(), Scalar::from(7u64)),
+		]);
+	}
+
+	/// Evaluates `lc` with `x = 3` and `y = 5`.
+	fn eval(lc: LinearCombination) -> Scalar {
+		lc.terms
+			.iter()
+			.map(|(var, coeff)| {
+				coeff
+					* match var {
+						Variable::Committed(0) => Scalar::from(3u64),
+						Variable::MultiplierLeft(0) => Scalar::from(5u64),
+						Variable::One() => Scalar::one(),
+						_ => unreachable!(),
+					}
+			})
+			.sum()
+	}
+
+	#[test]
+	#[allow(clippy::op_ref)]
+	fn operators_by_value_and_by_reference() {
+		let x = Variable::Committed(0);
+		let y = Variable::MultiplierLeft(0);
+		let lc = x + y;
+		let two = Scalar::from(2u64);
+		let s = |v: i64| -> Scalar {
+			if v < 0 {
+				-Scalar::from((-v) as u64)
+			} else {
+				Scalar::from(v as u64)
+			}
+		};
+
+		// Conversions
+		assert_eq!(eval(LinearCombination::from(x)), s(3));
+		assert_eq!(eval(LinearCombination::from(&x)), s(3));
+		assert_eq!(eval(LinearCombination::from(&lc)), s(8));
+		assert_eq!(eval(LinearCombination::from(two)), s(2));
+		assert_eq!(eval(LinearCombination::from(7u64)), s(7));
+
+		// Variable on the left
+		assert_eq!(eval(-x), s(-3));
+		assert_eq!(eval(-&x), s(-3));
+		assert_eq!(eval(x + y), s(8));
+		assert_eq!(eval(x + &y), s(8));
+		assert_eq!(eval(&x + y), s(8));
+		assert_eq!(eval(x + lc.clone()), s(11));
+		assert_eq!(eval(&x + &lc), s(11));
+		assert_eq!(eval(x + two), s(5));
+		assert_eq!(eval(&x + 1u64), s(4));
+		assert_eq!(eval(x - y), s(-2));
+		assert_eq!(eval(&x - &y), s(-2));
+		assert_eq!(eval(x - lc.clone()), s(-5));
+		assert_eq!(eval(&x - &lc), s(-5));
+		assert_eq!(eval(x - two), s(1));
+		assert_eq!(eval(&x - 1u64), s(2));
+		assert_eq!(eval(x * two), s(6));
+		assert_eq!(eval(&x * 2u64), s(6));
+
+		// Scalar on the left
+		assert_eq!(eval(two + x), s(5));
+		assert_eq!(eval(two + &x), s(5));
+		assert_eq!(eval(two + lc.clone()), s(10));
+		assert_eq!(eval(two + &lc), s(10));
+		assert_eq!(eval(two - x), s(-1));
+		assert_eq!(eval(two - &x), s(-1));
+		assert_eq!(eval(two - lc.clone()), s(-6));
+		assert_eq!(eval(two - &lc), s(-6));
+		assert_eq!(eval(two * x), s(6));
+		assert_eq!(eval(two * &x), s(6));
+		assert_eq!(eval(2u64 * x), s(6));
+		assert_eq!(eval(2u64 * &x), s(6));
+		assert_eq!(eval(two * lc.clone()), s(16));
+		assert_eq!(eval(two * &lc), s(16));
+
+		// LinearCombination on the left
+		assert_eq!(eval(-lc.clone()), s(-8));
+		assert_eq!(eval(-&lc), s(-8));
+		assert_eq!(eval(lc.clone() + x), s(11));
+		assert_eq!(eval(&lc + &y), s(13));
+		assert_eq!(eval(lc.clone() + lc.clone()), s(16));
+		assert_eq!(eval(&lc + &lc), s(16));
+		assert_eq!(eval(lc.clone() + two), s(10));
+		assert_eq!(eval(&lc + 1u64), s(9));
+		assert_eq!(eval(lc.clone() - x), s(5));
+		assert_eq!(eval(&lc - &y), s(3));
+		assert_eq!(eval(lc.clone() - &lc), s(0));
+		assert_eq!(eval(&lc - lc.clone()), s(0));
+		assert_eq!(eval(lc.clone() - two), s(6));
+		assert_eq!(eval(&lc - 1u64), s(7));
+		assert_eq!(eval(lc.clone() * two), s(16));
+		assert_eq!(eval(&lc * 2u64), s(16));
+
+		// A constraint subtracting a constant needs no explicit terms.
+		assert_eq!(eval(x + y - Scalar::from(8u64)), s(0));
+	}
+
+	#[test]
+	fn variable_arithmetic_terms() {
+		let x = Variable::Committed(0);
+		let y = Variable::MultiplierLeft(0);
+		let one = Variable::One();
+		let two = Scalar::from(2u64);
+
+		assert_eq!((x * two).terms, vec![(x, two)]);
+		assert_eq!((x * 2u64).terms, vec![(x, two)]);
+		assert_eq!((two * x).terms, vec![(x, two)]);
+		assert_eq!((2u64 * x).terms, vec![(x, two)]);
+		assert_eq!((x + y).terms, vec![(x, Scalar::one()), (y, Scalar::one())]);
+		assert_eq!((x - y).terms, vec![(x, Scalar::one()), (y, -Scalar::one())]);
+		assert_eq!((x - 2u64).terms, vec![(x, Scalar::one()), (one, -two)]);
+		assert_eq!((x + 2u64).terms, vec![(x, Scalar::one()), (one, two)]);
+		assert_eq!((-x).terms, vec![(x, -Scalar::one())]);
+		assert_eq!((two - x).terms, vec![(one, two), (x, -Scalar::one())]);
+	}
+}
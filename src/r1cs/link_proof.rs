@@ -0,0 +1,266 @@
+#![allow(non_snake_case)]
+//! Definition of the proof linking a committed value across two proofs.
+
+use alloc::vec::Vec;
+use curve25519_dalek::{
+	ristretto::{CompressedRistretto, RistrettoPoint},
+	scalar::Scalar,
+	traits::{IsIdentity, VartimeMultiscalarMul},
+};
+use merlin::Transcript;
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(feature = "std")]
+use rand::thread_rng;
+#[cfg(feature = "std")]
+use serde::de::Visitor;
+#[cfg(feature = "std")]
+use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+	errors::R1CSError,
+	generators::PedersenGens,
+	transcript::{append_message, challenge_bytes, TranscriptProtocol},
+	util,
+};
+
+/// A proof that two Pedersen commitments, made with independent blinding
+/// factors in two independent [`R1CSProof`](::r1cs::R1CSProof)s, open to
+/// the same value.
+///
+/// The prover commits the shared value \\(v\\) in each constraint system as
+/// \\(V_1 = v B + \tilde{v}_1 \widetilde{B}\\) and
+/// \\(V_2 = v B + \tilde{v}_2 \widetilde{B}\\), and proves knowledge of
+/// \\(v, \tilde{v}_1, \tilde{v}_2\\) with a sigma protocol.
+///
+/// The challenge of the sigma protocol is bound to both proofs: it is
+/// derived from the two transcripts in the state they are in after proving
+/// (for the prover) or verifying (for the verifier) the respective
+/// [`R1CSProof`](::r1cs::R1CSProof)s.  A `LinkProof` therefore only
+/// verifies together with the two proofs it was made for.
+#[derive(Clone, Debug)]
+pub struct LinkProof {
+	/// Commitment to the nonces for \\(V_1\\).
+	R_1: CompressedRistretto,
+	/// Commitment to the nonces for \\(V_2\\).
+	R_2: CompressedRistretto,
+	/// Response for the value.
+	s_v: Scalar,
+	/// Response for the blinding factor of \\(V_1\\).
+	s_1: Scalar,
+	/// Response for the blinding factor of \\(V_2\\).
+	s_2: Scalar,
+}
+
+impl LinkProof {
+	/// Creates a proof that the commitments to `value` with `blinding1` and
+	/// `blinding2` open to the same value.
+	///
+	/// This is a convenience wrapper around [`LinkProof::prove_with_rng`],
+	/// passing in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn prove(
+		value: Scalar,
+		blinding1: Scalar,
+		blinding2: Scalar,
+		transcript1: &mut Transcript,
+		transcript2: &mut Transcript,
+		pc_gens: &PedersenGens,
+	) -> LinkProof {
+		LinkProof::prove_with_rng(
+			value,
+			blinding1,
+			blinding2,
+			transcript1,
+			transcript2,
+			pc_gens,
+			&mut thread_rng(),
+		)
+	}
+
+	/// Creates a proof that the commitments to `value` with `blinding1` and
+	/// `blinding2` open to the same value.
+	///
+	/// The `transcript1` and `transcript2` must be the transcripts of the
+	/// two proofs, after their creation.  Both are advanced by the link.
+	pub fn prove_with_rng<T: RngCore + CryptoRng>(
+		value: Scalar,
+		blinding1: Scalar,
+		blinding2: Scalar,
+		transcript1: &mut Transcript,
+		transcript2: &mut Transcript,
+		pc_gens: &PedersenGens,
+		rng: &mut T,
+	) -> LinkProof {
+		let V_1 = pc_gens.commit(value, blinding1).compress();
+		let V_2 = pc_gens.commit(value, blinding2).compress();
+		let mut transcript = link_transcript(transcript1, transcript2, &V_1, &V_2);
+
+		let mut rng = transcript
+			.build_rng()
+			.rekey_with_witness_bytes(b"v", value.as_bytes())
+			.rekey_with_witness_bytes(b"v_blinding1", blinding1.as_bytes())
+			.rekey_with_witness_bytes(b"v_blinding2", blinding2.as_bytes())
+			.finalize(rng);
+
+		let k_v = Scalar::random(&mut rng);
+		let k_1 = Scalar::random(&mut rng);
+		let k_2 = Scalar::random(&mut rng);
+
+		let R_1 = pc_gens.commit(k_v, k_1).compress();
+		let R_2 = pc_gens.commit(k_v, k_2).compress();
+		transcript.append_point(b"R_1", &R_1);
+		transcript.append_point(b"R_2", &R_2);
+
+		let c = transcript.challenge_scalar(b"c");
+
+		LinkProof {
+			R_1,
+			R_2,
+			s_v: k_v + c * value,
+			s_1: k_1 + c * blinding1,
+			s_2: k_2 + c * blinding2,
+		}
+	}
+
+	/// Verifies that `commitment1` and `commitment2` open to the same value.
+	///
+	/// The `transcript1` and `transcript2` must be the transcripts of the
+	/// two proofs, after their verification.  Both are advanced by the link.
+	pub fn verify(
+		&self,
+		commitment1: &CompressedRistretto,
+		commitment2: &CompressedRistretto,
+		transcript1: &mut Transcript,
+		transcript2: &mut Transcript,
+		pc_gens: &PedersenGens,
+	) -> Result<(), R1CSError> {
+		let mut transcript = link_transcript(transcript1, transcript2, commitment1, commitment2);
+		transcript.validate_and_append_point(b"R_1", &self.R_1)?;
+		transcript.validate_and_append_point(b"R_2", &self.R_2)?;
+
+		let c = transcript.challenge_scalar(b"c");
+
+		// s_v * B + s_i * B_blinding - c * V_i - R_i == 0
+		let check = |s_i: &Scalar, V_i: &CompressedRistretto, R_i: &CompressedRistretto| {
+			RistrettoPoint::optional_multiscalar_mul(&[self.s_v, *s_i, -c, -Scalar::one()], vec![
+				Some(pc_gens.B),
+				Some(pc_gens.B_blinding),
+				V_i.decompress(),
+				R_i.decompress(),
+			])
+			.ok_or(R1CSError::VerificationError)
+		};
+
+		if check(&self.s_1, commitment1, &self.R_1)?.is_identity()
+			&& check(&self.s_2, commitment2, &self.R_2)?.is_identity()
+		{
+			Ok(())
+		} else {
+			Err(R1CSError::VerificationError)
+		}
+	}
+
+	/// Serializes the proof into a byte array of 5 32-byte elements.
+	///
+	/// # Layout
+	///
+	/// The layout of the link proof encoding is:
+	/// * two compressed Ristretto points \\(R_1, R_2\\),
+	/// * three scalars \\(s_v, s_1, s_2\\).
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut buf = Vec::with_capacity(5 * 32);
+		buf.extend_from_slice(self.R_1.as_bytes());
+		buf.extend_from_slice(self.R_2.as_bytes());
+		buf.extend_from_slice(self.s_v.as_bytes());
+		buf.extend_from_slice(self.s_1.as_bytes());
+		buf.extend_from_slice(self.s_2.as_bytes());
+		buf
+	}
+
+	/// Deserializes the proof from a byte slice.
+	///
+	/// Returns an error if the byte slice cannot be parsed into a `LinkProof`.
+	pub fn from_bytes(slice: &[u8]) -> Result<LinkProof, R1CSError> {
+		if slice.len() != 5 * 32 {
+			return Err(R1CSError::FormatError);
+		}
+
+		let R_1 = CompressedRistretto(util::read32(&slice[..]));
+		let R_2 = CompressedRistretto(util::read32(&slice[32..]));
+		let s_v = Scalar::from_canonical_bytes(util::read32(&slice[64..])).ok_or(R1CSError::FormatError)?;
+		let s_1 = Scalar::from_canonical_bytes(util::read32(&slice[96..])).ok_or(R1CSError::FormatError)?;
+		let s_2 = Scalar::from_canonical_bytes(util::read32(&slice[128..])).ok_or(R1CSError::FormatError)?;
+
+		Ok(LinkProof {
+			R_1,
+			R_2,
+			s_v,
+			s_1,
+			s_2,
+		})
+	}
+}
+
+/// Derives the transcript of the link from the transcripts of the two
+/// linked proofs and the commitments to the shared value.
+fn link_transcript(
+	transcript1: &mut Transcript,
+	transcript2: &mut Transcript,
+	V_1: &CompressedRistretto,
+	V_2: &CompressedRistretto,
+) -> Transcript {
+	let mut seed1 = [0u8; 32];
+	transcript1.link_domain_sep();
+	transcript1.append_point(b"V", V_1);
+	challenge_bytes(transcript1, b"link-seed", &mut seed1);
+
+	let mut seed2 = [0u8; 32];
+	transcript2.link_domain_sep();
+	transcript2.append_point(b"V", V_2);
+	challenge_bytes(transcript2, b"link-seed", &mut seed2);
+
+	let mut transcript = Transcript::new(b"bulletproofs link v1");
+	append_message(&mut transcript, b"seed1", &seed1);
+	append_message(&mut transcript, b"seed2", &seed2);
+	transcript.append_point(b"V_1", V_1);
+	transcript.append_point(b"V_2", V_2);
+	transcript
+}
+
+#[cfg(feature = "std")]
+impl Serialize for LinkProof {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		serializer.serialize_bytes(&self.to_bytes()[..])
+	}
+}
+
+#[cfg(feature = "std")]
+impl<'de> Deserialize<'de> for LinkProof {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		struct LinkProofVisitor;
+
+		impl<'de> Visitor<'de> for LinkProofVisitor {
+			type Value = LinkProof;
+
+			fn expecting(&self, formatter: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+				formatter.write_str("a valid LinkProof")
+			}
+
+			fn visit_bytes<E>(self, v: &[u8]) -> Result<LinkProof, E>
+			where
+				E: serde::de::Error,
+			{
+				LinkProof::from_bytes(v).map_err(serde::de::Error::custom)
+			}
+		}
+
+		deserializer.deserialize_bytes(LinkProofVisitor)
+	}
+}
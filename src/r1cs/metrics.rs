@@ -0,0 +1,32 @@
+//! Size metrics of a constraint system.
+
+/// Counts describing the size of a constraint system.
+///
+/// Returned by [`Prover::metrics`](::r1cs::Prover::metrics) and
+/// [`Verifier::metrics`](::r1cs::Verifier::metrics), which can be called at
+/// any point during synthesis, e.g. before and after a sub-gadget to
+/// measure its cost.  Randomized constraints are only synthesized during
+/// proving and verification, so they are not counted.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Metrics {
+	/// Number of multipliers.
+	pub multipliers: usize,
+	/// Number of linear constraints.
+	pub constraints: usize,
+	/// Number of committed high-level variables.
+	pub committed: usize,
+	/// Number of multipliers, padded to a power of two.  This is the
+	/// `gens_capacity` the [`BulletproofGens`](::BulletproofGens) need.
+	pub padded_n: usize,
+}
+
+impl Metrics {
+	pub(crate) fn new(multipliers: usize, constraints: usize, committed: usize) -> Metrics {
+		Metrics {
+			multipliers,
+			constraints,
+			committed,
+			padded_n: multipliers.next_power_of_two(),
+		}
+	}
+}
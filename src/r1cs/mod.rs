@@ -3,18 +3,43 @@
 #[doc(include = "../../docs/cs-proof.md")]
 mod notes {}
 
+pub mod aggregation;
+mod circuit;
 mod constraint_system;
+mod gadgets;
 mod linear_combination;
+mod link_proof;
+mod metrics;
+mod observer;
 mod proof;
 mod prover;
+mod snapshot;
+mod synthesis_trace;
+mod verification_key;
 mod verifier;
 
 pub use self::{
+	circuit::CircuitDescription,
 	constraint_system::{ConstraintSystem, RandomizableConstraintSystem, RandomizedConstraintSystem},
+	gadgets::{
+		check_commitment_sum, conservation_gadget, is_nonzero_gadget, merkle_root, merkle_root_gadget,
+		prove_gadget_with_rng, set_membership_1_gadget, set_non_membership_gadget, verify_gadget_with_rng,
+		verify_with_key_and_rng, AllocatedScalar, Assignment, CircuitHash, Conservation, Gadget, GadgetVerificationKey,
+		MerklePath,
+	},
 	linear_combination::{LinearCombination, Variable},
-	proof::R1CSProof,
-	prover::Prover,
-	verifier::Verifier,
+	link_proof::LinkProof,
+	metrics::Metrics,
+	observer::{Phase, ProverObserver, Stats},
+	proof::{estimated_verification_msm_size, proof_size, randomized_proof_size, R1CSProof},
+	prover::{Prover, ProverCheckpoint},
+	snapshot::Snapshot,
+	synthesis_trace::{diff, Divergence, Operation, SynthesisTrace},
+	verification_key::VerificationKey,
+	verifier::{BatchCircuit, PreVerified, Verifier},
 };
 
+#[cfg(feature = "std")]
+pub use self::gadgets::{prove_gadget, verify_gadget, verify_with_key};
+
 pub use crate::errors::R1CSError;
@@ -0,0 +1,92 @@
+//! Instrumentation hooks for the phases of proving and verification.
+
+use super::Metrics;
+
+/// A phase of the creation or verification of a constraint system proof.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Phase {
+	/// The prover commits to the first-phase low-level variables.
+	FirstPhaseCommitment,
+	/// The randomized constraints are synthesized.
+	Synthesis,
+	/// The prover commits to the second-phase low-level variables, if the
+	/// constraint system has randomized constraints.
+	SecondPhaseCommitment,
+	/// The constraints are flattened into weight vectors.
+	Flattening,
+	/// The prover computes and commits to the polynomial \\(t(x)\\).
+	Polynomial,
+	/// The prover creates the inner-product proof.
+	InnerProduct,
+	/// The verifier performs the multiscalar multiplication of its final
+	/// check.
+	Multiscalar,
+}
+
+/// Statistics about a proof, reported once the constraint system is fully
+/// synthesized.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Stats {
+	/// The size of the constraint system, including the randomized
+	/// constraints.
+	pub metrics: Metrics,
+	/// Number of terms of the multiscalar multiplications which depend on
+	/// the size of the circuit: the commitments to the low-level variables
+	/// for a prover, and the final check for a verifier.
+	pub multiscalar_terms: usize,
+}
+
+/// Observes the phases of a [`Prover`](::r1cs::Prover) or a
+/// [`Verifier`](::r1cs::Verifier), for instance to export timings as
+/// metrics.
+///
+/// The observer is set with
+/// [`Prover::set_observer`](::r1cs::Prover::set_observer) or
+/// [`Verifier::set_observer`](::r1cs::Verifier::set_observer), and is
+/// called synchronously from the proving or verifying thread.  Every
+/// callback does nothing by default.
+///
+/// A prover goes through the phases in the order
+/// [`Phase::FirstPhaseCommitment`], [`Phase::Synthesis`],
+/// [`Phase::SecondPhaseCommitment`], [`Phase::Flattening`],
+/// [`Phase::Polynomial`] and [`Phase::InnerProduct`], and a verifier
+/// through [`Phase::Synthesis`], [`Phase::Flattening`] and
+/// [`Phase::Multiscalar`].  The [`Stats`] are reported after the
+/// synthesis.  A phase which fails is not ended.
+pub trait ProverObserver {
+	/// Called when the `phase` starts.
+	fn on_phase_start(&self, _phase: Phase) {}
+
+	/// Called when the `phase` ends.
+	fn on_phase_end(&self, _phase: Phase) {}
+
+	/// Called with the `stats` of the proof.
+	fn on_stats(&self, _stats: &Stats) {}
+}
+
+/// The optional observer of a prover or verifier.
+#[derive(Copy, Clone, Default)]
+pub(super) struct Observer<'a>(pub(super) Option<&'a dyn ProverObserver>);
+
+impl<'a> Observer<'a> {
+	#[inline]
+	pub(super) fn start(&self, phase: Phase) {
+		if let Some(observer) = self.0 {
+			observer.on_phase_start(phase);
+		}
+	}
+
+	#[inline]
+	pub(super) fn end(&self, phase: Phase) {
+		if let Some(observer) = self.0 {
+			observer.on_phase_end(phase);
+		}
+	}
+
+	#[inline]
+	pub(super) fn stats(&self, stats: impl FnOnce() -> Stats) {
+		if let Some(observer) = self.0 {
+			observer.on_stats(&stats());
+		}
+	}
+}
@@ -10,9 +10,9 @@ use curve25519_dalek::{
 
 use crate::{errors::R1CSError, inner_product_proof::InnerProductProof, util};
 
-#[cfg(feature = "std")]
+#[cfg(feature = "serde")]
 use serde::de::Visitor;
-#[cfg(feature = "std")]
+#[cfg(feature = "serde")]
 use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
 
 const ONE_PHASE_COMMITMENTS: u8 = 0;
@@ -34,7 +34,7 @@ const TWO_PHASE_COMMITMENTS: u8 = 1;
 /// the constraint system using
 /// [`VerifierCS::verify`](::r1cs::VerifierCS::verify) to verify the
 /// proof.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 #[allow(non_snake_case)]
 pub struct R1CSProof {
 	/// Commitment to the values of input wires in the first phase.
@@ -70,6 +70,47 @@ pub struct R1CSProof {
 	pub(super) ipp_proof: InnerProductProof,
 }
 
+/// Returns the size in bytes of the serialization of an [`R1CSProof`] for
+/// a circuit with `n_multipliers` multipliers, as produced by
+/// [`R1CSProof::to_bytes`].
+///
+/// This assumes that no multipliers are allocated in the randomized phase;
+/// otherwise the proof also contains the second-phase commitments, which
+/// [`randomized_proof_size`] accounts for.
+///
+/// The proof size does not depend on the number of commitments: the
+/// high-level commitments are transmitted separately, so the
+/// `_m_commitments` parameter is only kept for symmetry with
+/// [`estimated_verification_msm_size`].
+pub fn proof_size(n_multipliers: usize, _m_commitments: usize) -> usize {
+	// version tag + 11 elements + the ipp
+	1 + 11 * 32 + InnerProductProof::serialized_size_for(n_multipliers)
+}
+
+/// Returns the size in bytes of the serialization of an [`R1CSProof`] for
+/// a circuit with `n_multipliers` multipliers in total, some of which are
+/// allocated in the randomized phase.
+///
+/// As with [`proof_size`], the size does not depend on the number of
+/// commitments.
+pub fn randomized_proof_size(n_multipliers: usize, _m_commitments: usize) -> usize {
+	// the 3 second-phase commitments are added to the proof
+	proof_size(n_multipliers, 0) + 3 * 32
+}
+
+/// Returns the number of points in the multiscalar multiplication
+/// performed to verify a proof for a circuit with `n_multipliers`
+/// multipliers and `m_commitments` high-level commitments.
+///
+/// This is the size of the multiplication passed to the
+/// [`MsmBackend`](::MsmBackend), which dominates the verification time.
+pub fn estimated_verification_msm_size(n_multipliers: usize, m_commitments: usize) -> usize {
+	let padded_n = n_multipliers.next_power_of_two();
+	let lg_n = padded_n.trailing_zeros() as usize;
+	// V + 11 proof points + L, R + B, B_blinding + G, H
+	m_commitments + 11 + 2 * lg_n + 2 + 2 * padded_n
+}
+
 impl R1CSProof {
 	/// Serializes the proof into a byte array of 1 version byte + \\((13 or 16)
 	/// + 2k\\) 32-byte elements, where \\(k=\lceil \log_2(n) \rceil\\) and
@@ -123,10 +164,75 @@ impl R1CSProof {
 		1 + elements * 32 + self.ipp_proof.serialized_size()
 	}
 
+	/// Returns the inner-product sub-proof.
+	///
+	/// Its number of rounds is \\(\lg n\\), where \\(n\\) is the number of
+	/// multipliers of the circuit padded to a power of two.
+	pub fn ipp_proof(&self) -> &InnerProductProof {
+		&self.ipp_proof
+	}
+
 	fn missing_phase2_commitments(&self) -> bool {
 		self.A_I2.is_identity() && self.A_O2.is_identity() && self.S2.is_identity()
 	}
 
+	/// Writes the serialized proof to `writer` without building the
+	/// whole encoding in memory.
+	///
+	/// The bytes written are exactly those of [`R1CSProof::to_bytes`].
+	/// Returns the number of bytes written.
+	#[cfg(feature = "std")]
+	pub fn write_to<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<usize> {
+		let mut points = vec![self.A_I1, self.A_O1, self.S1];
+		if self.missing_phase2_commitments() {
+			writer.write_all(&[ONE_PHASE_COMMITMENTS])?;
+		} else {
+			writer.write_all(&[TWO_PHASE_COMMITMENTS])?;
+			points.extend_from_slice(&[self.A_I2, self.A_O2, self.S2]);
+		}
+		points.extend_from_slice(&[self.T_1, self.T_3, self.T_4, self.T_5, self.T_6]);
+		for point in points.iter() {
+			writer.write_all(point.as_bytes())?;
+		}
+		writer.write_all(self.t_x.as_bytes())?;
+		writer.write_all(self.t_x_blinding.as_bytes())?;
+		writer.write_all(self.e_blinding.as_bytes())?;
+		Ok(1 + (points.len() + 3) * 32 + self.ipp_proof.write_to(writer)?)
+	}
+
+	/// Reads a proof in the [`R1CSProof::to_bytes`] format from `reader`,
+	/// consuming it to the end.
+	///
+	/// Returns an error if reading fails, if more than `max_size` bytes are
+	/// available, or if the bytes cannot be parsed into a `R1CSProof`.
+	#[cfg(feature = "std")]
+	pub fn read_from<R: std::io::Read>(reader: R, max_size: usize) -> Result<R1CSProof, R1CSError> {
+		let buf = util::read_bounded(reader, max_size).ok_or(R1CSError::FormatError)?;
+		R1CSProof::from_bytes(&buf)
+	}
+
+	/// Writes the serialized proof to `writer`, prefixed with its length as
+	/// a little-endian `u32`, so that it can be embedded in a larger
+	/// message and read back with [`R1CSProof::read_framed`].
+	///
+	/// Returns the number of bytes written, including the prefix.
+	#[cfg(feature = "std")]
+	pub fn write_framed<W: std::io::Write>(&self, writer: W) -> std::io::Result<usize> {
+		util::write_framed(writer, self.serialized_size(), |writer| self.write_to(writer))
+	}
+
+	/// Reads a proof written by [`R1CSProof::write_framed`] from `reader`,
+	/// consuming only the bytes of the frame.
+	///
+	/// Returns an error if reading fails or the input ends early, if the
+	/// frame is longer than `max_size` bytes, or if its content cannot be
+	/// parsed into a `R1CSProof`.
+	#[cfg(feature = "std")]
+	pub fn read_framed<R: std::io::Read>(reader: R, max_size: usize) -> Result<R1CSProof, R1CSError> {
+		let buf = util::read_framed(reader, max_size).ok_or(R1CSError::FormatError)?;
+		R1CSProof::from_bytes(&buf)
+	}
+
 	/// Deserializes the proof from a byte slice.
 	///
 	/// Returns an error if the byte slice cannot be parsed into a `R1CSProof`.
@@ -208,7 +314,7 @@ impl R1CSProof {
 	}
 }
 
-#[cfg(feature = "std")]
+#[cfg(feature = "serde")]
 impl Serialize for R1CSProof {
 	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
 	where
@@ -218,7 +324,7 @@ impl Serialize for R1CSProof {
 	}
 }
 
-#[cfg(feature = "std")]
+#[cfg(feature = "serde")]
 impl<'de> Deserialize<'de> for R1CSProof {
 	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
 	where
@@ -245,6 +351,19 @@ impl<'de> Deserialize<'de> for R1CSProof {
 				#[cfg(not(feature = "std"))]
 				return R1CSProof::from_bytes(v).map_err(|_| serde::de::Error::custom("deserialization error"));
 			}
+
+			fn visit_seq<A>(self, mut seq: A) -> Result<R1CSProof, A::Error>
+			where
+				A: serde::de::SeqAccess<'de>,
+			{
+				// Self-describing formats such as JSON encode byte arrays
+				// as sequences of integers.
+				let mut bytes = Vec::new();
+				while let Some(byte) = seq.next_element::<u8>()? {
+					bytes.push(byte);
+				}
+				self.visit_bytes(&bytes)
+			}
 		}
 
 		deserializer.deserialize_bytes(R1CSProofVisitor)
@@ -1,8 +1,7 @@
 #![allow(non_snake_case)]
 
-use alloc::{boxed::Box, vec::Vec};
-use clear_on_drop::clear::Clear;
-use core::mem;
+use alloc::{boxed::Box, rc::Rc, vec::Vec};
+use core::{cell::Cell, mem};
 use curve25519_dalek::{
 	ristretto::{CompressedRistretto, RistrettoPoint},
 	scalar::Scalar,
@@ -12,18 +11,28 @@ use merlin::Transcript;
 use rand_core::{CryptoRng, RngCore};
 
 use super::{
-	ConstraintSystem, LinearCombination, R1CSProof, RandomizableConstraintSystem, RandomizedConstraintSystem, Variable,
+	circuit,
+	constraint_system::call_within_namespaces,
+	observer::{Observer, Phase, ProverObserver, Stats},
+	proof_size, CircuitDescription, ConstraintSystem, LinearCombination, Metrics, Operation, R1CSProof,
+	RandomizableConstraintSystem, RandomizedConstraintSystem, Snapshot, SynthesisTrace, Variable,
 };
 
 use crate::{
 	errors::R1CSError,
 	generators::{BulletproofGens, PedersenGens},
 	inner_product_proof::InnerProductProof,
-	transcript::TranscriptProtocol,
+	progress::{CancellationToken, Milestone, Progress, ProgressSink},
+	transcript::{append_message, append_u64, challenge_bytes, TranscriptProtocol},
+	util,
 };
 
+#[cfg(feature = "zeroize")]
+use crate::util::ZeroizeOnDrop;
 #[cfg(feature = "std")]
 use rand::thread_rng;
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
 
 /// A [`ConstraintSystem`] implementation for use by the prover.
 ///
@@ -37,8 +46,16 @@ use rand::thread_rng;
 pub struct Prover<'t, 'g> {
 	transcript: &'t mut Transcript,
 	pc_gens: &'g PedersenGens,
+	/// The label of the circuit, empty if the constraint system is
+	/// unlabeled.
+	label: &'static [u8],
 	/// The constraints accumulated so far.
 	constraints: Vec<LinearCombination>,
+	/// The labels of the constraints, if any.
+	constraint_labels: Vec<Option<&'static str>>,
+	/// Whether unsatisfied constraints are reported when proving instead
+	/// of panicking when they are added.
+	diagnostics: bool,
 	/// Stores assignments to the "left" of multiplication gates
 	a_L: Vec<Scalar>,
 	/// Stores assignments to the "right" of multiplication gates
@@ -52,12 +69,67 @@ pub struct Prover<'t, 'g> {
 
 	/// This list holds closures that will be called in the second phase of the
 	/// protocol, when non-randomized variables are committed.
-	deferred_constraints: Vec<Box<dyn Fn(&mut RandomizingProver<'t, 'g>) -> Result<(), R1CSError>>>,
+	deferred_constraints: Vec<DeferredConstraint<'t, 'g>>,
 
 	/// Index of a pending multiplier that's not fully assigned yet.
 	pending_multiplier: Option<usize>,
+
+	/// Messages absorbed into the transcript by the prover since the
+	/// domain separator, kept so that a checkpoint can replay them.
+	transcript_log: Vec<(&'static [u8], Vec<u8>)>,
+
+	/// Structural log of the synthesis, if it is being recorded.
+	synthesis_trace: Option<SynthesisTrace>,
+
+	/// Labels of the namespaces entered, outermost first.
+	namespaces: Vec<&'static [u8]>,
+
+	/// Observer of the phases of proving, if any.
+	observer: Observer<'t>,
+
+	/// Whether the circuit hash is appended to the transcript when proving.
+	bind_circuit_hash: bool,
 }
 
+/// A snapshot of a partially synthesized [`Prover`].
+///
+/// Created by [`Prover::checkpoint`] and turned back into a prover with
+/// [`Prover::restore`].  Merlin transcripts cannot be copied out of their
+/// borrow or serialized, so the checkpoint keeps a log of the messages the
+/// prover absorbed and replays it into a fresh transcript on restore.
+///
+/// ## WARNING
+///
+/// A checkpoint contains the full witness (value openings, blinding factors
+/// and low-level assignments) and must be handled as secret data.
+pub struct ProverCheckpoint<'t, 'g> {
+	pc_gens: &'g PedersenGens,
+	label: &'static [u8],
+	constraints: Vec<LinearCombination>,
+	constraint_labels: Vec<Option<&'static str>>,
+	diagnostics: bool,
+	a_L: Vec<Scalar>,
+	a_R: Vec<Scalar>,
+	a_O: Vec<Scalar>,
+	v: Vec<Scalar>,
+	v_blinding: Vec<Scalar>,
+	deferred_constraints: Vec<DeferredConstraint<'t, 'g>>,
+	pending_multiplier: Option<usize>,
+	transcript_log: Vec<(&'static [u8], Vec<u8>)>,
+	synthesis_trace: Option<SynthesisTrace>,
+	namespaces: Vec<&'static [u8]>,
+	observer: Observer<'t>,
+	bind_circuit_hash: bool,
+}
+
+/// A callback for randomized constraints with the namespaces it was specified
+/// in, shared between a prover and its checkpoints and taken by the first of
+/// them to synthesize it.
+type DeferredConstraint<'t, 'g> = (
+	Vec<&'static [u8]>,
+	Rc<Cell<Option<Box<dyn FnOnce(&mut RandomizingProver<'t, 'g>) -> Result<(), R1CSError>>>>>,
+);
+
 /// Prover in the randomizing phase.
 ///
 /// Note: this type is exported because it is used to specify the associated
@@ -73,25 +145,54 @@ pub struct RandomizingProver<'t, 'g> {
 /// Overwrite secrets with null bytes when they go out of scope.
 impl<'t, 'g> Drop for Prover<'t, 'g> {
 	fn drop(&mut self) {
-		self.v.clear();
-		self.v_blinding.clear();
-
-		// Important: due to how ClearOnDrop auto-implements InitializableFromZeroed
-		// for T: Default, calling .clear() on Vec compiles, but does not
-		// clear the content. Instead, it only clears the Vec's header.
-		// Clearing the underlying buffer item-by-item will do the job, but will
-		// keep the header as-is, which is fine since the header does not contain
-		// secrets.
-		for e in self.a_L.iter_mut() {
-			e.clear();
-		}
-		for e in self.a_R.iter_mut() {
-			e.clear();
-		}
-		for e in self.a_O.iter_mut() {
-			e.clear();
-		}
-		// XXX use ClearOnDrop instead of doing the above
+		self.clear_witness();
+	}
+}
+
+/// Overwrite secrets with null bytes when they go out of scope.
+impl<'t, 'g> Drop for ProverCheckpoint<'t, 'g> {
+	fn drop(&mut self) {
+		self.clear_witness();
+	}
+}
+
+/// Wipes the witness of the prover, which is also done when it is dropped,
+/// and drops the callbacks for randomized constraints.
+#[cfg(feature = "zeroize")]
+impl<'t, 'g> Zeroize for Prover<'t, 'g> {
+	fn zeroize(&mut self) {
+		self.clear_witness();
+		self.deferred_constraints.clear();
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl<'t, 'g> ZeroizeOnDrop for Prover<'t, 'g> {}
+
+/// Wipes the witness of the checkpoint, which is also done when it is
+/// dropped, and drops the callbacks for randomized constraints.
+#[cfg(feature = "zeroize")]
+impl<'t, 'g> Zeroize for ProverCheckpoint<'t, 'g> {
+	fn zeroize(&mut self) {
+		self.clear_witness();
+		self.deferred_constraints.clear();
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl<'t, 'g> ZeroizeOnDrop for ProverCheckpoint<'t, 'g> {}
+
+impl<'t, 'g> ProverCheckpoint<'t, 'g> {
+	/// Overwrites the value openings, blinding factors and low-level
+	/// assignments with zeroes.
+	fn clear_witness(&mut self) {
+		util::clear_scalars(&mut [
+			&mut self.v,
+			&mut self.v_blinding,
+			&mut self.a_L,
+			&mut self.a_R,
+			&mut self.a_O,
+		]);
 	}
 }
 
@@ -118,6 +219,9 @@ impl<'t, 'g> ConstraintSystem for Prover<'t, 'g> {
 		self.a_L.push(l);
 		self.a_R.push(r);
 		self.a_O.push(o);
+		self.record(Operation::Multiply {
+			index: self.a_O.len() - 1,
+		});
 
 		// Constrain l,r,o:
 		left.terms.push((l_var, -Scalar::one()));
@@ -129,7 +233,13 @@ impl<'t, 'g> ConstraintSystem for Prover<'t, 'g> {
 	}
 
 	fn allocate(&mut self, assignment: Option<Scalar>) -> Result<Variable, R1CSError> {
-		let scalar = assignment.ok_or(R1CSError::MissingAssignment)?;
+		let scalar = assignment.ok_or(R1CSError::MissingAssignment {
+			variable: Some(match self.pending_multiplier {
+				None => Variable::MultiplierLeft(self.a_L.len()),
+				Some(i) => Variable::MultiplierRight(i),
+			}),
+			context: None,
+		})?;
 
 		match self.pending_multiplier {
 			None => {
@@ -138,12 +248,18 @@ impl<'t, 'g> ConstraintSystem for Prover<'t, 'g> {
 				self.a_L.push(scalar);
 				self.a_R.push(Scalar::zero());
 				self.a_O.push(Scalar::zero());
+				self.record(Operation::Allocate {
+					variable: Variable::MultiplierLeft(i),
+				});
 				Ok(Variable::MultiplierLeft(i))
 			}
 			Some(i) => {
 				self.pending_multiplier = None;
 				self.a_R[i] = scalar;
 				self.a_O[i] = self.a_L[i] * self.a_R[i];
+				self.record(Operation::Allocate {
+					variable: Variable::MultiplierRight(i),
+				});
 				Ok(Variable::MultiplierRight(i))
 			}
 		}
@@ -153,7 +269,10 @@ impl<'t, 'g> ConstraintSystem for Prover<'t, 'g> {
 		&mut self,
 		input_assignments: Option<(Scalar, Scalar)>,
 	) -> Result<(Variable, Variable, Variable), R1CSError> {
-		let (l, r) = input_assignments.ok_or(R1CSError::MissingAssignment)?;
+		let (l, r) = input_assignments.ok_or(R1CSError::MissingAssignment {
+			variable: Some(Variable::MultiplierLeft(self.a_L.len())),
+			context: None,
+		})?;
 		let o = l * r;
 
 		// Create variables for l,r,o ...
@@ -164,6 +283,9 @@ impl<'t, 'g> ConstraintSystem for Prover<'t, 'g> {
 		self.a_L.push(l);
 		self.a_R.push(r);
 		self.a_O.push(o);
+		self.record(Operation::AllocateMultiplier {
+			index: self.a_O.len() - 1,
+		});
 
 		Ok((l_var, r_var, o_var))
 	}
@@ -173,9 +295,11 @@ impl<'t, 'g> ConstraintSystem for Prover<'t, 'g> {
 	}
 
 	fn constrain(&mut self, lc: LinearCombination) {
-		let res = self.evaluate_lc(&lc).unwrap();
-		assert!(res == Scalar::zero());
-		self.constraints.push(lc);
+		self.add_constraint(lc, None)
+	}
+
+	fn constrain_labeled(&mut self, lc: LinearCombination, label: &'static str) {
+		self.add_constraint(lc, Some(label))
 	}
 
 	fn evaluate_lc(&self, lc: &LinearCombination) -> Option<Scalar> {
@@ -190,6 +314,25 @@ impl<'t, 'g> ConstraintSystem for Prover<'t, 'g> {
 			_ => Err(R1CSError::FormatError),
 		}
 	}
+
+	fn commit_public_bytes(&mut self, label: &'static [u8], bytes: &[u8]) -> Result<(), R1CSError> {
+		self.transcript.r1cs_public_input(label, bytes);
+		// Log the messages appended by `r1cs_public_input`.
+		self.transcript_log.push((b"dom-sep", b"r1cs-public-input".to_vec()));
+		self.transcript_log.push((label, bytes.to_vec()));
+		self.record(Operation::CommitPublic { label });
+		Ok(())
+	}
+
+	fn push_namespace(&mut self, label: &'static [u8]) {
+		self.namespaces.push(label);
+	}
+
+	fn pop_namespace(&mut self) {
+		self.namespaces
+			.pop()
+			.expect("pop_namespace called without a matching push_namespace");
+	}
 }
 
 impl<'t, 'g> RandomizableConstraintSystem for Prover<'t, 'g> {
@@ -197,9 +340,11 @@ impl<'t, 'g> RandomizableConstraintSystem for Prover<'t, 'g> {
 
 	fn specify_randomized_constraints<F>(&mut self, callback: F) -> Result<(), R1CSError>
 	where
-		F: 'static + Fn(&mut Self::RandomizedCS) -> Result<(), R1CSError>,
+		F: 'static + FnOnce(&mut Self::RandomizedCS) -> Result<(), R1CSError>,
 	{
-		self.deferred_constraints.push(Box::new(callback));
+		self.deferred_constraints
+			.push((self.namespaces.clone(), Rc::new(Cell::new(Some(Box::new(callback))))));
+		self.record(Operation::Randomize);
 		Ok(())
 	}
 }
@@ -232,6 +377,10 @@ impl<'t, 'g> ConstraintSystem for RandomizingProver<'t, 'g> {
 		self.prover.constrain(lc)
 	}
 
+	fn constrain_labeled(&mut self, lc: LinearCombination, label: &'static str) {
+		self.prover.constrain_labeled(lc, label)
+	}
+
 	fn evaluate_lc(&self, lc: &LinearCombination) -> Option<Scalar> {
 		self.prover.evaluate_lc(lc)
 	}
@@ -239,12 +388,62 @@ impl<'t, 'g> ConstraintSystem for RandomizingProver<'t, 'g> {
 	fn allocate_single(&mut self, assignment: Option<Scalar>) -> Result<(Variable, Option<Variable>), R1CSError> {
 		self.prover.allocate_single(assignment)
 	}
+
+	fn commit_public_bytes(&mut self, _label: &'static [u8], _bytes: &[u8]) -> Result<(), R1CSError> {
+		Err(R1CSError::PublicInputAfterChallenge)
+	}
+
+	fn push_namespace(&mut self, label: &'static [u8]) {
+		self.prover.transcript.r1cs_namespace_begin(label);
+		self.prover.namespaces.push(label);
+	}
+
+	fn pop_namespace(&mut self) {
+		let label = self
+			.prover
+			.namespaces
+			.pop()
+			.expect("pop_namespace called without a matching push_namespace");
+		self.prover.transcript.r1cs_namespace_end(label);
+	}
 }
 
 impl<'t, 'g> RandomizedConstraintSystem for RandomizingProver<'t, 'g> {
 	fn challenge_scalar(&mut self, label: &'static [u8]) -> Scalar {
 		self.prover.transcript.challenge_scalar(label)
 	}
+
+	fn challenge_bytes(&mut self, label: &'static [u8], dest: &mut [u8]) {
+		challenge_bytes(self.prover.transcript, label, dest)
+	}
+}
+
+/// The generators of a proof, which are grown on demand if the prover may
+/// mutate them.
+enum ProverGens<'a> {
+	Fixed(&'a BulletproofGens),
+	Growable(&'a mut BulletproofGens),
+}
+
+impl<'a> ProverGens<'a> {
+	/// Returns the generators, with at least `capacity` generators per
+	/// party.
+	///
+	/// Growable generators are extended if they are too small, while fixed
+	/// ones cause an [`R1CSError::InvalidGeneratorsLength`] error.
+	fn reserve(&mut self, capacity: usize) -> Result<&BulletproofGens, R1CSError> {
+		match self {
+			ProverGens::Fixed(bp_gens) if bp_gens.gens_capacity < capacity => Err(R1CSError::InvalidGeneratorsLength {
+				required: BulletproofGens::required_capacity_for_r1cs(capacity).gens_capacity,
+				actual: bp_gens.gens_capacity,
+			}),
+			ProverGens::Fixed(bp_gens) => Ok(*bp_gens),
+			ProverGens::Growable(bp_gens) => {
+				bp_gens.increase_capacity(capacity);
+				Ok(&**bp_gens)
+			}
+		}
+	}
 }
 
 impl<'t, 'g> Prover<'t, 'g> {
@@ -269,22 +468,153 @@ impl<'t, 'g> Prover<'t, 'g> {
 	///
 	/// Returns a new `Prover` instance.
 	pub fn new(pc_gens: &'g PedersenGens, transcript: &'t mut Transcript) -> Self {
-		transcript.r1cs_domain_sep();
+		Prover::new_labeled(pc_gens, transcript, b"")
+	}
+
+	/// Construct an empty constraint system like [`Prover::new`], and bind
+	/// the Pedersen bases of `pc_gens` to the transcript.
+	///
+	/// This is meant for commitments created by another system under
+	/// bases other than the default ones, for instance with
+	/// [`PedersenGens::from_points`]: binding the bases makes every
+	/// challenge depend on them, so that a proof cannot be mistaken for a
+	/// proof under other bases.  The proof only verifies with a
+	/// [`Verifier`](::r1cs::Verifier) created with
+	/// [`Verifier::new_with_gens`](::r1cs::Verifier::new_with_gens) for the
+	/// same bases.  The default bases are not bound, so with them this is
+	/// the same as [`Prover::new`].
+	pub fn new_with_gens(pc_gens: &'g PedersenGens, transcript: &'t mut Transcript) -> Self {
+		let mut prover = Prover::new(pc_gens, transcript);
+		if pc_gens.is_default() {
+			return prover;
+		}
+		let B = pc_gens.B.compress();
+		let B_blinding = pc_gens.B_blinding.compress();
+		prover.transcript.r1cs_pedersen_gens(&B, &B_blinding);
+		prover.transcript_log.push((b"dom-sep", b"r1cs-pedersen-gens".to_vec()));
+		prover.transcript_log.push((b"B", B.as_bytes().to_vec()));
+		prover
+			.transcript_log
+			.push((b"B_blinding", B_blinding.as_bytes().to_vec()));
+		prover
+	}
+
+	/// Construct an empty constraint system for the circuit `label`, like
+	/// [`Prover::new`].
+	///
+	/// The label is appended to the transcript with the domain separator,
+	/// so that a proof only verifies with a [`Verifier`](::r1cs::Verifier)
+	/// created with [`Verifier::new_labeled`](::r1cs::Verifier::new_labeled)
+	/// for the same circuit.  An empty label is the same as
+	/// [`Prover::new`].
+	pub fn new_labeled(pc_gens: &'g PedersenGens, transcript: &'t mut Transcript, label: &'static [u8]) -> Self {
+		transcript.r1cs_domain_sep(label);
 
 		Prover {
 			pc_gens,
 			transcript,
+			label,
 			v: Vec::new(),
 			v_blinding: Vec::new(),
 			constraints: Vec::new(),
+			constraint_labels: Vec::new(),
+			diagnostics: false,
 			a_L: Vec::new(),
 			a_R: Vec::new(),
 			a_O: Vec::new(),
 			deferred_constraints: Vec::new(),
 			pending_multiplier: None,
+			transcript_log: Vec::new(),
+			synthesis_trace: if cfg!(debug_assertions) {
+				Some(SynthesisTrace::default())
+			} else {
+				None
+			},
+			namespaces: Vec::new(),
+			observer: Observer::default(),
+			bind_circuit_hash: false,
 		}
 	}
 
+	/// Captures the current state of the constraint system so that
+	/// synthesis can later be resumed with [`Prover::restore`].
+	///
+	/// The checkpoint records the constraints, all variable assignments,
+	/// the high-level commitment openings, pending randomized constraints
+	/// and the log of transcript messages absorbed by the prover.
+	///
+	/// Messages appended directly through
+	/// [`ConstraintSystem::transcript`] are not recorded and must be
+	/// appended again by the caller after restoring.
+	///
+	/// The pending randomized constraints are shared with the checkpoint,
+	/// since their callbacks can only be invoked once: only the first of
+	/// this prover and the provers restored from the checkpoint to create
+	/// a proof can synthesize them, and the others fail to prove with
+	/// [`R1CSError::RandomizedConstraintsConsumed`] before doing any work.
+	/// A checkpoint taken before any randomized constraints are specified
+	/// can be restored and proven from any number of times.
+	pub fn checkpoint(&self) -> ProverCheckpoint<'t, 'g> {
+		ProverCheckpoint {
+			pc_gens: self.pc_gens,
+			label: self.label,
+			constraints: self.constraints.clone(),
+			constraint_labels: self.constraint_labels.clone(),
+			diagnostics: self.diagnostics,
+			a_L: self.a_L.clone(),
+			a_R: self.a_R.clone(),
+			a_O: self.a_O.clone(),
+			v: self.v.clone(),
+			v_blinding: self.v_blinding.clone(),
+			deferred_constraints: self.deferred_constraints.clone(),
+			pending_multiplier: self.pending_multiplier,
+			transcript_log: self.transcript_log.clone(),
+			synthesis_trace: self.synthesis_trace.clone(),
+			namespaces: self.namespaces.clone(),
+			observer: self.observer,
+			bind_circuit_hash: self.bind_circuit_hash,
+		}
+	}
+
+	/// Reconstructs a prover from a [`ProverCheckpoint`].
+	///
+	/// The `transcript` must be in the same state as the transcript
+	/// originally passed to [`Prover::new`]; the domain separator and
+	/// every logged message are replayed into it, so that the restored
+	/// prover produces the same challenges as the original would have.
+	pub fn restore(mut checkpoint: ProverCheckpoint<'t, 'g>, transcript: &'t mut Transcript) -> Self {
+		transcript.r1cs_domain_sep(checkpoint.label);
+		for (label, message) in checkpoint.transcript_log.iter() {
+			append_message(transcript, label, message);
+		}
+
+		Prover {
+			pc_gens: checkpoint.pc_gens,
+			transcript,
+			label: checkpoint.label,
+			v: mem::replace(&mut checkpoint.v, Vec::new()),
+			v_blinding: mem::replace(&mut checkpoint.v_blinding, Vec::new()),
+			constraints: mem::replace(&mut checkpoint.constraints, Vec::new()),
+			constraint_labels: mem::replace(&mut checkpoint.constraint_labels, Vec::new()),
+			diagnostics: checkpoint.diagnostics,
+			a_L: mem::replace(&mut checkpoint.a_L, Vec::new()),
+			a_R: mem::replace(&mut checkpoint.a_R, Vec::new()),
+			a_O: mem::replace(&mut checkpoint.a_O, Vec::new()),
+			deferred_constraints: mem::replace(&mut checkpoint.deferred_constraints, Vec::new()),
+			pending_multiplier: checkpoint.pending_multiplier,
+			transcript_log: mem::replace(&mut checkpoint.transcript_log, Vec::new()),
+			synthesis_trace: checkpoint.synthesis_trace.take(),
+			namespaces: mem::replace(&mut checkpoint.namespaces, Vec::new()),
+			observer: checkpoint.observer,
+			bind_circuit_hash: checkpoint.bind_circuit_hash,
+		}
+	}
+
+	/// Reports the phases of proving to the `observer`.
+	pub fn set_observer(&mut self, observer: &'t dyn ProverObserver) {
+		self.observer = Observer(Some(observer));
+	}
+
 	/// Creates commitment to a high-level variable and adds it to the
 	/// transcript.
 	///
@@ -312,42 +642,109 @@ impl<'t, 'g> Prover<'t, 'g> {
 		// Add the commitment to the transcript.
 		let V = self.pc_gens.commit(v, v_blinding).compress();
 		self.transcript.append_point(b"V", &V);
+		self.transcript_log.push((b"V", V.as_bytes().to_vec()));
+		self.record(Operation::Commit {
+			variable: Variable::Committed(i),
+		});
 
 		(V, Variable::Committed(i))
 	}
 
-	/// Use a challenge, `z`, to flatten the constraints in the
-	/// constraint system into vectors used for proving and
-	/// verification.
+	/// Commits to the opening of an existing Pedersen `commitment`, such as
+	/// the value commitment of a [`RangeProof`](::RangeProof), so that the
+	/// constraint system proves statements about the same value as the
+	/// other proof.
 	///
-	/// # Output
+	/// The value `v` and the blinding factor `v_blinding` must be those the
+	/// `commitment` was created with, using the same [`PedersenGens`] as
+	/// this prover.  The verifier passes the same `commitment` to
+	/// [`Verifier::commit`](::r1cs::Verifier::commit).  Each proof keeps its
+	/// own transcript, and both are verified against the one commitment.
 	///
-	/// Returns a tuple of
-	/// ```text
-	/// (wL, wR, wO, wV)
-	/// ```
-	/// where `w{L,R,O}` is \\( z \cdot z^Q \cdot W_{L,R,O} \\).
-	fn flattened_constraints(&mut self, z: &Scalar) -> (Vec<Scalar>, Vec<Scalar>, Vec<Scalar>, Vec<Scalar>) {
-		let n = self.a_L.len();
-		let m = self.v.len();
+	/// # Returns
+	///
+	/// Returns the [`Variable`] corresponding to the commitment, or
+	/// [`R1CSError::CommitmentMismatch`] without committing if the opening
+	/// does not recompute to `commitment`.
+	pub fn commit_existing(
+		&mut self,
+		v: Scalar,
+		v_blinding: Scalar,
+		commitment: &CompressedRistretto,
+	) -> Result<Variable, R1CSError> {
+		if self.pc_gens.commit(v, v_blinding).compress() != *commitment {
+			return Err(R1CSError::CommitmentMismatch);
+		}
+		Ok(self.commit(v, v_blinding).1)
+	}
+
+	/// Commits to each of the `values` in order, with blinding factors
+	/// drawn from `rng`.
+	///
+	/// This is equivalent to calling [`Prover::commit`] for each value, so
+	/// the verifier can absorb the commitments with
+	/// [`Verifier::commit_vec`](::r1cs::Verifier::commit_vec).
+	///
+	/// # Returns
+	///
+	/// Returns the Pedersen commitments, the corresponding [`Variable`]s and
+	/// the blinding factors, in the order of the `values`.
+	pub fn commit_vec<T: RngCore + CryptoRng>(
+		&mut self,
+		values: &[Scalar],
+		rng: &mut T,
+	) -> (Vec<CompressedRistretto>, Vec<Variable>, Vec<Scalar>) {
+		let mut commitments = Vec::with_capacity(values.len());
+		let mut variables = Vec::with_capacity(values.len());
+		let mut blindings = Vec::with_capacity(values.len());
+		for v in values.iter() {
+			let v_blinding = Scalar::random(rng);
+			let (V, var) = self.commit(*v, v_blinding);
+			commitments.push(V);
+			variables.push(var);
+			blindings.push(v_blinding);
+		}
+		(commitments, variables, blindings)
+	}
 
-		let mut wL = vec![Scalar::zero(); n];
-		let mut wR = vec![Scalar::zero(); n];
-		let mut wO = vec![Scalar::zero(); n];
-		let mut wV = vec![Scalar::zero(); m];
+	/// Uses a challenge, `z`, to flatten the constraints in the constraint
+	/// system, and adds the weights \\( z \cdot z^Q \cdot W_{L,R,O} \\) of
+	/// the multipliers into the vector polynomials \\(l(x)\\) and
+	/// \\(r(x)\\).
+	///
+	/// The constraints are consumed, and each one is dropped as soon as it
+	/// is folded in, so that large circuits do not hold both the
+	/// constraints and dense weight vectors.
+	///
+	/// Returns the weights \\( -z \cdot z^Q \cdot W_V \\) of the high-level
+	/// variables.
+	fn fold_constraints(
+		&mut self,
+		z: &Scalar,
+		exp_y_inv: &[Scalar],
+		l_poly: &mut util::VecPoly3,
+		r_poly: &mut util::VecPoly3,
+	) -> Vec<Scalar> {
+		let mut wV = vec![Scalar::zero(); self.v.len()];
+
+		let constraints = mem::replace(&mut self.constraints, Vec::new());
+		self.constraint_labels = Vec::new();
 
 		let mut exp_z = *z;
-		for lc in self.constraints.iter() {
+		for lc in constraints.into_iter() {
 			for (var, coeff) in &lc.terms {
 				match var {
 					Variable::MultiplierLeft(i) => {
-						wL[*i] += exp_z * coeff;
+						// r_poly.1 = y^n * a_R + (z * z^Q * W_L)
+						r_poly.1[*i] += exp_z * coeff;
 					}
 					Variable::MultiplierRight(i) => {
-						wR[*i] += exp_z * coeff;
+						// l_poly.1 = a_L + y^-n * (z * z^Q * W_R)
+						l_poly.1[*i] += exp_y_inv[*i] * (exp_z * coeff);
 					}
 					Variable::MultiplierOutput(i) => {
-						wO[*i] += exp_z * coeff;
+						// r_poly.0 = (z * z^Q * W_O) - y^n
+						r_poly.0[*i] += exp_z * coeff;
 					}
 					Variable::Committed(i) => {
 						wV[*i] -= exp_z * coeff;
@@ -360,7 +757,57 @@ impl<'t, 'g> Prover<'t, 'g> {
 			exp_z *= z;
 		}
 
-		(wL, wR, wO, wV)
+		wV
+	}
+
+	/// Overwrites the value openings, blinding factors and low-level
+	/// assignments with zeroes.
+	fn clear_witness(&mut self) {
+		util::clear_scalars(&mut [
+			&mut self.v,
+			&mut self.v_blinding,
+			&mut self.a_L,
+			&mut self.a_R,
+			&mut self.a_O,
+		]);
+	}
+
+	fn add_constraint(&mut self, lc: LinearCombination, label: Option<&'static str>) {
+		if !self.diagnostics {
+			let res = self.evaluate_lc(&lc).unwrap();
+			assert!(res == Scalar::zero());
+		}
+		if let Some(trace) = self.synthesis_trace.as_mut() {
+			trace.push_constraint(&lc);
+		}
+		self.constraints.push(lc);
+		self.constraint_labels.push(label);
+	}
+
+	/// Returns [`R1CSError::RandomizedConstraintsConsumed`] if the callbacks
+	/// for randomized constraints were taken by another prover sharing them.
+	fn check_deferred_constraints(&self) -> Result<(), R1CSError> {
+		for (_, callback) in self.deferred_constraints.iter() {
+			let pending = callback.take();
+			let consumed = pending.is_none();
+			callback.set(pending);
+			if consumed {
+				return Err(R1CSError::RandomizedConstraintsConsumed);
+			}
+		}
+		Ok(())
+	}
+
+	/// Returns the first unsatisfied constraint as an error, if
+	/// diagnostics are enabled.
+	fn check_constraints(&self) -> Result<(), R1CSError> {
+		if !self.diagnostics {
+			return Ok(());
+		}
+		match self.unsatisfied_constraints().first() {
+			Some(&(index, label)) => Err(R1CSError::UnsatisfiedConstraint { index, label }),
+			None => Ok(()),
+		}
 	}
 
 	fn eval(&self, lc: &LinearCombination) -> Scalar {
@@ -396,8 +843,9 @@ impl<'t, 'g> Prover<'t, 'g> {
 			// so we move the self into wrapper and then move it back out afterwards.
 			let mut callbacks = mem::replace(&mut self.deferred_constraints, Vec::new());
 			let mut wrapped_self = RandomizingProver { prover: self };
-			for callback in callbacks.drain(..) {
-				callback(&mut wrapped_self)?;
+			for (namespaces, callback) in callbacks.drain(..) {
+				let callback = callback.take().ok_or(R1CSError::RandomizedConstraintsConsumed)?;
+				call_within_namespaces(&mut wrapped_self, &namespaces, callback)?;
 			}
 			Ok(wrapped_self.prover)
 		}
@@ -410,53 +858,144 @@ impl<'t, 'g> Prover<'t, 'g> {
 	}
 
 	/// Consume this `ConstraintSystem` to produce a proof.
+	///
+	/// The blinding factors are drawn from the transcript rekeyed with the
+	/// witness, and finalized with `prng`, so that they stay secret even if
+	/// `prng` is weak.
 	pub fn prove_with_rng<T: RngCore + CryptoRng>(
-		mut self,
+		self,
 		bp_gens: &BulletproofGens,
 		prng: &mut T,
 	) -> Result<R1CSProof, R1CSError> {
-		use crate::util;
+		self.prove_with_progress_and_rng(bp_gens, None, None, prng)
+	}
+
+	/// Consume this `ConstraintSystem` to produce a proof, growing
+	/// `bp_gens` if they are too small for the circuit.
+	///
+	/// This is a convenience wrapper around
+	/// [`Prover::prove_growing_with_rng`], passing in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn prove_growing(self, bp_gens: &mut BulletproofGens) -> Result<R1CSProof, R1CSError> {
+		self.prove_growing_with_rng(bp_gens, &mut thread_rng())
+	}
+
+	/// Consume this `ConstraintSystem` to produce a proof, growing
+	/// `bp_gens` if they are too small for the circuit.
+	///
+	/// Instead of failing with [`R1CSError::InvalidGeneratorsLength`], the
+	/// generators of every party are extended with
+	/// [`BulletproofGens::increase_capacity`] to the number of multipliers
+	/// once it is known, padded to a power of two.  The grown generators
+	/// are identical to generators created at that size, so they can be
+	/// kept for the verifier and for later proofs.
+	pub fn prove_growing_with_rng<T: RngCore + CryptoRng>(
+		self,
+		bp_gens: &mut BulletproofGens,
+		prng: &mut T,
+	) -> Result<R1CSProof, R1CSError> {
+		self.prove_impl(ProverGens::Growable(bp_gens), None, None, prng)
+	}
+
+	/// Consume this `ConstraintSystem` to produce a proof, reporting each
+	/// [`Milestone`](::Milestone) to the `progress` sink.
+	///
+	/// This is a convenience wrapper around
+	/// [`Prover::prove_with_progress_and_rng`], passing in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn prove_with_progress(
+		self,
+		bp_gens: &BulletproofGens,
+		progress: Option<&dyn ProgressSink>,
+		cancel: Option<&CancellationToken>,
+	) -> Result<R1CSProof, R1CSError> {
+		self.prove_with_progress_and_rng(bp_gens, progress, cancel, &mut thread_rng())
+	}
+
+	/// Consume this `ConstraintSystem` to produce a proof, reporting each
+	/// [`Milestone`](::Milestone) to the `progress` sink.
+	///
+	/// The `cancel` token is checked at each milestone.  If it was
+	/// cancelled, the partial secrets are cleared and
+	/// [`R1CSError::Cancelled`] is returned.
+	pub fn prove_with_progress_and_rng<T: RngCore + CryptoRng>(
+		self,
+		bp_gens: &BulletproofGens,
+		progress: Option<&dyn ProgressSink>,
+		cancel: Option<&CancellationToken>,
+		prng: &mut T,
+	) -> Result<R1CSProof, R1CSError> {
+		self.prove_impl(ProverGens::Fixed(bp_gens), progress, cancel, prng)
+	}
+
+	fn prove_impl<T: RngCore + CryptoRng>(
+		mut self,
+		mut bp_gens: ProverGens<'_>,
+		progress: Option<&dyn ProgressSink>,
+		cancel: Option<&CancellationToken>,
+		prng: &mut T,
+	) -> Result<R1CSProof, R1CSError> {
 		use core::iter;
 
+		let progress = Progress::new(progress, cancel);
+		let observer = self.observer;
+
+		enter_span!("r1cs_prove", m = self.v.len(), n1 = self.a_L.len());
+
+		self.check_constraints()?;
+		self.check_deferred_constraints()?;
+
 		// Commit a length _suffix_ for the number of high-level variables.
 		// We cannot do this in advance because user can commit variables one-by-one,
 		// but this suffix provides safe disambiguation because each variable
 		// is prefixed with a separate label.
-		self.transcript.append_u64(b"m", self.v.len() as u64);
+		append_u64(self.transcript, b"m", self.v.len() as u64);
+
+		if self.bind_circuit_hash {
+			let circuit_hash = self.circuit_hash();
+			self.transcript.r1cs_circuit_hash(&circuit_hash);
+		}
 
 		// Create a `TranscriptRng` from the high-level witness data
 		//
 		// The prover wants to rekey the RNG with its witness data.
 		//
 		// This consists of the high level witness data (the v's and
-		// v_blinding's), as well as the low-level witness data (a_L,
-		// a_R, a_O).  Since the low-level data should (hopefully) be
-		// determined by the high-level data, it doesn't give any
-		// extra entropy for reseeding the RNG.
-		//
-		// Since the v_blindings should be random scalars (in order to
-		// protect the v's in the commitments), we don't gain much by
-		// committing the v's as well as the v_blinding's.
+		// v_blinding's), as well as the first-phase low-level witness
+		// data (a_L and a_R, which determine a_O).  The blinding factors
+		// are then distinct for distinct witnesses even if `prng` is
+		// weak or broken, and the v_blindings may have been drawn from the
+		// same broken RNG, so every part of the witness is committed.
 		let mut rng = {
 			let mut builder = self.transcript.build_rng();
 
-			// Commit the blinding factors for the input wires
-			for v_b in &self.v_blinding {
-				builder = builder.rekey_with_witness_bytes(b"v_blinding", v_b.as_bytes());
+			// Commit the openings of the input wires
+			for (v, v_b) in self.v.iter().zip(self.v_blinding.iter()) {
+				builder = builder
+					.rekey_with_witness_bytes(b"v", v.as_bytes())
+					.rekey_with_witness_bytes(b"v_blinding", v_b.as_bytes());
+			}
+
+			// Commit the assignments of the first-phase multipliers
+			for (a_L, a_R) in self.a_L.iter().zip(self.a_R.iter()) {
+				builder = builder
+					.rekey_with_witness_bytes(b"a_L", a_L.as_bytes())
+					.rekey_with_witness_bytes(b"a_R", a_R.as_bytes());
 			}
 
 			builder.finalize(prng)
 		};
 
+		// The witness is cleared when the prover is dropped.
+		if progress.cancelled_at(Milestone::WitnessBuilt) {
+			return Err(R1CSError::Cancelled);
+		}
+
 		// Commit to the first-phase low-level witness variables.
 		let n1 = self.a_L.len();
 
-		if bp_gens.gens_capacity < n1 {
-			return Err(R1CSError::InvalidGeneratorsLength);
-		}
-
 		// We are performing a single-party circuit proof, so party index is 0.
-		let gens = bp_gens.share(0);
+		let gens = bp_gens.reserve(n1)?.share(0);
 
 		let i_blinding1 = Scalar::random(&mut rng);
 		let o_blinding1 = Scalar::random(&mut rng);
@@ -465,33 +1004,40 @@ impl<'t, 'g> Prover<'t, 'g> {
 		let mut s_L1: Vec<Scalar> = (0..n1).map(|_| Scalar::random(&mut rng)).collect();
 		let mut s_R1: Vec<Scalar> = (0..n1).map(|_| Scalar::random(&mut rng)).collect();
 
-		// A_I = <a_L, G> + <a_R, H> + i_blinding * B_blinding
-		let A_I1 = RistrettoPoint::multiscalar_mul(
-			iter::once(&i_blinding1).chain(self.a_L.iter()).chain(self.a_R.iter()),
-			iter::once(&self.pc_gens.B_blinding).chain(gens.G(n1)).chain(gens.H(n1)),
-		)
-		.compress();
-
-		// A_O = <a_O, G> + o_blinding * B_blinding
-		let A_O1 = RistrettoPoint::multiscalar_mul(
-			iter::once(&o_blinding1).chain(self.a_O.iter()),
-			iter::once(&self.pc_gens.B_blinding).chain(gens.G(n1)),
-		)
-		.compress();
-
-		// S = <s_L, G> + <s_R, H> + s_blinding * B_blinding
-		let S1 = RistrettoPoint::multiscalar_mul(
-			iter::once(&s_blinding1).chain(s_L1.iter()).chain(s_R1.iter()),
-			iter::once(&self.pc_gens.B_blinding).chain(gens.G(n1)).chain(gens.H(n1)),
-		)
-		.compress();
+		observer.start(Phase::FirstPhaseCommitment);
+		let (A_I1, A_O1, S1) = in_span!("r1cs_commit", phase = 1, n = n1, {
+			(
+				// A_I = <a_L, G> + <a_R, H> + i_blinding * B_blinding
+				chunked_multiscalar_mul(
+					iter::once(&i_blinding1).chain(self.a_L.iter()).chain(self.a_R.iter()),
+					iter::once(&self.pc_gens.B_blinding).chain(gens.G(n1)).chain(gens.H(n1)),
+				)
+				.compress(),
+				// A_O = <a_O, G> + o_blinding * B_blinding
+				chunked_multiscalar_mul(
+					iter::once(&o_blinding1).chain(self.a_O.iter()),
+					iter::once(&self.pc_gens.B_blinding).chain(gens.G(n1)),
+				)
+				.compress(),
+				// S = <s_L, G> + <s_R, H> + s_blinding * B_blinding
+				chunked_multiscalar_mul(
+					iter::once(&s_blinding1).chain(s_L1.iter()).chain(s_R1.iter()),
+					iter::once(&self.pc_gens.B_blinding).chain(gens.G(n1)).chain(gens.H(n1)),
+				)
+				.compress(),
+			)
+		});
 
 		self.transcript.append_point(b"A_I1", &A_I1);
 		self.transcript.append_point(b"A_O1", &A_O1);
 		self.transcript.append_point(b"S1", &S1);
+		observer.end(Phase::FirstPhaseCommitment);
 
 		// Process the remaining constraints.
-		self = self.create_randomized_constraints()?;
+		observer.start(Phase::Synthesis);
+		self = in_span!("r1cs_synthesis", { self.create_randomized_constraints() })?;
+		self.check_constraints()?;
+		observer.end(Phase::Synthesis);
 
 		// Pad zeros to the next power of two (or do that implicitly when creating
 		// vectors)
@@ -503,11 +1049,25 @@ impl<'t, 'g> Prover<'t, 'g> {
 		let padded_n = self.a_L.len().next_power_of_two();
 		let pad = padded_n - n;
 
-		if bp_gens.gens_capacity < padded_n {
-			return Err(R1CSError::InvalidGeneratorsLength);
-		}
+		trace_event!(
+			n = n,
+			n2 = n2,
+			padded_n = padded_n,
+			constraints = self.constraints.len(),
+			"circuit synthesized"
+		);
+
+		observer.stats(|| Stats {
+			metrics: self.metrics(),
+			// The second-phase commitments are only computed if there are
+			// second-phase variables.
+			multiscalar_terms: witness_commitment_terms(n1) + if n2 > 0 { witness_commitment_terms(n2) } else { 0 },
+		});
+
+		let gens = bp_gens.reserve(padded_n)?.share(0);
 
 		// Commit to the second-phase low-level witness variables
+		observer.start(Phase::SecondPhaseCommitment);
 
 		let has_2nd_phase_commitments = n2 > 0;
 
@@ -525,32 +1085,34 @@ impl<'t, 'g> Prover<'t, 'g> {
 		let mut s_R2: Vec<Scalar> = (0..n2).map(|_| Scalar::random(&mut rng)).collect();
 
 		let (A_I2, A_O2, S2) = if has_2nd_phase_commitments {
-			(
-				// A_I = <a_L, G> + <a_R, H> + i_blinding * B_blinding
-				RistrettoPoint::multiscalar_mul(
-					iter::once(&i_blinding2)
-						.chain(self.a_L.iter().skip(n1))
-						.chain(self.a_R.iter().skip(n1)),
-					iter::once(&self.pc_gens.B_blinding)
-						.chain(gens.G(n).skip(n1))
-						.chain(gens.H(n).skip(n1)),
-				)
-				.compress(),
-				// A_O = <a_O, G> + o_blinding * B_blinding
-				RistrettoPoint::multiscalar_mul(
-					iter::once(&o_blinding2).chain(self.a_O.iter().skip(n1)),
-					iter::once(&self.pc_gens.B_blinding).chain(gens.G(n).skip(n1)),
+			in_span!("r1cs_commit", phase = 2, n = n2, {
+				(
+					// A_I = <a_L, G> + <a_R, H> + i_blinding * B_blinding
+					chunked_multiscalar_mul(
+						iter::once(&i_blinding2)
+							.chain(self.a_L.iter().skip(n1))
+							.chain(self.a_R.iter().skip(n1)),
+						iter::once(&self.pc_gens.B_blinding)
+							.chain(gens.G(n).skip(n1))
+							.chain(gens.H(n).skip(n1)),
+					)
+					.compress(),
+					// A_O = <a_O, G> + o_blinding * B_blinding
+					chunked_multiscalar_mul(
+						iter::once(&o_blinding2).chain(self.a_O.iter().skip(n1)),
+						iter::once(&self.pc_gens.B_blinding).chain(gens.G(n).skip(n1)),
+					)
+					.compress(),
+					// S = <s_L, G> + <s_R, H> + s_blinding * B_blinding
+					chunked_multiscalar_mul(
+						iter::once(&s_blinding2).chain(s_L2.iter()).chain(s_R2.iter()),
+						iter::once(&self.pc_gens.B_blinding)
+							.chain(gens.G(n).skip(n1))
+							.chain(gens.H(n).skip(n1)),
+					)
+					.compress(),
 				)
-				.compress(),
-				// S = <s_L, G> + <s_R, H> + s_blinding * B_blinding
-				RistrettoPoint::multiscalar_mul(
-					iter::once(&s_blinding2).chain(s_L2.iter()).chain(s_R2.iter()),
-					iter::once(&self.pc_gens.B_blinding)
-						.chain(gens.G(n).skip(n1))
-						.chain(gens.H(n).skip(n1)),
-				)
-				.compress(),
-			)
+			})
 		} else {
 			// Since we are using zero blinding factors and
 			// there are no variables to commit,
@@ -566,42 +1128,79 @@ impl<'t, 'g> Prover<'t, 'g> {
 		self.transcript.append_point(b"A_I2", &A_I2);
 		self.transcript.append_point(b"A_O2", &A_O2);
 		self.transcript.append_point(b"S2", &S2);
+		observer.end(Phase::SecondPhaseCommitment);
+
+		if progress.cancelled_at(Milestone::WitnessCommitted) {
+			util::clear_scalars(&mut [&mut s_L1, &mut s_R1, &mut s_L2, &mut s_R2]);
+			return Err(R1CSError::Cancelled);
+		}
 
 		// 4. Compute blinded vector polynomials l(x) and r(x)
 
+		// Bind the challenges to the size of the circuit.
+		self.transcript
+			.r1cs_circuit_size(n as u64, self.constraints.len() as u64);
+
 		let y = self.transcript.challenge_scalar(b"y");
 		let z = self.transcript.challenge_scalar(b"z");
 
-		let (wL, wR, wO, wV) = self.flattened_constraints(&z);
-
-		let mut l_poly = util::VecPoly3::zero(n);
-		let mut r_poly = util::VecPoly3::zero(n);
-
 		let mut exp_y = Scalar::one(); // y^n starting at n=0
 		let y_inv = y.invert();
 		let exp_y_inv = util::exp_iter(y_inv).take(padded_n).collect::<Vec<_>>();
 
+		// The terms of the witness are laid out first, with room for the
+		// padding in the buffers which are evaluated in place, and the
+		// weights of the constraints are added to them.  l_poly.0 and
+		// r_poly.2 are zero, so they are left empty.
+		let mut l_poly = util::VecPoly3(
+			Vec::new(),
+			Vec::with_capacity(padded_n),
+			Vec::with_capacity(n),
+			Vec::with_capacity(n),
+		);
+		let mut r_poly = util::VecPoly3(
+			Vec::with_capacity(n),
+			Vec::with_capacity(padded_n),
+			Vec::new(),
+			Vec::with_capacity(n),
+		);
 		let sLsR = s_L1.iter().chain(s_L2.iter()).zip(s_R1.iter().chain(s_R2.iter()));
 		for (i, (sl, sr)) in sLsR.enumerate() {
-			// l_poly.0 = 0
-			// l_poly.1 = a_L + y^-n * (z * z^Q * W_R)
-			l_poly.1[i] = self.a_L[i] + exp_y_inv[i] * wR[i];
+			// l_poly.1 = a_L
+			l_poly.1.push(self.a_L[i]);
 			// l_poly.2 = a_O
-			l_poly.2[i] = self.a_O[i];
+			l_poly.2.push(self.a_O[i]);
 			// l_poly.3 = s_L
-			l_poly.3[i] = *sl;
-			// r_poly.0 = (z * z^Q * W_O) - y^n
-			r_poly.0[i] = wO[i] - exp_y;
-			// r_poly.1 = y^n * a_R + (z * z^Q * W_L)
-			r_poly.1[i] = exp_y * self.a_R[i] + wL[i];
-			// r_poly.2 = 0
+			l_poly.3.push(*sl);
+			// r_poly.0 = -y^n
+			r_poly.0.push(-exp_y);
+			// r_poly.1 = y^n * a_R
+			r_poly.1.push(exp_y * self.a_R[i]);
 			// r_poly.3 = y^n * s_R
-			r_poly.3[i] = exp_y * sr;
+			r_poly.3.push(exp_y * sr);
 
 			exp_y = exp_y * y; // y^i -> y^(i+1)
 		}
 
-		let t_poly = util::VecPoly3::special_inner_product(&l_poly, &r_poly);
+		// The low-level witness and the blinding vectors now live in the
+		// polynomials, which clear them when dropped.
+		util::clear_scalars(&mut [&mut self.a_L, &mut self.a_R, &mut self.a_O]);
+		util::clear_scalars(&mut [&mut s_L1, &mut s_R1, &mut s_L2, &mut s_R2]);
+		self.a_L = Vec::new();
+		self.a_R = Vec::new();
+		self.a_O = Vec::new();
+		drop((s_L1, s_R1, s_L2, s_R2));
+
+		observer.start(Phase::Flattening);
+		let wV = in_span!("r1cs_flatten", constraints = self.constraints.len(), n = n, {
+			self.fold_constraints(&z, &exp_y_inv, &mut l_poly, &mut r_poly)
+		});
+		observer.end(Phase::Flattening);
+
+		observer.start(Phase::Polynomial);
+		let t_poly = in_span!("r1cs_t_poly", n = n, {
+			util::VecPoly3::special_inner_product(&l_poly, &r_poly)
+		});
 
 		let t_1_blinding = Scalar::random(&mut rng);
 		let t_3_blinding = Scalar::random(&mut rng);
@@ -620,6 +1219,11 @@ impl<'t, 'g> Prover<'t, 'g> {
 		self.transcript.append_point(b"T_4", &T_4);
 		self.transcript.append_point(b"T_5", &T_5);
 		self.transcript.append_point(b"T_6", &T_6);
+		observer.end(Phase::Polynomial);
+
+		if progress.cancelled_at(Milestone::PolynomialCommitted) {
+			return Err(R1CSError::Cancelled);
+		}
 
 		let u = self.transcript.challenge_scalar(b"u");
 		let x = self.transcript.challenge_scalar(b"x");
@@ -643,11 +1247,11 @@ impl<'t, 'g> Prover<'t, 'g> {
 
 		let t_x = t_poly.eval(x);
 		let t_x_blinding = t_blinding_poly.eval(x);
-		let mut l_vec = l_poly.eval(x);
-		l_vec.append(&mut vec![Scalar::zero(); pad]);
+		let mut l_vec = l_poly.eval_in_place(x);
+		l_vec.resize(padded_n, Scalar::zero());
 
-		let mut r_vec = r_poly.eval(x);
-		r_vec.append(&mut vec![Scalar::zero(); pad]);
+		let mut r_vec = r_poly.eval_in_place(x);
+		r_vec.resize(padded_n, Scalar::zero());
 
 		// XXX this should refer to the notes to explain why this is correct
 		for i in n..padded_n {
@@ -679,28 +1283,21 @@ impl<'t, 'g> Prover<'t, 'g> {
 			.map(|(y, u_or_1)| y * u_or_1)
 			.collect::<Vec<_>>();
 
-		let ipp_proof = InnerProductProof::create(
+		observer.start(Phase::InnerProduct);
+		let ipp_proof = InnerProductProof::create_with_progress(
 			self.transcript,
 			&Q,
 			&G_factors,
 			&H_factors,
-			gens.G(padded_n).cloned().collect(),
-			gens.H(padded_n).cloned().collect(),
+			gens.G_slice(padded_n).to_vec(),
+			gens.H_slice(padded_n).to_vec(),
 			l_vec,
 			r_vec,
+			progress,
 		);
 
-		// We do not yet have a ClearOnDrop wrapper for Vec<Scalar>.
-		// When PR 202 [1] is merged, we can simply wrap s_L and s_R at the point of
-		// creation. [1] https://github.com/dalek-cryptography/curve25519-dalek/pull/202
-		for scalar in s_L1
-			.iter_mut()
-			.chain(s_L2.iter_mut())
-			.chain(s_R1.iter_mut())
-			.chain(s_R2.iter_mut())
-		{
-			scalar.clear();
-		}
+		let ipp_proof = ipp_proof.ok_or(R1CSError::Cancelled)?;
+		observer.end(Phase::InnerProduct);
 
 		Ok(R1CSProof {
 			A_I1,
@@ -721,11 +1318,272 @@ impl<'t, 'g> Prover<'t, 'g> {
 		})
 	}
 
+	/// Returns the number of constraints synthesized so far.
 	pub fn num_constraints(&self) -> usize {
 		self.constraints.len()
 	}
 
+	/// Returns a canonical digest of the constraints synthesized so far.
+	///
+	/// The digest does not depend on the assignments, so it matches
+	/// [`Verifier::circuit_hash`](::r1cs::Verifier::circuit_hash) for a
+	/// verifier built by the same gadget code.  Randomized constraints
+	/// are only synthesized during proving and are not covered.
+	pub fn circuit_hash(&self) -> [u8; 32] {
+		circuit::circuit_hash(self.a_L.len(), self.v.len(), &self.constraints)
+	}
+
+	/// Returns a digest of the shape of the circuit synthesized so far, to
+	/// compare out-of-band with the
+	/// [`Verifier::shape_digest`](::r1cs::Verifier::shape_digest) of the
+	/// verifier.
+	///
+	/// This is the [`Prover::circuit_hash`].  It is not a running digest
+	/// kept up to date during synthesis: every call hashes all the
+	/// constraints again, so it costs time linear in the size of the
+	/// circuit and is best called once, when synthesis is done.
+	pub fn shape_digest(&self) -> [u8; 32] {
+		self.circuit_hash()
+	}
+
+	/// Checks that the constraints synthesized so far are the circuit of
+	/// the `description`.
+	///
+	/// This catches gadget code whose shape diverged from the circuit the
+	/// verifier compiled, before spending time on a proof which would be
+	/// rejected.
+	pub fn check_circuit(&self, description: &CircuitDescription) -> Result<(), R1CSError> {
+		if self.circuit_hash() != description.circuit_hash() {
+			return Err(R1CSError::CircuitMismatch);
+		}
+		Ok(())
+	}
+
+	/// Binds the proof to the circuit, by appending the
+	/// [`Prover::circuit_hash`] of the constraints synthesized before
+	/// proving to the transcript.
+	///
+	/// The verifier must bind the same hash with
+	/// [`Verifier::bind_circuit_hash`](::r1cs::Verifier::bind_circuit_hash),
+	/// which rejects a circuit of a different shape with
+	/// [`R1CSError::CircuitMismatch`] before checking the proof.
+	pub fn bind_circuit_hash(&mut self) {
+		self.bind_circuit_hash = true;
+	}
+
+	/// Returns the number of multipliers allocated so far.
 	pub fn num_multipliers(&self) -> usize {
 		self.a_O.len()
 	}
+
+	/// Returns the size in bytes of the serialization of a proof of the
+	/// constraint system synthesized so far, without proving it.
+	///
+	/// Randomized constraints are only synthesized during proving, so the
+	/// multipliers they allocate are not counted.  For circuits which
+	/// allocate multipliers in the randomized phase, use
+	/// [`randomized_proof_size`](::r1cs::randomized_proof_size) with the
+	/// final number of multipliers.
+	pub fn estimated_proof_size(&self) -> usize {
+		proof_size(self.a_O.len(), self.v.len())
+	}
+
+	/// Returns the [`Metrics`] of the constraint system synthesized so far.
+	pub fn metrics(&self) -> Metrics {
+		Metrics::new(self.a_O.len(), self.constraints.len(), self.v.len())
+	}
+
+	/// Reports unsatisfied constraints when proving instead of panicking
+	/// when they are added.
+	///
+	/// By default, adding a constraint which the assignments do not satisfy
+	/// panics.  With diagnostics enabled, the constraints are added
+	/// unchecked, and proving evaluates every constraint against the
+	/// assignments first, failing with
+	/// [`R1CSError::UnsatisfiedConstraint`] at the first unsatisfied one.
+	/// All of them are listed by [`Prover::unsatisfied_constraints`].
+	pub fn enable_diagnostics(&mut self) {
+		self.diagnostics = true;
+	}
+
+	/// Returns the index and label of every constraint synthesized so far
+	/// which the assignments do not satisfy.
+	pub fn unsatisfied_constraints(&self) -> Vec<(usize, Option<&'static str>)> {
+		self.constraints
+			.iter()
+			.zip(self.constraint_labels.iter())
+			.enumerate()
+			.filter(|(_, (lc, _))| self.eval(lc) != Scalar::zero())
+			.map(|(index, (_, label))| (index, *label))
+			.collect()
+	}
+
+	/// Checks whether the assignments satisfy the constraint system
+	/// synthesized so far, without creating a proof.
+	///
+	/// Every multiplier must have its output equal to the product of its
+	/// inputs, and every constraint must evaluate to zero.  Randomized
+	/// constraints are only synthesized during proving and are not
+	/// checked.
+	///
+	/// Constraints are checked when they are added unless diagnostics are
+	/// enabled with [`Prover::enable_diagnostics`], so this is mostly
+	/// useful together with them.  The prover refuses to allocate variables
+	/// without an assignment, so every assignment needed is known.
+	pub fn is_satisfied(&self) -> Result<bool, R1CSError> {
+		let multipliers_hold = self
+			.a_L
+			.iter()
+			.zip(self.a_R.iter())
+			.zip(self.a_O.iter())
+			.all(|((l, r), o)| l * r == *o);
+		Ok(multipliers_hold && self.unsatisfied_constraints().is_empty())
+	}
+
+	/// Starts recording a [`SynthesisTrace`] of the following operations.
+	///
+	/// The trace is recorded from [`Prover::new`] in debug builds.  In
+	/// release builds, call this method before the first operation so
+	/// that the trace can be compared with the verifier's.
+	pub fn record_trace(&mut self) {
+		if self.synthesis_trace.is_none() {
+			self.synthesis_trace = Some(SynthesisTrace::default());
+		}
+	}
+
+	/// Returns the [`SynthesisTrace`] recorded so far, if recording.
+	///
+	/// Compare it with [`Verifier::trace`](::r1cs::Verifier::trace) using
+	/// [`diff`](::r1cs::diff).
+	pub fn trace(&self) -> Option<&SynthesisTrace> {
+		self.synthesis_trace.as_ref()
+	}
+
+	/// Takes a [`Snapshot`] of the synthesis so far, to which the prover
+	/// can be rolled back with [`Prover::rollback`].
+	pub fn snapshot(&self) -> Snapshot {
+		Snapshot {
+			multipliers: self.a_L.len(),
+			pending_multiplier: self.pending_multiplier,
+			constraints: self.constraints.len(),
+			deferred_constraints: self.deferred_constraints.len(),
+			transcript_operations: self.transcript_log.len(),
+			trace: self.synthesis_trace.as_ref().map(SynthesisTrace::len),
+			namespaces: self.namespaces.clone(),
+		}
+	}
+
+	/// Rolls the synthesis back to the `snapshot`, discarding the
+	/// multipliers, constraints and randomized constraints added since.
+	///
+	/// The variables created since the snapshot must not be used
+	/// afterwards.  Fails with [`R1CSError::RollbackAcrossTranscript`] if
+	/// a variable or a public input was committed since the snapshot,
+	/// since the transcript cannot be rolled back, or if the prover was
+	/// already rolled back to an earlier point.  Messages appended
+	/// directly through [`ConstraintSystem::transcript`] are not detected.
+	pub fn rollback(&mut self, snapshot: &Snapshot) -> Result<(), R1CSError> {
+		if !snapshot.can_roll_back(
+			self.a_L.len(),
+			self.constraints.len(),
+			self.deferred_constraints.len(),
+			self.transcript_log.len(),
+		) {
+			return Err(R1CSError::RollbackAcrossTranscript);
+		}
+
+		self.a_L.truncate(snapshot.multipliers);
+		self.a_R.truncate(snapshot.multipliers);
+		self.a_O.truncate(snapshot.multipliers);
+		// The pending multiplier may have been completed since.
+		if let Some(i) = snapshot.pending_multiplier {
+			self.a_R[i] = Scalar::zero();
+			self.a_O[i] = Scalar::zero();
+		}
+		self.pending_multiplier = snapshot.pending_multiplier;
+		self.constraints.truncate(snapshot.constraints);
+		self.constraint_labels.truncate(snapshot.constraints);
+		self.deferred_constraints.truncate(snapshot.deferred_constraints);
+		if let Some(trace) = self.synthesis_trace.as_mut() {
+			trace.truncate(snapshot.trace.unwrap_or(0));
+		}
+		self.namespaces = snapshot.namespaces.clone();
+		Ok(())
+	}
+
+	fn record(&mut self, operation: Operation) {
+		if let Some(trace) = self.synthesis_trace.as_mut() {
+			trace.push(operation);
+		}
+	}
+
+	/// Returns the Pedersen generators of the prover.
+	pub(super) fn pc_gens(&self) -> &'g PedersenGens {
+		self.pc_gens
+	}
+
+	/// Consumes a prover whose gadgets have been synthesized and returns
+	/// its constraints and witness as `(constraints, a_L, a_R, a_O, v,
+	/// v_blinding)`.
+	///
+	/// Fails if randomized constraints were specified, since those depend
+	/// on challenges bound to a particular proof.
+	#[allow(clippy::type_complexity)]
+	pub(super) fn into_witness(
+		mut self,
+	) -> Result<
+		(
+			Vec<LinearCombination>,
+			Vec<Scalar>,
+			Vec<Scalar>,
+			Vec<Scalar>,
+			Vec<Scalar>,
+			Vec<Scalar>,
+		),
+		R1CSError,
+	> {
+		if !self.deferred_constraints.is_empty() {
+			return Err(R1CSError::gadget("randomized constraints cannot be aggregated"));
+		}
+		self.check_constraints()?;
+		// The witness is moved out, so the emptied vectors are cleared on drop.
+		Ok((
+			mem::replace(&mut self.constraints, Vec::new()),
+			mem::replace(&mut self.a_L, Vec::new()),
+			mem::replace(&mut self.a_R, Vec::new()),
+			mem::replace(&mut self.a_O, Vec::new()),
+			mem::replace(&mut self.v, Vec::new()),
+			mem::replace(&mut self.v_blinding, Vec::new()),
+		))
+	}
+}
+
+/// Returns the number of terms of the commitments \\(A_I, A_O, S\\) to
+/// `n` low-level variables.
+fn witness_commitment_terms(n: usize) -> usize {
+	(1 + 2 * n) + (1 + n) + (1 + 2 * n)
+}
+
+/// The number of terms committed by each constant-time multiscalar
+/// multiplication in [`chunked_multiscalar_mul`].
+const COMMITMENT_CHUNK_SIZE: usize = 1024;
+
+/// Computes a constant-time multiscalar multiplication in chunks of
+/// [`COMMITMENT_CHUNK_SIZE`] terms, so that the precomputed tables of the
+/// points do not grow with the size of the circuit.
+fn chunked_multiscalar_mul<'a, I, J>(scalars: I, points: J) -> RistrettoPoint
+where
+	I: IntoIterator<Item = &'a Scalar>,
+	J: IntoIterator<Item = &'a RistrettoPoint>,
+{
+	let mut scalars = scalars.into_iter().peekable();
+	let mut points = points.into_iter();
+	let mut sum = RistrettoPoint::identity();
+	while scalars.peek().is_some() {
+		sum += RistrettoPoint::multiscalar_mul(
+			scalars.by_ref().take(COMMITMENT_CHUNK_SIZE),
+			points.by_ref().take(COMMITMENT_CHUNK_SIZE),
+		);
+	}
+	sum
 }
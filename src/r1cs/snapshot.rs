@@ -0,0 +1,49 @@
+//! Snapshots of the synthesis of a constraint system.
+
+use alloc::vec::Vec;
+
+/// A point in the synthesis of a [`Prover`](::r1cs::Prover) or a
+/// [`Verifier`](::r1cs::Verifier), to which it can be rolled back.
+///
+/// Created by [`Prover::snapshot`](::r1cs::Prover::snapshot) or
+/// [`Verifier::snapshot`](::r1cs::Verifier::snapshot), and used with the
+/// `rollback` method of the same constraint system to discard the gadgets
+/// synthesized since, for instance to try several strategies for a gadget
+/// and keep the cheapest one.  Unlike a
+/// [`ProverCheckpoint`](::r1cs::ProverCheckpoint), a snapshot only records
+/// the sizes of the constraint system and copies none of it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot {
+	/// Number of multipliers.
+	pub(super) multipliers: usize,
+	/// Index of the pending multiplier, if any.
+	pub(super) pending_multiplier: Option<usize>,
+	/// Number of linear constraints.
+	pub(super) constraints: usize,
+	/// Number of randomized constraint callbacks.
+	pub(super) deferred_constraints: usize,
+	/// Number of operations on the transcript.
+	pub(super) transcript_operations: usize,
+	/// Length of the synthesis trace, if it is being recorded.
+	pub(super) trace: Option<usize>,
+	/// Labels of the namespaces entered, outermost first.
+	pub(super) namespaces: Vec<&'static [u8]>,
+}
+
+impl Snapshot {
+	/// Returns `true` if a constraint system with the given sizes can be
+	/// rolled back to this snapshot: it performed no transcript operation
+	/// since, and has not been rolled back further.
+	pub(super) fn can_roll_back(
+		&self,
+		multipliers: usize,
+		constraints: usize,
+		deferred_constraints: usize,
+		transcript_operations: usize,
+	) -> bool {
+		transcript_operations == self.transcript_operations
+			&& multipliers >= self.multipliers
+			&& constraints >= self.constraints
+			&& deferred_constraints >= self.deferred_constraints
+	}
+}
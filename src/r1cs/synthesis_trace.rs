@@ -0,0 +1,140 @@
+//! Structural log of the operations performed during synthesis.
+
+use alloc::vec::Vec;
+
+use super::{LinearCombination, Variable};
+
+/// A structural operation performed on a constraint system.
+///
+/// Operations only record the shape of the circuit: the kind of the
+/// operation and the variables it creates or uses.  Assignments and
+/// coefficients are never recorded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Operation {
+	/// A high-level variable was committed.
+	Commit {
+		/// The committed variable.
+		variable: Variable,
+	},
+	/// A multiplier was allocated by [`ConstraintSystem::multiply`].
+	///
+	/// [`ConstraintSystem::multiply`]: ::r1cs::ConstraintSystem::multiply
+	Multiply {
+		/// The index of the multiplier.
+		index: usize,
+	},
+	/// A single variable was allocated by [`ConstraintSystem::allocate`].
+	///
+	/// [`ConstraintSystem::allocate`]: ::r1cs::ConstraintSystem::allocate
+	Allocate {
+		/// The allocated variable.
+		variable: Variable,
+	},
+	/// A multiplier was allocated by
+	/// [`ConstraintSystem::allocate_multiplier`].
+	///
+	/// [`ConstraintSystem::allocate_multiplier`]: ::r1cs::ConstraintSystem::allocate_multiplier
+	AllocateMultiplier {
+		/// The index of the multiplier.
+		index: usize,
+	},
+	/// A constraint was added.
+	Constrain {
+		/// The variables of the terms, sorted, with repetitions.
+		terms: Vec<Variable>,
+	},
+	/// A public input was committed by
+	/// [`ConstraintSystem::commit_public_bytes`].
+	///
+	/// [`ConstraintSystem::commit_public_bytes`]: ::r1cs::ConstraintSystem::commit_public_bytes
+	CommitPublic {
+		/// The label of the public input.
+		label: &'static [u8],
+	},
+	/// A callback for randomized constraints was specified.
+	Randomize,
+}
+
+/// An ordered log of the [`Operation`]s performed while synthesizing a
+/// constraint system.
+///
+/// The trace is deterministic: the same gadget code yields the same trace
+/// for the prover and the verifier, whatever the assignments.  Compare the
+/// two with [`diff`] to find where they start building different circuits.
+///
+/// Randomized constraints are only synthesized during proving and
+/// verification, so the trace records that a callback was specified, but
+/// not what the callback does.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SynthesisTrace {
+	operations: Vec<Operation>,
+}
+
+impl SynthesisTrace {
+	/// Returns the recorded operations, in order.
+	pub fn operations(&self) -> &[Operation] {
+		&self.operations
+	}
+
+	/// Returns the number of recorded operations.
+	pub fn len(&self) -> usize {
+		self.operations.len()
+	}
+
+	/// Returns `true` if no operation was recorded.
+	pub fn is_empty(&self) -> bool {
+		self.operations.is_empty()
+	}
+
+	pub(crate) fn truncate(&mut self, len: usize) {
+		self.operations.truncate(len);
+	}
+
+	pub(crate) fn push(&mut self, operation: Operation) {
+		self.operations.push(operation);
+	}
+
+	pub(crate) fn push_constraint(&mut self, lc: &LinearCombination) {
+		let mut terms: Vec<Variable> = lc.terms.iter().map(|(var, _)| *var).collect();
+		terms.sort();
+		self.operations.push(Operation::Constrain { terms });
+	}
+}
+
+/// A position at which two [`SynthesisTrace`]s differ.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Divergence {
+	/// The index of the operation in both traces.
+	pub index: usize,
+	/// The operation of the prover, or `None` if its trace is shorter.
+	pub prover: Option<Operation>,
+	/// The operation of the verifier, or `None` if its trace is shorter.
+	pub verifier: Option<Operation>,
+}
+
+/// Compares the trace of a prover with the trace of a verifier.
+///
+/// Returns the positions at which the traces differ, in increasing order,
+/// or an empty vector if the prover and the verifier built the same
+/// circuit.  The first divergence is where the gadget code of the prover
+/// and the verifier parted ways; the following ones are usually its
+/// consequences, since a single extra operation shifts the rest of the
+/// trace.
+pub fn diff(prover_trace: &SynthesisTrace, verifier_trace: &SynthesisTrace) -> Vec<Divergence> {
+	let len = prover_trace.len().max(verifier_trace.len());
+	(0..len)
+		.filter_map(|index| {
+			let prover = prover_trace.operations.get(index);
+			let verifier = verifier_trace.operations.get(index);
+			if prover == verifier {
+				None
+			} else {
+				Some(Divergence {
+					index,
+					prover: prover.cloned(),
+					verifier: verifier.cloned(),
+				})
+			}
+		})
+		.collect()
+}
@@ -0,0 +1,186 @@
+#![allow(non_snake_case)]
+//! Definition of the verification key for a fixed circuit.
+
+use alloc::{sync::Arc, vec::Vec};
+use curve25519_dalek::{
+	ristretto::{CompressedRistretto, RistrettoPoint, VartimeRistrettoPrecomputation},
+	traits::VartimePrecomputedMultiscalarMul,
+};
+use merlin::Transcript;
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(feature = "std")]
+use rand::thread_rng;
+
+use super::{CircuitDescription, R1CSProof, Variable, Verifier};
+
+use crate::{
+	errors::R1CSError,
+	generators::{BulletproofGens, PedersenGens},
+	msm::{DefaultMsmBackend, MsmBackend},
+};
+
+/// Challenge-independent verification data for a fixed circuit.
+///
+/// A [`Verifier`] re-runs the gadget code for every proof.  When the
+/// circuit does not change between proofs, a `VerificationKey` runs the
+/// gadget code once and keeps the synthesized constraints, the circuit
+/// sizes and the generators needed for the circuit, so that
+/// [`VerificationKey::verify`] only has to absorb the commitments into the
+/// transcript and check the proof.
+///
+/// Only circuits without randomized constraints can be precomputed, and the
+/// gadget code must not append to the transcript itself: the key replays
+/// only the messages a [`Verifier`] appends on its own.
+#[derive(Clone)]
+pub struct VerificationKey {
+	pc_gens: PedersenGens,
+	/// The synthesized circuit.
+	description: CircuitDescription,
+	/// The first `padded_n` generators of the single party share.
+	G: Vec<RistrettoPoint>,
+	H: Vec<RistrettoPoint>,
+	/// Lookup tables for the Pedersen bases and the generators, if built
+	/// by [`VerificationKey::precompute`].
+	precomputation: Option<Arc<VartimeRistrettoPrecomputation>>,
+}
+
+impl VerificationKey {
+	/// Synthesizes the circuit defined by `gadget` and captures all data
+	/// that does not depend on a particular proof.
+	///
+	/// The `gadget` closure receives a verifier and the variables for
+	/// `num_commitments` high-level commitments, and must build the same
+	/// constraints as the prover's gadget code.
+	pub fn from_circuit<F>(
+		num_commitments: usize,
+		gadget: F,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+	) -> Result<Self, R1CSError>
+	where
+		F: FnOnce(&mut Verifier, Vec<Variable>) -> Result<(), R1CSError>,
+	{
+		let description = CircuitDescription::from_circuit(num_commitments, gadget)?;
+		Self::from_description(description, bp_gens, pc_gens)
+	}
+
+	/// Captures the generators needed to verify proofs of an already
+	/// synthesized circuit.
+	pub fn from_description(
+		description: CircuitDescription,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+	) -> Result<Self, R1CSError> {
+		let padded_n = description.num_multipliers().next_power_of_two();
+		if bp_gens.gens_capacity < padded_n {
+			return Err(R1CSError::InvalidGeneratorsLength {
+				required: padded_n,
+				actual: bp_gens.gens_capacity,
+			});
+		}
+
+		Ok(VerificationKey {
+			pc_gens: *pc_gens,
+			description,
+			G: bp_gens.G_vec[0][..padded_n].to_vec(),
+			H: bp_gens.H_vec[0][..padded_n].to_vec(),
+			precomputation: None,
+		})
+	}
+
+	/// Builds lookup tables for the Pedersen bases and the generators of
+	/// the key, which never change between proofs.
+	///
+	/// Verification then only has to build tables for the points of the
+	/// proof and the commitments, at the cost of keeping the tables in
+	/// memory.  The tables are shared between the clones of the key.
+	///
+	/// The precomputed multiplication is a Straus multiplication, which
+	/// pays off for small circuits.  For circuits with more than a few
+	/// hundred multipliers, the Pippenger multiplication of the default
+	/// path is faster, as the `verification_key` benchmarks show.
+	pub fn precompute(&mut self) {
+		let static_points = [self.pc_gens.B, self.pc_gens.B_blinding];
+		let static_points = static_points.iter().chain(self.G.iter()).chain(self.H.iter());
+		self.precomputation = Some(Arc::new(VartimeRistrettoPrecomputation::new(static_points)));
+	}
+
+	/// Returns whether the key holds the lookup tables built by
+	/// [`VerificationKey::precompute`].
+	pub fn is_precomputed(&self) -> bool {
+		self.precomputation.is_some()
+	}
+
+	/// Returns the description of the circuit.
+	pub fn description(&self) -> &CircuitDescription {
+		&self.description
+	}
+
+	/// Returns the number of high-level commitments the circuit expects.
+	pub fn num_committed(&self) -> usize {
+		self.description.num_committed()
+	}
+
+	/// Returns the number of multipliers in the circuit.
+	pub fn num_multipliers(&self) -> usize {
+		self.description.num_multipliers()
+	}
+
+	/// Verifies the `proof` against the given `commitments`.
+	///
+	/// This is a convenience wrapper around
+	/// [`VerificationKey::verify_with_rng`], passing in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn verify(
+		&self,
+		transcript: &mut Transcript,
+		commitments: &[CompressedRistretto],
+		proof: &R1CSProof,
+	) -> Result<(), R1CSError> {
+		self.verify_with_rng(transcript, commitments, proof, &mut thread_rng())
+	}
+
+	/// Verifies the `proof` against the given `commitments`.
+	///
+	/// The `transcript` must be in the same state as the one passed to
+	/// [`Verifier::new_with_gens`] when verifying without a key.
+	pub fn verify_with_rng<T: RngCore + CryptoRng>(
+		&self,
+		transcript: &mut Transcript,
+		commitments: &[CompressedRistretto],
+		proof: &R1CSProof,
+		prng: &mut T,
+	) -> Result<(), R1CSError> {
+		self.verify_with_backend(transcript, commitments, proof, prng, None)
+	}
+
+	/// Verifies the `proof` against the given `commitments`, performing the
+	/// final multiscalar multiplication with `backend`.
+	///
+	/// If `backend` is `None`, the lookup tables of
+	/// [`VerificationKey::precompute`] are used if they were built, and the
+	/// [`DefaultMsmBackend`] otherwise.
+	pub fn verify_with_backend<T: RngCore + CryptoRng>(
+		&self,
+		transcript: &mut Transcript,
+		commitments: &[CompressedRistretto],
+		proof: &R1CSProof,
+		prng: &mut T,
+		backend: Option<&dyn MsmBackend>,
+	) -> Result<(), R1CSError> {
+		enter_span!(
+			"r1cs_verify_with_key",
+			m = commitments.len(),
+			n = self.description.num_multipliers()
+		);
+
+		let pre_verified = self
+			.description
+			.pre_verify(transcript, &self.pc_gens, commitments, proof, prng)?;
+		match (backend, self.precomputation.as_ref()) {
+			(None, Some(precomputation)) => pre_verified.check_precomputed(precomputation),
+			(backend, _) => pre_verified.check(&self.pc_gens, &self.G, &self.H, backend.unwrap_or(&DefaultMsmBackend)),
+		}
+	}
+}
@@ -3,9 +3,8 @@
 use alloc::{boxed::Box, vec::Vec};
 use core::mem;
 use curve25519_dalek::{
-	ristretto::{CompressedRistretto, RistrettoPoint},
+	ristretto::{CompressedRistretto, RistrettoPoint, VartimeRistrettoPrecomputation},
 	scalar::Scalar,
-	traits::VartimeMultiscalarMul,
 };
 use merlin::Transcript;
 use rand_core::{CryptoRng, RngCore};
@@ -14,13 +13,19 @@ use rand_core::{CryptoRng, RngCore};
 use rand::thread_rng;
 
 use super::{
-	ConstraintSystem, LinearCombination, R1CSProof, RandomizableConstraintSystem, RandomizedConstraintSystem, Variable,
+	circuit,
+	constraint_system::call_within_namespaces,
+	observer::{Observer, Phase, ProverObserver, Stats},
+	CircuitDescription, ConstraintSystem, LinearCombination, Metrics, Operation, R1CSProof,
+	RandomizableConstraintSystem, RandomizedConstraintSystem, Snapshot, SynthesisTrace, Variable,
 };
 
 use crate::{
 	errors::R1CSError,
 	generators::{BulletproofGens, PedersenGens},
-	transcript::TranscriptProtocol,
+	msm::{DefaultMsmBackend, MsmBackend},
+	transcript::{append_u64, challenge_bytes, TranscriptProtocol},
+	verification_batch::{Statement, TermPoint, VerificationBatch, VerificationTerms},
 };
 
 /// A [`ConstraintSystem`] implementation for use by the verifier.
@@ -44,16 +49,45 @@ pub struct Verifier<'t> {
 	/// `Missing`), so the `num_vars` isn't kept implicitly in the
 	/// variable assignments.
 	num_vars: usize,
-	V: Vec<CompressedRistretto>,
+	V: Vec<Commitment>,
 
 	/// This list holds closures that will be called in the second phase of the
 	/// protocol, when non-randomized variables are committed.
 	/// After that, the option will flip to None and additional calls to
 	/// `randomize_constraints` will invoke closures immediately.
-	deferred_constraints: Vec<Box<dyn Fn(&mut RandomizingVerifier<'t>) -> Result<(), R1CSError>>>,
+	deferred_constraints: Vec<(
+		Vec<&'static [u8]>,
+		Box<dyn FnOnce(&mut RandomizingVerifier<'t>) -> Result<(), R1CSError>>,
+	)>,
 
 	/// Index of a pending multiplier that's not fully assigned yet.
 	pending_multiplier: Option<usize>,
+
+	/// Number of public inputs committed, which a [`CircuitDescription`]
+	/// cannot replay.
+	num_public_inputs: usize,
+
+	/// Structural log of the synthesis, if it is being recorded.
+	synthesis_trace: Option<SynthesisTrace>,
+
+	/// Labels of the namespaces entered, outermost first.
+	namespaces: Vec<&'static [u8]>,
+
+	/// Observer of the phases of verification, if any.
+	observer: Observer<'t>,
+
+	/// The circuit hash expected from the prover, if it is appended to the
+	/// transcript.
+	circuit_hash_binding: Option<[u8; 32]>,
+}
+
+/// A commitment to a high-level variable, as given to the [`Verifier`].
+enum Commitment {
+	/// A commitment from [`Verifier::commit`], decompressed when the proof
+	/// is verified.
+	Compressed(CompressedRistretto),
+	/// A commitment from [`Verifier::commit_decompressed`].
+	Decompressed(RistrettoPoint),
 }
 
 /// Verifier in the randomizing phase.
@@ -85,6 +119,7 @@ impl<'t> ConstraintSystem for Verifier<'t> {
 		let l_var = Variable::MultiplierLeft(var);
 		let r_var = Variable::MultiplierRight(var);
 		let o_var = Variable::MultiplierOutput(var);
+		self.record(Operation::Multiply { index: var });
 
 		// Constrain l,r,o:
 		left.terms.push((l_var, -Scalar::one()));
@@ -101,10 +136,16 @@ impl<'t> ConstraintSystem for Verifier<'t> {
 				let i = self.num_vars;
 				self.num_vars += 1;
 				self.pending_multiplier = Some(i);
+				self.record(Operation::Allocate {
+					variable: Variable::MultiplierLeft(i),
+				});
 				Ok(Variable::MultiplierLeft(i))
 			}
 			Some(i) => {
 				self.pending_multiplier = None;
+				self.record(Operation::Allocate {
+					variable: Variable::MultiplierRight(i),
+				});
 				Ok(Variable::MultiplierRight(i))
 			}
 		}
@@ -121,6 +162,7 @@ impl<'t> ConstraintSystem for Verifier<'t> {
 		let l_var = Variable::MultiplierLeft(var);
 		let r_var = Variable::MultiplierRight(var);
 		let o_var = Variable::MultiplierOutput(var);
+		self.record(Operation::AllocateMultiplier { index: var });
 
 		Ok((l_var, r_var, o_var))
 	}
@@ -133,6 +175,9 @@ impl<'t> ConstraintSystem for Verifier<'t> {
 		// TODO: check that the linear combinations are valid
 		// (e.g. that variables are valid, that the linear combination
 		// evals to 0 for prover, etc).
+		if let Some(trace) = self.synthesis_trace.as_mut() {
+			trace.push_constraint(&lc);
+		}
 		self.constraints.push(lc);
 	}
 
@@ -148,6 +193,23 @@ impl<'t> ConstraintSystem for Verifier<'t> {
 			_ => Err(R1CSError::FormatError),
 		}
 	}
+
+	fn commit_public_bytes(&mut self, label: &'static [u8], bytes: &[u8]) -> Result<(), R1CSError> {
+		self.transcript.r1cs_public_input(label, bytes);
+		self.num_public_inputs += 1;
+		self.record(Operation::CommitPublic { label });
+		Ok(())
+	}
+
+	fn push_namespace(&mut self, label: &'static [u8]) {
+		self.namespaces.push(label);
+	}
+
+	fn pop_namespace(&mut self) {
+		self.namespaces
+			.pop()
+			.expect("pop_namespace called without a matching push_namespace");
+	}
 }
 
 impl<'t> RandomizableConstraintSystem for Verifier<'t> {
@@ -155,9 +217,11 @@ impl<'t> RandomizableConstraintSystem for Verifier<'t> {
 
 	fn specify_randomized_constraints<F>(&mut self, callback: F) -> Result<(), R1CSError>
 	where
-		F: 'static + Fn(&mut Self::RandomizedCS) -> Result<(), R1CSError>,
+		F: 'static + FnOnce(&mut Self::RandomizedCS) -> Result<(), R1CSError>,
 	{
-		self.deferred_constraints.push(Box::new(callback));
+		self.deferred_constraints
+			.push((self.namespaces.clone(), Box::new(callback)));
+		self.record(Operation::Randomize);
 		Ok(())
 	}
 }
@@ -197,12 +261,34 @@ impl<'t> ConstraintSystem for RandomizingVerifier<'t> {
 	fn allocate_single(&mut self, _: Option<Scalar>) -> Result<(Variable, Option<Variable>), R1CSError> {
 		self.verifier.allocate_single(None)
 	}
+
+	fn commit_public_bytes(&mut self, _label: &'static [u8], _bytes: &[u8]) -> Result<(), R1CSError> {
+		Err(R1CSError::PublicInputAfterChallenge)
+	}
+
+	fn push_namespace(&mut self, label: &'static [u8]) {
+		self.verifier.transcript.r1cs_namespace_begin(label);
+		self.verifier.namespaces.push(label);
+	}
+
+	fn pop_namespace(&mut self) {
+		let label = self
+			.verifier
+			.namespaces
+			.pop()
+			.expect("pop_namespace called without a matching push_namespace");
+		self.verifier.transcript.r1cs_namespace_end(label);
+	}
 }
 
 impl<'t> RandomizedConstraintSystem for RandomizingVerifier<'t> {
 	fn challenge_scalar(&mut self, label: &'static [u8]) -> Scalar {
 		self.verifier.transcript.challenge_scalar(label)
 	}
+
+	fn challenge_bytes(&mut self, label: &'static [u8], dest: &mut [u8]) {
+		challenge_bytes(self.verifier.transcript, label, dest)
+	}
 }
 
 impl<'t> Verifier<'t> {
@@ -232,7 +318,36 @@ impl<'t> Verifier<'t> {
 	/// The second element is a list of [`Variable`]s corresponding to
 	/// the external inputs, which can be used to form constraints.
 	pub fn new(transcript: &'t mut Transcript) -> Self {
-		transcript.r1cs_domain_sep();
+		Verifier::new_labeled(transcript, b"")
+	}
+
+	/// Construct an empty constraint system like [`Verifier::new`], and
+	/// bind the Pedersen bases of `pc_gens` to the transcript.
+	///
+	/// Only proofs created by a [`Prover`](::r1cs::Prover) constructed with
+	/// [`Prover::new_with_gens`](::r1cs::Prover::new_with_gens) for the
+	/// same bases verify, and the same `pc_gens` must be passed to
+	/// [`Verifier::verify`].  The default bases are not bound, so with them
+	/// this is the same as [`Verifier::new`].
+	pub fn new_with_gens(transcript: &'t mut Transcript, pc_gens: &PedersenGens) -> Self {
+		let verifier = Verifier::new(transcript);
+		if !pc_gens.is_default() {
+			verifier
+				.transcript
+				.r1cs_pedersen_gens(&pc_gens.B.compress(), &pc_gens.B_blinding.compress());
+		}
+		verifier
+	}
+
+	/// Construct an empty constraint system for the circuit `label`, like
+	/// [`Verifier::new`].
+	///
+	/// Only proofs created by a [`Prover`](::r1cs::Prover) constructed
+	/// with [`Prover::new_labeled`](::r1cs::Prover::new_labeled) for the
+	/// same label verify.  An empty label is
+	/// the same as [`Verifier::new`].
+	pub fn new_labeled(transcript: &'t mut Transcript, label: &'static [u8]) -> Self {
+		transcript.r1cs_domain_sep(label);
 
 		Verifier {
 			transcript,
@@ -241,9 +356,23 @@ impl<'t> Verifier<'t> {
 			constraints: Vec::new(),
 			deferred_constraints: Vec::new(),
 			pending_multiplier: None,
+			num_public_inputs: 0,
+			synthesis_trace: if cfg!(debug_assertions) {
+				Some(SynthesisTrace::default())
+			} else {
+				None
+			},
+			namespaces: Vec::new(),
+			observer: Observer::default(),
+			circuit_hash_binding: None,
 		}
 	}
 
+	/// Reports the phases of verification to the `observer`.
+	pub fn set_observer(&mut self, observer: &'t dyn ProverObserver) {
+		self.observer = Observer(Some(observer));
+	}
+
 	/// Creates commitment to a high-level variable and adds it to the
 	/// transcript.
 	///
@@ -255,71 +384,175 @@ impl<'t> Verifier<'t> {
 	/// produced by [`ConstraintSystem::challenge_scalar`] are bound
 	/// to the external variables.
 	///
+	/// The commitment may have been published with another proof, such as
+	/// a [`RangeProof`](::RangeProof), in which case the prover commits to
+	/// it with [`Prover::commit_existing`](::r1cs::Prover::commit_existing).
+	///
 	/// # Returns
 	///
 	/// Returns a pair of a Pedersen commitment (as a compressed Ristretto
 	/// point), and a [`Variable`] corresponding to it, which can be used to
 	/// form constraints.
 	pub fn commit(&mut self, commitment: CompressedRistretto) -> Variable {
-		let i = self.V.len();
-		self.V.push(commitment);
-
 		// Add the commitment to the transcript.
 		self.transcript.append_point(b"V", &commitment);
+		self.push_commitment(Commitment::Compressed(commitment))
+	}
+
+	/// Creates commitment to a high-level variable from an already
+	/// decompressed point, like [`Verifier::commit`].
+	///
+	/// The commitment is compressed once to be added to the transcript,
+	/// and is not decompressed again when the proof is verified.  Proofs
+	/// verify the same whichever of the two functions commits them.
+	pub fn commit_decompressed(&mut self, commitment: RistrettoPoint) -> Variable {
+		self.transcript.append_point(b"V", &commitment.compress());
+		self.push_commitment(Commitment::Decompressed(commitment))
+	}
+
+	/// Stores a commitment already added to the transcript, and returns
+	/// its variable.
+	fn push_commitment(&mut self, commitment: Commitment) -> Variable {
+		let i = self.V.len();
+		self.V.push(commitment);
+		self.record(Operation::Commit {
+			variable: Variable::Committed(i),
+		});
 
 		Variable::Committed(i)
 	}
 
-	/// Use a challenge, `z`, to flatten the constraints in the
-	/// constraint system into vectors used for proving and
-	/// verification.
-	///
-	/// # Output
-	///
-	/// Returns a tuple of
-	/// ```text
-	/// (wL, wR, wO, wV, wc)
-	/// ```
-	/// where `w{L,R,O}` is \\( z \cdot z^Q \cdot W_{L,R,O} \\).
-	///
-	/// This has the same logic as `ProverCS::flattened_constraints()`
-	/// but also computes the constant terms (which the prover skips
-	/// because they're not needed to construct the proof).
-	fn flattened_constraints(&mut self, z: &Scalar) -> (Vec<Scalar>, Vec<Scalar>, Vec<Scalar>, Vec<Scalar>, Scalar) {
-		let n = self.num_vars;
-		let m = self.V.len();
-
-		let mut wL = vec![Scalar::zero(); n];
-		let mut wR = vec![Scalar::zero(); n];
-		let mut wO = vec![Scalar::zero(); n];
-		let mut wV = vec![Scalar::zero(); m];
-		let mut wc = Scalar::zero();
-
-		let mut exp_z = *z;
-		for lc in self.constraints.iter() {
-			for (var, coeff) in &lc.terms {
-				match var {
-					Variable::MultiplierLeft(i) => {
-						wL[*i] += exp_z * coeff;
-					}
-					Variable::MultiplierRight(i) => {
-						wR[*i] += exp_z * coeff;
-					}
-					Variable::MultiplierOutput(i) => {
-						wO[*i] += exp_z * coeff;
-					}
-					Variable::Committed(i) => {
-						wV[*i] -= exp_z * coeff;
-					}
-					Variable::One() => {
-						wc -= exp_z * coeff;
-					}
-				}
-			}
-			exp_z *= z;
+	/// Absorbs each of the `commitments` in order, like calling
+	/// [`Verifier::commit`] for each of them.
+	///
+	/// Returns the [`Variable`]s corresponding to the commitments, in the
+	/// same order.
+	pub fn commit_vec(&mut self, commitments: &[CompressedRistretto]) -> Vec<Variable> {
+		commitments.iter().map(|V| self.commit(*V)).collect()
+	}
+
+	/// Returns a canonical digest of the constraints synthesized so far.
+	///
+	/// See [`Prover::circuit_hash`](::r1cs::Prover::circuit_hash).
+	pub fn circuit_hash(&self) -> [u8; 32] {
+		circuit::circuit_hash(self.num_vars, self.V.len(), &self.constraints)
+	}
+
+	/// Returns a digest of the shape of the circuit synthesized so far.
+	///
+	/// This is the [`Verifier::circuit_hash`], and it hashes all the
+	/// constraints again on every call, as
+	/// [`Prover::shape_digest`](::r1cs::Prover::shape_digest) does.
+	pub fn shape_digest(&self) -> [u8; 32] {
+		self.circuit_hash()
+	}
+
+	/// Requires the proof to be bound to the circuit hash
+	/// `expected_circuit_hash`, as with
+	/// [`Prover::bind_circuit_hash`](::r1cs::Prover::bind_circuit_hash).
+	///
+	/// The expected hash is the
+	/// [`Prover::circuit_hash`](::r1cs::Prover::circuit_hash) of the prover,
+	/// exchanged out-of-band, or the [`CircuitDescription::circuit_hash`] of
+	/// the circuit.  Verification fails with [`R1CSError::CircuitMismatch`]
+	/// before checking the proof if the [`Verifier::circuit_hash`] of the
+	/// constraints synthesized before verifying is different.
+	pub fn bind_circuit_hash(&mut self, expected_circuit_hash: [u8; 32]) {
+		self.circuit_hash_binding = Some(expected_circuit_hash);
+	}
+
+	/// Returns the number of constraints synthesized so far.
+	///
+	/// Like [`Prover::num_constraints`](::r1cs::Prover::num_constraints),
+	/// this does not include randomized constraints.
+	pub fn num_constraints(&self) -> usize {
+		self.constraints.len()
+	}
+
+	/// Returns the number of multipliers allocated so far.
+	pub fn num_multipliers(&self) -> usize {
+		self.num_vars
+	}
+
+	/// Returns the [`Metrics`] of the constraint system synthesized so far.
+	pub fn metrics(&self) -> Metrics {
+		Metrics::new(self.num_vars, self.constraints.len(), self.V.len())
+	}
+
+	/// Starts recording a [`SynthesisTrace`] of the following operations.
+	///
+	/// See [`Prover::record_trace`](::r1cs::Prover::record_trace).
+	pub fn record_trace(&mut self) {
+		if self.synthesis_trace.is_none() {
+			self.synthesis_trace = Some(SynthesisTrace::default());
+		}
+	}
+
+	/// Returns the [`SynthesisTrace`] recorded so far, if recording.
+	pub fn trace(&self) -> Option<&SynthesisTrace> {
+		self.synthesis_trace.as_ref()
+	}
+
+	/// Takes a [`Snapshot`] of the synthesis so far.
+	///
+	/// See [`Prover::snapshot`](::r1cs::Prover::snapshot).
+	pub fn snapshot(&self) -> Snapshot {
+		Snapshot {
+			multipliers: self.num_vars,
+			pending_multiplier: self.pending_multiplier,
+			constraints: self.constraints.len(),
+			deferred_constraints: self.deferred_constraints.len(),
+			transcript_operations: self.V.len() + self.num_public_inputs,
+			trace: self.synthesis_trace.as_ref().map(SynthesisTrace::len),
+			namespaces: self.namespaces.clone(),
+		}
+	}
+
+	/// Rolls the synthesis back to the `snapshot`, discarding the
+	/// multipliers, constraints and randomized constraints added since.
+	///
+	/// See [`Prover::rollback`](::r1cs::Prover::rollback).
+	pub fn rollback(&mut self, snapshot: &Snapshot) -> Result<(), R1CSError> {
+		if !snapshot.can_roll_back(
+			self.num_vars,
+			self.constraints.len(),
+			self.deferred_constraints.len(),
+			self.V.len() + self.num_public_inputs,
+		) {
+			return Err(R1CSError::RollbackAcrossTranscript);
+		}
+
+		self.num_vars = snapshot.multipliers;
+		self.pending_multiplier = snapshot.pending_multiplier;
+		self.constraints.truncate(snapshot.constraints);
+		self.deferred_constraints.truncate(snapshot.deferred_constraints);
+		if let Some(trace) = self.synthesis_trace.as_mut() {
+			trace.truncate(snapshot.trace.unwrap_or(0));
+		}
+		self.namespaces = snapshot.namespaces.clone();
+		Ok(())
+	}
+
+	fn record(&mut self, operation: Operation) {
+		if let Some(trace) = self.synthesis_trace.as_mut() {
+			trace.push(operation);
 		}
+	}
 
-		(wL, wR, wO, wV, wc)
+	/// Consumes a verifier whose gadgets have been synthesized and returns
+	/// its constraints together with the number of multipliers.
+	///
+	/// Fails if randomized constraints were specified, since those depend
+	/// on challenges bound to a particular proof, or if public inputs were
+	/// committed, since those are bound to a particular statement.
+	pub(super) fn into_constraints(self) -> Result<(Vec<LinearCombination>, usize), R1CSError> {
+		if !self.deferred_constraints.is_empty() {
+			return Err(R1CSError::gadget("randomized constraints cannot be precomputed"));
+		}
+		if self.num_public_inputs > 0 {
+			return Err(R1CSError::gadget("public inputs cannot be precomputed"));
+		}
+		Ok((self.constraints, self.num_vars))
 	}
 
 	/// Calls all remembered callbacks with an API that
@@ -339,20 +572,19 @@ impl<'t> Verifier<'t> {
 			// so we move the self into wrapper and then move it back out afterwards.
 			let mut callbacks = mem::replace(&mut self.deferred_constraints, Vec::new());
 			let mut wrapped_self = RandomizingVerifier { verifier: self };
-			for callback in callbacks.drain(..) {
-				callback(&mut wrapped_self)?;
+			for (namespaces, callback) in callbacks.drain(..) {
+				call_within_namespaces(&mut wrapped_self, &namespaces, callback)?;
 			}
 			Ok(wrapped_self.verifier)
 		}
 	}
 
+	/// Consume this `VerifierCS` and attempt to verify the supplied `proof`.
+	///
+	/// This is a convenience wrapper around [`Verifier::verify_with_rng`],
+	/// passing in a threadsafe RNG.
 	#[cfg(feature = "std")]
-	pub fn verify(
-		mut self,
-		proof: &R1CSProof,
-		pc_gens: &PedersenGens,
-		bp_gens: &BulletproofGens,
-	) -> Result<(), R1CSError> {
+	pub fn verify(self, proof: &R1CSProof, pc_gens: &PedersenGens, bp_gens: &BulletproofGens) -> Result<(), R1CSError> {
 		self.verify_with_rng(proof, pc_gens, bp_gens, &mut thread_rng())
 	}
 
@@ -362,18 +594,308 @@ impl<'t> Verifier<'t> {
 	/// [`BulletproofGens`] should have `gens_capacity` greater than
 	/// the number of multiplication constraints that will eventually
 	/// be added into the constraint system.
+	///
+	/// This runs [`Verifier::pre_verify_with_rng`] followed by
+	/// [`PreVerified::finish`].
 	pub fn verify_with_rng<T: RngCore + CryptoRng>(
-		mut self,
+		self,
+		proof: &R1CSProof,
+		pc_gens: &PedersenGens,
+		bp_gens: &BulletproofGens,
+		prng: &mut T,
+	) -> Result<(), R1CSError> {
+		self.verify_with_backend(proof, pc_gens, bp_gens, prng, None)
+	}
+
+	/// Consume this `VerifierCS` and attempt to verify the supplied `proof`,
+	/// performing the final multiscalar multiplication with `backend`.
+	///
+	/// If `backend` is `None`, the [`DefaultMsmBackend`] is used.
+	pub fn verify_with_backend<T: RngCore + CryptoRng>(
+		self,
+		proof: &R1CSProof,
+		pc_gens: &PedersenGens,
+		bp_gens: &BulletproofGens,
+		prng: &mut T,
+		backend: Option<&dyn MsmBackend>,
+	) -> Result<(), R1CSError> {
+		let observer = self.observer;
+		let pre_verified = self.pre_verify_with_rng(proof, prng)?;
+
+		observer.start(Phase::Multiscalar);
+		pre_verified.finish_with_backend(pc_gens, bp_gens, backend)?;
+		observer.end(Phase::Multiscalar);
+		Ok(())
+	}
+
+	/// Consume this `VerifierCS` and perform the cheap part of the
+	/// verification of `proof`, adding its final check to the `batch`
+	/// instead of performing it.
+	///
+	/// Returns the index of the statement in the `batch`.  Errors which do
+	/// not need the final check, such as a malformed proof or too few
+	/// generators in the batch, are returned immediately, and nothing is
+	/// added to the `batch`.
+	pub fn verify_deferred<T: RngCore + CryptoRng>(
+		self,
+		proof: &R1CSProof,
+		batch: &mut VerificationBatch<'_, T>,
+	) -> Result<usize, R1CSError> {
+		let pre_verified = self.pre_verify_with_rng(proof, &mut batch.rng)?;
+		let gens_capacity = batch.bp_gens().gens_capacity;
+		if gens_capacity < pre_verified.padded_n {
+			return Err(R1CSError::InvalidGeneratorsLength {
+				required: pre_verified.padded_n,
+				actual: gens_capacity,
+			});
+		}
+		Ok(batch.push(pre_verified.into_statement()))
+	}
+
+	/// Verifies the supplied `proof` of the circuit of the `description`.
+	///
+	/// This is a convenience wrapper around
+	/// [`Verifier::verify_compiled_with_rng`], passing in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn verify_compiled(
+		transcript: &mut Transcript,
+		description: &CircuitDescription,
+		proof: &R1CSProof,
+		commitments: &[CompressedRistretto],
+		pc_gens: &PedersenGens,
+		bp_gens: &BulletproofGens,
+	) -> Result<(), R1CSError> {
+		Verifier::verify_compiled_with_rng(
+			transcript,
+			description,
+			proof,
+			commitments,
+			pc_gens,
+			bp_gens,
+			&mut thread_rng(),
+		)
+	}
+
+	/// Verifies the supplied `proof` of the circuit of the `description`
+	/// against the given `commitments`, without running any gadget code.
+	///
+	/// The `transcript` must be in the same state as the one passed to
+	/// [`Verifier::new_with_gens`] when verifying without a description.  A
+	/// proof is accepted exactly when a verifier created with
+	/// [`Verifier::new_with_gens`] for the `pc_gens` and synthesizing the
+	/// same circuit accepts it.
+	pub fn verify_compiled_with_rng<T: RngCore + CryptoRng>(
+		transcript: &mut Transcript,
+		description: &CircuitDescription,
 		proof: &R1CSProof,
+		commitments: &[CompressedRistretto],
 		pc_gens: &PedersenGens,
 		bp_gens: &BulletproofGens,
 		prng: &mut T,
 	) -> Result<(), R1CSError> {
+		description
+			.pre_verify(transcript, pc_gens, commitments, proof, prng)?
+			.finish(pc_gens, bp_gens)
+	}
+
+	/// Verifies a batch of proofs with a single multiscalar multiplication.
+	///
+	/// This is a convenience wrapper around
+	/// [`Verifier::verify_batch_with_rng`], passing in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn verify_batch(
+		instances: Vec<(Verifier<'t>, &R1CSProof)>,
+		pc_gens: &PedersenGens,
+		bp_gens: &BulletproofGens,
+	) -> Result<(), R1CSError> {
+		Verifier::verify_batch_with_rng(instances, pc_gens, bp_gens, &mut thread_rng())
+	}
+
+	/// Verifies a batch of proofs with a single multiscalar multiplication.
+	///
+	/// Each of the `instances` is a verifier with its gadgets synthesized,
+	/// together with the proof to check against it.  Every proof is
+	/// pre-verified with [`Verifier::pre_verify_with_rng`], and the
+	/// verification equations are then combined with
+	/// [`PreVerified::finish_batch`].
+	///
+	/// Returns [`R1CSError::BatchVerificationError`] with the index of the
+	/// first proof which does not verify.
+	pub fn verify_batch_with_rng<T: RngCore + CryptoRng>(
+		instances: Vec<(Verifier<'t>, &R1CSProof)>,
+		pc_gens: &PedersenGens,
+		bp_gens: &BulletproofGens,
+		prng: &mut T,
+	) -> Result<(), R1CSError> {
+		let instances = instances
+			.into_iter()
+			.map(|(verifier, proof)| (BatchCircuit::Synthesized(verifier), proof))
+			.collect();
+		Verifier::verify_circuit_batch_with_rng(instances, pc_gens, bp_gens, prng)
+	}
+
+	/// Verifies a batch of proofs of possibly different circuits with a
+	/// single multiscalar multiplication.
+	///
+	/// This is a convenience wrapper around
+	/// [`Verifier::verify_circuit_batch_with_rng`], passing in a threadsafe
+	/// RNG.
+	#[cfg(feature = "std")]
+	pub fn verify_circuit_batch<'a>(
+		instances: Vec<(BatchCircuit<'t, 'a>, &R1CSProof)>,
+		pc_gens: &PedersenGens,
+		bp_gens: &BulletproofGens,
+	) -> Result<(), R1CSError> {
+		Verifier::verify_circuit_batch_with_rng(instances, pc_gens, bp_gens, &mut thread_rng())
+	}
+
+	/// Verifies a batch of proofs of possibly different circuits with a
+	/// single multiscalar multiplication.
+	///
+	/// Each of the `instances` is a circuit, either synthesized in a
+	/// verifier or described by a [`CircuitDescription`], together with the
+	/// proof to check against it.  Every statement is pre-verified with its
+	/// own transcript, and the verification equations are then combined
+	/// with [`PreVerified::finish_batch`]: the scalars of the generators of
+	/// each statement are added to those of the first generators of the
+	/// shared party share, up to the largest padded number of multipliers.
+	///
+	/// Returns [`R1CSError::BatchVerificationError`] with the index of the
+	/// first proof which does not verify.
+	pub fn verify_circuit_batch_with_rng<'a, T: RngCore + CryptoRng>(
+		instances: Vec<(BatchCircuit<'t, 'a>, &R1CSProof)>,
+		pc_gens: &PedersenGens,
+		bp_gens: &BulletproofGens,
+		prng: &mut T,
+	) -> Result<(), R1CSError> {
+		let batch = instances
+			.into_iter()
+			.enumerate()
+			.map(|(index, (circuit, proof))| {
+				circuit
+					.pre_verify(pc_gens, proof, prng)
+					.map_err(|_| R1CSError::BatchVerificationError { index })
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+
+		PreVerified::finish_batch(batch, pc_gens, bp_gens, prng)
+	}
+
+	/// Verifies a batch of proofs in up to `parallelism` partitions, each
+	/// checked with one multiscalar multiplication on the current `rayon`
+	/// thread pool.
+	///
+	/// The verifiers cannot be sent to other threads, so every proof is
+	/// first pre-verified on the calling thread with
+	/// [`Verifier::pre_verify`].  The partitions then combine and check
+	/// their verification equations in parallel, so the batch is accepted
+	/// exactly when [`Verifier::verify_batch`] would accept it.
+	///
+	/// Returns [`R1CSError::BatchStatementsVerificationError`] with the
+	/// indexes of all the proofs which do not verify, from every partition.
+	#[cfg(feature = "rayon")]
+	pub fn verify_batch_parallel(
+		instances: Vec<(Verifier<'t>, &R1CSProof)>,
+		pc_gens: &PedersenGens,
+		bp_gens: &BulletproofGens,
+		parallelism: usize,
+	) -> Result<(), R1CSError> {
+		let batch: Vec<_> = instances
+			.into_iter()
+			.map(|(verifier, proof)| verifier.pre_verify(proof))
+			.collect();
+
+		let padded_n = batch
+			.iter()
+			.filter_map(|pre_verified| pre_verified.as_ref().ok())
+			.map(|pre_verified| pre_verified.padded_n)
+			.max()
+			.unwrap_or(0);
+		if bp_gens.gens_capacity < padded_n {
+			return Err(R1CSError::InvalidGeneratorsLength {
+				required: padded_n,
+				actual: bp_gens.gens_capacity,
+			});
+		}
+
+		let indexes = crate::verification_batch::verify_partitioned(
+			batch,
+			parallelism,
+			bp_gens,
+			pc_gens,
+			|pre_verified, batch| pre_verified.map(|pre_verified| batch.push(pre_verified.into_statement())),
+		);
+		if indexes.is_empty() {
+			Ok(())
+		} else {
+			Err(R1CSError::BatchStatementsVerificationError { indexes })
+		}
+	}
+
+	/// Consume this `VerifierCS` and return the terms of the verification
+	/// equation of `proof`.
+	///
+	/// This is a convenience wrapper around
+	/// [`Verifier::verification_terms_with_rng`], passing in a threadsafe
+	/// RNG.
+	#[cfg(feature = "std")]
+	pub fn verification_terms(self, proof: &R1CSProof) -> Result<VerificationTerms, R1CSError> {
+		self.verification_terms_with_rng(proof, &mut thread_rng())
+	}
+
+	/// Consume this `VerifierCS` and return the terms of the verification
+	/// equation of `proof`, for protocols which combine it with their own
+	/// equations.
+	///
+	/// This runs [`Verifier::pre_verify_with_rng`] followed by
+	/// [`PreVerified::into_terms`], and the proof verifies if and only if
+	/// [`VerificationTerms::verify`] succeeds.
+	pub fn verification_terms_with_rng<T: RngCore + CryptoRng>(
+		self,
+		proof: &R1CSProof,
+		prng: &mut T,
+	) -> Result<VerificationTerms, R1CSError> {
+		Ok(self.pre_verify_with_rng(proof, prng)?.into_terms())
+	}
+
+	/// Consume this `VerifierCS` and perform the cheap part of the
+	/// verification of `proof`.
+	///
+	/// This is a convenience wrapper around
+	/// [`Verifier::pre_verify_with_rng`], passing in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn pre_verify(self, proof: &R1CSProof) -> Result<PreVerified, R1CSError> {
+		self.pre_verify_with_rng(proof, &mut thread_rng())
+	}
+
+	/// Consume this `VerifierCS` and perform the cheap part of the
+	/// verification of `proof`: size consistency checks, scalar
+	/// canonicality and point decompression, followed by replaying the
+	/// transcript and computing the scalars of the verification equation.
+	///
+	/// No generators are used at this stage, so malformed proofs can be
+	/// rejected before paying for a multiscalar multiplication.  The
+	/// returned [`PreVerified`] performs the expensive check in
+	/// [`PreVerified::finish`].
+	pub fn pre_verify_with_rng<T: RngCore + CryptoRng>(
+		mut self,
+		proof: &R1CSProof,
+		prng: &mut T,
+	) -> Result<PreVerified, R1CSError> {
+		enter_span!("r1cs_pre_verify", m = self.V.len(), n1 = self.num_vars);
+
 		// Commit a length _suffix_ for the number of high-level variables.
 		// We cannot do this in advance because user can commit variables one-by-one,
 		// but this suffix provides safe disambiguation because each variable
 		// is prefixed with a separate label.
-		self.transcript.append_u64(b"m", self.V.len() as u64);
+		append_u64(self.transcript, b"m", self.V.len() as u64);
+
+		if let Some(expected_circuit_hash) = self.circuit_hash_binding {
+			if self.circuit_hash() != expected_circuit_hash {
+				return Err(R1CSError::CircuitMismatch);
+			}
+			self.transcript.r1cs_circuit_hash(&expected_circuit_hash);
+		}
 
 		let n1 = self.num_vars;
 		self.transcript.validate_and_append_point(b"A_I1", &proof.A_I1)?;
@@ -381,145 +903,298 @@ impl<'t> Verifier<'t> {
 		self.transcript.validate_and_append_point(b"S1", &proof.S1)?;
 
 		// Process the remaining constraints.
-		self = self.create_randomized_constraints()?;
+		let observer = self.observer;
+		observer.start(Phase::Synthesis);
+		self = in_span!("r1cs_synthesis", { self.create_randomized_constraints() })?;
+		observer.end(Phase::Synthesis);
 
-		// If the number of multiplications is not 0 or a power of 2, then pad the
-		// circuit.
-		let n = self.num_vars;
-		let n2 = n - n1;
-		let padded_n = self.num_vars.next_power_of_two();
-		let pad = padded_n - n;
+		let V = self
+			.V
+			.iter()
+			.map(|V| match V {
+				Commitment::Compressed(V) => V.decompress().ok_or(R1CSError::FormatError),
+				Commitment::Decompressed(V) => Ok(*V),
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+
+		pre_verify_committed_proof(
+			self.transcript,
+			proof,
+			&V,
+			&self.constraints,
+			n1,
+			self.num_vars,
+			prng,
+			observer,
+		)
+	}
+}
 
-		use crate::{inner_product_proof::inner_product, util};
-		use core::iter;
+/// The circuit of a statement verified with
+/// [`Verifier::verify_circuit_batch`].
+pub enum BatchCircuit<'t, 'a> {
+	/// A verifier with the gadgets of the circuit synthesized and the
+	/// commitments of the statement committed.
+	Synthesized(Verifier<'t>),
+	/// A circuit described once, verified against the `commitments` as in
+	/// [`Verifier::verify_compiled`].
+	Described {
+		/// The transcript of the statement, in the same state as the one
+		/// passed to [`Verifier::new_with_gens`] when verifying without a
+		/// description.
+		transcript: &'t mut Transcript,
+		/// The description of the circuit.
+		description: &'a CircuitDescription,
+		/// The commitments to the high-level variables of the circuit.
+		commitments: &'a [CompressedRistretto],
+	},
+}
 
-		if bp_gens.gens_capacity < padded_n {
-			return Err(R1CSError::InvalidGeneratorsLength);
+impl<'t, 'a> BatchCircuit<'t, 'a> {
+	/// Performs the cheap part of the verification of the `proof` of the
+	/// circuit under the bases of `pc_gens`.
+	fn pre_verify<T: RngCore + CryptoRng>(
+		self,
+		pc_gens: &PedersenGens,
+		proof: &R1CSProof,
+		prng: &mut T,
+	) -> Result<PreVerified, R1CSError> {
+		match self {
+			BatchCircuit::Synthesized(verifier) => verifier.pre_verify_with_rng(proof, prng),
+			BatchCircuit::Described {
+				transcript,
+				description,
+				commitments,
+			} => description.pre_verify(transcript, pc_gens, commitments, proof, prng),
 		}
-		// We are performing a single-party circuit proof, so party index is 0.
-		let gens = bp_gens.share(0);
+	}
+}
 
-		// These points are the identity in the 1-phase unrandomized case.
-		self.transcript.append_point(b"A_I2", &proof.A_I2);
-		self.transcript.append_point(b"A_O2", &proof.A_O2);
-		self.transcript.append_point(b"S2", &proof.S2);
+/// A proof which passed the cheap checks of [`Verifier::pre_verify`] and
+/// awaits the final multiscalar multiplication.
+///
+/// All points of the proof and the commitments are decompressed, and the
+/// scalars of the verification equation are computed, so that only the
+/// generators are still needed to complete the verification.
+pub struct PreVerified {
+	/// Number of multipliers, padded to a power of two.
+	padded_n: usize,
+	/// Points from the proof and the commitments.
+	points: Vec<RistrettoPoint>,
+	/// Scalars for `points`.
+	point_scalars: Vec<Scalar>,
+	/// Scalar for the Pedersen base `B`.
+	B_scalar: Scalar,
+	/// Scalar for the Pedersen base `B_blinding`.
+	B_blinding_scalar: Scalar,
+	/// Scalars for the \\(\mathbf G\\) generators.
+	g_scalars: Vec<Scalar>,
+	/// Scalars for the \\(\mathbf H\\) generators.
+	h_scalars: Vec<Scalar>,
+}
 
-		let y = self.transcript.challenge_scalar(b"y");
-		let z = self.transcript.challenge_scalar(b"z");
+impl PreVerified {
+	/// Completes the verification with a single multiscalar
+	/// multiplication over the proof points and the generators.
+	///
+	/// The [`BulletproofGens`] should have `gens_capacity` greater than
+	/// the number of multipliers in the constraint system.
+	pub fn finish(self, pc_gens: &PedersenGens, bp_gens: &BulletproofGens) -> Result<(), R1CSError> {
+		self.finish_with_backend(pc_gens, bp_gens, None)
+	}
 
-		self.transcript.validate_and_append_point(b"T_1", &proof.T_1)?;
-		self.transcript.validate_and_append_point(b"T_3", &proof.T_3)?;
-		self.transcript.validate_and_append_point(b"T_4", &proof.T_4)?;
-		self.transcript.validate_and_append_point(b"T_5", &proof.T_5)?;
-		self.transcript.validate_and_append_point(b"T_6", &proof.T_6)?;
+	/// Completes the verification like [`PreVerified::finish`], performing
+	/// the multiscalar multiplication with `backend`.
+	///
+	/// If `backend` is `None`, the [`DefaultMsmBackend`] is used.
+	pub fn finish_with_backend(
+		self,
+		pc_gens: &PedersenGens,
+		bp_gens: &BulletproofGens,
+		backend: Option<&dyn MsmBackend>,
+	) -> Result<(), R1CSError> {
+		if bp_gens.gens_capacity < self.padded_n {
+			return Err(R1CSError::InvalidGeneratorsLength {
+				required: self.padded_n,
+				actual: bp_gens.gens_capacity,
+			});
+		}
+		// We are performing a single-party circuit proof, so party index is 0.
+		let G = &bp_gens.G_vec[0][..self.padded_n];
+		let H = &bp_gens.H_vec[0][..self.padded_n];
 
-		let u = self.transcript.challenge_scalar(b"u");
-		let x = self.transcript.challenge_scalar(b"x");
+		self.check(pc_gens, G, H, backend.unwrap_or(&DefaultMsmBackend))
+	}
 
-		self.transcript.append_scalar(b"t_x", &proof.t_x);
-		self.transcript.append_scalar(b"t_x_blinding", &proof.t_x_blinding);
-		self.transcript.append_scalar(b"e_blinding", &proof.e_blinding);
+	/// Converts the verification equation into a statement of a
+	/// [`VerificationBatch`], using the single party share.
+	fn into_statement(self) -> Statement {
+		Statement {
+			n: self.padded_n,
+			m: 1,
+			points: self.points,
+			point_scalars: self.point_scalars,
+			B_scalar: self.B_scalar,
+			B_blinding_scalar: self.B_blinding_scalar,
+			g_scalars: self.g_scalars,
+			h_scalars: self.h_scalars,
+		}
+	}
 
-		let w = self.transcript.challenge_scalar(b"w");
+	/// Converts the verification equation into [`VerificationTerms`],
+	/// identifying each point by its role in the proof.
+	pub fn into_terms(self) -> VerificationTerms {
+		const PROOF_POINTS: [&[u8]; 6] = [b"A_I1", b"A_O1", b"S1", b"A_I2", b"A_O2", b"S2"];
+		const T_POINTS: [&[u8]; 5] = [b"T_1", b"T_3", b"T_4", b"T_5", b"T_6"];
 
-		let (wL, wR, wO, wV, wc) = self.flattened_constraints(&z);
+		let lg_n = self.padded_n.trailing_zeros() as usize;
+		let m = self.points.len() - PROOF_POINTS.len() - T_POINTS.len() - 2 * lg_n;
+		let point_ids = PROOF_POINTS
+			.iter()
+			.map(|label| TermPoint::Proof(label))
+			.chain((0..m).map(TermPoint::Commitment))
+			.chain(T_POINTS.iter().map(|label| TermPoint::Proof(label)))
+			.chain((0..lg_n).map(TermPoint::L))
+			.chain((0..lg_n).map(TermPoint::R))
+			.collect();
+		VerificationTerms::new(self.into_statement(), point_ids)
+	}
 
-		// Get IPP variables
-		let (u_sq, u_inv_sq, s) = proof
-			.ipp_proof
-			.verification_scalars(padded_n, self.transcript)
-			.map_err(|_| R1CSError::VerificationError)?;
+	/// Completes the verification like [`PreVerified::check`], with the
+	/// Pedersen bases and the generators in a `precomputation`.
+	///
+	/// The static points of the `precomputation` must be `B`, `B_blinding`
+	/// and the first `padded_n` \\(\mathbf G\\) and \\(\mathbf H\\)
+	/// generators, in this order.
+	pub(super) fn check_precomputed(self, precomputation: &VartimeRistrettoPrecomputation) -> Result<(), R1CSError> {
+		use core::iter;
+		use curve25519_dalek::traits::{IsIdentity, VartimePrecomputedMultiscalarMul};
 
-		let a = proof.ipp_proof.a;
-		let b = proof.ipp_proof.b;
+		let static_scalars = iter::once(self.B_scalar)
+			.chain(iter::once(self.B_blinding_scalar))
+			.chain(self.g_scalars)
+			.chain(self.h_scalars);
 
-		let y_inv = y.invert();
-		let y_inv_vec = util::exp_iter(y_inv).take(padded_n).collect::<Vec<Scalar>>();
-		let yneg_wR = wR
-			.into_iter()
-			.zip(y_inv_vec.iter())
-			.map(|(wRi, exp_y_inv)| wRi * exp_y_inv)
-			.chain(iter::repeat(Scalar::zero()).take(pad))
-			.collect::<Vec<Scalar>>();
+		let mega_check = in_span!("r1cs_multiexp", size = self.points.len(), {
+			precomputation.vartime_mixed_multiscalar_mul(static_scalars, &self.point_scalars, &self.points)
+		});
 
-		let delta = inner_product(&yneg_wR[0..n], &wL);
+		if !mega_check.is_identity() {
+			return Err(R1CSError::VerificationError);
+		}
 
-		let u_for_g = iter::repeat(Scalar::one())
-			.take(n1)
-			.chain(iter::repeat(u).take(n2 + pad));
-		let u_for_h = u_for_g.clone();
+		Ok(())
+	}
 
-		// define parameters for P check
-		let g_scalars = yneg_wR
-			.iter()
-			.zip(u_for_g)
-			.zip(s.iter().take(padded_n))
-			.map(|((yneg_wRi, u_or_1), s_i)| u_or_1 * (x * yneg_wRi - a * s_i));
+	/// Completes the verification of a `batch` of proofs with a single
+	/// multiscalar multiplication.
+	///
+	/// The verification equation of each proof is weighted by a random
+	/// scalar drawn from `prng`, and the weighted equations are summed, so
+	/// that the generators shared by all proofs appear only once in the
+	/// multiscalar multiplication.  If the combined check fails, the
+	/// proofs are checked one by one to find the invalid one.
+	///
+	/// The [`BulletproofGens`] should have `gens_capacity` greater than
+	/// the number of multipliers of the largest constraint system.
+	///
+	/// Returns [`R1CSError::BatchVerificationError`] with the index of the
+	/// first proof which does not verify.
+	pub fn finish_batch<T: RngCore + CryptoRng>(
+		batch: Vec<PreVerified>,
+		pc_gens: &PedersenGens,
+		bp_gens: &BulletproofGens,
+		prng: &mut T,
+	) -> Result<(), R1CSError> {
+		use curve25519_dalek::traits::IsIdentity;
 
-		let h_scalars = y_inv_vec
+		let padded_n = batch
 			.iter()
-			.zip(u_for_h)
-			.zip(s.iter().rev().take(padded_n))
-			.zip(wL.into_iter().chain(iter::repeat(Scalar::zero()).take(pad)))
-			.zip(wO.into_iter().chain(iter::repeat(Scalar::zero()).take(pad)))
-			.map(|((((y_inv_i, u_or_1), s_i_inv), wLi), wOi)| {
-				u_or_1 * (y_inv_i * (x * wLi + wOi - b * s_i_inv) - Scalar::one())
+			.map(|pre_verified| pre_verified.padded_n)
+			.max()
+			.unwrap_or(0);
+		if bp_gens.gens_capacity < padded_n {
+			return Err(R1CSError::InvalidGeneratorsLength {
+				required: padded_n,
+				actual: bp_gens.gens_capacity,
 			});
+		}
 
-		// Create a `TranscriptRng` from the transcript. The verifier
-		// has no witness data to commit, so this just mixes external
-		// randomness into the existing transcript.
-		let mut rng = self.transcript.build_rng().finalize(prng);
-		let r = Scalar::random(&mut rng);
-
-		let xx = x * x;
-		let rxx = r * xx;
-		let xxx = x * xx;
-
-		// group the T_scalars and T_points together
-		let r_xxx = r * xxx;
-		let r_xxxx = r_xxx * x;
-		let r_xxxxx = r_xxxx * x;
-		let r_xxxxxx = r_xxxxx * x;
-		let T_scalars = [r * x, r_xxx, r_xxxx, r_xxxxx, r_xxxxxx];
-		let T_points = [proof.T_1, proof.T_3, proof.T_4, proof.T_5, proof.T_6];
-
-		let mega_check = RistrettoPoint::optional_multiscalar_mul(
-			iter::once(x) // A_I1
-				.chain(iter::once(xx)) // A_O1
-				.chain(iter::once(xxx)) // S1
-				.chain(iter::once(u * x)) // A_I2
-				.chain(iter::once(u * xx)) // A_O2
-				.chain(iter::once(u * xxx)) // S2
-				.chain(wV.iter().map(|wVi| wVi * rxx)) // V
-				.chain(T_scalars.iter().cloned()) // T_points
-				.chain(iter::once(
-					w * (proof.t_x - a * b) + r * (xx * (wc + delta) - proof.t_x),
-				)) // B
-				.chain(iter::once(-proof.e_blinding - r * proof.t_x_blinding)) // B_blinding
-				.chain(g_scalars) // G
-				.chain(h_scalars) // H
-				.chain(u_sq.iter().cloned()) // ipp_proof.L_vec
-				.chain(u_inv_sq.iter().cloned()), // ipp_proof.R_vec
-			iter::once(proof.A_I1.decompress())
-				.chain(iter::once(proof.A_O1.decompress()))
-				.chain(iter::once(proof.S1.decompress()))
-				.chain(iter::once(proof.A_I2.decompress()))
-				.chain(iter::once(proof.A_O2.decompress()))
-				.chain(iter::once(proof.S2.decompress()))
-				.chain(self.V.iter().map(|V_i| V_i.decompress()))
-				.chain(T_points.iter().map(|T_i| T_i.decompress()))
-				.chain(iter::once(Some(pc_gens.B)))
-				.chain(iter::once(Some(pc_gens.B_blinding)))
-				.chain(gens.G(padded_n).map(|&G_i| Some(G_i)))
-				.chain(gens.H(padded_n).map(|&H_i| Some(H_i)))
-				.chain(proof.ipp_proof.L_vec.iter().map(|L_i| L_i.decompress()))
-				.chain(proof.ipp_proof.R_vec.iter().map(|R_i| R_i.decompress())),
-		)
-		.ok_or_else(|| R1CSError::VerificationError)?;
+		let mut scalars = Vec::new();
+		let mut points = Vec::new();
+		let mut B_scalar = Scalar::zero();
+		let mut B_blinding_scalar = Scalar::zero();
+		let mut g_scalars = vec![Scalar::zero(); padded_n];
+		let mut h_scalars = vec![Scalar::zero(); padded_n];
+
+		for pre_verified in batch.iter() {
+			let c = Scalar::random(prng);
+
+			scalars.extend(pre_verified.point_scalars.iter().map(|s| c * s));
+			points.extend_from_slice(&pre_verified.points);
+			B_scalar += c * pre_verified.B_scalar;
+			B_blinding_scalar += c * pre_verified.B_blinding_scalar;
+			for (acc, s) in g_scalars.iter_mut().zip(pre_verified.g_scalars.iter()) {
+				*acc += c * s;
+			}
+			for (acc, s) in h_scalars.iter_mut().zip(pre_verified.h_scalars.iter()) {
+				*acc += c * s;
+			}
+		}
 
+		scalars.push(B_scalar);
+		scalars.push(B_blinding_scalar);
+		scalars.extend_from_slice(&g_scalars);
+		scalars.extend_from_slice(&h_scalars);
+
+		points.push(pc_gens.B);
+		points.push(pc_gens.B_blinding);
+		points.extend_from_slice(&bp_gens.G_vec[0][..padded_n]);
+		points.extend_from_slice(&bp_gens.H_vec[0][..padded_n]);
+
+		let mega_check = in_span!("r1cs_batch_multiexp", size = points.len(), {
+			DefaultMsmBackend.vartime_msm(&scalars, &points)
+		});
+		if mega_check.is_identity() {
+			return Ok(());
+		}
+
+		// Fall back to individual checks to find the invalid proof.
+		for (index, pre_verified) in batch.into_iter().enumerate() {
+			pre_verified
+				.finish(pc_gens, bp_gens)
+				.map_err(|_| R1CSError::BatchVerificationError { index })?;
+		}
+		Err(R1CSError::VerificationError)
+	}
+
+	/// Completes the verification using the first `padded_n` generators of
+	/// the single party share.
+	pub(super) fn check(
+		self,
+		pc_gens: &PedersenGens,
+		G: &[RistrettoPoint],
+		H: &[RistrettoPoint],
+		backend: &dyn MsmBackend,
+	) -> Result<(), R1CSError> {
 		use curve25519_dalek::traits::IsIdentity;
 
+		let mut scalars = self.point_scalars;
+		scalars.push(self.B_scalar);
+		scalars.push(self.B_blinding_scalar);
+		scalars.extend_from_slice(&self.g_scalars);
+		scalars.extend_from_slice(&self.h_scalars);
+
+		let mut points = self.points;
+		points.push(pc_gens.B);
+		points.push(pc_gens.B_blinding);
+		points.extend_from_slice(G);
+		points.extend_from_slice(H);
+
+		let mega_check = in_span!("r1cs_multiexp", size = points.len(), {
+			backend.vartime_msm(&scalars, &points)
+		});
+
 		if !mega_check.is_identity() {
 			return Err(R1CSError::VerificationError);
 		}
@@ -527,3 +1202,313 @@ impl<'t> Verifier<'t> {
 		Ok(())
 	}
 }
+
+/// Decompresses the `commitments` to the high-level variables.
+pub(super) fn decompress_commitments(commitments: &[CompressedRistretto]) -> Result<Vec<RistrettoPoint>, R1CSError> {
+	commitments
+		.iter()
+		.map(|V| V.decompress().ok_or(R1CSError::FormatError))
+		.collect()
+}
+
+/// Performs the cheap part of the verification once the constraint system
+/// is fully synthesized and the first-phase commitments have been absorbed
+/// into the `transcript`.
+///
+/// `V` are the decompressed commitments, `n1` is the number of first-phase
+/// multipliers and `n` the total number of multipliers.  The flattening of
+/// the constraints is reported to the `observer`.
+pub(super) fn pre_verify_committed_proof<T: RngCore + CryptoRng>(
+	transcript: &mut Transcript,
+	proof: &R1CSProof,
+	V: &[RistrettoPoint],
+	constraints: &[LinearCombination],
+	n1: usize,
+	n: usize,
+	prng: &mut T,
+	observer: Observer<'_>,
+) -> Result<PreVerified, R1CSError> {
+	use crate::util;
+	use core::iter;
+
+	let n2 = n - n1;
+	let padded_n = n.next_power_of_two();
+	let pad = padded_n - n;
+
+	// Check that the proof sizes match the constraint system
+	// before doing any further work.
+	let lg_n = proof.ipp_proof.L_vec.len();
+	if proof.ipp_proof.R_vec.len() != lg_n || lg_n >= 32 || (1 << lg_n) != padded_n {
+		return Err(R1CSError::FormatError);
+	}
+
+	let scalars = [
+		&proof.t_x,
+		&proof.t_x_blinding,
+		&proof.e_blinding,
+		&proof.ipp_proof.a,
+		&proof.ipp_proof.b,
+	];
+	if !scalars.iter().all(|s| s.is_canonical()) {
+		return Err(R1CSError::FormatError);
+	}
+
+	let T_points = [proof.T_1, proof.T_3, proof.T_4, proof.T_5, proof.T_6];
+	let decompress = |p: &CompressedRistretto| p.decompress().ok_or(R1CSError::FormatError);
+	let mut points = [proof.A_I1, proof.A_O1, proof.S1, proof.A_I2, proof.A_O2, proof.S2]
+		.iter()
+		.map(decompress)
+		.collect::<Result<Vec<_>, _>>()?;
+	points.extend_from_slice(V);
+	for p in T_points
+		.iter()
+		.chain(proof.ipp_proof.L_vec.iter())
+		.chain(proof.ipp_proof.R_vec.iter())
+	{
+		points.push(decompress(p)?);
+	}
+
+	// These points are the identity in the 1-phase unrandomized case.
+	transcript.append_point(b"A_I2", &proof.A_I2);
+	transcript.append_point(b"A_O2", &proof.A_O2);
+	transcript.append_point(b"S2", &proof.S2);
+
+	// Bind the challenges to the size of the circuit.
+	transcript.r1cs_circuit_size(n as u64, constraints.len() as u64);
+
+	let y = transcript.challenge_scalar(b"y");
+	let z = transcript.challenge_scalar(b"z");
+
+	transcript.validate_and_append_point(b"T_1", &proof.T_1)?;
+	transcript.validate_and_append_point(b"T_3", &proof.T_3)?;
+	transcript.validate_and_append_point(b"T_4", &proof.T_4)?;
+	transcript.validate_and_append_point(b"T_5", &proof.T_5)?;
+	transcript.validate_and_append_point(b"T_6", &proof.T_6)?;
+
+	let u = transcript.challenge_scalar(b"u");
+	let x = transcript.challenge_scalar(b"x");
+
+	transcript.append_scalar(b"t_x", &proof.t_x);
+	transcript.append_scalar(b"t_x_blinding", &proof.t_x_blinding);
+	transcript.append_scalar(b"e_blinding", &proof.e_blinding);
+
+	let w = transcript.challenge_scalar(b"w");
+
+	observer.stats(|| Stats {
+		metrics: Metrics::new(n, constraints.len(), V.len()),
+		multiscalar_terms: points.len() + 2 + 2 * padded_n,
+	});
+
+	observer.start(Phase::Flattening);
+	let (wL, wR, wO, wV, wc) = in_span!("r1cs_flatten", constraints = constraints.len(), n = n, {
+		flattened_constraints(constraints, n, V.len(), &z)
+	});
+	observer.end(Phase::Flattening);
+
+	// Get IPP variables
+	let (u_sq, u_inv_sq, s) = proof.ipp_proof.verification_scalars(padded_n, transcript)?;
+
+	let a = proof.ipp_proof.a;
+	let b = proof.ipp_proof.b;
+
+	let y_inv = y.invert();
+	let y_inv_vec = util::exp_iter(y_inv).take(padded_n).collect::<Vec<Scalar>>();
+
+	let u_or_1 = |i: usize| if i < n1 { Scalar::one() } else { u };
+	let u_for_g = iter::repeat(Scalar::one())
+		.take(n1)
+		.chain(iter::repeat(u).take(n2 + pad));
+	let u_for_h = u_for_g.clone();
+
+	// define parameters for P check, starting with the terms which do not
+	// depend on the constraints, then adding the weights of the
+	// multipliers which appear in them
+	let mut g_scalars = u_for_g
+		.zip(s.iter().take(padded_n))
+		.map(|(u_or_1, s_i)| u_or_1 * -(a * s_i))
+		.collect::<Vec<Scalar>>();
+
+	let mut h_scalars = y_inv_vec
+		.iter()
+		.zip(u_for_h)
+		.zip(s.iter().rev().take(padded_n))
+		.map(|((y_inv_i, u_or_1), s_i_inv)| u_or_1 * (y_inv_i * -(b * s_i_inv) - Scalar::one()))
+		.collect::<Vec<Scalar>>();
+
+	let mut delta = Scalar::zero();
+	wR.for_each(|i, wRi| {
+		let yneg_wRi = wRi * y_inv_vec[i];
+		delta += yneg_wRi * wL.get(i);
+		g_scalars[i] += u_or_1(i) * (x * yneg_wRi);
+	});
+	wL.for_each(|i, wLi| h_scalars[i] += u_or_1(i) * (y_inv_vec[i] * (x * wLi)));
+	wO.for_each(|i, wOi| h_scalars[i] += u_or_1(i) * (y_inv_vec[i] * wOi));
+
+	// Create a `TranscriptRng` from the transcript. The verifier
+	// has no witness data to commit, so this just mixes external
+	// randomness into the existing transcript.
+	let mut rng = transcript.build_rng().finalize(prng);
+	let r = Scalar::random(&mut rng);
+
+	let xx = x * x;
+	let rxx = r * xx;
+	let xxx = x * xx;
+
+	// group the T_scalars and T_points together
+	let r_xxx = r * xxx;
+	let r_xxxx = r_xxx * x;
+	let r_xxxxx = r_xxxx * x;
+	let r_xxxxxx = r_xxxxx * x;
+	let T_scalars = [r * x, r_xxx, r_xxxx, r_xxxxx, r_xxxxxx];
+
+	let point_scalars = iter::once(x) // A_I1
+		.chain(iter::once(xx)) // A_O1
+		.chain(iter::once(xxx)) // S1
+		.chain(iter::once(u * x)) // A_I2
+		.chain(iter::once(u * xx)) // A_O2
+		.chain(iter::once(u * xxx)) // S2
+		.chain(wV.iter().map(|wVi| wVi * rxx)) // V
+		.chain(T_scalars.iter().cloned()) // T_points
+		.chain(u_sq.iter().cloned()) // ipp_proof.L_vec
+		.chain(u_inv_sq.iter().cloned()) // ipp_proof.R_vec
+		.collect();
+
+	Ok(PreVerified {
+		padded_n,
+		points,
+		point_scalars,
+		B_scalar: w * (proof.t_x - a * b) + r * (xx * (wc + delta) - proof.t_x),
+		B_blinding_scalar: -proof.e_blinding - r * proof.t_x_blinding,
+		g_scalars,
+		h_scalars,
+	})
+}
+
+/// The flattened weights of the multipliers in one of the vectors
+/// \\(\mathbf{w}\_L, \mathbf{w}\_R, \mathbf{w}\_O\\).
+enum MultiplierWeights {
+	/// The weight of every multiplier, by index.
+	Dense(Vec<Scalar>),
+	/// The weights of the terms of the constraints with a multiplier.
+	/// Once [`MultiplierWeights::merge`] is called, they are sorted by
+	/// index, with one entry per multiplier.
+	Sparse(Vec<(usize, Scalar)>),
+}
+
+impl MultiplierWeights {
+	fn new(n: usize, sparse: bool) -> Self {
+		if sparse {
+			MultiplierWeights::Sparse(Vec::new())
+		} else {
+			MultiplierWeights::Dense(vec![Scalar::zero(); n])
+		}
+	}
+
+	fn add(&mut self, i: usize, w: Scalar) {
+		match self {
+			MultiplierWeights::Dense(weights) => weights[i] += w,
+			MultiplierWeights::Sparse(weights) => weights.push((i, w)),
+		}
+	}
+
+	/// Sorts the sparse weights by index and adds up the weights of the
+	/// same multiplier.
+	fn merge(mut self) -> Self {
+		if let MultiplierWeights::Sparse(weights) = &mut self {
+			weights.sort_by_key(|(i, _)| *i);
+			weights.dedup_by(|(i, w), (j, acc)| {
+				if i == j {
+					*acc += *w;
+				}
+				i == j
+			});
+		}
+		self
+	}
+
+	fn get(&self, i: usize) -> Scalar {
+		match self {
+			MultiplierWeights::Dense(weights) => weights[i],
+			MultiplierWeights::Sparse(weights) => weights
+				.binary_search_by_key(&i, |(j, _)| *j)
+				.map(|k| weights[k].1)
+				.unwrap_or_else(|_| Scalar::zero()),
+		}
+	}
+
+	fn for_each<F: FnMut(usize, &Scalar)>(&self, mut f: F) {
+		match self {
+			MultiplierWeights::Dense(weights) => weights.iter().enumerate().for_each(|(i, w)| f(i, w)),
+			MultiplierWeights::Sparse(weights) => weights.iter().for_each(|(i, w)| f(*i, w)),
+		}
+	}
+}
+
+/// Use a challenge, `z`, to flatten the constraints in the
+/// constraint system into vectors used for proving and
+/// verification.
+///
+/// # Output
+///
+/// Returns a tuple of
+/// ```text
+/// (wL, wR, wO, wV, wc)
+/// ```
+/// where `w{L,R,O}` is \\( z \cdot z^Q \cdot W_{L,R,O} \\).
+///
+/// The weights of the multipliers are only kept for the multipliers which
+/// appear in the constraints if the constraints have few terms compared to
+/// the number of multipliers.
+///
+/// This has the same logic as `ProverCS::flattened_constraints()`
+/// but also computes the constant terms (which the prover skips
+/// because they're not needed to construct the proof).
+fn flattened_constraints(
+	constraints: &[LinearCombination],
+	n: usize,
+	m: usize,
+	z: &Scalar,
+) -> (
+	MultiplierWeights,
+	MultiplierWeights,
+	MultiplierWeights,
+	Vec<Scalar>,
+	Scalar,
+) {
+	// Sorting the terms costs more than filling in dense vectors, unless
+	// there are far fewer terms than multipliers.
+	let num_terms: usize = constraints.iter().map(|lc| lc.terms.len()).sum();
+	let sparse = 4 * num_terms < n;
+
+	let mut wL = MultiplierWeights::new(n, sparse);
+	let mut wR = MultiplierWeights::new(n, sparse);
+	let mut wO = MultiplierWeights::new(n, sparse);
+	let mut wV = vec![Scalar::zero(); m];
+	let mut wc = Scalar::zero();
+
+	let mut exp_z = *z;
+	for lc in constraints.iter() {
+		for (var, coeff) in &lc.terms {
+			match var {
+				Variable::MultiplierLeft(i) => {
+					wL.add(*i, exp_z * coeff);
+				}
+				Variable::MultiplierRight(i) => {
+					wR.add(*i, exp_z * coeff);
+				}
+				Variable::MultiplierOutput(i) => {
+					wO.add(*i, exp_z * coeff);
+				}
+				Variable::Committed(i) => {
+					wV[*i] -= exp_z * coeff;
+				}
+				Variable::One() => {
+					wc -= exp_z * coeff;
+				}
+			}
+		}
+		exp_z *= z;
+	}
+
+	(wL.merge(), wR.merge(), wO.merge(), wV, wc)
+}
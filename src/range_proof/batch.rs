@@ -0,0 +1,220 @@
+#![allow(non_snake_case)]
+//! Streaming batch verification of single-value range proofs.
+//!
+//! Verifying proofs one at a time costs one multiscalar multiplication
+//! per proof, while verifying them all at once needs to hold every proof in
+//! memory.  The [`RangeProofVerificationBatcher`] sits in between: it
+//! adds the random multiples of the verification equations of the proofs
+//! pushed into it, keeping a single scalar for each of the static
+//! generators, and only the points of the proofs of the current chunk.  It
+//! checks the chunk with one multiscalar multiplication whenever the chunk
+//! is full, or when it is flushed.
+
+extern crate alloc;
+
+use alloc::{vec, vec::Vec};
+use curve25519_dalek::{
+	ristretto::{CompressedRistretto, RistrettoPoint},
+	scalar::Scalar,
+	traits::IsIdentity,
+};
+use merlin::Transcript;
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(feature = "std")]
+use rand::{rngs::ThreadRng, thread_rng};
+
+use super::{check_parameters, RangeProof};
+use crate::{
+	errors::ProofError,
+	generators::{BulletproofGens, PedersenGens},
+	msm::{DefaultMsmBackend, MsmBackend},
+	transcript::TranscriptProtocol,
+};
+
+/// Verifies a stream of independent range proofs, each for a single value
+/// commitment to an `n`-bit value, in chunks of up to `capacity` proofs.
+///
+/// The proofs are numbered in the order in which they are pushed, and an
+/// error names the proof, or the chunk of proofs, which failed.
+pub struct RangeProofVerificationBatcher<'g, T: RngCore + CryptoRng> {
+	bp_gens: &'g BulletproofGens,
+	pc_gens: &'g PedersenGens,
+	transcript: Transcript,
+	n: usize,
+	capacity: usize,
+	rng: T,
+	/// The index of the first proof of the current chunk.
+	start: usize,
+	/// The index of the next proof to be pushed.
+	next: usize,
+	/// The number of proofs in the current chunk.
+	len: usize,
+	/// Points from the proofs of the current chunk and their commitments.
+	points: Vec<RistrettoPoint>,
+	/// Scalars for `points`.
+	point_scalars: Vec<Scalar>,
+	/// Scalar for the Pedersen base `B`.
+	B_scalar: Scalar,
+	/// Scalar for the Pedersen base `B_blinding`.
+	B_blinding_scalar: Scalar,
+	/// Scalars for the \\(\mathbf G\\) generators.
+	g_scalars: Vec<Scalar>,
+	/// Scalars for the \\(\mathbf H\\) generators.
+	h_scalars: Vec<Scalar>,
+}
+
+#[cfg(feature = "std")]
+impl<'g> RangeProofVerificationBatcher<'g, ThreadRng> {
+	/// Creates a batcher verifying `n`-bit range proofs against copies of
+	/// `transcript`, in chunks of up to `capacity` proofs.
+	///
+	/// This is a convenience wrapper around
+	/// [`RangeProofVerificationBatcher::new_with_rng`], passing in a
+	/// threadsafe RNG.
+	pub fn new(
+		bp_gens: &'g BulletproofGens,
+		pc_gens: &'g PedersenGens,
+		transcript: &Transcript,
+		n: usize,
+		capacity: usize,
+	) -> Result<Self, ProofError> {
+		RangeProofVerificationBatcher::new_with_rng(bp_gens, pc_gens, transcript, n, capacity, thread_rng())
+	}
+}
+
+impl<'g, T: RngCore + CryptoRng> RangeProofVerificationBatcher<'g, T> {
+	/// Creates a batcher verifying `n`-bit range proofs against copies of
+	/// `transcript`, in chunks of up to `capacity` proofs, weighting their
+	/// verification equations with scalars drawn from `rng`.
+	///
+	/// The `transcript` must be in the same state as the transcripts of the
+	/// provers.  A `capacity` of zero is treated as a capacity of one.
+	pub fn new_with_rng(
+		bp_gens: &'g BulletproofGens,
+		pc_gens: &'g PedersenGens,
+		transcript: &Transcript,
+		n: usize,
+		capacity: usize,
+		rng: T,
+	) -> Result<Self, ProofError> {
+		check_parameters(bp_gens, n, 1)?;
+		let padded_n = n.next_power_of_two();
+		let capacity = capacity.max(1);
+		let lg_n = padded_n.trailing_zeros() as usize;
+
+		Ok(RangeProofVerificationBatcher {
+			bp_gens,
+			pc_gens,
+			transcript: transcript.clone(),
+			n,
+			capacity,
+			rng,
+			start: 0,
+			next: 0,
+			len: 0,
+			points: Vec::with_capacity(capacity * (5 + 2 * lg_n)),
+			point_scalars: Vec::with_capacity(capacity * (5 + 2 * lg_n)),
+			B_scalar: Scalar::zero(),
+			B_blinding_scalar: Scalar::zero(),
+			g_scalars: vec![Scalar::zero(); padded_n],
+			h_scalars: vec![Scalar::zero(); padded_n],
+		})
+	}
+
+	/// Adds a `proof` for the value commitment `V` to the current chunk, and
+	/// checks the chunk if it is full.
+	///
+	/// Returns [`ProofError::BatchVerificationError`] if the proof is
+	/// malformed, in which case it is skipped, and
+	/// [`ProofError::BatchChunkVerificationError`] if the chunk is checked
+	/// and does not verify.
+	pub fn push(&mut self, proof: &RangeProof, V: &CompressedRistretto) -> Result<(), ProofError> {
+		let index = self.next;
+		self.next += 1;
+		let padded_n = self.n.next_power_of_two();
+		let lg_n = padded_n.trailing_zeros() as usize;
+		if proof.ipp_proof.L_vec.len() != lg_n || proof.ipp_proof.R_vec.len() != lg_n {
+			return Err(ProofError::BatchVerificationError { index });
+		}
+
+		let mut transcript = self.transcript.clone();
+		transcript.rangeproof_domain_sep(self.n as u64, 1);
+		let equation = proof
+			.verification_equation(
+				&mut transcript,
+				&[*V],
+				&[(self.n, padded_n)],
+				(padded_n, 1),
+				&mut self.rng,
+			)
+			.map_err(|_| ProofError::BatchVerificationError { index })?;
+
+		let c = Scalar::random(&mut self.rng);
+		self.point_scalars.extend(equation.point_scalars.iter().map(|s| c * s));
+		self.points.extend_from_slice(&equation.points);
+		self.B_scalar += c * equation.B_scalar;
+		self.B_blinding_scalar += c * equation.B_blinding_scalar;
+		for (acc, s) in self.g_scalars.iter_mut().zip(equation.g_scalars.iter()) {
+			*acc += c * s;
+		}
+		for (acc, s) in self.h_scalars.iter_mut().zip(equation.h_scalars.iter()) {
+			*acc += c * s;
+		}
+		self.len += 1;
+
+		if self.len == self.capacity {
+			self.flush()
+		} else {
+			Ok(())
+		}
+	}
+
+	/// Checks the proofs of the current chunk with a single multiscalar
+	/// multiplication, and starts a new chunk.
+	///
+	/// Returns [`ProofError::BatchChunkVerificationError`] with the
+	/// indexes of the proofs of the chunk if it does not verify.
+	pub fn flush(&mut self) -> Result<(), ProofError> {
+		if self.len == 0 {
+			self.start = self.next;
+			return Ok(());
+		}
+		let (start, end) = (self.start, self.next);
+		let padded_n = self.n.next_power_of_two();
+
+		let mut scalars = core::mem::take(&mut self.point_scalars);
+		scalars.push(self.B_scalar);
+		scalars.push(self.B_blinding_scalar);
+		scalars.extend_from_slice(&self.g_scalars);
+		scalars.extend_from_slice(&self.h_scalars);
+
+		let mut points = core::mem::take(&mut self.points);
+		points.push(self.pc_gens.B);
+		points.push(self.pc_gens.B_blinding);
+		points.extend(self.bp_gens.G(padded_n, 1));
+		points.extend(self.bp_gens.H(padded_n, 1));
+
+		let mega_check = in_span!("range_proof_batch_multiexp", size = points.len(), {
+			DefaultMsmBackend.vartime_msm(&scalars, &points)
+		});
+
+		// Reuse the allocations for the next chunk.
+		scalars.clear();
+		points.clear();
+		self.point_scalars = scalars;
+		self.points = points;
+		self.B_scalar = Scalar::zero();
+		self.B_blinding_scalar = Scalar::zero();
+		self.g_scalars.iter_mut().for_each(|s| *s = Scalar::zero());
+		self.h_scalars.iter_mut().for_each(|s| *s = Scalar::zero());
+		self.start = end;
+		self.len = 0;
+
+		if mega_check.is_identity() {
+			Ok(())
+		} else {
+			Err(ProofError::BatchChunkVerificationError { start, end })
+		}
+	}
+}
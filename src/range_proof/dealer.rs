@@ -20,6 +20,7 @@ use crate::{
 	errors::MPCError,
 	generators::{BulletproofGens, PedersenGens},
 	inner_product_proof,
+	progress::Progress,
 	range_proof::RangeProof,
 	transcript::TranscriptProtocol,
 };
@@ -38,6 +39,10 @@ pub struct Dealer {}
 
 impl Dealer {
 	/// Creates a new dealer coordinating `m` parties proving `n`-bit ranges.
+	///
+	/// If `m` is not a power of two, the dealer pads the aggregation with
+	/// parties proving that the identity commits to zero, which the
+	/// verifier adds to the value commitments in the same way.
 	pub fn new<'a, 'b>(
 		bp_gens: &'b BulletproofGens,
 		pc_gens: &'b PedersenGens,
@@ -45,17 +50,24 @@ impl Dealer {
 		n: usize,
 		m: usize,
 	) -> Result<DealerAwaitingBitCommitments<'a, 'b>, MPCError> {
-		if !(n == 8 || n == 16 || n == 32 || n == 64) {
+		if !(1..=64).contains(&n) {
 			return Err(MPCError::InvalidBitsize);
 		}
-		if !m.is_power_of_two() {
+		if m == 0 {
 			return Err(MPCError::InvalidAggregation);
 		}
-		if bp_gens.gens_capacity < n {
-			return Err(MPCError::InvalidGeneratorsLength);
+		let required = BulletproofGens::required_capacity_for_range(n, m);
+		if bp_gens.gens_capacity < required.gens_capacity {
+			return Err(MPCError::InvalidGeneratorsLength {
+				required: required.gens_capacity,
+				actual: bp_gens.gens_capacity,
+			});
 		}
-		if bp_gens.party_capacity < m {
-			return Err(MPCError::InvalidGeneratorsLength);
+		if bp_gens.party_capacity < required.party_capacity {
+			return Err(MPCError::InvalidGeneratorsLength {
+				required: required.party_capacity,
+				actual: bp_gens.party_capacity,
+			});
 		}
 
 		// At the end of the protocol, the dealer will attempt to
@@ -100,14 +112,28 @@ pub struct DealerAwaitingBitCommitments<'a, 'b> {
 impl<'a, 'b> DealerAwaitingBitCommitments<'a, 'b> {
 	/// Receive each party's [`BitCommitment`]s and compute the
 	/// [`BitChallenge`].
+	///
+	/// Returns [`MPCError::MalformedBitCommitments`] with the indexes of
+	/// the parties whose commitments are malformed, if any.
 	pub fn receive_bit_commitments(
 		self,
-		bit_commitments: Vec<BitCommitment>,
+		mut bit_commitments: Vec<BitCommitment>,
 	) -> Result<(DealerAwaitingPolyCommitments<'a, 'b>, BitChallenge), MPCError> {
 		if self.m != bit_commitments.len() {
 			return Err(MPCError::WrongNumBitCommitments);
 		}
 
+		let bad_commitments: Vec<usize> = (0..self.m)
+			.filter(|&j| bit_commitments[j].check_well_formed().is_err())
+			.collect();
+		if !bad_commitments.is_empty() {
+			return Err(MPCError::MalformedBitCommitments { bad_commitments });
+		}
+
+		for j in self.m..self.m.next_power_of_two() {
+			bit_commitments.push(BitCommitment::padding(self.bp_gens, self.n, j));
+		}
+
 		// Commit each V_j individually
 		for vc in bit_commitments.iter() {
 			self.transcript.append_point(b"V", &vc.V_j);
@@ -162,14 +188,26 @@ pub struct DealerAwaitingPolyCommitments<'a, 'b> {
 impl<'a, 'b> DealerAwaitingPolyCommitments<'a, 'b> {
 	/// Receive [`PolyCommitment`]s from the parties and compute the
 	/// [`PolyChallenge`].
+	///
+	/// Returns [`MPCError::MalformedPolyCommitments`] with the indexes of
+	/// the parties whose commitments are malformed, if any.
 	pub fn receive_poly_commitments(
 		self,
-		poly_commitments: Vec<PolyCommitment>,
+		mut poly_commitments: Vec<PolyCommitment>,
 	) -> Result<(DealerAwaitingProofShares<'a, 'b>, PolyChallenge), MPCError> {
 		if self.m != poly_commitments.len() {
 			return Err(MPCError::WrongNumPolyCommitments);
 		}
 
+		let bad_commitments: Vec<usize> = (0..self.m)
+			.filter(|&j| poly_commitments[j].check_well_formed().is_err())
+			.collect();
+		if !bad_commitments.is_empty() {
+			return Err(MPCError::MalformedPolyCommitments { bad_commitments });
+		}
+
+		poly_commitments.resize(self.m.next_power_of_two(), PolyCommitment::padding());
+
 		// Commit sums of T_1_j's and T_2_j's
 		let T_1: RistrettoPoint = poly_commitments.iter().map(|pc| pc.T_1_j).sum();
 		let T_2: RistrettoPoint = poly_commitments.iter().map(|pc| pc.T_2_j).sum();
@@ -228,15 +266,30 @@ impl<'a, 'b> DealerAwaitingProofShares<'a, 'b> {
 	/// Used as a helper function by `receive_trusted_shares` (which
 	/// just hands back the result) and `receive_shares` (which
 	/// validates the proof shares.
-	fn assemble_shares(&mut self, proof_shares: &[ProofShare]) -> Result<RangeProof, MPCError> {
+	///
+	/// Returns `None` if cancellation was requested through `progress`.
+	fn assemble_shares(
+		&mut self,
+		proof_shares: &[ProofShare],
+		progress: Progress<'_>,
+	) -> Result<Option<RangeProof>, MPCError> {
 		if self.m != proof_shares.len() {
 			return Err(MPCError::WrongNumProofShares);
 		}
 
+		// The bits of each value are padded up to a power of two, and the
+		// parties are padded up to a power of two.
+		let n = self.n.next_power_of_two();
+		let m = self.m.next_power_of_two();
+		let padding_shares: Vec<ProofShare> = (self.m..m)
+			.map(|j| ProofShare::padding(self.n, j, &self.bit_challenge))
+			.collect();
+		let proof_shares: Vec<&ProofShare> = proof_shares.iter().chain(padding_shares.iter()).collect();
+
 		// Validate lengths for each share
 		let mut bad_shares = Vec::<usize>::new(); // no allocations until we append
 		for (j, share) in proof_shares.iter().enumerate() {
-			share.check_size(self.n, &self.bp_gens, j).unwrap_or_else(|_| {
+			share.check_size(n, &self.bp_gens, j).unwrap_or_else(|_| {
 				bad_shares.push(j);
 			});
 		}
@@ -257,10 +310,8 @@ impl<'a, 'b> DealerAwaitingProofShares<'a, 'b> {
 		let w = self.transcript.challenge_scalar(b"w");
 		let Q = w * self.pc_gens.B;
 
-		let G_factors: Vec<Scalar> = iter::repeat(Scalar::one()).take(self.n * self.m).collect();
-		let H_factors: Vec<Scalar> = util::exp_iter(self.bit_challenge.y.invert())
-			.take(self.n * self.m)
-			.collect();
+		let G_factors: Vec<Scalar> = iter::repeat(Scalar::one()).take(n * m).collect();
+		let H_factors: Vec<Scalar> = util::exp_iter(self.bit_challenge.y.invert()).take(n * m).collect();
 
 		let l_vec: Vec<Scalar> = proof_shares
 			.iter()
@@ -271,18 +322,22 @@ impl<'a, 'b> DealerAwaitingProofShares<'a, 'b> {
 			.flat_map(|ps| ps.r_vec.clone().into_iter())
 			.collect();
 
-		let ipp_proof = inner_product_proof::InnerProductProof::create(
+		let ipp_proof = match inner_product_proof::InnerProductProof::create_with_progress(
 			self.transcript,
 			&Q,
 			&G_factors,
 			&H_factors,
-			self.bp_gens.G(self.n, self.m).cloned().collect(),
-			self.bp_gens.H(self.n, self.m).cloned().collect(),
+			self.bp_gens.G(n, m).cloned().collect(),
+			self.bp_gens.H(n, m).cloned().collect(),
 			l_vec,
 			r_vec,
-		);
+			progress,
+		) {
+			Some(ipp_proof) => ipp_proof,
+			None => return Ok(None),
+		};
 
-		Ok(RangeProof {
+		Ok(Some(RangeProof {
 			A: self.A.compress(),
 			S: self.S.compress(),
 			T_1: self.T_1.compress(),
@@ -291,7 +346,9 @@ impl<'a, 'b> DealerAwaitingProofShares<'a, 'b> {
 			t_x_blinding,
 			e_blinding,
 			ipp_proof,
-		})
+			n: self.n,
+			m: self.m,
+		}))
 	}
 
 	/// Assemble the final aggregated [`RangeProof`] from the given
@@ -323,9 +380,15 @@ impl<'a, 'b> DealerAwaitingProofShares<'a, 'b> {
 		proof_shares: &[ProofShare],
 		rng: &mut T,
 	) -> Result<RangeProof, MPCError> {
-		let proof = self.assemble_shares(proof_shares)?;
+		let proof = match self.assemble_shares(proof_shares, Progress::default()) {
+			Ok(proof) => proof.expect("proof creation without a cancellation token is never cancelled"),
+			// Some shares have the wrong size, but the shares of the right
+			// size may be malformed as well.
+			Err(MPCError::MalformedProofShares { .. }) => return Err(self.audit_shares(proof_shares)),
+			Err(e) => return Err(e),
+		};
 
-		let Vs: Vec<_> = self.bit_commitments.iter().map(|vc| vc.V_j).collect();
+		let Vs: Vec<_> = self.bit_commitments[..self.m].iter().map(|vc| vc.V_j).collect();
 
 		// See comment in `Dealer::new` for why we use `initial_transcript`
 		let transcript = &mut self.initial_transcript;
@@ -336,23 +399,30 @@ impl<'a, 'b> DealerAwaitingProofShares<'a, 'b> {
 			Ok(proof)
 		} else {
 			// Proof verification failed. Now audit the parties:
-			let mut bad_shares = Vec::new();
-			for j in 0..self.m {
-				match proof_shares[j].audit_share(
-					&self.bp_gens,
-					&self.pc_gens,
-					j,
-					&self.bit_commitments[j],
-					&self.bit_challenge,
-					&self.poly_commitments[j],
-					&self.poly_challenge,
-				) {
-					Ok(_) => {}
-					Err(_) => bad_shares.push(j),
-				}
+			Err(self.audit_shares(proof_shares))
+		}
+	}
+
+	/// Audits each of the `proof_shares` to determine which parties
+	/// submitted malformed shares.
+	fn audit_shares(&self, proof_shares: &[ProofShare]) -> MPCError {
+		let mut bad_shares = Vec::new();
+		for j in 0..self.m {
+			match proof_shares[j].audit_share(
+				&self.bp_gens,
+				&self.pc_gens,
+				self.n,
+				j,
+				&self.bit_commitments[j],
+				&self.bit_challenge,
+				&self.poly_commitments[j],
+				&self.poly_challenge,
+			) {
+				Ok(_) => {}
+				Err(_) => bad_shares.push(j),
 			}
-			Err(MPCError::MalformedProofShares { bad_shares })
 		}
+		MPCError::MalformedProofShares { bad_shares }
 	}
 
 	/// Assemble the final aggregated [`RangeProof`] from the given
@@ -369,7 +439,22 @@ impl<'a, 'b> DealerAwaitingProofShares<'a, 'b> {
 	/// [`receive_shares`](DealerAwaitingProofShares::receive_shares),
 	/// which validates that all shares are well-formed, or else
 	/// detects which party(ies) submitted malformed shares.
-	pub fn receive_trusted_shares(mut self, proof_shares: &[ProofShare]) -> Result<RangeProof, MPCError> {
-		self.assemble_shares(proof_shares)
+	pub fn receive_trusted_shares(self, proof_shares: &[ProofShare]) -> Result<RangeProof, MPCError> {
+		Ok(self
+			.receive_trusted_shares_with_progress(proof_shares, Progress::default())?
+			.expect("proof creation without a cancellation token is never cancelled"))
+	}
+
+	/// Assemble the final aggregated [`RangeProof`] like
+	/// [`receive_trusted_shares`](DealerAwaitingProofShares::receive_trusted_shares),
+	/// reporting the inner-product rounds to `progress`.
+	///
+	/// Returns `None` if cancellation was requested.
+	pub(crate) fn receive_trusted_shares_with_progress(
+		mut self,
+		proof_shares: &[ProofShare],
+		progress: Progress<'_>,
+	) -> Result<Option<RangeProof>, MPCError> {
+		self.assemble_shares(proof_shares, progress)
 	}
 }
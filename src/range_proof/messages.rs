@@ -6,6 +6,11 @@
 //! multiparty computation
 //! protocol](../aggregation/index.html#
 //! api-for-the-aggregated-multiparty-computation-protocol).
+//!
+//! With the `serde` feature, the messages implement `Serialize` and
+//! `Deserialize`, so that the parties and the dealer can run in separate
+//! processes.  Deserialization rejects points which do not decompress and
+//! scalars which are not canonically encoded.
 
 extern crate alloc;
 
@@ -14,19 +19,72 @@ use core::iter;
 use curve25519_dalek::{
 	ristretto::{CompressedRistretto, RistrettoPoint},
 	scalar::Scalar,
+	traits::Identity,
 };
 
 use crate::generators::{BulletproofGens, PedersenGens};
 
+// The dealer pads an aggregation to a power of two with parties whose
+// value, blinding factor and nonces are all zero.  Their messages are
+// public, so the dealer computes them itself, and their value commitments
+// are the identity.
+
+impl BitCommitment {
+	/// The bit commitment of a padding party at position `j` for `n`-bit
+	/// values.
+	pub(super) fn padding(bp_gens: &BulletproofGens, n: usize, j: usize) -> BitCommitment {
+		// All bits are zero, so a_L = 0 and a_R = -1.
+		let H_sum: RistrettoPoint = bp_gens.share(j).H(n.next_power_of_two()).sum();
+		BitCommitment {
+			V_j: CompressedRistretto::identity(),
+			A_j: -H_sum,
+			S_j: RistrettoPoint::identity(),
+		}
+	}
+}
+
+impl PolyCommitment {
+	/// The polynomial commitment of a padding party, whose polynomial
+	/// \\(t(x)\\) is constant.
+	pub(super) fn padding() -> PolyCommitment {
+		PolyCommitment {
+			T_1_j: RistrettoPoint::identity(),
+			T_2_j: RistrettoPoint::identity(),
+		}
+	}
+}
+
 /// A commitment to the bits of a party's value.
+#[cfg_attr(feature = "serde", derive(serde_derive::Serialize, serde_derive::Deserialize))]
 #[derive(Copy, Clone, Debug)]
 pub struct BitCommitment {
+	#[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_valid_point"))]
 	pub(super) V_j: CompressedRistretto,
 	pub(super) A_j: RistrettoPoint,
 	pub(super) S_j: RistrettoPoint,
 }
 
+impl BitCommitment {
+	/// Checks that the value commitment is a valid point, and that the
+	/// commitments to the bits and to their blindings are not the
+	/// identity, which an honest party only sends with negligible
+	/// probability.
+	///
+	/// The value commitment may be the identity, which commits to zero with
+	/// a zero blinding factor.
+	pub(super) fn check_well_formed(&self) -> Result<(), ()> {
+		use curve25519_dalek::traits::IsIdentity;
+
+		self.V_j.decompress().ok_or(())?;
+		if self.A_j.is_identity() || self.S_j.is_identity() {
+			return Err(());
+		}
+		Ok(())
+	}
+}
+
 /// Challenge values derived from all parties' [`BitCommitment`]s.
+#[cfg_attr(feature = "serde", derive(serde_derive::Serialize, serde_derive::Deserialize))]
 #[derive(Copy, Clone, Debug)]
 pub struct BitChallenge {
 	pub(super) y: Scalar,
@@ -34,13 +92,43 @@ pub struct BitChallenge {
 }
 
 /// A commitment to a party's polynomial coefficents.
+#[cfg_attr(feature = "serde", derive(serde_derive::Serialize, serde_derive::Deserialize))]
 #[derive(Copy, Clone, Debug)]
 pub struct PolyCommitment {
 	pub(super) T_1_j: RistrettoPoint,
 	pub(super) T_2_j: RistrettoPoint,
 }
 
+/// Deserializes a compressed point, rejecting it unless it decompresses.
+#[cfg(feature = "serde")]
+fn deserialize_valid_point<'de, D>(deserializer: D) -> Result<CompressedRistretto, D::Error>
+where
+	D: serde::Deserializer<'de>,
+{
+	use serde::Deserialize;
+
+	let point = CompressedRistretto::deserialize(deserializer)?;
+	point
+		.decompress()
+		.ok_or_else(|| serde::de::Error::custom("point is not a valid Ristretto point"))?;
+	Ok(point)
+}
+
+impl PolyCommitment {
+	/// Checks that the commitments to the coefficients of \\(t(x)\\) are
+	/// not the identity, which they are not for a party which blinds them.
+	pub(super) fn check_well_formed(&self) -> Result<(), ()> {
+		use curve25519_dalek::traits::IsIdentity;
+
+		if self.T_1_j.is_identity() || self.T_2_j.is_identity() {
+			return Err(());
+		}
+		Ok(())
+	}
+}
+
 /// Challenge values derived from all parties' [`PolyCommitment`]s.
+#[cfg_attr(feature = "serde", derive(serde_derive::Serialize, serde_derive::Deserialize))]
 #[derive(Copy, Clone, Debug)]
 pub struct PolyChallenge {
 	pub(super) x: Scalar,
@@ -48,6 +136,7 @@ pub struct PolyChallenge {
 
 /// A party's proof share, ready for aggregation into the final
 /// [`RangeProof`](::RangeProof).
+#[cfg_attr(feature = "serde", derive(serde_derive::Serialize, serde_derive::Deserialize))]
 #[derive(Clone, Debug)]
 pub struct ProofShare {
 	pub(super) t_x: Scalar,
@@ -58,6 +147,37 @@ pub struct ProofShare {
 }
 
 impl ProofShare {
+	/// The proof share of a padding party at position `j` for `n`-bit
+	/// values.
+	pub(super) fn padding(n: usize, j: usize, bit_challenge: &BitChallenge) -> ProofShare {
+		use crate::{inner_product_proof::inner_product, util};
+
+		let (y, z) = (&bit_challenge.y, &bit_challenge.z);
+		let padded_n = n.next_power_of_two();
+		let offset_y = util::scalar_exp_vartime(y, (j * padded_n) as u64);
+		let offset_zz = z * z * util::scalar_exp_vartime(z, j as u64);
+
+		let l_vec: Vec<Scalar> = iter::repeat(-z).take(padded_n).collect();
+		let r_vec: Vec<Scalar> = util::exp_iter(*y)
+			.map(|exp_y| offset_y * exp_y)
+			.zip(
+				util::exp_iter(Scalar::from(2u64))
+					.take(n)
+					.chain(iter::repeat(Scalar::zero())),
+			)
+			.take(padded_n)
+			.map(|(exp_y, exp_2)| exp_y * (z - Scalar::one()) + offset_zz * exp_2)
+			.collect();
+
+		ProofShare {
+			t_x: inner_product(&l_vec, &r_vec),
+			t_x_blinding: Scalar::zero(),
+			e_blinding: Scalar::zero(),
+			l_vec,
+			r_vec,
+		}
+	}
+
 	/// Checks consistency of all sizes in the proof share and returns the size
 	/// of the l/r vector.
 	pub(super) fn check_size(&self, expected_n: usize, bp_gens: &BulletproofGens, j: usize) -> Result<(), ()> {
@@ -80,12 +200,13 @@ impl ProofShare {
 		Ok(())
 	}
 
-	/// Audit an individual proof share to determine whether it is
-	/// malformed.
+	/// Audit an individual proof share of a value of `n_bits` bits to
+	/// determine whether it is malformed.
 	pub(super) fn audit_share(
 		&self,
 		bp_gens: &BulletproofGens,
 		pc_gens: &PedersenGens,
+		n_bits: usize,
 		j: usize,
 		bit_commitment: &BitCommitment,
 		bit_challenge: &BitChallenge,
@@ -96,7 +217,7 @@ impl ProofShare {
 
 		use crate::{inner_product_proof::inner_product, util};
 
-		let n = self.l_vec.len();
+		let n = n_bits.next_power_of_two();
 
 		self.check_size(n, bp_gens, j)?;
 
@@ -119,7 +240,11 @@ impl ProofShare {
 		let h = self
 			.r_vec
 			.iter()
-			.zip(util::exp_iter(Scalar::from(2u64)))
+			.zip(
+				util::exp_iter(Scalar::from(2u64))
+					.take(n_bits)
+					.chain(iter::repeat(Scalar::zero())),
+			)
 			.zip(util::exp_iter(y_inv))
 			.map(|((r_i, exp_2), exp_y_inv)| {
 				z + exp_y_inv * y_jn_inv * (-r_i) + exp_y_inv * y_jn_inv * (zz * z_j * exp_2)
@@ -144,7 +269,7 @@ impl ProofShare {
 		let V_j = bit_commitment.V_j.decompress().ok_or(())?;
 
 		let sum_of_powers_y = util::sum_of_powers(&y, n);
-		let sum_of_powers_2 = util::sum_of_powers(&Scalar::from(2u64), n);
+		let sum_of_powers_2 = util::sum_of_powers(&Scalar::from(2u64), n_bits);
 		let delta = (z - zz) * sum_of_powers_y * y_jn - z * zz * sum_of_powers_2 * z_j;
 		let t_check = RistrettoPoint::vartime_multiscalar_mul(
 			iter::once(zz * z_j)
@@ -0,0 +1,357 @@
+#![allow(non_snake_case)]
+//! Range proofs aggregating values of different bit sizes.
+//!
+//! The bits of the values are laid out one after the other in the
+//! vectors of a single-party proof, and padded to a power of two with
+//! zero bits which do not contribute to any value.  The proof uses the
+//! first generators of the first party share, like a range proof of a
+//! single value of the padded size.
+//!
+//! The single-party prover is shared with the rewindable range proofs,
+//! which derive its nonces from a rewind key instead of drawing them at
+//! random.
+
+use alloc::vec::Vec;
+use clear_on_drop::clear::Clear;
+use core::iter;
+use curve25519_dalek::{
+	ristretto::{CompressedRistretto, RistrettoPoint},
+	scalar::Scalar,
+	traits::MultiscalarMul,
+};
+use merlin::Transcript;
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(feature = "std")]
+use rand::thread_rng;
+
+use super::{concat_z_and_2, witness_rng, RangeProof};
+use crate::{
+	errors::ProofError,
+	generators::{BulletproofGens, PedersenGens},
+	inner_product_proof::InnerProductProof,
+	msm::DefaultMsmBackend,
+	transcript::TranscriptProtocol,
+	util,
+};
+
+impl RangeProof {
+	/// Create a rangeproof for a set of values of different bit sizes.
+	///
+	/// This is a convenience wrapper around
+	/// [`RangeProof::prove_multiple_mixed_with_rng`], passing in a
+	/// threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn prove_multiple_mixed(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		values: &[(u64, Scalar, usize)],
+	) -> Result<(RangeProof, Vec<CompressedRistretto>), ProofError> {
+		RangeProof::prove_multiple_mixed_with_rng(bp_gens, pc_gens, transcript, values, &mut thread_rng())
+	}
+
+	/// Create a rangeproof for a set of `(value, blinding, n)` triples,
+	/// proving that each value is in the range \\([0, 2^n)\\) for its own
+	/// bitsize \\(n\\), from \\(1\\) to \\(64\\).
+	///
+	/// The [`BulletproofGens`] must have a `gens_capacity` of at least the
+	/// total number of bits, rounded up to a power of two.  A value which
+	/// does not fit in its bitsize gives a proof which does not verify.
+	///
+	/// Returns the proof and the commitments to the values, which are
+	/// verified with [`RangeProof::verify_multiple_mixed_with_rng`] and
+	/// the same list of bitsizes.
+	pub fn prove_multiple_mixed_with_rng<T: RngCore + CryptoRng>(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		values: &[(u64, Scalar, usize)],
+		rng: &mut T,
+	) -> Result<(RangeProof, Vec<CompressedRistretto>), ProofError> {
+		let bit_sizes: Vec<usize> = values.iter().map(|&(_, _, n)| n).collect();
+		let padded_n = check_mixed_parameters(bp_gens, &bit_sizes)?;
+
+		enter_span!("range_proof_prove_mixed", n = padded_n, m = values.len());
+
+		transcript.rangeproof_mixed_domain_sep(&bit_sizes);
+
+		let value_commitments: Vec<CompressedRistretto> = values
+			.iter()
+			.map(|&(v, v_blinding, _)| pc_gens.commit(v.into(), v_blinding).compress())
+			.collect();
+		for V in value_commitments.iter() {
+			transcript.append_point(b"V", V);
+		}
+
+		let openings: Vec<(u64, Scalar)> = values.iter().map(|&(v, v_blinding, _)| (v, v_blinding)).collect();
+		let ranges: Vec<(usize, usize)> = bit_sizes.iter().map(|&n| (n, n)).collect();
+		let mut rng = witness_rng(transcript, openings.iter().copied(), rng);
+		let nonces = ProverNonces::random(padded_n, &mut rng);
+		let proof = prove_ranges(bp_gens, pc_gens, transcript, &openings, &ranges, padded_n, nonces);
+
+		Ok((proof, value_commitments))
+	}
+
+	/// Verifies a rangeproof for values of different bit sizes.
+	///
+	/// This is a convenience wrapper around
+	/// [`RangeProof::verify_multiple_mixed_with_rng`], passing in a
+	/// threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn verify_multiple_mixed(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		value_commitments: &[CompressedRistretto],
+		bit_sizes: &[usize],
+	) -> Result<(), ProofError> {
+		self.verify_multiple_mixed_with_rng(
+			bp_gens,
+			pc_gens,
+			transcript,
+			value_commitments,
+			bit_sizes,
+			&mut thread_rng(),
+		)
+	}
+
+	/// Verifies a rangeproof created with
+	/// [`RangeProof::prove_multiple_mixed_with_rng`] for the given value
+	/// commitments, with the bitsize of each value in `bit_sizes`.
+	pub fn verify_multiple_mixed_with_rng<T: RngCore + CryptoRng>(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		value_commitments: &[CompressedRistretto],
+		bit_sizes: &[usize],
+		rng: &mut T,
+	) -> Result<(), ProofError> {
+		let padded_n = check_mixed_parameters(bp_gens, bit_sizes)?;
+		if value_commitments.len() != bit_sizes.len() {
+			return Err(ProofError::VerificationError);
+		}
+
+		enter_span!("range_proof_verify_mixed", n = padded_n, m = bit_sizes.len());
+
+		transcript.rangeproof_mixed_domain_sep(bit_sizes);
+		let ranges: Vec<(usize, usize)> = bit_sizes.iter().map(|&n| (n, n)).collect();
+		self.verification_equation(transcript, value_commitments, &ranges, (padded_n, 1), rng)?
+			.check(bp_gens, pc_gens, &DefaultMsmBackend)
+	}
+}
+
+/// Checks that a proof for values of `bit_sizes` can be created with the
+/// generators, and returns the padded number of bits.
+fn check_mixed_parameters(bp_gens: &BulletproofGens, bit_sizes: &[usize]) -> Result<usize, ProofError> {
+	if bit_sizes.is_empty() {
+		return Err(ProofError::InvalidAggregation);
+	}
+	if bit_sizes.iter().any(|&n| !(1..=64).contains(&n)) {
+		return Err(ProofError::InvalidBitsize);
+	}
+	let padded_n = bit_sizes.iter().sum::<usize>().next_power_of_two();
+	if bp_gens.gens_capacity < padded_n {
+		return Err(ProofError::InvalidGeneratorsLength {
+			required: padded_n,
+			actual: bp_gens.gens_capacity,
+		});
+	}
+	Ok(padded_n)
+}
+
+/// The secret scalars chosen by a single-party prover.
+pub(super) struct ProverNonces {
+	pub(super) a_blinding: Scalar,
+	pub(super) s_blinding: Scalar,
+	pub(super) t_1_blinding: Scalar,
+	pub(super) t_2_blinding: Scalar,
+	pub(super) s_L: Vec<Scalar>,
+	pub(super) s_R: Vec<Scalar>,
+}
+
+impl ProverNonces {
+	/// Draws the nonces of a proof of `padded_n` bits from `rng`.
+	fn random<T: RngCore + CryptoRng>(padded_n: usize, rng: &mut T) -> ProverNonces {
+		ProverNonces {
+			a_blinding: Scalar::random(rng),
+			s_blinding: Scalar::random(rng),
+			t_1_blinding: Scalar::random(rng),
+			t_2_blinding: Scalar::random(rng),
+			s_L: (0..padded_n).map(|_| Scalar::random(rng)).collect(),
+			s_R: (0..padded_n).map(|_| Scalar::random(rng)).collect(),
+		}
+	}
+
+	/// Derives the nonces of a proof of `padded_n` bits from challenges of
+	/// `transcript`, in the order of the fields.
+	pub(super) fn derive(transcript: &mut Transcript, padded_n: usize) -> ProverNonces {
+		ProverNonces {
+			a_blinding: transcript.challenge_scalar(b"a_blinding"),
+			s_blinding: transcript.challenge_scalar(b"s_blinding"),
+			t_1_blinding: transcript.challenge_scalar(b"t_1_blinding"),
+			t_2_blinding: transcript.challenge_scalar(b"t_2_blinding"),
+			s_L: (0..padded_n).map(|_| transcript.challenge_scalar(b"s_L")).collect(),
+			s_R: (0..padded_n).map(|_| transcript.challenge_scalar(b"s_R")).collect(),
+		}
+	}
+}
+
+/// Overwrite secrets with null bytes when they go out of scope.
+impl Drop for ProverNonces {
+	fn drop(&mut self) {
+		self.a_blinding.clear();
+		self.s_blinding.clear();
+		self.t_1_blinding.clear();
+		self.t_2_blinding.clear();
+		util::clear_scalars(&mut [&mut self.s_L, &mut self.s_R]);
+	}
+}
+
+/// Creates a single-party proof for the `openings` of the value
+/// commitments, which the caller has appended to the `transcript` after the
+/// domain separator.
+///
+/// Each of the `ranges` gives the bitsize of a value and the number of
+/// positions its bits occupy, as for the verification equation, and the
+/// positions are padded to `padded_n`.
+pub(super) fn prove_ranges(
+	bp_gens: &BulletproofGens,
+	pc_gens: &PedersenGens,
+	transcript: &mut Transcript,
+	openings: &[(u64, Scalar)],
+	ranges: &[(usize, usize)],
+	padded_n: usize,
+	nonces: ProverNonces,
+) -> RangeProof {
+	// The bits of the values, followed by zero bits for the padding.
+	let mut a_L: Vec<Scalar> = openings
+		.iter()
+		.zip(ranges.iter())
+		.flat_map(|(&(v, _), &(n, positions))| {
+			(0..positions).map(move |i| {
+				if i < n {
+					Scalar::from((v >> i) & 1)
+				} else {
+					Scalar::zero()
+				}
+			})
+		})
+		.chain(iter::repeat(Scalar::zero()))
+		.take(padded_n)
+		.collect();
+	let mut a_R: Vec<Scalar> = a_L.iter().map(|a_L_i| a_L_i - Scalar::one()).collect();
+
+	let gens = bp_gens.share(0);
+
+	// Compute A = <a_L, G> + <a_R, H> + a_blinding * B_blinding
+	let A = RistrettoPoint::multiscalar_mul(
+		iter::once(&nonces.a_blinding).chain(a_L.iter()).chain(a_R.iter()),
+		iter::once(&pc_gens.B_blinding)
+			.chain(gens.G(padded_n))
+			.chain(gens.H(padded_n)),
+	)
+	.compress();
+
+	// Compute S = <s_L, G> + <s_R, H> + s_blinding * B_blinding
+	let S = RistrettoPoint::multiscalar_mul(
+		iter::once(&nonces.s_blinding)
+			.chain(nonces.s_L.iter())
+			.chain(nonces.s_R.iter()),
+		iter::once(&pc_gens.B_blinding)
+			.chain(gens.G(padded_n))
+			.chain(gens.H(padded_n)),
+	)
+	.compress();
+
+	transcript.append_point(b"A", &A);
+	transcript.append_point(b"S", &S);
+
+	let y = transcript.challenge_scalar(b"y");
+	let z = transcript.challenge_scalar(b"z");
+	let zz = z * z;
+
+	// Calculate t by calculating vectors l0, l1, r0, r1 and multiplying
+	let mut l_poly = util::VecPoly1::zero(padded_n);
+	let mut r_poly = util::VecPoly1::zero(padded_n);
+
+	let concat_z_and_2 = concat_z_and_2(ranges, padded_n, &z);
+	let mut exp_y = Scalar::one(); // start at y^0 = 1
+	for i in 0..padded_n {
+		l_poly.0[i] = a_L[i] - z;
+		l_poly.1[i] = nonces.s_L[i];
+		r_poly.0[i] = exp_y * (a_R[i] + z) + zz * concat_z_and_2[i];
+		r_poly.1[i] = exp_y * nonces.s_R[i];
+
+		exp_y *= y; // y^i -> y^(i+1)
+	}
+
+	let t_poly = l_poly.inner_product(&r_poly);
+
+	let T_1 = pc_gens.commit(t_poly.1, nonces.t_1_blinding).compress();
+	let T_2 = pc_gens.commit(t_poly.2, nonces.t_2_blinding).compress();
+
+	transcript.append_point(b"T_1", &T_1);
+	transcript.append_point(b"T_2", &T_2);
+
+	let x = transcript.challenge_scalar(b"x");
+
+	let v_blinding: Scalar = openings
+		.iter()
+		.zip(util::exp_iter(z))
+		.map(|(&(_, v_blinding), exp_z)| zz * exp_z * v_blinding)
+		.sum();
+	let t_blinding_poly = util::Poly2(v_blinding, nonces.t_1_blinding, nonces.t_2_blinding);
+
+	let t_x = t_poly.eval(x);
+	let t_x_blinding = t_blinding_poly.eval(x);
+	let e_blinding = nonces.a_blinding + nonces.s_blinding * x;
+	let l_vec = l_poly.eval(x);
+	let r_vec = r_poly.eval(x);
+
+	transcript.append_scalar(b"t_x", &t_x);
+	transcript.append_scalar(b"t_x_blinding", &t_x_blinding);
+	transcript.append_scalar(b"e_blinding", &e_blinding);
+
+	// Get a challenge value to combine statements for the IPP
+	let w = transcript.challenge_scalar(b"w");
+	let Q = w * pc_gens.B;
+
+	let G_factors: Vec<Scalar> = iter::repeat(Scalar::one()).take(padded_n).collect();
+	let H_factors: Vec<Scalar> = util::exp_iter(y.invert()).take(padded_n).collect();
+
+	let ipp_proof = InnerProductProof::create(
+		transcript,
+		&Q,
+		&G_factors,
+		&H_factors,
+		gens.G(padded_n).cloned().collect(),
+		gens.H(padded_n).cloned().collect(),
+		l_vec,
+		r_vec,
+	);
+
+	util::clear_scalars(&mut [&mut a_L, &mut a_R]);
+
+	// The ranges have a bitsize when they are laid out like those of a
+	// proof of values of the same bitsize.
+	let n = ranges[0].0;
+	let uniform = ranges
+		.iter()
+		.all(|&(bits, positions)| bits == n && positions == n.next_power_of_two());
+
+	RangeProof {
+		A,
+		S,
+		T_1,
+		T_2,
+		t_x,
+		t_x_blinding,
+		e_blinding,
+		ipp_proof,
+		n: if uniform { n } else { 0 },
+		m: ranges.len(),
+	}
+}
@@ -7,27 +7,32 @@ extern crate rand;
 
 #[cfg(feature = "std")]
 use self::rand::thread_rng;
-use alloc::vec::Vec;
+use alloc::{vec, vec::Vec};
 
 use core::iter;
 
 use curve25519_dalek::{
 	ristretto::{CompressedRistretto, RistrettoPoint},
 	scalar::Scalar,
-	traits::{IsIdentity, VartimeMultiscalarMul},
+	traits::{Identity, IsIdentity},
 };
-use merlin::Transcript;
+use merlin::{Transcript, TranscriptRng};
 
 use crate::{
 	errors::ProofError,
 	generators::{BulletproofGens, PedersenGens},
 	inner_product_proof::InnerProductProof,
+	msm::{DefaultMsmBackend, MsmBackend},
+	progress::{CancellationToken, Milestone, Progress, ProgressSink},
 	transcript::TranscriptProtocol,
 	util,
+	verification_batch::{Statement, TermPoint, VerificationBatch, VerificationTerms},
 };
 
 use rand_core::{CryptoRng, RngCore};
 
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
 #[cfg(feature = "std")]
 use serde::de::Visitor;
 #[cfg(feature = "std")]
@@ -39,6 +44,12 @@ pub mod dealer;
 pub mod messages;
 pub mod party;
 
+mod batch;
+mod mixed;
+mod rewind;
+
+pub use self::batch::RangeProofVerificationBatcher;
+
 /// The `RangeProof` struct represents a proof that one or more values
 /// are in a range.
 ///
@@ -46,13 +57,19 @@ pub mod party;
 /// verifying aggregated range proofs.  The single-value case is
 /// implemented as a special case of aggregated range proofs.
 ///
-/// The bitsize of the range, as well as the list of commitments to
-/// the values, are not included in the proof, and must be known to
-/// the verifier.
+/// The list of commitments to the values is not included in the proof,
+/// and must be known to the verifier, as must the bitsize of the range.
+/// The encoding of the proof records the bitsize and the number of values
+/// for inspection, as [`RangeProof::bit_size`] and
+/// [`RangeProof::aggregation_size`], but the verifier does not rely on
+/// them.
 ///
-/// This implementation requires that both the bitsize `n` and the
-/// aggregation size `m` be powers of two, so that `n = 8, 16, 32, 64`
-/// and `m = 1, 2, 4, 8, 16, ...`.  Note that the aggregation size is
+/// The bitsize `n` can be any value from \\(1\\) to \\(64\\): the bits
+/// of each value are padded with zero bits up to the next power of two, so
+/// that a proof of \\(52\\)-bit ranges has the size of a proof of
+/// \\(64\\)-bit ranges.  Likewise, the aggregation size `m` is padded to
+/// the next power of two with commitments to zero, which the prover and
+/// the verifier add internally.  Note that the aggregation size is
 /// not given as an explicit parameter, but is determined by the
 /// number of values or commitments passed to the prover or verifier.
 ///
@@ -81,8 +98,19 @@ pub struct RangeProof {
 	e_blinding: Scalar,
 	/// Proof data for the inner-product argument.
 	ipp_proof: InnerProductProof,
+	/// The bitsize of the ranges, or zero if it is unknown.
+	n: usize,
+	/// The number of values, or zero if it is unknown.
+	m: usize,
 }
 
+/// The version of the [`RangeProof::to_bytes`] encoding.
+const ENCODING_VERSION: u8 = 1;
+
+/// The size of the header of the [`RangeProof::to_bytes`] encoding: the
+/// version, the bitsize and the number of values.
+const HEADER_SIZE: usize = 1 + 1 + 4;
+
 impl RangeProof {
 	/// Create a rangeproof for a given pair of value `v` and
 	/// blinding scalar `v_blinding`.
@@ -162,8 +190,114 @@ impl RangeProof {
 		RangeProof::prove_single_with_rng(bp_gens, pc_gens, transcript, v, v_blinding, n, &mut thread_rng())
 	}
 
+	/// Create a rangeproof for an existing commitment
+	/// `expected_commitment` to the value `v` with blinding scalar
+	/// `v_blinding`.
+	///
+	/// This is a convenience wrapper around
+	/// [`RangeProof::prove_single_existing_with_rng`], passing in a
+	/// threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn prove_single_existing(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		v: u64,
+		v_blinding: &Scalar,
+		n: usize,
+		expected_commitment: &CompressedRistretto,
+	) -> Result<RangeProof, ProofError> {
+		RangeProof::prove_single_existing_with_rng(
+			bp_gens,
+			pc_gens,
+			transcript,
+			v,
+			v_blinding,
+			n,
+			expected_commitment,
+			&mut thread_rng(),
+		)
+	}
+
+	/// Create a rangeproof for an existing commitment
+	/// `expected_commitment` to the value `v` with blinding scalar
+	/// `v_blinding`, which verifies against `expected_commitment`.
+	///
+	/// Returns [`ProofError::WrongCommitment`], without touching the
+	/// transcript, if `v` and `v_blinding` do not open
+	/// `expected_commitment`.
+	pub fn prove_single_existing_with_rng<T: RngCore + CryptoRng>(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		v: u64,
+		v_blinding: &Scalar,
+		n: usize,
+		expected_commitment: &CompressedRistretto,
+		rng: &mut T,
+	) -> Result<RangeProof, ProofError> {
+		if pc_gens.commit(v.into(), *v_blinding).compress() != *expected_commitment {
+			return Err(ProofError::WrongCommitment);
+		}
+		let (proof, _) = RangeProof::prove_single_with_rng(bp_gens, pc_gens, transcript, v, v_blinding, n, rng)?;
+		Ok(proof)
+	}
+
+	/// Create a rangeproof for a value `v` given as a scalar, with blinding
+	/// scalar `v_blinding`.
+	///
+	/// This is a convenience wrapper around
+	/// [`RangeProof::prove_single_scalar_with_rng`], passing in a
+	/// threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn prove_single_scalar(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		v: &Scalar,
+		v_blinding: &Scalar,
+		n: usize,
+	) -> Result<(RangeProof, CompressedRistretto), ProofError> {
+		RangeProof::prove_single_scalar_with_rng(bp_gens, pc_gens, transcript, v, v_blinding, n, &mut thread_rng())
+	}
+
+	/// Create a rangeproof for a value `v` given as a scalar, with blinding
+	/// scalar `v_blinding`.
+	///
+	/// The value is read from the low `n` bits of the canonical encoding of
+	/// `v`, and [`ProofError::ValueOutOfRange`] is returned if any higher
+	/// bit is set.  Otherwise, this is the same as
+	/// [`RangeProof::prove_single_with_rng`].
+	pub fn prove_single_scalar_with_rng<T: RngCore + CryptoRng>(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		v: &Scalar,
+		v_blinding: &Scalar,
+		n: usize,
+		rng: &mut T,
+	) -> Result<(RangeProof, CompressedRistretto), ProofError> {
+		if !(1..=64).contains(&n) {
+			return Err(ProofError::InvalidBitsize);
+		}
+
+		let bytes = v.to_bytes();
+		let mut low_bytes = [0u8; 8];
+		low_bytes.copy_from_slice(&bytes[..8]);
+		let value = u64::from_le_bytes(low_bytes);
+		if bytes[8..].iter().any(|&b| b != 0) || (n < 64 && value >> n != 0) {
+			return Err(ProofError::ValueOutOfRange);
+		}
+
+		RangeProof::prove_single_with_rng(bp_gens, pc_gens, transcript, value, v_blinding, n, rng)
+	}
+
 	/// Create a rangeproof for a set of values.
 	///
+	/// The nonces are drawn from the transcript rekeyed with the values and
+	/// their blindings, and finalized with `rng`, so that they stay secret
+	/// even if `rng` is weak.
+	///
 	/// # Example
 	/// ```
 	/// extern crate rand;
@@ -193,149 +327,904 @@ impl RangeProof {
 	/// // The API takes blinding factors for the commitments.
 	/// let blindings: Vec<_> = (0..4).map(|_| Scalar::random(&mut thread_rng())).collect();
 	///
-	/// // The proof can be chained to an existing transcript.
-	/// // Here we create a transcript with a doctest domain separator.
-	/// let mut prover_transcript = Transcript::new(b"doctest example");
+	/// // The proof can be chained to an existing transcript.
+	/// // Here we create a transcript with a doctest domain separator.
+	/// let mut prover_transcript = Transcript::new(b"doctest example");
+	///
+	/// // Create an aggregated 32-bit rangeproof and corresponding commitments.
+	/// let (proof, commitments) =
+	/// 	RangeProof::prove_multiple(&bp_gens, &pc_gens, &mut prover_transcript, &secrets, &blindings, 32)
+	/// 		.expect("A real program could handle errors");
+	///
+	/// // Verification requires a transcript with identical initial state:
+	/// let mut verifier_transcript = Transcript::new(b"doctest example");
+	/// assert!(proof
+	/// 	.verify_multiple(&bp_gens, &pc_gens, &mut verifier_transcript, &commitments, 32)
+	/// 	.is_ok());
+	/// # }
+	/// ```
+	pub fn prove_multiple_with_rng<T: RngCore + CryptoRng>(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		values: &[u64],
+		blindings: &[Scalar],
+		n: usize,
+		rng: &mut T,
+	) -> Result<(RangeProof, Vec<CompressedRistretto>), ProofError> {
+		RangeProof::prove_multiple_with_progress_and_rng(
+			bp_gens, pc_gens, transcript, values, blindings, n, None, None, rng,
+		)
+	}
+
+	/// Create a rangeproof for a set of values, reporting each
+	/// [`Milestone`](::Milestone) to the `progress` sink.
+	///
+	/// This is a convenience wrapper around
+	/// [`RangeProof::prove_multiple_with_progress_and_rng`], passing in a
+	/// threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn prove_multiple_with_progress(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		values: &[u64],
+		blindings: &[Scalar],
+		n: usize,
+		progress: Option<&dyn ProgressSink>,
+		cancel: Option<&CancellationToken>,
+	) -> Result<(RangeProof, Vec<CompressedRistretto>), ProofError> {
+		RangeProof::prove_multiple_with_progress_and_rng(
+			bp_gens,
+			pc_gens,
+			transcript,
+			values,
+			blindings,
+			n,
+			progress,
+			cancel,
+			&mut thread_rng(),
+		)
+	}
+
+	/// Create a rangeproof for a set of values, reporting each
+	/// [`Milestone`](::Milestone) to the `progress` sink.
+	///
+	/// The `cancel` token is checked at each milestone.  If it was
+	/// cancelled, the parties are dropped, clearing their secrets, and
+	/// [`ProofError::Cancelled`] is returned.
+	pub fn prove_multiple_with_progress_and_rng<T: RngCore + CryptoRng>(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		values: &[u64],
+		blindings: &[Scalar],
+		n: usize,
+		progress: Option<&dyn ProgressSink>,
+		cancel: Option<&CancellationToken>,
+		rng: &mut T,
+	) -> Result<(RangeProof, Vec<CompressedRistretto>), ProofError> {
+		use self::{dealer::*, party::*};
+
+		let progress = Progress::new(progress, cancel);
+
+		enter_span!("range_proof_prove", n = n, m = values.len());
+
+		if values.len() != blindings.len() {
+			return Err(ProofError::WrongNumBlindingFactors);
+		}
+
+		let rng = &mut witness_rng(transcript, values.iter().copied().zip(blindings.iter().copied()), rng);
+		let dealer = Dealer::new(bp_gens, pc_gens, transcript, n, values.len())?;
+
+		let parties: Vec<_> = values
+			.iter()
+			.zip(blindings.iter())
+			.map(|(&v, &v_blinding)| Party::new(bp_gens, pc_gens, v, v_blinding, n))
+			// Collect the iterator of Results into a Result<Vec>, then unwrap it
+			.collect::<Result<Vec<_>, _>>()?;
+
+		if progress.cancelled_at(Milestone::WitnessBuilt) {
+			return Err(ProofError::Cancelled);
+		}
+
+		// The parties' nonces are drawn in order, so that their commitments
+		// can be computed in parallel with the `rayon` feature and still
+		// produce the same proof.
+		let (parties, bit_commitments): (Vec<_>, Vec<_>) = in_span!("range_proof_commit", n = n, {
+			let nonces: Vec<_> = parties.iter().map(|_| BitNonces::random(n, rng)).collect();
+			#[cfg(not(feature = "rayon"))]
+			let parties = parties.into_iter().zip(nonces).enumerate();
+			#[cfg(feature = "rayon")]
+			let parties = parties.into_par_iter().zip(nonces).enumerate();
+			parties
+				.map(|(j, (p, nonces))| {
+					p.assign_position_with_nonces(j, nonces)
+						.expect("We already checked the parameters, so this should never happen")
+				})
+				.unzip()
+		});
+
+		let value_commitments: Vec<_> = bit_commitments.iter().map(|c| c.V_j).collect();
+
+		let (dealer, bit_challenge) = dealer.receive_bit_commitments(bit_commitments)?;
+
+		if progress.cancelled_at(Milestone::WitnessCommitted) {
+			return Err(ProofError::Cancelled);
+		}
+
+		let (parties, poly_commitments): (Vec<_>, Vec<_>) = in_span!("range_proof_t_poly", n = n, {
+			let blindings: Vec<_> = parties
+				.iter()
+				.map(|_| (Scalar::random(rng), Scalar::random(rng)))
+				.collect();
+			#[cfg(not(feature = "rayon"))]
+			let parties = parties.into_iter().zip(blindings);
+			#[cfg(feature = "rayon")]
+			let parties = parties.into_par_iter().zip(blindings);
+			parties
+				.map(|(p, (t_1_blinding, t_2_blinding))| {
+					p.apply_challenge_with_blindings(&bit_challenge, t_1_blinding, t_2_blinding)
+				})
+				.unzip()
+		});
+
+		let (dealer, poly_challenge) = dealer.receive_poly_commitments(poly_commitments)?;
+
+		if progress.cancelled_at(Milestone::PolynomialCommitted) {
+			return Err(ProofError::Cancelled);
+		}
+
+		#[cfg(not(feature = "rayon"))]
+		let parties = parties.into_iter();
+		#[cfg(feature = "rayon")]
+		let parties = parties.into_par_iter();
+		let proof_shares: Vec<_> = parties
+			.map(|p| p.apply_challenge(&poly_challenge))
+			// Collect the iterator of Results into a Result<Vec>, then unwrap it
+			.collect::<Result<Vec<_>, _>>()?;
+
+		let proof = dealer
+			.receive_trusted_shares_with_progress(&proof_shares, progress)?
+			.ok_or(ProofError::Cancelled)?;
+
+		Ok((proof, value_commitments))
+	}
+
+	/// Create a rangeproof for a set of values.
+	/// This is a convenience wrapper around
+	/// [`RangeProof::prove_multiple_with_rng`], passing in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn prove_multiple(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		values: &[u64],
+		blindings: &[Scalar],
+		n: usize,
+	) -> Result<(RangeProof, Vec<CompressedRistretto>), ProofError> {
+		RangeProof::prove_multiple_with_rng(bp_gens, pc_gens, transcript, values, blindings, n, &mut thread_rng())
+	}
+
+	/// Verifies a rangeproof for a given value commitment \\(V\\).
+	///
+	/// This is a convenience wrapper around `verify_multiple` for the `m=1`
+	/// case.
+	pub fn verify_single_with_rng<T: RngCore + CryptoRng>(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		V: &CompressedRistretto,
+		n: usize,
+		rng: &mut T,
+	) -> Result<(), ProofError> {
+		self.verify_multiple_with_rng(bp_gens, pc_gens, transcript, &[*V], n, rng)
+	}
+
+	/// Verifies a rangeproof for a given value commitment \\(V\\).
+	///
+	/// This is a convenience wrapper around
+	/// [`RangeProof::verify_single_with_rng`], passing in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn verify_single(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		V: &CompressedRistretto,
+		n: usize,
+	) -> Result<(), ProofError> {
+		self.verify_single_with_rng(bp_gens, pc_gens, transcript, V, n, &mut thread_rng())
+	}
+
+	/// Create a rangeproof that a value `v` is in the range
+	/// \\([min, min + 2^n)\\) for a public minimum `min`.
+	///
+	/// This is a convenience wrapper around
+	/// [`RangeProof::prove_single_with_min_and_rng`], passing in a
+	/// threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn prove_single_with_min(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		v: u64,
+		v_blinding: &Scalar,
+		n: usize,
+		min: u64,
+	) -> Result<(RangeProof, CompressedRistretto), ProofError> {
+		RangeProof::prove_single_with_min_and_rng(
+			bp_gens,
+			pc_gens,
+			transcript,
+			v,
+			v_blinding,
+			n,
+			min,
+			&mut thread_rng(),
+		)
+	}
+
+	/// Create a rangeproof that a value `v` is in the range
+	/// \\([min, min + 2^n)\\) for a public minimum `min`, by proving that
+	/// \\(v - min\\) is in the range \\([0, 2^n)\\).
+	///
+	/// Returns the proof and the commitment to `v` itself, which is
+	/// verified with [`RangeProof::verify_single_with_min_and_rng`], or
+	/// [`ProofError::ValueBelowMinimum`] if `v` is less than `min`.
+	pub fn prove_single_with_min_and_rng<T: RngCore + CryptoRng>(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		v: u64,
+		v_blinding: &Scalar,
+		n: usize,
+		min: u64,
+		rng: &mut T,
+	) -> Result<(RangeProof, CompressedRistretto), ProofError> {
+		let offset = v.checked_sub(min).ok_or(ProofError::ValueBelowMinimum)?;
+		let (proof, _) = RangeProof::prove_single_with_rng(bp_gens, pc_gens, transcript, offset, v_blinding, n, rng)?;
+		Ok((proof, pc_gens.commit(v.into(), *v_blinding).compress()))
+	}
+
+	/// Verifies a rangeproof that the value committed in \\(V\\) is in
+	/// the range \\([min, min + 2^n)\\).
+	///
+	/// This is a convenience wrapper around
+	/// [`RangeProof::verify_single_with_min_and_rng`], passing in a
+	/// threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn verify_single_with_min(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		V: &CompressedRistretto,
+		n: usize,
+		min: u64,
+	) -> Result<(), ProofError> {
+		self.verify_single_with_min_and_rng(bp_gens, pc_gens, transcript, V, n, min, &mut thread_rng())
+	}
+
+	/// Verifies a rangeproof that the value committed in \\(V\\) is in
+	/// the range \\([min, min + 2^n)\\), by verifying the proof of an
+	/// \\(n\\)-bit range for \\(V - min \cdot B\\).
+	pub fn verify_single_with_min_and_rng<T: RngCore + CryptoRng>(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		V: &CompressedRistretto,
+		n: usize,
+		min: u64,
+		rng: &mut T,
+	) -> Result<(), ProofError> {
+		let V = V.decompress().ok_or(ProofError::VerificationError)?;
+		let offset_V = (V - pc_gens.B * Scalar::from(min)).compress();
+		self.verify_single_with_rng(bp_gens, pc_gens, transcript, &offset_V, n, rng)
+	}
+
+	/// Create a rangeproof that a value `value_a` is at least a value
+	/// `value_b`, with both values committed to separately.
+	///
+	/// This is a convenience wrapper around
+	/// [`RangeProof::prove_difference_with_rng`], passing in a threadsafe
+	/// RNG.
+	#[cfg(feature = "std")]
+	pub fn prove_difference(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		value_a: u64,
+		blinding_a: &Scalar,
+		value_b: u64,
+		blinding_b: &Scalar,
+		n: usize,
+	) -> Result<(RangeProof, CompressedRistretto, CompressedRistretto), ProofError> {
+		RangeProof::prove_difference_with_rng(
+			bp_gens,
+			pc_gens,
+			transcript,
+			value_a,
+			blinding_a,
+			value_b,
+			blinding_b,
+			n,
+			&mut thread_rng(),
+		)
+	}
+
+	/// Create a rangeproof that a value `value_a` is at least a value
+	/// `value_b`, by proving that \\(a - b\\) is in the range \\([0,
+	/// 2^n)\\) under the commitment \\(C\_a - C\_b\\).
+	///
+	/// Returns the proof and the commitments \\(C\_a\\) and \\(C\_b\\) to
+	/// the two values, which are verified with
+	/// [`RangeProof::verify_difference_with_rng`], or
+	/// [`ProofError::ValueBelowMinimum`] if `value_a` is less than
+	/// `value_b`.
+	pub fn prove_difference_with_rng<T: RngCore + CryptoRng>(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		value_a: u64,
+		blinding_a: &Scalar,
+		value_b: u64,
+		blinding_b: &Scalar,
+		n: usize,
+		rng: &mut T,
+	) -> Result<(RangeProof, CompressedRistretto, CompressedRistretto), ProofError> {
+		// The difference of the scalars wraps around the group order when
+		// a < b, so it must be checked on the integers.
+		let difference = value_a.checked_sub(value_b).ok_or(ProofError::ValueBelowMinimum)?;
+		let blinding = blinding_a - blinding_b;
+		let (proof, _) =
+			RangeProof::prove_single_with_rng(bp_gens, pc_gens, transcript, difference, &blinding, n, rng)?;
+		Ok((
+			proof,
+			pc_gens.commit(value_a.into(), *blinding_a).compress(),
+			pc_gens.commit(value_b.into(), *blinding_b).compress(),
+		))
+	}
+
+	/// Verifies a rangeproof that the value committed in \\(C\_a\\) is at
+	/// least the value committed in \\(C\_b\\).
+	///
+	/// This is a convenience wrapper around
+	/// [`RangeProof::verify_difference_with_rng`], passing in a threadsafe
+	/// RNG.
+	#[cfg(feature = "std")]
+	pub fn verify_difference(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		C_a: &CompressedRistretto,
+		C_b: &CompressedRistretto,
+		n: usize,
+	) -> Result<(), ProofError> {
+		self.verify_difference_with_rng(bp_gens, pc_gens, transcript, C_a, C_b, n, &mut thread_rng())
+	}
+
+	/// Verifies a rangeproof that the value committed in \\(C\_a\\) is at
+	/// least the value committed in \\(C\_b\\), by verifying the proof of an
+	/// \\(n\\)-bit range for \\(C\_a - C\_b\\).
+	///
+	/// This only shows that \\(a \geq b\\) if the values are known to be
+	/// less than the order of the group minus \\(2^n\\), for example if
+	/// they are themselves proven to be in a range.
+	pub fn verify_difference_with_rng<T: RngCore + CryptoRng>(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		C_a: &CompressedRistretto,
+		C_b: &CompressedRistretto,
+		n: usize,
+		rng: &mut T,
+	) -> Result<(), ProofError> {
+		let C_a = C_a.decompress().ok_or(ProofError::VerificationError)?;
+		let C_b = C_b.decompress().ok_or(ProofError::VerificationError)?;
+		let difference = (C_a - C_b).compress();
+		self.verify_single_with_rng(bp_gens, pc_gens, transcript, &difference, n, rng)
+	}
+
+	/// Create a rangeproof that a signed value `v` is in the range
+	/// \\([-2^{n-1}, 2^{n-1})\\).
+	///
+	/// This is a convenience wrapper around
+	/// [`RangeProof::prove_single_signed_with_rng`], passing in a
+	/// threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn prove_single_signed(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		v: i64,
+		v_blinding: &Scalar,
+		n: usize,
+	) -> Result<(RangeProof, CompressedRistretto), ProofError> {
+		RangeProof::prove_single_signed_with_rng(bp_gens, pc_gens, transcript, v, v_blinding, n, &mut thread_rng())
+	}
+
+	/// Create a rangeproof that a signed value `v` is in the range
+	/// \\([-2^{n-1}, 2^{n-1})\\), by proving that the offset binary
+	/// encoding \\(v + 2^{n-1}\\) is in the range \\([0, 2^n)\\).
+	///
+	/// A negative value is committed to as its negation in the scalar
+	/// field.  Returns the proof and the commitment to `v` itself, which is
+	/// verified with [`RangeProof::verify_single_signed_with_rng`], or
+	/// [`ProofError::ValueOutOfRange`] if `v` is not in the range.  With
+	/// \\(n = 64\\), the range covers every `i64`, from `i64::MIN` to
+	/// `i64::MAX`.
+	pub fn prove_single_signed_with_rng<T: RngCore + CryptoRng>(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		v: i64,
+		v_blinding: &Scalar,
+		n: usize,
+		rng: &mut T,
+	) -> Result<(RangeProof, CompressedRistretto), ProofError> {
+		if !(1..=64).contains(&n) {
+			return Err(ProofError::InvalidBitsize);
+		}
+		let offset = i128::from(v) + (1i128 << (n - 1));
+		if offset < 0 || offset >= 1i128 << n {
+			return Err(ProofError::ValueOutOfRange);
+		}
+
+		let (proof, _) =
+			RangeProof::prove_single_with_rng(bp_gens, pc_gens, transcript, offset as u64, v_blinding, n, rng)?;
+		let v_scalar = if v < 0 {
+			-Scalar::from(v.unsigned_abs())
+		} else {
+			Scalar::from(v as u64)
+		};
+		Ok((proof, pc_gens.commit(v_scalar, *v_blinding).compress()))
+	}
+
+	/// Verifies a rangeproof that the signed value committed in \\(V\\) is
+	/// in the range \\([-2^{n-1}, 2^{n-1})\\).
+	///
+	/// This is a convenience wrapper around
+	/// [`RangeProof::verify_single_signed_with_rng`], passing in a
+	/// threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn verify_single_signed(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		V: &CompressedRistretto,
+		n: usize,
+	) -> Result<(), ProofError> {
+		self.verify_single_signed_with_rng(bp_gens, pc_gens, transcript, V, n, &mut thread_rng())
+	}
+
+	/// Verifies a rangeproof that the signed value committed in \\(V\\) is
+	/// in the range \\([-2^{n-1}, 2^{n-1})\\), by verifying the proof of an
+	/// \\(n\\)-bit range for \\(V + 2^{n-1} \cdot B\\).
+	pub fn verify_single_signed_with_rng<T: RngCore + CryptoRng>(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		V: &CompressedRistretto,
+		n: usize,
+		rng: &mut T,
+	) -> Result<(), ProofError> {
+		if !(1..=64).contains(&n) {
+			return Err(ProofError::InvalidBitsize);
+		}
+		let V = V.decompress().ok_or(ProofError::VerificationError)?;
+		let offset_V = (V + pc_gens.B * Scalar::from(1u64 << (n - 1))).compress();
+		self.verify_single_with_rng(bp_gens, pc_gens, transcript, &offset_V, n, rng)
+	}
+
+	/// Verifies a rangeproof for a given value commitment \\(V\\), using
+	/// the given multiscalar multiplication `backend`.
+	///
+	/// This is a convenience wrapper around
+	/// [`RangeProof::verify_multiple_with_backend`] for the `m=1` case.
+	pub fn verify_single_with_backend<T: RngCore + CryptoRng>(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		V: &CompressedRistretto,
+		n: usize,
+		rng: &mut T,
+		backend: Option<&dyn MsmBackend>,
+	) -> Result<(), ProofError> {
+		self.verify_multiple_with_backend(bp_gens, pc_gens, transcript, &[*V], n, rng, backend)
+	}
+
+	/// Verifies an aggregated rangeproof for the given value commitments.
+	pub fn verify_multiple_with_rng<T: RngCore + CryptoRng>(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		value_commitments: &[CompressedRistretto],
+		n: usize,
+		rng: &mut T,
+	) -> Result<(), ProofError> {
+		self.verify_multiple_with_backend(bp_gens, pc_gens, transcript, value_commitments, n, rng, None)
+	}
+
+	/// Verifies an aggregated rangeproof for the given value commitments,
+	/// performing the final multiscalar multiplication with `backend`.
+	///
+	/// If `backend` is `None`, the [`DefaultMsmBackend`] is used.
+	pub fn verify_multiple_with_backend<T: RngCore + CryptoRng>(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		value_commitments: &[CompressedRistretto],
+		n: usize,
+		rng: &mut T,
+		backend: Option<&dyn MsmBackend>,
+	) -> Result<(), ProofError> {
+		enter_span!("range_proof_verify", n = n, m = value_commitments.len());
+
+		self.aggregated_verification_equation(bp_gens, transcript, value_commitments, n, rng)?
+			.check(bp_gens, pc_gens, backend.unwrap_or(&DefaultMsmBackend))
+	}
+
+	/// Performs the cheap part of the verification of a rangeproof for a
+	/// given value commitment \\(V\\), and adds its final check to the
+	/// `batch` instead of performing it.
+	///
+	/// This is the deferred counterpart of
+	/// [`RangeProof::verify_single_with_rng`] for the `m=1` case.
+	pub fn verify_single_deferred<T: RngCore + CryptoRng>(
+		&self,
+		transcript: &mut Transcript,
+		V: &CompressedRistretto,
+		n: usize,
+		batch: &mut VerificationBatch<'_, T>,
+	) -> Result<usize, ProofError> {
+		self.verify_multiple_deferred(transcript, &[*V], n, batch)
+	}
+
+	/// Performs the cheap part of the verification of an aggregated
+	/// rangeproof for the given value commitments, and adds its final check
+	/// to the `batch` instead of performing it.
+	///
+	/// Returns the index of the statement in the `batch`.  Errors which do
+	/// not need the final check, such as a malformed proof or too few
+	/// generators in the batch, are returned immediately, and nothing is
+	/// added to the `batch`.
+	pub fn verify_multiple_deferred<T: RngCore + CryptoRng>(
+		&self,
+		transcript: &mut Transcript,
+		value_commitments: &[CompressedRistretto],
+		n: usize,
+		batch: &mut VerificationBatch<'_, T>,
+	) -> Result<usize, ProofError> {
+		enter_span!("range_proof_verify_deferred", n = n, m = value_commitments.len());
+
+		let bp_gens = batch.bp_gens();
+		let equation =
+			self.aggregated_verification_equation(bp_gens, transcript, value_commitments, n, &mut batch.rng)?;
+		Ok(batch.push(equation.into_statement()))
+	}
+
+	/// Checks the sizes of an aggregated rangeproof for the given value
+	/// commitments, pads the commitments, and computes the scalars of its
+	/// verification equation.
+	fn aggregated_verification_equation<T: RngCore + CryptoRng>(
+		&self,
+		bp_gens: &BulletproofGens,
+		transcript: &mut Transcript,
+		value_commitments: &[CompressedRistretto],
+		n: usize,
+		rng: &mut T,
+	) -> Result<VerificationEquation, ProofError> {
+		let m = value_commitments.len();
+		check_parameters(bp_gens, n, m)?;
+		let padded_n = n.next_power_of_two();
+		let padded_m = m.next_power_of_two();
+
+		// Reject a proof with the wrong number of rounds before decompressing
+		// any of its points.
+		let lg_nm = (padded_n * padded_m).trailing_zeros() as usize;
+		if self.ipp_proof.L_vec.len() != lg_nm || self.ipp_proof.R_vec.len() != lg_nm {
+			return Err(ProofError::FormatError);
+		}
+
+		transcript.rangeproof_domain_sep(n as u64, m as u64);
+
+		// The dealer pads the aggregation with commitments to zero with a
+		// zero blinding factor.
+		let value_commitments: Vec<CompressedRistretto> = value_commitments
+			.iter()
+			.cloned()
+			.chain(iter::repeat(CompressedRistretto::identity()))
+			.take(padded_m)
+			.collect();
+		let ranges = vec![(n, padded_n); padded_m];
+		self.verification_equation(transcript, &value_commitments, &ranges, (padded_n, padded_m), rng)
+	}
+
+	/// Returns the terms of the verification equation of an aggregated
+	/// rangeproof for the given value commitments.
+	///
+	/// This is a convenience wrapper around
+	/// [`RangeProof::verification_terms_with_rng`], passing in a threadsafe
+	/// RNG.
+	#[cfg(feature = "std")]
+	pub fn verification_terms(
+		&self,
+		bp_gens: &BulletproofGens,
+		transcript: &mut Transcript,
+		value_commitments: &[CompressedRistretto],
+		n: usize,
+	) -> Result<VerificationTerms, ProofError> {
+		self.verification_terms_with_rng(bp_gens, transcript, value_commitments, n, &mut thread_rng())
+	}
+
+	/// Returns the terms of the verification equation of an aggregated
+	/// rangeproof for the given value commitments, for protocols which
+	/// combine it with their own equations.
+	///
+	/// The proof verifies if and only if [`VerificationTerms::verify`]
+	/// succeeds.  The commitments are padded to a power of two with the
+	/// identity, which appears as further [`TermPoint::Commitment`]s.
+	pub fn verification_terms_with_rng<T: RngCore + CryptoRng>(
+		&self,
+		bp_gens: &BulletproofGens,
+		transcript: &mut Transcript,
+		value_commitments: &[CompressedRistretto],
+		n: usize,
+		rng: &mut T,
+	) -> Result<VerificationTerms, ProofError> {
+		Ok(self
+			.aggregated_verification_equation(bp_gens, transcript, value_commitments, n, rng)?
+			.into_terms())
+	}
+
+	/// Verifies an aggregated rangeproof for the given value commitments.
+	/// This is a convenience wrapper around
+	/// [`RangeProof::verify_multiple_with_rng`], passing in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn verify_multiple(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		value_commitments: &[CompressedRistretto],
+		n: usize,
+	) -> Result<(), ProofError> {
+		self.verify_multiple_with_rng(bp_gens, pc_gens, transcript, value_commitments, n, &mut thread_rng())
+	}
+
+	/// Verifies a batch of independent rangeproofs, each for a single value
+	/// commitment, with a single multiscalar multiplication.
+	///
+	/// This is a convenience wrapper around
+	/// [`RangeProof::verify_batch_with_rng`], passing in a threadsafe RNG.
+	#[cfg(feature = "std")]
+	pub fn verify_batch(
+		proofs: &[(&RangeProof, &CompressedRistretto)],
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &Transcript,
+		n: usize,
+	) -> Result<(), ProofError> {
+		RangeProof::verify_batch_with_rng(proofs, bp_gens, pc_gens, transcript, n, &mut thread_rng())
+	}
+
+	/// Verifies a batch of independent rangeproofs, each for a single value
+	/// commitment, with a single multiscalar multiplication.
+	///
+	/// Every proof is checked against a copy of `transcript`, which must be
+	/// in the same state as the transcripts of the provers.  The
+	/// verification equations are weighted with random scalars and summed,
+	/// so that the generators shared by the proofs are only multiplied
+	/// once.
 	///
-	/// // Create an aggregated 32-bit rangeproof and corresponding commitments.
-	/// let (proof, commitments) =
-	/// 	RangeProof::prove_multiple(&bp_gens, &pc_gens, &mut prover_transcript, &secrets, &blindings, 32)
-	/// 		.expect("A real program could handle errors");
+	/// Returns [`ProofError::BatchVerificationError`] with the index of the
+	/// first proof which does not verify.  Proofs with a malformed
+	/// inner-product proof are rejected before any transcript is replayed;
+	/// otherwise, if the combined check fails, the proofs are verified one
+	/// by one to find the offender.
 	///
-	/// // Verification requires a transcript with identical initial state:
-	/// let mut verifier_transcript = Transcript::new(b"doctest example");
-	/// assert!(proof
-	/// 	.verify_multiple(&bp_gens, &pc_gens, &mut verifier_transcript, &commitments, 32)
-	/// 	.is_ok());
-	/// # }
-	/// ```
-	pub fn prove_multiple_with_rng<T: RngCore + CryptoRng>(
+	/// To verify more proofs than fit in memory at once, use a
+	/// [`RangeProofVerificationBatcher`].
+	pub fn verify_batch_with_rng<T: RngCore + CryptoRng>(
+		proofs: &[(&RangeProof, &CompressedRistretto)],
 		bp_gens: &BulletproofGens,
 		pc_gens: &PedersenGens,
-		transcript: &mut Transcript,
-		values: &[u64],
-		blindings: &[Scalar],
+		transcript: &Transcript,
 		n: usize,
 		rng: &mut T,
-	) -> Result<(RangeProof, Vec<CompressedRistretto>), ProofError> {
-		use self::{dealer::*, party::*};
+	) -> Result<(), ProofError> {
+		enter_span!("range_proof_verify_batch", n = n, batch = proofs.len());
 
-		if values.len() != blindings.len() {
-			return Err(ProofError::WrongNumBlindingFactors);
+		check_parameters(bp_gens, n, 1)?;
+		let padded_n = n.next_power_of_two();
+		let lg_n = padded_n.trailing_zeros() as usize;
+		if let Some(index) = proofs
+			.iter()
+			.position(|(proof, _)| proof.ipp_proof.L_vec.len() != lg_n || proof.ipp_proof.R_vec.len() != lg_n)
+		{
+			return Err(ProofError::BatchVerificationError { index });
 		}
 
-		let dealer = Dealer::new(bp_gens, pc_gens, transcript, n, values.len())?;
-
-		let parties: Vec<_> = values
+		let equations = proofs
 			.iter()
-			.zip(blindings.iter())
-			.map(|(&v, &v_blinding)| Party::new(bp_gens, pc_gens, v, v_blinding, n))
-			// Collect the iterator of Results into a Result<Vec>, then unwrap it
-			.collect::<Result<Vec<_>, _>>()?;
-
-		let (parties, bit_commitments): (Vec<_>, Vec<_>) = parties
-			.into_iter()
 			.enumerate()
-			.map(|(j, p)| {
-				p.assign_position_with_rng(j, rng)
-					.expect("We already checked the parameters, so this should never happen")
+			.map(|(index, (proof, V))| {
+				let mut transcript = transcript.clone();
+				transcript.rangeproof_domain_sep(n as u64, 1);
+				proof
+					.verification_equation(&mut transcript, &[**V], &[(n, padded_n)], (padded_n, 1), rng)
+					.map_err(|_| ProofError::BatchVerificationError { index })
 			})
-			.unzip();
-
-		let value_commitments: Vec<_> = bit_commitments.iter().map(|c| c.V_j).collect();
-
-		let (dealer, bit_challenge) = dealer.receive_bit_commitments(bit_commitments)?;
+			.collect::<Result<Vec<_>, _>>()?;
 
-		let (parties, poly_commitments): (Vec<_>, Vec<_>) = parties
-			.into_iter()
-			.map(|p| p.apply_challenge_with_rng(&bit_challenge, rng))
-			.unzip();
+		let mut scalars = Vec::new();
+		let mut points = Vec::new();
+		let mut B_scalar = Scalar::zero();
+		let mut B_blinding_scalar = Scalar::zero();
+		let mut g_scalars = vec![Scalar::zero(); padded_n];
+		let mut h_scalars = vec![Scalar::zero(); padded_n];
+
+		for equation in equations.iter() {
+			let c = Scalar::random(rng);
+
+			scalars.extend(equation.point_scalars.iter().map(|s| c * s));
+			points.extend_from_slice(&equation.points);
+			B_scalar += c * equation.B_scalar;
+			B_blinding_scalar += c * equation.B_blinding_scalar;
+			for (acc, s) in g_scalars.iter_mut().zip(equation.g_scalars.iter()) {
+				*acc += c * s;
+			}
+			for (acc, s) in h_scalars.iter_mut().zip(equation.h_scalars.iter()) {
+				*acc += c * s;
+			}
+		}
 
-		let (dealer, poly_challenge) = dealer.receive_poly_commitments(poly_commitments)?;
+		scalars.push(B_scalar);
+		scalars.push(B_blinding_scalar);
+		scalars.extend_from_slice(&g_scalars);
+		scalars.extend_from_slice(&h_scalars);
 
-		let proof_shares: Vec<_> = parties
-			.into_iter()
-			.map(|p| p.apply_challenge(&poly_challenge))
-			// Collect the iterator of Results into a Result<Vec>, then unwrap it
-			.collect::<Result<Vec<_>, _>>()?;
+		points.push(pc_gens.B);
+		points.push(pc_gens.B_blinding);
+		points.extend(bp_gens.G(padded_n, 1));
+		points.extend(bp_gens.H(padded_n, 1));
 
-		let proof = dealer.receive_trusted_shares(&proof_shares)?;
+		let mega_check = in_span!("range_proof_batch_multiexp", size = points.len(), {
+			DefaultMsmBackend.vartime_msm(&scalars, &points)
+		});
+		if mega_check.is_identity() {
+			return Ok(());
+		}
 
-		Ok((proof, value_commitments))
+		// Fall back to individual checks to find the invalid proof.
+		for (index, equation) in equations.into_iter().enumerate() {
+			equation
+				.check(bp_gens, pc_gens, &DefaultMsmBackend)
+				.map_err(|_| ProofError::BatchVerificationError { index })?;
+		}
+		Err(ProofError::VerificationError)
 	}
 
-	/// Create a rangeproof for a set of values.
+	/// Verifies a batch of independent aggregated rangeproofs, each for its
+	/// own value commitments, with a single multiscalar multiplication.
+	///
 	/// This is a convenience wrapper around
-	/// [`RangeProof::prove_multiple_with_rng`], passing in a threadsafe RNG.
+	/// [`RangeProof::verify_multiple_batch_with_rng`], passing in a
+	/// threadsafe RNG.
 	#[cfg(feature = "std")]
-	pub fn prove_multiple(
+	pub fn verify_multiple_batch(
+		proofs: &[(&RangeProof, &[CompressedRistretto])],
 		bp_gens: &BulletproofGens,
 		pc_gens: &PedersenGens,
-		transcript: &mut Transcript,
-		values: &[u64],
-		blindings: &[Scalar],
+		transcript: &Transcript,
 		n: usize,
-	) -> Result<(RangeProof, Vec<CompressedRistretto>), ProofError> {
-		RangeProof::prove_multiple_with_rng(bp_gens, pc_gens, transcript, values, blindings, n, &mut thread_rng())
+	) -> Result<(), ProofError> {
+		RangeProof::verify_multiple_batch_with_rng(proofs, bp_gens, pc_gens, transcript, n, &mut thread_rng())
 	}
 
-	/// Verifies a rangeproof for a given value commitment \\(V\\).
+	/// Verifies a batch of independent aggregated rangeproofs, each for its
+	/// own value commitments, with a single multiscalar multiplication.
 	///
-	/// This is a convenience wrapper around `verify_multiple` for the `m=1`
-	/// case.
-	pub fn verify_single_with_rng<T: RngCore + CryptoRng>(
-		&self,
+	/// Every proof is checked against a copy of `transcript`, as with
+	/// [`RangeProof::verify_multiple_with_rng`], and the proofs may
+	/// aggregate different numbers of values.  An aggregated proof is
+	/// accepted or rejected as a whole.
+	///
+	/// Returns [`ProofError::BatchStatementsVerificationError`] with the
+	/// indexes of all the proofs which are malformed or do not verify.  If
+	/// the combined check fails, the batch is bisected to find them, as in
+	/// [`VerificationBatch::verify`].
+	pub fn verify_multiple_batch_with_rng<T: RngCore + CryptoRng>(
+		proofs: &[(&RangeProof, &[CompressedRistretto])],
 		bp_gens: &BulletproofGens,
 		pc_gens: &PedersenGens,
-		transcript: &mut Transcript,
-		V: &CompressedRistretto,
+		transcript: &Transcript,
 		n: usize,
 		rng: &mut T,
 	) -> Result<(), ProofError> {
-		self.verify_multiple_with_rng(bp_gens, pc_gens, transcript, &[*V], n, rng)
+		enter_span!("range_proof_verify_multiple_batch", n = n, batch = proofs.len());
+
+		let indexes = crate::verification_batch::verify_indexed(
+			proofs.iter().enumerate(),
+			VerificationBatch::new_with_rng(bp_gens, pc_gens, rng),
+			|&(proof, value_commitments), batch| {
+				proof.verify_multiple_deferred(&mut transcript.clone(), value_commitments, n, batch)
+			},
+		);
+		if indexes.is_empty() {
+			Ok(())
+		} else {
+			Err(ProofError::BatchStatementsVerificationError { indexes })
+		}
 	}
 
-	/// Verifies a rangeproof for a given value commitment \\(V\\).
+	/// Verifies a batch of independent rangeproofs, each for a single value
+	/// commitment, in up to `parallelism` partitions checked on the current
+	/// `rayon` thread pool.
 	///
-	/// This is a convenience wrapper around
-	/// [`RangeProof::verify_single_with_rng`], passing in a threadsafe RNG.
-	#[cfg(feature = "std")]
-	pub fn verify_single(
-		&self,
+	/// Each partition replays the transcripts of its proofs and checks them
+	/// with one multiscalar multiplication, as in
+	/// [`RangeProof::verify_batch_with_rng`], so the batch is accepted
+	/// exactly when the serial batch would be.  The random weights are drawn
+	/// from a thread-local RNG in each partition.
+	///
+	/// Returns [`ProofError::BatchStatementsVerificationError`] with the
+	/// indexes of all the proofs which do not verify, from every partition.
+	#[cfg(feature = "rayon")]
+	pub fn verify_batch_parallel(
+		proofs: &[(&RangeProof, &CompressedRistretto)],
 		bp_gens: &BulletproofGens,
 		pc_gens: &PedersenGens,
-		transcript: &mut Transcript,
-		V: &CompressedRistretto,
+		transcript: &Transcript,
 		n: usize,
+		parallelism: usize,
 	) -> Result<(), ProofError> {
-		self.verify_single_with_rng(bp_gens, pc_gens, transcript, V, n, &mut thread_rng())
+		enter_span!("range_proof_verify_batch_parallel", n = n, batch = proofs.len());
+
+		check_parameters(bp_gens, n, 1)?;
+		let indexes = crate::verification_batch::verify_partitioned(
+			proofs.to_vec(),
+			parallelism,
+			bp_gens,
+			pc_gens,
+			|(proof, V), batch| proof.verify_single_deferred(&mut transcript.clone(), V, n, batch),
+		);
+		if indexes.is_empty() {
+			Ok(())
+		} else {
+			Err(ProofError::BatchStatementsVerificationError { indexes })
+		}
 	}
 
-	/// Verifies an aggregated rangeproof for the given value commitments.
-	pub fn verify_multiple_with_rng<T: RngCore + CryptoRng>(
+	/// Replays the protocol of the proof for the given value commitments,
+	/// after the domain separator, and computes the scalars of its
+	/// verification equation.
+	///
+	/// Each of the `ranges` gives the bitsize of a value and the number of
+	/// positions its bits occupy, the remaining positions being padding;
+	/// the ranges are laid out one after the other and padded to a power of
+	/// two.  The proof uses the first `gens.0` generators of the first
+	/// `gens.1` party shares, which must have been checked to be available.
+	fn verification_equation<T: RngCore + CryptoRng>(
 		&self,
-		bp_gens: &BulletproofGens,
-		pc_gens: &PedersenGens,
 		transcript: &mut Transcript,
 		value_commitments: &[CompressedRistretto],
-		n: usize,
+		ranges: &[(usize, usize)],
+		gens: (usize, usize),
 		rng: &mut T,
-	) -> Result<(), ProofError> {
-		let m = value_commitments.len();
-
-		// First, replay the "interactive" protocol using the proof
-		// data to recompute all challenges.
-		if !(n == 8 || n == 16 || n == 32 || n == 64) {
-			return Err(ProofError::InvalidBitsize);
-		}
-		if bp_gens.gens_capacity < n {
-			return Err(ProofError::InvalidGeneratorsLength);
-		}
-		if bp_gens.party_capacity < m {
-			return Err(ProofError::InvalidGeneratorsLength);
-		}
-
-		transcript.rangeproof_domain_sep(n as u64, m as u64);
+	) -> Result<VerificationEquation, ProofError> {
+		let (n, m) = gens;
+		let padded_n = n * m;
 
 		for V in value_commitments.iter() {
 			// Allow the commitments to be zero (0 value, 0 blinding)
@@ -365,92 +1254,140 @@ impl RangeProof {
 		// Challenge value for batching statements to be verified
 		let c = Scalar::random(rng);
 
-		let (x_sq, x_inv_sq, s) = self.ipp_proof.verification_scalars(n * m, transcript)?;
+		let (x_sq, x_inv_sq, s) = self.ipp_proof.verification_scalars(padded_n, transcript)?;
 		let s_inv = s.iter().rev();
 
 		let a = self.ipp_proof.a;
 		let b = self.ipp_proof.b;
 
-		// Construct concat_z_and_2, an iterator of the values of
-		// z^0 * \vec(2)^n || z^1 * \vec(2)^n || ... || z^(m-1) * \vec(2)^n
-		let powers_of_2: Vec<Scalar> = util::exp_iter(Scalar::from(2u64)).take(n).collect();
-		let concat_z_and_2: Vec<Scalar> = util::exp_iter(z)
-			.take(m)
-			.flat_map(|exp_z| powers_of_2.iter().map(move |exp_2| exp_2 * exp_z))
-			.collect();
+		let concat_z_and_2 = concat_z_and_2(ranges, padded_n, &z);
 
-		let g = s.iter().map(|s_i| minus_z - a * s_i);
-		let h = s_inv
+		let g_scalars = s.iter().map(|s_i| minus_z - a * s_i).collect();
+		let h_scalars = s_inv
 			.zip(util::exp_iter(y.invert()))
 			.zip(concat_z_and_2.iter())
-			.map(|((s_i_inv, exp_y_inv), z_and_2)| z + exp_y_inv * (zz * z_and_2 - b * s_i_inv));
-
-		let value_commitment_scalars = util::exp_iter(z).take(m).map(|z_exp| c * zz * z_exp);
-		let basepoint_scalar = w * (self.t_x - a * b) + c * (delta(n, m, &y, &z) - self.t_x);
-
-		let mega_check = RistrettoPoint::optional_multiscalar_mul(
-			iter::once(Scalar::one())
-				.chain(iter::once(x))
-				.chain(iter::once(c * x))
-				.chain(iter::once(c * x * x))
-				.chain(x_sq.iter().cloned())
-				.chain(x_inv_sq.iter().cloned())
-				.chain(iter::once(-self.e_blinding - c * self.t_x_blinding))
-				.chain(iter::once(basepoint_scalar))
-				.chain(g)
-				.chain(h)
-				.chain(value_commitment_scalars),
-			iter::once(self.A.decompress())
-				.chain(iter::once(self.S.decompress()))
-				.chain(iter::once(self.T_1.decompress()))
-				.chain(iter::once(self.T_2.decompress()))
-				.chain(self.ipp_proof.L_vec.iter().map(|L| L.decompress()))
-				.chain(self.ipp_proof.R_vec.iter().map(|R| R.decompress()))
-				.chain(iter::once(Some(pc_gens.B_blinding)))
-				.chain(iter::once(Some(pc_gens.B)))
-				.chain(bp_gens.G(n, m).map(|&x| Some(x)))
-				.chain(bp_gens.H(n, m).map(|&x| Some(x)))
-				.chain(value_commitments.iter().map(|V| V.decompress())),
-		)
-		.ok_or_else(|| ProofError::VerificationError)?;
+			.map(|((s_i_inv, exp_y_inv), z_and_2)| z + exp_y_inv * (zz * z_and_2 - b * s_i_inv))
+			.collect();
 
-		if mega_check.is_identity() {
-			Ok(())
-		} else {
-			Err(ProofError::VerificationError)
-		}
+		let value_commitment_scalars = util::exp_iter(z)
+			.take(value_commitments.len())
+			.map(|z_exp| c * zz * z_exp);
+
+		let point_scalars = iter::once(Scalar::one())
+			.chain(iter::once(x))
+			.chain(iter::once(c * x))
+			.chain(iter::once(c * x * x))
+			.chain(x_sq.iter().cloned())
+			.chain(x_inv_sq.iter().cloned())
+			.chain(value_commitment_scalars)
+			.collect();
+		let points = iter::once(self.A.decompress())
+			.chain(iter::once(self.S.decompress()))
+			.chain(iter::once(self.T_1.decompress()))
+			.chain(iter::once(self.T_2.decompress()))
+			.chain(self.ipp_proof.L_vec.iter().map(|L| L.decompress()))
+			.chain(self.ipp_proof.R_vec.iter().map(|R| R.decompress()))
+			.chain(value_commitments.iter().map(|V| V.decompress()))
+			.collect::<Option<_>>()
+			.ok_or(ProofError::VerificationError)?;
+
+		Ok(VerificationEquation {
+			n,
+			m,
+			points,
+			point_scalars,
+			B_scalar: w * (self.t_x - a * b) + c * (delta(ranges, padded_n, &y, &z) - self.t_x),
+			B_blinding_scalar: -self.e_blinding - c * self.t_x_blinding,
+			g_scalars,
+			h_scalars,
+		})
 	}
 
-	/// Verifies an aggregated rangeproof for the given value commitments.
-	/// This is a convenience wrapper around
-	/// [`RangeProof::verify_multiple_with_rng`], passing in a threadsafe RNG.
-	#[cfg(feature = "std")]
-	pub fn verify_multiple(
-		&self,
-		bp_gens: &BulletproofGens,
-		pc_gens: &PedersenGens,
-		transcript: &mut Transcript,
-		value_commitments: &[CompressedRistretto],
-		n: usize,
-	) -> Result<(), ProofError> {
-		self.verify_multiple_with_rng(bp_gens, pc_gens, transcript, value_commitments, n, &mut thread_rng())
+	/// Returns the commitment \\(A\\) to the bits of the values.
+	pub fn A(&self) -> CompressedRistretto {
+		self.A
+	}
+
+	/// Returns the commitment \\(S\\) to the blinding factors.
+	pub fn S(&self) -> CompressedRistretto {
+		self.S
+	}
+
+	/// Returns the commitment \\(T_1\\) to the \\(t_1\\) coefficient of
+	/// \\( t(x) \\).
+	pub fn T_1(&self) -> CompressedRistretto {
+		self.T_1
+	}
+
+	/// Returns the commitment \\(T_2\\) to the \\(t_2\\) coefficient of
+	/// \\( t(x) \\).
+	pub fn T_2(&self) -> CompressedRistretto {
+		self.T_2
+	}
+
+	/// Returns the inner-product sub-proof.
+	pub fn ipp_proof(&self) -> &InnerProductProof {
+		&self.ipp_proof
+	}
+
+	/// Returns the bitsize \\(n\\) of the ranges of the proof.
+	///
+	/// Returns `None` for a proof of ranges of different bit sizes, or
+	/// parsed with [`RangeProof::from_bytes_legacy`].
+	pub fn bit_size(&self) -> Option<usize> {
+		Some(self.n).filter(|&n| n != 0)
 	}
 
-	/// Serializes the proof into a byte array of \\(2 \lg n + 9\\)
-	/// 32-byte elements, where \\(n\\) is the number of secret bits.
+	/// Returns the number \\(m\\) of values in the proof, before padding to
+	/// a power of two.
+	///
+	/// Returns `None` for a proof parsed with
+	/// [`RangeProof::from_bytes_legacy`].
+	pub fn aggregation_size(&self) -> Option<usize> {
+		Some(self.m).filter(|&m| m != 0)
+	}
+
+	/// Infers the bitsize \\(n\\) of a proof aggregating `m` values from
+	/// the number of rounds of the inner-product proof, which is
+	/// \\(\lg(n \cdot m)\\).  For a bitsize which is not a power of two,
+	/// this is the padded bitsize.
+	///
+	/// This allows rejecting a proof of unexpected size before running
+	/// the verification.  The aggregation size is padded to a power of two
+	/// like by the prover, and the result is only meaningful when it is no
+	/// larger than \\(n \cdot m\\); otherwise `0` is returned.
+	pub fn n_from_proof(&self, m: usize) -> usize {
+		let lg_nm = self.ipp_proof.L_vec.len();
+		if m == 0 || lg_nm >= 32 {
+			return 0;
+		}
+		(1 << lg_nm) / m.next_power_of_two()
+	}
+
+	/// Serializes the proof into a 6-byte header followed by a byte array
+	/// of \\(2 \lg n + 9\\) 32-byte elements, where \\(n\\) is the
+	/// number of secret bits.
 	///
 	/// # Layout
 	///
 	/// The layout of the range proof encoding is:
 	///
+	/// * a version byte, currently `1`,
+	/// * the bitsize \\(n\\) of the ranges as a byte, or `0` if it is unknown,
+	/// * the number \\(m\\) of values as a 32-bit little-endian integer, or `0`
+	///   if it is unknown,
 	/// * four compressed Ristretto points \\(A,S,T_1,T_2\\),
 	/// * three scalars \\(t_x, \tilde{t}_x, \tilde{e}\\),
 	/// * \\(n\\) pairs of compressed Ristretto points
 	///   \\(L_0,R_0\dots,L_{n-1},R_{n-1}\\),
 	/// * two scalars \\(a, b\\).
+	///
+	/// The encoding without the header is parsed by
+	/// [`RangeProof::from_bytes_legacy`].
 	pub fn to_bytes(&self) -> Vec<u8> {
 		// 7 elements: points A, S, T1, T2, scalars tx, tx_bl, e_bl.
-		let mut buf = Vec::with_capacity(7 * 32 + self.ipp_proof.serialized_size());
+		let mut buf = Vec::with_capacity(HEADER_SIZE + 7 * 32 + self.ipp_proof.serialized_size());
+		buf.extend_from_slice(&self.header());
 		buf.extend_from_slice(self.A.as_bytes());
 		buf.extend_from_slice(self.S.as_bytes());
 		buf.extend_from_slice(self.T_1.as_bytes());
@@ -462,10 +1399,105 @@ impl RangeProof {
 		buf
 	}
 
-	/// Deserializes the proof from a byte slice.
+	/// Writes the serialized proof to `writer` without building the
+	/// whole encoding in memory.
+	///
+	/// The bytes written are exactly those of [`RangeProof::to_bytes`].
+	/// Returns the number of bytes written.
+	#[cfg(feature = "std")]
+	pub fn write_to<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<usize> {
+		writer.write_all(&self.header())?;
+		writer.write_all(self.A.as_bytes())?;
+		writer.write_all(self.S.as_bytes())?;
+		writer.write_all(self.T_1.as_bytes())?;
+		writer.write_all(self.T_2.as_bytes())?;
+		writer.write_all(self.t_x.as_bytes())?;
+		writer.write_all(self.t_x_blinding.as_bytes())?;
+		writer.write_all(self.e_blinding.as_bytes())?;
+		Ok(HEADER_SIZE + 7 * 32 + self.ipp_proof.write_to(writer)?)
+	}
+
+	/// Encodes the version, the bitsize and the number of values.
+	fn header(&self) -> [u8; HEADER_SIZE] {
+		let mut header = [0u8; HEADER_SIZE];
+		header[0] = ENCODING_VERSION;
+		header[1] = self.n as u8;
+		header[2..].copy_from_slice(&(self.m as u32).to_le_bytes());
+		header
+	}
+
+	/// Reads a proof in the [`RangeProof::to_bytes`] format from `reader`,
+	/// consuming it to the end.
+	///
+	/// Returns an error if reading fails, if more than `max_size` bytes are
+	/// available, or if the bytes cannot be parsed into a `RangeProof`.
+	#[cfg(feature = "std")]
+	pub fn read_from<R: std::io::Read>(reader: R, max_size: usize) -> Result<RangeProof, ProofError> {
+		let buf = util::read_bounded(reader, max_size).ok_or(ProofError::FormatError)?;
+		RangeProof::from_bytes(&buf)
+	}
+
+	/// Writes the serialized proof to `writer`, prefixed with its length as
+	/// a little-endian `u32`, so that it can be embedded in a larger
+	/// message and read back with [`RangeProof::read_framed`].
+	///
+	/// Returns the number of bytes written, including the prefix.
+	#[cfg(feature = "std")]
+	pub fn write_framed<W: std::io::Write>(&self, writer: W) -> std::io::Result<usize> {
+		let len = HEADER_SIZE + 7 * 32 + self.ipp_proof.serialized_size();
+		util::write_framed(writer, len, |writer| self.write_to(writer))
+	}
+
+	/// Reads a proof written by [`RangeProof::write_framed`] from `reader`,
+	/// consuming only the bytes of the frame.
+	///
+	/// Returns an error if reading fails or the input ends early, if the
+	/// frame is longer than `max_size` bytes, or if its content cannot be
+	/// parsed into a `RangeProof`.
+	#[cfg(feature = "std")]
+	pub fn read_framed<R: std::io::Read>(reader: R, max_size: usize) -> Result<RangeProof, ProofError> {
+		let buf = util::read_framed(reader, max_size).ok_or(ProofError::FormatError)?;
+		RangeProof::from_bytes(&buf)
+	}
+
+	/// Deserializes the proof from a byte slice in the
+	/// [`RangeProof::to_bytes`] format.
 	///
-	/// Returns an error if the byte slice cannot be parsed into a `RangeProof`.
+	/// Returns an error if the byte slice cannot be parsed into a
+	/// `RangeProof`, or if the bitsize and the number of values of the
+	/// header do not match the size of the inner-product proof.
 	pub fn from_bytes(slice: &[u8]) -> Result<RangeProof, ProofError> {
+		if slice.len() < HEADER_SIZE || slice[0] != ENCODING_VERSION {
+			return Err(ProofError::FormatError);
+		}
+		let n = slice[1] as usize;
+		let mut m_bytes = [0u8; 4];
+		m_bytes.copy_from_slice(&slice[2..HEADER_SIZE]);
+		let m = u32::from_le_bytes(m_bytes) as usize;
+
+		let mut proof = RangeProof::from_bytes_legacy(&slice[HEADER_SIZE..])?;
+		if n != 0 {
+			if n > 64 || m == 0 {
+				return Err(ProofError::FormatError);
+			}
+			let lg_nm = n.next_power_of_two().trailing_zeros() + m.next_power_of_two().trailing_zeros();
+			if proof.ipp_proof.L_vec.len() != lg_nm as usize {
+				return Err(ProofError::FormatError);
+			}
+		}
+		proof.n = n;
+		proof.m = m;
+		Ok(proof)
+	}
+
+	/// Deserializes the proof from a byte slice in the format of
+	/// [`RangeProof::to_bytes`] without its header, as produced by earlier
+	/// versions.
+	///
+	/// The bitsize and the number of values of the proof are unknown.
+	/// Returns an error if the byte slice cannot be parsed into a
+	/// `RangeProof`.
+	pub fn from_bytes_legacy(slice: &[u8]) -> Result<RangeProof, ProofError> {
 		if slice.len() % 32 != 0 {
 			return Err(ProofError::FormatError);
 		}
@@ -495,10 +1527,34 @@ impl RangeProof {
 			t_x_blinding,
 			e_blinding,
 			ipp_proof,
+			n: 0,
+			m: 0,
 		})
 	}
 }
 
+/// Returns the size in bytes of the serialization of an aggregated
+/// [`RangeProof`] for `m_parties` values of `n_bits` bits, as produced by
+/// [`RangeProof::to_bytes`].
+pub fn range_proof_size(n_bits: usize, m_parties: usize) -> usize {
+	// The prover pads the bitsize and the number of values separately.
+	let nm = n_bits.next_power_of_two() * m_parties.next_power_of_two();
+	let lg_nm = nm.trailing_zeros() as usize;
+	// 7 elements: points A, S, T1, T2, scalars tx, tx_bl, e_bl.
+	HEADER_SIZE + 7 * 32 + InnerProductProof::serialized_size_for_rounds(lg_nm)
+}
+
+/// Returns the number of points in the multiscalar multiplication
+/// performed to verify an aggregated [`RangeProof`] for `m_parties`
+/// values of `n_bits` bits.
+pub fn range_proof_verification_msm_size(n_bits: usize, m_parties: usize) -> usize {
+	let padded_m = m_parties.next_power_of_two();
+	let nm = n_bits.next_power_of_two() * padded_m;
+	let lg_nm = nm.trailing_zeros() as usize;
+	// A, S, T1, T2 + L, R + B, B_blinding + G, H + V, including padding
+	4 + 2 * lg_nm + 2 + 2 * nm + padded_m
+}
+
 #[cfg(feature = "std")]
 impl Serialize for RangeProof {
 	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -542,17 +1598,170 @@ impl<'de> Deserialize<'de> for RangeProof {
 	}
 }
 
+/// Builds the RNG of a range proof prover from the `transcript`, rekeyed
+/// with the `openings` of the values before being finalized with `rng`.
+///
+/// The nonces drawn from it are then distinct for distinct witnesses, and
+/// remain secret, even if `rng` is weak or broken.
+pub(crate) fn witness_rng<I, T>(transcript: &Transcript, openings: I, rng: &mut T) -> TranscriptRng
+where
+	I: Iterator<Item = (u64, Scalar)>,
+	T: RngCore + CryptoRng,
+{
+	let mut builder = transcript.build_rng();
+	for (v, v_blinding) in openings {
+		builder = builder
+			.rekey_with_witness_bytes(b"v", &v.to_le_bytes())
+			.rekey_with_witness_bytes(b"v_blinding", v_blinding.as_bytes());
+	}
+	builder.finalize(rng)
+}
+
+/// Checks that proofs for `m` values of `n` bits can be verified with
+/// the generators.
+pub(crate) fn check_parameters(bp_gens: &BulletproofGens, n: usize, m: usize) -> Result<(), ProofError> {
+	if !(1..=64).contains(&n) {
+		return Err(ProofError::InvalidBitsize);
+	}
+	if m == 0 {
+		return Err(ProofError::InvalidAggregation);
+	}
+	let required = BulletproofGens::required_capacity_for_range(n, m);
+	if bp_gens.gens_capacity < required.gens_capacity {
+		return Err(ProofError::InvalidGeneratorsLength {
+			required: required.gens_capacity,
+			actual: bp_gens.gens_capacity,
+		});
+	}
+	if bp_gens.party_capacity < required.party_capacity {
+		return Err(ProofError::InvalidGeneratorsLength {
+			required: required.party_capacity,
+			actual: bp_gens.party_capacity,
+		});
+	}
+	Ok(())
+}
+
+/// The verification equation of a rangeproof for `m` values of `n` bits,
+/// which holds when the multiscalar multiplication of its scalars and
+/// points is the identity.
+struct VerificationEquation {
+	n: usize,
+	m: usize,
+	/// Points from the proof and the value commitments.
+	points: Vec<RistrettoPoint>,
+	/// Scalars for `points`.
+	point_scalars: Vec<Scalar>,
+	/// Scalar for the Pedersen base `B`.
+	B_scalar: Scalar,
+	/// Scalar for the Pedersen base `B_blinding`.
+	B_blinding_scalar: Scalar,
+	/// Scalars for the \\(\mathbf G\\) generators.
+	g_scalars: Vec<Scalar>,
+	/// Scalars for the \\(\mathbf H\\) generators.
+	h_scalars: Vec<Scalar>,
+}
+
+impl VerificationEquation {
+	/// Converts the equation into a statement of a [`VerificationBatch`].
+	fn into_statement(self) -> Statement {
+		Statement {
+			n: self.n,
+			m: self.m,
+			points: self.points,
+			point_scalars: self.point_scalars,
+			B_scalar: self.B_scalar,
+			B_blinding_scalar: self.B_blinding_scalar,
+			g_scalars: self.g_scalars,
+			h_scalars: self.h_scalars,
+		}
+	}
+
+	/// Converts the equation into [`VerificationTerms`], identifying each
+	/// point by its role in the proof.  The padding commitments are
+	/// identified like the value commitments.
+	fn into_terms(self) -> VerificationTerms {
+		let lg_nm = (self.n * self.m).trailing_zeros() as usize;
+		let commitments = self.points.len() - 4 - 2 * lg_nm;
+		let point_ids = [b"A" as &[u8], b"S", b"T_1", b"T_2"]
+			.iter()
+			.map(|label| TermPoint::Proof(*label))
+			.chain((0..lg_nm).map(TermPoint::L))
+			.chain((0..lg_nm).map(TermPoint::R))
+			.chain((0..commitments).map(TermPoint::Commitment))
+			.collect();
+		VerificationTerms::new(self.into_statement(), point_ids)
+	}
+
+	/// Checks the equation with a single multiscalar multiplication.
+	fn check(
+		self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		backend: &dyn MsmBackend,
+	) -> Result<(), ProofError> {
+		let mut scalars = self.point_scalars;
+		scalars.push(self.B_scalar);
+		scalars.push(self.B_blinding_scalar);
+		scalars.extend_from_slice(&self.g_scalars);
+		scalars.extend_from_slice(&self.h_scalars);
+
+		let mut points = self.points;
+		points.push(pc_gens.B);
+		points.push(pc_gens.B_blinding);
+		points.extend(bp_gens.G(self.n, self.m));
+		points.extend(bp_gens.H(self.n, self.m));
+
+		let mega_check = in_span!("range_proof_multiexp", size = points.len(), {
+			backend.vartime_msm(&scalars, &points)
+		});
+
+		if mega_check.is_identity() {
+			Ok(())
+		} else {
+			Err(ProofError::VerificationError)
+		}
+	}
+}
+
+/// Computes the values of
+/// \\(z^0 \cdot \mathbf{2}^{n_0} || z^1 \cdot \mathbf{2}^{n_1} || \ldots ||
+/// z^{m-1} \cdot \mathbf{2}^{n_{m-1}}\\) for the `ranges` of bitsize
+/// \\(n_j\\), each followed by zeroes up to the number of positions of the
+/// range, and then by zeroes up to `padded_n`.
+fn concat_z_and_2(ranges: &[(usize, usize)], padded_n: usize, z: &Scalar) -> Vec<Scalar> {
+	let max_n = ranges.iter().map(|&(n_j, _)| n_j).max().unwrap_or(0);
+	let powers_of_2: Vec<Scalar> = util::exp_iter(Scalar::from(2u64)).take(max_n).collect();
+	ranges
+		.iter()
+		.zip(util::exp_iter(*z))
+		.flat_map(|(&(n_j, positions), exp_z)| {
+			powers_of_2[..n_j]
+				.iter()
+				.map(move |exp_2| exp_2 * exp_z)
+				.chain(iter::repeat(Scalar::zero()).take(positions - n_j))
+		})
+		.chain(iter::repeat(Scalar::zero()))
+		.take(padded_n)
+		.collect()
+}
+
 /// Compute
 /// \\[
 /// \delta(y,z) = (z - z^{2}) \langle \mathbf{1}, {\mathbf{y}}^{n \cdot m}
-/// \rangle - \sum_{j=0}^{m-1} z^{j+3} \cdot \langle \mathbf{1}, {\mathbf{2}}^{n
-/// \cdot m} \rangle \\]
-fn delta(n: usize, m: usize, y: &Scalar, z: &Scalar) -> Scalar {
-	let sum_y = util::sum_of_powers(y, n * m);
-	let sum_2 = util::sum_of_powers(&Scalar::from(2u64), n);
-	let sum_z = util::sum_of_powers(z, m);
-
-	(z - z * z) * sum_y - z * z * z * sum_2 * sum_z
+/// \rangle - \sum_{j=0}^{m-1} z^{j+3} \cdot \langle \mathbf{1},
+/// {\mathbf{2}}^{n_j} \rangle \\]
+/// where the bits of the `ranges` of bitsize \\(n_j\\) are padded to
+/// \\(n \cdot m\\) = `padded_n` bits.
+fn delta(ranges: &[(usize, usize)], padded_n: usize, y: &Scalar, z: &Scalar) -> Scalar {
+	let sum_y = util::sum_of_powers(y, padded_n);
+	let sum_z_2: Scalar = ranges
+		.iter()
+		.zip(util::exp_iter(*z))
+		.map(|(&(n_j, _), exp_z)| exp_z * util::sum_of_powers(&Scalar::from(2u64), n_j))
+		.sum();
+
+	(z - z * z) * sum_y - z * z * z * sum_z_2
 }
 
 #[cfg(test)]
@@ -584,7 +1793,7 @@ mod tests {
 			exp_2 = exp_2 + exp_2; // 2^i -> 2^(i+1)
 		}
 
-		assert_eq!(power_g, delta(n, 1, &y, &z),);
+		assert_eq!(power_g, delta(&[(n, n)], n, &y, &z),);
 	}
 
 	/// Given a bitsize `n`, test the following:
@@ -681,6 +1890,32 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn getters_and_n_from_proof() {
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(64, 4);
+		let mut rng = rand::thread_rng();
+
+		let values = [1u64, 2, 3, 4];
+		let blindings: Vec<Scalar> = (0..4).map(|_| Scalar::random(&mut rng)).collect();
+		let mut transcript = Transcript::new(b"RangeProofGettersTest");
+		let (proof, _) =
+			RangeProof::prove_multiple(&bp_gens, &pc_gens, &mut transcript, &values, &blindings, 32).unwrap();
+
+		let bytes = proof.to_bytes();
+		assert_eq!(bytes[..HEADER_SIZE], [1, 32, 4, 0, 0, 0]);
+		let bytes = &bytes[HEADER_SIZE..];
+		assert_eq!(proof.A().as_bytes()[..], bytes[0..32]);
+		assert_eq!(proof.S().as_bytes()[..], bytes[32..64]);
+		assert_eq!(proof.T_1().as_bytes()[..], bytes[64..96]);
+		assert_eq!(proof.T_2().as_bytes()[..], bytes[96..128]);
+		assert_eq!(proof.ipp_proof().to_bytes()[..], bytes[7 * 32..]);
+
+		assert_eq!(proof.n_from_proof(4), 32);
+		assert_eq!(proof.n_from_proof(2), 64);
+		assert_eq!(proof.n_from_proof(0), 0);
+	}
+
 	#[test]
 	fn create_and_verify_n_32_m_4() {
 		singleparty_create_and_verify_helper(32, 4);
@@ -784,6 +2019,126 @@ mod tests {
 		}
 	}
 
+	/// Runs the protocol with four honest parties, after which the messages
+	/// of the parties are tampered with before the dealer receives them,
+	/// and returns the error of the dealer.
+	fn tampered_aggregation_error(
+		tamper_bit_commitments: impl Fn(&mut [messages::BitCommitment]),
+		tamper_poly_commitments: impl Fn(&mut [messages::PolyCommitment]),
+		tamper_shares: impl Fn(&mut [messages::ProofShare]),
+	) -> crate::errors::MPCError {
+		use self::{dealer::*, party::*};
+
+		let m = 4;
+		let n = 16;
+
+		let pc_gens = PedersenGens::default();
+		let bp_gens = BulletproofGens::new(n, m);
+
+		let mut rng = rand::thread_rng();
+		let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+
+		let dealer = Dealer::new(&bp_gens, &pc_gens, &mut transcript, n, m).unwrap();
+
+		let (parties, mut bit_commitments): (Vec<_>, Vec<_>) = (0..m)
+			.map(|j| {
+				Party::new(&bp_gens, &pc_gens, j as u64, Scalar::random(&mut rng), n)
+					.unwrap()
+					.assign_position(j)
+					.unwrap()
+			})
+			.unzip();
+		tamper_bit_commitments(&mut bit_commitments);
+		let (dealer, bit_challenge) = match dealer.receive_bit_commitments(bit_commitments) {
+			Ok(next) => next,
+			Err(e) => return e,
+		};
+
+		let (parties, mut poly_commitments): (Vec<_>, Vec<_>) =
+			parties.into_iter().map(|p| p.apply_challenge(&bit_challenge)).unzip();
+		tamper_poly_commitments(&mut poly_commitments);
+		let (dealer, poly_challenge) = match dealer.receive_poly_commitments(poly_commitments) {
+			Ok(next) => next,
+			Err(e) => return e,
+		};
+
+		let mut shares: Vec<_> = parties
+			.into_iter()
+			.map(|p| p.apply_challenge(&poly_challenge).unwrap())
+			.collect();
+		tamper_shares(&mut shares);
+		dealer
+			.receive_shares(&shares)
+			.expect_err("The messages were malformed, but it was not detected")
+	}
+
+	#[test]
+	fn identify_party_sending_garbage() {
+		use crate::errors::MPCError;
+		use curve25519_dalek::traits::Identity;
+
+		// A value commitment which is not a valid point
+		assert_eq!(
+			tampered_aggregation_error(|bc| bc[2].V_j = CompressedRistretto([0xff; 32]), |_| {}, |_| {}),
+			MPCError::MalformedBitCommitments {
+				bad_commitments: vec![2]
+			}
+		);
+		// Bit blindings which cancel out
+		assert_eq!(
+			tampered_aggregation_error(|bc| bc[2].S_j = RistrettoPoint::identity(), |_| {}, |_| {}),
+			MPCError::MalformedBitCommitments {
+				bad_commitments: vec![2]
+			}
+		);
+		// A commitment to t_2 which cancels out
+		assert_eq!(
+			tampered_aggregation_error(|_| {}, |pc| pc[2].T_2_j = RistrettoPoint::identity(), |_| {}),
+			MPCError::MalformedPolyCommitments {
+				bad_commitments: vec![2]
+			}
+		);
+		// A commitment to t_1 of another party
+		assert_eq!(
+			tampered_aggregation_error(|_| {}, |pc| pc[2].T_1_j = pc[1].T_1_j, |_| {}),
+			MPCError::MalformedProofShares { bad_shares: vec![2] }
+		);
+		// An evaluation of t(x) inconsistent with the vectors
+		assert_eq!(
+			tampered_aggregation_error(|_| {}, |_| {}, |ps| ps[2].t_x += Scalar::one()),
+			MPCError::MalformedProofShares { bad_shares: vec![2] }
+		);
+		// The share of another party
+		assert_eq!(
+			tampered_aggregation_error(|_| {}, |_| {}, |ps| ps[2] = ps[1].clone()),
+			MPCError::MalformedProofShares { bad_shares: vec![2] }
+		);
+		// Vectors of the wrong size
+		assert_eq!(
+			tampered_aggregation_error(
+				|_| {},
+				|_| {},
+				|ps| {
+					ps[2].l_vec.truncate(8);
+					ps[2].r_vec.truncate(8);
+				}
+			),
+			MPCError::MalformedProofShares { bad_shares: vec![2] }
+		);
+		// Every malformed share is identified, whichever check it fails
+		assert_eq!(
+			tampered_aggregation_error(
+				|_| {},
+				|_| {},
+				|ps| {
+					ps[0].e_blinding += Scalar::one();
+					ps[2].l_vec.pop();
+				}
+			),
+			MPCError::MalformedProofShares { bad_shares: vec![0, 2] }
+		);
+	}
+
 	#[test]
 	fn detect_dishonest_dealer_during_aggregation() {
 		use self::{dealer::*, party::*};
@@ -46,11 +46,15 @@ impl Party {
 		v_blinding: Scalar,
 		n: usize,
 	) -> Result<PartyAwaitingPosition<'a>, MPCError> {
-		if !(n == 8 || n == 16 || n == 32 || n == 64) {
+		if !(1..=64).contains(&n) {
 			return Err(MPCError::InvalidBitsize);
 		}
-		if bp_gens.gens_capacity < n {
-			return Err(MPCError::InvalidGeneratorsLength);
+		let required = BulletproofGens::required_capacity_for_range(n, 1);
+		if bp_gens.gens_capacity < required.gens_capacity {
+			return Err(MPCError::InvalidGeneratorsLength {
+				required: required.gens_capacity,
+				actual: bp_gens.gens_capacity,
+			});
 		}
 
 		let V = pc_gens.commit(v.into(), v_blinding).compress();
@@ -92,37 +96,67 @@ impl<'a> PartyAwaitingPosition<'a> {
 		rng: &mut T,
 	) -> Result<(PartyAwaitingBitChallenge<'a>, BitCommitment), MPCError> {
 		if self.bp_gens.party_capacity <= j {
-			return Err(MPCError::InvalidGeneratorsLength);
+			return Err(MPCError::InvalidGeneratorsLength {
+				required: j + 1,
+				actual: self.bp_gens.party_capacity,
+			});
+		}
+
+		let nonces = BitNonces::random(self.n, rng);
+		self.assign_position_with_nonces(j, nonces)
+	}
+
+	/// Assigns a position in the aggregated proof to this party, committing
+	/// to the bits of their value with the given `nonces`.
+	///
+	/// This does all the work of [`assign_position_with_rng`], which only
+	/// draws the nonces first, so that the commitments of several parties
+	/// can be computed in parallel without changing the order in which the
+	/// nonces are drawn.
+	///
+	/// [`assign_position_with_rng`]: PartyAwaitingPosition::assign_position_with_rng
+	pub(crate) fn assign_position_with_nonces(
+		self,
+		j: usize,
+		mut nonces: BitNonces,
+	) -> Result<(PartyAwaitingBitChallenge<'a>, BitCommitment), MPCError> {
+		if self.bp_gens.party_capacity <= j {
+			return Err(MPCError::InvalidGeneratorsLength {
+				required: j + 1,
+				actual: self.bp_gens.party_capacity,
+			});
 		}
 
 		let bp_share = self.bp_gens.share(j);
+		let a_blinding = nonces.a_blinding;
+		let s_blinding = nonces.s_blinding;
+		let s_L = core::mem::take(&mut nonces.s_L);
+		let s_R = core::mem::take(&mut nonces.s_R);
 
-		let a_blinding = Scalar::random(rng);
 		// Compute A = <a_L, G> + <a_R, H> + a_blinding * B_blinding
 		let mut A = self.pc_gens.B_blinding * a_blinding;
 
+		// The bits of the value are padded with zero bits up to a power of two.
+		let padded_n = self.n.next_power_of_two();
+
 		use subtle::{Choice, ConditionallySelectable};
 		let mut i = 0;
-		for (G_i, H_i) in bp_share.G(self.n).zip(bp_share.H(self.n)) {
+		for (G_i, H_i) in bp_share.G(padded_n).zip(bp_share.H(padded_n)) {
 			// If v_i = 0, we add a_L[i] * G[i] + a_R[i] * H[i] = - H[i]
 			// If v_i = 1, we add a_L[i] * G[i] + a_R[i] * H[i] =   G[i]
-			let v_i = Choice::from(((self.v >> i) & 1) as u8);
+			let v_i = Choice::from((((self.v >> i) & 1) as u8) & ((i < self.n) as u8));
 			let mut point = -H_i;
 			point.conditional_assign(G_i, v_i);
 			A += point;
 			i += 1;
 		}
 
-		let s_blinding = Scalar::random(rng);
-		let s_L: Vec<Scalar> = (0..self.n).map(|_| Scalar::random(rng)).collect();
-		let s_R: Vec<Scalar> = (0..self.n).map(|_| Scalar::random(rng)).collect();
-
 		// Compute S = <s_L, G> + <s_R, H> + s_blinding * B_blinding
 		let S = RistrettoPoint::multiscalar_mul(
 			iter::once(&s_blinding).chain(s_L.iter()).chain(s_R.iter()),
 			iter::once(&self.pc_gens.B_blinding)
-				.chain(bp_share.G(self.n))
-				.chain(bp_share.H(self.n)),
+				.chain(bp_share.G(padded_n))
+				.chain(bp_share.H(padded_n)),
 		);
 
 		// Return next state and all commitments
@@ -146,6 +180,45 @@ impl<'a> PartyAwaitingPosition<'a> {
 	}
 }
 
+/// The blinding factors a party draws to commit to the bits of its value.
+pub(crate) struct BitNonces {
+	a_blinding: Scalar,
+	s_blinding: Scalar,
+	s_L: Vec<Scalar>,
+	s_R: Vec<Scalar>,
+}
+
+impl BitNonces {
+	/// Draws the blinding factors for an `n`-bit value from `rng`.
+	pub(crate) fn random<T: RngCore + CryptoRng>(n: usize, rng: &mut T) -> BitNonces {
+		let padded_n = n.next_power_of_two();
+		let a_blinding = Scalar::random(rng);
+		let s_blinding = Scalar::random(rng);
+		let s_L = (0..padded_n).map(|_| Scalar::random(rng)).collect();
+		let s_R = (0..padded_n).map(|_| Scalar::random(rng)).collect();
+		BitNonces {
+			a_blinding,
+			s_blinding,
+			s_L,
+			s_R,
+		}
+	}
+}
+
+/// Overwrite secrets with null bytes when they go out of scope.
+impl Drop for BitNonces {
+	fn drop(&mut self) {
+		self.a_blinding.clear();
+		self.s_blinding.clear();
+		for e in self.s_L.iter_mut() {
+			e.clear();
+		}
+		for e in self.s_R.iter_mut() {
+			e.clear();
+		}
+	}
+}
+
 /// Overwrite secrets with null bytes when they go out of scope.
 impl<'a> Drop for PartyAwaitingPosition<'a> {
 	fn drop(&mut self) {
@@ -183,7 +256,21 @@ impl<'a> PartyAwaitingBitChallenge<'a> {
 		vc: &BitChallenge,
 		rng: &mut T,
 	) -> (PartyAwaitingPolyChallenge, PolyCommitment) {
-		let n = self.n;
+		let t_1_blinding = Scalar::random(rng);
+		let t_2_blinding = Scalar::random(rng);
+		self.apply_challenge_with_blindings(vc, t_1_blinding, t_2_blinding)
+	}
+
+	/// Receive a [`BitChallenge`] from the dealer and use it to
+	/// compute commitments to the party's polynomial coefficients, with the
+	/// given blinding factors for the commitments.
+	pub(crate) fn apply_challenge_with_blindings(
+		self,
+		vc: &BitChallenge,
+		t_1_blinding: Scalar,
+		t_2_blinding: Scalar,
+	) -> (PartyAwaitingPolyChallenge, PolyCommitment) {
+		let n = self.n.next_power_of_two();
 		let offset_y = util::scalar_exp_vartime(&vc.y, (self.j * n) as u64);
 		let offset_z = util::scalar_exp_vartime(&vc.z, self.j as u64);
 
@@ -195,12 +282,17 @@ impl<'a> PartyAwaitingBitChallenge<'a> {
 		let mut exp_y = offset_y; // start at y^j
 		let mut exp_2 = Scalar::one(); // start at 2^0 = 1
 		for i in 0..n {
-			let a_L_i = Scalar::from((self.v >> i) & 1);
+			// The padding bits are zero, and do not contribute to the value.
+			let (a_L_i, exp_2_i) = if i < self.n {
+				(Scalar::from((self.v >> i) & 1), exp_2)
+			} else {
+				(Scalar::zero(), Scalar::zero())
+			};
 			let a_R_i = a_L_i - Scalar::one();
 
 			l_poly.0[i] = a_L_i - vc.z;
 			l_poly.1[i] = self.s_L[i];
-			r_poly.0[i] = exp_y * (a_R_i + vc.z) + offset_zz * exp_2;
+			r_poly.0[i] = exp_y * (a_R_i + vc.z) + offset_zz * exp_2_i;
 			r_poly.1[i] = exp_y * self.s_R[i];
 
 			exp_y *= vc.y; // y^i -> y^(i+1)
@@ -210,8 +302,6 @@ impl<'a> PartyAwaitingBitChallenge<'a> {
 		let t_poly = l_poly.inner_product(&r_poly);
 
 		// Generate x by committing to T_1, T_2 (line 49-54)
-		let t_1_blinding = Scalar::random(rng);
-		let t_2_blinding = Scalar::random(rng);
 		let T_1 = self.pc_gens.commit(t_poly.1, t_1_blinding);
 		let T_2 = self.pc_gens.commit(t_poly.2, t_2_blinding);
 
@@ -0,0 +1,153 @@
+#![allow(non_snake_case)]
+//! Rewindable range proofs, from which the holder of a rewind key can
+//! recover the value, its blinding factor and a short message.
+//!
+//! The nonces of the prover are derived from the rewind key and the
+//! transcript after the value commitment, instead of being drawn at
+//! random.  The value and the message are added to the blinding factor
+//! \\(\alpha\\) of \\(A\\), so that \\(\tilde{e} = \alpha + \rho x\\)
+//! reveals them to whoever can recompute the nonces, and the blinding
+//! factor \\(\gamma\\) of the value is recovered from \\(\tilde{t}_x = z^2
+//! \gamma + \tau_1 x + \tau_2 x^2\\).  Without the key, the nonces are
+//! indistinguishable from random, and the proof is an ordinary range
+//! proof.
+//!
+//! Since the nonces are determined by the key and the state of the
+//! transcript, proving the same value twice from the same transcript state
+//! gives the same proof, rather than two proofs sharing nonces.
+
+use core::convert::TryInto;
+use curve25519_dalek::{ristretto::CompressedRistretto, scalar::Scalar};
+use merlin::Transcript;
+
+use super::{
+	check_parameters,
+	mixed::{prove_ranges, ProverNonces},
+	RangeProof,
+};
+use crate::{
+	errors::ProofError,
+	generators::{BulletproofGens, PedersenGens},
+	transcript::{unrecorded, TranscriptProtocol},
+};
+
+impl RangeProof {
+	/// Create a rangeproof for a value `v` with blinding scalar
+	/// `v_blinding`, from which the value, the blinding scalar and the
+	/// `message` can be recovered with [`RangeProof::rewind_single`] and
+	/// the same `rewind_key`.
+	///
+	/// The proof is verified like any other proof of a single value, with
+	/// [`RangeProof::verify_single`].  It uses no randomness besides the
+	/// `rewind_key`, which must be kept as secret as the value.
+	pub fn prove_single_rewindable(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		v: u64,
+		v_blinding: &Scalar,
+		n: usize,
+		rewind_key: &[u8; 32],
+		message: &[u8; 20],
+	) -> Result<(RangeProof, CompressedRistretto), ProofError> {
+		check_parameters(bp_gens, n, 1)?;
+		let padded_n = n.next_power_of_two();
+
+		enter_span!("range_proof_prove_rewindable", n = n, m = 1);
+
+		transcript.rangeproof_domain_sep(n as u64, 1);
+		let V = pc_gens.commit(v.into(), *v_blinding).compress();
+		transcript.append_point(b"V", &V);
+
+		let mut nonces = rewind_nonces(transcript, rewind_key, padded_n);
+		nonces.a_blinding += embed(v, message);
+
+		let proof = prove_ranges(
+			bp_gens,
+			pc_gens,
+			transcript,
+			&[(v, *v_blinding)],
+			&[(n, padded_n)],
+			padded_n,
+			nonces,
+		);
+		Ok((proof, V))
+	}
+
+	/// Recovers the value, its blinding scalar and the message embedded in a
+	/// proof created with [`RangeProof::prove_single_rewindable`] for the
+	/// commitment \\(V\\) to an `n`-bit value.
+	///
+	/// The `transcript` must be in the same state as the one passed to the
+	/// prover.  Returns [`ProofError::RewindError`] if the proof was not
+	/// created with `rewind_key` for \\(V\\).  Rewinding does not verify
+	/// the proof.
+	pub fn rewind_single(
+		&self,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		V: &CompressedRistretto,
+		n: usize,
+		rewind_key: &[u8; 32],
+	) -> Result<(u64, Scalar, [u8; 20]), ProofError> {
+		if !(1..=64).contains(&n) {
+			return Err(ProofError::InvalidBitsize);
+		}
+
+		transcript.rangeproof_domain_sep(n as u64, 1);
+		transcript.append_point(b"V", V);
+
+		// The vectors of nonces are derived last, and are not needed.
+		let nonces = rewind_nonces(transcript, rewind_key, 0);
+
+		transcript.validate_and_append_point(b"A", &self.A)?;
+		transcript.validate_and_append_point(b"S", &self.S)?;
+
+		let _y = transcript.challenge_scalar(b"y");
+		let z = transcript.challenge_scalar(b"z");
+
+		transcript.validate_and_append_point(b"T_1", &self.T_1)?;
+		transcript.validate_and_append_point(b"T_2", &self.T_2)?;
+
+		let x = transcript.challenge_scalar(b"x");
+
+		let v_blinding = (self.t_x_blinding - nonces.t_1_blinding * x - nonces.t_2_blinding * x * x) * (z * z).invert();
+		let embedded = (self.e_blinding - nonces.a_blinding - nonces.s_blinding * x).to_bytes();
+		if embedded[28..] != [0u8; 4] {
+			return Err(ProofError::RewindError);
+		}
+
+		let v = u64::from_le_bytes(embedded[..8].try_into().unwrap());
+		if pc_gens.commit(v.into(), v_blinding).compress() != *V {
+			return Err(ProofError::RewindError);
+		}
+
+		let mut message = [0u8; 20];
+		message.copy_from_slice(&embedded[8..28]);
+		Ok((v, v_blinding, message))
+	}
+}
+
+/// Derives the nonces of a rewindable proof of `padded_n` bits from the
+/// `rewind_key` and the state of the `transcript`, which is not modified.
+///
+/// The nonces are secret, so their derivation is not recorded on a
+/// transcript tape.
+fn rewind_nonces(transcript: &Transcript, rewind_key: &[u8; 32], padded_n: usize) -> ProverNonces {
+	unrecorded(|| {
+		let mut nonce_transcript = transcript.clone();
+		nonce_transcript.rangeproof_rewind_domain_sep();
+		nonce_transcript.append_message(b"rewind_key", rewind_key);
+		ProverNonces::derive(&mut nonce_transcript, padded_n)
+	})
+}
+
+/// Encodes the value `v` and the `message` as the scalar with the
+/// little-endian bytes of `v`, followed by the `message` and four zero
+/// bytes.
+fn embed(v: u64, message: &[u8; 20]) -> Scalar {
+	let mut bytes = [0u8; 32];
+	bytes[..8].copy_from_slice(&v.to_le_bytes());
+	bytes[8..28].copy_from_slice(message);
+	Scalar::from_bits(bytes)
+}
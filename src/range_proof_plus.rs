@@ -0,0 +1,603 @@
+#![allow(non_snake_case)]
+//! Bulletproofs+ range proofs.
+//!
+//! A [`RangeProofPlus`] proves the same statement as a
+//! [`RangeProof`](::RangeProof), about the same Pedersen commitments, but
+//! replaces the inner-product argument with a zero-knowledge weighted
+//! inner-product argument.  The proof no longer needs the commitments
+//! \\(S, T\_1, T\_2\\) to the blindings and the polynomial \\(t(x)\\), so it
+//! is three points and three scalars shorter: a proof for a single 64-bit
+//! value is 576 bytes instead of 672.
+//!
+//! The protocol follows [Bulletproofs+: Shorter Proofs for Privacy-Enhanced
+//! Distributed Ledger](https://eprint.iacr.org/2020/735), with generator
+//! vectors \\(\mathbf G, \mathbf H\\) from the
+//! [`BulletproofGens`](::BulletproofGens) and the Pedersen bases \\(B,
+//! \widetilde B\\) playing the roles of \\(g, h\\).
+
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate rand;
+
+#[cfg(feature = "std")]
+use self::rand::thread_rng;
+use alloc::vec::Vec;
+
+use core::iter;
+
+use curve25519_dalek::{
+	ristretto::{CompressedRistretto, RistrettoPoint},
+	scalar::Scalar,
+	traits::{Identity, IsIdentity, MultiscalarMul},
+};
+use merlin::Transcript;
+
+use crate::{
+	errors::ProofError,
+	generators::{BulletproofGens, PedersenGens},
+	msm::{DefaultMsmBackend, MsmBackend},
+	range_proof::check_parameters,
+	transcript::TranscriptProtocol,
+	util,
+};
+
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(feature = "std")]
+use serde::de::Visitor;
+#[cfg(feature = "std")]
+use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
+
+/// A Bulletproofs+ proof that one or more values are in a range.
+///
+/// As for a [`RangeProof`](::RangeProof), the commitments to the values and
+/// the bitsize of the range are not included in the proof, and must be
+/// known to the verifier.
+#[derive(Clone, Debug)]
+pub struct RangeProofPlus {
+	/// Commitment to the bits of the values
+	A: CompressedRistretto,
+	/// Commitment to the blinding factors of the last round of the
+	/// weighted inner-product argument
+	A1: CompressedRistretto,
+	/// Commitment to the weighted inner product of those blinding factors
+	B: CompressedRistretto,
+	/// Blinded left vector of the last round
+	r1: Scalar,
+	/// Blinded right vector of the last round
+	s1: Scalar,
+	/// Blinding factor of the last round
+	d1: Scalar,
+	/// Left commitments of the folding rounds
+	L_vec: Vec<CompressedRistretto>,
+	/// Right commitments of the folding rounds
+	R_vec: Vec<CompressedRistretto>,
+}
+
+impl RangeProofPlus {
+	/// Create a Bulletproofs+ rangeproof for a given pair of value `v` and
+	/// blinding scalar `v_blinding`.
+	pub fn prove_single_with_rng<T: RngCore + CryptoRng>(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		v: u64,
+		v_blinding: &Scalar,
+		n: usize,
+		rng: &mut T,
+	) -> Result<(RangeProofPlus, CompressedRistretto), ProofError> {
+		let (p, Vs) =
+			RangeProofPlus::prove_multiple_with_rng(bp_gens, pc_gens, transcript, &[v], &[*v_blinding], n, rng)?;
+		Ok((p, Vs[0]))
+	}
+
+	/// Create a Bulletproofs+ rangeproof for a given pair of value `v` and
+	/// blinding scalar `v_blinding`.
+	/// This is a convenience wrapper around
+	/// [`RangeProofPlus::prove_single_with_rng`], passing in a threadsafe
+	/// RNG.
+	#[cfg(feature = "std")]
+	pub fn prove_single(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		v: u64,
+		v_blinding: &Scalar,
+		n: usize,
+	) -> Result<(RangeProofPlus, CompressedRistretto), ProofError> {
+		RangeProofPlus::prove_single_with_rng(bp_gens, pc_gens, transcript, v, v_blinding, n, &mut thread_rng())
+	}
+
+	/// Create a Bulletproofs+ rangeproof for a set of values.
+	///
+	/// The values are committed to with the Pedersen generators, exactly
+	/// as by [`RangeProof::prove_multiple_with_rng`](::RangeProof::prove_multiple_with_rng),
+	/// and the commitments are returned with the proof.  Any `n` from 1 to
+	/// 64 and any number of values are supported; the generators must have
+	/// capacity for `n` and the number of values rounded up to powers of two.
+	pub fn prove_multiple_with_rng<T: RngCore + CryptoRng>(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		values: &[u64],
+		blindings: &[Scalar],
+		n: usize,
+		rng: &mut T,
+	) -> Result<(RangeProofPlus, Vec<CompressedRistretto>), ProofError> {
+		if values.len() != blindings.len() {
+			return Err(ProofError::WrongNumBlindingFactors);
+		}
+		let m = values.len();
+		check_parameters(bp_gens, n, m)?;
+		let padded_n = n.next_power_of_two();
+		let padded_m = m.next_power_of_two();
+		let nm = padded_n * padded_m;
+
+		transcript.rangeproof_plus_domain_sep(n as u64, m as u64);
+
+		let value_commitments: Vec<CompressedRistretto> = values
+			.iter()
+			.zip(blindings.iter())
+			.map(|(v, v_blinding)| pc_gens.commit(Scalar::from(*v), *v_blinding).compress())
+			.collect();
+		// Pad with commitments to zero with a zero blinding factor.
+		let padding = CompressedRistretto::identity();
+		for V in value_commitments
+			.iter()
+			.chain(iter::repeat(&padding).take(padded_m - m))
+		{
+			transcript.append_point(b"V", V);
+		}
+
+		// The bits of the values, padded with zero bits.
+		let mut a_L: Vec<Scalar> = (0..nm)
+			.map(|k| {
+				let (j, i) = (k / padded_n, k % padded_n);
+				if j < m && i < n {
+					Scalar::from((values[j] >> i) & 1)
+				} else {
+					Scalar::zero()
+				}
+			})
+			.collect();
+		let mut a_R: Vec<Scalar> = a_L.iter().map(|a| a - Scalar::one()).collect();
+
+		let alpha = Scalar::random(rng);
+		let A = RistrettoPoint::multiscalar_mul(
+			iter::once(&alpha).chain(a_L.iter()).chain(a_R.iter()),
+			iter::once(&pc_gens.B_blinding)
+				.chain(bp_gens.G(padded_n, padded_m))
+				.chain(bp_gens.H(padded_n, padded_m)),
+		)
+		.compress();
+		transcript.append_point(b"A", &A);
+
+		let y = transcript.challenge_scalar(b"y");
+		let z = transcript.challenge_scalar(b"z");
+
+		// Powers y^0, ..., y^(nm + 1).
+		let y_powers: Vec<Scalar> = util::exp_iter(y).take(nm + 2).collect();
+		let d = weights(n, padded_n, padded_m, &z);
+
+		let mut a: Vec<Scalar> = a_L.iter().map(|a_i| a_i - z).collect();
+		let mut b: Vec<Scalar> = a_R
+			.iter()
+			.zip(d.iter())
+			.enumerate()
+			.map(|(k, (a_i, d_i))| a_i + d_i * y_powers[nm - k] + z)
+			.collect();
+		let mut alpha = alpha
+			+ y_powers[nm + 1]
+				* util::exp_iter(z * z)
+					.skip(1)
+					.zip(blindings.iter())
+					.map(|(zz_j, gamma_j)| zz_j * gamma_j)
+					.sum::<Scalar>();
+		util::clear_scalars(&mut [&mut a_L, &mut a_R]);
+
+		// The weighted inner-product argument for the statement
+		// A_hat = <a, G> + <b, H> + (a (.)_y b) B + alpha B_blinding.
+		let mut G: Vec<RistrettoPoint> = bp_gens.G(padded_n, padded_m).cloned().collect();
+		let mut H: Vec<RistrettoPoint> = bp_gens.H(padded_n, padded_m).cloned().collect();
+		let lg_nm = nm.trailing_zeros() as usize;
+		let mut L_vec = Vec::with_capacity(lg_nm);
+		let mut R_vec = Vec::with_capacity(lg_nm);
+
+		let mut len = nm;
+		while len > 1 {
+			len /= 2;
+			let (a1, a2) = a.split_at(len);
+			let (b1, b2) = b.split_at(len);
+			let (G1, G2) = G.split_at(len);
+			let (H1, H2) = H.split_at(len);
+			let y_len = y_powers[len];
+			let y_len_inv = y_len.invert();
+
+			let c_L = weighted_inner_product(a1, b2, &y_powers);
+			let c_R = y_len * weighted_inner_product(a2, b1, &y_powers);
+			let d_L = Scalar::random(rng);
+			let d_R = Scalar::random(rng);
+
+			let L = RistrettoPoint::multiscalar_mul(
+				a1.iter()
+					.map(|a_i| a_i * y_len_inv)
+					.chain(b2.iter().cloned())
+					.chain(iter::once(c_L))
+					.chain(iter::once(d_L)),
+				G2.iter()
+					.chain(H1.iter())
+					.chain(iter::once(&pc_gens.B))
+					.chain(iter::once(&pc_gens.B_blinding)),
+			)
+			.compress();
+			let R = RistrettoPoint::multiscalar_mul(
+				a2.iter()
+					.map(|a_i| a_i * y_len)
+					.chain(b1.iter().cloned())
+					.chain(iter::once(c_R))
+					.chain(iter::once(d_R)),
+				G1.iter()
+					.chain(H2.iter())
+					.chain(iter::once(&pc_gens.B))
+					.chain(iter::once(&pc_gens.B_blinding)),
+			)
+			.compress();
+			transcript.append_point(b"L", &L);
+			transcript.append_point(b"R", &R);
+			L_vec.push(L);
+			R_vec.push(R);
+
+			let e = transcript.challenge_scalar(b"e");
+			let e_inv = e.invert();
+
+			let mut a_next: Vec<Scalar> = (0..len).map(|i| e * a1[i] + e_inv * y_len * a2[i]).collect();
+			let mut b_next: Vec<Scalar> = (0..len).map(|i| e_inv * b1[i] + e * b2[i]).collect();
+			let G_next: Vec<RistrettoPoint> = (0..len)
+				.map(|i| RistrettoPoint::multiscalar_mul(&[e_inv, e * y_len_inv], &[G1[i], G2[i]]))
+				.collect();
+			let H_next: Vec<RistrettoPoint> = (0..len)
+				.map(|i| RistrettoPoint::multiscalar_mul(&[e, e_inv], &[H1[i], H2[i]]))
+				.collect();
+			alpha += e * e * d_L + e_inv * e_inv * d_R;
+
+			core::mem::swap(&mut a, &mut a_next);
+			core::mem::swap(&mut b, &mut b_next);
+			util::clear_scalars(&mut [&mut a_next, &mut b_next]);
+			G = G_next;
+			H = H_next;
+		}
+
+		let r = Scalar::random(rng);
+		let s = Scalar::random(rng);
+		let delta = Scalar::random(rng);
+		let eta = Scalar::random(rng);
+
+		let A1 = RistrettoPoint::multiscalar_mul(&[r, s, y * (r * b[0] + s * a[0]), delta], &[
+			G[0],
+			H[0],
+			pc_gens.B,
+			pc_gens.B_blinding,
+		])
+		.compress();
+		let B = RistrettoPoint::multiscalar_mul(&[y * r * s, eta], &[pc_gens.B, pc_gens.B_blinding]).compress();
+		transcript.append_point(b"A1", &A1);
+		transcript.append_point(b"B", &B);
+
+		let e = transcript.challenge_scalar(b"e");
+		let r1 = r + a[0] * e;
+		let s1 = s + b[0] * e;
+		let d1 = eta + delta * e + alpha * e * e;
+		util::clear_scalars(&mut [&mut a, &mut b]);
+
+		let proof = RangeProofPlus {
+			A,
+			A1,
+			B,
+			r1,
+			s1,
+			d1,
+			L_vec,
+			R_vec,
+		};
+		Ok((proof, value_commitments))
+	}
+
+	/// Create a Bulletproofs+ rangeproof for a set of values.
+	/// This is a convenience wrapper around
+	/// [`RangeProofPlus::prove_multiple_with_rng`], passing in a threadsafe
+	/// RNG.
+	#[cfg(feature = "std")]
+	pub fn prove_multiple(
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		values: &[u64],
+		blindings: &[Scalar],
+		n: usize,
+	) -> Result<(RangeProofPlus, Vec<CompressedRistretto>), ProofError> {
+		RangeProofPlus::prove_multiple_with_rng(bp_gens, pc_gens, transcript, values, blindings, n, &mut thread_rng())
+	}
+
+	/// Verifies a Bulletproofs+ rangeproof for a given value commitment
+	/// \\(V\\).
+	///
+	/// This is a convenience wrapper around
+	/// [`RangeProofPlus::verify_multiple`], for the `m=1` case.
+	pub fn verify_single(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		V: &CompressedRistretto,
+		n: usize,
+	) -> Result<(), ProofError> {
+		self.verify_multiple(bp_gens, pc_gens, transcript, &[*V], n)
+	}
+
+	/// Verifies a Bulletproofs+ rangeproof for a set of value commitments
+	/// \\(V_j\\).
+	///
+	/// The whole proof is checked with a single multiscalar
+	/// multiplication, so no randomness is needed.
+	pub fn verify_multiple(
+		&self,
+		bp_gens: &BulletproofGens,
+		pc_gens: &PedersenGens,
+		transcript: &mut Transcript,
+		value_commitments: &[CompressedRistretto],
+		n: usize,
+	) -> Result<(), ProofError> {
+		let m = value_commitments.len();
+		check_parameters(bp_gens, n, m)?;
+		let padded_n = n.next_power_of_two();
+		let padded_m = m.next_power_of_two();
+		let nm = padded_n * padded_m;
+		let lg_nm = nm.trailing_zeros() as usize;
+		if self.L_vec.len() != lg_nm || self.R_vec.len() != lg_nm {
+			return Err(ProofError::FormatError);
+		}
+
+		transcript.rangeproof_plus_domain_sep(n as u64, m as u64);
+
+		let padding = CompressedRistretto::identity();
+		let Vs: Vec<&CompressedRistretto> = value_commitments
+			.iter()
+			.chain(iter::repeat(&padding).take(padded_m - m))
+			.collect();
+		for V in Vs.iter() {
+			transcript.append_point(b"V", V);
+		}
+
+		transcript.validate_and_append_point(b"A", &self.A)?;
+		let y = transcript.challenge_scalar(b"y");
+		let z = transcript.challenge_scalar(b"z");
+
+		let mut challenges = Vec::with_capacity(lg_nm);
+		for (L, R) in self.L_vec.iter().zip(self.R_vec.iter()) {
+			transcript.validate_and_append_point(b"L", L)?;
+			transcript.validate_and_append_point(b"R", R)?;
+			challenges.push(transcript.challenge_scalar(b"e"));
+		}
+
+		transcript.validate_and_append_point(b"A1", &self.A1)?;
+		transcript.validate_and_append_point(b"B", &self.B)?;
+		let e = transcript.challenge_scalar(b"e");
+
+		let mut challenges_inv = challenges.clone();
+		let allinv = Scalar::batch_invert(&mut challenges_inv);
+		let challenges_sq: Vec<Scalar> = challenges.iter().map(|e_k| e_k * e_k).collect();
+		let challenges_inv_sq: Vec<Scalar> = challenges_inv.iter().map(|e_k| e_k * e_k).collect();
+
+		// The scalars s_i by which the folded G_0 and H_0 multiply the
+		// original generators, computed as in the inner-product proof.
+		let mut s = Vec::with_capacity(nm);
+		s.push(allinv);
+		for i in 1..nm {
+			let lg_i = (32 - 1 - (i as u32).leading_zeros()) as usize;
+			let k = 1 << lg_i;
+			s.push(s[i - k] * challenges_sq[(lg_nm - 1) - lg_i]);
+		}
+
+		let y_powers: Vec<Scalar> = util::exp_iter(y).take(nm + 2).collect();
+		let y_inv = y.invert();
+		let d = weights(n, padded_n, padded_m, &z);
+		let zz = z * z;
+		let ee = e * e;
+
+		let sum_y = util::sum_of_powers(&y, nm) * y;
+		let sum_d: Scalar = d.iter().sum();
+		let zeta = (z - zz) * sum_y - z * y_powers[nm + 1] * sum_d;
+
+		let g_scalars = util::exp_iter(y_inv)
+			.zip(s.iter())
+			.map(|(y_inv_i, s_i)| -ee * z - self.r1 * e * y_inv_i * s_i);
+		let h_scalars = d
+			.iter()
+			.zip(s.iter().rev())
+			.enumerate()
+			.map(|(i, (d_i, s_inv_i))| ee * (d_i * y_powers[nm - i] + z) - self.s1 * e * s_inv_i);
+
+		let scalars: Vec<Scalar> = iter::once(ee)
+			.chain(iter::once(e))
+			.chain(iter::once(Scalar::one()))
+			.chain(
+				util::exp_iter(zz)
+					.skip(1)
+					.take(padded_m)
+					.map(|zz_j| ee * y_powers[nm + 1] * zz_j),
+			)
+			.chain(challenges_sq.iter().map(|e_k| ee * e_k))
+			.chain(challenges_inv_sq.iter().map(|e_k| ee * e_k))
+			.chain(iter::once(ee * zeta - self.r1 * y * self.s1))
+			.chain(iter::once(-self.d1))
+			.chain(g_scalars)
+			.chain(h_scalars)
+			.collect();
+
+		let points: Vec<RistrettoPoint> = iter::once(&self.A)
+			.chain(iter::once(&self.A1))
+			.chain(iter::once(&self.B))
+			.chain(Vs)
+			.chain(self.L_vec.iter())
+			.chain(self.R_vec.iter())
+			.map(|p| p.decompress().ok_or(ProofError::VerificationError))
+			.chain(iter::once(Ok(pc_gens.B)))
+			.chain(iter::once(Ok(pc_gens.B_blinding)))
+			.chain(bp_gens.G(padded_n, padded_m).map(|G_i| Ok(*G_i)))
+			.chain(bp_gens.H(padded_n, padded_m).map(|H_i| Ok(*H_i)))
+			.collect::<Result<_, _>>()?;
+
+		let mega_check = in_span!("range_proof_plus_multiexp", size = points.len(), {
+			DefaultMsmBackend.vartime_msm(&scalars, &points)
+		});
+
+		if mega_check.is_identity() {
+			Ok(())
+		} else {
+			Err(ProofError::VerificationError)
+		}
+	}
+
+	/// Returns the size in bytes required to serialize the Bulletproofs+
+	/// rangeproof.
+	pub fn serialized_size(&self) -> usize {
+		6 * 32 + (self.L_vec.len() + self.R_vec.len()) * 32
+	}
+
+	/// Serializes the proof into a byte array of \\(2 \lg n + 6\\) 32-byte
+	/// elements, where \\(n\\) is the number of secret bits.
+	///
+	/// # Layout
+	///
+	/// The layout of the Bulletproofs+ rangeproof encoding is:
+	///
+	/// * three compressed Ristretto points \\(A, A_1, B\\),
+	/// * three scalars \\(r_1, s_1, d_1\\),
+	/// * \\(n\\) pairs of compressed Ristretto points \\(L_0, R_0, \dots,
+	///   L_{n-1}, R_{n-1}\\).
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut buf = Vec::with_capacity(self.serialized_size());
+		buf.extend_from_slice(self.A.as_bytes());
+		buf.extend_from_slice(self.A1.as_bytes());
+		buf.extend_from_slice(self.B.as_bytes());
+		buf.extend_from_slice(self.r1.as_bytes());
+		buf.extend_from_slice(self.s1.as_bytes());
+		buf.extend_from_slice(self.d1.as_bytes());
+		for (L, R) in self.L_vec.iter().zip(self.R_vec.iter()) {
+			buf.extend_from_slice(L.as_bytes());
+			buf.extend_from_slice(R.as_bytes());
+		}
+		buf
+	}
+
+	/// Deserializes the proof from a byte slice.
+	///
+	/// Returns an error if the byte slice cannot be parsed into a
+	/// `RangeProofPlus`.
+	pub fn from_bytes(slice: &[u8]) -> Result<RangeProofPlus, ProofError> {
+		if slice.len() % 32 != 0 || slice.len() < 6 * 32 {
+			return Err(ProofError::FormatError);
+		}
+		let num_elements = slice.len() / 32 - 6;
+		if num_elements % 2 != 0 {
+			return Err(ProofError::FormatError);
+		}
+		let lg_n = num_elements / 2;
+		if lg_n >= 32 {
+			return Err(ProofError::FormatError);
+		}
+
+		use crate::util::read32;
+
+		let A = CompressedRistretto(read32(&slice[0..]));
+		let A1 = CompressedRistretto(read32(&slice[32..]));
+		let B = CompressedRistretto(read32(&slice[2 * 32..]));
+
+		let r1 = Scalar::from_canonical_bytes(read32(&slice[3 * 32..])).ok_or(ProofError::FormatError)?;
+		let s1 = Scalar::from_canonical_bytes(read32(&slice[4 * 32..])).ok_or(ProofError::FormatError)?;
+		let d1 = Scalar::from_canonical_bytes(read32(&slice[5 * 32..])).ok_or(ProofError::FormatError)?;
+
+		let mut L_vec = Vec::with_capacity(lg_n);
+		let mut R_vec = Vec::with_capacity(lg_n);
+		for i in 0..lg_n {
+			let pos = (6 + 2 * i) * 32;
+			L_vec.push(CompressedRistretto(read32(&slice[pos..])));
+			R_vec.push(CompressedRistretto(read32(&slice[pos + 32..])));
+		}
+
+		Ok(RangeProofPlus {
+			A,
+			A1,
+			B,
+			r1,
+			s1,
+			d1,
+			L_vec,
+			R_vec,
+		})
+	}
+}
+
+#[cfg(feature = "std")]
+impl Serialize for RangeProofPlus {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		serializer.serialize_bytes(&self.to_bytes()[..])
+	}
+}
+
+#[cfg(feature = "std")]
+impl<'de> Deserialize<'de> for RangeProofPlus {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		struct RangeProofPlusVisitor;
+
+		impl<'de> Visitor<'de> for RangeProofPlusVisitor {
+			type Value = RangeProofPlus;
+
+			fn expecting(&self, formatter: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+				formatter.write_str("a valid RangeProofPlus")
+			}
+
+			fn visit_bytes<E>(self, v: &[u8]) -> Result<RangeProofPlus, E>
+			where
+				E: serde::de::Error,
+			{
+				RangeProofPlus::from_bytes(v).map_err(serde::de::Error::custom)
+			}
+		}
+
+		deserializer.deserialize_bytes(RangeProofPlusVisitor)
+	}
+}
+
+/// The weights \\(d\_{jn + i} = z^{2(j+1)} 2^i\\) of the bits of the
+/// values, which are zero for the padding bits.
+fn weights(n: usize, padded_n: usize, padded_m: usize, z: &Scalar) -> Vec<Scalar> {
+	let powers_of_2: Vec<Scalar> = util::exp_iter(Scalar::from(2u64)).take(n).collect();
+	util::exp_iter(z * z)
+		.skip(1)
+		.take(padded_m)
+		.flat_map(|zz_j| {
+			powers_of_2
+				.iter()
+				.map(move |exp_2| zz_j * exp_2)
+				.chain(iter::repeat(Scalar::zero()).take(padded_n - n))
+				.collect::<Vec<_>>()
+		})
+		.collect()
+}
+
+/// Computes the inner product of `a` and `b` weighted by the powers
+/// \\(y, y^2, \dots\\) in `y_powers[1..]`.
+fn weighted_inner_product(a: &[Scalar], b: &[Scalar], y_powers: &[Scalar]) -> Scalar {
+	a.iter()
+		.zip(b.iter())
+		.zip(y_powers[1..].iter())
+		.map(|((a_i, b_i), y_i)| a_i * b_i * y_i)
+		.sum()
+}
@@ -0,0 +1,6 @@
+//! Helpers for testing gadgets and applications built on this crate.
+//!
+//! Enabled by the `proptest` feature, which is meant for development
+//! dependencies only.
+
+pub mod strategies;
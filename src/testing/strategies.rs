@@ -0,0 +1,400 @@
+//! [`proptest`] strategies generating random circuits over the
+//! [`ConstraintSystem`] API, together with harnesses checking that their
+//! proofs verify and that tampering with them is rejected.
+//!
+//! A [`RandomCircuit`] is a directed acyclic graph of multipliers over
+//! committed inputs, with linear constraints over the inputs and the
+//! outputs of the multipliers.  [`satisfiable_circuit`] generates a
+//! circuit together with a witness satisfying it, which
+//! [`perturbed_witness`] and [`perturbed_constraint`] turn into an
+//! unsatisfiable statement.  The harnesses return a [`TestCaseError`], to
+//! be used with `?` in the body of a [`proptest!`](proptest::proptest)
+//! test:
+//!
+//! ```ignore
+//! proptest! {
+//!     #[test]
+//!     fn random_circuits_roundtrip((circuit, witness) in satisfiable_circuit(4, 8, 4)) {
+//!         assert_roundtrip(&circuit, &witness)?;
+//!     }
+//! }
+//! ```
+
+use alloc::vec::Vec;
+use curve25519_dalek::{ristretto::CompressedRistretto, scalar::Scalar};
+use merlin::Transcript;
+use proptest::prelude::*;
+use rand::thread_rng;
+
+use crate::{
+	generators::{BulletproofGens, PedersenGens},
+	r1cs::{
+		verify_gadget, AllocatedScalar, ConstraintSystem, Gadget, LinearCombination, Prover, R1CSError, R1CSProof,
+		Variable,
+	},
+};
+
+/// The label of the transcripts of the harnesses.
+const TRANSCRIPT_LABEL: &[u8] = b"RandomCircuitTest";
+
+/// A wire of a [`RandomCircuit`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Wire {
+	/// The constant one.
+	One,
+	/// The committed input of the given index.
+	Input(usize),
+	/// The output of the multiplier of the given index.
+	Output(usize),
+}
+
+/// A linear combination of the wires of a [`RandomCircuit`].
+pub type Terms = Vec<(Wire, Scalar)>;
+
+/// A multiplier of a [`RandomCircuit`].
+///
+/// Its inputs are linear combinations of the inputs of the circuit and of
+/// the outputs of the previous multipliers only.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Multiplier {
+	/// The left input of the multiplier.
+	pub left: Terms,
+	/// The right input of the multiplier.
+	pub right: Terms,
+}
+
+/// A circuit of multipliers and linear constraints over committed inputs,
+/// assembled as a [`Gadget`].
+///
+/// The outputs of the gadget are the outputs of the multipliers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RandomCircuit {
+	/// Number of committed inputs.
+	pub inputs: usize,
+	/// The multipliers, in the order in which they are allocated.
+	pub multipliers: Vec<Multiplier>,
+	/// The linear constraints, each of which must evaluate to zero.
+	pub constraints: Vec<Terms>,
+}
+
+impl RandomCircuit {
+	/// Returns the outputs of the multipliers for the `witness`, which
+	/// assigns the inputs.
+	pub fn outputs(&self, witness: &[Scalar]) -> Vec<Scalar> {
+		let mut outputs = Vec::with_capacity(self.multipliers.len());
+		for multiplier in &self.multipliers {
+			let left = evaluate(&multiplier.left, witness, &outputs);
+			let right = evaluate(&multiplier.right, witness, &outputs);
+			outputs.push(left * right);
+		}
+		outputs
+	}
+
+	/// Returns `true` if the `witness` satisfies every constraint.
+	pub fn is_satisfied(&self, witness: &[Scalar]) -> bool {
+		let outputs = self.outputs(witness);
+		witness.len() == self.inputs
+			&& self
+				.constraints
+				.iter()
+				.all(|constraint| evaluate(constraint, witness, &outputs) == Scalar::zero())
+	}
+
+	/// Returns the circuit with `offset` added to the constant term of the
+	/// constraint of the given `index`.
+	///
+	/// If `offset` is nonzero, no witness satisfies both circuits.
+	pub fn with_offset(&self, index: usize, offset: Scalar) -> RandomCircuit {
+		let mut circuit = self.clone();
+		circuit.constraints[index].push((Wire::One, offset));
+		circuit
+	}
+}
+
+impl Gadget for RandomCircuit {
+	fn assemble<CS: ConstraintSystem>(
+		&self,
+		cs: &mut CS,
+		inputs: &[AllocatedScalar],
+	) -> Result<Vec<AllocatedScalar>, R1CSError> {
+		if inputs.len() != self.inputs {
+			return Err(R1CSError::gadget("the random circuit needs one value per input"));
+		}
+
+		let mut outputs = Vec::with_capacity(self.multipliers.len());
+		for multiplier in &self.multipliers {
+			let left = linear_combination(&multiplier.left, inputs, &outputs);
+			let right = linear_combination(&multiplier.right, inputs, &outputs);
+			let (_, _, output) = cs.multiply(left, right);
+			outputs.push(output);
+		}
+		for constraint in &self.constraints {
+			cs.constrain(linear_combination(constraint, inputs, &outputs));
+		}
+
+		Ok(outputs
+			.into_iter()
+			.map(|variable| AllocatedScalar {
+				variable,
+				assignment: LinearCombination::from(variable).evaluate(cs),
+			})
+			.collect())
+	}
+}
+
+/// Evaluates the `terms` for the `witness` and the `outputs` of the
+/// multipliers.
+fn evaluate(terms: &Terms, witness: &[Scalar], outputs: &[Scalar]) -> Scalar {
+	terms
+		.iter()
+		.map(|&(wire, coefficient)| {
+			coefficient
+				* match wire {
+					Wire::One => Scalar::one(),
+					Wire::Input(i) => witness[i],
+					Wire::Output(i) => outputs[i],
+				}
+		})
+		.sum()
+}
+
+/// Converts the `terms` into a linear combination of the `inputs` and the
+/// `outputs` of the multipliers.
+fn linear_combination(terms: &Terms, inputs: &[AllocatedScalar], outputs: &[Variable]) -> LinearCombination {
+	terms
+		.iter()
+		.map(|&(wire, coefficient)| {
+			let variable = match wire {
+				Wire::One => Variable::One(),
+				Wire::Input(i) => inputs[i].variable,
+				Wire::Output(i) => outputs[i],
+			};
+			(variable, coefficient)
+		})
+		.collect()
+}
+
+/// Generates scalars, either small or uniformly random, so that failing
+/// cases shrink to readable values.
+pub fn scalar() -> impl Strategy<Value = Scalar> {
+	prop_oneof![
+		(0u64..16).prop_map(Scalar::from),
+		any::<[u8; 32]>().prop_map(Scalar::from_bytes_mod_order),
+	]
+}
+
+/// Generates nonzero scalars.
+pub fn nonzero_scalar() -> impl Strategy<Value = Scalar> {
+	scalar().prop_filter("the scalar must be nonzero", |scalar| *scalar != Scalar::zero())
+}
+
+/// Generates wires among the constant, the `inputs` and the `outputs` of
+/// the first multipliers.
+pub fn wire(inputs: usize, outputs: usize) -> impl Strategy<Value = Wire> {
+	(0..=inputs + outputs).prop_map(move |index| match index {
+		0 => Wire::One,
+		i if i <= inputs => Wire::Input(i - 1),
+		i => Wire::Output(i - 1 - inputs),
+	})
+}
+
+/// Generates linear combinations of one to three [`wire`]s.
+pub fn terms(inputs: usize, outputs: usize) -> impl Strategy<Value = Terms> {
+	prop::collection::vec((wire(inputs, outputs), scalar()), 1..=3)
+}
+
+/// Generates `count` multipliers over the `inputs`, each of which may use
+/// the outputs of the previous ones.
+pub fn multipliers(inputs: usize, count: usize) -> impl Strategy<Value = Vec<Multiplier>> {
+	(0..count)
+		.map(|previous| {
+			(terms(inputs, previous), terms(inputs, previous)).prop_map(|(left, right)| Multiplier { left, right })
+		})
+		.collect::<Vec<_>>()
+}
+
+/// Generates circuits of one to `max_inputs` inputs, up to
+/// `max_multipliers` multipliers and `max_constraints` random constraints,
+/// together with a witness satisfying them.
+///
+/// The constant term of every constraint is chosen for the witness to
+/// satisfy it.  A further constraint weighs every input and every output
+/// with a nonzero coefficient, so that most changes to the witness are
+/// caught.
+pub fn satisfiable_circuit(
+	max_inputs: usize,
+	max_multipliers: usize,
+	max_constraints: usize,
+) -> impl Strategy<Value = (RandomCircuit, Vec<Scalar>)> {
+	(1..=max_inputs, 0..=max_multipliers, 0..=max_constraints)
+		.prop_flat_map(|(inputs, count, constraints)| {
+			(
+				prop::collection::vec(scalar(), inputs),
+				multipliers(inputs, count),
+				prop::collection::vec(terms(inputs, count), constraints),
+				prop::collection::vec(nonzero_scalar(), inputs + count),
+			)
+		})
+		.prop_map(|(witness, multipliers, mut constraints, weights)| {
+			let inputs = witness.len();
+			let wires = (0..inputs)
+				.map(Wire::Input)
+				.chain((0..multipliers.len()).map(Wire::Output));
+			constraints.push(wires.zip(weights).collect());
+
+			let mut circuit = RandomCircuit {
+				inputs,
+				multipliers,
+				constraints,
+			};
+			let outputs = circuit.outputs(&witness);
+			for constraint in circuit.constraints.iter_mut() {
+				let value = evaluate(constraint, &witness, &outputs);
+				constraint.push((Wire::One, -value));
+			}
+			(circuit, witness)
+		})
+}
+
+/// Generates witnesses which differ from the `witness` of the `circuit` in
+/// one input, and do not satisfy the `circuit`.
+pub fn perturbed_witness(circuit: RandomCircuit, witness: Vec<Scalar>) -> impl Strategy<Value = Vec<Scalar>> {
+	(0..witness.len(), nonzero_scalar()).prop_filter_map(
+		"the perturbed witness satisfies the circuit",
+		move |(index, offset)| {
+			let mut perturbed = witness.clone();
+			perturbed[index] += offset;
+			if circuit.is_satisfied(&perturbed) {
+				None
+			} else {
+				Some(perturbed)
+			}
+		},
+	)
+}
+
+/// Generates circuits which differ from the `circuit` in the constant term
+/// of one constraint, and which no witness of the `circuit` satisfies.
+///
+/// The `circuit` must have a constraint, as those of
+/// [`satisfiable_circuit`] do.
+pub fn perturbed_constraint(circuit: RandomCircuit) -> impl Strategy<Value = RandomCircuit> {
+	(0..circuit.constraints.len(), nonzero_scalar()).prop_map(move |(index, offset)| circuit.with_offset(index, offset))
+}
+
+/// Returns generators large enough for the `circuit`.
+fn generators(circuit: &RandomCircuit) -> (PedersenGens, BulletproofGens) {
+	let capacity = circuit.multipliers.len().next_power_of_two();
+	(PedersenGens::default(), BulletproofGens::new(capacity, 1))
+}
+
+/// Commits to the `witness` and proves that it satisfies the `circuit`.
+///
+/// The diagnostics of the prover are enabled, so that an unsatisfied
+/// constraint is reported as [`R1CSError::UnsatisfiedConstraint`] instead
+/// of panicking.
+pub fn prove(
+	circuit: &RandomCircuit,
+	witness: &[Scalar],
+	pc_gens: &PedersenGens,
+	bp_gens: &BulletproofGens,
+) -> Result<(R1CSProof, Vec<CompressedRistretto>), R1CSError> {
+	let mut transcript = Transcript::new(TRANSCRIPT_LABEL);
+	let mut prover = Prover::new(pc_gens, &mut transcript);
+	prover.enable_diagnostics();
+
+	let mut rng = thread_rng();
+	let (commitments, inputs): (Vec<_>, Vec<_>) = witness
+		.iter()
+		.map(|value| {
+			let (commitment, variable) = prover.commit(*value, Scalar::random(&mut rng));
+			let input = AllocatedScalar {
+				variable,
+				assignment: Some(*value),
+			};
+			(commitment, input)
+		})
+		.unzip();
+	circuit.assemble(&mut prover, &inputs)?;
+
+	let proof = prover.prove_with_rng(bp_gens, &mut rng)?;
+	Ok((proof, commitments))
+}
+
+/// Verifies that the `proof` shows the values of the `commitments` satisfy
+/// the `circuit`.
+pub fn verify(
+	circuit: &RandomCircuit,
+	commitments: &[CompressedRistretto],
+	proof: &R1CSProof,
+	pc_gens: &PedersenGens,
+	bp_gens: &BulletproofGens,
+) -> Result<(), R1CSError> {
+	let mut transcript = Transcript::new(TRANSCRIPT_LABEL);
+	verify_gadget(circuit, pc_gens, bp_gens, &mut transcript, commitments, proof)
+}
+
+/// Checks that a proof that the `witness` satisfies the `circuit`
+/// verifies.
+pub fn assert_roundtrip(circuit: &RandomCircuit, witness: &[Scalar]) -> Result<(), TestCaseError> {
+	let (pc_gens, bp_gens) = generators(circuit);
+	let (proof, commitments) = prove(circuit, witness, &pc_gens, &bp_gens)
+		.map_err(|error| TestCaseError::fail(format!("proving failed: {}", error)))?;
+	prop_assert_eq!(verify(circuit, &commitments, &proof, &pc_gens, &bp_gens), Ok(()));
+	Ok(())
+}
+
+/// Checks that the `perturbed` witness is rejected: the prover refuses to
+/// prove that it satisfies the `circuit`, and a proof for the `witness`
+/// does not verify against commitments to the `perturbed` witness.
+pub fn assert_rejects_witness(
+	circuit: &RandomCircuit,
+	witness: &[Scalar],
+	perturbed: &[Scalar],
+) -> Result<(), TestCaseError> {
+	let (pc_gens, bp_gens) = generators(circuit);
+	let refused = prove(circuit, perturbed, &pc_gens, &bp_gens);
+	prop_assert!(
+		matches!(refused, Err(R1CSError::UnsatisfiedConstraint { .. })),
+		"the prover did not refuse the perturbed witness: {:?}",
+		refused.map(|_| ())
+	);
+
+	let (proof, _) = prove(circuit, witness, &pc_gens, &bp_gens)
+		.map_err(|error| TestCaseError::fail(format!("proving failed: {}", error)))?;
+	let mut rng = thread_rng();
+	let commitments: Vec<_> = perturbed
+		.iter()
+		.map(|value| pc_gens.commit(*value, Scalar::random(&mut rng)).compress())
+		.collect();
+	prop_assert_eq!(
+		verify(circuit, &commitments, &proof, &pc_gens, &bp_gens),
+		Err(R1CSError::VerificationError)
+	);
+	Ok(())
+}
+
+/// Checks that the `perturbed` circuit is rejected: the prover refuses to
+/// prove that the `witness` of the `circuit` satisfies it, and a proof for
+/// the `circuit` does not verify as a proof for the `perturbed` circuit.
+pub fn assert_rejects_circuit(
+	circuit: &RandomCircuit,
+	perturbed: &RandomCircuit,
+	witness: &[Scalar],
+) -> Result<(), TestCaseError> {
+	let (pc_gens, bp_gens) = generators(circuit);
+	let refused = prove(perturbed, witness, &pc_gens, &bp_gens);
+	prop_assert!(
+		matches!(refused, Err(R1CSError::UnsatisfiedConstraint { .. })),
+		"the prover did not refuse the perturbed circuit: {:?}",
+		refused.map(|_| ())
+	);
+
+	let (proof, commitments) = prove(circuit, witness, &pc_gens, &bp_gens)
+		.map_err(|error| TestCaseError::fail(format!("proving failed: {}", error)))?;
+	prop_assert_eq!(
+		verify(perturbed, &commitments, &proof, &pc_gens, &bp_gens),
+		Err(R1CSError::VerificationError)
+	);
+	Ok(())
+}
@@ -0,0 +1,34 @@
+//! Optional instrumentation of the proving and verification phases.
+//!
+//! With the `tracing` feature, the macros below create `DEBUG`-level
+//! [`tracing`](https://docs.rs/tracing) spans and events.  Without it, they
+//! expand to nothing but the instrumented code, and the field expressions
+//! are not evaluated.
+//!
+//! The fields must only record public sizes: never pass witness values,
+//! blinding factors or challenges to these macros.
+
+/// Enters a span named `$name` until the end of the enclosing block.
+macro_rules! enter_span {
+	($name:literal $(, $field:ident = $value:expr)* $(,)?) => {
+		#[cfg(feature = "tracing")]
+		let _span = ::tracing::debug_span!($name $(, $field = $value)*).entered();
+	};
+}
+
+/// Evaluates `$body` inside a span named `$name`.
+macro_rules! in_span {
+	($name:literal, $($field:ident = $value:expr,)* $body:block) => {{
+		enter_span!($name $(, $field = $value)*);
+		$body
+	}};
+}
+
+/// Emits an event with the message `$message`.
+#[allow(unused_macros)]
+macro_rules! trace_event {
+	($($field:ident = $value:expr,)* $message:literal) => {
+		#[cfg(feature = "tracing")]
+		::tracing::debug!($($field = $value,)* $message);
+	};
+}
@@ -9,11 +9,39 @@ pub trait TranscriptProtocol {
 	/// Append a domain separator for an `n`-bit, `m`-party range proof.
 	fn rangeproof_domain_sep(&mut self, n: u64, m: u64);
 
+	/// Append a domain separator for a range proof of values with the
+	/// given `bit_sizes`.
+	fn rangeproof_mixed_domain_sep(&mut self, bit_sizes: &[usize]);
+
+	/// Append a domain separator for deriving the nonces of a rewindable
+	/// range proof.
+	fn rangeproof_rewind_domain_sep(&mut self);
+
+	/// Append a domain separator for an `n`-bit, `m`-party Bulletproofs+
+	/// range proof.
+	fn rangeproof_plus_domain_sep(&mut self, n: u64, m: u64);
+
 	/// Append a domain separator for a length-`n` inner product proof.
 	fn innerproduct_domain_sep(&mut self, n: u64);
 
-	/// Append a domain separator for a constraint system.
-	fn r1cs_domain_sep(&mut self);
+	/// Append a domain separator for a length-`n` linear proof.
+	fn linear_proof_domain_sep(&mut self, n: u64);
+
+	/// Append a domain separator for a constraint system of the circuit
+	/// `label`, which is empty for unlabeled constraint systems.
+	fn r1cs_domain_sep(&mut self, label: &[u8]);
+
+	/// Append the number of multipliers `n` and constraints `q` of a
+	/// constraint system, before its challenges `y` and `z` are drawn.
+	fn r1cs_circuit_size(&mut self, n: u64, q: u64);
+
+	/// Append the Pedersen bases `base` and `blinding_base` of the commitments
+	/// of a constraint system.
+	fn r1cs_pedersen_gens(&mut self, base: &CompressedRistretto, blinding_base: &CompressedRistretto);
+
+	/// Append a domain separator for a constraint system proved by `m`
+	/// parties with `n` multipliers each.
+	fn r1cs_aggregation_domain_sep(&mut self, n: u64, m: u64);
 
 	/// Commit a domain separator for a CS without randomized constraints.
 	fn r1cs_1phase_domain_sep(&mut self);
@@ -21,6 +49,33 @@ pub trait TranscriptProtocol {
 	/// Commit a domain separator for a CS with randomized constraints.
 	fn r1cs_2phase_domain_sep(&mut self);
 
+	/// Append the number `n` of challenges drawn with the `label` by a
+	/// constraint system.
+	fn r1cs_challenge_count(&mut self, label: &'static [u8], n: u64);
+
+	/// Append the hash of the circuit of a constraint system, after a
+	/// domain separator.
+	fn r1cs_circuit_hash(&mut self, circuit_hash: &[u8; 32]);
+
+	/// Append a domain separator for entering the namespace `label` of a
+	/// constraint system.
+	fn r1cs_namespace_begin(&mut self, label: &[u8]);
+
+	/// Append a domain separator for leaving the namespace `label` of a
+	/// constraint system.
+	fn r1cs_namespace_end(&mut self, label: &[u8]);
+
+	/// Append a public input `bytes` of a constraint system with the given
+	/// `label`, after a domain separator.
+	fn r1cs_public_input(&mut self, label: &'static [u8], bytes: &[u8]);
+
+	/// Append a domain separator for a proof linking two proofs.
+	fn link_domain_sep(&mut self);
+
+	/// Append a domain separator for a confidential transaction with the
+	/// given `fee` and `inputs`.
+	fn ctx_domain_sep(&mut self, fee: u64, inputs: &[CompressedRistretto]);
+
 	/// Append a `scalar` with the given `label`.
 	fn append_scalar(&mut self, label: &'static [u8], scalar: &Scalar);
 
@@ -41,34 +96,115 @@ pub trait TranscriptProtocol {
 
 impl TranscriptProtocol for Transcript {
 	fn rangeproof_domain_sep(&mut self, n: u64, m: u64) {
-		self.append_message(b"dom-sep", b"rangeproof v1");
-		self.append_u64(b"n", n);
-		self.append_u64(b"m", m);
+		append_message(self, b"dom-sep", b"rangeproof v1");
+		append_u64(self, b"n", n);
+		append_u64(self, b"m", m);
+	}
+
+	fn rangeproof_mixed_domain_sep(&mut self, bit_sizes: &[usize]) {
+		append_message(self, b"dom-sep", b"rangeproof-mixed v1");
+		append_u64(self, b"m", bit_sizes.len() as u64);
+		for n in bit_sizes {
+			append_u64(self, b"n", *n as u64);
+		}
+	}
+
+	fn rangeproof_rewind_domain_sep(&mut self) {
+		append_message(self, b"dom-sep", b"rangeproof-rewind v1");
+	}
+
+	fn rangeproof_plus_domain_sep(&mut self, n: u64, m: u64) {
+		append_message(self, b"dom-sep", b"rangeproof-plus v1");
+		append_u64(self, b"n", n);
+		append_u64(self, b"m", m);
 	}
 
 	fn innerproduct_domain_sep(&mut self, n: u64) {
-		self.append_message(b"dom-sep", b"ipp v1");
-		self.append_u64(b"n", n);
+		append_message(self, b"dom-sep", b"ipp v1");
+		append_u64(self, b"n", n);
+	}
+
+	fn linear_proof_domain_sep(&mut self, n: u64) {
+		append_message(self, b"dom-sep", b"linear-proof v1");
+		append_u64(self, b"n", n);
+	}
+
+	fn r1cs_domain_sep(&mut self, label: &[u8]) {
+		// Version 2 binds the size of the circuit with `r1cs_circuit_size`.
+		append_message(self, b"dom-sep", b"r1cs v2");
+		append_message(self, b"circuit", label);
+	}
+
+	fn r1cs_circuit_size(&mut self, n: u64, q: u64) {
+		append_message(self, b"dom-sep", b"r1cs-circuit-size");
+		append_u64(self, b"n", n);
+		append_u64(self, b"q", q);
+	}
+
+	fn r1cs_pedersen_gens(&mut self, base: &CompressedRistretto, blinding_base: &CompressedRistretto) {
+		append_message(self, b"dom-sep", b"r1cs-pedersen-gens");
+		self.append_point(b"B", base);
+		self.append_point(b"B_blinding", blinding_base);
 	}
 
-	fn r1cs_domain_sep(&mut self) {
-		self.append_message(b"dom-sep", b"r1cs v1");
+	fn r1cs_aggregation_domain_sep(&mut self, n: u64, m: u64) {
+		append_message(self, b"dom-sep", b"r1cs-aggregation v2");
+		append_u64(self, b"n", n);
+		append_u64(self, b"m", m);
 	}
 
 	fn r1cs_1phase_domain_sep(&mut self) {
-		self.append_message(b"dom-sep", b"r1cs-1phase");
+		append_message(self, b"dom-sep", b"r1cs-1phase");
 	}
 
 	fn r1cs_2phase_domain_sep(&mut self) {
-		self.append_message(b"dom-sep", b"r1cs-2phase");
+		append_message(self, b"dom-sep", b"r1cs-2phase");
+	}
+
+	fn r1cs_challenge_count(&mut self, label: &'static [u8], n: u64) {
+		append_message(self, b"dom-sep", b"r1cs-challenge-count");
+		append_u64(self, label, n);
+	}
+
+	fn r1cs_circuit_hash(&mut self, circuit_hash: &[u8; 32]) {
+		append_message(self, b"dom-sep", b"r1cs-circuit-hash");
+		append_message(self, b"circuit-hash", circuit_hash);
+	}
+
+	fn r1cs_namespace_begin(&mut self, label: &[u8]) {
+		append_message(self, b"dom-sep", b"r1cs-namespace-begin");
+		append_message(self, b"namespace", label);
+	}
+
+	fn r1cs_namespace_end(&mut self, label: &[u8]) {
+		append_message(self, b"dom-sep", b"r1cs-namespace-end");
+		append_message(self, b"namespace", label);
+	}
+
+	fn r1cs_public_input(&mut self, label: &'static [u8], bytes: &[u8]) {
+		append_message(self, b"dom-sep", b"r1cs-public-input");
+		append_message(self, label, bytes);
+	}
+
+	fn link_domain_sep(&mut self) {
+		append_message(self, b"dom-sep", b"link v1");
+	}
+
+	fn ctx_domain_sep(&mut self, fee: u64, inputs: &[CompressedRistretto]) {
+		append_message(self, b"dom-sep", b"ctx v1");
+		append_u64(self, b"fee", fee);
+		append_u64(self, b"inputs", inputs.len() as u64);
+		for input in inputs {
+			self.append_point(b"V_in", input);
+		}
 	}
 
 	fn append_scalar(&mut self, label: &'static [u8], scalar: &Scalar) {
-		self.append_message(label, scalar.as_bytes());
+		append_message(self, label, scalar.as_bytes());
 	}
 
 	fn append_point(&mut self, label: &'static [u8], point: &CompressedRistretto) {
-		self.append_message(label, point.as_bytes());
+		append_message(self, label, point.as_bytes());
 	}
 
 	fn validate_and_append_point(
@@ -81,14 +217,80 @@ impl TranscriptProtocol for Transcript {
 		if point.is_identity() {
 			Err(ProofError::VerificationError)
 		} else {
-			Ok(self.append_message(label, point.as_bytes()))
+			Ok(append_message(self, label, point.as_bytes()))
 		}
 	}
 
 	fn challenge_scalar(&mut self, label: &'static [u8]) -> Scalar {
 		let mut buf = [0u8; 64];
-		self.challenge_bytes(label, &mut buf);
+		challenge_bytes(self, label, &mut buf);
 
 		Scalar::from_bytes_mod_order_wide(&buf)
 	}
 }
+
+/// Appends the `message` with the `label` to the `transcript`.
+///
+/// With the `transcript-audit` feature, the message is recorded on the
+/// [`TranscriptTape`](crate::TranscriptTape) being recorded, if any, like
+/// all the messages appended by the [`TranscriptProtocol`] functions.
+pub(crate) fn append_message(transcript: &mut Transcript, label: &'static [u8], message: &[u8]) {
+	#[cfg(feature = "transcript-audit")]
+	crate::transcript_tape::record_append(label, message.len());
+	transcript.append_message(label, message);
+}
+
+/// Appends `x` with the `label` to the `transcript`, like
+/// `Transcript::append_u64`.
+pub(crate) fn append_u64(transcript: &mut Transcript, label: &'static [u8], x: u64) {
+	append_message(transcript, label, &x.to_le_bytes());
+}
+
+/// Fills `dest` with a challenge drawn from the `transcript` with the
+/// `label`.
+pub(crate) fn challenge_bytes(transcript: &mut Transcript, label: &'static [u8], dest: &mut [u8]) {
+	transcript.challenge_bytes(label, dest);
+	#[cfg(feature = "transcript-audit")]
+	crate::transcript_tape::record_challenge(label, dest);
+}
+
+/// Calls `f` without recording its transcript operations on the
+/// [`TranscriptTape`](crate::TranscriptTape) being recorded, if any, for
+/// transcripts whose challenges are secret.
+pub(crate) fn unrecorded<R, F: FnOnce() -> R>(f: F) -> R {
+	#[cfg(feature = "transcript-audit")]
+	return crate::transcript_tape::unrecorded(f);
+	#[cfg(not(feature = "transcript-audit"))]
+	f()
+}
+
+/// Derives a child transcript for a sub-proof bound to the `parent`
+/// transcript.
+///
+/// A seed is squeezed from the `parent` under the `label`, and absorbed
+/// together with the `label` into a fresh transcript, which is returned.
+/// The seed is then appended back to the `parent`, so that the fork is
+/// recorded in the parent's subsequent challenges.
+///
+/// # Binding
+///
+/// Every challenge of the child transcript depends on the full state of
+/// the `parent` at the time of the fork and on the `label`.  A proof made
+/// with a child transcript therefore only verifies against a child forked
+/// from a parent with the same history, using the same label, at the same
+/// position.  Several forks may be taken from one parent: each fork changes
+/// the parent's state, so the children are distinct even with equal labels,
+/// but they must be forked in the same order by the prover and the verifier.
+pub fn fork_transcript(parent: &mut Transcript, label: &'static [u8]) -> Transcript {
+	append_message(parent, b"dom-sep", b"fork v1");
+	append_message(parent, b"fork-label", label);
+
+	let mut seed = [0u8; 32];
+	challenge_bytes(parent, b"fork-seed", &mut seed);
+	append_message(parent, b"fork-commit", &seed);
+
+	let mut child = Transcript::new(b"bulletproofs fork v1");
+	append_message(&mut child, b"fork-label", label);
+	append_message(&mut child, b"fork-seed", &seed);
+	child
+}
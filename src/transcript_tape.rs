@@ -0,0 +1,159 @@
+//! The `transcript_tape` module records the messages of the Fiat-Shamir
+//! transcripts, for auditing the protocols.
+//!
+//! Recording is per thread: [`TranscriptTape::record`] captures every
+//! message appended to a transcript, and every challenge drawn from one,
+//! by the provers and verifiers of this crate running on the current
+//! thread.  Only the label and the length of the appended messages are
+//! recorded, together with the output of the challenges, which are public.
+//! The nonces derived from the secrets of a prover are never recorded.
+
+#![deny(missing_docs)]
+
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+thread_local! {
+	/// The tape being recorded on this thread, if any.
+	static TAPE: RefCell<Option<TranscriptTape>> = RefCell::default();
+}
+
+/// The kind of a transcript operation.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TranscriptOperation {
+	/// A message was appended to the transcript.
+	Append,
+	/// A challenge was drawn from the transcript.
+	Challenge,
+}
+
+/// A transcript operation recorded on a [`TranscriptTape`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TranscriptEntry {
+	/// The label of the message or challenge.
+	pub label: &'static [u8],
+	/// The kind of the operation.
+	pub operation: TranscriptOperation,
+	/// The length in bytes of the message or challenge.
+	pub length: usize,
+	/// The bytes of the challenge, for [`TranscriptOperation::Challenge`].
+	pub challenge: Option<Vec<u8>>,
+}
+
+/// The transcript operations performed while recording, in order.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TranscriptTape {
+	entries: Vec<TranscriptEntry>,
+}
+
+/// Replaces the tape being recorded on this thread until it is dropped,
+/// even if the code recording the replacement panics.
+struct Recording {
+	outer: Option<Option<TranscriptTape>>,
+}
+
+impl Recording {
+	/// Starts recording on the `tape`, saving the current one.
+	fn replace(tape: Option<TranscriptTape>) -> Recording {
+		Recording {
+			outer: Some(TAPE.with(|current| current.replace(tape))),
+		}
+	}
+
+	/// Restores the saved tape, and returns the replacement.
+	fn restore(&mut self) -> Option<TranscriptTape> {
+		let outer = self.outer.take().flatten();
+		TAPE.with(|current| current.replace(outer))
+	}
+}
+
+impl Drop for Recording {
+	fn drop(&mut self) {
+		if self.outer.is_some() {
+			self.restore();
+		}
+	}
+}
+
+impl TranscriptTape {
+	/// Calls `f`, and returns its result together with the tape of the
+	/// transcript operations it performed on the current thread.
+	///
+	/// Operations performed by other threads, such as those of the
+	/// `rayon` thread pool, are not recorded.  Recordings may be nested, in
+	/// which case the operations are recorded on every tape.
+	pub fn record<R, F: FnOnce() -> R>(f: F) -> (R, TranscriptTape) {
+		let mut recording = Recording::replace(Some(TranscriptTape::default()));
+		let result = f();
+		let tape = recording.restore().unwrap_or_default();
+		// The operations also belong to the outer recording, if any.
+		TAPE.with(|outer| {
+			if let Some(outer) = outer.borrow_mut().as_mut() {
+				outer.entries.extend_from_slice(&tape.entries);
+			}
+		});
+		(result, tape)
+	}
+
+	/// Returns the recorded operations, in order.
+	pub fn entries(&self) -> &[TranscriptEntry] {
+		&self.entries
+	}
+
+	/// Returns the number of recorded operations.
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	/// Returns `true` if no operation was recorded.
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+
+	/// Returns the index of the first operation which differs between this
+	/// tape and `other`, or `None` if the tapes are identical.
+	///
+	/// If one tape is a prefix of the other, the index is the length of
+	/// the shorter one.
+	pub fn first_divergence(&self, other: &TranscriptTape) -> Option<usize> {
+		match self.entries.iter().zip(other.entries.iter()).position(|(a, b)| a != b) {
+			Some(index) => Some(index),
+			None if self.len() != other.len() => Some(self.len().min(other.len())),
+			None => None,
+		}
+	}
+}
+
+/// Records that a message of `length` bytes was appended with the `label`.
+pub(crate) fn record_append(label: &'static [u8], length: usize) {
+	push(TranscriptEntry {
+		label,
+		operation: TranscriptOperation::Append,
+		length,
+		challenge: None,
+	});
+}
+
+/// Records that the `challenge` was drawn with the `label`.
+pub(crate) fn record_challenge(label: &'static [u8], challenge: &[u8]) {
+	push(TranscriptEntry {
+		label,
+		operation: TranscriptOperation::Challenge,
+		length: challenge.len(),
+		challenge: Some(challenge.to_vec()),
+	});
+}
+
+/// Calls `f` without recording the transcript operations it performs.
+pub(crate) fn unrecorded<R, F: FnOnce() -> R>(f: F) -> R {
+	let _recording = Recording::replace(None);
+	f()
+}
+
+fn push(entry: TranscriptEntry) {
+	TAPE.with(|tape| {
+		if let Some(tape) = tape.borrow_mut().as_mut() {
+			tape.entries.push(entry);
+		}
+	});
+}
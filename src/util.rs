@@ -6,6 +6,8 @@ extern crate alloc;
 use alloc::{vec, vec::Vec};
 use clear_on_drop::clear::Clear;
 use curve25519_dalek::scalar::Scalar;
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
 
 use crate::inner_product_proof::inner_product;
 
@@ -142,6 +144,22 @@ impl VecPoly3 {
 		}
 		out
 	}
+
+	/// Evaluates the polynomial at `x`, reusing the buffer of the
+	/// \\(\mathbf{b}\\) coefficients for the result.
+	///
+	/// The other coefficient vectors may be left empty when they are zero.
+	pub fn eval_in_place(mut self, x: Scalar) -> Vec<Scalar> {
+		let zero = Scalar::zero();
+		let mut out = core::mem::take(&mut self.1);
+		for (i, out_i) in out.iter_mut().enumerate() {
+			let a = self.0.get(i).unwrap_or(&zero);
+			let c = self.2.get(i).unwrap_or(&zero);
+			let d = self.3.get(i).unwrap_or(&zero);
+			*out_i = a + x * (*out_i + x * (c + x * d));
+		}
+		out
+	}
 }
 
 impl Poly2 {
@@ -157,17 +175,32 @@ impl Poly6 {
 	}
 }
 
-impl Drop for VecPoly1 {
-	fn drop(&mut self) {
-		for e in self.0.iter_mut() {
-			e.clear();
+/// Clears the contents of the given vectors of secret scalars, which keep
+/// their length.
+///
+/// With the `zeroize` feature, the unused capacity of the vectors is
+/// wiped as well.
+pub fn clear_scalars(vecs: &mut [&mut Vec<Scalar>]) {
+	for vec in vecs.iter_mut() {
+		#[cfg(feature = "zeroize")]
+		{
+			let len = vec.len();
+			vec.zeroize();
+			vec.resize(len, Scalar::zero());
 		}
-		for e in self.1.iter_mut() {
-			e.clear();
+		#[cfg(not(feature = "zeroize"))]
+		for scalar in vec.iter_mut() {
+			scalar.clear();
 		}
 	}
 }
 
+impl Drop for VecPoly1 {
+	fn drop(&mut self) {
+		clear_scalars(&mut [&mut self.0, &mut self.1]);
+	}
+}
+
 impl Drop for Poly2 {
 	fn drop(&mut self) {
 		self.0.clear();
@@ -176,27 +209,25 @@ impl Drop for Poly2 {
 	}
 }
 
+#[cfg(feature = "yoloproofs")]
+impl VecPoly3 {
+	/// Clears the coefficients of the polynomial.
+	pub fn clear(&mut self) {
+		clear_scalars(&mut [&mut self.0, &mut self.1, &mut self.2, &mut self.3]);
+	}
+}
+
 #[cfg(feature = "yoloproofs")]
 impl Drop for VecPoly3 {
 	fn drop(&mut self) {
-		for e in self.0.iter_mut() {
-			e.clear();
-		}
-		for e in self.1.iter_mut() {
-			e.clear();
-		}
-		for e in self.2.iter_mut() {
-			e.clear();
-		}
-		for e in self.3.iter_mut() {
-			e.clear();
-		}
+		self.clear();
 	}
 }
 
 #[cfg(feature = "yoloproofs")]
-impl Drop for Poly6 {
-	fn drop(&mut self) {
+impl Poly6 {
+	/// Clears the coefficients of the polynomial.
+	pub fn clear(&mut self) {
 		self.t1.clear();
 		self.t2.clear();
 		self.t3.clear();
@@ -206,6 +237,22 @@ impl Drop for Poly6 {
 	}
 }
 
+#[cfg(feature = "yoloproofs")]
+impl Drop for Poly6 {
+	fn drop(&mut self) {
+		self.clear();
+	}
+}
+
+/// Marker for types which wipe their secrets with [`Zeroize`] when they
+/// are dropped.
+///
+/// This stands in for `zeroize::ZeroizeOnDrop`, which was added in
+/// `zeroize` 1.5, while `curve25519-dalek` 3 requires a version older than
+/// 1.4.
+#[cfg(feature = "zeroize")]
+pub trait ZeroizeOnDrop: Zeroize {}
+
 /// Raises `x` to the power `n` using binary exponentiation,
 /// with (1 to 2)*lg(n) scalar multiplications.
 /// TODO: a consttime version of this would be awfully similar to a Montgomery
@@ -259,6 +306,57 @@ pub fn read32(data: &[u8]) -> [u8; 32] {
 	buf32
 }
 
+/// Reads `reader` to the end into a buffer of at most `max_size` bytes.
+///
+/// Returns `None` if reading fails or the input exceeds `max_size` bytes.
+#[cfg(feature = "std")]
+pub fn read_bounded<R: std::io::Read>(reader: R, max_size: usize) -> Option<Vec<u8>> {
+	use std::io::Read;
+
+	let mut buf = Vec::new();
+	reader.take(max_size as u64 + 1).read_to_end(&mut buf).ok()?;
+	if buf.len() > max_size {
+		return None;
+	}
+	Some(buf)
+}
+
+/// Writes a frame of `len` bytes to `writer`, prefixed with its length as a
+/// little-endian `u32`, calling `write` to write the content.
+///
+/// Returns the number of bytes written, including the prefix.
+#[cfg(feature = "std")]
+pub fn write_framed<W, F>(mut writer: W, len: usize, write: F) -> std::io::Result<usize>
+where
+	W: std::io::Write,
+	F: FnOnce(&mut W) -> std::io::Result<usize>,
+{
+	use core::convert::TryFrom;
+
+	let prefix = u32::try_from(len)
+		.map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidInput, "frame longer than u32::MAX bytes"))?;
+	writer.write_all(&prefix.to_le_bytes())?;
+	Ok(4 + write(&mut writer)?)
+}
+
+/// Reads a frame written by [`write_framed`] from `reader`, consuming
+/// exactly the prefix and the content of the frame.
+///
+/// Returns `None` if reading fails, including when the input ends before
+/// the end of the frame, or if the frame is longer than `max_size` bytes.
+#[cfg(feature = "std")]
+pub fn read_framed<R: std::io::Read>(mut reader: R, max_size: usize) -> Option<Vec<u8>> {
+	let mut prefix = [0u8; 4];
+	reader.read_exact(&mut prefix).ok()?;
+	let len = u32::from_le_bytes(prefix) as usize;
+	if len > max_size {
+		return None;
+	}
+	let mut buf = vec![0u8; len];
+	reader.read_exact(&mut buf).ok()?;
+	Some(buf)
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -0,0 +1,440 @@
+#![allow(non_snake_case)]
+//! Deferred verification of heterogeneous statements.
+//!
+//! Every verifier in this crate ends with a multiscalar multiplication
+//! whose result must be the identity.  A [`VerificationBatch`] collects the
+//! scalars and points of those final checks instead, for range proofs and
+//! R1CS proofs alike, and performs them all at once with a single
+//! multiscalar multiplication, weighting each statement by a random scalar
+//! so that the generators shared by the statements only appear once.
+
+extern crate alloc;
+
+use alloc::{vec, vec::Vec};
+use core::iter;
+use curve25519_dalek::{ristretto::RistrettoPoint, scalar::Scalar, traits::IsIdentity};
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(feature = "std")]
+use rand::{rngs::ThreadRng, thread_rng};
+
+use crate::{
+	errors::ProofError,
+	generators::{BulletproofGens, PedersenGens},
+	msm::{DefaultMsmBackend, MsmBackend},
+};
+
+/// The final check of a statement, which holds when the multiscalar
+/// multiplication of its scalars and points is the identity.
+pub(crate) struct Statement {
+	/// Number of \\(\mathbf G\\) and \\(\mathbf H\\) generators of each
+	/// party share used by the statement.
+	pub(crate) n: usize,
+	/// Number of party shares used by the statement.
+	pub(crate) m: usize,
+	/// Points from the proof and the commitments.
+	pub(crate) points: Vec<RistrettoPoint>,
+	/// Scalars for `points`.
+	pub(crate) point_scalars: Vec<Scalar>,
+	/// Scalar for the Pedersen base `B`.
+	pub(crate) B_scalar: Scalar,
+	/// Scalar for the Pedersen base `B_blinding`.
+	pub(crate) B_blinding_scalar: Scalar,
+	/// Scalars for the \\(\mathbf G\\) generators, party by party.
+	pub(crate) g_scalars: Vec<Scalar>,
+	/// Scalars for the \\(\mathbf H\\) generators, party by party.
+	pub(crate) h_scalars: Vec<Scalar>,
+}
+
+/// The point multiplied by a scalar in [`VerificationTerms`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TermPoint {
+	/// A point of the proof, by its label in the transcript, such as
+	/// `b"A_I1"` or `b"T_1"`.
+	Proof(&'static [u8]),
+	/// The point \\(L\_i\\) of round \\(i\\) of the inner-product proof.
+	L(usize),
+	/// The point \\(R\_i\\) of round \\(i\\) of the inner-product proof.
+	R(usize),
+	/// The `i`-th value commitment.
+	Commitment(usize),
+	/// The Pedersen base `B`.
+	B,
+	/// The Pedersen base `B_blinding`.
+	BBlinding,
+	/// The generator \\(G\_i\\) of a party `share`.
+	G {
+		/// The index of the party share.
+		share: usize,
+		/// The index of the generator in the share.
+		index: usize,
+	},
+	/// The generator \\(H\_i\\) of a party `share`.
+	H {
+		/// The index of the party share.
+		share: usize,
+		/// The index of the generator in the share.
+		index: usize,
+	},
+}
+
+/// The final check of a proof as data: a proof verifies when the
+/// multiscalar multiplication of the scalars of its terms and their points
+/// is the identity.
+///
+/// Returned by `Verifier::verification_terms` and
+/// [`RangeProof::verification_terms`], for protocols which combine the
+/// check of a proof with their own equations.  The points of the proof and
+/// the commitments are decompressed, while the generators are only
+/// identified, and resolved by [`VerificationTerms::scalars_and_points`].
+///
+/// [`RangeProof::verification_terms`]: crate::RangeProof::verification_terms
+pub struct VerificationTerms {
+	statement: Statement,
+	/// The identities of the points of the `statement`.
+	point_ids: Vec<TermPoint>,
+}
+
+impl VerificationTerms {
+	pub(crate) fn new(statement: Statement, point_ids: Vec<TermPoint>) -> Self {
+		debug_assert_eq!(statement.points.len(), point_ids.len());
+		VerificationTerms { statement, point_ids }
+	}
+
+	/// Returns the terms of the equation, as the scalars paired with the
+	/// points they multiply: the points of the proof and the commitments,
+	/// then `B`, `B_blinding`, and the \\(\mathbf G\\) and \\(\mathbf H\\)
+	/// generators share by share.
+	pub fn terms(&self) -> impl Iterator<Item = (Scalar, TermPoint)> + '_ {
+		let statement = &self.statement;
+		let generators = (0..statement.m).flat_map(move |share| {
+			let g = (0..statement.n).map(move |index| {
+				(statement.g_scalars[share * statement.n + index], TermPoint::G {
+					share,
+					index,
+				})
+			});
+			let h = (0..statement.n).map(move |index| {
+				(statement.h_scalars[share * statement.n + index], TermPoint::H {
+					share,
+					index,
+				})
+			});
+			g.chain(h)
+		});
+		statement
+			.point_scalars
+			.iter()
+			.cloned()
+			.zip(self.point_ids.iter().cloned())
+			.chain(iter::once((statement.B_scalar, TermPoint::B)))
+			.chain(iter::once((statement.B_blinding_scalar, TermPoint::BBlinding)))
+			.chain(generators)
+	}
+
+	/// Returns the decompressed point of the proof or commitment `id`, or
+	/// `None` if `id` is a generator or is not part of the equation.
+	pub fn proof_point(&self, id: TermPoint) -> Option<RistrettoPoint> {
+		self.point_ids
+			.iter()
+			.position(|point_id| *point_id == id)
+			.map(|i| self.statement.points[i])
+	}
+
+	/// Returns the scalars of the terms and their points, in the order of
+	/// [`VerificationTerms::terms`], with the generators taken from
+	/// `pc_gens` and `bp_gens`.
+	pub fn scalars_and_points(
+		&self,
+		pc_gens: &PedersenGens,
+		bp_gens: &BulletproofGens,
+	) -> Result<(Vec<Scalar>, Vec<RistrettoPoint>), ProofError> {
+		let statement = &self.statement;
+		if bp_gens.gens_capacity < statement.n {
+			return Err(ProofError::InvalidGeneratorsLength {
+				required: statement.n,
+				actual: bp_gens.gens_capacity,
+			});
+		}
+		if bp_gens.party_capacity < statement.m {
+			return Err(ProofError::InvalidGeneratorsLength {
+				required: statement.m,
+				actual: bp_gens.party_capacity,
+			});
+		}
+
+		let scalars = self.terms().map(|(scalar, _)| scalar).collect();
+		let mut points = statement.points.clone();
+		points.push(pc_gens.B);
+		points.push(pc_gens.B_blinding);
+		for share in 0..statement.m {
+			points.extend_from_slice(bp_gens.share(share).G_slice(statement.n));
+			points.extend_from_slice(bp_gens.share(share).H_slice(statement.n));
+		}
+		Ok((scalars, points))
+	}
+
+	/// Evaluates the terms and checks that the result is the identity,
+	/// which is the final check of the verification of the proof.
+	pub fn verify(&self, pc_gens: &PedersenGens, bp_gens: &BulletproofGens) -> Result<(), ProofError> {
+		let (scalars, points) = self.scalars_and_points(pc_gens, bp_gens)?;
+		if DefaultMsmBackend.vartime_msm(&scalars, &points).is_identity() {
+			Ok(())
+		} else {
+			Err(ProofError::VerificationError)
+		}
+	}
+}
+
+/// Collects the final checks of range proofs and R1CS proofs, and verifies
+/// them together with one multiscalar multiplication.
+///
+/// Statements are added with [`RangeProof::verify_single_deferred`],
+/// [`RangeProof::verify_multiple_deferred`] and, with the `yoloproofs`
+/// feature, `Verifier::verify_deferred`, which perform the cheap part of
+/// the verification immediately and return the index of the statement in
+/// the batch.  [`VerificationBatch::verify`] then checks all of them.
+///
+/// Every statement must use the same [`PedersenGens`] and
+/// [`BulletproofGens`], given when the batch is created.
+///
+/// [`RangeProof::verify_single_deferred`]: crate::RangeProof::verify_single_deferred
+/// [`RangeProof::verify_multiple_deferred`]: crate::RangeProof::verify_multiple_deferred
+pub struct VerificationBatch<'g, T: RngCore + CryptoRng> {
+	bp_gens: &'g BulletproofGens,
+	pc_gens: &'g PedersenGens,
+	/// The RNG for the random weights of the statements, also used by the
+	/// verifiers adding them.
+	pub(crate) rng: T,
+	statements: Vec<Statement>,
+}
+
+#[cfg(feature = "std")]
+impl<'g> VerificationBatch<'g, ThreadRng> {
+	/// Creates an empty batch of statements using the given generators.
+	///
+	/// This is a convenience wrapper around
+	/// [`VerificationBatch::new_with_rng`], passing in a threadsafe RNG.
+	pub fn new(bp_gens: &'g BulletproofGens, pc_gens: &'g PedersenGens) -> Self {
+		VerificationBatch::new_with_rng(bp_gens, pc_gens, thread_rng())
+	}
+}
+
+impl<'g, T: RngCore + CryptoRng> VerificationBatch<'g, T> {
+	/// Creates an empty batch of statements using the given generators,
+	/// weighting them with scalars drawn from `rng`.
+	pub fn new_with_rng(bp_gens: &'g BulletproofGens, pc_gens: &'g PedersenGens, rng: T) -> Self {
+		VerificationBatch {
+			bp_gens,
+			pc_gens,
+			rng,
+			statements: Vec::new(),
+		}
+	}
+
+	/// Returns the number of statements in the batch.
+	pub fn len(&self) -> usize {
+		self.statements.len()
+	}
+
+	/// Returns `true` if the batch has no statements.
+	pub fn is_empty(&self) -> bool {
+		self.statements.is_empty()
+	}
+
+	/// Returns the generators of the batch.
+	pub(crate) fn bp_gens(&self) -> &'g BulletproofGens {
+		self.bp_gens
+	}
+
+	/// Adds a `statement`, whose generators must have been checked to be
+	/// available, and returns its index.
+	pub(crate) fn push(&mut self, statement: Statement) -> usize {
+		self.statements.push(statement);
+		self.statements.len() - 1
+	}
+
+	/// Verifies all the statements of the batch with a single multiscalar
+	/// multiplication.
+	///
+	/// If the combined check fails, the batch is bisected until the
+	/// statements which do not verify are isolated, and
+	/// [`ProofError::BatchStatementsVerificationError`] lists their
+	/// indexes.  This takes a number of checks logarithmic in the size of
+	/// the batch for each failing statement.
+	pub fn verify(self) -> Result<(), ProofError> {
+		enter_span!("verification_batch_verify", batch = self.statements.len());
+
+		match self.failures() {
+			Some(indexes) if indexes.is_empty() => Err(ProofError::VerificationError),
+			Some(indexes) => Err(ProofError::BatchStatementsVerificationError { indexes }),
+			None => Ok(()),
+		}
+	}
+
+	/// Checks the statements of the batch, returning `None` if they all
+	/// verify, and the indexes of those which do not otherwise.
+	///
+	/// The indexes are empty if the combined check fails although every
+	/// statement verifies on its own, which only a faulty multiscalar
+	/// multiplication can cause.
+	fn failures(mut self) -> Option<Vec<usize>> {
+		let rng = &mut self.rng;
+		let weights: Vec<Scalar> = self.statements.iter().map(|_| Scalar::random(rng)).collect();
+		let weighted: Vec<_> = weights.iter().zip(self.statements.iter()).collect();
+		if self.check(weighted.iter().cloned()) {
+			return None;
+		}
+
+		// Bisect the batch to find the invalid statements.
+		let mut indexes = Vec::new();
+		self.bisect(&weighted, 0, &mut indexes);
+		Some(indexes)
+	}
+
+	/// Adds to `indexes` the indexes of the statements which do not verify
+	/// among the `weighted` ones, whose combined check fails, by checking
+	/// each half of them.  The first statement has the index `offset`.
+	fn bisect(&self, weighted: &[(&Scalar, &Statement)], offset: usize, indexes: &mut Vec<usize>) {
+		if weighted.len() == 1 {
+			indexes.push(offset);
+			return;
+		}
+		let (left, right) = weighted.split_at(weighted.len() / 2);
+		for &(half, offset) in [(left, offset), (right, offset + left.len())].iter() {
+			if !self.check(half.iter().cloned()) {
+				self.bisect(half, offset, indexes);
+			}
+		}
+	}
+
+	/// Sums the weighted `statements` and checks that the multiscalar
+	/// multiplication of the sum is the identity.
+	fn check<'a, I>(&self, statements: I) -> bool
+	where
+		I: Iterator<Item = (&'a Scalar, &'a Statement)> + Clone,
+	{
+		// The number of generators used from each party share.
+		let mut lengths = Vec::new();
+		for (_, statement) in statements.clone() {
+			if lengths.len() < statement.m {
+				lengths.resize(statement.m, 0);
+			}
+			for length in lengths.iter_mut().take(statement.m) {
+				*length = statement.n.max(*length);
+			}
+		}
+
+		let mut scalars = Vec::new();
+		let mut points = Vec::new();
+		let mut B_scalar = Scalar::zero();
+		let mut B_blinding_scalar = Scalar::zero();
+		let mut g_scalars: Vec<Vec<Scalar>> = lengths.iter().map(|&n| vec![Scalar::zero(); n]).collect();
+		let mut h_scalars: Vec<Vec<Scalar>> = lengths.iter().map(|&n| vec![Scalar::zero(); n]).collect();
+
+		for (c, statement) in statements {
+			scalars.extend(statement.point_scalars.iter().map(|s| c * s));
+			points.extend_from_slice(&statement.points);
+			B_scalar += c * statement.B_scalar;
+			B_blinding_scalar += c * statement.B_blinding_scalar;
+			for j in 0..statement.m {
+				let share = j * statement.n..(j + 1) * statement.n;
+				for (acc, s) in g_scalars[j].iter_mut().zip(statement.g_scalars[share.clone()].iter()) {
+					*acc += c * s;
+				}
+				for (acc, s) in h_scalars[j].iter_mut().zip(statement.h_scalars[share].iter()) {
+					*acc += c * s;
+				}
+			}
+		}
+
+		scalars.push(B_scalar);
+		scalars.push(B_blinding_scalar);
+		points.push(self.pc_gens.B);
+		points.push(self.pc_gens.B_blinding);
+		for (j, (g_share, h_share)) in g_scalars.iter().zip(h_scalars.iter()).enumerate() {
+			scalars.extend_from_slice(g_share);
+			scalars.extend_from_slice(h_share);
+			points.extend_from_slice(self.bp_gens.share(j).G_slice(g_share.len()));
+			points.extend_from_slice(self.bp_gens.share(j).H_slice(h_share.len()));
+		}
+
+		let mega_check = in_span!("verification_batch_multiexp", size = points.len(), {
+			DefaultMsmBackend.vartime_msm(&scalars, &points)
+		});
+		mega_check.is_identity()
+	}
+}
+
+/// Verifies the `items` in up to `parallelism` partitions, each checked
+/// with its own [`VerificationBatch`] on the current `rayon` thread pool.
+///
+/// The `defer` function adds the final check of an item to the batch of
+/// its partition, or fails if the item is malformed.  Returns the indexes
+/// of the items which are malformed or do not verify, in increasing order.
+/// A partition whose combined check fails without any failing item is
+/// rejected as a whole.
+#[cfg(feature = "rayon")]
+pub(crate) fn verify_partitioned<S, E, F>(
+	items: Vec<S>,
+	parallelism: usize,
+	bp_gens: &BulletproofGens,
+	pc_gens: &PedersenGens,
+	defer: F,
+) -> Vec<usize>
+where
+	S: Send,
+	F: Fn(S, &mut VerificationBatch<'_, ThreadRng>) -> Result<usize, E> + Sync,
+{
+	use rayon::prelude::*;
+
+	let chunk_size = ((items.len() + parallelism.max(1) - 1) / parallelism.max(1)).max(1);
+	let mut partitions: Vec<Vec<(usize, S)>> = Vec::new();
+	for (index, item) in items.into_iter().enumerate() {
+		if index % chunk_size == 0 {
+			partitions.push(Vec::with_capacity(chunk_size));
+		}
+		partitions
+			.last_mut()
+			.expect("a partition was just pushed")
+			.push((index, item));
+	}
+
+	let failures: Vec<Vec<usize>> = partitions
+		.into_par_iter()
+		.map(|partition| verify_indexed(partition, VerificationBatch::new(bp_gens, pc_gens), &defer))
+		.collect();
+	failures.concat()
+}
+
+/// Verifies the indexed `items` with the `batch`.
+///
+/// The `defer` function adds the final check of an item to the `batch`, or
+/// fails if the item is malformed.  Returns the indexes of the items which
+/// are malformed or do not verify, in increasing order.  A batch whose
+/// combined check fails without any failing item is rejected as a whole.
+pub(crate) fn verify_indexed<S, E, T, F>(
+	items: impl IntoIterator<Item = (usize, S)>,
+	mut batch: VerificationBatch<'_, T>,
+	mut defer: F,
+) -> Vec<usize>
+where
+	T: RngCore + CryptoRng,
+	F: FnMut(S, &mut VerificationBatch<'_, T>) -> Result<usize, E>,
+{
+	let mut deferred = Vec::new();
+	let mut failed = Vec::new();
+	for (index, item) in items {
+		match defer(item, &mut batch) {
+			Ok(_) => deferred.push(index),
+			Err(_) => failed.push(index),
+		}
+	}
+	match batch.failures() {
+		Some(statements) if statements.is_empty() => failed.extend_from_slice(&deferred),
+		Some(statements) => failed.extend(statements.into_iter().map(|statement| deferred[statement])),
+		None => {}
+	}
+	failed.sort_unstable();
+	failed
+}
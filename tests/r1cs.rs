@@ -2,7 +2,11 @@
 use curve25519_dalek::{ristretto::CompressedRistretto, scalar::Scalar};
 use merlin::Transcript;
 use rand::{seq::SliceRandom, thread_rng};
-use std::time::{Duration, Instant};
+use std::{
+	cell::RefCell,
+	rc::Rc,
+	time::{Duration, Instant},
+};
 use webb_bulletproofs::{r1cs::*, BulletproofGens, PedersenGens};
 
 // Shuffle gadget (documented in markdown file)
@@ -328,6 +332,52 @@ fn example_gadget_roundtrip_serialization_helper(
 	example_gadget_verify(&pc_gens, &bp_gens, c2, proof, commitments)
 }
 
+#[test]
+fn example_gadget_stream_roundtrip() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(128, 1);
+	let (proof, commitments) = example_gadget_proof(&pc_gens, &bp_gens, 3, 4, 6, 1, 40, 9).unwrap();
+
+	let mut buf = Vec::new();
+	let written = proof.write_to(&mut buf).unwrap();
+	assert_eq!(written, buf.len());
+	assert_eq!(buf, proof.to_bytes());
+
+	let read = R1CSProof::read_from(&buf[..], buf.len()).unwrap();
+	assert_eq!(read.to_bytes(), buf);
+	assert!(example_gadget_verify(&pc_gens, &bp_gens, 9, read, commitments).is_ok());
+
+	assert!(R1CSProof::read_from(&buf[..], buf.len() - 1).is_err());
+}
+
+#[test]
+fn example_gadget_framed_stream_roundtrip() {
+	use std::io::{Cursor, Read};
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(128, 1);
+	let (proof, commitments) = example_gadget_proof(&pc_gens, &bp_gens, 3, 4, 6, 1, 40, 9).unwrap();
+	let proof_len = proof.serialized_size();
+
+	let mut envelope = vec![0xff; 3];
+	assert_eq!(proof.write_framed(&mut envelope).unwrap(), 4 + proof_len);
+	envelope.push(0xff);
+
+	let mut cursor = Cursor::new(&envelope[3..]);
+	let read = R1CSProof::read_framed(&mut cursor, proof_len).unwrap();
+	assert_eq!(cursor.position() as usize, 4 + proof_len);
+	assert_eq!(read.to_bytes(), proof.to_bytes());
+
+	// A reader split inside the prefix and inside an element.
+	let frame = &envelope[3..7 + proof_len];
+	let pieces = (&frame[..3]).chain(&frame[3..50]).chain(&frame[50..]);
+	let read = R1CSProof::read_framed(pieces, proof_len).unwrap();
+	assert!(example_gadget_verify(&pc_gens, &bp_gens, 9, read, commitments).is_ok());
+
+	assert!(R1CSProof::read_framed(&frame[..frame.len() - 1], proof_len).is_err());
+	assert!(R1CSProof::read_framed(frame, proof_len - 1).is_err());
+}
+
 #[test]
 fn example_gadget_test() {
 	// (3 + 4) * (6 + 1) = (40 + 9)
@@ -344,6 +394,217 @@ fn example_gadget_serialization_test() {
 	assert!(example_gadget_roundtrip_serialization_helper(3, 4, 6, 1, 40, 10).is_err());
 }
 
+#[test]
+fn decompressed_commitments_verify_like_compressed_ones() {
+	use curve25519_dalek::ristretto::RistrettoPoint;
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(128, 1);
+
+	// (3 + 4) * (6 + 1) = (40 + 9)
+	let (proof, commitments) = example_gadget_proof(&pc_gens, &bp_gens, 3, 4, 6, 1, 40, 9).unwrap();
+	let points: Vec<RistrettoPoint> = commitments.iter().map(|V| V.decompress().unwrap()).collect();
+
+	// Commits the i-th commitment decompressed if `decompressed(i)`.
+	let verify = |points: &[RistrettoPoint], decompressed: &dyn Fn(usize) -> bool| {
+		let mut transcript = Transcript::new(b"R1CSExampleGadget");
+		let mut verifier = Verifier::new(&mut transcript);
+		let vars: Vec<_> = points
+			.iter()
+			.enumerate()
+			.map(|(i, V)| {
+				if decompressed(i) {
+					verifier.commit_decompressed(*V)
+				} else {
+					verifier.commit(V.compress())
+				}
+			})
+			.collect();
+		example_gadget(
+			&mut verifier,
+			vars[0].into(),
+			vars[1].into(),
+			vars[2].into(),
+			vars[3].into(),
+			vars[4].into(),
+			Scalar::from(9u64).into(),
+		);
+		verifier.verify(&proof, &pc_gens, &bp_gens)
+	};
+
+	assert!(verify(&points, &|_| true).is_ok());
+	assert!(verify(&points, &|i| i % 2 == 0).is_ok());
+	assert!(example_gadget_verify(&pc_gens, &bp_gens, 9, proof.clone(), commitments).is_ok());
+
+	let mut other_points = points.clone();
+	other_points[4] += pc_gens.B;
+	assert!(matches!(
+		verify(&other_points, &|_| true),
+		Err(R1CSError::VerificationError)
+	));
+}
+
+#[cfg(feature = "transcript-audit")]
+#[test]
+fn transcript_tapes_of_prover_and_verifier_match() {
+	use webb_bulletproofs::{TranscriptOperation, TranscriptTape};
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(8, 1);
+	let input: Vec<Scalar> = (0..4u64).map(Scalar::from).collect();
+	let output: Vec<Scalar> = input.iter().rev().cloned().collect();
+
+	let ((proof, input_commitments, output_commitments), prover_tape) = TranscriptTape::record(|| {
+		let mut transcript = Transcript::new(b"ShuffleProofTest");
+		ShuffleProof::prove(&pc_gens, &bp_gens, &mut transcript, &input, &output).unwrap()
+	});
+
+	let verify = |output_commitments: &Vec<CompressedRistretto>| {
+		TranscriptTape::record(|| {
+			let mut transcript = Transcript::new(b"ShuffleProofTest");
+			proof.verify(
+				&pc_gens,
+				&bp_gens,
+				&mut transcript,
+				&input_commitments,
+				output_commitments,
+			)
+		})
+	};
+
+	let (result, verifier_tape) = verify(&output_commitments);
+	assert!(result.is_ok());
+	assert!(!prover_tape.is_empty());
+	assert_eq!(prover_tape, verifier_tape);
+	assert_eq!(prover_tape.first_divergence(&verifier_tape), None);
+
+	// Reordering the outputs changes the commitments absorbed before the
+	// first challenge, which is where the tapes diverge.
+	let reordered: Vec<CompressedRistretto> = output_commitments.iter().rev().cloned().collect();
+	let (_, other_tape) = verify(&reordered);
+	let first_challenge = prover_tape
+		.entries()
+		.iter()
+		.position(|entry| entry.operation == TranscriptOperation::Challenge)
+		.unwrap();
+	assert_eq!(prover_tape.first_divergence(&other_tape), Some(first_challenge));
+	let (expected, actual) = (
+		&prover_tape.entries()[first_challenge],
+		&other_tape.entries()[first_challenge],
+	);
+	assert_eq!((expected.label, expected.length), (actual.label, actual.length));
+	assert_ne!(expected.challenge, actual.challenge);
+}
+
+#[test]
+fn observers_see_every_phase_in_order() {
+	use webb_bulletproofs::r1cs::{Phase, ProverObserver, Stats};
+
+	#[derive(Debug, PartialEq)]
+	enum Event {
+		Start(Phase),
+		End(Phase),
+		Stats(Stats),
+	}
+
+	/// Records the callbacks it receives.
+	#[derive(Default)]
+	struct RecordingObserver(RefCell<Vec<Event>>);
+
+	impl ProverObserver for RecordingObserver {
+		fn on_phase_start(&self, phase: Phase) {
+			self.0.borrow_mut().push(Event::Start(phase));
+		}
+
+		fn on_phase_end(&self, phase: Phase) {
+			self.0.borrow_mut().push(Event::End(phase));
+		}
+
+		fn on_stats(&self, stats: &Stats) {
+			self.0.borrow_mut().push(Event::Stats(*stats));
+		}
+	}
+
+	/// Returns the phases of `events` in order, and the stats.
+	fn phases(events: Vec<Event>) -> (Vec<Event>, Vec<Stats>) {
+		let (stats, phases): (Vec<_>, Vec<_>) = events.into_iter().partition(|e| matches!(e, Event::Stats(_)));
+		let stats = stats
+			.into_iter()
+			.map(|e| match e {
+				Event::Stats(stats) => stats,
+				_ => unreachable!(),
+			})
+			.collect();
+		(phases, stats)
+	}
+
+	// The shuffle gadget has randomized constraints.
+	let k = 4;
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(16, 1);
+	let input: Vec<Scalar> = (0..k as u64).map(Scalar::from).collect();
+	let output: Vec<Scalar> = input.iter().rev().cloned().collect();
+
+	let prover_observer = RecordingObserver::default();
+	let mut transcript = Transcript::new(b"ObserverTest");
+	let mut prover = Prover::new(&pc_gens, &mut transcript);
+	prover.set_observer(&prover_observer);
+	let (input_commitments, input_vars): (Vec<_>, Vec<_>) = input
+		.iter()
+		.map(|v| prover.commit(*v, Scalar::random(&mut thread_rng())))
+		.unzip();
+	let (output_commitments, output_vars): (Vec<_>, Vec<_>) = output
+		.iter()
+		.map(|v| prover.commit(*v, Scalar::random(&mut thread_rng())))
+		.unzip();
+	ShuffleProof::gadget(&mut prover, input_vars, output_vars).unwrap();
+	let proof = prover.prove(&bp_gens).unwrap();
+
+	let verifier_observer = RecordingObserver::default();
+	let mut transcript = Transcript::new(b"ObserverTest");
+	let mut verifier = Verifier::new(&mut transcript);
+	verifier.set_observer(&verifier_observer);
+	let input_vars = verifier.commit_vec(&input_commitments);
+	let output_vars = verifier.commit_vec(&output_commitments);
+	ShuffleProof::gadget(&mut verifier, input_vars, output_vars).unwrap();
+	assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_ok());
+
+	let (prover_phases, prover_stats) = phases(prover_observer.0.into_inner());
+	let expected: Vec<Event> = [
+		Phase::FirstPhaseCommitment,
+		Phase::Synthesis,
+		Phase::SecondPhaseCommitment,
+		Phase::Flattening,
+		Phase::Polynomial,
+		Phase::InnerProduct,
+	]
+	.iter()
+	.flat_map(|&phase| vec![Event::Start(phase), Event::End(phase)])
+	.collect();
+	assert_eq!(prover_phases, expected);
+
+	let (verifier_phases, verifier_stats) = phases(verifier_observer.0.into_inner());
+	let expected: Vec<Event> = [Phase::Synthesis, Phase::Flattening, Phase::Multiscalar]
+		.iter()
+		.flat_map(|&phase| vec![Event::Start(phase), Event::End(phase)])
+		.collect();
+	assert_eq!(verifier_phases, expected);
+
+	// Both see the full circuit, including the randomized multipliers.
+	assert_eq!(prover_stats.len(), 1);
+	assert_eq!(verifier_stats.len(), 1);
+	assert_eq!(prover_stats[0].metrics, verifier_stats[0].metrics);
+	let metrics = verifier_stats[0].metrics;
+	assert_eq!(metrics.committed, 2 * k);
+	assert_eq!(
+		verifier_stats[0].multiscalar_terms,
+		estimated_verification_msm_size(metrics.multipliers, metrics.committed)
+	);
+	// The multipliers are all allocated in the randomized phase, so the
+	// first-phase commitments only have their blinding terms.
+	assert_eq!(prover_stats[0].multiscalar_terms, 3 + (3 + 5 * metrics.multipliers));
+}
+
 // Range Proof gadget
 
 /// Enforces that the quantity of v is in the range [0, 2^n).
@@ -431,3 +692,4062 @@ fn range_proof_helper(v_val: u64, n: usize) -> Result<(), R1CSError> {
 	// Verifier verifies proof
 	Ok(verifier.verify(&proof, &pc_gens, &bp_gens)?)
 }
+
+#[test]
+fn prover_checkpoint_restore() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(128, 1);
+	let mut rng = rand::thread_rng();
+	let (v_a, v_b) = (200u64, 3000u64);
+
+	let mut original_transcript = Transcript::new(b"CheckpointTest");
+	let mut restored_transcript = Transcript::new(b"CheckpointTest");
+
+	// Prover's scope
+	let (proof, commitments) = {
+		let mut prover = Prover::new(&pc_gens, &mut original_transcript);
+
+		let (com_a, var_a) = prover.commit(v_a.into(), Scalar::random(&mut rng));
+		let (com_b, var_b) = prover.commit(v_b.into(), Scalar::random(&mut rng));
+		assert!(range_proof(&mut prover, var_a.into(), Some(v_a), 16).is_ok());
+
+		// Checkpoint halfway through synthesis and drop the original prover.
+		let checkpoint = prover.checkpoint();
+		drop(prover);
+
+		let mut prover = Prover::restore(checkpoint, &mut restored_transcript);
+		assert!(range_proof(&mut prover, var_b.into(), Some(v_b), 16).is_ok());
+
+		(prover.prove(&bp_gens).unwrap(), vec![com_a, com_b])
+	};
+
+	let mut verifier_transcript = Transcript::new(b"CheckpointTest");
+	let mut verifier = Verifier::new(&mut verifier_transcript);
+
+	let var_a = verifier.commit(commitments[0]);
+	let var_b = verifier.commit(commitments[1]);
+	assert!(range_proof(&mut verifier, var_a.into(), None, 16).is_ok());
+	assert!(range_proof(&mut verifier, var_b.into(), None, 16).is_ok());
+
+	assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_ok());
+}
+
+#[test]
+fn prover_checkpoint_restored_twice() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(16, 1);
+	let mut rng = rand::thread_rng();
+	let (x, y) = ([3u64, 5], [5u64, 3]);
+	let blindings: Vec<_> = (0..4).map(|_| Scalar::random(&mut rng)).collect();
+
+	// Commits to the `values`, and returns them as allocated scalars.
+	fn commit_all(
+		prover: &mut Prover,
+		values: &[u64],
+		blindings: &[Scalar],
+	) -> (Vec<CompressedRistretto>, Vec<AllocatedScalar>) {
+		values
+			.iter()
+			.zip(blindings.iter())
+			.map(|(v, v_blinding)| {
+				let (com, variable) = prover.commit((*v).into(), *v_blinding);
+				(com, AllocatedScalar {
+					variable,
+					assignment: Some((*v).into()),
+				})
+			})
+			.unzip()
+	}
+	let verify = |proof: &R1CSProof, commitments: &[CompressedRistretto]| {
+		let mut transcript = Transcript::new(b"CheckpointTwiceTest");
+		let mut verifier = Verifier::new(&mut transcript);
+		let vars: Vec<_> = commitments
+			.iter()
+			.map(|com| AllocatedScalar {
+				variable: verifier.commit(*com),
+				assignment: None,
+			})
+			.collect();
+		let (x_vars, y_vars) = vars.split_at(2);
+		scalar_shuffle_gadget(&mut verifier, x_vars.to_vec(), y_vars.to_vec())?;
+		verifier.verify(proof, &pc_gens, &bp_gens)
+	};
+
+	// The restored provers borrow their transcripts for as long as the
+	// original prover borrows its own.
+	let mut restored_transcripts: Vec<_> = (0..4).map(|_| Transcript::new(b"CheckpointTwiceTest")).collect();
+	let mut restored_transcripts = restored_transcripts.iter_mut();
+
+	// A checkpoint taken before the randomized constraints are specified
+	// can be proven from any number of times.
+	let mut transcript = Transcript::new(b"CheckpointTwiceTest");
+	let mut prover = Prover::new(&pc_gens, &mut transcript);
+	let (x_coms, x_vars) = commit_all(&mut prover, &x, &blindings[..2]);
+	let (y_coms, y_vars) = commit_all(&mut prover, &y, &blindings[2..]);
+	let commitments = [x_coms, y_coms].concat();
+	for _ in 0..2 {
+		let mut restored = Prover::restore(prover.checkpoint(), restored_transcripts.next().unwrap());
+		scalar_shuffle_gadget(&mut restored, x_vars.clone(), y_vars.clone()).unwrap();
+		let proof = restored.prove(&bp_gens).unwrap();
+		assert_eq!(verify(&proof, &commitments), Ok(()));
+	}
+
+	// Once specified, the randomized constraints are shared by the prover,
+	// its checkpoint and every restored prover, and only the first of them
+	// to prove can synthesize them.
+	scalar_shuffle_gadget(&mut prover, x_vars, y_vars).unwrap();
+	let first = Prover::restore(prover.checkpoint(), restored_transcripts.next().unwrap());
+	let second = Prover::restore(prover.checkpoint(), restored_transcripts.next().unwrap());
+
+	let proof = first.prove(&bp_gens).unwrap();
+	assert_eq!(verify(&proof, &commitments), Ok(()));
+	assert_eq!(
+		second.prove(&bp_gens).err(),
+		Some(R1CSError::RandomizedConstraintsConsumed)
+	);
+	assert_eq!(
+		prover.prove(&bp_gens).err(),
+		Some(R1CSError::RandomizedConstraintsConsumed)
+	);
+}
+
+#[test]
+fn circuit_hash_matches_between_prover_and_verifier() {
+	let pc_gens = PedersenGens::default();
+	let mut rng = rand::thread_rng();
+
+	// Range proof gadget
+	let mut prover_transcript = Transcript::new(b"CircuitHashTest");
+	let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+	let (com, var) = prover.commit(1234u64.into(), Scalar::random(&mut rng));
+	assert!(range_proof(&mut prover, var.into(), Some(1234), 32).is_ok());
+
+	let mut verifier_transcript = Transcript::new(b"CircuitHashTest");
+	let mut verifier = Verifier::new(&mut verifier_transcript);
+	let var = verifier.commit(com);
+	assert!(range_proof(&mut verifier, var.into(), None, 32).is_ok());
+
+	assert_eq!(prover.num_constraints(), verifier.num_constraints());
+	assert_eq!(verifier.num_multipliers(), 32);
+	let range_proof_hash = prover.circuit_hash();
+	assert_eq!(range_proof_hash, verifier.circuit_hash());
+
+	// Example gadget
+	let mut prover_transcript = Transcript::new(b"CircuitHashTest");
+	let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+	let (coms, vars): (Vec<_>, Vec<_>) = [3u64, 4, 6, 1, 40]
+		.iter()
+		.map(|x| prover.commit(Scalar::from(*x), Scalar::random(&mut rng)))
+		.unzip();
+	example_gadget(
+		&mut prover,
+		vars[0].into(),
+		vars[1].into(),
+		vars[2].into(),
+		vars[3].into(),
+		vars[4].into(),
+		Scalar::from(9u64).into(),
+	);
+
+	let mut verifier_transcript = Transcript::new(b"CircuitHashTest");
+	let mut verifier = Verifier::new(&mut verifier_transcript);
+	let vars: Vec<_> = coms.iter().map(|V| verifier.commit(*V)).collect();
+	example_gadget(
+		&mut verifier,
+		vars[0].into(),
+		vars[1].into(),
+		vars[2].into(),
+		vars[3].into(),
+		vars[4].into(),
+		Scalar::from(9u64).into(),
+	);
+
+	assert_eq!(prover.num_constraints(), verifier.num_constraints());
+	assert_eq!(prover.num_multipliers(), verifier.num_multipliers());
+	assert_eq!(prover.circuit_hash(), verifier.circuit_hash());
+	assert_ne!(prover.circuit_hash(), range_proof_hash);
+}
+
+#[test]
+fn verification_key_for_range_proof() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(128, 1);
+	let n = 32;
+
+	let vk = VerificationKey::from_circuit(
+		1,
+		|verifier, vars| range_proof(verifier, vars[0].into(), None, n),
+		&bp_gens,
+		&pc_gens,
+	)
+	.unwrap();
+	assert_eq!(vk.num_committed(), 1);
+	assert_eq!(vk.num_multipliers(), n);
+
+	let mut rng = rand::thread_rng();
+	for v in [0u64, 1, 1234, std::u32::MAX as u64].iter() {
+		let mut prover_transcript = Transcript::new(b"VerificationKeyTest");
+		let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+		let (com, var) = prover.commit((*v).into(), Scalar::random(&mut rng));
+		assert!(range_proof(&mut prover, var.into(), Some(*v), n).is_ok());
+		let proof = prover.prove(&bp_gens).unwrap();
+
+		let mut verifier_transcript = Transcript::new(b"VerificationKeyTest");
+		assert!(vk.verify(&mut verifier_transcript, &[com], &proof).is_ok());
+
+		// A different commitment or transcript must not verify.
+		let other = pc_gens.commit((*v).into(), Scalar::random(&mut rng)).compress();
+		let mut verifier_transcript = Transcript::new(b"VerificationKeyTest");
+		assert!(vk.verify(&mut verifier_transcript, &[other], &proof).is_err());
+		let mut verifier_transcript = Transcript::new(b"OtherTest");
+		assert!(vk.verify(&mut verifier_transcript, &[com], &proof).is_err());
+	}
+}
+
+#[test]
+fn precomputed_verification_key_matches_generic_path() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(128, 1);
+	let n = 32;
+	let mut rng = rand::thread_rng();
+
+	let vk = VerificationKey::from_circuit(
+		1,
+		|verifier, vars| range_proof(verifier, vars[0].into(), None, n),
+		&bp_gens,
+		&pc_gens,
+	)
+	.unwrap();
+	let mut precomputed = vk.clone();
+	precomputed.precompute();
+	assert!(!vk.is_precomputed());
+	assert!(precomputed.is_precomputed());
+
+	for v in [0u64, 1, 1234, std::u32::MAX as u64].iter() {
+		let mut prover_transcript = Transcript::new(b"PrecomputedKeyTest");
+		let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+		let (com, var) = prover.commit((*v).into(), Scalar::random(&mut rng));
+		assert!(range_proof(&mut prover, var.into(), Some(*v), n).is_ok());
+		let proof = prover.prove(&bp_gens).unwrap();
+
+		let mut tampered = proof.to_bytes();
+		tampered[1 + 11 * 32] ^= 1;
+		let tampered = R1CSProof::from_bytes(&tampered).unwrap();
+		let other = pc_gens.commit((*v).into(), Scalar::random(&mut rng)).compress();
+
+		for (label, com, proof) in [
+			(&b"PrecomputedKeyTest"[..], com, &proof),
+			(&b"OtherTest"[..], com, &proof),
+			(&b"PrecomputedKeyTest"[..], other, &proof),
+			(&b"PrecomputedKeyTest"[..], com, &tampered),
+		]
+		.iter()
+		{
+			let generic = vk.verify(&mut Transcript::new(label), &[*com], proof);
+			let fast = precomputed.verify(&mut Transcript::new(label), &[*com], proof);
+			assert_eq!(generic, fast);
+		}
+		let mut verifier_transcript = Transcript::new(b"PrecomputedKeyTest");
+		assert!(precomputed.verify(&mut verifier_transcript, &[com], &proof).is_ok());
+	}
+}
+
+#[test]
+fn verification_key_rejects_randomized_constraints() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(128, 1);
+
+	let vk = VerificationKey::from_circuit(
+		4,
+		|verifier, vars| ShuffleProof::gadget(verifier, vars[..2].to_vec(), vars[2..].to_vec()),
+		&bp_gens,
+		&pc_gens,
+	);
+	assert!(vk.is_err());
+}
+
+fn range_proof_pre_verify(proof: &R1CSProof, com: CompressedRistretto, n: usize) -> Result<PreVerified, R1CSError> {
+	let mut verifier_transcript = Transcript::new(b"PreVerifyTest");
+	let mut verifier = Verifier::new(&mut verifier_transcript);
+	let var = verifier.commit(com);
+	range_proof(&mut verifier, var.into(), None, n)?;
+	verifier.pre_verify(proof)
+}
+
+#[test]
+fn pre_verify_rejects_malformed_proofs() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64, 1);
+	let n = 32;
+	let v = 1234u64;
+
+	let (proof, com) = {
+		let mut prover_transcript = Transcript::new(b"PreVerifyTest");
+		let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+		let (com, var) = prover.commit(v.into(), Scalar::random(&mut thread_rng()));
+		assert!(range_proof(&mut prover, var.into(), Some(v), n).is_ok());
+		(prover.prove(&bp_gens).unwrap(), com)
+	};
+
+	// A well-formed proof passes both stages.
+	let pre_verified = range_proof_pre_verify(&proof, com, n).unwrap();
+	assert!(pre_verified.finish(&pc_gens, &bp_gens).is_ok());
+
+	// A proof for a circuit of a different size is caught before the multiexp.
+	assert!(matches!(
+		range_proof_pre_verify(&proof, com, 8),
+		Err(R1CSError::FormatError)
+	));
+
+	let bytes = proof.to_bytes();
+
+	// An invalid encoding of T_1, which follows the version byte and the
+	// three first-phase commitments.
+	let mut bad_point = bytes.clone();
+	bad_point[1 + 3 * 32..1 + 4 * 32].copy_from_slice(&[0xff; 32]);
+	let bad_point = R1CSProof::from_bytes(&bad_point).unwrap();
+	assert!(matches!(
+		range_proof_pre_verify(&bad_point, com, n),
+		Err(R1CSError::FormatError)
+	));
+
+	// An inner-product proof with a missing (L, R) pair.
+	let mut truncated = bytes.clone();
+	let ipp_end = truncated.len() - 2 * 32;
+	truncated.drain(ipp_end - 2 * 32..ipp_end);
+	let truncated = R1CSProof::from_bytes(&truncated).unwrap();
+	assert!(matches!(
+		range_proof_pre_verify(&truncated, com, n),
+		Err(R1CSError::FormatError)
+	));
+
+	// An invalid commitment.
+	let bad_com = CompressedRistretto([0xff; 32]);
+	assert!(matches!(
+		range_proof_pre_verify(&proof, bad_com, n),
+		Err(R1CSError::FormatError)
+	));
+
+	// A well-formed proof for another value only fails in the final check.
+	let other_com = pc_gens
+		.commit((v + 1).into(), Scalar::random(&mut thread_rng()))
+		.compress();
+	let pre_verified = range_proof_pre_verify(&proof, other_com, n).unwrap();
+	assert!(matches!(
+		pre_verified.finish(&pc_gens, &bp_gens),
+		Err(R1CSError::VerificationError)
+	));
+}
+
+#[test]
+fn verification_terms_evaluate_like_verify() {
+	use curve25519_dalek::traits::IsIdentity;
+	use webb_bulletproofs::{ProofError, TermPoint};
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64, 1);
+	let n = 32;
+	let v = 1234u64;
+
+	let (proof, com) = {
+		let mut prover_transcript = Transcript::new(b"PreVerifyTest");
+		let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+		let (com, var) = prover.commit(v.into(), Scalar::random(&mut thread_rng()));
+		assert!(range_proof(&mut prover, var.into(), Some(v), n).is_ok());
+		(prover.prove(&bp_gens).unwrap(), com)
+	};
+	let terms = |com: CompressedRistretto| {
+		let mut verifier_transcript = Transcript::new(b"PreVerifyTest");
+		let mut verifier = Verifier::new(&mut verifier_transcript);
+		let var = verifier.commit(com);
+		range_proof(&mut verifier, var.into(), None, n)?;
+		verifier.verification_terms(&proof)
+	};
+
+	let valid = terms(com).unwrap();
+	let (scalars, points) = valid.scalars_and_points(&pc_gens, &bp_gens).unwrap();
+	let evaluation: curve25519_dalek::ristretto::RistrettoPoint = scalars
+		.iter()
+		.zip(points.iter())
+		.map(|(scalar, point)| scalar * point)
+		.sum();
+	assert!(evaluation.is_identity());
+	assert_eq!(valid.verify(&pc_gens, &bp_gens), Ok(()));
+
+	// The terms identify the points of the proof and the generators of the
+	// single party share.
+	assert_eq!(scalars.len(), valid.terms().count());
+	assert_eq!(
+		valid
+			.proof_point(TermPoint::Commitment(0))
+			.map(|point| point.compress()),
+		Some(com)
+	);
+	assert!(valid.proof_point(TermPoint::Proof(b"A_O2")).is_some());
+	assert!(valid.proof_point(TermPoint::R(4)).is_some());
+	assert_eq!(valid.proof_point(TermPoint::R(5)), None);
+	assert_eq!(valid.proof_point(TermPoint::G { share: 0, index: 0 }), None);
+	assert!(valid
+		.terms()
+		.any(|(_, point)| point == TermPoint::H { share: 0, index: 31 }));
+	assert!(!valid
+		.terms()
+		.any(|(_, point)| point == TermPoint::G { share: 1, index: 0 }));
+
+	// The terms of the proof against another commitment reject, as the
+	// verifier does.
+	let other_com = pc_gens
+		.commit((v + 1).into(), Scalar::random(&mut thread_rng()))
+		.compress();
+	let invalid = terms(other_com).unwrap();
+	assert_eq!(invalid.verify(&pc_gens, &bp_gens), Err(ProofError::VerificationError));
+	assert!(matches!(
+		range_proof_pre_verify(&proof, other_com, n)
+			.unwrap()
+			.finish(&pc_gens, &bp_gens),
+		Err(R1CSError::VerificationError)
+	));
+	assert!(matches!(
+		terms(CompressedRistretto([0xff; 32])),
+		Err(R1CSError::FormatError)
+	));
+}
+
+#[test]
+fn verify_rejects_wrong_number_of_rounds() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64, 1);
+	let n = 32;
+	let v = 1234u64;
+
+	let (proof, com) = {
+		let mut prover_transcript = Transcript::new(b"WrongRoundsTest");
+		let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+		let (com, var) = prover.commit(v.into(), Scalar::random(&mut thread_rng()));
+		assert!(range_proof(&mut prover, var.into(), Some(v), n).is_ok());
+		(prover.prove(&bp_gens).unwrap(), com)
+	};
+	let verify = |proof: &R1CSProof| {
+		let mut verifier_transcript = Transcript::new(b"WrongRoundsTest");
+		let mut verifier = Verifier::new(&mut verifier_transcript);
+		let var = verifier.commit(com);
+		range_proof(&mut verifier, var.into(), None, n)?;
+		verifier.verify(proof, &pc_gens, &bp_gens)
+	};
+	assert!(verify(&proof).is_ok());
+
+	// Remove or repeat the last (L, R) pair, which precedes the scalars a
+	// and b at the end of the encoding.
+	let bytes = proof.to_bytes();
+	let end = bytes.len() - 2 * 32;
+	let pair = &bytes[end - 2 * 32..end];
+	let fewer = [&bytes[..end - 2 * 32], &bytes[end..]].concat();
+	let more = [&bytes[..end], pair, &bytes[end..]].concat();
+	for changed in [fewer, more].iter() {
+		let changed = R1CSProof::from_bytes(changed).unwrap();
+		assert!(matches!(verify(&changed), Err(R1CSError::FormatError)));
+	}
+}
+
+#[test]
+fn oversized_proofs_are_rejected_early() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64, 1);
+
+	let (proof, com) = {
+		let mut prover_transcript = Transcript::new(b"OversizedTest");
+		let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+		let (com, var) = prover.commit(7u64.into(), Scalar::random(&mut thread_rng()));
+		assert!(range_proof(&mut prover, var.into(), Some(7), 32).is_ok());
+		(prover.prove(&bp_gens).unwrap(), com)
+	};
+	let start = Instant::now();
+	let mut verifier_transcript = Transcript::new(b"OversizedTest");
+	let mut verifier = Verifier::new(&mut verifier_transcript);
+	let var = verifier.commit(com);
+	assert!(range_proof(&mut verifier, var.into(), None, 32).is_ok());
+	assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_ok());
+	let verify_time = start.elapsed();
+
+	// A well-formed one-phase encoding claiming 2^20 rounds, 64 MiB long,
+	// is rejected from its length alone.
+	let mut bytes = proof.to_bytes();
+	bytes.truncate(1 + 11 * 32);
+	bytes.resize(1 + 11 * 32 + (2 << 20) * 32 + 2 * 32, 0);
+	let start = Instant::now();
+	for _ in 0..100 {
+		assert!(matches!(R1CSProof::from_bytes(&bytes), Err(R1CSError::FormatError)));
+	}
+	let reject_time = start.elapsed();
+	println!(
+		"Rejection time: {:?}, verification time: {:?}",
+		reject_time, verify_time
+	);
+	assert!(reject_time < verify_time);
+}
+
+#[test]
+fn proofs_roundtrip_through_bytes() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64, 1);
+
+	// A proof with one phase, for 32 multipliers.
+	let (one_phase, _) = {
+		let mut prover_transcript = Transcript::new(b"RoundtripTest");
+		let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+		let (com, var) = prover.commit(7u64.into(), Scalar::random(&mut thread_rng()));
+		assert!(range_proof(&mut prover, var.into(), Some(7), 32).is_ok());
+		(prover.prove(&bp_gens).unwrap(), com)
+	};
+	// A proof with a randomized phase, for 10 multipliers padded to 16.
+	let input: Vec<Scalar> = (0..6u64).map(Scalar::from).collect();
+	let mut output = input.clone();
+	output.reverse();
+	let (ShuffleProof(two_phase), ..) = ShuffleProof::prove(
+		&pc_gens,
+		&bp_gens,
+		&mut Transcript::new(b"RoundtripTest"),
+		&input,
+		&output,
+	)
+	.unwrap();
+
+	for (proof, rounds) in [(&one_phase, 5), (&two_phase, 4)].iter() {
+		let bytes = proof.to_bytes();
+		assert_eq!(bytes.len(), proof.serialized_size());
+		assert_eq!(proof.ipp_proof().rounds(), *rounds);
+		let parsed = R1CSProof::from_bytes(&bytes).unwrap();
+		assert_eq!(&&parsed, proof);
+		assert_eq!(parsed.clone(), parsed);
+	}
+	assert_ne!(one_phase, two_phase);
+}
+
+#[test]
+fn custom_pedersen_gens_do_not_verify_under_defaults() {
+	use curve25519_dalek::ristretto::RistrettoPoint;
+	use sha3::Sha3_512;
+
+	let custom_gens = PedersenGens::from_points(
+		RistrettoPoint::hash_from_bytes::<Sha3_512>(b"custom value base"),
+		RistrettoPoint::hash_from_bytes::<Sha3_512>(b"custom blinding base"),
+	)
+	.unwrap();
+	let bp_gens = BulletproofGens::new(64, 1);
+	let (n, v) = (32, 1234u64);
+
+	let (proof, com) = {
+		let mut prover_transcript = Transcript::new(b"CustomGensTest");
+		let mut prover = Prover::new(&custom_gens, &mut prover_transcript);
+		let (com, var) = prover.commit(v.into(), Scalar::random(&mut thread_rng()));
+		assert!(range_proof(&mut prover, var.into(), Some(v), n).is_ok());
+		(prover.prove(&bp_gens).unwrap(), com)
+	};
+
+	for (pc_gens, expected) in [(custom_gens, true), (PedersenGens::default(), false)].iter() {
+		let mut verifier_transcript = Transcript::new(b"CustomGensTest");
+		let mut verifier = Verifier::new(&mut verifier_transcript);
+		let var = verifier.commit(com);
+		assert!(range_proof(&mut verifier, var.into(), None, n).is_ok());
+		assert_eq!(verifier.verify(&proof, pc_gens, &bp_gens).is_ok(), *expected);
+	}
+}
+
+#[test]
+fn domain_separated_pedersen_gens() {
+	let asset_a = PedersenGens::new(b"asset A");
+	let asset_b = PedersenGens::new(b"asset B");
+	let bp_gens = BulletproofGens::new(16, 1);
+	let value = 9000u64;
+
+	let (proof, commitment) = {
+		let mut prover_transcript = Transcript::new(b"DomainSeparatedGens");
+		let mut prover = Prover::new(&asset_a, &mut prover_transcript);
+		let (com, var) = prover.commit(value.into(), Scalar::random(&mut thread_rng()));
+		assert!(range_proof(&mut prover, var.into(), Some(value), 16).is_ok());
+		(prover.prove(&bp_gens).unwrap(), com)
+	};
+
+	// The proof only verifies under the label it was made with.
+	let verify = |pc_gens: &PedersenGens| {
+		let mut verifier_transcript = Transcript::new(b"DomainSeparatedGens");
+		let mut verifier = Verifier::new(&mut verifier_transcript);
+		let var = verifier.commit(commitment);
+		range_proof(&mut verifier, var.into(), None, 16).unwrap();
+		verifier.verify(&proof, pc_gens, &bp_gens)
+	};
+	assert!(verify(&asset_a).is_ok());
+	assert!(verify(&asset_b).is_err());
+	assert!(verify(&PedersenGens::default()).is_err());
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn parallel_msm_matches_serial() {
+	use curve25519_dalek::{ristretto::RistrettoPoint, traits::VartimeMultiscalarMul};
+	use webb_bulletproofs::{DefaultMsmBackend, MsmBackend};
+
+	let pools: Vec<_> = [1, 3, 8]
+		.iter()
+		.map(|&threads| rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap())
+		.collect();
+
+	// Sizes below, at and above the threshold for splitting, and sizes
+	// which do not divide evenly between the threads.
+	let mut rng = thread_rng();
+	for &size in [0, 1, 2047, 2048, 5001].iter() {
+		let scalars: Vec<Scalar> = (0..size).map(|_| Scalar::random(&mut rng)).collect();
+		let points: Vec<RistrettoPoint> = (0..size).map(|_| RistrettoPoint::random(&mut rng)).collect();
+		let expected = RistrettoPoint::vartime_multiscalar_mul(&scalars, &points);
+		for pool in pools.iter() {
+			assert_eq!(
+				pool.install(|| DefaultMsmBackend.vartime_msm(&scalars, &points)),
+				expected
+			);
+		}
+	}
+
+	// A circuit of 2048 multipliers, whose verification is split.
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(2048, 1);
+	let (proof, commitments) = {
+		let mut prover_transcript = Transcript::new(b"ParallelMsmTest");
+		let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+		let commitments: Vec<_> = (0..32u64)
+			.map(|v| {
+				let (com, var) = prover.commit(v.into(), Scalar::random(&mut rng));
+				assert!(range_proof(&mut prover, var.into(), Some(v), 64).is_ok());
+				com
+			})
+			.collect();
+		(prover.prove(&bp_gens).unwrap(), commitments)
+	};
+	let verify = |commitments: &[CompressedRistretto]| {
+		let mut verifier_transcript = Transcript::new(b"ParallelMsmTest");
+		let mut verifier = Verifier::new(&mut verifier_transcript);
+		for com in commitments.iter() {
+			let var = verifier.commit(*com);
+			range_proof(&mut verifier, var.into(), None, 64)?;
+		}
+		verifier.verify(&proof, &pc_gens, &bp_gens)
+	};
+	let mut wrong_commitments = commitments.clone();
+	wrong_commitments.swap(0, 1);
+	for pool in pools.iter() {
+		assert!(pool.install(|| verify(&commitments)).is_ok());
+		assert!(matches!(
+			pool.install(|| verify(&wrong_commitments)),
+			Err(R1CSError::VerificationError)
+		));
+	}
+}
+
+#[test]
+fn verify_with_counting_backend() {
+	use curve25519_dalek::ristretto::RistrettoPoint;
+	use std::cell::Cell;
+	use webb_bulletproofs::{DefaultMsmBackend, MsmBackend};
+
+	struct CountingBackend(Cell<usize>);
+
+	impl MsmBackend for CountingBackend {
+		fn vartime_msm(&self, scalars: &[Scalar], points: &[RistrettoPoint]) -> RistrettoPoint {
+			self.0.set(self.0.get() + 1);
+			DefaultMsmBackend.vartime_msm(scalars, points)
+		}
+	}
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64, 1);
+	let (n, v) = (32, 1234u64);
+
+	let (proof, com) = {
+		let mut prover_transcript = Transcript::new(b"BackendTest");
+		let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+		let (com, var) = prover.commit(v.into(), Scalar::random(&mut thread_rng()));
+		assert!(range_proof(&mut prover, var.into(), Some(v), n).is_ok());
+		(prover.prove(&bp_gens).unwrap(), com)
+	};
+
+	let backend = CountingBackend(Cell::new(0));
+	let mut verifier_transcript = Transcript::new(b"BackendTest");
+	let mut verifier = Verifier::new(&mut verifier_transcript);
+	let var = verifier.commit(com);
+	assert!(range_proof(&mut verifier, var.into(), None, n).is_ok());
+	assert!(verifier
+		.verify_with_backend(&proof, &pc_gens, &bp_gens, &mut thread_rng(), Some(&backend))
+		.is_ok());
+	assert_eq!(backend.0.get(), 1);
+
+	let vk = VerificationKey::from_circuit(
+		1,
+		|verifier, vars| range_proof(verifier, vars[0].into(), None, n),
+		&bp_gens,
+		&pc_gens,
+	)
+	.unwrap();
+	let mut verifier_transcript = Transcript::new(b"BackendTest");
+	assert!(vk
+		.verify_with_backend(
+			&mut verifier_transcript,
+			&[com],
+			&proof,
+			&mut thread_rng(),
+			Some(&backend)
+		)
+		.is_ok());
+	assert_eq!(backend.0.get(), 2);
+}
+
+#[test]
+fn prove_with_progress_and_cancellation() {
+	use webb_bulletproofs::{CancellationToken, Milestone, ProgressSink};
+
+	struct Recorder<'a> {
+		milestones: RefCell<Vec<(Milestone, f64)>>,
+		cancel_at: Option<&'a CancellationToken>,
+	}
+
+	impl<'a> ProgressSink for Recorder<'a> {
+		fn report(&self, milestone: Milestone, fraction: f64) {
+			self.milestones.borrow_mut().push((milestone, fraction));
+			if let Some(token) = self.cancel_at {
+				token.cancel();
+			}
+		}
+	}
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64, 1);
+	let (n, v) = (32, 1234u64);
+	let mut prover_transcript = Transcript::new(b"ProgressTest");
+	let new_prover = |transcript| {
+		let mut prover = Prover::new(&pc_gens, transcript);
+		let (_, var) = prover.commit(v.into(), Scalar::random(&mut thread_rng()));
+		assert!(range_proof(&mut prover, var.into(), Some(v), n).is_ok());
+		prover
+	};
+
+	// Without cancellation, all milestones are reported in order.
+	let recorder = Recorder {
+		milestones: RefCell::new(Vec::new()),
+		cancel_at: None,
+	};
+	let token = CancellationToken::new();
+	let proof = new_prover(&mut prover_transcript).prove_with_progress(&bp_gens, Some(&recorder), Some(&token));
+	assert!(proof.is_ok());
+	let milestones = recorder.milestones.into_inner();
+	assert_eq!(milestones.len(), 3 + 5);
+	assert_eq!(milestones[0].0, Milestone::WitnessBuilt);
+	assert_eq!(milestones[1].0, Milestone::WitnessCommitted);
+	assert_eq!(milestones[2].0, Milestone::PolynomialCommitted);
+	assert_eq!(milestones[7].0, Milestone::InnerProductRound { round: 4, rounds: 5 });
+	assert!(milestones.windows(2).all(|w| w[0].1 < w[1].1));
+	assert_eq!(milestones[7].1, 1.0);
+
+	// Cancelling after the first milestone aborts the proof.
+	let token = CancellationToken::new();
+	let recorder = Recorder {
+		milestones: RefCell::new(Vec::new()),
+		cancel_at: Some(&token),
+	};
+	let mut prover_transcript = Transcript::new(b"ProgressTest");
+	let proof = new_prover(&mut prover_transcript).prove_with_progress(&bp_gens, Some(&recorder), Some(&token));
+	assert_eq!(proof.err(), Some(R1CSError::Cancelled));
+	assert_eq!(recorder.milestones.into_inner().len(), 1);
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn tracing_spans_record_circuit_sizes() {
+	use std::{
+		collections::BTreeMap,
+		fmt,
+		sync::{
+			atomic::{AtomicU64, Ordering},
+			Arc, Mutex,
+		},
+	};
+	use tracing::{
+		field::{Field, Visit},
+		span, Event, Metadata, Subscriber,
+	};
+
+	type Spans = Arc<Mutex<Vec<(&'static str, BTreeMap<&'static str, String>)>>>;
+
+	struct Fields<'a>(&'a mut BTreeMap<&'static str, String>);
+
+	impl<'a> Visit for Fields<'a> {
+		fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+			self.0.insert(field.name(), format!("{:?}", value));
+		}
+	}
+
+	struct Capture {
+		spans: Spans,
+		next_id: AtomicU64,
+	}
+
+	impl Subscriber for Capture {
+		fn enabled(&self, _: &Metadata<'_>) -> bool {
+			true
+		}
+
+		fn new_span(&self, span: &span::Attributes<'_>) -> span::Id {
+			let mut fields = BTreeMap::new();
+			span.record(&mut Fields(&mut fields));
+			self.spans.lock().unwrap().push((span.metadata().name(), fields));
+			span::Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed))
+		}
+
+		fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+
+		fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+		fn event(&self, _: &Event<'_>) {}
+
+		fn enter(&self, _: &span::Id) {}
+
+		fn exit(&self, _: &span::Id) {}
+	}
+
+	let spans = Spans::default();
+	let subscriber = Capture {
+		spans: spans.clone(),
+		next_id: AtomicU64::new(1),
+	};
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(16, 1);
+	let input = [3u64, 7, 5, 9].iter().map(|&x| Scalar::from(x)).collect::<Vec<_>>();
+	let output = [9u64, 3, 7, 5].iter().map(|&x| Scalar::from(x)).collect::<Vec<_>>();
+
+	tracing::subscriber::with_default(subscriber, || {
+		let mut prover_transcript = Transcript::new(b"TracingTest");
+		let (proof, in_commitments, out_commitments) =
+			ShuffleProof::prove(&pc_gens, &bp_gens, &mut prover_transcript, &input, &output).unwrap();
+		let mut verifier_transcript = Transcript::new(b"TracingTest");
+		assert!(proof
+			.verify(
+				&pc_gens,
+				&bp_gens,
+				&mut verifier_transcript,
+				&in_commitments,
+				&out_commitments
+			)
+			.is_ok());
+	});
+
+	let spans = spans.lock().unwrap();
+	let find = |name: &str, field: &str, value: &str| {
+		spans
+			.iter()
+			.any(|(n, fields)| *n == name && fields.get(field).map(String::as_str) == Some(value))
+	};
+
+	// The shuffle of 4 values has no first-phase multipliers
+	// and 6 randomized multipliers, padded to 8.
+	assert!(find("r1cs_prove", "m", "8"));
+	assert!(spans.iter().any(|(name, _)| *name == "r1cs_synthesis"));
+	assert!(find("r1cs_commit", "n", "6"));
+	assert!(find("r1cs_flatten", "n", "6"));
+	assert!(find("r1cs_t_poly", "n", "6"));
+	assert!(find("ipp_create", "n", "8"));
+	assert!(find("r1cs_pre_verify", "m", "8"));
+	// 8 commitments, 11 proof points, 2 * 3 IPP points, B, B_blinding
+	// and 2 * 8 generators.
+	assert!(find("r1cs_multiexp", "size", &(8 + 11 + 6 + 2 + 16).to_string()));
+
+	// Only sizes are recorded.
+	let size_fields = ["m", "n", "n1", "phase", "constraints", "size"];
+	for (_, fields) in spans.iter() {
+		for field in fields.keys() {
+			assert!(size_fields.contains(field), "unexpected field {}", field);
+		}
+	}
+}
+
+#[test]
+fn predicted_sizes_match_proofs() {
+	use curve25519_dalek::ristretto::RistrettoPoint;
+	use std::cell::Cell;
+	use webb_bulletproofs::{DefaultMsmBackend, MsmBackend};
+
+	struct SizeBackend(Cell<usize>);
+
+	impl MsmBackend for SizeBackend {
+		fn vartime_msm(&self, scalars: &[Scalar], points: &[RistrettoPoint]) -> RistrettoPoint {
+			self.0.set(points.len());
+			DefaultMsmBackend.vartime_msm(scalars, points)
+		}
+	}
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64, 1);
+
+	// Circuits without randomized constraints.
+	for &bits in [0, 1, 3, 8, 16].iter() {
+		for m in 1..4 {
+			let mut prover_transcript = Transcript::new(b"SizeTest");
+			let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+			let commitments: Vec<_> = (0..m)
+				.map(|_| {
+					let (com, var) = prover.commit(Scalar::zero(), Scalar::random(&mut thread_rng()));
+					assert!(range_proof(&mut prover, var.into(), Some(0), bits).is_ok());
+					com
+				})
+				.collect();
+			let estimated_size = prover.estimated_proof_size();
+			let proof = prover.prove(&bp_gens).unwrap();
+			assert_eq!(proof.to_bytes().len(), proof_size(bits * m, m));
+			assert_eq!(proof.to_bytes().len(), estimated_size);
+
+			let backend = SizeBackend(Cell::new(0));
+			let mut verifier_transcript = Transcript::new(b"SizeTest");
+			let mut verifier = Verifier::new(&mut verifier_transcript);
+			for com in commitments.iter() {
+				let var = verifier.commit(*com);
+				assert!(range_proof(&mut verifier, var.into(), None, bits).is_ok());
+			}
+			assert!(verifier
+				.verify_with_backend(&proof, &pc_gens, &bp_gens, &mut thread_rng(), Some(&backend))
+				.is_ok());
+			assert_eq!(backend.0.get(), estimated_verification_msm_size(bits * m, m));
+		}
+	}
+
+	// The shuffle allocates all of its 2(k-1) multipliers in the randomized phase.
+	for k in 1..6 {
+		let input: Vec<Scalar> = (0..k).map(|i| Scalar::from(i as u64)).collect();
+		let output: Vec<Scalar> = input.iter().rev().cloned().collect();
+		let mut prover_transcript = Transcript::new(b"SizeTest");
+		let (proof, in_commitments, out_commitments) =
+			ShuffleProof::prove(&pc_gens, &bp_gens, &mut prover_transcript, &input, &output).unwrap();
+		let n = 2 * (k - 1);
+		let predicted = if n == 0 {
+			proof_size(n, 2 * k)
+		} else {
+			randomized_proof_size(n, 2 * k)
+		};
+		assert_eq!(proof.0.to_bytes().len(), predicted);
+		assert_eq!(proof.0.serialized_size(), predicted);
+
+		let backend = SizeBackend(Cell::new(0));
+		let mut verifier_transcript = Transcript::new(b"SizeTest");
+		verifier_transcript.append_message(b"dom-sep", b"ShuffleProof");
+		verifier_transcript.append_u64(b"k", k as u64);
+		let mut verifier = Verifier::new(&mut verifier_transcript);
+		let input_vars: Vec<_> = in_commitments.iter().map(|V| verifier.commit(*V)).collect();
+		let output_vars: Vec<_> = out_commitments.iter().map(|V| verifier.commit(*V)).collect();
+		assert!(ShuffleProof::gadget(&mut verifier, input_vars, output_vars).is_ok());
+		assert!(verifier
+			.verify_with_backend(&proof.0, &pc_gens, &bp_gens, &mut thread_rng(), Some(&backend))
+			.is_ok());
+		assert_eq!(backend.0.get(), estimated_verification_msm_size(n, 2 * k));
+	}
+}
+
+/// Proves and verifies that the value committed in `total` is the sum
+/// of the values committed in `parts`, each given as `(value, blinding)`.
+fn conservation_roundtrip(parts: &[(u64, Scalar)], total: (u64, Scalar)) -> Result<(), R1CSError> {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(8, 1);
+
+	let (proof, part_commitments, total_commitment) = {
+		let mut prover_transcript = Transcript::new(b"ConservationTest");
+		let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+		let (part_commitments, part_vars): (Vec<_>, Vec<_>) = parts
+			.iter()
+			.map(|&(v, v_blinding)| {
+				let (com, var) = prover.commit(v.into(), v_blinding);
+				(com, AllocatedScalar {
+					variable: var,
+					assignment: Some(v.into()),
+				})
+			})
+			.unzip();
+		let (total_commitment, total_var) = prover.commit(total.0.into(), total.1);
+		let total_var = AllocatedScalar {
+			variable: total_var,
+			assignment: Some(total.0.into()),
+		};
+		conservation_gadget(&mut prover, &part_vars, total_var)?;
+		(prover.prove(&bp_gens)?, part_commitments, total_commitment)
+	};
+
+	let mut verifier_transcript = Transcript::new(b"ConservationTest");
+	let mut verifier = Verifier::new(&mut verifier_transcript);
+	let part_vars: Vec<_> = part_commitments
+		.iter()
+		.map(|com| AllocatedScalar {
+			variable: verifier.commit(*com),
+			assignment: None,
+		})
+		.collect();
+	let total_var = AllocatedScalar {
+		variable: verifier.commit(total_commitment),
+		assignment: None,
+	};
+	conservation_gadget(&mut verifier, &part_vars, total_var)?;
+	verifier.verify(&proof, &pc_gens, &bp_gens)
+}
+
+#[test]
+fn conservation_gadget_and_commitment_sum() {
+	let pc_gens = PedersenGens::default();
+	let mut rng = thread_rng();
+	let blindings: Vec<Scalar> = (0..3).map(|_| Scalar::random(&mut rng)).collect();
+	let parts: Vec<(u64, Scalar)> = [3u64, 5, 9].iter().cloned().zip(blindings.iter().cloned()).collect();
+	let part_commitments: Vec<_> = parts
+		.iter()
+		.map(|&(v, v_blinding)| pc_gens.commit(v.into(), v_blinding).compress())
+		.collect();
+
+	// The blinding of the total is the sum of the blindings of the parts:
+	// both the homomorphic check and the circuit accept.
+	let summed_blinding: Scalar = blindings.iter().sum();
+	let total_commitment = pc_gens.commit(17u64.into(), summed_blinding).compress();
+	assert!(check_commitment_sum(&total_commitment, &part_commitments));
+	assert!(conservation_roundtrip(&parts, (17, summed_blinding)).is_ok());
+
+	// The total is committed with an independent blinding: the values are
+	// conserved, but only the in-circuit constraint can show it.
+	let independent_blinding = Scalar::random(&mut rng);
+	let total_commitment = pc_gens.commit(17u64.into(), independent_blinding).compress();
+	assert!(!check_commitment_sum(&total_commitment, &part_commitments));
+	assert!(conservation_roundtrip(&parts, (17, independent_blinding)).is_ok());
+
+	// The values are not conserved: both paths reject.
+	let total_commitment = pc_gens.commit(18u64.into(), summed_blinding).compress();
+	assert!(!check_commitment_sum(&total_commitment, &part_commitments));
+	match conservation_roundtrip(&parts, (18, independent_blinding)) {
+		Err(R1CSError::GadgetError { .. }) => {}
+		_ => panic!("the prover should refuse unbalanced values"),
+	}
+
+	// An invalid point is rejected.
+	let invalid = CompressedRistretto([0xff; 32]);
+	assert!(!check_commitment_sum(&invalid, &part_commitments));
+}
+
+/// Proves that `v` is in range in its own proof, returning the proof,
+/// the commitment and the transcript after proving.
+fn range_proof_with_transcript(
+	label: &'static [u8],
+	v: u64,
+	v_blinding: Scalar,
+	n: usize,
+) -> (R1CSProof, CompressedRistretto, Transcript) {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64, 1);
+	let mut transcript = Transcript::new(label);
+	let (proof, com) = {
+		let mut prover = Prover::new(&pc_gens, &mut transcript);
+		let (com, var) = prover.commit(v.into(), v_blinding);
+		assert!(range_proof(&mut prover, var.into(), Some(v), n).is_ok());
+		(prover.prove(&bp_gens).unwrap(), com)
+	};
+	(proof, com, transcript)
+}
+
+/// Verifies a proof made by `range_proof_with_transcript`, returning the
+/// transcript after verification.
+fn verify_range_proof_with_transcript(
+	label: &'static [u8],
+	proof: &R1CSProof,
+	com: CompressedRistretto,
+	n: usize,
+) -> Transcript {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64, 1);
+	let mut transcript = Transcript::new(label);
+	{
+		let mut verifier = Verifier::new(&mut transcript);
+		let var = verifier.commit(com);
+		assert!(range_proof(&mut verifier, var.into(), None, n).is_ok());
+		assert!(verifier.verify(proof, &pc_gens, &bp_gens).is_ok());
+	}
+	transcript
+}
+
+#[test]
+fn link_proof_across_proofs() {
+	let pc_gens = PedersenGens::default();
+	let mut rng = thread_rng();
+	let (v, blinding1, blinding2) = (1234u64, Scalar::random(&mut rng), Scalar::random(&mut rng));
+
+	// Two statements about the same balance, proven independently.
+	let (proof1, com1, mut prover_transcript1) = range_proof_with_transcript(b"Statement1", v, blinding1, 32);
+	let (proof2, com2, mut prover_transcript2) = range_proof_with_transcript(b"Statement2", v, blinding2, 16);
+	let link = LinkProof::prove(
+		v.into(),
+		blinding1,
+		blinding2,
+		&mut prover_transcript1,
+		&mut prover_transcript2,
+		&pc_gens,
+	);
+
+	let link = LinkProof::from_bytes(&link.to_bytes()).unwrap();
+	let link: LinkProof = bincode::deserialize(&bincode::serialize(&link).unwrap()).unwrap();
+
+	let mut transcript1 = verify_range_proof_with_transcript(b"Statement1", &proof1, com1, 32);
+	let mut transcript2 = verify_range_proof_with_transcript(b"Statement2", &proof2, com2, 16);
+	assert!(link
+		.verify(&com1, &com2, &mut transcript1, &mut transcript2, &pc_gens)
+		.is_ok());
+
+	// The link is bound to the order of the proofs.
+	let mut transcript1 = verify_range_proof_with_transcript(b"Statement1", &proof1, com1, 32);
+	let mut transcript2 = verify_range_proof_with_transcript(b"Statement2", &proof2, com2, 16);
+	assert!(link
+		.verify(&com2, &com1, &mut transcript2, &mut transcript1, &pc_gens)
+		.is_err());
+
+	// The link is bound to the proofs: it does not verify with fresh transcripts.
+	assert!(link
+		.verify(
+			&com1,
+			&com2,
+			&mut Transcript::new(b"Statement1"),
+			&mut Transcript::new(b"Statement2"),
+			&pc_gens
+		)
+		.is_err());
+
+	// A proof about a different value cannot be linked.
+	let (proof3, com3, mut prover_transcript3) = range_proof_with_transcript(b"Statement2", v + 1, blinding2, 16);
+	let mut prover_transcript1 = range_proof_with_transcript(b"Statement1", v, blinding1, 32).2;
+	let link = LinkProof::prove(
+		v.into(),
+		blinding1,
+		blinding2,
+		&mut prover_transcript1,
+		&mut prover_transcript3,
+		&pc_gens,
+	);
+	let mut transcript1 = verify_range_proof_with_transcript(b"Statement1", &proof1, com1, 32);
+	let mut transcript3 = verify_range_proof_with_transcript(b"Statement2", &proof3, com3, 16);
+	assert!(link
+		.verify(&com1, &com3, &mut transcript1, &mut transcript3, &pc_gens)
+		.is_err());
+
+	assert!(LinkProof::from_bytes(&[0u8; 5 * 32 - 1]).is_err());
+}
+
+/// A gadget with a bug: the prover allocates an extra variable that the
+/// verifier does not.
+fn asymmetric_gadget<CS: ConstraintSystem>(cs: &mut CS, x: Variable, x_val: Option<u64>) -> Result<(), R1CSError> {
+	let (_, _, square) = cs.multiply(x.into(), x.into());
+	if let Some(x_val) = x_val {
+		cs.allocate(Some(Scalar::from(x_val * x_val)))?;
+	}
+	cs.constrain(square - Scalar::from(9u64));
+	Ok(())
+}
+
+#[test]
+fn synthesis_trace_pinpoints_divergence() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(8, 1);
+	let mut rng = rand::thread_rng();
+
+	// Symmetric gadget: the traces match.
+	let mut prover_transcript = Transcript::new(b"SynthesisTraceTest");
+	let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+	prover.record_trace();
+	let (com, var) = prover.commit(1234u64.into(), Scalar::random(&mut rng));
+	assert!(range_proof(&mut prover, var.into(), Some(1234), 16).is_ok());
+
+	let mut verifier_transcript = Transcript::new(b"SynthesisTraceTest");
+	let mut verifier = Verifier::new(&mut verifier_transcript);
+	verifier.record_trace();
+	let var = verifier.commit(com);
+	assert!(range_proof(&mut verifier, var.into(), None, 16).is_ok());
+
+	let prover_trace = prover.trace().unwrap();
+	let verifier_trace = verifier.trace().unwrap();
+	assert!(!prover_trace.is_empty());
+	assert_eq!(prover_trace, verifier_trace);
+	assert!(diff(prover_trace, verifier_trace).is_empty());
+
+	// Asymmetric gadget: the first divergence is the extra allocation.
+	let mut prover_transcript = Transcript::new(b"SynthesisTraceTest");
+	let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+	prover.record_trace();
+	let (com, var) = prover.commit(3u64.into(), Scalar::random(&mut rng));
+	assert!(asymmetric_gadget(&mut prover, var, Some(3)).is_ok());
+
+	let mut verifier_transcript = Transcript::new(b"SynthesisTraceTest");
+	let mut verifier = Verifier::new(&mut verifier_transcript);
+	verifier.record_trace();
+	let var = verifier.commit(com);
+	assert!(asymmetric_gadget(&mut verifier, var, None).is_ok());
+
+	let square_constraint = Operation::Constrain {
+		terms: vec![Variable::MultiplierOutput(0), Variable::One()],
+	};
+	let divergences = diff(prover.trace().unwrap(), verifier.trace().unwrap());
+	assert_eq!(divergences.len(), 2);
+	assert_eq!(divergences[0], Divergence {
+		index: 4,
+		prover: Some(Operation::Allocate {
+			variable: Variable::MultiplierLeft(1),
+		}),
+		verifier: Some(square_constraint.clone()),
+	});
+	assert_eq!(divergences[1], Divergence {
+		index: 5,
+		prover: Some(square_constraint),
+		verifier: None,
+	});
+
+	let proof = prover.prove(&bp_gens).unwrap();
+	assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_err());
+}
+
+#[test]
+fn batch_verification_matches_sequential() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(16, 1);
+	let n = 16;
+	let batch_size = 100;
+	let mut rng = rand::thread_rng();
+
+	let (mut proofs, commitments): (Vec<_>, Vec<_>) = (0..batch_size)
+		.map(|i| {
+			let v = 1000 + i as u64;
+			let mut prover_transcript = Transcript::new(b"BatchVerificationTest");
+			let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+			let (com, var) = prover.commit(v.into(), Scalar::random(&mut rng));
+			assert!(range_proof(&mut prover, var.into(), Some(v), n).is_ok());
+			(prover.prove(&bp_gens).unwrap(), com)
+		})
+		.unzip();
+
+	let verify_sequential = |proofs: &[R1CSProof]| -> Vec<bool> {
+		proofs
+			.iter()
+			.zip(commitments.iter())
+			.map(|(proof, com)| {
+				let mut verifier_transcript = Transcript::new(b"BatchVerificationTest");
+				let mut verifier = Verifier::new(&mut verifier_transcript);
+				let var = verifier.commit(*com);
+				assert!(range_proof(&mut verifier, var.into(), None, n).is_ok());
+				verifier.verify(proof, &pc_gens, &bp_gens).is_ok()
+			})
+			.collect()
+	};
+	let verify_batch = |proofs: &[R1CSProof]| -> Result<(), R1CSError> {
+		let mut transcripts: Vec<_> = (0..proofs.len())
+			.map(|_| Transcript::new(b"BatchVerificationTest"))
+			.collect();
+		let instances = transcripts
+			.iter_mut()
+			.zip(proofs.iter().zip(commitments.iter()))
+			.map(|(verifier_transcript, (proof, com))| {
+				let mut verifier = Verifier::new(verifier_transcript);
+				let var = verifier.commit(*com);
+				assert!(range_proof(&mut verifier, var.into(), None, n).is_ok());
+				(verifier, proof)
+			})
+			.collect();
+		Verifier::verify_batch(instances, &pc_gens, &bp_gens)
+	};
+
+	// A valid batch verifies, faster than one proof at a time.
+	let start = Instant::now();
+	assert!(verify_sequential(&proofs).iter().all(|ok| *ok));
+	let sequential_time = start.elapsed();
+
+	let start = Instant::now();
+	assert!(verify_batch(&proofs).is_ok());
+	let batch_time = start.elapsed();
+
+	println!(
+		"Verification time for {} proofs: {:?} sequential, {:?} batched",
+		batch_size, sequential_time, batch_time
+	);
+	assert!(batch_time < sequential_time);
+
+	// A proof for another commitment is pinpointed in the batch.
+	proofs.swap(37, 38);
+	let sequential = verify_sequential(&proofs);
+	assert_eq!(
+		sequential
+			.iter()
+			.enumerate()
+			.filter(|(_, ok)| !**ok)
+			.map(|(i, _)| i)
+			.collect::<Vec<_>>(),
+		vec![37, 38]
+	);
+	assert_eq!(
+		verify_batch(&proofs),
+		Err(R1CSError::BatchVerificationError { index: 37 })
+	);
+
+	// An empty batch verifies trivially.
+	assert!(verify_batch(&[]).is_ok());
+}
+
+#[test]
+fn shuffle_proof_rejects_tampered_first_phase_commitments() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(16, 1);
+	let input: Vec<Scalar> = [3u64, 1, 4, 1, 5].iter().map(|v| Scalar::from(*v)).collect();
+	let output: Vec<Scalar> = [1u64, 5, 1, 3, 4].iter().map(|v| Scalar::from(*v)).collect();
+
+	let mut prover_transcript = Transcript::new(b"ShuffleProofTest");
+	let (proof, input_commitments, output_commitments) =
+		ShuffleProof::prove(&pc_gens, &bp_gens, &mut prover_transcript, &input, &output).unwrap();
+
+	let verify = |proof: &R1CSProof| {
+		let mut verifier_transcript = Transcript::new(b"ShuffleProofTest");
+		ShuffleProof(proof.clone()).verify(
+			&pc_gens,
+			&bp_gens,
+			&mut verifier_transcript,
+			&input_commitments,
+			&output_commitments,
+		)
+	};
+	assert!(verify(&proof.0).is_ok());
+
+	// The shuffle challenge is bound to A_I1, A_O1 and S1, which follow the
+	// version byte: replacing any of them must invalidate the proof.
+	let bytes = proof.0.to_bytes();
+	for offset in [1, 33, 65].iter() {
+		let mut tampered = bytes.clone();
+		tampered[*offset..*offset + 32].copy_from_slice(pc_gens.B.compress().as_bytes());
+		let tampered = R1CSProof::from_bytes(&tampered).unwrap();
+		assert!(verify(&tampered).is_err());
+	}
+}
+
+/// Constrains `x` to be a permutation of `y`.
+///
+/// The allocated scalars are moved into the randomized constraints
+/// callback, which consumes them.
+fn scalar_shuffle_gadget<CS: RandomizableConstraintSystem>(
+	cs: &mut CS,
+	x: Vec<AllocatedScalar>,
+	y: Vec<AllocatedScalar>,
+) -> Result<(), R1CSError> {
+	cs.specify_randomized_constraints(move |cs| {
+		let z = cs.challenge_scalar(b"scalar shuffle challenge");
+		let product = |cs: &mut CS::RandomizedCS, values: Vec<AllocatedScalar>| {
+			values
+				.into_iter()
+				.fold(LinearCombination::from(Scalar::one()), |acc, value| {
+					let (_, _, out) = cs.multiply(acc, value.variable - z);
+					out.into()
+				})
+		};
+		let x_product = product(cs, x);
+		let y_product = product(cs, y);
+		cs.constrain(x_product - y_product);
+		Ok(())
+	})
+}
+
+#[test]
+fn randomized_constraints_move_captures() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(16, 1);
+	let (x, y) = ([2u64, 7, 9], [9u64, 2, 7]);
+
+	let proof = {
+		let mut prover_transcript = Transcript::new(b"ScalarShuffleTest");
+		let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+		let mut allocate = |values: &[u64]| -> Vec<AllocatedScalar> {
+			values
+				.iter()
+				.map(|v| {
+					let assignment = Some(Scalar::from(*v));
+					AllocatedScalar {
+						variable: prover.allocate(assignment).unwrap(),
+						assignment,
+					}
+				})
+				.collect()
+		};
+		let (x, y) = (allocate(&x), allocate(&y));
+		assert!(scalar_shuffle_gadget(&mut prover, x, y).is_ok());
+		prover.prove(&bp_gens).unwrap()
+	};
+
+	let mut verifier_transcript = Transcript::new(b"ScalarShuffleTest");
+	let mut verifier = Verifier::new(&mut verifier_transcript);
+	let mut allocate = |n: usize| -> Vec<AllocatedScalar> {
+		(0..n)
+			.map(|_| AllocatedScalar {
+				variable: verifier.allocate(None).unwrap(),
+				assignment: None,
+			})
+			.collect()
+	};
+	let (x, y) = (allocate(x.len()), allocate(y.len()));
+	assert!(scalar_shuffle_gadget(&mut verifier, x, y).is_ok());
+	assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_ok());
+}
+
+#[test]
+fn metrics_of_known_gadgets() {
+	let pc_gens = PedersenGens::default();
+	let mut rng = rand::thread_rng();
+
+	let mut prover_transcript = Transcript::new(b"MetricsTest");
+	let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+	assert_eq!(prover.metrics(), Metrics {
+		multipliers: 0,
+		constraints: 0,
+		committed: 0,
+		padded_n: 1,
+	});
+
+	// The example gadget: one multiplier and three constraints.
+	let vars: Vec<_> = [3u64, 4, 6, 1, 40]
+		.iter()
+		.map(|x| prover.commit(Scalar::from(*x), Scalar::random(&mut rng)).1)
+		.collect();
+	example_gadget(
+		&mut prover,
+		vars[0].into(),
+		vars[1].into(),
+		vars[2].into(),
+		vars[3].into(),
+		vars[4].into(),
+		Scalar::from(9u64).into(),
+	);
+	let before = prover.metrics();
+	assert_eq!(before, Metrics {
+		multipliers: 1,
+		constraints: 3,
+		committed: 5,
+		padded_n: 1,
+	});
+
+	// A 16-bit range proof: one multiplier per bit, two constraints per bit
+	// and one for the bit decomposition.
+	let (com, var) = prover.commit(1234u64.into(), Scalar::random(&mut rng));
+	assert!(range_proof(&mut prover, var.into(), Some(1234), 16).is_ok());
+	let after = prover.metrics();
+	assert_eq!(after.multipliers - before.multipliers, 16);
+	assert_eq!(after.constraints - before.constraints, 33);
+	assert_eq!(after.committed - before.committed, 1);
+	assert_eq!(after.padded_n, 32);
+
+	let mut verifier_transcript = Transcript::new(b"MetricsTest");
+	let mut verifier = Verifier::new(&mut verifier_transcript);
+	let var = verifier.commit(com);
+	assert!(range_proof(&mut verifier, var.into(), None, 16).is_ok());
+	assert_eq!(verifier.metrics(), Metrics {
+		multipliers: 16,
+		constraints: 33,
+		committed: 1,
+		padded_n: 16,
+	});
+}
+
+#[test]
+fn undersized_generators_report_required_capacity() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(128, 1);
+	let small_gens = BulletproofGens::new(8, 1);
+	let mut rng = rand::thread_rng();
+
+	// A 24-bit range proof has 24 multipliers, padded to 32.
+	let mut prover_transcript = Transcript::new(b"GeneratorsLengthTest");
+	let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+	let (_, var) = prover.commit(1234u64.into(), Scalar::random(&mut rng));
+	assert!(range_proof(&mut prover, var.into(), Some(1234), 24).is_ok());
+	let err = prover.prove(&small_gens).unwrap_err();
+	assert_eq!(err, R1CSError::InvalidGeneratorsLength {
+		required: 32,
+		actual: 8
+	});
+	assert_eq!(
+		err.to_string(),
+		"Invalid generators size, too few generators for proof: 32 required, 8 available"
+	);
+
+	let mut prover_transcript = Transcript::new(b"GeneratorsLengthTest");
+	let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+	let (com, var) = prover.commit(1234u64.into(), Scalar::random(&mut rng));
+	assert!(range_proof(&mut prover, var.into(), Some(1234), 24).is_ok());
+	let proof = prover.prove(&bp_gens).unwrap();
+
+	let mut verifier_transcript = Transcript::new(b"GeneratorsLengthTest");
+	let mut verifier = Verifier::new(&mut verifier_transcript);
+	let var = verifier.commit(com);
+	assert!(range_proof(&mut verifier, var.into(), None, 24).is_ok());
+	assert_eq!(
+		verifier.verify(&proof, &pc_gens, &small_gens),
+		Err(R1CSError::InvalidGeneratorsLength {
+			required: 32,
+			actual: 8
+		})
+	);
+}
+
+#[test]
+fn two_party_aggregated_range_proof() {
+	use webb_bulletproofs::r1cs::aggregation::{self, dealer::Dealer, party::Party};
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(16, 2);
+	let (n, m) = (16, 2);
+	let values = [1234u64, 65535];
+
+	// Each party synthesizes the shared circuit over its own input.
+	let mut rng = thread_rng();
+	let mut party_transcripts = vec![Transcript::new(b"Party0"), Transcript::new(b"Party1")];
+	let mut commitments = Vec::new();
+	let mut parties = Vec::new();
+	for (v, party_transcript) in values.iter().zip(party_transcripts.iter_mut()) {
+		let mut prover = Prover::new(&pc_gens, party_transcript);
+		let (com, var) = prover.commit((*v).into(), Scalar::random(&mut rng));
+		range_proof(&mut prover, var.into(), Some(*v), n).unwrap();
+		commitments.push(com);
+		parties.push(Party::new(&bp_gens, prover, n).unwrap());
+	}
+
+	let mut dealer_transcript = Transcript::new(b"AggregationTest");
+	let dealer = Dealer::new(&bp_gens, &pc_gens, &mut dealer_transcript, n, m).unwrap();
+
+	let (parties, wire_commitments): (Vec<_>, Vec<_>) = parties
+		.into_iter()
+		.enumerate()
+		.map(|(j, party)| party.assign_position(j).unwrap())
+		.unzip();
+	let (dealer, wire_challenge) = dealer.receive_wire_commitments(wire_commitments).unwrap();
+
+	let (parties, poly_commitments): (Vec<_>, Vec<_>) = parties
+		.into_iter()
+		.map(|party| party.apply_challenge(&wire_challenge).unwrap())
+		.unzip();
+	let (dealer, poly_challenge) = dealer.receive_poly_commitments(poly_commitments).unwrap();
+
+	let proof_shares: Vec<_> = parties
+		.into_iter()
+		.map(|party| party.apply_challenge(&poly_challenge).unwrap())
+		.collect();
+	let proof = dealer.receive_shares(&proof_shares).unwrap();
+
+	let gadget = |verifier: &mut Verifier, vars: Vec<Variable>| range_proof(verifier, vars[0].into(), None, n);
+
+	let mut verifier_transcript = Transcript::new(b"AggregationTest");
+	assert!(aggregation::verify(
+		&proof,
+		&bp_gens,
+		&pc_gens,
+		&mut verifier_transcript,
+		&commitments,
+		n,
+		m,
+		gadget
+	)
+	.is_ok());
+
+	// The commitments are bound to the positions of their parties.
+	let swapped = [commitments[1], commitments[0]];
+	let mut verifier_transcript = Transcript::new(b"AggregationTest");
+	assert!(aggregation::verify(
+		&proof,
+		&bp_gens,
+		&pc_gens,
+		&mut verifier_transcript,
+		&swapped,
+		n,
+		m,
+		gadget
+	)
+	.is_err());
+
+	let mut verifier_transcript = Transcript::new(b"OtherTest");
+	assert!(aggregation::verify(
+		&proof,
+		&bp_gens,
+		&pc_gens,
+		&mut verifier_transcript,
+		&commitments,
+		n,
+		m,
+		gadget
+	)
+	.is_err());
+
+	// A mismatched shape is reported as an input error, not as a proof
+	// which does not verify.
+	let mut verifier_transcript = Transcript::new(b"AggregationTest");
+	assert!(matches!(
+		aggregation::verify(
+			&proof,
+			&bp_gens,
+			&pc_gens,
+			&mut verifier_transcript,
+			&commitments,
+			n / 2,
+			m,
+			gadget
+		),
+		Err(R1CSError::GadgetError { .. })
+	));
+}
+
+#[test]
+fn r1cs_proof_serde_roundtrip() {
+	use webb_bulletproofs::InnerProductProof;
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(16, 1);
+	let n = 16;
+
+	let mut prover_transcript = Transcript::new(b"SerdeTest");
+	let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+	let (com, var) = prover.commit(1234u64.into(), Scalar::random(&mut thread_rng()));
+	range_proof(&mut prover, var.into(), Some(1234), n).unwrap();
+	let proof = prover.prove(&bp_gens).unwrap();
+	let bytes = proof.to_bytes();
+
+	let from_json: R1CSProof = serde_json::from_str(&serde_json::to_string(&proof).unwrap()).unwrap();
+	let from_bincode: R1CSProof = bincode::deserialize(&bincode::serialize(&proof).unwrap()).unwrap();
+	for decoded in [from_json, from_bincode].iter() {
+		assert_eq!(decoded.to_bytes(), bytes);
+		let mut verifier_transcript = Transcript::new(b"SerdeTest");
+		let mut verifier = Verifier::new(&mut verifier_transcript);
+		let var = verifier.commit(com);
+		range_proof(&mut verifier, var.into(), None, n).unwrap();
+		assert!(verifier.verify(decoded, &pc_gens, &bp_gens).is_ok());
+	}
+
+	// The inner-product proof follows the version byte, 8 points and 3 scalars.
+	let ipp = InnerProductProof::from_bytes(&bytes[1 + 11 * 32..]).unwrap();
+	let from_json: InnerProductProof = serde_json::from_str(&serde_json::to_string(&ipp).unwrap()).unwrap();
+	let from_bincode: InnerProductProof = bincode::deserialize(&bincode::serialize(&ipp).unwrap()).unwrap();
+	assert_eq!(from_json.to_bytes(), ipp.to_bytes());
+	assert_eq!(from_bincode.to_bytes(), ipp.to_bytes());
+
+	// Malformed input is rejected with an error.
+	let mut non_canonical = bytes.clone();
+	non_canonical[1 + 8 * 32..1 + 9 * 32].copy_from_slice(&[0xff; 32]);
+	assert!(serde_json::from_str::<R1CSProof>(&serde_json::to_string(&non_canonical).unwrap()).is_err());
+	assert!(bincode::deserialize::<R1CSProof>(&bincode::serialize(&non_canonical).unwrap()).is_err());
+	assert!(bincode::deserialize::<R1CSProof>(&bincode::serialize(&bytes[..bytes.len() - 1]).unwrap()).is_err());
+	assert!(serde_json::from_str::<R1CSProof>("[1, 2, 3]").is_err());
+	assert!(serde_json::from_str::<R1CSProof>("[256]").is_err());
+	assert!(serde_json::from_str::<InnerProductProof>("\"proof\"").is_err());
+}
+
+#[test]
+fn compiled_verification_matches_range_proof_verifier() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(16, 1);
+	let n = 16;
+	let mut rng = rand::thread_rng();
+
+	let description =
+		CircuitDescription::from_circuit(1, |verifier, vars| range_proof(verifier, vars[0].into(), None, n)).unwrap();
+	assert_eq!(description.num_committed(), 1);
+	assert_eq!(description.num_multipliers(), n);
+
+	for v in [0u64, 1, 1234, std::u16::MAX as u64].iter() {
+		let mut prover_transcript = Transcript::new(b"CompiledTest");
+		let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+		let (com, var) = prover.commit((*v).into(), Scalar::random(&mut rng));
+		assert!(range_proof(&mut prover, var.into(), Some(*v), n).is_ok());
+		assert!(prover.check_circuit(&description).is_ok());
+		let proof = prover.prove(&bp_gens).unwrap();
+
+		let verify_direct = |com: CompressedRistretto| {
+			let mut verifier_transcript = Transcript::new(b"CompiledTest");
+			let mut verifier = Verifier::new(&mut verifier_transcript);
+			let var = verifier.commit(com);
+			assert!(range_proof(&mut verifier, var.into(), None, n).is_ok());
+			verifier.verify(&proof, &pc_gens, &bp_gens).is_ok()
+		};
+		let verify_compiled = |com: CompressedRistretto| {
+			let mut verifier_transcript = Transcript::new(b"CompiledTest");
+			Verifier::verify_compiled(
+				&mut verifier_transcript,
+				&description,
+				&proof,
+				&[com],
+				&pc_gens,
+				&bp_gens,
+			)
+			.is_ok()
+		};
+
+		assert!(verify_direct(com));
+		assert!(verify_compiled(com));
+
+		// Both paths reject a proof for another commitment.
+		let other = pc_gens.commit((*v).into(), Scalar::random(&mut rng)).compress();
+		assert!(!verify_direct(other));
+		assert!(!verify_compiled(other));
+	}
+
+	// A prover synthesizing another circuit is caught before proving.
+	let mut prover_transcript = Transcript::new(b"CompiledTest");
+	let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+	let (_, var) = prover.commit(12u64.into(), Scalar::random(&mut rng));
+	assert!(range_proof(&mut prover, var.into(), Some(12), 8).is_ok());
+	assert_eq!(prover.check_circuit(&description), Err(R1CSError::CircuitMismatch));
+}
+
+/// Constrains the committed `vars` by the linear equations of a
+/// Vandermonde matrix, \\(\sum_j (i + 1)^j x_j = b_i\\).
+fn vandermonde_gadget<CS: ConstraintSystem>(cs: &mut CS, vars: &[Variable], targets: &[Scalar]) {
+	for (i, b) in targets.iter().enumerate() {
+		let base = Scalar::from((i + 1) as u64);
+		let lc: LinearCombination = vars
+			.iter()
+			.scan(Scalar::one(), |power, var| {
+				let term = (*var, *power);
+				*power *= base;
+				Some(term)
+			})
+			.collect();
+		cs.constrain(lc - *b);
+	}
+}
+
+#[test]
+fn compiled_verification_skips_gadget_synthesis() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(1, 1);
+	let num_constraints = 1000;
+	let iterations = 100;
+	let mut rng = rand::thread_rng();
+
+	let values: Vec<Scalar> = (0..4).map(|_| Scalar::random(&mut rng)).collect();
+	let targets: Vec<Scalar> = (0..num_constraints)
+		.map(|i| {
+			let base = Scalar::from((i + 1) as u64);
+			values.iter().rev().fold(Scalar::zero(), |acc, x| acc * base + x)
+		})
+		.collect();
+
+	let mut prover_transcript = Transcript::new(b"CompiledTimingTest");
+	let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+	let (commitments, vars): (Vec<_>, Vec<_>) = values
+		.iter()
+		.map(|x| prover.commit(*x, Scalar::random(&mut rng)))
+		.unzip();
+	vandermonde_gadget(&mut prover, &vars, &targets);
+	let proof = prover.prove(&bp_gens).unwrap();
+
+	let description = CircuitDescription::from_circuit(4, |verifier, vars| {
+		vandermonde_gadget(verifier, &vars, &targets);
+		Ok(())
+	})
+	.unwrap();
+	assert_eq!(description.num_constraints(), num_constraints);
+
+	let start = Instant::now();
+	for _ in 0..iterations {
+		let mut verifier_transcript = Transcript::new(b"CompiledTimingTest");
+		let mut verifier = Verifier::new(&mut verifier_transcript);
+		let vars: Vec<_> = commitments.iter().map(|V| verifier.commit(*V)).collect();
+		vandermonde_gadget(&mut verifier, &vars, &targets);
+		assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_ok());
+	}
+	let direct_time = start.elapsed();
+
+	let start = Instant::now();
+	for _ in 0..iterations {
+		let mut verifier_transcript = Transcript::new(b"CompiledTimingTest");
+		assert!(Verifier::verify_compiled(
+			&mut verifier_transcript,
+			&description,
+			&proof,
+			&commitments,
+			&pc_gens,
+			&bp_gens
+		)
+		.is_ok());
+	}
+	let compiled_time = start.elapsed();
+
+	println!(
+		"Verification time for {} proofs of {} constraints: {:?} direct, {:?} compiled",
+		iterations, num_constraints, direct_time, compiled_time
+	);
+	assert!(compiled_time < direct_time);
+}
+
+/// Constrains the second input to be the square of the first, returning
+/// the output of the multiplier.
+struct Square;
+
+impl Gadget for Square {
+	fn assemble<CS: ConstraintSystem>(
+		&self,
+		cs: &mut CS,
+		inputs: &[AllocatedScalar],
+	) -> Result<Vec<AllocatedScalar>, R1CSError> {
+		let (x, y) = (inputs[0], inputs[1]);
+		let (_, _, x_squared) = cs.multiply(x.variable.into(), x.variable.into());
+		cs.constrain(y.variable - x_squared);
+		Ok(vec![AllocatedScalar {
+			variable: x_squared,
+			assignment: x.assignment.map(|x| x * x),
+		}])
+	}
+}
+
+#[test]
+fn trait_based_gadgets_roundtrip() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(16, 1);
+	let transcript = || Transcript::new(b"GadgetTraitTest");
+
+	// The conservation gadget, with the total as the last input.
+	let values: Vec<Scalar> = [3u64, 5, 9, 17].iter().map(|v| Scalar::from(*v)).collect();
+	let (proof, commitments) = prove_gadget(&Conservation, &pc_gens, &bp_gens, &mut transcript(), &values).unwrap();
+	assert!(verify_gadget(
+		&Conservation,
+		&pc_gens,
+		&bp_gens,
+		&mut transcript(),
+		&commitments,
+		&proof
+	)
+	.is_ok());
+
+	// Swapping a part with the total breaks the statement.
+	let mut swapped = commitments.clone();
+	swapped.swap(0, 3);
+	assert!(verify_gadget(&Conservation, &pc_gens, &bp_gens, &mut transcript(), &swapped, &proof).is_err());
+
+	// The prover refuses values which do not satisfy the gadget.
+	let unbalanced: Vec<Scalar> = [3u64, 5, 9, 18].iter().map(|v| Scalar::from(*v)).collect();
+	assert!(prove_gadget(&Conservation, &pc_gens, &bp_gens, &mut transcript(), &unbalanced).is_err());
+
+	// A gadget with multipliers and outputs.
+	let values = vec![Scalar::from(7u64), Scalar::from(49u64)];
+	let (proof, commitments) = prove_gadget(&Square, &pc_gens, &bp_gens, &mut transcript(), &values).unwrap();
+	assert!(verify_gadget(&Square, &pc_gens, &bp_gens, &mut transcript(), &commitments, &proof).is_ok());
+	assert!(verify_gadget(
+		&Conservation,
+		&pc_gens,
+		&bp_gens,
+		&mut transcript(),
+		&commitments,
+		&proof
+	)
+	.is_err());
+}
+
+/// Constrains `x` to be non-zero by constraining `x_inv` to be its inverse.
+fn nonzero_gadget<CS: ConstraintSystem>(cs: &mut CS, x: Variable, x_inv: Variable) {
+	let (_, _, product) = cs.multiply(x.into(), x_inv.into());
+	cs.constrain_labeled(product - Variable::One(), "is_nonzero");
+}
+
+/// Proves that the `values` are non-zero, either committing to their
+/// inverses or allocating them as low-level variables.
+fn nonzero_roundtrip(values: &[u64], commit_inverses: bool) -> (Metrics, Result<(), R1CSError>) {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(16, 1);
+	let mut rng = thread_rng();
+
+	let mut prover_transcript = Transcript::new(b"NonzeroTest");
+	let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+	let values: Vec<Scalar> = values.iter().map(|v| Scalar::from(*v)).collect();
+	let inverses: Vec<Scalar> = values.iter().map(|v| v.invert()).collect();
+	let (mut commitments, vars, _) = prover.commit_vec(&values, &mut rng);
+	let inverse_vars = if commit_inverses {
+		let (inverse_commitments, inverse_vars, _) = prover.commit_vec(&inverses, &mut rng);
+		commitments.extend(inverse_commitments);
+		inverse_vars
+	} else {
+		inverses
+			.iter()
+			.map(|inverse| prover.allocate(Some(*inverse)).unwrap())
+			.collect()
+	};
+	for (x, x_inv) in vars.iter().zip(inverse_vars.iter()) {
+		nonzero_gadget(&mut prover, *x, *x_inv);
+	}
+	let metrics = prover.metrics();
+	let proof = prover.prove(&bp_gens).unwrap();
+
+	let mut verifier_transcript = Transcript::new(b"NonzeroTest");
+	let mut verifier = Verifier::new(&mut verifier_transcript);
+	let vars = verifier.commit_vec(&commitments[..values.len()]);
+	let inverse_vars = if commit_inverses {
+		verifier.commit_vec(&commitments[values.len()..])
+	} else {
+		values.iter().map(|_| verifier.allocate(None).unwrap()).collect()
+	};
+	for (x, x_inv) in vars.iter().zip(inverse_vars.iter()) {
+		nonzero_gadget(&mut verifier, *x, *x_inv);
+	}
+	(metrics, verifier.verify(&proof, &pc_gens, &bp_gens))
+}
+
+#[test]
+fn allocated_witness_needs_no_commitment() {
+	let values = [3u64, 5, 7, 11];
+
+	let (committed, result) = nonzero_roundtrip(&values, true);
+	assert!(result.is_ok());
+	assert_eq!(committed.committed, 8);
+
+	// Allocating the inverses halves the commitments, and adds two
+	// multipliers for the four inverses.
+	let (allocated, result) = nonzero_roundtrip(&values, false);
+	assert!(result.is_ok());
+	assert_eq!(allocated.committed, 4);
+	assert_eq!(allocated.multipliers, committed.multipliers + 2);
+}
+
+#[test]
+fn simplified_constraints_verify() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(16, 1);
+	let values = [3u64, 5, 9];
+
+	// Opens each commitment to its value, with a redundant constraint
+	// built by repeated additions.
+	let gadget = |vars: &[Variable]| -> Vec<LinearCombination> {
+		vars.iter()
+			.zip(values.iter())
+			.map(|(x, v)| {
+				let lc = *x + *x - Scalar::from(2u64) * *x + (*x - Scalar::from(*v));
+				assert_eq!(lc.clone().get_terms().len(), 5);
+				let lc = lc.simplify();
+				assert_eq!(lc.clone().get_terms().len(), 2);
+				lc
+			})
+			.collect()
+	};
+
+	let mut prover_transcript = Transcript::new(b"SimplifyTest");
+	let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+	let (commitments, vars): (Vec<_>, Vec<_>) = values
+		.iter()
+		.map(|v| prover.commit((*v).into(), Scalar::random(&mut thread_rng())))
+		.unzip();
+	for lc in gadget(&vars) {
+		prover.constrain(lc);
+	}
+	let proof = prover.prove(&bp_gens).unwrap();
+
+	let mut verifier_transcript = Transcript::new(b"SimplifyTest");
+	let mut verifier = Verifier::new(&mut verifier_transcript);
+	let vars: Vec<_> = commitments.iter().map(|V| verifier.commit(*V)).collect();
+	for lc in gadget(&vars) {
+		verifier.constrain(lc);
+	}
+	assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_ok());
+}
+
+#[test]
+fn evaluate_catches_wrong_witness_before_proving() {
+	let pc_gens = PedersenGens::default();
+	let mut rng = thread_rng();
+
+	let mut prover_transcript = Transcript::new(b"EvaluateTest");
+	let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+	let (_, x) = prover.commit(6u64.into(), Scalar::random(&mut rng));
+
+	// The product of the multiplier matches x.
+	let (a, b, product) = prover.multiply(Scalar::from(2u64).into(), Scalar::from(3u64).into());
+	assert_eq!((a + b + Variable::One()).evaluate(&prover), Some(Scalar::from(6u64)));
+	assert_eq!((x - product).evaluate(&prover), Some(Scalar::zero()));
+
+	// A wrong witness for half of x is caught before it is constrained.
+	let half = prover.allocate(Some(Scalar::from(4u64))).unwrap();
+	let lc = x - half * Scalar::from(2u64);
+	assert_eq!(lc.evaluate(&prover), Some(-Scalar::from(2u64)));
+
+	// The verifier knows no assignments.
+	let mut verifier_transcript = Transcript::new(b"EvaluateTest");
+	let mut verifier = Verifier::new(&mut verifier_transcript);
+	let x = verifier.commit(pc_gens.commit(6u64.into(), Scalar::random(&mut rng)).compress());
+	assert_eq!((x + Variable::One()).evaluate(&verifier), None);
+}
+
+#[test]
+fn diagnostics_report_unsatisfied_constraint_labels() {
+	use rand_chacha::ChaChaRng;
+	use rand_core::SeedableRng;
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(16, 1);
+
+	// Commits x and y, and claims that x is non-zero and that x + y = 10.
+	let prove = |x: u64, x_inv: Scalar, diagnostics: bool, labeled: bool| {
+		let mut rng = ChaChaRng::from_seed([7u8; 32]);
+		let mut prover_transcript = Transcript::new(b"DiagnosticsTest");
+		let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+		if diagnostics {
+			prover.enable_diagnostics();
+		}
+		let (_, x_var) = prover.commit(x.into(), Scalar::random(&mut rng));
+		let (_, y_var) = prover.commit(3u64.into(), Scalar::random(&mut rng));
+		let sum = x_var + y_var - Scalar::from(10u64);
+		if labeled {
+			prover.constrain_labeled(sum, "sum");
+		} else {
+			prover.constrain(sum);
+		}
+		let x_inv_var = prover.allocate(Some(x_inv)).unwrap();
+		nonzero_gadget(&mut prover, x_var, x_inv_var);
+		let unsatisfied = prover.unsatisfied_constraints();
+		(unsatisfied, prover.prove_with_rng(&bp_gens, &mut rng))
+	};
+
+	// Labels do not change the proof.
+	let (unsatisfied, labeled) = prove(7, Scalar::from(7u64).invert(), true, true);
+	assert!(unsatisfied.is_empty());
+	let (_, unlabeled) = prove(7, Scalar::from(7u64).invert(), false, false);
+	assert_eq!(labeled.unwrap().to_bytes(), unlabeled.unwrap().to_bytes());
+
+	// A wrong inverse fails the labeled constraint of the gadget, which
+	// follows the two constraints on the inputs of its multiplier.
+	let (unsatisfied, result) = prove(7, Scalar::from(7u64), true, true);
+	assert_eq!(unsatisfied, vec![(3, Some("is_nonzero"))]);
+	assert_eq!(result.unwrap_err(), R1CSError::UnsatisfiedConstraint {
+		index: 3,
+		label: Some("is_nonzero")
+	});
+
+	// All unsatisfied constraints are listed, with `None` for unlabeled ones.
+	let (unsatisfied, result) = prove(0, Scalar::zero(), true, false);
+	assert_eq!(unsatisfied, vec![(0, None), (3, Some("is_nonzero"))]);
+	assert_eq!(result.unwrap_err(), R1CSError::UnsatisfiedConstraint {
+		index: 0,
+		label: None
+	});
+}
+
+#[test]
+fn is_satisfied_checks_witness_before_proving() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(16, 1);
+	let mut rng = thread_rng();
+
+	// Commits x = 7 and claims x * x_inv = 1 and x - c = 0.
+	let mut check = |x_inv: Scalar, c: u64| {
+		let mut prover_transcript = Transcript::new(b"IsSatisfiedTest");
+		let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+		prover.enable_diagnostics();
+		let (_, x) = prover.commit(7u64.into(), Scalar::random(&mut rng));
+		let x_inv = prover.allocate(Some(x_inv)).unwrap();
+		nonzero_gadget(&mut prover, x, x_inv);
+		prover.constrain(x - Scalar::from(c));
+		let satisfied = prover.is_satisfied();
+		(satisfied, prover.prove(&bp_gens).is_ok())
+	};
+
+	// A satisfied circuit proves.
+	assert_eq!(check(Scalar::from(7u64).invert(), 7), (Ok(true), true));
+
+	// An unsatisfied linear constraint.
+	assert_eq!(check(Scalar::from(7u64).invert(), 8), (Ok(false), false));
+
+	// A wrong inverse breaks the constraint on the product.
+	assert_eq!(check(Scalar::from(7u64), 7), (Ok(false), false));
+
+	// A missing assignment is refused when allocating.
+	let mut prover_transcript = Transcript::new(b"IsSatisfiedTest");
+	let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+	let _ = prover.commit(7u64.into(), Scalar::random(&mut rng));
+	assert_eq!(
+		prover.allocate(None),
+		Err(R1CSError::MissingAssignment {
+			variable: Some(Variable::MultiplierLeft(0)),
+			context: None
+		})
+	);
+	assert_eq!(prover.is_satisfied(), Ok(true));
+}
+
+#[test]
+fn missing_assignments_identify_the_variable() {
+	let pc_gens = PedersenGens::default();
+	let mut rng = thread_rng();
+
+	// The prover names the variable it could not allocate.
+	let mut prover_transcript = Transcript::new(b"MissingAssignmentTest");
+	let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+	let _ = prover.allocate(Some(Scalar::one())).unwrap();
+	assert_eq!(
+		prover.allocate(None),
+		Err(R1CSError::MissingAssignment {
+			variable: Some(Variable::MultiplierRight(0)),
+			context: None
+		})
+	);
+	assert_eq!(
+		prover.allocate_multiplier(None),
+		Err(R1CSError::MissingAssignment {
+			variable: Some(Variable::MultiplierLeft(1)),
+			context: None
+		})
+	);
+
+	// Gadget code passes on the context of the missing value.
+	let mut verifier_transcript = Transcript::new(b"MissingAssignmentTest");
+	let mut verifier = Verifier::new(&mut verifier_transcript);
+	let x = AllocatedScalar {
+		variable: verifier.commit(pc_gens.commit(Scalar::one(), Scalar::random(&mut rng)).compress()),
+		assignment: None,
+	};
+	let err = x.required("the inverse of x").unwrap_err();
+	assert_eq!(err, R1CSError::MissingAssignment {
+		variable: Some(Variable::Committed(0)),
+		context: Some("the inverse of x")
+	});
+	assert_eq!(
+		err.to_string(),
+		"Variable Some(Committed(0)) does not have a value assignment (Some(\"the inverse of x\"))."
+	);
+	assert_eq!(
+		x.assignment.map(|x| x.invert()).required("the inverse of x"),
+		Err(R1CSError::MissingAssignment {
+			variable: None,
+			context: Some("the inverse of x")
+		})
+	);
+	assert_eq!(Some(Scalar::one()).required("one"), Ok(Scalar::one()));
+}
+
+#[test]
+fn commit_vec_matches_individual_commitments() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(16, 1);
+	let values: Vec<Scalar> = [3u64, 5, 7].iter().map(|v| Scalar::from(*v)).collect();
+
+	let mut prover_transcript = Transcript::new(b"CommitVecTest");
+	let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+	let (commitments, vars, blindings) = prover.commit_vec(&values, &mut thread_rng());
+	assert_eq!(vars, vec![
+		Variable::Committed(0),
+		Variable::Committed(1),
+		Variable::Committed(2)
+	]);
+	for ((V, v), v_blinding) in commitments.iter().zip(values.iter()).zip(blindings.iter()) {
+		assert_eq!(*V, pc_gens.commit(*v, *v_blinding).compress());
+	}
+	prover.constrain(vars[0] + vars[1] - vars[2] - Scalar::one());
+	let proof = prover.prove(&bp_gens).unwrap();
+
+	// The commitments are absorbed in the same order as one at a time.
+	let mut verifier_transcript = Transcript::new(b"CommitVecTest");
+	let mut verifier = Verifier::new(&mut verifier_transcript);
+	let vars: Vec<_> = commitments.iter().map(|V| verifier.commit(*V)).collect();
+	verifier.constrain(vars[0] + vars[1] - vars[2] - Scalar::one());
+	assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_ok());
+
+	let mut reversed = commitments.clone();
+	reversed.reverse();
+	let mut verifier_transcript = Transcript::new(b"CommitVecTest");
+	let mut verifier = Verifier::new(&mut verifier_transcript);
+	let vars = verifier.commit_vec(&reversed);
+	verifier.constrain(vars[2] + vars[1] - vars[0] - Scalar::one());
+	assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_err());
+}
+
+#[test]
+fn seeded_rng_gives_identical_proofs() {
+	use rand_chacha::ChaChaRng;
+	use rand_core::SeedableRng;
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(16, 1);
+	let input: Vec<Scalar> = [3u64, 1, 4, 5].iter().map(|v| Scalar::from(*v)).collect();
+	let output: Vec<Scalar> = [5u64, 4, 3, 1].iter().map(|v| Scalar::from(*v)).collect();
+
+	// The shuffle gadget has randomized constraints, so both phases of the
+	// proof draw from the RNG.
+	let prove = |seed: u8| {
+		let mut rng = ChaChaRng::from_seed([seed; 32]);
+		let mut prover_transcript = Transcript::new(b"SeededRngTest");
+		let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+		let (input_commitments, input_vars, _) = prover.commit_vec(&input, &mut rng);
+		let (output_commitments, output_vars, _) = prover.commit_vec(&output, &mut rng);
+		ShuffleProof::gadget(&mut prover, input_vars, output_vars).unwrap();
+		let proof = prover.prove_with_rng(&bp_gens, &mut rng).unwrap();
+		(proof, input_commitments, output_commitments)
+	};
+
+	let (proof, input_commitments, output_commitments) = prove(1);
+	let (same, same_inputs, same_outputs) = prove(1);
+	assert_eq!(proof.to_bytes(), same.to_bytes());
+	assert_eq!(input_commitments, same_inputs);
+	assert_eq!(output_commitments, same_outputs);
+
+	let (other, ..) = prove(2);
+	assert_ne!(proof.to_bytes(), other.to_bytes());
+
+	let mut verifier_transcript = Transcript::new(b"SeededRngTest");
+	let mut verifier = Verifier::new(&mut verifier_transcript);
+	let input_vars = verifier.commit_vec(&input_commitments);
+	let output_vars = verifier.commit_vec(&output_commitments);
+	ShuffleProof::gadget(&mut verifier, input_vars, output_vars).unwrap();
+	let mut rng = ChaChaRng::from_seed([3u8; 32]);
+	assert!(verifier.verify_with_rng(&proof, &pc_gens, &bp_gens, &mut rng).is_ok());
+}
+
+#[cfg(feature = "zeroize")]
+#[test]
+fn zeroize_wipes_prover_witness() {
+	use zeroize::Zeroize;
+
+	fn prover_with_witness<'t, 'g>(pc_gens: &'g PedersenGens, transcript: &'t mut Transcript) -> Prover<'t, 'g> {
+		let mut prover = Prover::new(pc_gens, transcript);
+		let (_, x) = prover.commit(3u64.into(), Scalar::from(7u64));
+		let (_, _, out) = prover.multiply(x.into(), x.into());
+		prover.constrain(out - Scalar::from(9u64));
+		prover
+	}
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(16, 1);
+
+	let mut transcript = Transcript::new(b"ZeroizeTest");
+	let mut prover = prover_with_witness(&pc_gens, &mut transcript);
+	assert_eq!(prover.is_satisfied(), Ok(true));
+	prover.zeroize();
+	assert_eq!(prover.is_satisfied(), Ok(false));
+
+	// A restored checkpoint no longer has the witness of a wiped one.
+	let mut transcript = Transcript::new(b"ZeroizeTest");
+	let mut checkpoint = prover_with_witness(&pc_gens, &mut transcript).checkpoint();
+	checkpoint.zeroize();
+	let mut transcript = Transcript::new(b"ZeroizeTest");
+	let mut prover = Prover::restore(checkpoint, &mut transcript);
+	prover.enable_diagnostics();
+	assert_eq!(prover.is_satisfied(), Ok(false));
+	assert!(matches!(
+		prover.prove(&bp_gens),
+		Err(R1CSError::UnsatisfiedConstraint { .. })
+	));
+}
+
+/// Every type holding witness data wipes it when dropped.
+#[cfg(feature = "zeroize")]
+#[allow(dead_code)]
+fn witness_holders_zeroize_on_drop() {
+	use webb_bulletproofs::{r1cs::aggregation::party::*, ZeroizeOnDrop};
+
+	fn assert_zod<T: ZeroizeOnDrop>() {}
+	assert_zod::<Prover<'static, 'static>>();
+	assert_zod::<ProverCheckpoint<'static, 'static>>();
+	assert_zod::<PartyAwaitingPosition<'static>>();
+	assert_zod::<PartyAwaitingWireChallenge<'static>>();
+	assert_zod::<PartyAwaitingPolyChallenge>();
+}
+
+#[test]
+fn range_proof_commitment_in_constraint_system() {
+	use webb_bulletproofs::RangeProof;
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64, 1);
+	let mut rng = thread_rng();
+	let (v, w) = (3000u64, 9_000_000u64);
+	let v_blinding = Scalar::random(&mut rng);
+
+	// The value is range-proven once, publishing its commitment.
+	let (range_proof, V) =
+		RangeProof::prove_single(&bp_gens, &pc_gens, &mut Transcript::new(b"Range"), v, &v_blinding, 32).unwrap();
+
+	// The constraint system proves v * v = w about the same commitment.
+	let (proof, W) = {
+		let mut transcript = Transcript::new(b"Square");
+		let mut prover = Prover::new(&pc_gens, &mut transcript);
+		let v_var = prover.commit_existing(v.into(), v_blinding, &V).unwrap();
+		let (W, w_var) = prover.commit(w.into(), Scalar::random(&mut rng));
+		let (_, _, square) = prover.multiply(v_var.into(), v_var.into());
+		prover.constrain(square - w_var);
+		(prover.prove(&bp_gens).unwrap(), W)
+	};
+
+	// Both proofs verify against the single commitment.
+	assert!(range_proof
+		.verify_single(&bp_gens, &pc_gens, &mut Transcript::new(b"Range"), &V, 32)
+		.is_ok());
+	let verify_square = |V: CompressedRistretto| {
+		let mut transcript = Transcript::new(b"Square");
+		let mut verifier = Verifier::new(&mut transcript);
+		let v_var = verifier.commit(V);
+		let w_var = verifier.commit(W);
+		let (_, _, square) = verifier.multiply(v_var.into(), v_var.into());
+		verifier.constrain(square - w_var);
+		verifier.verify(&proof, &pc_gens, &bp_gens)
+	};
+	assert!(verify_square(V).is_ok());
+
+	// The square proof does not verify for another commitment to the value.
+	let other_V = pc_gens.commit(v.into(), Scalar::random(&mut rng)).compress();
+	assert!(verify_square(other_V).is_err());
+
+	// The prover cannot open the commitment to another value.
+	let mut transcript = Transcript::new(b"Square");
+	let mut prover = Prover::new(&pc_gens, &mut transcript);
+	assert_eq!(
+		prover.commit_existing((v + 1).into(), v_blinding, &V).unwrap_err(),
+		R1CSError::CommitmentMismatch
+	);
+}
+
+/// Allocates `n` multipliers computing \\((i + 1) \cdot 2\\), and only
+/// constrains every thousandth left input, and the first one to the
+/// committed value `v`.  With `padding`, the last constraint also has a
+/// zero-weighted term for the left input of every multiplier.
+fn sparse_circuit<CS: ConstraintSystem>(
+	cs: &mut CS,
+	v: Variable,
+	v_assignment: Option<u64>,
+	n: usize,
+	padding: bool,
+) -> Result<(), R1CSError> {
+	let mut left = Vec::with_capacity(n);
+	for i in 0..n {
+		let l_assignment = if i == 0 { v_assignment } else { Some(i as u64 + 1) };
+		let (l, ..) = cs.allocate_multiplier(l_assignment.map(|l| (l.into(), 2u64.into())))?;
+		if i > 0 && i % 1000 == 0 {
+			cs.constrain(l - (i as u64 + 1));
+		}
+		left.push(l);
+	}
+	let zeros: LinearCombination = left.iter().map(|l| (*l, Scalar::zero())).collect();
+	if padding {
+		cs.constrain(left[0] - v + zeros);
+	} else {
+		cs.constrain(left[0] - v);
+	}
+	Ok(())
+}
+
+#[test]
+fn sparse_constraints_verify_like_dense_ones() {
+	use curve25519_dalek::ristretto::RistrettoPoint;
+	use rand_chacha::ChaChaRng;
+	use rand_core::SeedableRng;
+	use webb_bulletproofs::{DefaultMsmBackend, MsmBackend};
+
+	/// Records the scalars of the verification equation.
+	struct RecordingBackend(RefCell<Vec<Scalar>>);
+
+	impl MsmBackend for RecordingBackend {
+		fn vartime_msm(&self, scalars: &[Scalar], points: &[RistrettoPoint]) -> RistrettoPoint {
+			*self.0.borrow_mut() = scalars.to_vec();
+			DefaultMsmBackend.vartime_msm(scalars, points)
+		}
+	}
+
+	let n: usize = 10_000;
+	let v = 1u64;
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(n.next_power_of_two(), 1);
+
+	let (proof, com) = {
+		let mut prover_transcript = Transcript::new(b"SparseTest");
+		let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+		let (com, var) = prover.commit(v.into(), Scalar::random(&mut thread_rng()));
+		assert!(sparse_circuit(&mut prover, var, Some(v), n, false).is_ok());
+		(prover.prove(&bp_gens).unwrap(), com)
+	};
+
+	// The zero-weighted terms make the verifier flatten the constraints
+	// into dense vectors, and do not change the proof or the transcript.
+	let verify = |com: CompressedRistretto, padding: bool| {
+		let backend = RecordingBackend(RefCell::new(Vec::new()));
+		let mut verifier_transcript = Transcript::new(b"SparseTest");
+		let mut verifier = Verifier::new(&mut verifier_transcript);
+		let var = verifier.commit(com);
+		sparse_circuit(&mut verifier, var, None, n, padding)?;
+		verifier.verify_with_backend(
+			&proof,
+			&pc_gens,
+			&bp_gens,
+			&mut ChaChaRng::from_seed([41u8; 32]),
+			Some(&backend),
+		)?;
+		Ok::<_, R1CSError>(backend.0.into_inner())
+	};
+
+	let sparse = verify(com, false).unwrap();
+	let dense = verify(com, true).unwrap();
+	assert_eq!(sparse, dense);
+
+	let other_com = pc_gens
+		.commit((v + 1).into(), Scalar::random(&mut thread_rng()))
+		.compress();
+	for &padding in [false, true].iter() {
+		assert!(matches!(verify(other_com, padding), Err(R1CSError::VerificationError)));
+	}
+}
+
+/// Proves that the committed `a` and `b` multiply to 12.
+fn factors_circuit<CS: ConstraintSystem>(cs: &mut CS, a: Variable, b: Variable) {
+	let (_, _, c) = cs.multiply(a.into(), b.into());
+	cs.constrain(c - 12u64);
+}
+
+#[test]
+fn seeded_verification_of_a_fixture_proof() {
+	use curve25519_dalek::ristretto::RistrettoPoint;
+	use rand_chacha::ChaChaRng;
+	use rand_core::SeedableRng;
+	use webb_bulletproofs::{DefaultMsmBackend, MsmBackend};
+
+	/// Records the scalars of the verification equation.
+	struct RecordingBackend(RefCell<Vec<Scalar>>);
+
+	impl MsmBackend for RecordingBackend {
+		fn vartime_msm(&self, scalars: &[Scalar], points: &[RistrettoPoint]) -> RistrettoPoint {
+			*self.0.borrow_mut() = scalars.to_vec();
+			DefaultMsmBackend.vartime_msm(scalars, points)
+		}
+	}
+
+	// A proof of 3 * 4 = 12, created with `prove_with_rng` and blindings
+	// drawn from `ChaChaRng::from_seed([7u8; 32])`.
+	let commitments = [
+		"1056f824c009b3d7cc928701bfc9319154ffa287333608bbc60b00caa4905868",
+		"745645718ff27ff07fefa5bf7ca855910798069f39e66e576930064d4284a22e",
+	];
+	let proof = "00f0e7ac50737b8fa436832e11cae2b8e92b3175225785928e50017001bb3ecb3636e92f52872183e7bbde10fc7a8239e1104\
+	             75c5e9156592594b1018707955f0c48dea94c7dafd1e633c6e6fb54a6f64449b873ec173330d9a2884a55e7e5fb4c16e5913fe1\
+	             3ad2e3cefb7037afe7ca461b387880874a8e240a7ced2c875d976bc806d35af6b27e6da38ce837656440edb9dc3da2e50b1e64c\
+	             54da93475703900a48271ed4cdf6eaf671000128d1c2e802059b19c1f5a9983c1f6ead31b8a920d9ce5f7057d89757329d2fecf\
+	             8f55e44558ad51cfb2818182275353ba9d058d33f6367470762da7348f0e1984c7e4ba2a90719e921b2299b6e23f6ba592e5251\
+	             188af95fc8a12f35582f04aa93e237631a9bd10efa4d7e0e40f2eed030384ea028e58f6ef134dbb9baa5134ed87cf40593fb3037\
+	             353a648451a8f3d4d2c15240aada5bf25dda0b0059d67b074c7e6762193e7434648196fcc61cb074ad9072a0b04823db470005ee\
+	             72e76981e18b78ec513ec1258fd0227e6bd142d9687e0bf09a82d10f285e6e45477e7610ebc453941b1f0613904ce2767e8978e5\
+	             6cda9cf00";
+	let proof = R1CSProof::from_bytes(&hex::decode(proof).unwrap()).unwrap();
+	let commitments: Vec<CompressedRistretto> = commitments
+		.iter()
+		.map(|c| CompressedRistretto::from_slice(&hex::decode(c).unwrap()))
+		.collect();
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(8, 1);
+
+	// Verifies the fixture with the verifier randomness drawn from `seed`,
+	// returning the scalars of the final multiscalar multiplication.
+	let verify = |seed: [u8; 32]| {
+		let backend = RecordingBackend(RefCell::new(Vec::new()));
+		let mut transcript = Transcript::new(b"SeededVerificationTest");
+		let mut verifier = Verifier::new(&mut transcript);
+		let a = verifier.commit(commitments[0]);
+		let b = verifier.commit(commitments[1]);
+		factors_circuit(&mut verifier, a, b);
+		verifier.verify_with_backend(
+			&proof,
+			&pc_gens,
+			&bp_gens,
+			&mut ChaChaRng::from_seed(seed),
+			Some(&backend),
+		)?;
+		Ok::<_, R1CSError>(backend.0.into_inner())
+	};
+
+	// The same seed reproduces the internal state of the check exactly.
+	let scalars = verify([1u8; 32]).unwrap();
+	assert_eq!(scalars, verify([1u8; 32]).unwrap());
+	assert_ne!(scalars, verify([2u8; 32]).unwrap());
+}
+
+#[test]
+fn verification_batch_mixes_r1cs_and_range_proofs() {
+	use webb_bulletproofs::{ProofError, RangeProof, VerificationBatch};
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64, 2);
+	let mut rng = thread_rng();
+
+	let (r1cs_proof1, r1cs_com1, _) = range_proof_with_transcript(b"Batch1", 1234, Scalar::random(&mut rng), 32);
+	let (r1cs_proof2, r1cs_com2, _) = range_proof_with_transcript(b"Batch2", 77, Scalar::random(&mut rng), 16);
+	let (single, single_com) = RangeProof::prove_single(
+		&bp_gens,
+		&pc_gens,
+		&mut Transcript::new(b"Single"),
+		5000,
+		&Scalar::random(&mut rng),
+		32,
+	)
+	.unwrap();
+	let (aggregated, aggregated_coms) = RangeProof::prove_multiple(
+		&bp_gens,
+		&pc_gens,
+		&mut Transcript::new(b"Aggregated"),
+		&[1, 2],
+		&[Scalar::random(&mut rng), Scalar::random(&mut rng)],
+		16,
+	)
+	.unwrap();
+
+	let verify = |single_com: &CompressedRistretto| {
+		let mut batch = VerificationBatch::new(&bp_gens, &pc_gens);
+
+		let mut transcript = Transcript::new(b"Batch1");
+		let mut verifier = Verifier::new(&mut transcript);
+		let var = verifier.commit(r1cs_com1);
+		range_proof(&mut verifier, var.into(), None, 32).unwrap();
+		assert_eq!(verifier.verify_deferred(&r1cs_proof1, &mut batch).unwrap(), 0);
+
+		let index = single
+			.verify_single_deferred(&mut Transcript::new(b"Single"), single_com, 32, &mut batch)
+			.unwrap();
+		assert_eq!(index, 1);
+
+		let index = aggregated
+			.verify_multiple_deferred(&mut Transcript::new(b"Aggregated"), &aggregated_coms, 16, &mut batch)
+			.unwrap();
+		assert_eq!(index, 2);
+
+		let mut transcript = Transcript::new(b"Batch2");
+		let mut verifier = Verifier::new(&mut transcript);
+		let var = verifier.commit(r1cs_com2);
+		range_proof(&mut verifier, var.into(), None, 16).unwrap();
+		assert_eq!(verifier.verify_deferred(&r1cs_proof2, &mut batch).unwrap(), 3);
+
+		assert_eq!(batch.len(), 4);
+		batch.verify()
+	};
+
+	assert!(verify(&single_com).is_ok());
+
+	// A range proof checked against another commitment passes the cheap
+	// checks, and is reported by the combined check.
+	let other_com = pc_gens.commit(5000u64.into(), Scalar::random(&mut rng)).compress();
+	assert_eq!(
+		verify(&other_com),
+		Err(ProofError::BatchStatementsVerificationError { indexes: vec![1] })
+	);
+
+	// Statements needing more generators than the batch has are rejected
+	// when they are added.
+	let small_gens = BulletproofGens::new(16, 1);
+	let mut batch = VerificationBatch::new(&small_gens, &pc_gens);
+	assert_eq!(
+		single.verify_single_deferred(&mut Transcript::new(b"Single"), &single_com, 32, &mut batch),
+		Err(ProofError::InvalidGeneratorsLength {
+			required: 32,
+			actual: 16
+		})
+	);
+	assert!(batch.is_empty());
+	assert!(batch.verify().is_ok());
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn parallel_batch_verification_matches_serial() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(16, 1);
+	let n = 16;
+	let mut rng = thread_rng();
+
+	let (mut proofs, commitments): (Vec<_>, Vec<_>) = (0..10u64)
+		.map(|v| {
+			let mut prover_transcript = Transcript::new(b"ParallelBatchTest");
+			let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+			let (com, var) = prover.commit(v.into(), Scalar::random(&mut rng));
+			assert!(range_proof(&mut prover, var.into(), Some(v), n).is_ok());
+			(prover.prove(&bp_gens).unwrap(), com)
+		})
+		.unzip();
+
+	let verify = |proofs: &[R1CSProof], parallelism: Option<usize>| -> Result<(), R1CSError> {
+		let mut transcripts: Vec<_> = (0..proofs.len())
+			.map(|_| Transcript::new(b"ParallelBatchTest"))
+			.collect();
+		let instances = transcripts
+			.iter_mut()
+			.zip(proofs.iter().zip(commitments.iter()))
+			.map(|(verifier_transcript, (proof, com))| {
+				let mut verifier = Verifier::new(verifier_transcript);
+				let var = verifier.commit(*com);
+				assert!(range_proof(&mut verifier, var.into(), None, n).is_ok());
+				(verifier, proof)
+			})
+			.collect();
+		match parallelism {
+			Some(parallelism) => Verifier::verify_batch_parallel(instances, &pc_gens, &bp_gens, parallelism),
+			None => Verifier::verify_batch(instances, &pc_gens, &bp_gens),
+		}
+	};
+
+	let parallelisms = [1, 3, 10, 64];
+	assert!(verify(&proofs, None).is_ok());
+	for &parallelism in parallelisms.iter() {
+		assert!(verify(&proofs, Some(parallelism)).is_ok());
+		assert!(verify(&[], Some(parallelism)).is_ok());
+	}
+
+	// Swapped proofs are both reported, as well as a proof which fails the
+	// cheap checks, in whichever partitions they fall.
+	proofs.swap(3, 4);
+	let bytes = proofs[8].to_bytes();
+	let end = bytes.len() - 2 * 32;
+	proofs[8] = R1CSProof::from_bytes(&[&bytes[..end - 2 * 32], &bytes[end..]].concat()).unwrap();
+	assert!(verify(&proofs, None).is_err());
+	for &parallelism in parallelisms.iter() {
+		assert_eq!(
+			verify(&proofs, Some(parallelism)),
+			Err(R1CSError::BatchStatementsVerificationError { indexes: vec![3, 4, 8] })
+		);
+	}
+}
+
+/// Proves that the `value` is in the `set`, or not in it if `member` is
+/// false, and verifies the proof against the commitment to the
+/// `verified_value`.
+fn set_membership_roundtrip<S: Copy + Into<Scalar>>(
+	value: Scalar,
+	verified_value: Scalar,
+	set: &[S],
+	member: bool,
+) -> Result<(), R1CSError> {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(16, 1);
+
+	let proof = {
+		let mut prover_transcript = Transcript::new(b"SetMembershipTest");
+		let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+		let (_, var) = prover.commit(value, Scalar::one());
+		let v = AllocatedScalar {
+			variable: var,
+			assignment: Some(value),
+		};
+		if member {
+			set_membership_1_gadget(&mut prover, v, set)?;
+		} else {
+			set_non_membership_gadget(&mut prover, v, set)?;
+		}
+		prover.prove(&bp_gens)?
+	};
+
+	let mut verifier_transcript = Transcript::new(b"SetMembershipTest");
+	let mut verifier = Verifier::new(&mut verifier_transcript);
+	let v = AllocatedScalar {
+		variable: verifier.commit(pc_gens.commit(verified_value, Scalar::one()).compress()),
+		assignment: None,
+	};
+	if member {
+		set_membership_1_gadget(&mut verifier, v, set)?;
+	} else {
+		set_non_membership_gadget(&mut verifier, v, set)?;
+	}
+	verifier.verify(&proof, &pc_gens, &bp_gens)
+}
+
+#[test]
+fn set_membership_gadgets_with_u64_sets() {
+	let set = [2u64, 3, 5, 7, 11];
+	let roundtrip = |value: u64, member| set_membership_roundtrip(value.into(), value.into(), &set, member);
+
+	for &value in set.iter() {
+		assert!(roundtrip(value, true).is_ok());
+		assert!(matches!(roundtrip(value, false), Err(R1CSError::GadgetError { .. })));
+	}
+	for &value in [0u64, 4, 12].iter() {
+		assert!(roundtrip(value, false).is_ok());
+		assert!(matches!(roundtrip(value, true), Err(R1CSError::GadgetError { .. })));
+	}
+
+	// A set of one element needs no multiplier for membership.
+	assert!(set_membership_roundtrip(9u64.into(), 9u64.into(), &[9u64], true).is_ok());
+	assert!(matches!(
+		set_membership_roundtrip(9u64.into(), 9u64.into(), &[] as &[u64], true),
+		Err(R1CSError::GadgetError { .. })
+	));
+	assert!(set_membership_roundtrip(9u64.into(), 9u64.into(), &[] as &[u64], false).is_ok());
+}
+
+#[test]
+fn set_membership_gadgets_with_scalar_sets() {
+	let mut rng = thread_rng();
+	let set: Vec<Scalar> = (0..8).map(|_| Scalar::random(&mut rng)).collect();
+	let outsider = Scalar::random(&mut rng);
+
+	assert!(set_membership_roundtrip(set[3], set[3], &set, true).is_ok());
+	assert!(set_membership_roundtrip(outsider, outsider, &set, false).is_ok());
+
+	// The prover refuses a value which collides with an element of the set
+	// for non-membership, or which is not in the set for membership.
+	assert!(matches!(
+		set_membership_roundtrip(set[5], set[5], &set, false),
+		Err(R1CSError::GadgetError { .. })
+	));
+	assert!(matches!(
+		set_membership_roundtrip(outsider, outsider, &set, true),
+		Err(R1CSError::GadgetError { .. })
+	));
+
+	// The proofs do not verify for a commitment to a colliding value, nor
+	// for a commitment to a value outside of the set.
+	assert!(matches!(
+		set_membership_roundtrip(outsider, set[5], &set, false),
+		Err(R1CSError::VerificationError)
+	));
+	assert!(matches!(
+		set_membership_roundtrip(set[3], outsider, &set, true),
+		Err(R1CSError::VerificationError)
+	));
+}
+
+#[test]
+fn circuit_batch_mixes_circuit_sizes() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(256, 1);
+	let mut rng = thread_rng();
+
+	// A 16-multiplier circuit, described once and proven for two values.
+	let small =
+		CircuitDescription::from_circuit(1, |verifier, vars| range_proof(verifier, vars[0].into(), None, 16)).unwrap();
+	assert_eq!(small.num_multipliers(), 16);
+	let (small_proofs, small_coms): (Vec<_>, Vec<_>) = [77u64, 9000]
+		.iter()
+		.map(|&v| {
+			let mut prover_transcript = Transcript::new(b"SmallCircuit");
+			let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+			let (com, var) = prover.commit(v.into(), Scalar::random(&mut rng));
+			assert!(range_proof(&mut prover, var.into(), Some(v), 16).is_ok());
+			(prover.prove(&bp_gens).unwrap(), com)
+		})
+		.unzip();
+
+	// A 256-multiplier circuit of four 64-bit range proofs, synthesized for
+	// each verification.
+	let large_values = [1u64, 2, 3, std::u64::MAX];
+	let (large_proof, large_coms) = {
+		let mut prover_transcript = Transcript::new(b"LargeCircuit");
+		let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+		let mut coms = Vec::new();
+		for &v in large_values.iter() {
+			let (com, var) = prover.commit(v.into(), Scalar::random(&mut rng));
+			assert!(range_proof(&mut prover, var.into(), Some(v), 64).is_ok());
+			coms.push(com);
+		}
+		assert_eq!(prover.num_multipliers(), 256);
+		(prover.prove(&bp_gens).unwrap(), coms)
+	};
+
+	let verify = |small_coms: &[CompressedRistretto], bp_gens: &BulletproofGens| {
+		let mut small_transcript1 = Transcript::new(b"SmallCircuit");
+		let mut small_transcript2 = Transcript::new(b"SmallCircuit");
+		let mut large_transcript = Transcript::new(b"LargeCircuit");
+
+		let mut large_verifier = Verifier::new(&mut large_transcript);
+		for com in large_coms.iter() {
+			let var = large_verifier.commit(*com);
+			range_proof(&mut large_verifier, var.into(), None, 64).unwrap();
+		}
+
+		let instances = vec![
+			(
+				BatchCircuit::Described {
+					transcript: &mut small_transcript1,
+					description: &small,
+					commitments: &small_coms[0..1],
+				},
+				&small_proofs[0],
+			),
+			(BatchCircuit::Synthesized(large_verifier), &large_proof),
+			(
+				BatchCircuit::Described {
+					transcript: &mut small_transcript2,
+					description: &small,
+					commitments: &small_coms[1..2],
+				},
+				&small_proofs[1],
+			),
+		];
+		Verifier::verify_circuit_batch(instances, &pc_gens, bp_gens)
+	};
+
+	assert!(verify(&small_coms, &bp_gens).is_ok());
+
+	// Swapping the commitments of the small statements fails the batch, and
+	// the first of them is reported.
+	let swapped = [small_coms[1], small_coms[0]];
+	assert_eq!(
+		verify(&swapped, &bp_gens),
+		Err(R1CSError::BatchVerificationError { index: 0 })
+	);
+
+	// The generators must cover the largest circuit.
+	assert_eq!(
+		verify(&small_coms, &BulletproofGens::new(16, 1)),
+		Err(R1CSError::InvalidGeneratorsLength {
+			required: 256,
+			actual: 16
+		})
+	);
+}
+
+#[test]
+fn prover_grows_generators_on_demand() {
+	let pc_gens = PedersenGens::default();
+	let (x, y) = ([2u64, 7, 9, 4, 1], [9u64, 1, 2, 4, 7]);
+
+	let prover = |transcript| {
+		let mut prover = Prover::new(&pc_gens, transcript);
+		let mut allocate = |values: &[u64]| -> Vec<AllocatedScalar> {
+			values
+				.iter()
+				.map(|v| {
+					let assignment = Some(Scalar::from(*v));
+					AllocatedScalar {
+						variable: prover.allocate(assignment).unwrap(),
+						assignment,
+					}
+				})
+				.collect()
+		};
+		let (x, y) = (allocate(&x), allocate(&y));
+		assert!(scalar_shuffle_gadget(&mut prover, x, y).is_ok());
+		prover
+	};
+
+	// The circuit has 5 multipliers in the first phase and 10 in the
+	// second, so the generators grow twice, to 16 after padding.
+	let mut bp_gens = BulletproofGens::new(2, 2);
+	let mut prover_transcript = Transcript::new(b"ScalarShuffleTest");
+	assert_eq!(
+		prover(&mut prover_transcript).prove(&bp_gens).err(),
+		Some(R1CSError::InvalidGeneratorsLength { required: 8, actual: 2 })
+	);
+	let mut prover_transcript = Transcript::new(b"ScalarShuffleTest");
+	let proof = prover(&mut prover_transcript).prove_growing(&mut bp_gens).unwrap();
+
+	// Every party's generators grew, to the ones created at that size.
+	let fresh = BulletproofGens::new(16, 2);
+	assert_eq!(bp_gens.gens_capacity, 16);
+	assert_eq!(bp_gens.G_vec, fresh.G_vec);
+	assert_eq!(bp_gens.H_vec, fresh.H_vec);
+
+	let mut verifier_transcript = Transcript::new(b"ScalarShuffleTest");
+	let mut verifier = Verifier::new(&mut verifier_transcript);
+	let mut allocate = |n: usize| -> Vec<AllocatedScalar> {
+		(0..n)
+			.map(|_| AllocatedScalar {
+				variable: verifier.allocate(None).unwrap(),
+				assignment: None,
+			})
+			.collect()
+	};
+	let (x, y) = (allocate(x.len()), allocate(y.len()));
+	assert!(scalar_shuffle_gadget(&mut verifier, x, y).is_ok());
+	assert!(verifier.verify(&proof, &pc_gens, &BulletproofGens::new(16, 1)).is_ok());
+
+	// Generators which are large enough are left as they are.
+	let mut prover_transcript = Transcript::new(b"ScalarShuffleTest");
+	let mut large = BulletproofGens::new(64, 1);
+	assert!(prover(&mut prover_transcript).prove_growing(&mut large).is_ok());
+	assert_eq!(large.gens_capacity, 64);
+}
+
+#[test]
+fn required_capacity_matches_prover() {
+	let pc_gens = PedersenGens::default();
+
+	for &num_multipliers in [0usize, 1, 5, 16, 17].iter() {
+		let required = BulletproofGens::required_capacity_for_r1cs(num_multipliers);
+		let prove = |bp_gens: &BulletproofGens| {
+			let mut transcript = Transcript::new(b"CapacityTest");
+			let mut prover = Prover::new(&pc_gens, &mut transcript);
+			let (_, v) = prover.commit(3u64.into(), Scalar::random(&mut thread_rng()));
+			for _ in 0..num_multipliers {
+				prover.multiply(v.into(), v.into());
+			}
+			assert_eq!(prover.metrics().padded_n, required.gens_capacity);
+			prover.prove(bp_gens)
+		};
+
+		let exact = BulletproofGens::new(required.gens_capacity, required.party_capacity);
+		assert!(exact.fits(required));
+		assert!(prove(&exact).is_ok());
+
+		let too_small = BulletproofGens::new(required.gens_capacity - 1, 1);
+		assert!(!too_small.fits(required));
+		assert_eq!(prove(&too_small).unwrap_err(), R1CSError::InvalidGeneratorsLength {
+			required: required.gens_capacity,
+			actual: required.gens_capacity - 1
+		});
+	}
+}
+
+/// Multiplies `v` by a challenge in the randomized phase, and records the
+/// challenge in `challenges`.
+fn recording_gadget<CS: RandomizableConstraintSystem>(
+	cs: &mut CS,
+	v: Variable,
+	challenges: Rc<RefCell<Vec<Scalar>>>,
+) -> Result<(), R1CSError> {
+	cs.specify_randomized_constraints(move |cs| {
+		let z = cs.challenge_scalar(b"x");
+		challenges.borrow_mut().push(z);
+		let (_, _, out) = cs.multiply(v.into(), z.into());
+		let (_, _, expected) = cs.multiply(v.into(), Scalar::one().into());
+		cs.constrain(out - expected * z);
+		Ok(())
+	})
+}
+
+#[test]
+fn namespaces_separate_gadget_challenges() {
+	use rand_chacha::ChaChaRng;
+	use rand_core::SeedableRng;
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(8, 1);
+
+	// Proves the recording gadget within the namespace `outer`, if any, and
+	// then within `inner`, with the same RNG every time.
+	let prove = |outer: Option<&'static [u8]>, inner: &'static [u8]| {
+		let challenges = Rc::new(RefCell::new(Vec::new()));
+		let mut rng = ChaChaRng::from_seed([7u8; 32]);
+		let mut prover_transcript = Transcript::new(b"NamespaceTest");
+		let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+		let (commitment, v) = prover.commit(Scalar::from(3u64), Scalar::from(5u64));
+		let gadget = |cs: &mut Prover| cs.scoped(inner, |cs| recording_gadget(cs, v, challenges.clone()));
+		match outer {
+			Some(outer) => prover.scoped(outer, gadget).unwrap(),
+			None => gadget(&mut prover).unwrap(),
+		}
+		let proof = prover.prove_with_rng(&bp_gens, &mut rng).unwrap();
+		let challenge = challenges.borrow()[0];
+		(proof, commitment, challenge)
+	};
+	let verify = |outer: Option<&'static [u8]>, inner: &'static [u8], proof: &R1CSProof, commitment| {
+		let challenges = Rc::new(RefCell::new(Vec::new()));
+		let mut verifier_transcript = Transcript::new(b"NamespaceTest");
+		let mut verifier = Verifier::new(&mut verifier_transcript);
+		let v = verifier.commit(commitment);
+		let gadget = |cs: &mut Verifier| cs.scoped(inner, |cs| recording_gadget(cs, v, challenges.clone()));
+		match outer {
+			Some(outer) => verifier.scoped(outer, gadget).unwrap(),
+			None => gadget(&mut verifier).unwrap(),
+		}
+		let result = verifier.verify(proof, &pc_gens, &bp_gens);
+		let challenge = challenges.borrow()[0];
+		(result, challenge)
+	};
+
+	let (proof, commitment, challenge) = prove(None, b"merkle-gadget");
+	let (_, _, same) = prove(None, b"merkle-gadget");
+	let (_, _, other) = prove(None, b"shuffle-gadget");
+	let (_, _, nested) = prove(Some(b"outer"), b"merkle-gadget");
+	assert_eq!(challenge, same);
+	assert_ne!(challenge, other);
+	assert_ne!(challenge, nested);
+
+	// The verifier derives the same challenges within the same namespaces.
+	let (result, verifier_challenge) = verify(None, b"merkle-gadget", &proof, commitment);
+	assert!(result.is_ok());
+	assert_eq!(verifier_challenge, challenge);
+
+	let (nested_proof, nested_commitment, _) = prove(Some(b"outer"), b"merkle-gadget");
+	let (result, verifier_challenge) = verify(Some(b"outer"), b"merkle-gadget", &nested_proof, nested_commitment);
+	assert!(result.is_ok());
+	assert_eq!(verifier_challenge, nested);
+
+	// A proof does not verify within other namespaces.
+	let (result, verifier_challenge) = verify(None, b"shuffle-gadget", &proof, commitment);
+	assert!(result.is_err());
+	assert_eq!(verifier_challenge, other);
+}
+
+#[test]
+#[should_panic(expected = "pop_namespace called without a matching push_namespace")]
+fn unbalanced_pop_namespace_panics() {
+	let pc_gens = PedersenGens::default();
+	let mut transcript = Transcript::new(b"NamespaceTest");
+	let mut prover = Prover::new(&pc_gens, &mut transcript);
+	prover.push_namespace(b"gadget");
+	prover.pop_namespace();
+	prover.pop_namespace();
+}
+
+#[test]
+fn public_inputs_bind_the_proof() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(8, 1);
+	let root = [7u8; 32];
+	let fee = Scalar::from(25u64);
+
+	// Constrains the committed value to be the square of `x`, and binds the
+	// proof to a Merkle `root` and a `fee`.
+	fn gadget<CS: ConstraintSystem>(
+		cs: &mut CS,
+		v: Variable,
+		x: Option<Scalar>,
+		root: &[u8],
+		fee: &Scalar,
+	) -> Result<(), R1CSError> {
+		cs.commit_public_bytes(b"merkle-root", root)?;
+		cs.commit_public_scalar(b"fee", fee)?;
+		let x = cs.allocate(x)?;
+		let (_, _, square) = cs.multiply(x.into(), x.into());
+		cs.constrain(square - v);
+		Ok(())
+	}
+
+	let mut prover_transcript = Transcript::new(b"PublicInputTest");
+	let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+	let (commitment, v) = prover.commit(Scalar::from(9u64), Scalar::random(&mut thread_rng()));
+	gadget(&mut prover, v, Some(Scalar::from(3u64)), &root, &fee).unwrap();
+	let proof = prover.prove(&bp_gens).unwrap();
+
+	let verify = |root: &[u8], fee: &Scalar| {
+		let mut verifier_transcript = Transcript::new(b"PublicInputTest");
+		let mut verifier = Verifier::new(&mut verifier_transcript);
+		let v = verifier.commit(commitment);
+		gadget(&mut verifier, v, None, root, fee).unwrap();
+		verifier.verify(&proof, &pc_gens, &bp_gens)
+	};
+	assert!(verify(&root, &fee).is_ok());
+	assert_eq!(verify(&[8u8; 32], &fee), Err(R1CSError::VerificationError));
+	assert_eq!(verify(&root, &Scalar::from(26u64)), Err(R1CSError::VerificationError));
+
+	// A verifier which forgets the public inputs rejects the proof as well.
+	let mut verifier_transcript = Transcript::new(b"PublicInputTest");
+	let mut verifier = Verifier::new(&mut verifier_transcript);
+	let v = verifier.commit(commitment);
+	let x = verifier.allocate(None).unwrap();
+	let (_, _, square) = verifier.multiply(x.into(), x.into());
+	verifier.constrain(square - v);
+	assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_err());
+
+	// Public inputs cannot be precomputed into a circuit description.
+	assert!(CircuitDescription::from_circuit(1, |cs, vars| gadget(cs, vars[0], None, &root, &fee)).is_err());
+}
+
+#[test]
+fn public_inputs_are_rejected_after_challenges() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(8, 1);
+
+	let mut prover_transcript = Transcript::new(b"PublicInputTest");
+	let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+	let (_, v) = prover.commit(Scalar::from(9u64), Scalar::random(&mut thread_rng()));
+	prover
+		.specify_randomized_constraints(move |cs| {
+			let z = cs.challenge_scalar(b"z");
+			cs.commit_public_scalar(b"late", &z)?;
+			cs.constrain(v - z);
+			Ok(())
+		})
+		.unwrap();
+	assert_eq!(
+		prover.prove(&bp_gens).unwrap_err(),
+		R1CSError::PublicInputAfterChallenge
+	);
+
+	let mut verifier_transcript = Transcript::new(b"PublicInputTest");
+	let mut verifier = Verifier::new(&mut verifier_transcript);
+	verifier
+		.specify_randomized_constraints(|cs| cs.commit_public_bytes(b"late", b"input"))
+		.unwrap();
+	let mut proof_transcript = Transcript::new(b"PublicInputTest");
+	let mut other = Prover::new(&pc_gens, &mut proof_transcript);
+	other.specify_randomized_constraints(|_| Ok(())).unwrap();
+	let proof = other.prove(&bp_gens).unwrap();
+	assert_eq!(
+		verifier.verify(&proof, &pc_gens, &bp_gens),
+		Err(R1CSError::PublicInputAfterChallenge)
+	);
+}
+
+#[test]
+fn auxiliary_points_bind_the_proof() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(8, 1);
+	let mut rng = thread_rng();
+
+	// An ElGamal-style ciphertext computed outside of the constraint system.
+	let r = Scalar::random(&mut rng);
+	let ciphertext = (pc_gens.B_blinding * r).compress();
+	let (a, b) = (Scalar::from(3u64), Scalar::from(4u64));
+
+	let mut prover_transcript = Transcript::new(b"AuxiliaryPointTest");
+	let (proof, a_commitment, b_commitment) = {
+		let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+		let (a_commitment, a_var) = prover.commit(a, Scalar::random(&mut rng));
+		prover.commit_point(b"ciphertext", &ciphertext).unwrap();
+		let (b_commitment, b_var) = prover.commit(b, Scalar::random(&mut rng));
+		let (_, _, product) = prover.multiply(a_var.into(), b_var.into());
+		prover.constrain(product - Scalar::from(12u64));
+		(prover.prove(&bp_gens).unwrap(), a_commitment, b_commitment)
+	};
+
+	// The point is absorbed between the commitments, on both sides.
+	let verify = |transcript: &mut Transcript, point: Option<&CompressedRistretto>| {
+		let mut verifier = Verifier::new(transcript);
+		let a_var = verifier.commit(a_commitment);
+		if let Some(point) = point {
+			verifier.commit_point(b"ciphertext", point).unwrap();
+		}
+		let b_var = verifier.commit(b_commitment);
+		let (_, _, product) = verifier.multiply(a_var.into(), b_var.into());
+		verifier.constrain(product - Scalar::from(12u64));
+		verifier.verify(&proof, &pc_gens, &bp_gens)
+	};
+
+	let mut verifier_transcript = Transcript::new(b"AuxiliaryPointTest");
+	assert!(verify(&mut verifier_transcript, Some(&ciphertext)).is_ok());
+	// The transcripts stay in sync, so a protocol continuing after the proof
+	// derives the same challenges on both sides.
+	let (mut prover_next, mut verifier_next) = ([0u8; 32], [0u8; 32]);
+	prover_transcript.challenge_bytes(b"next", &mut prover_next);
+	verifier_transcript.challenge_bytes(b"next", &mut verifier_next);
+	assert_eq!(prover_next, verifier_next);
+
+	let mut verifier_transcript = Transcript::new(b"AuxiliaryPointTest");
+	assert!(verify(&mut verifier_transcript, None).is_err());
+	let other = (pc_gens.B_blinding * Scalar::random(&mut rng)).compress();
+	let mut verifier_transcript = Transcript::new(b"AuxiliaryPointTest");
+	assert!(verify(&mut verifier_transcript, Some(&other)).is_err());
+}
+
+/// Constrains `x` and `y` to be equal, by checking a random linear
+/// combination of their differences with a challenge vector, and records
+/// the challenges in `challenges`.
+fn vector_equality_gadget<CS: RandomizableConstraintSystem>(
+	cs: &mut CS,
+	x: Vec<Variable>,
+	y: Vec<Variable>,
+	challenges: Rc<RefCell<Vec<Scalar>>>,
+) -> Result<(), R1CSError> {
+	assert_eq!(x.len(), y.len());
+	cs.specify_randomized_constraints(move |cs| {
+		let weights = cs.challenge_scalars(b"equality weights", x.len());
+		let mut nonce = [0u8; 16];
+		cs.challenge_bytes(b"equality nonce", &mut nonce);
+		let mut lc = LinearCombination::default();
+		for ((x, y), w) in x.into_iter().zip(y).zip(weights.iter()) {
+			lc = lc + (x - y) * *w;
+		}
+		cs.constrain(lc);
+		challenges.borrow_mut().extend(weights);
+		challenges.borrow_mut().push(Scalar::from(u128::from_le_bytes(nonce)));
+		Ok(())
+	})
+}
+
+#[test]
+fn challenge_vectors_in_randomized_constraints() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(8, 1);
+	let values = [4u64, 8, 15, 16];
+
+	let prove = |y: &[u64]| {
+		let challenges = Rc::new(RefCell::new(Vec::new()));
+		let mut prover_transcript = Transcript::new(b"ChallengeVectorTest");
+		let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+		prover.enable_diagnostics();
+		let x = values
+			.iter()
+			.map(|v| prover.allocate(Some(Scalar::from(*v))).unwrap())
+			.collect();
+		let y = y
+			.iter()
+			.map(|v| prover.allocate(Some(Scalar::from(*v))).unwrap())
+			.collect();
+		vector_equality_gadget(&mut prover, x, y, challenges.clone()).unwrap();
+		let proof = prover.prove(&bp_gens);
+		let challenges = challenges.borrow().clone();
+		(proof, challenges)
+	};
+	let verify = |proof: &R1CSProof| {
+		let challenges = Rc::new(RefCell::new(Vec::new()));
+		let mut verifier_transcript = Transcript::new(b"ChallengeVectorTest");
+		let mut verifier = Verifier::new(&mut verifier_transcript);
+		let x = values.iter().map(|_| verifier.allocate(None).unwrap()).collect();
+		let y = values.iter().map(|_| verifier.allocate(None).unwrap()).collect();
+		vector_equality_gadget(&mut verifier, x, y, challenges.clone()).unwrap();
+		let result = verifier.verify(proof, &pc_gens, &bp_gens);
+		let challenges = challenges.borrow().clone();
+		(result, challenges)
+	};
+
+	let (proof, prover_challenges) = prove(&values);
+	let (result, verifier_challenges) = verify(&proof.unwrap());
+	assert!(result.is_ok());
+	assert_eq!(prover_challenges, verifier_challenges);
+
+	// Every challenge drawn under the same label is fresh.
+	assert_eq!(prover_challenges.len(), values.len() + 1);
+	for (i, c) in prover_challenges.iter().enumerate() {
+		assert!(prover_challenges[i + 1..].iter().all(|other| other != c));
+	}
+
+	// A prover whose vectors differ cannot satisfy the randomized constraint.
+	let (proof, _) = prove(&[4, 8, 15, 17]);
+	assert!(matches!(proof, Err(R1CSError::UnsatisfiedConstraint { .. })));
+}
+
+/// Constrains a random linear combination of `x` with `n` challenges, of
+/// which only the first `x.len()` are used, to be zero, and records the
+/// challenges in `challenges`.
+fn challenge_count_gadget<CS: RandomizableConstraintSystem>(
+	cs: &mut CS,
+	x: Vec<Variable>,
+	n: usize,
+	challenges: Rc<RefCell<Vec<Scalar>>>,
+) -> Result<(), R1CSError> {
+	cs.specify_randomized_constraints(move |cs| {
+		let weights = cs.challenge_scalars(b"count weights", n);
+		let lc = x
+			.into_iter()
+			.zip(weights.iter())
+			.fold(LinearCombination::default(), |lc, (x, w)| lc + x * *w);
+		cs.constrain(lc);
+		challenges.borrow_mut().extend(weights);
+		Ok(())
+	})
+}
+
+#[test]
+fn challenge_vector_lengths_are_bound() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(8, 1);
+
+	let challenges = Rc::new(RefCell::new(Vec::new()));
+	let mut prover_transcript = Transcript::new(b"ChallengeCountTest");
+	let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+	let x = (0..3).map(|_| prover.allocate(Some(Scalar::zero())).unwrap()).collect();
+	challenge_count_gadget(&mut prover, x, 3, challenges.clone()).unwrap();
+	let proof = prover.prove(&bp_gens).unwrap();
+	let prover_challenges = challenges.borrow().clone();
+
+	let verify = |n: usize| {
+		let challenges = Rc::new(RefCell::new(Vec::new()));
+		let mut verifier_transcript = Transcript::new(b"ChallengeCountTest");
+		let mut verifier = Verifier::new(&mut verifier_transcript);
+		let x = (0..3).map(|_| verifier.allocate(None).unwrap()).collect();
+		challenge_count_gadget(&mut verifier, x, n, challenges.clone()).unwrap();
+		let result = verifier.verify(&proof, &pc_gens, &bp_gens);
+		let challenges = challenges.borrow().clone();
+		(result, challenges)
+	};
+
+	let (result, verifier_challenges) = verify(3);
+	assert!(result.is_ok());
+	assert_eq!(prover_challenges, verifier_challenges);
+
+	// Asking for more challenges changes the first ones too, so the
+	// transcripts diverge and the proof is rejected.
+	let (result, longer_challenges) = verify(4);
+	assert!(matches!(result, Err(R1CSError::VerificationError)));
+	assert_eq!(longer_challenges.len(), 4);
+	assert!(prover_challenges
+		.iter()
+		.zip(longer_challenges.iter())
+		.all(|(a, b)| a != b));
+}
+
+#[test]
+fn circuit_labels_separate_proofs() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(8, 1);
+	let blinding = Scalar::random(&mut thread_rng());
+
+	// Both circuits square a committed value, so only the label tells them
+	// apart.
+	let prove = |label: &'static [u8]| {
+		let mut transcript = Transcript::new(b"CircuitLabelTest");
+		let mut prover = Prover::new_labeled(&pc_gens, &mut transcript, label);
+		let (commitment, v) = prover.commit(Scalar::from(3u64), blinding);
+		prover.multiply(v.into(), v.into());
+		(prover.prove(&bp_gens).unwrap(), commitment)
+	};
+	let verify = |label: &'static [u8], proof: &R1CSProof, commitment| {
+		let mut transcript = Transcript::new(b"CircuitLabelTest");
+		let mut verifier = Verifier::new_labeled(&mut transcript, label);
+		let v = verifier.commit(commitment);
+		verifier.multiply(v.into(), v.into());
+		verifier.verify(proof, &pc_gens, &bp_gens)
+	};
+
+	let (proof, commitment) = prove(b"withdraw-v2");
+	assert!(verify(b"withdraw-v2", &proof, commitment).is_ok());
+	assert!(verify(b"deposit-v2", &proof, commitment).is_err());
+	assert!(verify(b"", &proof, commitment).is_err());
+
+	// An empty label is the unlabeled constraint system.
+	let (proof, commitment) = prove(b"");
+	assert!(verify(b"", &proof, commitment).is_ok());
+	let mut transcript = Transcript::new(b"CircuitLabelTest");
+	let mut verifier = Verifier::new(&mut transcript);
+	let v = verifier.commit(commitment);
+	verifier.multiply(v.into(), v.into());
+	assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_ok());
+	assert!(verify(b"withdraw-v2", &proof, commitment).is_err());
+
+	// A circuit with the same label but another size does not verify.
+	let (proof, commitment) = prove(b"withdraw-v2");
+	let mut transcript = Transcript::new(b"CircuitLabelTest");
+	let mut verifier = Verifier::new_labeled(&mut transcript, b"withdraw-v2");
+	let v = verifier.commit(commitment);
+	verifier.multiply(v.into(), v.into());
+	verifier.constrain(v - v);
+	assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_err());
+
+	// A restored prover keeps its label.
+	let mut transcript = Transcript::new(b"CircuitLabelTest");
+	let mut prover = Prover::new_labeled(&pc_gens, &mut transcript, b"withdraw-v2");
+	let (commitment, v) = prover.commit(Scalar::from(3u64), blinding);
+	let checkpoint = prover.checkpoint();
+	drop(prover);
+	let mut transcript = Transcript::new(b"CircuitLabelTest");
+	let mut prover = Prover::restore(checkpoint, &mut transcript);
+	prover.multiply(v.into(), v.into());
+	let proof = prover.prove(&bp_gens).unwrap();
+	assert!(verify(b"withdraw-v2", &proof, commitment).is_ok());
+}
+
+#[test]
+fn verifier_with_fewer_constraints_fails_cleanly() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(8, 1);
+
+	// The last constraint is implied by the others, so a verifier omitting
+	// it would accept the proof if the size of the circuit was not bound.
+	fn gadget<CS: ConstraintSystem>(cs: &mut CS, v: Variable, redundant: bool) {
+		let (l, _, o) = cs.multiply(v.into(), v.into());
+		cs.constrain(o - Scalar::from(9u64));
+		if redundant {
+			cs.constrain(l - v);
+		}
+	}
+
+	let mut prover_transcript = Transcript::new(b"CircuitSizeTest");
+	let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+	let (commitment, v) = prover.commit(Scalar::from(3u64), Scalar::random(&mut thread_rng()));
+	gadget(&mut prover, v, true);
+	let proof = prover.prove(&bp_gens).unwrap();
+
+	let verify = |redundant: bool| {
+		let mut verifier_transcript = Transcript::new(b"CircuitSizeTest");
+		let mut verifier = Verifier::new(&mut verifier_transcript);
+		let v = verifier.commit(commitment);
+		gadget(&mut verifier, v, redundant);
+		verifier.verify(&proof, &pc_gens, &bp_gens)
+	};
+	assert!(verify(true).is_ok());
+	assert_eq!(verify(false), Err(R1CSError::VerificationError));
+}
+
+/// A broken RNG which only produces zeros.
+struct ZeroRng;
+
+impl rand_core::RngCore for ZeroRng {
+	fn next_u32(&mut self) -> u32 {
+		0
+	}
+
+	fn next_u64(&mut self) -> u64 {
+		0
+	}
+
+	fn fill_bytes(&mut self, dest: &mut [u8]) {
+		dest.iter_mut().for_each(|b| *b = 0);
+	}
+
+	fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+		self.fill_bytes(dest);
+		Ok(())
+	}
+}
+
+impl rand_core::CryptoRng for ZeroRng {}
+
+#[test]
+fn broken_rng_blindings_depend_on_the_witness() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(8, 1);
+
+	// Proves knowledge of a factorization of a public product, with no
+	// committed values to rekey the RNG with.
+	let prove = |a: u64, b: u64| {
+		let mut transcript = Transcript::new(b"BrokenRngTest");
+		let mut prover = Prover::new(&pc_gens, &mut transcript);
+		let (_, _, product) = prover
+			.allocate_multiplier(Some((Scalar::from(a), Scalar::from(b))))
+			.unwrap();
+		prover.constrain(product - Scalar::from(a * b));
+		let proof = prover.prove_with_rng(&bp_gens, &mut ZeroRng).unwrap();
+
+		let mut transcript = Transcript::new(b"BrokenRngTest");
+		let mut verifier = Verifier::new(&mut transcript);
+		let (_, _, product) = verifier.allocate_multiplier(None).unwrap();
+		verifier.constrain(product - Scalar::from(a * b));
+		assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_ok());
+		proof.to_bytes()
+	};
+	// The commitment S1 of the proof only depends on its blindings.
+	let s_commitment = |proof: &[u8]| proof[1 + 64..1 + 96].to_vec();
+
+	let (first, second) = (prove(3, 4), prove(2, 6));
+	assert_ne!(s_commitment(&first), s_commitment(&second));
+	assert_ne!(s_commitment(&first), vec![0u8; 32]);
+	assert_eq!(first, prove(3, 4));
+}
+
+#[test]
+fn r1cs_errors_implement_std_error() {
+	use webb_bulletproofs::ProofError;
+
+	fn assert_error<E: std::error::Error + Send + Sync + 'static>() {}
+	assert_error::<R1CSError>();
+
+	assert_eq!(
+		R1CSError::VerificationError.to_string(),
+		"R1CSProof did not verify correctly."
+	);
+	assert_eq!(
+		R1CSError::GadgetError {
+			description: "bad input".into()
+		}
+		.to_string(),
+		"Gadget error: \"bad input\""
+	);
+	assert_eq!(
+		R1CSError::malformed_witness("the value is too large").to_string(),
+		"Malformed witness: the value is too large"
+	);
+	assert_eq!(
+		R1CSError::UnsatisfiedConstraint {
+			index: 2,
+			label: Some("sum")
+		}
+		.to_string(),
+		"Constraint 2 (Some(\"sum\")) is not satisfied."
+	);
+
+	// The errors shared with range proofs convert, keeping their message.
+	let err = R1CSError::from(ProofError::InvalidGeneratorsLength {
+		required: 64,
+		actual: 32,
+	});
+	assert_eq!(err, R1CSError::InvalidGeneratorsLength {
+		required: 64,
+		actual: 32
+	});
+	assert_eq!(
+		err.to_string(),
+		"Invalid generators size, too few generators for proof: 64 required, 32 available"
+	);
+}
+
+/// Decomposes its input into eight bits, as a gadget library outside of
+/// the crate would, reporting values which do not fit as malformed.
+struct Byte;
+
+impl Gadget for Byte {
+	fn assemble<CS: ConstraintSystem>(
+		&self,
+		cs: &mut CS,
+		inputs: &[AllocatedScalar],
+	) -> Result<Vec<AllocatedScalar>, R1CSError> {
+		let input = inputs
+			.first()
+			.ok_or_else(|| R1CSError::gadget("the byte gadget needs an input"))?;
+		let value = match input.assignment {
+			Some(value) if value.as_bytes()[1..].iter().any(|&b| b != 0) => {
+				return Err(R1CSError::malformed_witness("the value does not fit in a byte"))
+			}
+			Some(value) => Some(value.as_bytes()[0]),
+			None => None,
+		};
+
+		let mut sum = LinearCombination::default();
+		for i in 0..8 {
+			// bit * (1 - bit) = 0
+			let (a, b, o) = cs.allocate_multiplier(value.map(|v| {
+				let bit = Scalar::from(((v >> i) & 1) as u64);
+				(bit, Scalar::one() - bit)
+			}))?;
+			cs.constrain(o.into());
+			cs.constrain(a + b - Scalar::one());
+			sum = sum + a * Scalar::from(1u64 << i);
+		}
+		cs.constrain(sum - input.variable);
+		Ok(Vec::new())
+	}
+}
+
+#[test]
+fn gadget_errors_are_specific() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(16, 1);
+	let transcript = || Transcript::new(b"GadgetErrorTest");
+
+	assert_eq!(R1CSError::gadget("bad input"), R1CSError::GadgetError {
+		description: "bad input".into()
+	});
+	assert_eq!(R1CSError::malformed_witness("too large"), R1CSError::MalformedWitness {
+		reason: "too large"
+	});
+
+	// The errors of a gadget outside of the crate reach the caller.
+	let (proof, commitments) =
+		prove_gadget(&Byte, &pc_gens, &bp_gens, &mut transcript(), &[Scalar::from(200u64)]).unwrap();
+	assert!(verify_gadget(&Byte, &pc_gens, &bp_gens, &mut transcript(), &commitments, &proof).is_ok());
+	assert_eq!(
+		prove_gadget(&Byte, &pc_gens, &bp_gens, &mut transcript(), &[Scalar::from(256u64)]).unwrap_err(),
+		R1CSError::MalformedWitness {
+			reason: "the value does not fit in a byte"
+		}
+	);
+	assert!(matches!(
+		prove_gadget(&Byte, &pc_gens, &bp_gens, &mut transcript(), &[]),
+		Err(R1CSError::GadgetError { .. })
+	));
+
+	// The nonzero gadget refuses an inverse which does not match, and zero,
+	// which has none.
+	let nonzero = |x: Scalar, x_inv: Scalar| {
+		let mut transcript = transcript();
+		let mut prover = Prover::new(&pc_gens, &mut transcript);
+		let (_, x_var) = prover.commit(x, Scalar::one());
+		let (_, x_inv_var) = prover.commit(x_inv, Scalar::one());
+		is_nonzero_gadget(
+			&mut prover,
+			AllocatedScalar {
+				variable: x_var,
+				assignment: Some(x),
+			},
+			AllocatedScalar {
+				variable: x_inv_var,
+				assignment: Some(x_inv),
+			},
+		)
+	};
+	let seven = Scalar::from(7u64);
+	assert!(nonzero(seven, seven.invert()).is_ok());
+	assert!(matches!(nonzero(seven, seven), Err(R1CSError::GadgetError { .. })));
+	assert!(matches!(
+		nonzero(Scalar::zero(), Scalar::zero()),
+		Err(R1CSError::GadgetError { .. })
+	));
+}
+
+/// A toy MiMC-style compression function, which cubes the left input
+/// plus the right input and a round constant over a few rounds.
+struct CubeHash;
+
+impl CubeHash {
+	const ROUNDS: u64 = 3;
+}
+
+impl CircuitHash for CubeHash {
+	fn hash(&self, left: Scalar, right: Scalar) -> Scalar {
+		(0..Self::ROUNDS).fold(left, |state, c| {
+			let t = state + right + Scalar::from(c);
+			t * t * t
+		})
+	}
+
+	fn hash_gadget<CS: ConstraintSystem>(
+		&self,
+		cs: &mut CS,
+		left: AllocatedScalar,
+		right: AllocatedScalar,
+	) -> Result<AllocatedScalar, R1CSError> {
+		let mut state = left.variable;
+		let mut assignment = left.assignment;
+		for c in 0..Self::ROUNDS {
+			let t = state + right.variable + Scalar::from(c);
+			let (_, _, square) = cs.multiply(t.clone(), t.clone());
+			let (_, _, cube) = cs.multiply(square.into(), t);
+			state = cube;
+			assignment = assignment.and_then(|a| right.assignment.map(|r| a + r + Scalar::from(c)));
+			assignment = assignment.map(|t| t * t * t);
+		}
+		Ok(AllocatedScalar {
+			variable: state,
+			assignment,
+		})
+	}
+}
+
+/// Proves that the `leaves` have the Merkle root committed by the prover,
+/// and verifies the proof against a commitment to `claimed_root`.
+fn merkle_root_roundtrip(leaves: &[u64], claimed_root: Scalar) -> (usize, Result<(), R1CSError>) {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64, 1);
+	let mut rng = thread_rng();
+
+	let leaves: Vec<Scalar> = leaves.iter().map(|leaf| Scalar::from(*leaf)).collect();
+	let root = merkle_root(&leaves, &CubeHash).unwrap();
+
+	let mut prover_transcript = Transcript::new(b"MerkleRootTest");
+	let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+	let (leaf_commitments, leaf_vars, _) = prover.commit_vec(&leaves, &mut rng);
+	let (root_commitment, root_var) = prover.commit(root, Scalar::random(&mut rng));
+	let allocated: Vec<AllocatedScalar> = leaf_vars
+		.iter()
+		.zip(leaves.iter())
+		.map(|(variable, leaf)| AllocatedScalar {
+			variable: *variable,
+			assignment: Some(*leaf),
+		})
+		.collect();
+	let computed = merkle_root_gadget(&mut prover, &allocated, &CubeHash).unwrap();
+	prover.constrain(computed - root_var);
+	let multipliers = prover.num_multipliers();
+	let proof = prover.prove(&bp_gens).unwrap();
+
+	let root_commitment = if claimed_root == root {
+		root_commitment
+	} else {
+		pc_gens.commit(claimed_root, Scalar::random(&mut rng)).compress()
+	};
+
+	let mut verifier_transcript = Transcript::new(b"MerkleRootTest");
+	let mut verifier = Verifier::new(&mut verifier_transcript);
+	let allocated: Vec<AllocatedScalar> = leaf_commitments
+		.iter()
+		.map(|commitment| AllocatedScalar {
+			variable: verifier.commit(*commitment),
+			assignment: None,
+		})
+		.collect();
+	let root_var = verifier.commit(root_commitment);
+	let computed = merkle_root_gadget(&mut verifier, &allocated, &CubeHash).unwrap();
+	verifier.constrain(computed - root_var);
+	(multipliers, verifier.verify(&proof, &pc_gens, &bp_gens))
+}
+
+#[test]
+fn merkle_root_gadget_matches_native_root() {
+	let hash = |l: u64, r: u64| CubeHash.hash(l.into(), r.into());
+
+	// Four leaves make a full tree of three hashes, of six multipliers each.
+	let root = CubeHash.hash(hash(1, 2), hash(3, 4));
+	assert_eq!(
+		merkle_root(&[1u64.into(), 2u64.into(), 3u64.into(), 4u64.into()], &CubeHash).unwrap(),
+		root
+	);
+	let (multipliers, result) = merkle_root_roundtrip(&[1, 2, 3, 4], root);
+	assert_eq!(multipliers, 3 * 6);
+	assert!(result.is_ok());
+
+	// Six leaves are padded with two leaves of value one.
+	let leaves = [5u64, 6, 7, 8, 9, 10];
+	let root = CubeHash.hash(
+		CubeHash.hash(hash(5, 6), hash(7, 8)),
+		CubeHash.hash(hash(9, 10), hash(1, 1)),
+	);
+	let scalars: Vec<Scalar> = leaves.iter().map(|leaf| Scalar::from(*leaf)).collect();
+	assert_eq!(merkle_root(&scalars, &CubeHash).unwrap(), root);
+	let (multipliers, result) = merkle_root_roundtrip(&leaves, root);
+	assert_eq!(multipliers, 7 * 6);
+	assert!(result.is_ok());
+
+	// The proof does not verify for another root.
+	let (_, result) = merkle_root_roundtrip(&leaves, root + Scalar::one());
+	assert_eq!(result, Err(R1CSError::VerificationError));
+
+	// A single leaf is its own root, and there is no root without leaves.
+	assert_eq!(
+		merkle_root(&[Scalar::from(3u64)], &CubeHash).unwrap(),
+		Scalar::from(3u64)
+	);
+	assert!(matches!(
+		merkle_root(&[], &CubeHash),
+		Err(R1CSError::GadgetError { .. })
+	));
+}
+
+#[test]
+fn gadget_verification_key_matches_fresh_synthesis() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64, 1);
+	let path = MerklePath::new(4, CubeHash);
+	let key = path.verification_key().unwrap();
+	assert_eq!(key.num_inputs(), 2 + 2 * 4);
+	assert_eq!(key.description().num_multipliers(), 4 * (6 + 3));
+
+	let siblings: Vec<(Scalar, bool)> = [(3u64, false), (5, true), (11, true), (13, false)]
+		.iter()
+		.map(|&(sibling, is_right)| (Scalar::from(sibling), is_right))
+		.collect();
+	let inputs = path.inputs(Scalar::from(7u64), &siblings).unwrap();
+	let (proof, commitments) = prove_gadget(
+		&path,
+		&pc_gens,
+		&bp_gens,
+		&mut Transcript::new(b"MerklePathTest"),
+		&inputs,
+	)
+	.unwrap();
+
+	let fresh = |commitments: &[CompressedRistretto]| {
+		let mut transcript = Transcript::new(b"MerklePathTest");
+		verify_gadget(&path, &pc_gens, &bp_gens, &mut transcript, commitments, &proof)
+	};
+	let keyed = |key: &GadgetVerificationKey, commitments: &[CompressedRistretto]| {
+		let mut transcript = Transcript::new(b"MerklePathTest");
+		verify_with_key(key, &pc_gens, &bp_gens, &mut transcript, commitments, &proof)
+	};
+	assert_eq!(fresh(&commitments), Ok(()));
+	assert_eq!(keyed(&key, &commitments), Ok(()));
+
+	// Both reject the proof for another root.
+	let mut other_root = commitments.clone();
+	other_root[1] = pc_gens
+		.commit(inputs[1] + Scalar::one(), Scalar::random(&mut thread_rng()))
+		.compress();
+	assert_eq!(fresh(&other_root), Err(R1CSError::VerificationError));
+	assert_eq!(keyed(&key, &other_root), Err(R1CSError::VerificationError));
+
+	// The key round-trips through its encoding and serde, and verifies
+	// the same.
+	let decoded = GadgetVerificationKey::from_bytes(&key.to_bytes()).unwrap();
+	assert_eq!(decoded, key);
+	assert_eq!(keyed(&decoded, &commitments), Ok(()));
+	let from_bincode: GadgetVerificationKey = bincode::deserialize(&bincode::serialize(&key).unwrap()).unwrap();
+	assert_eq!(from_bincode.fingerprint(), key.fingerprint());
+
+	// A corrupted key is rejected, whether the fingerprint or the circuit
+	// is altered.
+	let bytes = key.to_bytes();
+	let mut bad_fingerprint = bytes.clone();
+	bad_fingerprint[0] ^= 1;
+	assert_eq!(
+		GadgetVerificationKey::from_bytes(&bad_fingerprint),
+		Err(R1CSError::FormatError)
+	);
+	let mut bad_coefficient = bytes.clone();
+	let last = bad_coefficient.len() - 1;
+	bad_coefficient[last - 1] ^= 1;
+	assert_eq!(
+		GadgetVerificationKey::from_bytes(&bad_coefficient),
+		Err(R1CSError::FormatError)
+	);
+	assert_eq!(
+		GadgetVerificationKey::from_bytes(&bytes[..bytes.len() - 1]),
+		Err(R1CSError::FormatError)
+	);
+
+	// The fingerprint identifies the depth of the tree.
+	assert_ne!(
+		MerklePath::new(3, CubeHash).verification_key().unwrap().fingerprint(),
+		key.fingerprint()
+	);
+
+	// A direction which is not a bit is refused by the gadget.
+	let mut bad_direction = inputs.clone();
+	bad_direction[3] = Scalar::from(2u64);
+	assert!(matches!(
+		prove_gadget(
+			&path,
+			&pc_gens,
+			&bp_gens,
+			&mut Transcript::new(b"MerklePathTest"),
+			&bad_direction
+		),
+		Err(R1CSError::GadgetError { .. })
+	));
+}
+
+#[test]
+fn gadget_verification_key_rejects_other_depths() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(256, 1);
+	let key = MerklePath::new(20, CubeHash).verification_key().unwrap();
+
+	let path = MerklePath::new(16, CubeHash);
+	let siblings: Vec<(Scalar, bool)> = (0..16u64).map(|i| (Scalar::from(i), i % 3 == 0)).collect();
+	let leaf = Scalar::from(42u64);
+	let inputs = path.inputs(leaf, &siblings).unwrap();
+	let (proof, commitments) = prove_gadget(
+		&path,
+		&pc_gens,
+		&bp_gens,
+		&mut Transcript::new(b"MerklePathTest"),
+		&inputs,
+	)
+	.unwrap();
+
+	let keyed = |key: &GadgetVerificationKey| {
+		let mut transcript = Transcript::new(b"MerklePathTest");
+		verify_with_key(key, &pc_gens, &bp_gens, &mut transcript, &commitments, &proof)
+	};
+	assert_eq!(
+		keyed(&key),
+		Err(R1CSError::WrongNumberOfCommitments {
+			expected: 2 + 2 * 20,
+			actual: 2 + 2 * 16
+		})
+	);
+	assert_eq!(keyed(&path.verification_key().unwrap()), Ok(()));
+
+	// A path of another depth has no root in this tree.
+	assert!(matches!(
+		path.root(leaf, &siblings[..15]),
+		Err(R1CSError::GadgetError { .. })
+	));
+}
+
+#[test]
+fn custom_pedersen_bases_are_bound_to_the_proof() {
+	use curve25519_dalek::ristretto::RistrettoPoint;
+	use sha3::Sha3_512;
+
+	let custom_gens = PedersenGens::from_points(
+		RistrettoPoint::hash_from_bytes::<Sha3_512>(b"external value base"),
+		RistrettoPoint::hash_from_bytes::<Sha3_512>(b"external blinding base"),
+	)
+	.unwrap();
+	let default_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(8, 1);
+	let mut rng = thread_rng();
+
+	// A commitment created by another system under the custom bases.
+	let (x, x_blinding) = (Scalar::from(42u64), Scalar::random(&mut rng));
+	let commitment = custom_gens.commit(x, x_blinding).compress();
+
+	let mut prover_transcript = Transcript::new(b"CustomBasesTest");
+	let mut prover = Prover::new_with_gens(&custom_gens, &mut prover_transcript);
+	let x_var = prover.commit_existing(x, x_blinding, &commitment).unwrap();
+	prover.constrain(x_var - Scalar::from(42u64));
+	let checkpoint = prover.checkpoint();
+	let proof = prover.prove(&bp_gens).unwrap();
+
+	// A prover restored from a checkpoint replays the bases.
+	let mut restored_transcript = Transcript::new(b"CustomBasesTest");
+	let restored = Prover::restore(checkpoint, &mut restored_transcript);
+	let restored_proof = restored.prove(&bp_gens).unwrap();
+
+	let verify = |proof: &R1CSProof, bound_gens: Option<&PedersenGens>, pc_gens: &PedersenGens| {
+		let mut verifier_transcript = Transcript::new(b"CustomBasesTest");
+		let mut verifier = match bound_gens {
+			Some(bound_gens) => Verifier::new_with_gens(&mut verifier_transcript, bound_gens),
+			None => Verifier::new(&mut verifier_transcript),
+		};
+		let x_var = verifier.commit(commitment);
+		verifier.constrain(x_var - Scalar::from(42u64));
+		verifier.verify(proof, pc_gens, &bp_gens)
+	};
+
+	assert!(verify(&proof, Some(&custom_gens), &custom_gens).is_ok());
+	assert!(verify(&restored_proof, Some(&custom_gens), &custom_gens).is_ok());
+
+	// A verifier assuming the default bases rejects the proof, as does one
+	// which does not bind the bases to the transcript.
+	assert_eq!(
+		verify(&proof, Some(&default_gens), &default_gens),
+		Err(R1CSError::VerificationError)
+	);
+	assert_eq!(
+		verify(&proof, Some(&custom_gens), &default_gens),
+		Err(R1CSError::VerificationError)
+	);
+	assert_eq!(verify(&proof, None, &custom_gens), Err(R1CSError::VerificationError));
+}
+
+#[test]
+fn custom_pedersen_bases_are_bound_when_verifying_compiled() {
+	use curve25519_dalek::ristretto::RistrettoPoint;
+	use sha3::Sha3_512;
+
+	let custom_gens = PedersenGens::from_points(
+		RistrettoPoint::hash_from_bytes::<Sha3_512>(b"external value base"),
+		RistrettoPoint::hash_from_bytes::<Sha3_512>(b"external blinding base"),
+	)
+	.unwrap();
+	let default_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(8, 1);
+
+	// Shows that x^2 = 1764 for the committed x.
+	let square = |cs: &mut Verifier, vars: Vec<Variable>| {
+		let (_, _, x_squared) = cs.multiply(vars[0].into(), vars[0].into());
+		cs.constrain(x_squared - Scalar::from(1764u64));
+		Ok(())
+	};
+	let description = CircuitDescription::from_circuit(1, square).unwrap();
+
+	let prove = |pc_gens: &PedersenGens| {
+		let mut prover_transcript = Transcript::new(b"CompiledBasesTest");
+		let mut prover = Prover::new_with_gens(pc_gens, &mut prover_transcript);
+		let (commitment, x) = prover.commit(Scalar::from(42u64), Scalar::random(&mut thread_rng()));
+		let (_, _, x_squared) = prover.multiply(x.into(), x.into());
+		prover.constrain(x_squared - Scalar::from(1764u64));
+		assert!(prover.check_circuit(&description).is_ok());
+		(prover.prove(&bp_gens).unwrap(), commitment)
+	};
+	let verify = |proof: &R1CSProof, commitment: CompressedRistretto, pc_gens: &PedersenGens| {
+		let mut verifier_transcript = Transcript::new(b"CompiledBasesTest");
+		let compiled = Verifier::verify_compiled(
+			&mut verifier_transcript,
+			&description,
+			proof,
+			&[commitment],
+			pc_gens,
+			&bp_gens,
+		);
+
+		let key = VerificationKey::from_description(description.clone(), &bp_gens, pc_gens).unwrap();
+		let mut verifier_transcript = Transcript::new(b"CompiledBasesTest");
+		assert_eq!(key.verify(&mut verifier_transcript, &[commitment], proof), compiled);
+		compiled
+	};
+
+	let (proof, commitment) = prove(&custom_gens);
+	assert_eq!(verify(&proof, commitment, &custom_gens), Ok(()));
+	assert_eq!(
+		verify(&proof, commitment, &default_gens),
+		Err(R1CSError::VerificationError)
+	);
+
+	// The default bases are not bound, so proofs from `Prover::new_with_gens`
+	// and `Prover::new` are alike.
+	let (proof, commitment) = prove(&default_gens);
+	assert_eq!(verify(&proof, commitment, &default_gens), Ok(()));
+	let mut verifier_transcript = Transcript::new(b"CompiledBasesTest");
+	let mut verifier = Verifier::new(&mut verifier_transcript);
+	let x = verifier.commit(commitment);
+	assert!(square(&mut verifier, vec![x]).is_ok());
+	assert!(verifier.verify(&proof, &default_gens, &bp_gens).is_ok());
+}
+
+/// Constrains \\(x^2 = c y\\) for the coefficient \\(c\\).
+fn scaled_square<CS: ConstraintSystem>(cs: &mut CS, x: Variable, y: Variable, c: u64) {
+	let (_, _, x_squared) = cs.multiply(x.into(), x.into());
+	cs.constrain(x_squared - Scalar::from(c) * y);
+}
+
+#[test]
+fn circuit_hash_binding_catches_a_different_coefficient() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(8, 1);
+
+	// The prover shows that 6^2 = 4 * 9.
+	let prove = |bind: bool| {
+		let mut transcript = Transcript::new(b"CircuitHashBindingTest");
+		let mut prover = Prover::new(&pc_gens, &mut transcript);
+		let (x_com, x) = prover.commit(Scalar::from(6u64), Scalar::random(&mut thread_rng()));
+		let (y_com, y) = prover.commit(Scalar::from(9u64), Scalar::random(&mut thread_rng()));
+		scaled_square(&mut prover, x, y, 4);
+		if bind {
+			prover.bind_circuit_hash();
+		}
+		let circuit_hash = prover.shape_digest();
+		assert_eq!(circuit_hash, prover.circuit_hash());
+		(prover.prove(&bp_gens).unwrap(), circuit_hash, x_com, y_com)
+	};
+	let verify = |proof: &R1CSProof, x_com, y_com, c: u64, binding: Option<[u8; 32]>| {
+		let mut transcript = Transcript::new(b"CircuitHashBindingTest");
+		let mut verifier = Verifier::new(&mut transcript);
+		let x = verifier.commit(x_com);
+		let y = verifier.commit(y_com);
+		scaled_square(&mut verifier, x, y, c);
+		if let Some(circuit_hash) = binding {
+			verifier.bind_circuit_hash(circuit_hash);
+		}
+		let circuit_hash = verifier.shape_digest();
+		(verifier.verify(proof, &pc_gens, &bp_gens), circuit_hash)
+	};
+
+	let (proof, prover_hash, x_com, y_com) = prove(true);
+	let (result, verifier_hash) = verify(&proof, x_com, y_com, 4, Some(prover_hash));
+	assert!(result.is_ok());
+	assert_eq!(verifier_hash, prover_hash);
+
+	// The hashes differ in a single coefficient, and the bound verifier
+	// reports the mismatch instead of a failed proof.
+	let (result, verifier_hash) = verify(&proof, x_com, y_com, 3, Some(prover_hash));
+	assert_eq!(result, Err(R1CSError::CircuitMismatch));
+	assert_ne!(verifier_hash, prover_hash);
+	let (result, _) = verify(&proof, x_com, y_com, 3, None);
+	assert_eq!(result, Err(R1CSError::VerificationError));
+
+	// Bound proofs are only accepted by bound verifiers, and conversely.
+	let (result, _) = verify(&proof, x_com, y_com, 4, None);
+	assert_eq!(result, Err(R1CSError::VerificationError));
+	let (unbound_proof, _, x_com, y_com) = prove(false);
+	let (result, _) = verify(&unbound_proof, x_com, y_com, 4, None);
+	assert!(result.is_ok());
+	let (result, _) = verify(&unbound_proof, x_com, y_com, 4, Some(prover_hash));
+	assert_eq!(result, Err(R1CSError::VerificationError));
+}
+
+#[test]
+fn rolled_back_gadgets_are_not_proven() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(16, 1);
+	let mut rng = thread_rng();
+
+	// The prover tries two range checks of a value of 200, and keeps the
+	// cheaper one, with 8 bits instead of 16.
+	let mut prover_transcript = Transcript::new(b"RollbackTest");
+	let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+	let (commitment, var) = prover.commit(200u64.into(), Scalar::random(&mut rng));
+	let snapshot = prover.snapshot();
+	let mut costs = Vec::new();
+	for &n in [16, 8].iter() {
+		prover.rollback(&snapshot).unwrap();
+		range_proof(&mut prover, var.into(), Some(200), n).unwrap();
+		costs.push((prover.metrics().multipliers, n));
+	}
+	let (multipliers, n) = *costs.iter().min().unwrap();
+	assert_eq!((multipliers, n), (8, 8));
+
+	// Rolling back and synthesizing the chosen strategy again gives the
+	// same constraint system.
+	let hash = prover.circuit_hash();
+	prover.rollback(&snapshot).unwrap();
+	assert_eq!(prover.metrics().multipliers, 0);
+	range_proof(&mut prover, var.into(), Some(200), n).unwrap();
+	assert_eq!(prover.circuit_hash(), hash);
+	let proof = prover.prove(&bp_gens).unwrap();
+
+	// The verifier only synthesizes the chosen strategy.
+	let mut verifier_transcript = Transcript::new(b"RollbackTest");
+	let mut verifier = Verifier::new(&mut verifier_transcript);
+	let var = verifier.commit(commitment);
+	range_proof(&mut verifier, var.into(), None, n).unwrap();
+	assert_eq!(verifier.circuit_hash(), hash);
+	assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_ok());
+}
+
+#[test]
+fn rollbacks_cannot_cross_transcript_operations() {
+	let pc_gens = PedersenGens::default();
+
+	let mut prover_transcript = Transcript::new(b"RollbackTest");
+	let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+	let early = prover.snapshot();
+	let (_, x) = prover.commit(3u64.into(), Scalar::one());
+	let snapshot = prover.snapshot();
+	prover.multiply(x.into(), x.into());
+	let late = prover.snapshot();
+
+	assert_eq!(prover.rollback(&early), Err(R1CSError::RollbackAcrossTranscript));
+	assert_eq!(prover.rollback(&snapshot), Ok(()));
+	assert_eq!(prover.rollback(&late), Err(R1CSError::RollbackAcrossTranscript));
+	prover.commit_public_bytes(b"public", b"input").unwrap();
+	assert_eq!(prover.rollback(&snapshot), Err(R1CSError::RollbackAcrossTranscript));
+
+	let mut verifier_transcript = Transcript::new(b"RollbackTest");
+	let mut verifier = Verifier::new(&mut verifier_transcript);
+	let snapshot = verifier.snapshot();
+	let x = verifier.commit(CompressedRistretto::default());
+	verifier.multiply(x.into(), x.into());
+	assert_eq!(verifier.rollback(&snapshot), Err(R1CSError::RollbackAcrossTranscript));
+
+	let snapshot = verifier.snapshot();
+	verifier.multiply(x.into(), x.into());
+	assert_eq!(verifier.rollback(&snapshot), Ok(()));
+	assert_eq!(verifier.num_multipliers(), 1);
+	verifier.commit_public_bytes(b"public", b"input").unwrap();
+	assert_eq!(verifier.rollback(&snapshot), Err(R1CSError::RollbackAcrossTranscript));
+}
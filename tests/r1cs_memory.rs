@@ -0,0 +1,81 @@
+//! Measures the memory used by the R1CS prover.
+//!
+//! This is a separate test target so that the counting allocator only
+//! replaces the global allocator of this binary, and so that no other
+//! test allocates concurrently while the peak is recorded.
+
+use curve25519_dalek::scalar::Scalar;
+use merlin::Transcript;
+use std::{
+	alloc::{GlobalAlloc, Layout, System},
+	sync::atomic::{AtomicUsize, Ordering},
+};
+use webb_bulletproofs::{r1cs::*, BulletproofGens, PedersenGens};
+
+/// Tracks the number of bytes currently allocated and the peak since it was
+/// last reset.
+struct CountingAllocator;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+	unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+		let current = CURRENT.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+		PEAK.fetch_max(current, Ordering::SeqCst);
+		System.alloc(layout)
+	}
+
+	unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+		CURRENT.fetch_sub(layout.size(), Ordering::SeqCst);
+		System.dealloc(ptr, layout)
+	}
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Allocates `n` multipliers, each with two constraints, over one committed
+/// value.
+fn synthetic_circuit<CS: ConstraintSystem>(cs: &mut CS, v: Variable, n: u64) {
+	for i in 0..n {
+		let (l, r, o) = cs.multiply(v + i, v + 1u64);
+		cs.constrain(o - l * Scalar::from(4u64));
+		cs.constrain(r - v - 1u64);
+	}
+}
+
+#[test]
+fn prover_peak_allocation_is_bounded() {
+	// Proving this circuit used to allocate about 2750 bytes per multiplier
+	// on top of the synthesized constraint system, mostly for the
+	// precomputed tables of the commitments and the dense weight vectors.
+	const MAX_BYTES_PER_MULTIPLIER: usize = 1024;
+
+	let n = 1 << 12;
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(n, 1);
+
+	let mut transcript = Transcript::new(b"R1CSMemoryTest");
+	let mut prover = Prover::new(&pc_gens, &mut transcript);
+	let (commitment, v) = prover.commit(3u64.into(), Scalar::from(5u64));
+	synthetic_circuit(&mut prover, v, n as u64);
+
+	let before = CURRENT.load(Ordering::SeqCst);
+	PEAK.store(before, Ordering::SeqCst);
+	let proof = prover.prove(&bp_gens).unwrap();
+	let peak = PEAK.load(Ordering::SeqCst) - before;
+
+	assert!(
+		peak / n < MAX_BYTES_PER_MULTIPLIER,
+		"proving allocated {} bytes, {} per multiplier",
+		peak,
+		peak / n
+	);
+
+	let mut transcript = Transcript::new(b"R1CSMemoryTest");
+	let mut verifier = Verifier::new(&mut transcript);
+	let v = verifier.commit(commitment);
+	synthetic_circuit(&mut verifier, v, n as u64);
+	assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_ok());
+}
@@ -0,0 +1,64 @@
+//! Property tests of the R1CS proofs over random circuits.
+//!
+//! Proving is slow, so every property runs a small number of cases over
+//! small circuits.
+
+use curve25519_dalek::scalar::Scalar;
+use merlin::Transcript;
+use proptest::prelude::*;
+use webb_bulletproofs::{
+	r1cs::{AllocatedScalar, Gadget, Prover},
+	testing::strategies::*,
+	PedersenGens,
+};
+
+/// Returns whether the prover finds the `witness` to satisfy the `circuit`.
+fn prover_is_satisfied(circuit: &RandomCircuit, witness: &[Scalar]) -> bool {
+	let pc_gens = PedersenGens::default();
+	let mut transcript = Transcript::new(b"RandomCircuitTest");
+	let mut prover = Prover::new(&pc_gens, &mut transcript);
+	prover.enable_diagnostics();
+	let inputs: Vec<_> = witness
+		.iter()
+		.map(|value| AllocatedScalar {
+			variable: prover.commit(*value, Scalar::one()).1,
+			assignment: Some(*value),
+		})
+		.collect();
+	circuit.assemble(&mut prover, &inputs).unwrap();
+	prover.is_satisfied().unwrap()
+}
+
+proptest! {
+	#![proptest_config(ProptestConfig::with_cases(32))]
+
+	#[test]
+	fn satisfiable_circuits_roundtrip((circuit, witness) in satisfiable_circuit(4, 8, 4)) {
+		prop_assert!(circuit.is_satisfied(&witness));
+		prop_assert!(prover_is_satisfied(&circuit, &witness));
+		assert_roundtrip(&circuit, &witness)?;
+	}
+
+	#[test]
+	fn perturbed_witnesses_are_rejected(
+		(circuit, witness, perturbed) in satisfiable_circuit(4, 8, 4).prop_flat_map(|(circuit, witness)| {
+			let perturbed = perturbed_witness(circuit.clone(), witness.clone());
+			(Just(circuit), Just(witness), perturbed)
+		})
+	) {
+		prop_assert!(!prover_is_satisfied(&circuit, &perturbed));
+		assert_rejects_witness(&circuit, &witness, &perturbed)?;
+	}
+
+	#[test]
+	fn perturbed_constraints_are_rejected(
+		(circuit, witness, perturbed) in satisfiable_circuit(4, 8, 4).prop_flat_map(|(circuit, witness)| {
+			let perturbed = perturbed_constraint(circuit.clone());
+			(Just(circuit), Just(witness), perturbed)
+		})
+	) {
+		prop_assert!(!perturbed.is_satisfied(&witness));
+		prop_assert!(!prover_is_satisfied(&perturbed, &witness));
+		assert_rejects_circuit(&circuit, &perturbed, &witness)?;
+	}
+}
@@ -72,11 +72,13 @@ fn deserialize_and_verify() {
 	for i in 0..4 {
 		for j in 0..4 {
 			let (n, m) = (8 << i, 1 << j);
-			let proof = RangeProof::from_bytes(&hex::decode(&proofs[i][j]).unwrap())
+			let proof = RangeProof::from_bytes_legacy(&hex::decode(&proofs[i][j]).unwrap())
 				.expect("Rangeproof deserialization failed");
 			let mut transcript = Transcript::new(b"Deserialize-And-Verify Test");
+			// Pin the verifier randomness so that a failure is reproducible.
+			let mut rng = ChaChaRng::from_seed([(i * 4 + j) as u8; 32]);
 			assert_eq!(
-				proof.verify_multiple(&bp_gens, &pc_gens, &mut transcript, &vc[0..m], n,),
+				proof.verify_multiple_with_rng(&bp_gens, &pc_gens, &mut transcript, &vc[0..m], n, &mut rng),
 				Ok(())
 			);
 		}
@@ -124,3 +126,2259 @@ fn generate_test_vectors() {
 
 	panic!();
 }
+
+#[test]
+fn stream_roundtrip() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64, 8);
+	let mut test_rng = ChaChaRng::from_seed([24u8; 32]);
+
+	let values = vec![0u64, 1, 2, 3];
+	let blindings = (0..4).map(|_| Scalar::random(&mut test_rng)).collect::<Vec<_>>();
+	let mut transcript = Transcript::new(b"Stream Roundtrip Test");
+	let (proof, value_commitments) =
+		RangeProof::prove_multiple(&bp_gens, &pc_gens, &mut transcript, &values, &blindings, 64).unwrap();
+
+	let mut buf = Vec::new();
+	let written = proof.write_to(&mut buf).unwrap();
+	assert_eq!(written, buf.len());
+	assert_eq!(buf, proof.to_bytes());
+
+	let read = RangeProof::read_from(&buf[..], buf.len()).unwrap();
+	assert_eq!(read.to_bytes(), buf);
+	let mut transcript = Transcript::new(b"Stream Roundtrip Test");
+	assert!(read
+		.verify_multiple(&bp_gens, &pc_gens, &mut transcript, &value_commitments, 64)
+		.is_ok());
+
+	// Inputs above the size cap or with trailing bytes are rejected.
+	assert!(RangeProof::read_from(&buf[..], buf.len() - 1).is_err());
+	let mut trailing = buf.clone();
+	trailing.push(0);
+	assert!(RangeProof::read_from(&trailing[..], trailing.len()).is_err());
+}
+
+#[test]
+fn framed_stream_roundtrip() {
+	use std::io::{Cursor, Read};
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(32, 2);
+	let mut test_rng = ChaChaRng::from_seed([27u8; 32]);
+
+	let blindings = [Scalar::random(&mut test_rng), Scalar::random(&mut test_rng)];
+	let mut transcript = Transcript::new(b"Framed Stream Test");
+	let (proof, value_commitments) =
+		RangeProof::prove_multiple(&bp_gens, &pc_gens, &mut transcript, &[5, 6], &blindings, 32).unwrap();
+	let proof_len = proof.to_bytes().len();
+
+	// An envelope with a header, two framed proofs and a trailer.
+	let mut envelope = b"head".to_vec();
+	assert_eq!(proof.write_framed(&mut envelope).unwrap(), 4 + proof_len);
+	assert_eq!(proof.write_framed(&mut envelope).unwrap(), 4 + proof_len);
+	envelope.extend_from_slice(b"tail");
+	assert_eq!(&envelope[4..8], &(proof_len as u32).to_le_bytes());
+	assert_eq!(&envelope[8..8 + proof_len], &proof.to_bytes()[..]);
+
+	let read_envelope = |mut reader: &mut dyn Read| {
+		let mut head = [0u8; 4];
+		reader.read_exact(&mut head).unwrap();
+		let first = RangeProof::read_framed(&mut reader, proof_len).unwrap();
+		let second = RangeProof::read_framed(&mut reader, proof_len).unwrap();
+		let mut tail = Vec::new();
+		reader.read_to_end(&mut tail).unwrap();
+		assert_eq!((&head, &tail[..]), (b"head", &b"tail"[..]));
+		(first, second)
+	};
+
+	let (first, second) = read_envelope(&mut Cursor::new(&envelope));
+	assert_eq!(first.to_bytes(), proof.to_bytes());
+	assert_eq!(second.to_bytes(), proof.to_bytes());
+	let mut transcript = Transcript::new(b"Framed Stream Test");
+	assert!(first
+		.verify_multiple(&bp_gens, &pc_gens, &mut transcript, &value_commitments, 32)
+		.is_ok());
+
+	// Readers returning the envelope in pieces split inside the prefixes
+	// and the elements.
+	for &(i, j) in [(1, 6), (5, 39), (8 + proof_len + 2, 8 + proof_len + 77)].iter() {
+		let mut pieces = (&envelope[..i]).chain(&envelope[i..j]).chain(&envelope[j..]);
+		let (first, second) = read_envelope(&mut pieces);
+		assert_eq!(first.to_bytes(), proof.to_bytes());
+		assert_eq!(second.to_bytes(), proof.to_bytes());
+	}
+
+	// Short reads and oversized frames are rejected.
+	let frame = &envelope[4..8 + proof_len];
+	assert!(RangeProof::read_framed(&frame[..2], proof_len).is_err());
+	assert!(RangeProof::read_framed(&frame[..frame.len() - 1], proof_len).is_err());
+	assert!(RangeProof::read_framed(frame, proof_len - 1).is_err());
+}
+
+#[test]
+fn forked_transcripts_are_not_interchangeable() {
+	use webb_bulletproofs::fork_transcript;
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64, 1);
+	let mut test_rng = ChaChaRng::from_seed([24u8; 32]);
+	let blinding = Scalar::random(&mut test_rng);
+
+	let mut parent = Transcript::new(b"Fork Test Session");
+	let mut child = fork_transcript(&mut parent, b"first");
+	let (proof, com) = RangeProof::prove_single(&bp_gens, &pc_gens, &mut child, 7, &blinding, 32).unwrap();
+
+	// Same session and label.
+	let mut parent = Transcript::new(b"Fork Test Session");
+	let mut child = fork_transcript(&mut parent, b"first");
+	assert!(proof.verify_single(&bp_gens, &pc_gens, &mut child, &com, 32).is_ok());
+
+	// Different label.
+	let mut parent = Transcript::new(b"Fork Test Session");
+	let mut child = fork_transcript(&mut parent, b"second");
+	assert!(proof.verify_single(&bp_gens, &pc_gens, &mut child, &com, 32).is_err());
+
+	// Same label, but a second fork of the same parent.
+	let mut parent = Transcript::new(b"Fork Test Session");
+	let _ = fork_transcript(&mut parent, b"first");
+	let mut child = fork_transcript(&mut parent, b"first");
+	assert!(proof.verify_single(&bp_gens, &pc_gens, &mut child, &com, 32).is_err());
+
+	// Different session.
+	let mut parent = Transcript::new(b"Other Session");
+	let mut child = fork_transcript(&mut parent, b"first");
+	assert!(proof.verify_single(&bp_gens, &pc_gens, &mut child, &com, 32).is_err());
+}
+
+#[test]
+fn custom_pedersen_gens_do_not_verify_under_defaults() {
+	use curve25519_dalek::ristretto::RistrettoPoint;
+	use sha3::Sha3_512;
+
+	let custom_gens = PedersenGens::from_points(
+		RistrettoPoint::hash_from_bytes::<Sha3_512>(b"custom value base"),
+		RistrettoPoint::hash_from_bytes::<Sha3_512>(b"custom blinding base"),
+	)
+	.unwrap();
+	let bp_gens = BulletproofGens::new(64, 1);
+	let mut test_rng = ChaChaRng::from_seed([24u8; 32]);
+	let blinding = Scalar::random(&mut test_rng);
+
+	let mut transcript = Transcript::new(b"Custom Gens Test");
+	let (proof, com) = RangeProof::prove_single(&bp_gens, &custom_gens, &mut transcript, 7, &blinding, 32).unwrap();
+
+	let mut transcript = Transcript::new(b"Custom Gens Test");
+	assert!(proof
+		.verify_single(&bp_gens, &custom_gens, &mut transcript, &com, 32)
+		.is_ok());
+
+	let mut transcript = Transcript::new(b"Custom Gens Test");
+	assert!(proof
+		.verify_single(&bp_gens, &PedersenGens::default(), &mut transcript, &com, 32)
+		.is_err());
+}
+
+#[test]
+fn domain_separated_pedersen_gens() {
+	let asset_a = PedersenGens::new(b"asset A");
+	let asset_b = PedersenGens::new(b"asset B");
+	let bp_gens = BulletproofGens::new(32, 2);
+	let mut test_rng = ChaChaRng::from_seed([57u8; 32]);
+	let values = [1037u64, 4_000_000];
+	let blindings: Vec<Scalar> = values.iter().map(|_| Scalar::random(&mut test_rng)).collect();
+	let transcript = Transcript::new(b"Domain Separated Gens Test");
+
+	let (proof, commitments) = RangeProof::prove_multiple_with_rng(
+		&bp_gens,
+		&asset_a,
+		&mut transcript.clone(),
+		&values,
+		&blindings,
+		32,
+		&mut test_rng,
+	)
+	.unwrap();
+	assert_eq!(
+		commitments[0],
+		asset_a.commit(Scalar::from(values[0]), blindings[0]).compress()
+	);
+
+	// The proof only verifies under the label it was made with.
+	let verify =
+		|pc_gens: &PedersenGens| proof.verify_multiple(&bp_gens, pc_gens, &mut transcript.clone(), &commitments, 32);
+	assert!(verify(&asset_a).is_ok());
+	assert!(verify(&asset_b).is_err());
+	assert!(verify(&PedersenGens::default()).is_err());
+}
+
+#[test]
+fn seeded_bulletproof_gens_do_not_verify_under_other_seeds() {
+	let pc_gens = PedersenGens::default();
+	let consortium = BulletproofGens::new_with_seed(32, 1, b"consortium protocol v1");
+	let other = BulletproofGens::from_seed(b"other protocol", 32, 1);
+	let default = BulletproofGens::new(32, 1);
+	assert_ne!(consortium.seed_id(), other.seed_id());
+	assert_ne!(consortium.seed_id(), default.seed_id());
+
+	let mut test_rng = ChaChaRng::from_seed([60u8; 32]);
+	let blinding = Scalar::random(&mut test_rng);
+	let transcript = Transcript::new(b"Seeded Gens Test");
+	let (proof, com) = RangeProof::prove_single_with_rng(
+		&consortium,
+		&pc_gens,
+		&mut transcript.clone(),
+		1037,
+		&blinding,
+		32,
+		&mut test_rng,
+	)
+	.unwrap();
+
+	let verify = |bp_gens: &BulletproofGens| proof.verify_single(bp_gens, &pc_gens, &mut transcript.clone(), &com, 32);
+	assert!(verify(&consortium).is_ok());
+	assert!(verify(&other).is_err());
+	assert!(verify(&default).is_err());
+}
+
+#[test]
+fn verify_with_counting_backend() {
+	use curve25519_dalek::ristretto::RistrettoPoint;
+	use std::cell::Cell;
+	use webb_bulletproofs::{DefaultMsmBackend, MsmBackend};
+
+	struct CountingBackend(Cell<usize>);
+
+	impl MsmBackend for CountingBackend {
+		fn vartime_msm(&self, scalars: &[Scalar], points: &[RistrettoPoint]) -> RistrettoPoint {
+			self.0.set(self.0.get() + 1);
+			DefaultMsmBackend.vartime_msm(scalars, points)
+		}
+	}
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64, 2);
+	let mut test_rng = ChaChaRng::from_seed([24u8; 32]);
+	let blindings = [Scalar::random(&mut test_rng), Scalar::random(&mut test_rng)];
+
+	let mut transcript = Transcript::new(b"Backend Test");
+	let (proof, commitments) =
+		RangeProof::prove_multiple(&bp_gens, &pc_gens, &mut transcript, &[3, 4], &blindings, 16).unwrap();
+
+	let backend = CountingBackend(Cell::new(0));
+	let mut transcript = Transcript::new(b"Backend Test");
+	assert!(proof
+		.verify_multiple_with_backend(
+			&bp_gens,
+			&pc_gens,
+			&mut transcript,
+			&commitments,
+			16,
+			&mut test_rng,
+			Some(&backend)
+		)
+		.is_ok());
+	assert_eq!(backend.0.get(), 1);
+
+	let mut transcript = Transcript::new(b"Backend Test");
+	assert!(proof
+		.verify_single_with_backend(
+			&bp_gens,
+			&pc_gens,
+			&mut transcript,
+			&commitments[0],
+			16,
+			&mut test_rng,
+			Some(&backend)
+		)
+		.is_err());
+}
+
+#[test]
+fn prove_with_progress_and_cancellation() {
+	use std::cell::Cell;
+	use webb_bulletproofs::{CancellationToken, Milestone, ProgressSink, ProofError};
+
+	struct CancelAfter<'a> {
+		remaining: Cell<usize>,
+		token: &'a CancellationToken,
+	}
+
+	impl<'a> ProgressSink for CancelAfter<'a> {
+		fn report(&self, _milestone: Milestone, _fraction: f64) {
+			if self.remaining.get() == 0 {
+				self.token.cancel();
+			} else {
+				self.remaining.set(self.remaining.get() - 1);
+			}
+		}
+	}
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64, 2);
+	let mut test_rng = ChaChaRng::from_seed([24u8; 32]);
+	let blindings = [Scalar::random(&mut test_rng), Scalar::random(&mut test_rng)];
+
+	// Cancel after the first milestone, and during the inner-product proof.
+	for remaining in [0, 4].iter() {
+		let token = CancellationToken::new();
+		let sink = CancelAfter {
+			remaining: Cell::new(*remaining),
+			token: &token,
+		};
+		let mut transcript = Transcript::new(b"Progress Test");
+		let result = RangeProof::prove_multiple_with_progress(
+			&bp_gens,
+			&pc_gens,
+			&mut transcript,
+			&[3, 4],
+			&blindings,
+			16,
+			Some(&sink),
+			Some(&token),
+		);
+		assert_eq!(result.err(), Some(ProofError::Cancelled));
+	}
+
+	// An uncancelled token does not interfere with proving.
+	let token = CancellationToken::new();
+	let mut transcript = Transcript::new(b"Progress Test");
+	let (proof, commitments) = RangeProof::prove_multiple_with_progress(
+		&bp_gens,
+		&pc_gens,
+		&mut transcript,
+		&[3, 4],
+		&blindings,
+		16,
+		None,
+		Some(&token),
+	)
+	.unwrap();
+	let mut transcript = Transcript::new(b"Progress Test");
+	assert!(proof
+		.verify_multiple(&bp_gens, &pc_gens, &mut transcript, &commitments, 16)
+		.is_ok());
+}
+
+#[test]
+fn predicted_sizes_match_proofs() {
+	use curve25519_dalek::ristretto::RistrettoPoint;
+	use std::cell::Cell;
+	use webb_bulletproofs::{range_proof_size, range_proof_verification_msm_size, DefaultMsmBackend, MsmBackend};
+
+	struct SizeBackend(Cell<usize>);
+
+	impl MsmBackend for SizeBackend {
+		fn vartime_msm(&self, scalars: &[Scalar], points: &[RistrettoPoint]) -> RistrettoPoint {
+			self.0.set(points.len());
+			DefaultMsmBackend.vartime_msm(scalars, points)
+		}
+	}
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64, 8);
+	let mut test_rng = ChaChaRng::from_seed([24u8; 32]);
+
+	// Bitsizes which are not powers of two are padded separately from the
+	// number of values.
+	for &n in [8, 10, 16, 24, 32, 64].iter() {
+		for &m in [1, 2, 3, 4, 5, 8].iter() {
+			let values: Vec<u64> = (0..m).map(|i| i as u64).collect();
+			let blindings: Vec<Scalar> = (0..m).map(|_| Scalar::random(&mut test_rng)).collect();
+
+			let mut transcript = Transcript::new(b"Size Test");
+			let (proof, commitments) =
+				RangeProof::prove_multiple(&bp_gens, &pc_gens, &mut transcript, &values, &blindings, n).unwrap();
+			assert_eq!(proof.to_bytes().len(), range_proof_size(n, m));
+
+			let backend = SizeBackend(Cell::new(0));
+			let mut transcript = Transcript::new(b"Size Test");
+			assert!(proof
+				.verify_multiple_with_backend(
+					&bp_gens,
+					&pc_gens,
+					&mut transcript,
+					&commitments,
+					n,
+					&mut test_rng,
+					Some(&backend)
+				)
+				.is_ok());
+			assert_eq!(backend.0.get(), range_proof_verification_msm_size(n, m));
+		}
+	}
+}
+
+#[test]
+fn confidential_tx() {
+	use webb_bulletproofs::{ctx::ConfidentialTx, ProofError};
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64, 4);
+	let mut test_rng = ChaChaRng::from_seed([24u8; 32]);
+	let mut blinding = || Scalar::random(&mut test_rng);
+
+	let inputs = [(100u64, blinding()), (50, blinding())];
+	let outputs = [(70u64, blinding()), (5, blinding()), (72, blinding())];
+	let fee = 3;
+
+	let mut transcript = Transcript::new(b"Ctx Test");
+	let (tx, input_commitments, output_commitments) =
+		ConfidentialTx::prove(&bp_gens, &pc_gens, &mut transcript, &inputs, &outputs, fee).unwrap();
+	assert_eq!(output_commitments.len(), 3);
+
+	let tx: ConfidentialTx = bincode::deserialize(&bincode::serialize(&tx).unwrap()).unwrap();
+	let tx = ConfidentialTx::from_bytes(&tx.to_bytes()).unwrap();
+
+	let mut transcript = Transcript::new(b"Ctx Test");
+	assert!(tx
+		.verify(
+			&bp_gens,
+			&pc_gens,
+			&mut transcript,
+			&input_commitments,
+			&output_commitments,
+			fee
+		)
+		.is_ok());
+
+	// Unbalanced transactions can neither be proven nor verified.
+	let mut transcript = Transcript::new(b"Ctx Test");
+	assert_eq!(
+		ConfidentialTx::prove(&bp_gens, &pc_gens, &mut transcript, &inputs, &outputs, fee + 1).err(),
+		Some(ProofError::UnbalancedTransaction)
+	);
+	let mut transcript = Transcript::new(b"Ctx Test");
+	assert!(tx
+		.verify(
+			&bp_gens,
+			&pc_gens,
+			&mut transcript,
+			&input_commitments,
+			&output_commitments,
+			fee + 1
+		)
+		.is_err());
+	let mut transcript = Transcript::new(b"Ctx Test");
+	assert!(tx
+		.verify(
+			&bp_gens,
+			&pc_gens,
+			&mut transcript,
+			&input_commitments,
+			&output_commitments[..2],
+			fee
+		)
+		.is_err());
+
+	// Moving 10 units from the second output to the first one keeps the
+	// balance, but makes the second output negative, that is out of range.
+	let shifted_outputs = [
+		pc_gens.commit(Scalar::from(80u64), outputs[0].1).compress(),
+		pc_gens
+			.commit(Scalar::from(5u64) - Scalar::from(10u64), outputs[1].1)
+			.compress(),
+		output_commitments[2],
+	];
+	let mut transcript = Transcript::new(b"Ctx Test");
+	assert!(tx
+		.verify(
+			&bp_gens,
+			&pc_gens,
+			&mut transcript,
+			&input_commitments,
+			&shifted_outputs,
+			fee
+		)
+		.is_err());
+}
+
+#[test]
+fn undersized_generators_report_required_capacity() {
+	use webb_bulletproofs::ProofError;
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(16, 2);
+	let mut rng = rand::thread_rng();
+
+	let mut transcript = Transcript::new(b"GeneratorsLengthTest");
+	let blinding = Scalar::random(&mut rng);
+	assert_eq!(
+		RangeProof::prove_single(&bp_gens, &pc_gens, &mut transcript, 1234, &blinding, 32).unwrap_err(),
+		ProofError::InvalidGeneratorsLength {
+			required: 32,
+			actual: 16
+		}
+	);
+
+	let mut transcript = Transcript::new(b"GeneratorsLengthTest");
+	let blindings: Vec<Scalar> = (0..4).map(|_| Scalar::random(&mut rng)).collect();
+	assert_eq!(
+		RangeProof::prove_multiple(&bp_gens, &pc_gens, &mut transcript, &[1, 2, 3, 4], &blindings, 16).unwrap_err(),
+		ProofError::InvalidGeneratorsLength { required: 4, actual: 2 }
+	);
+}
+
+#[test]
+fn seeded_rng_gives_identical_proofs() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(32, 1);
+	let blinding = Scalar::from(7u64);
+
+	let prove = |seed: u8| {
+		let mut transcript = Transcript::new(b"Seeded RNG Test");
+		let mut rng = ChaChaRng::from_seed([seed; 32]);
+		RangeProof::prove_single_with_rng(&bp_gens, &pc_gens, &mut transcript, 1037, &blinding, 32, &mut rng).unwrap()
+	};
+
+	let (proof, commitment) = prove(1);
+	let (same, same_commitment) = prove(1);
+	assert_eq!(proof.to_bytes(), same.to_bytes());
+	assert_eq!(commitment, same_commitment);
+
+	let (other, _) = prove(2);
+	assert_ne!(proof.to_bytes(), other.to_bytes());
+
+	let mut transcript = Transcript::new(b"Seeded RNG Test");
+	let mut rng = ChaChaRng::from_seed([3u8; 32]);
+	assert!(proof
+		.verify_single_with_rng(&bp_gens, &pc_gens, &mut transcript, &commitment, 32, &mut rng)
+		.is_ok());
+}
+
+#[test]
+fn batch_verification_finds_bad_proof() {
+	use webb_bulletproofs::ProofError;
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(32, 1);
+	let mut test_rng = ChaChaRng::from_seed([24u8; 32]);
+	let transcript = Transcript::new(b"Batch Verification Test");
+
+	let (proofs, mut commitments): (Vec<_>, Vec<_>) = (0..8u64)
+		.map(|v| {
+			let blinding = Scalar::random(&mut test_rng);
+			RangeProof::prove_single_with_rng(
+				&bp_gens,
+				&pc_gens,
+				&mut transcript.clone(),
+				1000 + v,
+				&blinding,
+				32,
+				&mut test_rng,
+			)
+			.unwrap()
+		})
+		.unzip();
+	let batch = |proofs: &[RangeProof], commitments: &[CompressedRistretto], rng: &mut ChaChaRng| {
+		let pairs: Vec<_> = proofs.iter().zip(commitments.iter()).collect();
+		RangeProof::verify_batch_with_rng(&pairs, &bp_gens, &pc_gens, &transcript, 32, rng)
+	};
+
+	assert_eq!(batch(&proofs, &commitments, &mut test_rng), Ok(()));
+	assert_eq!(batch(&[], &[], &mut test_rng), Ok(()));
+
+	// A valid proof for another commitment is found by the fallback.
+	commitments[5] = pc_gens.commit(Scalar::from(1005u64), Scalar::one()).compress();
+	assert_eq!(
+		batch(&proofs, &commitments, &mut test_rng),
+		Err(ProofError::BatchVerificationError { index: 5 })
+	);
+	assert!(proofs[5]
+		.verify_single(&bp_gens, &pc_gens, &mut transcript.clone(), &commitments[5], 32)
+		.is_err());
+
+	// A proof of the wrong size is rejected before any other check.
+	let mut proofs = proofs;
+	proofs[2] = RangeProof::prove_single(&bp_gens, &pc_gens, &mut transcript.clone(), 7, &Scalar::one(), 16)
+		.unwrap()
+		.0;
+	assert_eq!(
+		batch(&proofs, &commitments, &mut test_rng),
+		Err(ProofError::BatchVerificationError { index: 2 })
+	);
+}
+
+#[test]
+fn aggregated_batch_verification_reports_every_bad_proof() {
+	use webb_bulletproofs::ProofError;
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(16, 4);
+	let mut test_rng = ChaChaRng::from_seed([26u8; 32]);
+	let transcript = Transcript::new(b"Aggregated Batch Verification Test");
+
+	// Eight aggregated proofs of one, two or four values.
+	let (mut proofs, commitments): (Vec<_>, Vec<_>) = (0..8u64)
+		.map(|i| {
+			let m = 1 << (i % 3);
+			let values: Vec<u64> = (0..m).map(|j| 100 * i + j).collect();
+			let blindings: Vec<Scalar> = (0..m).map(|_| Scalar::random(&mut test_rng)).collect();
+			RangeProof::prove_multiple_with_rng(
+				&bp_gens,
+				&pc_gens,
+				&mut transcript.clone(),
+				&values,
+				&blindings,
+				16,
+				&mut test_rng,
+			)
+			.unwrap()
+		})
+		.unzip();
+	let batch = |proofs: &[RangeProof], commitments: &[Vec<CompressedRistretto>], rng: &mut ChaChaRng| {
+		let pairs: Vec<_> = proofs
+			.iter()
+			.zip(commitments.iter())
+			.map(|(proof, commitments)| (proof, &commitments[..]))
+			.collect();
+		RangeProof::verify_multiple_batch_with_rng(&pairs, &bp_gens, &pc_gens, &transcript, 16, rng)
+	};
+
+	assert_eq!(batch(&proofs, &commitments, &mut test_rng), Ok(()));
+	assert_eq!(batch(&[], &[], &mut test_rng), Ok(()));
+
+	// Replacing the third and seventh proofs with proofs of other values
+	// implicates exactly those.
+	for &index in [2, 6].iter() {
+		let m = commitments[index].len();
+		let blindings: Vec<Scalar> = (0..m).map(|_| Scalar::random(&mut test_rng)).collect();
+		proofs[index] = RangeProof::prove_multiple_with_rng(
+			&bp_gens,
+			&pc_gens,
+			&mut transcript.clone(),
+			&vec![7; m],
+			&blindings,
+			16,
+			&mut test_rng,
+		)
+		.unwrap()
+		.0;
+	}
+	assert_eq!(
+		batch(&proofs, &commitments, &mut test_rng),
+		Err(ProofError::BatchStatementsVerificationError { indexes: vec![2, 6] })
+	);
+	for (index, (proof, commitments)) in proofs.iter().zip(commitments.iter()).enumerate() {
+		let result =
+			proof.verify_multiple_with_rng(&bp_gens, &pc_gens, &mut transcript.clone(), commitments, 16, &mut test_rng);
+		assert_eq!(result.is_err(), index == 2 || index == 6);
+	}
+}
+
+#[test]
+fn verification_terms_evaluate_like_verify() {
+	use curve25519_dalek::{
+		ristretto::RistrettoPoint,
+		traits::{Identity, IsIdentity},
+	};
+	use webb_bulletproofs::{ProofError, TermPoint, VerificationTerms};
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(16, 4);
+	let mut test_rng = ChaChaRng::from_seed([27u8; 32]);
+	let transcript = Transcript::new(b"Verification Terms Test");
+
+	let values = [3u64, 14, 15];
+	let blindings: Vec<Scalar> = values.iter().map(|_| Scalar::random(&mut test_rng)).collect();
+	let (proof, commitments) = RangeProof::prove_multiple_with_rng(
+		&bp_gens,
+		&pc_gens,
+		&mut transcript.clone(),
+		&values,
+		&blindings,
+		16,
+		&mut test_rng,
+	)
+	.unwrap();
+
+	// Evaluates the terms one by one, resolving the generators by hand.
+	let evaluate = |terms: &VerificationTerms| -> RistrettoPoint {
+		terms
+			.terms()
+			.map(|(scalar, point)| {
+				scalar
+					* match point {
+						TermPoint::B => pc_gens.B,
+						TermPoint::BBlinding => pc_gens.B_blinding,
+						TermPoint::G { share, index } => bp_gens.share(share).G_slice(16)[index],
+						TermPoint::H { share, index } => bp_gens.share(share).H_slice(16)[index],
+						point => terms.proof_point(point).unwrap(),
+					}
+			})
+			.sum()
+	};
+	let terms = |commitments: &[CompressedRistretto]| {
+		proof.verification_terms(&bp_gens, &mut transcript.clone(), commitments, 16)
+	};
+
+	let valid = terms(&commitments).unwrap();
+	assert!(evaluate(&valid).is_identity());
+	assert_eq!(valid.verify(&pc_gens, &bp_gens), Ok(()));
+	assert_eq!(
+		valid.proof_point(TermPoint::Commitment(1)).map(|point| point.compress()),
+		Some(commitments[1])
+	);
+	// The commitments are padded to four with the identity.
+	assert_eq!(valid.proof_point(TermPoint::Commitment(3)), Some(RistrettoPoint::identity()));
+	assert_eq!(valid.proof_point(TermPoint::Commitment(4)), None);
+	assert!(valid.proof_point(TermPoint::Proof(b"T_2")).is_some());
+	assert!(valid.proof_point(TermPoint::L(5)).is_some());
+	assert_eq!(valid.proof_point(TermPoint::L(6)), None);
+	assert_eq!(
+		valid.verify(&pc_gens, &BulletproofGens::new(8, 4)),
+		Err(ProofError::InvalidGeneratorsLength {
+			required: 16,
+			actual: 8
+		})
+	);
+
+	// The terms of the proof against other commitments reject, as the
+	// verifier does.
+	let mut other_commitments = commitments.clone();
+	other_commitments.swap(0, 2);
+	let invalid = terms(&other_commitments).unwrap();
+	assert!(!evaluate(&invalid).is_identity());
+	assert_eq!(invalid.verify(&pc_gens, &bp_gens), Err(ProofError::VerificationError));
+	assert_eq!(
+		proof.verify_multiple_with_rng(
+			&bp_gens,
+			&pc_gens,
+			&mut transcript.clone(),
+			&other_commitments,
+			16,
+			&mut test_rng
+		),
+		Err(ProofError::VerificationError)
+	);
+
+	// Malformed proofs are rejected before any term is computed.
+	assert_eq!(terms(&commitments[..2]).err(), Some(ProofError::FormatError));
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn parallel_batch_verification_matches_serial() {
+	use webb_bulletproofs::ProofError;
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(32, 1);
+	let mut test_rng = ChaChaRng::from_seed([25u8; 32]);
+	let transcript = Transcript::new(b"Parallel Batch Verification Test");
+
+	let (mut proofs, mut commitments): (Vec<_>, Vec<_>) = (0..16u64)
+		.map(|v| {
+			let blinding = Scalar::random(&mut test_rng);
+			RangeProof::prove_single_with_rng(
+				&bp_gens,
+				&pc_gens,
+				&mut transcript.clone(),
+				1000 + v,
+				&blinding,
+				32,
+				&mut test_rng,
+			)
+			.unwrap()
+		})
+		.unzip();
+	let serial = |proofs: &[RangeProof], commitments: &[CompressedRistretto], rng: &mut ChaChaRng| {
+		let pairs: Vec<_> = proofs.iter().zip(commitments.iter()).collect();
+		RangeProof::verify_batch_with_rng(&pairs, &bp_gens, &pc_gens, &transcript, 32, rng)
+	};
+	let parallel = |proofs: &[RangeProof], commitments: &[CompressedRistretto], parallelism: usize| {
+		let pairs: Vec<_> = proofs.iter().zip(commitments.iter()).collect();
+		RangeProof::verify_batch_parallel(&pairs, &bp_gens, &pc_gens, &transcript, 32, parallelism)
+	};
+
+	// Partitions of one proof, of uneven sizes, and more partitions than
+	// proofs.
+	let parallelisms = [0, 1, 3, 16, 40];
+	assert!(serial(&proofs, &commitments, &mut test_rng).is_ok());
+	for &parallelism in parallelisms.iter() {
+		assert_eq!(parallel(&proofs, &commitments, parallelism), Ok(()));
+		assert_eq!(parallel(&[], &[], parallelism), Ok(()));
+	}
+
+	// Proofs for other commitments, and a proof of the wrong size, are all
+	// reported, whichever partitions they fall into.
+	commitments[2] = pc_gens.commit(Scalar::from(1002u64), Scalar::one()).compress();
+	commitments[13] = pc_gens.commit(Scalar::from(1013u64), Scalar::one()).compress();
+	proofs[7] = RangeProof::prove_single(&bp_gens, &pc_gens, &mut transcript.clone(), 7, &Scalar::one(), 16)
+		.unwrap()
+		.0;
+	assert!(serial(&proofs, &commitments, &mut test_rng).is_err());
+	for &parallelism in parallelisms.iter() {
+		assert_eq!(
+			parallel(&proofs, &commitments, parallelism),
+			Err(ProofError::BatchStatementsVerificationError {
+				indexes: vec![2, 7, 13]
+			})
+		);
+	}
+
+	// The generators are checked before any proof.
+	assert_eq!(
+		RangeProof::verify_batch_parallel(&[], &bp_gens, &pc_gens, &transcript, 64, 4),
+		Err(ProofError::InvalidGeneratorsLength {
+			required: 64,
+			actual: 32
+		})
+	);
+}
+
+#[test]
+fn mixed_bit_sizes_round_trip() {
+	use webb_bulletproofs::ProofError;
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(128, 1);
+	let mut test_rng = ChaChaRng::from_seed([24u8; 32]);
+
+	let prove = |values: &[(u64, Scalar, usize)], rng: &mut ChaChaRng| {
+		RangeProof::prove_multiple_mixed_with_rng(&bp_gens, &pc_gens, &mut Transcript::new(b"MixedTest"), values, rng)
+	};
+	let verify = |proof: &RangeProof, commitments: &[CompressedRistretto], bit_sizes: &[usize], rng: &mut ChaChaRng| {
+		proof.verify_multiple_mixed_with_rng(
+			&bp_gens,
+			&pc_gens,
+			&mut Transcript::new(b"MixedTest"),
+			commitments,
+			bit_sizes,
+			rng,
+		)
+	};
+
+	let values = [
+		(u32::MAX as u64, Scalar::random(&mut test_rng), 32),
+		(u64::MAX, Scalar::random(&mut test_rng), 64),
+	];
+	let (proof, commitments) = prove(&values, &mut test_rng).unwrap();
+	assert_eq!(verify(&proof, &commitments, &[32, 64], &mut test_rng), Ok(()));
+	assert!(verify(&proof, &commitments, &[64, 32], &mut test_rng).is_err());
+	assert!(verify(&proof, &commitments, &[32, 32], &mut test_rng).is_err());
+	assert!(verify(&proof, &commitments[..1], &[32, 64], &mut test_rng).is_err());
+
+	// A total of 56 bits is padded to 64.
+	let values = [
+		(200, Scalar::random(&mut test_rng), 8),
+		(60000, Scalar::random(&mut test_rng), 16),
+		(1 << 31, Scalar::random(&mut test_rng), 32),
+	];
+	let (proof, commitments) = prove(&values, &mut test_rng).unwrap();
+	assert_eq!(proof.to_bytes().len(), webb_bulletproofs::range_proof_size(64, 1));
+	assert_eq!(verify(&proof, &commitments, &[8, 16, 32], &mut test_rng), Ok(()));
+
+	// A value which does not fit in its bitsize gives an invalid proof.
+	let values = [
+		(1 << 32, Scalar::random(&mut test_rng), 32),
+		(5, Scalar::random(&mut test_rng), 64),
+	];
+	let (proof, commitments) = prove(&values, &mut test_rng).unwrap();
+	assert!(verify(&proof, &commitments, &[32, 64], &mut test_rng).is_err());
+
+	assert_eq!(
+		prove(&[(5, Scalar::one(), 65)], &mut test_rng).unwrap_err(),
+		ProofError::InvalidBitsize
+	);
+	assert_eq!(
+		prove(
+			&[(5, Scalar::one(), 64), (5, Scalar::one(), 64), (5, Scalar::one(), 8)],
+			&mut test_rng
+		)
+		.unwrap_err(),
+		ProofError::InvalidGeneratorsLength {
+			required: 256,
+			actual: 128
+		}
+	);
+}
+
+#[test]
+fn arbitrary_bit_sizes() {
+	use webb_bulletproofs::{
+		range_proof_mpc::{dealer::Dealer, party::Party, MPCError},
+		ProofError,
+	};
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64, 2);
+	let mut test_rng = ChaChaRng::from_seed([24u8; 32]);
+	let transcript = Transcript::new(b"Arbitrary Bitsize Test");
+
+	for &n in [1, 13, 52, 63].iter() {
+		let max = (1u64 << n) - 1;
+		for &(v, valid) in [(0, true), (max, true), (max + 1, false)].iter() {
+			let blinding = Scalar::random(&mut test_rng);
+			let (proof, commitment) = RangeProof::prove_single_with_rng(
+				&bp_gens,
+				&pc_gens,
+				&mut transcript.clone(),
+				v,
+				&blinding,
+				n,
+				&mut test_rng,
+			)
+			.unwrap();
+			assert_eq!(proof.n_from_proof(1), n.next_power_of_two());
+
+			let result = proof.verify_single_with_rng(
+				&bp_gens,
+				&pc_gens,
+				&mut transcript.clone(),
+				&commitment,
+				n,
+				&mut test_rng,
+			);
+			assert_eq!(result.is_ok(), valid, "n = {}, v = {}", n, v);
+			if valid && !n.is_power_of_two() {
+				// The bitsize is bound to the proof, not only its padded size.
+				assert!(proof
+					.verify_single_with_rng(
+						&bp_gens,
+						&pc_gens,
+						&mut transcript.clone(),
+						&commitment,
+						n.next_power_of_two(),
+						&mut test_rng
+					)
+					.is_err());
+			}
+		}
+	}
+
+	// Aggregated proofs, with the dealer verifying the proof shares and
+	// blaming only the party whose value is out of range.
+	for &(values, bad_party) in [([(1u64 << 13) - 1, 1000], None), ([1000, 1u64 << 13], Some(1))].iter() {
+		let blindings: Vec<Scalar> = (0..2).map(|_| Scalar::random(&mut test_rng)).collect();
+		let mut prover_transcript = transcript.clone();
+		let dealer = Dealer::new(&bp_gens, &pc_gens, &mut prover_transcript, 13, 2).unwrap();
+		let (parties, bit_commitments): (Vec<_>, Vec<_>) = values
+			.iter()
+			.zip(blindings.iter())
+			.enumerate()
+			.map(|(j, (&v, &v_blinding))| {
+				Party::new(&bp_gens, &pc_gens, v, v_blinding, 13)
+					.unwrap()
+					.assign_position_with_rng(j, &mut test_rng)
+					.unwrap()
+			})
+			.unzip();
+		let commitments: Vec<_> = values
+			.iter()
+			.zip(blindings.iter())
+			.map(|(&v, &v_blinding)| pc_gens.commit(Scalar::from(v), v_blinding).compress())
+			.collect();
+		let (dealer, bit_challenge) = dealer.receive_bit_commitments(bit_commitments).unwrap();
+		let (parties, poly_commitments): (Vec<_>, Vec<_>) = parties
+			.into_iter()
+			.map(|p| p.apply_challenge_with_rng(&bit_challenge, &mut test_rng))
+			.unzip();
+		let (dealer, poly_challenge) = dealer.receive_poly_commitments(poly_commitments).unwrap();
+		let shares: Vec<_> = parties
+			.into_iter()
+			.map(|p| p.apply_challenge(&poly_challenge).unwrap())
+			.collect();
+		match (dealer.receive_shares_with_rng(&shares, &mut test_rng), bad_party) {
+			(Ok(proof), None) => assert!(proof
+				.verify_multiple_with_rng(
+					&bp_gens,
+					&pc_gens,
+					&mut transcript.clone(),
+					&commitments,
+					13,
+					&mut test_rng
+				)
+				.is_ok()),
+			(Err(MPCError::MalformedProofShares { bad_shares }), Some(j)) => assert_eq!(bad_shares, vec![j]),
+			(result, _) => panic!("unexpected result {:?}", result.map(|_| ())),
+		}
+	}
+
+	for &n in [0, 65].iter() {
+		assert_eq!(
+			RangeProof::prove_single_with_rng(
+				&bp_gens,
+				&pc_gens,
+				&mut transcript.clone(),
+				0,
+				&Scalar::one(),
+				n,
+				&mut test_rng
+			)
+			.unwrap_err(),
+			ProofError::InvalidBitsize
+		);
+	}
+}
+
+#[test]
+fn minimum_value_offset() {
+	use webb_bulletproofs::ProofError;
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(8, 1);
+	let mut test_rng = ChaChaRng::from_seed([24u8; 32]);
+	let transcript = Transcript::new(b"Minimum Value Test");
+	let blinding = Scalar::random(&mut test_rng);
+
+	let (proof, commitment) = RangeProof::prove_single_with_min_and_rng(
+		&bp_gens,
+		&pc_gens,
+		&mut transcript.clone(),
+		150,
+		&blinding,
+		8,
+		100,
+		&mut test_rng,
+	)
+	.unwrap();
+	assert_eq!(commitment, pc_gens.commit(Scalar::from(150u64), blinding).compress());
+
+	let verify = |min: u64, rng: &mut ChaChaRng| {
+		proof.verify_single_with_min_and_rng(&bp_gens, &pc_gens, &mut transcript.clone(), &commitment, 8, min, rng)
+	};
+	assert_eq!(verify(100, &mut test_rng), Ok(()));
+	assert!(verify(200, &mut test_rng).is_err());
+	assert!(verify(0, &mut test_rng).is_err());
+
+	assert_eq!(
+		RangeProof::prove_single_with_min_and_rng(
+			&bp_gens,
+			&pc_gens,
+			&mut transcript.clone(),
+			150,
+			&blinding,
+			8,
+			200,
+			&mut test_rng
+		)
+		.unwrap_err(),
+		ProofError::ValueBelowMinimum
+	);
+
+	// A value beyond the top of the range gives a proof which does not verify.
+	let (proof, commitment) = RangeProof::prove_single_with_min_and_rng(
+		&bp_gens,
+		&pc_gens,
+		&mut transcript.clone(),
+		356,
+		&blinding,
+		8,
+		100,
+		&mut test_rng,
+	)
+	.unwrap();
+	assert!(proof
+		.verify_single_with_min_and_rng(
+			&bp_gens,
+			&pc_gens,
+			&mut transcript.clone(),
+			&commitment,
+			8,
+			100,
+			&mut test_rng
+		)
+		.is_err());
+}
+
+#[test]
+fn rewindable_proofs() {
+	use webb_bulletproofs::ProofError;
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64, 1);
+	let mut test_rng = ChaChaRng::from_seed([24u8; 32]);
+	let transcript = Transcript::new(b"Rewind Test");
+
+	let rewind_key = [7u8; 32];
+	let message = *b"a 20-byte memo field";
+	let blinding = Scalar::random(&mut test_rng);
+
+	let prove = |v: u64, n: usize| {
+		RangeProof::prove_single_rewindable(
+			&bp_gens,
+			&pc_gens,
+			&mut transcript.clone(),
+			v,
+			&blinding,
+			n,
+			&rewind_key,
+			&message,
+		)
+		.unwrap()
+	};
+
+	for &(v, n) in [(0u64, 8), (1037578891, 32), (u64::MAX, 64), (1 << 51, 52)].iter() {
+		let (proof, commitment) = prove(v, n);
+
+		// The proof verifies with the ordinary verifier.
+		assert!(proof
+			.verify_single_with_rng(
+				&bp_gens,
+				&pc_gens,
+				&mut transcript.clone(),
+				&commitment,
+				n,
+				&mut test_rng
+			)
+			.is_ok());
+
+		assert_eq!(
+			proof.rewind_single(&pc_gens, &mut transcript.clone(), &commitment, n, &rewind_key),
+			Ok((v, blinding, message))
+		);
+	}
+
+	let (proof, commitment) = prove(1037578891, 32);
+
+	// The proof is determined by the key, the value and the transcript.
+	assert_eq!(prove(1037578891, 32).0.to_bytes(), proof.to_bytes());
+
+	// Rewinding with the wrong key or commitment fails cleanly.
+	assert_eq!(
+		proof.rewind_single(&pc_gens, &mut transcript.clone(), &commitment, 32, &[8u8; 32]),
+		Err(ProofError::RewindError)
+	);
+	let other = pc_gens.commit(Scalar::from(5u64), blinding).compress();
+	assert_eq!(
+		proof.rewind_single(&pc_gens, &mut transcript.clone(), &other, 32, &rewind_key),
+		Err(ProofError::RewindError)
+	);
+	assert_eq!(
+		proof.rewind_single(&pc_gens, &mut Transcript::new(b"Other"), &commitment, 32, &rewind_key),
+		Err(ProofError::RewindError)
+	);
+
+	// An ordinary proof cannot be rewound.
+	let (proof, commitment) = RangeProof::prove_single_with_rng(
+		&bp_gens,
+		&pc_gens,
+		&mut transcript.clone(),
+		1037578891,
+		&blinding,
+		32,
+		&mut test_rng,
+	)
+	.unwrap();
+	assert_eq!(
+		proof.rewind_single(&pc_gens, &mut transcript.clone(), &commitment, 32, &rewind_key),
+		Err(ProofError::RewindError)
+	);
+}
+
+#[test]
+fn aggregation_sizes_are_padded() {
+	use curve25519_dalek::traits::Identity;
+	use webb_bulletproofs::{
+		range_proof_mpc::{dealer::Dealer, party::Party},
+		range_proof_size, ProofError,
+	};
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(32, 8);
+	let mut test_rng = ChaChaRng::from_seed([24u8; 32]);
+	let transcript = Transcript::new(b"Padded Aggregation Test");
+
+	for &m in [3, 5].iter() {
+		let values: Vec<u64> = (0..m).map(|i| 1000 * i as u64).collect();
+		let blindings: Vec<Scalar> = (0..m).map(|_| Scalar::random(&mut test_rng)).collect();
+		let (proof, commitments) = RangeProof::prove_multiple_with_rng(
+			&bp_gens,
+			&pc_gens,
+			&mut transcript.clone(),
+			&values,
+			&blindings,
+			32,
+			&mut test_rng,
+		)
+		.unwrap();
+		assert_eq!(commitments.len(), m);
+		assert_eq!(proof.to_bytes().len(), range_proof_size(32, m.next_power_of_two()));
+		assert_eq!(proof.n_from_proof(m), 32);
+
+		let verify = |commitments: &[CompressedRistretto], rng: &mut ChaChaRng| {
+			proof.verify_multiple_with_rng(&bp_gens, &pc_gens, &mut transcript.clone(), commitments, 32, rng)
+		};
+		assert_eq!(verify(&commitments, &mut test_rng), Ok(()));
+		assert!(verify(&commitments[..m - 1], &mut test_rng).is_err());
+
+		// The padding is not interchangeable with explicit commitments to zero.
+		let mut padded = commitments.clone();
+		padded.resize(m.next_power_of_two(), CompressedRistretto::identity());
+		assert!(verify(&padded, &mut test_rng).is_err());
+	}
+
+	// The dealer pads the parties, and verifies the aggregated proof.
+	let values = [1u64, 2, 3];
+	let blindings: Vec<Scalar> = (0..3).map(|_| Scalar::random(&mut test_rng)).collect();
+	let mut prover_transcript = transcript.clone();
+	let dealer = Dealer::new(&bp_gens, &pc_gens, &mut prover_transcript, 32, 3).unwrap();
+	let (parties, bit_commitments): (Vec<_>, Vec<_>) = values
+		.iter()
+		.zip(blindings.iter())
+		.enumerate()
+		.map(|(j, (&v, &v_blinding))| {
+			Party::new(&bp_gens, &pc_gens, v, v_blinding, 32)
+				.unwrap()
+				.assign_position_with_rng(j, &mut test_rng)
+				.unwrap()
+		})
+		.unzip();
+	let (dealer, bit_challenge) = dealer.receive_bit_commitments(bit_commitments).unwrap();
+	let (parties, poly_commitments): (Vec<_>, Vec<_>) = parties
+		.into_iter()
+		.map(|p| p.apply_challenge_with_rng(&bit_challenge, &mut test_rng))
+		.unzip();
+	let (dealer, poly_challenge) = dealer.receive_poly_commitments(poly_commitments).unwrap();
+	let shares: Vec<_> = parties
+		.into_iter()
+		.map(|p| p.apply_challenge(&poly_challenge).unwrap())
+		.collect();
+	assert!(dealer.receive_shares_with_rng(&shares, &mut test_rng).is_ok());
+
+	// The party capacity must cover the padded aggregation size.
+	let result = RangeProof::prove_multiple_with_rng(
+		&bp_gens,
+		&pc_gens,
+		&mut transcript.clone(),
+		&[1, 2, 3, 4, 5, 6, 7, 8, 9],
+		&[Scalar::one(); 9],
+		32,
+		&mut test_rng,
+	);
+	assert_eq!(result.unwrap_err(), ProofError::InvalidGeneratorsLength {
+		required: 16,
+		actual: 8
+	});
+}
+
+#[test]
+fn scalar_values_are_bound_checked() {
+	use webb_bulletproofs::ProofError;
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64, 1);
+	let transcript = Transcript::new(b"Scalar Value Test");
+	let blinding = Scalar::from(7u64);
+
+	let prove = |v: Scalar, n: usize, seed: u8| {
+		RangeProof::prove_single_scalar_with_rng(
+			&bp_gens,
+			&pc_gens,
+			&mut transcript.clone(),
+			&v,
+			&blinding,
+			n,
+			&mut ChaChaRng::from_seed([seed; 32]),
+		)
+	};
+
+	for &n in [8, 13, 32, 64].iter() {
+		let max = (1u128 << n) - 1;
+		let (proof, commitment) = prove(Scalar::from(max as u64), n, 1).unwrap();
+
+		// The proof is the same as for the value given as a u64.
+		let (expected, expected_commitment) = RangeProof::prove_single_with_rng(
+			&bp_gens,
+			&pc_gens,
+			&mut transcript.clone(),
+			max as u64,
+			&blinding,
+			n,
+			&mut ChaChaRng::from_seed([1; 32]),
+		)
+		.unwrap();
+		assert_eq!(proof.to_bytes(), expected.to_bytes());
+		assert_eq!(commitment, expected_commitment);
+		assert!(proof
+			.verify_single(&bp_gens, &pc_gens, &mut transcript.clone(), &commitment, n)
+			.is_ok());
+
+		assert_eq!(
+			prove(Scalar::from(max + 1), n, 1).unwrap_err(),
+			ProofError::ValueOutOfRange
+		);
+	}
+
+	let large = Scalar::from(u64::MAX) * Scalar::from(3u64);
+	assert_eq!(prove(large, 64, 1).unwrap_err(), ProofError::ValueOutOfRange);
+	assert_eq!(prove(-Scalar::one(), 64, 1).unwrap_err(), ProofError::ValueOutOfRange);
+	assert_eq!(prove(Scalar::one(), 65, 1).unwrap_err(), ProofError::InvalidBitsize);
+}
+
+#[test]
+fn mpc_messages_roundtrip_through_serde() {
+	use serde::{de::DeserializeOwned, Serialize};
+	use webb_bulletproofs::range_proof_mpc::{
+		dealer::Dealer,
+		messages::{BitCommitment, PolyChallenge},
+		party::Party,
+	};
+
+	// Every message crosses a process boundary as bytes.
+	fn send<T: Serialize + DeserializeOwned>(message: &T) -> T {
+		bincode::deserialize(&bincode::serialize(message).unwrap()).unwrap()
+	}
+
+	let m = 3;
+	let n = 32;
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(n, 4);
+	let mut test_rng = ChaChaRng::from_seed([25u8; 32]);
+	let transcript = Transcript::new(b"MPC Serde Test");
+
+	let values: Vec<u64> = (0..m).map(|j| 1000 * j as u64).collect();
+	let blindings: Vec<Scalar> = (0..m).map(|_| Scalar::random(&mut test_rng)).collect();
+
+	let mut prover_transcript = transcript.clone();
+	let dealer = Dealer::new(&bp_gens, &pc_gens, &mut prover_transcript, n, m).unwrap();
+	let (parties, bit_commitments): (Vec<_>, Vec<_>) = values
+		.iter()
+		.zip(blindings.iter())
+		.enumerate()
+		.map(|(j, (&v, &v_blinding))| {
+			let (party, bit_commitment) = Party::new(&bp_gens, &pc_gens, v, v_blinding, n)
+				.unwrap()
+				.assign_position_with_rng(j, &mut test_rng)
+				.unwrap();
+			(party, send(&bit_commitment))
+		})
+		.unzip();
+	let (dealer, bit_challenge) = dealer.receive_bit_commitments(bit_commitments).unwrap();
+
+	let bit_challenge = send(&bit_challenge);
+	let (parties, poly_commitments): (Vec<_>, Vec<_>) = parties
+		.into_iter()
+		.map(|p| {
+			let (party, poly_commitment) = p.apply_challenge_with_rng(&bit_challenge, &mut test_rng);
+			(party, send(&poly_commitment))
+		})
+		.unzip();
+	let (dealer, poly_challenge) = dealer.receive_poly_commitments(poly_commitments).unwrap();
+
+	let poly_challenge = send(&poly_challenge);
+	let shares: Vec<_> = parties
+		.into_iter()
+		.map(|p| send(&p.apply_challenge(&poly_challenge).unwrap()))
+		.collect();
+	let proof = dealer.receive_shares_with_rng(&shares, &mut test_rng).unwrap();
+
+	let commitments: Vec<_> = values
+		.iter()
+		.zip(blindings.iter())
+		.map(|(&v, &v_blinding)| pc_gens.commit(Scalar::from(v), v_blinding).compress())
+		.collect();
+	assert!(proof
+		.verify_multiple_with_rng(
+			&bp_gens,
+			&pc_gens,
+			&mut transcript.clone(),
+			&commitments,
+			n,
+			&mut test_rng
+		)
+		.is_ok());
+
+	// A value commitment which does not decompress is rejected.
+	let (_, bit_commitment) = Party::new(&bp_gens, &pc_gens, 0, Scalar::one(), n)
+		.unwrap()
+		.assign_position_with_rng(0, &mut test_rng)
+		.unwrap();
+	let mut bytes = bincode::serialize(&bit_commitment).unwrap();
+	bytes[..32].copy_from_slice(&[0xff; 32]);
+	assert!(bincode::deserialize::<BitCommitment>(&bytes).is_err());
+
+	// A challenge which is not canonically encoded is rejected.
+	let mut bytes = bincode::serialize(&poly_challenge).unwrap();
+	bytes.copy_from_slice(&[0xff; 32]);
+	assert!(bincode::deserialize::<PolyChallenge>(&bytes).is_err());
+}
+
+#[test]
+fn streaming_batch_verification() {
+	use webb_bulletproofs::{ProofError, RangeProofVerificationBatcher};
+
+	let n = 8;
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(n, 1);
+	let mut test_rng = ChaChaRng::from_seed([26u8; 32]);
+	let transcript = Transcript::new(b"Streaming Batch Test");
+
+	// The batcher verifies each proof it is given, so 1000 proofs are
+	// pushed by repeating 125 distinct ones, which are faster to create.
+	let distinct_proofs: Vec<(RangeProof, CompressedRistretto)> = (0..125u64)
+		.map(|v| {
+			RangeProof::prove_single_with_rng(
+				&bp_gens,
+				&pc_gens,
+				&mut transcript.clone(),
+				v,
+				&Scalar::random(&mut test_rng),
+				n,
+				&mut test_rng,
+			)
+			.unwrap()
+		})
+		.collect();
+	let proofs: Vec<&(RangeProof, CompressedRistretto)> = distinct_proofs.iter().cycle().take(1000).collect();
+
+	let mut batcher =
+		RangeProofVerificationBatcher::new_with_rng(&bp_gens, &pc_gens, &transcript, n, 64, &mut test_rng).unwrap();
+	for (proof, commitment) in proofs.iter().copied() {
+		batcher.push(proof, commitment).unwrap();
+	}
+	batcher.flush().unwrap();
+
+	// A proof for the wrong commitment fails its chunk, and only its chunk.
+	let mut rng = ChaChaRng::from_seed([27u8; 32]);
+	let mut batcher =
+		RangeProofVerificationBatcher::new_with_rng(&bp_gens, &pc_gens, &transcript, n, 64, &mut rng).unwrap();
+	let results: Vec<_> = proofs
+		.iter()
+		.enumerate()
+		.map(|(i, &(proof, commitment))| {
+			let commitment = if i == 700 { &proofs[1].1 } else { commitment };
+			batcher.push(proof, commitment)
+		})
+		.collect();
+	for (i, result) in results.into_iter().enumerate() {
+		if i == 703 {
+			assert_eq!(
+				result,
+				Err(ProofError::BatchChunkVerificationError { start: 640, end: 704 })
+			);
+		} else {
+			assert_eq!(result, Ok(()));
+		}
+	}
+	assert_eq!(batcher.flush(), Ok(()));
+
+	// A proof of the wrong size is rejected without failing its chunk.
+	let (short_proof, short_commitment) = RangeProof::prove_single_with_rng(
+		&bp_gens,
+		&pc_gens,
+		&mut transcript.clone(),
+		0,
+		&Scalar::one(),
+		4,
+		&mut rng,
+	)
+	.unwrap();
+	let mut batcher = RangeProofVerificationBatcher::new(&bp_gens, &pc_gens, &transcript, n, 64).unwrap();
+	batcher.push(&proofs[0].0, &proofs[0].1).unwrap();
+	assert_eq!(
+		batcher.push(&short_proof, &short_commitment),
+		Err(ProofError::BatchVerificationError { index: 1 })
+	);
+	batcher.push(&proofs[1].0, &proofs[1].1).unwrap();
+	assert_eq!(batcher.flush(), Ok(()));
+}
+
+#[test]
+fn self_describing_encoding() {
+	use webb_bulletproofs::ProofError;
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64, 4);
+	let mut test_rng = ChaChaRng::from_seed([28u8; 32]);
+	let transcript = Transcript::new(b"Self-Describing Encoding Test");
+
+	let values = [1u64, 2, 3];
+	let blindings: Vec<Scalar> = (0..3).map(|_| Scalar::random(&mut test_rng)).collect();
+	let (proof, commitments) = RangeProof::prove_multiple_with_rng(
+		&bp_gens,
+		&pc_gens,
+		&mut transcript.clone(),
+		&values,
+		&blindings,
+		13,
+		&mut test_rng,
+	)
+	.unwrap();
+	assert_eq!((proof.bit_size(), proof.aggregation_size()), (Some(13), Some(3)));
+
+	// Round trip
+	let bytes = proof.to_bytes();
+	assert_eq!(bytes[..6], [1, 13, 3, 0, 0, 0]);
+	let parsed = RangeProof::from_bytes(&bytes).unwrap();
+	assert_eq!((parsed.bit_size(), parsed.aggregation_size()), (Some(13), Some(3)));
+	assert_eq!(parsed.to_bytes(), bytes);
+	assert!(parsed
+		.verify_multiple_with_rng(
+			&bp_gens,
+			&pc_gens,
+			&mut transcript.clone(),
+			&commitments,
+			13,
+			&mut test_rng
+		)
+		.is_ok());
+
+	// Headers which do not match the size of the inner-product proof,
+	// which is that of 16-bit ranges for 4 values, or which are malformed.
+	for header in [
+		[1, 13, 5, 0, 0, 0],
+		[1, 32, 3, 0, 0, 0],
+		[1, 8, 3, 0, 0, 0],
+		[1, 13, 0, 0, 0, 0],
+		[1, 65, 3, 0, 0, 0],
+		[2, 13, 3, 0, 0, 0],
+	]
+	.iter()
+	{
+		let mut bytes = bytes.clone();
+		bytes[..6].copy_from_slice(header);
+		assert_eq!(
+			RangeProof::from_bytes(&bytes).unwrap_err(),
+			ProofError::FormatError,
+			"{:?}",
+			header
+		);
+	}
+	// Other sizes with the same padding are accepted.
+	let mut resized = bytes.clone();
+	resized[1] = 16;
+	resized[2] = 4;
+	assert!(RangeProof::from_bytes(&resized).is_ok());
+	assert!(RangeProof::from_bytes(&bytes[..5]).is_err());
+
+	// Legacy parsing of the encoding without the header
+	let legacy = RangeProof::from_bytes_legacy(&bytes[6..]).unwrap();
+	assert_eq!((legacy.bit_size(), legacy.aggregation_size()), (None, None));
+	assert_eq!(legacy.to_bytes()[..6], [1, 0, 0, 0, 0, 0]);
+	assert_eq!(legacy.to_bytes()[6..], bytes[6..]);
+	assert!(RangeProof::from_bytes(&legacy.to_bytes()).is_ok());
+	assert!(RangeProof::from_bytes_legacy(&bytes).is_err());
+	assert!(legacy
+		.verify_multiple_with_rng(
+			&bp_gens,
+			&pc_gens,
+			&mut transcript.clone(),
+			&commitments,
+			13,
+			&mut test_rng
+		)
+		.is_ok());
+
+	// Proofs of ranges of different bit sizes have no single bitsize.
+	let (mixed, _) = RangeProof::prove_multiple_mixed_with_rng(
+		&bp_gens,
+		&pc_gens,
+		&mut transcript.clone(),
+		&[(1, blindings[0], 8), (2, blindings[1], 32)],
+		&mut test_rng,
+	)
+	.unwrap();
+	assert_eq!((mixed.bit_size(), mixed.aggregation_size()), (None, Some(2)));
+	let parsed = RangeProof::from_bytes(&mixed.to_bytes()).unwrap();
+	assert_eq!((parsed.bit_size(), parsed.aggregation_size()), (None, Some(2)));
+}
+
+#[test]
+fn range_proof_plus() {
+	use webb_bulletproofs::RangeProofPlus;
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64, 4);
+	let mut test_rng = ChaChaRng::from_seed([29u8; 32]);
+	let transcript = Transcript::new(b"Bulletproofs+ Test");
+
+	// A single 64-bit proof, committing exactly as a RangeProof does.
+	let blinding = Scalar::random(&mut test_rng);
+	let (proof, commitment) = RangeProofPlus::prove_single_with_rng(
+		&bp_gens,
+		&pc_gens,
+		&mut transcript.clone(),
+		u64::MAX,
+		&blinding,
+		64,
+		&mut test_rng,
+	)
+	.unwrap();
+	let (_, range_proof_commitment) = RangeProof::prove_single_with_rng(
+		&bp_gens,
+		&pc_gens,
+		&mut transcript.clone(),
+		u64::MAX,
+		&blinding,
+		64,
+		&mut test_rng,
+	)
+	.unwrap();
+	assert_eq!(commitment, range_proof_commitment);
+	assert!(proof
+		.verify_single(&bp_gens, &pc_gens, &mut transcript.clone(), &commitment, 64)
+		.is_ok());
+
+	let bytes = proof.to_bytes();
+	assert_eq!(bytes.len(), 576);
+	assert_eq!(proof.serialized_size(), 576);
+	let parsed = RangeProofPlus::from_bytes(&bytes).unwrap();
+	assert_eq!(parsed.to_bytes(), bytes);
+	assert!(parsed
+		.verify_single(&bp_gens, &pc_gens, &mut transcript.clone(), &commitment, 64)
+		.is_ok());
+	assert!(RangeProofPlus::from_bytes(&bytes[..bytes.len() - 32]).is_err());
+	assert!(RangeProofPlus::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+	// Tampering with any element makes the proof fail to parse or verify.
+	for i in 0..bytes.len() / 32 {
+		let mut tampered = bytes.clone();
+		tampered[32 * i] ^= 1;
+		assert!(
+			RangeProofPlus::from_bytes(&tampered)
+				.and_then(|p| p.verify_single(&bp_gens, &pc_gens, &mut transcript.clone(), &commitment, 64))
+				.is_err(),
+			"element {}",
+			i
+		);
+	}
+
+	// Wrong commitment, wrong bitsize, or wrong transcript
+	let other = pc_gens.commit(Scalar::from(5u64), blinding).compress();
+	assert!(proof
+		.verify_single(&bp_gens, &pc_gens, &mut transcript.clone(), &other, 64)
+		.is_err());
+	assert!(proof
+		.verify_single(&bp_gens, &pc_gens, &mut transcript.clone(), &commitment, 32)
+		.is_err());
+	assert!(proof
+		.verify_single(&bp_gens, &pc_gens, &mut Transcript::new(b"Other"), &commitment, 64)
+		.is_err());
+
+	// An aggregated proof for 3 values of 13 bits
+	let values = [0u64, 1234, (1 << 13) - 1];
+	let blindings: Vec<Scalar> = (0..3).map(|_| Scalar::random(&mut test_rng)).collect();
+	let (proof, commitments) = RangeProofPlus::prove_multiple_with_rng(
+		&bp_gens,
+		&pc_gens,
+		&mut transcript.clone(),
+		&values,
+		&blindings,
+		13,
+		&mut test_rng,
+	)
+	.unwrap();
+	assert_eq!(proof.to_bytes().len(), 6 * 32 + 2 * 6 * 32);
+	assert!(proof
+		.verify_multiple(&bp_gens, &pc_gens, &mut transcript.clone(), &commitments, 13)
+		.is_ok());
+	let mut swapped = commitments.clone();
+	swapped.swap(0, 1);
+	assert!(proof
+		.verify_multiple(&bp_gens, &pc_gens, &mut transcript.clone(), &swapped, 13)
+		.is_err());
+	assert!(proof
+		.verify_multiple(&bp_gens, &pc_gens, &mut transcript.clone(), &commitments[..2], 13)
+		.is_err());
+
+	// A value out of range does not yield a valid proof.
+	let (proof, commitments) = RangeProofPlus::prove_multiple_with_rng(
+		&bp_gens,
+		&pc_gens,
+		&mut transcript.clone(),
+		&[1, 1 << 13, 2],
+		&blindings,
+		13,
+		&mut test_rng,
+	)
+	.unwrap();
+	assert!(proof
+		.verify_multiple(&bp_gens, &pc_gens, &mut transcript.clone(), &commitments, 13)
+		.is_err());
+}
+
+#[test]
+fn aggregated_proving_is_deterministic() {
+	use rand_core::RngCore;
+	use webb_bulletproofs::range_proof_mpc::{dealer::Dealer, party::Party};
+
+	// With the `rayon` feature, `prove_multiple_with_rng` computes the
+	// parties' messages in parallel, and must produce the same proof as the
+	// parties running the protocol one after the other.
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(32, 8);
+	let transcript = Transcript::new(b"Deterministic Aggregation Test");
+
+	for &(n, m) in [(32, 8), (13, 5)].iter() {
+		let mut values_rng = ChaChaRng::from_seed([30u8; 32]);
+		let values: Vec<u64> = (0..m).map(|_| values_rng.next_u64() >> (64 - n)).collect();
+		let blindings: Vec<Scalar> = (0..m).map(|_| Scalar::random(&mut values_rng)).collect();
+
+		let mut rng = ChaChaRng::from_seed([31u8; 32]);
+		let (proof, commitments) = RangeProof::prove_multiple_with_rng(
+			&bp_gens,
+			&pc_gens,
+			&mut transcript.clone(),
+			&values,
+			&blindings,
+			n,
+			&mut rng,
+		)
+		.unwrap();
+
+		// The prover's nonces are drawn from the transcript rekeyed with
+		// the witness.
+		let mut serial_transcript = transcript.clone();
+		let mut rng = values
+			.iter()
+			.zip(blindings.iter())
+			.fold(serial_transcript.build_rng(), |builder, (v, v_blinding)| {
+				builder
+					.rekey_with_witness_bytes(b"v", &v.to_le_bytes())
+					.rekey_with_witness_bytes(b"v_blinding", v_blinding.as_bytes())
+			})
+			.finalize(&mut ChaChaRng::from_seed([31u8; 32]));
+		let dealer = Dealer::new(&bp_gens, &pc_gens, &mut serial_transcript, n, m).unwrap();
+		let mut parties = Vec::new();
+		let mut bit_commitments = Vec::new();
+		for (j, (v, v_blinding)) in values.iter().zip(blindings.iter()).enumerate() {
+			let party = Party::new(&bp_gens, &pc_gens, *v, *v_blinding, n).unwrap();
+			let (party, bit_commitment) = party.assign_position_with_rng(j, &mut rng).unwrap();
+			parties.push(party);
+			bit_commitments.push(bit_commitment);
+		}
+		let (dealer, bit_challenge) = dealer.receive_bit_commitments(bit_commitments).unwrap();
+		let (parties, poly_commitments): (Vec<_>, Vec<_>) = parties
+			.into_iter()
+			.map(|p| p.apply_challenge_with_rng(&bit_challenge, &mut rng))
+			.unzip();
+		let (dealer, poly_challenge) = dealer.receive_poly_commitments(poly_commitments).unwrap();
+		let proof_shares: Vec<_> = parties
+			.into_iter()
+			.map(|p| p.apply_challenge(&poly_challenge).unwrap())
+			.collect();
+		let serial_proof = dealer.receive_trusted_shares(&proof_shares).unwrap();
+
+		assert_eq!(proof.to_bytes(), serial_proof.to_bytes(), "n = {}, m = {}", n, m);
+		assert!(proof
+			.verify_multiple(&bp_gens, &pc_gens, &mut transcript.clone(), &commitments, n)
+			.is_ok());
+	}
+}
+
+#[test]
+fn difference_of_commitments() {
+	use webb_bulletproofs::ProofError;
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64, 1);
+	let mut test_rng = ChaChaRng::from_seed([32u8; 32]);
+	let transcript = Transcript::new(b"Difference Test");
+	let blinding_a = Scalar::random(&mut test_rng);
+	let blinding_b = Scalar::random(&mut test_rng);
+
+	for &(a, b) in [(1000u64, 10u64), (77, 77), (u64::MAX, 0)].iter() {
+		let (proof, c_a, c_b) = RangeProof::prove_difference_with_rng(
+			&bp_gens,
+			&pc_gens,
+			&mut transcript.clone(),
+			a,
+			&blinding_a,
+			b,
+			&blinding_b,
+			64,
+			&mut test_rng,
+		)
+		.unwrap();
+		assert_eq!(c_a, pc_gens.commit(Scalar::from(a), blinding_a).compress());
+		assert_eq!(c_b, pc_gens.commit(Scalar::from(b), blinding_b).compress());
+		assert!(proof
+			.verify_difference(&bp_gens, &pc_gens, &mut transcript.clone(), &c_a, &c_b, 64)
+			.is_ok());
+		if a != b {
+			assert!(proof
+				.verify_difference(&bp_gens, &pc_gens, &mut transcript.clone(), &c_b, &c_a, 64)
+				.is_err());
+		}
+	}
+
+	// The prover rejects a value which is less than the other one.
+	assert_eq!(
+		RangeProof::prove_difference_with_rng(
+			&bp_gens,
+			&pc_gens,
+			&mut transcript.clone(),
+			10,
+			&blinding_a,
+			11,
+			&blinding_b,
+			64,
+			&mut test_rng,
+		)
+		.unwrap_err(),
+		ProofError::ValueBelowMinimum
+	);
+
+	// Proving the wrapped difference of a < b in the integers does not give
+	// a proof for c_a - c_b, which commits to a - b modulo the group order.
+	let (a, b) = (10u64, 11u64);
+	let c_a = pc_gens.commit(Scalar::from(a), blinding_a).compress();
+	let c_b = pc_gens.commit(Scalar::from(b), blinding_b).compress();
+	for &n in [8, 64].iter() {
+		let wrapped = a.wrapping_sub(b) & (u64::MAX >> (64 - n));
+		let (proof, _) = RangeProof::prove_single_with_rng(
+			&bp_gens,
+			&pc_gens,
+			&mut transcript.clone(),
+			wrapped,
+			&(blinding_a - blinding_b),
+			n,
+			&mut test_rng,
+		)
+		.unwrap();
+		assert!(proof
+			.verify_difference(&bp_gens, &pc_gens, &mut transcript.clone(), &c_a, &c_b, n)
+			.is_err());
+	}
+}
+
+#[test]
+fn existing_commitment() {
+	use webb_bulletproofs::ProofError;
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(32, 1);
+	let mut test_rng = ChaChaRng::from_seed([33u8; 32]);
+	let transcript = Transcript::new(b"Existing Commitment Test");
+	let blinding = Scalar::random(&mut test_rng);
+
+	// A commitment published earlier, by some other component.
+	let commitment = pc_gens.commit(Scalar::from(1037u64), blinding).compress();
+
+	let proof = RangeProof::prove_single_existing_with_rng(
+		&bp_gens,
+		&pc_gens,
+		&mut transcript.clone(),
+		1037,
+		&blinding,
+		32,
+		&commitment,
+		&mut test_rng,
+	)
+	.unwrap();
+	assert!(proof
+		.verify_single(&bp_gens, &pc_gens, &mut transcript.clone(), &commitment, 32)
+		.is_ok());
+
+	// A wrong value or blinding factor is caught before proving.
+	let other_blinding = Scalar::random(&mut test_rng);
+	for &(v, v_blinding) in [(1038u64, &blinding), (1037, &other_blinding)].iter() {
+		let mut prover_transcript = transcript.clone();
+		assert_eq!(
+			RangeProof::prove_single_existing_with_rng(
+				&bp_gens,
+				&pc_gens,
+				&mut prover_transcript,
+				v,
+				v_blinding,
+				32,
+				&commitment,
+				&mut test_rng,
+			)
+			.unwrap_err(),
+			ProofError::WrongCommitment
+		);
+		// The transcript is left untouched.
+		let (mut expected, mut actual) = ([0u8; 32], [0u8; 32]);
+		transcript.clone().challenge_bytes(b"check", &mut expected);
+		prover_transcript.challenge_bytes(b"check", &mut actual);
+		assert_eq!(actual, expected);
+	}
+}
+
+#[test]
+fn signed_values() {
+	use webb_bulletproofs::ProofError;
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64, 1);
+	let mut test_rng = ChaChaRng::from_seed([34u8; 32]);
+	let transcript = Transcript::new(b"Signed Range Test");
+	let blinding = Scalar::random(&mut test_rng);
+
+	let cases = [
+		(i64::MIN, 64),
+		(-1, 64),
+		(0, 64),
+		(i64::MAX, 64),
+		(-128, 8),
+		(-1, 8),
+		(127, 8),
+		(-1, 1),
+		(0, 1),
+	];
+	for &(v, n) in cases.iter() {
+		let (proof, commitment) = RangeProof::prove_single_signed_with_rng(
+			&bp_gens,
+			&pc_gens,
+			&mut transcript.clone(),
+			v,
+			&blinding,
+			n,
+			&mut test_rng,
+		)
+		.unwrap();
+		let v_scalar = if v < 0 {
+			-Scalar::from(v.unsigned_abs())
+		} else {
+			Scalar::from(v as u64)
+		};
+		assert_eq!(commitment, pc_gens.commit(v_scalar, blinding).compress());
+		assert!(
+			proof
+				.verify_single_signed(&bp_gens, &pc_gens, &mut transcript.clone(), &commitment, n)
+				.is_ok(),
+			"v = {}, n = {}",
+			v,
+			n
+		);
+		// The proof is for the offset value, not for the value itself.
+		assert!(proof
+			.verify_single(&bp_gens, &pc_gens, &mut transcript.clone(), &commitment, n)
+			.is_err());
+	}
+
+	for &(v, n) in [(128, 8), (-129, 8), (1, 1), (-2, 1)].iter() {
+		assert_eq!(
+			RangeProof::prove_single_signed_with_rng(
+				&bp_gens,
+				&pc_gens,
+				&mut transcript.clone(),
+				v,
+				&blinding,
+				n,
+				&mut test_rng,
+			)
+			.unwrap_err(),
+			ProofError::ValueOutOfRange
+		);
+	}
+
+	// An unsigned proof does not verify as a signed one.
+	let (proof, commitment) = RangeProof::prove_single_with_rng(
+		&bp_gens,
+		&pc_gens,
+		&mut transcript.clone(),
+		5,
+		&blinding,
+		64,
+		&mut test_rng,
+	)
+	.unwrap();
+	assert!(proof
+		.verify_single_signed(&bp_gens, &pc_gens, &mut transcript.clone(), &commitment, 64)
+		.is_err());
+}
+
+/// Copies `bytes` with the last (L, R) pair, which ends `tail` bytes before
+/// the end of the encoding, either removed or repeated.
+fn change_rounds(bytes: &[u8], tail: usize, extra: bool) -> Vec<u8> {
+	let end = bytes.len() - tail;
+	let pair = &bytes[end - 64..end];
+	let mut changed = bytes[..end - 64].to_vec();
+	if extra {
+		changed.extend_from_slice(pair);
+		changed.extend_from_slice(pair);
+	}
+	changed.extend_from_slice(&bytes[end..]);
+	changed
+}
+
+#[test]
+fn wrong_number_of_rounds() {
+	use webb_bulletproofs::{ProofError, RangeProofPlus};
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(32, 2);
+	let mut test_rng = ChaChaRng::from_seed([31u8; 32]);
+	let transcript = Transcript::new(b"WrongRoundsTest");
+	let blindings = [Scalar::random(&mut test_rng), Scalar::random(&mut test_rng)];
+
+	let (single, commitment) = RangeProof::prove_single_with_rng(
+		&bp_gens,
+		&pc_gens,
+		&mut transcript.clone(),
+		7,
+		&blindings[0],
+		32,
+		&mut test_rng,
+	)
+	.unwrap();
+	let (aggregated, commitments) = RangeProof::prove_multiple_with_rng(
+		&bp_gens,
+		&pc_gens,
+		&mut transcript.clone(),
+		&[7, 8],
+		&blindings,
+		32,
+		&mut test_rng,
+	)
+	.unwrap();
+	let (plus, plus_commitment) = RangeProofPlus::prove_single_with_rng(
+		&bp_gens,
+		&pc_gens,
+		&mut transcript.clone(),
+		7,
+		&blindings[0],
+		32,
+		&mut test_rng,
+	)
+	.unwrap();
+
+	for &extra in [false, true].iter() {
+		// The header of the encoding records the number of rounds, so use
+		// the legacy encoding to get the proofs past parsing.
+		let changed = change_rounds(&single.to_bytes(), 64, extra);
+		assert_eq!(RangeProof::from_bytes(&changed).unwrap_err(), ProofError::FormatError);
+		let changed = RangeProof::from_bytes_legacy(&changed[6..]).unwrap();
+		assert_eq!(
+			changed
+				.verify_single(&bp_gens, &pc_gens, &mut transcript.clone(), &commitment, 32)
+				.unwrap_err(),
+			ProofError::FormatError
+		);
+
+		let changed = change_rounds(&aggregated.to_bytes(), 64, extra);
+		let changed = RangeProof::from_bytes_legacy(&changed[6..]).unwrap();
+		assert_eq!(
+			changed
+				.verify_multiple(&bp_gens, &pc_gens, &mut transcript.clone(), &commitments, 32)
+				.unwrap_err(),
+			ProofError::FormatError
+		);
+
+		let changed = RangeProofPlus::from_bytes(&change_rounds(&plus.to_bytes(), 0, extra)).unwrap();
+		assert_eq!(
+			changed
+				.verify_single(&bp_gens, &pc_gens, &mut transcript.clone(), &plus_commitment, 32)
+				.unwrap_err(),
+			ProofError::FormatError
+		);
+	}
+
+	// A well-formed proof checked for another bitsize has the wrong number
+	// of rounds as well.
+	assert_eq!(
+		single
+			.verify_single(&bp_gens, &pc_gens, &mut transcript.clone(), &commitment, 16)
+			.unwrap_err(),
+		ProofError::FormatError
+	);
+}
+
+#[test]
+fn oversized_proofs_are_rejected_early() {
+	use std::time::Instant;
+	use webb_bulletproofs::{InnerProductProof, ProofError, RangeProofPlus};
+
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(64, 1);
+	let mut test_rng = ChaChaRng::from_seed([37u8; 32]);
+	let transcript = Transcript::new(b"OversizedTest");
+
+	// Well-formed encodings claiming 2^20 rounds, 64 MiB each.
+	let rounds = 1 << 20;
+	let ipp = vec![0u8; InnerProductProof::serialized_size_for_rounds(rounds)];
+	let legacy = [vec![0u8; 7 * 32], ipp.clone()].concat();
+	let with_header = [vec![1u8, 64, 1, 0, 0, 0], legacy.clone()].concat();
+	let plus = vec![0u8; 6 * 32 + 2 * rounds * 32];
+
+	let (proof, commitment) = RangeProof::prove_single_with_rng(
+		&bp_gens,
+		&pc_gens,
+		&mut transcript.clone(),
+		7,
+		&Scalar::random(&mut test_rng),
+		64,
+		&mut test_rng,
+	)
+	.unwrap();
+	let start = Instant::now();
+	assert!(proof
+		.verify_single(&bp_gens, &pc_gens, &mut transcript.clone(), &commitment, 64)
+		.is_ok());
+	let verify_time = start.elapsed();
+
+	// The round count is checked against the length of the input before
+	// anything is read from it, so rejecting the encodings a hundred times
+	// over is still cheaper than verifying a single proof.
+	let start = Instant::now();
+	for _ in 0..100 {
+		assert_eq!(
+			InnerProductProof::from_bytes(&ipp).unwrap_err(),
+			ProofError::FormatError
+		);
+		assert_eq!(
+			RangeProof::from_bytes_legacy(&legacy).unwrap_err(),
+			ProofError::FormatError
+		);
+		assert_eq!(
+			RangeProof::from_bytes(&with_header).unwrap_err(),
+			ProofError::FormatError
+		);
+		assert_eq!(RangeProofPlus::from_bytes(&plus).unwrap_err(), ProofError::FormatError);
+	}
+	let reject_time = start.elapsed();
+	println!(
+		"Rejection time: {:?}, verification time: {:?}",
+		reject_time, verify_time
+	);
+	assert!(reject_time < verify_time);
+}
+
+#[test]
+fn required_capacity_matches_prover() {
+	use webb_bulletproofs::ProofError;
+
+	let pc_gens = PedersenGens::default();
+	let mut test_rng = ChaChaRng::from_seed([61u8; 32]);
+
+	for &(n, m) in [(8, 1), (10, 3), (32, 2), (64, 5)].iter() {
+		let required = BulletproofGens::required_capacity_for_range(n, m);
+		let values: Vec<u64> = (0..m as u64).collect();
+		let blindings: Vec<Scalar> = (0..m).map(|_| Scalar::random(&mut test_rng)).collect();
+		let mut prove = |bp_gens: &BulletproofGens| {
+			RangeProof::prove_multiple_with_rng(
+				bp_gens,
+				&pc_gens,
+				&mut Transcript::new(b"Capacity Test"),
+				&values,
+				&blindings,
+				n,
+				&mut test_rng,
+			)
+		};
+
+		let exact = BulletproofGens::new(required.gens_capacity, required.party_capacity);
+		assert!(exact.fits(required));
+		assert!(prove(&exact).is_ok());
+
+		let too_few_gens = BulletproofGens::new(required.gens_capacity - 1, required.party_capacity);
+		assert!(!too_few_gens.fits(required));
+		assert_eq!(prove(&too_few_gens).unwrap_err(), ProofError::InvalidGeneratorsLength {
+			required: required.gens_capacity,
+			actual: required.gens_capacity - 1
+		});
+
+		if required.party_capacity > 1 {
+			let too_few_parties = BulletproofGens::new(required.gens_capacity, required.party_capacity - 1);
+			assert!(!too_few_parties.fits(required));
+			assert_eq!(
+				prove(&too_few_parties).unwrap_err(),
+				ProofError::InvalidGeneratorsLength {
+					required: required.party_capacity,
+					actual: required.party_capacity - 1
+				}
+			);
+		}
+	}
+}
+
+/// A broken RNG which only produces zeros.
+struct ZeroRng;
+
+impl rand_core::RngCore for ZeroRng {
+	fn next_u32(&mut self) -> u32 {
+		0
+	}
+
+	fn next_u64(&mut self) -> u64 {
+		0
+	}
+
+	fn fill_bytes(&mut self, dest: &mut [u8]) {
+		dest.iter_mut().for_each(|b| *b = 0);
+	}
+
+	fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+		self.fill_bytes(dest);
+		Ok(())
+	}
+}
+
+impl rand_core::CryptoRng for ZeroRng {}
+
+#[test]
+fn broken_rng_nonces_depend_on_the_witness() {
+	let pc_gens = PedersenGens::default();
+	let bp_gens = BulletproofGens::new(32, 2);
+
+	let prove = |values: &[u64]| {
+		// The blindings come from the same broken source.
+		let blindings = vec![Scalar::zero(); values.len()];
+		let mut transcript = Transcript::new(b"BrokenRngTest");
+		let (proof, commitments) = RangeProof::prove_multiple_with_rng(
+			&bp_gens,
+			&pc_gens,
+			&mut transcript,
+			values,
+			&blindings,
+			32,
+			&mut ZeroRng,
+		)
+		.unwrap();
+
+		let mut transcript = Transcript::new(b"BrokenRngTest");
+		assert!(proof
+			.verify_multiple_with_rng(
+				&bp_gens,
+				&pc_gens,
+				&mut transcript,
+				&commitments,
+				32,
+				&mut ChaChaRng::from_seed([1u8; 32])
+			)
+			.is_ok());
+		proof.to_bytes()
+	};
+	// The commitment S of the proof only depends on its nonces.
+	let s_commitment = |proof: &[u8]| proof[6 + 32..6 + 64].to_vec();
+
+	let (five, six) = (prove(&[5]), prove(&[6]));
+	assert_ne!(s_commitment(&five), s_commitment(&six));
+	assert_ne!(s_commitment(&five), vec![0u8; 32]);
+	assert_ne!(s_commitment(&prove(&[5, 6])), s_commitment(&prove(&[6, 5])));
+
+	// The same witness gives the same proof rather than reusing the nonces
+	// in another one.
+	assert_eq!(five, prove(&[5]));
+}
+
+#[test]
+fn errors_implement_std_error() {
+	use webb_bulletproofs::{range_proof_mpc::MPCError, GeneratorError, ProofError};
+
+	fn assert_error<E: std::error::Error + Send + Sync + 'static>() {}
+	assert_error::<ProofError>();
+	assert_error::<MPCError>();
+	assert_error::<GeneratorError>();
+
+	assert_eq!(ProofError::VerificationError.to_string(), "Proof verification failed.");
+	assert_eq!(
+		ProofError::InvalidGeneratorsLength {
+			required: 64,
+			actual: 32
+		}
+		.to_string(),
+		"Invalid generators size, too few generators for proof: 64 required, 32 available"
+	);
+	assert_eq!(
+		ProofError::from(MPCError::MaliciousDealer).to_string(),
+		"Internal error during proof creation: Dealer gave a malicious challenge value."
+	);
+	assert_eq!(
+		MPCError::MalformedBitCommitments {
+			bad_commitments: vec![1, 3]
+		}
+		.to_string(),
+		"Malformed bit commitments from parties [1, 3]"
+	);
+	assert_eq!(
+		GeneratorError::FormatError.to_string(),
+		"Generator data could not be parsed."
+	);
+
+	// The errors box into the trait objects used by applications.
+	let boxed: Box<dyn std::error::Error + Send + Sync> = ProofError::FormatError.into();
+	assert_eq!(boxed.to_string(), "Proof data could not be parsed.");
+}